@@ -0,0 +1,76 @@
+/*
+ * C ABI of the nsys-chrome converter (src/ffi.rs).
+ *
+ * Link against the nsys_chrome cdylib (libnsys_chrome.so / .dylib /
+ * nsys_chrome.dll). Every function returning int returns NSYS_CHROME_OK on
+ * success; on failure, nsys_chrome_last_error() describes the error.
+ *
+ * options_json is NULL, "" or a JSON object whose keys mirror the CLI flags:
+ *   activity_types, nvtx_prefix, nvtx_domains (arrays of strings),
+ *   nvtx_domain_prefix, nvtx_domain_tracks, include_metadata,
+ *   api_call_stacks, synthesize_steps, infer_layers, kernel_concurrency,
+ *   transfer_throughput, estimate_costs,
+ *   api_thread_states, source_rows, nvtx_kernel_per_stream, tag_unattributed,
+ *   unattributed_work, annotations_only (booleans),
+ *   source_frames, jobs (integers), outlier_factor (number, e.g. 5),
+ *   min_duration ("5us"), max_link_gap ("1s"), link_window ("10s"),
+ *   sticky_lookback ("5ms"),
+ *   time_origin ("capture-start"), virtual_tids ("category"),
+ *   time_window ("2s..3.5s"), link_policy ("innermost"), missing_stream ("infer"),
+ *   nvtx_nesting ("collapse"), nvtx_kernel_layout ("annotation"),
+ *   preset ("training"), flow_style ("bound"), nvtx_colors ({"^loss": "bad"}),
+ *   flow_bind ({"launch": "next"}), time_shifts ({"nvtx": "-20us"}),
+ *   routes (["nvtx-kernel=ranges.json"]).
+ *
+ * Events can also be exchanged as delimited protobuf streams of the
+ * ChromeTraceEvent message in proto/chrome_trace_event.proto: each message
+ * preceded by its length as a varint.
+ *
+ * Keep in sync with src/ffi.rs; tests/test_ffi.rs checks the declarations.
+ */
+
+#ifndef NSYS_CHROME_H
+#define NSYS_CHROME_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define NSYS_CHROME_OK 0
+#define NSYS_CHROME_INVALID_ARGUMENT 1
+#define NSYS_CHROME_FAILED 2
+#define NSYS_CHROME_PANIC 3
+
+/* Convert an nsys SQLite export to a Chrome trace, gzip-compressed when
+ * output ends in ".gz". */
+int nsys_chrome_convert(const char* input, const char* output, const char* options_json);
+
+/* Link the nvtx ranges of an in-memory Chrome trace to its kernels through
+ * its cuda_api calls. On success *result holds a JSON array of the
+ * nvtx-kernel and flow events; release it with nsys_chrome_free_string. */
+int nsys_chrome_link(const char* events_json, const char* options_json, char** result);
+
+/* Like nsys_chrome_link, with events_len bytes of protobuf-framed events in
+ * and a protobuf-framed stream of result_len bytes out; release the result
+ * with nsys_chrome_free_buffer. */
+int nsys_chrome_link_pb(const uint8_t* events, size_t events_len, const char* options_json,
+                        uint8_t** result, size_t* result_len);
+
+/* Release a buffer returned by nsys_chrome_link_pb (NULL is ignored). */
+void nsys_chrome_free_buffer(uint8_t* buf, size_t len);
+
+/* Release a string returned by nsys_chrome_link (NULL is ignored). */
+void nsys_chrome_free_string(char* s);
+
+/* Message of the last failure on the calling thread, or NULL. Valid until
+ * the next failing call on the same thread. */
+const char* nsys_chrome_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NSYS_CHROME_H */
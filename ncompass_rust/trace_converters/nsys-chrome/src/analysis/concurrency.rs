@@ -0,0 +1,148 @@
+//! Kernel concurrency per device
+//!
+//! Multi-stream code only pays off if kernels actually run side by side. This
+//! pass sweeps each device's kernel intervals and counts how many are running
+//! at every instant, emitting the count as a `Concurrent kernels` counter track
+//! and summarizing how long the device spent with 0, 1 and 2+ kernels in
+//! flight between its first kernel start and last kernel end.
+
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase};
+
+/// Name of the per-device counter of running kernels
+pub const CONCURRENCY_COUNTER: &str = "Concurrent kernels";
+
+/// Name of the metadata event holding a device's concurrency summary
+pub const KERNEL_CONCURRENCY_EVENT: &str = "kernel_concurrency";
+
+/// Category of concurrency counter events
+pub const CONCURRENCY_CATEGORY: &str = "concurrency";
+
+/// Running-kernel count of one device over time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelConcurrency {
+    pub device_id: i64,
+    /// `(timestamp, kernels running from then on)` at every change, ending at 0
+    pub steps: Vec<(i64, usize)>,
+    /// Time with no kernel running
+    pub idle_ns: i64,
+    /// Time with exactly one kernel running
+    pub serial_ns: i64,
+    /// Time with two or more kernels running
+    pub concurrent_ns: i64,
+    pub max_concurrency: usize,
+}
+
+impl KernelConcurrency {
+    /// Share of the time with a kernel running that had two or more, in percent
+    pub fn concurrent_pct(&self) -> f64 {
+        let busy = self.serial_ns + self.concurrent_ns;
+        if busy <= 0 {
+            return 0.0;
+        }
+        let pct = self.concurrent_ns as f64 / busy as f64 * 100.0;
+        (pct * 10.0).round() / 10.0
+    }
+}
+
+/// Sweep each device's kernels into a running-kernel count
+pub fn kernel_concurrency(kernel_events: &[ChromeTraceEvent]) -> Vec<KernelConcurrency> {
+    let adapter = NsysEventAdapter;
+
+    // +1 at each kernel start, -1 at each end; starts and ends at the same
+    // instant cancel, so back-to-back kernels do not count as overlapping
+    let mut deltas: BTreeMap<i64, BTreeMap<i64, i64>> = BTreeMap::new();
+    for event in kernel_events {
+        let (Some(device_id), Some((start, end))) = (
+            event.args.get("deviceId").and_then(|v| v.as_i64()),
+            adapter.get_time_range_ns(event),
+        ) else {
+            continue;
+        };
+        if end <= start {
+            continue;
+        }
+        let device = deltas.entry(device_id).or_default();
+        *device.entry(start).or_default() += 1;
+        *device.entry(end).or_default() -= 1;
+    }
+
+    deltas
+        .into_iter()
+        .map(|(device_id, device)| {
+            let mut concurrency = KernelConcurrency {
+                device_id,
+                steps: Vec::new(),
+                idle_ns: 0,
+                serial_ns: 0,
+                concurrent_ns: 0,
+                max_concurrency: 0,
+            };
+            let mut running = 0i64;
+            let mut previous: Option<i64> = None;
+            for (ts_ns, delta) in device {
+                if let Some(since) = previous {
+                    let elapsed = ts_ns - since;
+                    match running {
+                        0 => concurrency.idle_ns += elapsed,
+                        1 => concurrency.serial_ns += elapsed,
+                        _ => concurrency.concurrent_ns += elapsed,
+                    }
+                }
+                previous = Some(ts_ns);
+                if delta == 0 {
+                    continue;
+                }
+                running += delta;
+                let count = running.max(0) as usize;
+                concurrency.max_concurrency = concurrency.max_concurrency.max(count);
+                concurrency.steps.push((ts_ns, count));
+            }
+            concurrency
+        })
+        .collect()
+}
+
+/// Build `Concurrent kernels` counter events, one per change of each device's count
+pub fn concurrency_counter_events(concurrency: &[KernelConcurrency]) -> Vec<ChromeTraceEvent> {
+    concurrency
+        .iter()
+        .flat_map(|device| {
+            device.steps.iter().map(move |&(ts_ns, count)| {
+                ChromeTraceEvent::new(
+                    CONCURRENCY_COUNTER.to_string(),
+                    ChromeTracePhase::Counter,
+                    ns_to_us(ts_ns),
+                    format!("Device {}", device.device_id),
+                    String::new(),
+                    CONCURRENCY_CATEGORY.to_string(),
+                )
+                .with_arg("kernels", json!(count))
+            })
+        })
+        .collect()
+}
+
+/// Build one `kernel_concurrency` metadata event per device with its time at 0/1/2+
+pub fn concurrency_summary_events(concurrency: &[KernelConcurrency]) -> Vec<ChromeTraceEvent> {
+    concurrency
+        .iter()
+        .map(|device| {
+            let mut args = HashMap::new();
+            args.insert("idle_ns".to_string(), json!(device.idle_ns));
+            args.insert("serial_ns".to_string(), json!(device.serial_ns));
+            args.insert("concurrent_ns".to_string(), json!(device.concurrent_ns));
+            args.insert("concurrent_pct".to_string(), json!(device.concurrent_pct()));
+            args.insert("max_concurrency".to_string(), json!(device.max_concurrency));
+            ChromeTraceEvent::metadata(
+                KERNEL_CONCURRENCY_EVENT.to_string(),
+                format!("Device {}", device.device_id),
+                String::new(),
+                args,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,208 @@
+//! Link-aware removal of short kernels
+//!
+//! Dropping kernels below a duration threshold shrinks traces dominated by tiny
+//! elementwise launches, but naive removal leaves flow arrows pointing at nothing
+//! and can empty out nvtx-kernel ranges. This pass:
+//! - keeps the longest kernel of any nvtx-kernel range whose kernels would all be
+//!   dropped, so every linked range keeps at least one kernel,
+//! - removes the flow arrows of dropped kernels together with them, and
+//! - records what was hidden on each nvtx-kernel range (`filtered_kernels`,
+//!   `filtered_kernel_ns`), leaving its span and `gpu_busy_pct` untouched.
+
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{ConvertError, Result};
+use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, StringOrInt};
+
+/// What the duration filter removed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DurationFilterStats {
+    /// Kernels removed
+    pub dropped_kernels: usize,
+    /// Short kernels kept so an nvtx-kernel range would not become empty
+    pub kept_for_links: usize,
+    /// Flow events (starts and finishes) removed with their kernels
+    pub dropped_flow_events: usize,
+}
+
+/// Parse a duration such as `5us`, `250ns`, `1.5ms` or `2s` into nanoseconds
+///
+/// A bare number is taken as nanoseconds.
+pub fn parse_duration_ns(value: &str) -> Result<i64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| ConvertError::InvalidOption(format!("Invalid duration: '{}'", value)))?;
+    let scale = match unit.trim() {
+        "" | "ns" => 1.0,
+        "us" | "µs" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        other => {
+            return Err(ConvertError::InvalidOption(format!(
+                "Unknown duration unit '{}' (use ns, us, ms or s)",
+                other
+            )))
+        }
+    };
+    Ok((number * scale).round() as i64)
+}
+
+/// Kernel duration in nanoseconds, preferring the exact `start_ns`/`end_ns` args
+pub(crate) fn duration_ns(event: &ChromeTraceEvent, adapter: &dyn EventAdapter) -> i64 {
+    adapter
+        .get_time_range_ns(event)
+        .map(|(start, end)| end - start)
+        .unwrap_or_else(|| (event.dur.unwrap_or(0.0) * 1000.0).round() as i64)
+}
+
+fn is_kernel(event: &ChromeTraceEvent) -> bool {
+    event.ph == ChromeTracePhase::Complete && event.cat == "kernel"
+}
+
+/// Remove kernels shorter than `min_duration_ns` without breaking links
+pub fn filter_short_kernels(
+    events: Vec<ChromeTraceEvent>,
+    min_duration_ns: i64,
+) -> (Vec<ChromeTraceEvent>, DurationFilterStats) {
+    let mut stats = DurationFilterStats::default();
+    if min_duration_ns <= 0 {
+        return (events, stats);
+    }
+    let adapter = NsysEventAdapter;
+
+    let mut candidates: HashSet<usize> = events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| is_kernel(e) && duration_ns(e, &adapter) < min_duration_ns)
+        .map(|(i, _)| i)
+        .collect();
+    if candidates.is_empty() {
+        return (events, stats);
+    }
+
+    // Kernels per device track, sorted by start, for range lookups
+    let mut kernels_by_pid: HashMap<&str, Vec<(f64, f64, usize)>> = HashMap::new();
+    for (index, event) in events.iter().enumerate().filter(|(_, e)| is_kernel(e)) {
+        let end = event.ts + event.dur.unwrap_or(0.0);
+        kernels_by_pid
+            .entry(event.pid.as_str())
+            .or_default()
+            .push((event.ts, end, index));
+    }
+    for kernels in kernels_by_pid.values_mut() {
+        kernels.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    // Kernels inside each nvtx-kernel range
+    let ranges: Vec<(usize, Vec<usize>)> = events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.ph == ChromeTracePhase::Complete && e.cat == "nvtx-kernel")
+        .map(|(range_index, range)| {
+            let range_end = range.ts + range.dur.unwrap_or(0.0);
+            let inside = kernels_by_pid
+                .get(range.pid.as_str())
+                .map(|kernels| {
+                    let first = kernels.partition_point(|&(start, _, _)| start < range.ts);
+                    kernels[first..]
+                        .iter()
+                        .take_while(|&&(start, _, _)| start <= range_end)
+                        .filter(|&&(_, end, _)| end <= range_end)
+                        .map(|&(_, _, index)| index)
+                        .collect()
+                })
+                .unwrap_or_default();
+            (range_index, inside)
+        })
+        .collect();
+
+    for (_, inside) in &ranges {
+        if !inside.is_empty() && inside.iter().all(|i| candidates.contains(i)) {
+            let longest = inside
+                .iter()
+                .copied()
+                .max_by_key(|&i| duration_ns(&events[i], &adapter))
+                .expect("range has kernels");
+            candidates.remove(&longest);
+            stats.kept_for_links += 1;
+        }
+    }
+
+    // Summarize hidden kernels on each range
+    let mut range_updates: Vec<(usize, usize, i64)> = Vec::new();
+    for (range_index, inside) in &ranges {
+        let hidden: Vec<usize> = inside
+            .iter()
+            .copied()
+            .filter(|i| candidates.contains(i))
+            .collect();
+        if !hidden.is_empty() {
+            let hidden_ns = hidden
+                .iter()
+                .map(|&i| duration_ns(&events[i], &adapter))
+                .sum();
+            range_updates.push((*range_index, hidden.len(), hidden_ns));
+        }
+    }
+
+    // Flow finishes land on their kernel's track at its start time
+    let dropped_kernel_keys: HashSet<(&str, &str, u64)> = candidates
+        .iter()
+        .map(|&i| {
+            (
+                events[i].pid.as_str(),
+                events[i].tid.as_str(),
+                events[i].ts.to_bits(),
+            )
+        })
+        .collect();
+    let mut dropped_flows: HashSet<usize> = HashSet::new();
+    let mut starts_to_drop: HashMap<i64, usize> = HashMap::new();
+    for (index, event) in events.iter().enumerate() {
+        if event.ph != ChromeTracePhase::FlowFinish {
+            continue;
+        }
+        let key = (event.pid.as_str(), event.tid.as_str(), event.ts.to_bits());
+        if let (true, Some(StringOrInt::Int(id))) = (dropped_kernel_keys.contains(&key), &event.id)
+        {
+            dropped_flows.insert(index);
+            *starts_to_drop.entry(*id).or_insert(0) += 1;
+        }
+    }
+    for (index, event) in events.iter().enumerate() {
+        if event.ph != ChromeTracePhase::FlowStart {
+            continue;
+        }
+        if let Some(StringOrInt::Int(id)) = &event.id {
+            if let Some(remaining) = starts_to_drop.get_mut(id).filter(|n| **n > 0) {
+                *remaining -= 1;
+                dropped_flows.insert(index);
+            }
+        }
+    }
+
+    stats.dropped_kernels = candidates.len();
+    stats.dropped_flow_events = dropped_flows.len();
+
+    let mut events = events;
+    for (range_index, count, hidden_ns) in range_updates {
+        let args = &mut events[range_index].args;
+        args.insert("filtered_kernels".into(), json!(count));
+        args.insert("filtered_kernel_ns".into(), json!(hidden_ns));
+    }
+    let filtered = events
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !candidates.contains(i) && !dropped_flows.contains(i))
+        .map(|(_, e)| e)
+        .collect();
+
+    (filtered, stats)
+}
@@ -0,0 +1,263 @@
+//! Kernel fusion opportunities in launch-bound regions
+//!
+//! A stream that runs a series of short kernels, each followed by an idle gap
+//! longer than the kernel itself, is waiting on the CPU to launch work rather
+//! than computing. Fusing such a series into one kernel removes the gaps, so
+//! their total is reported as the time a fusion could save. Regions are
+//! attributed to the innermost `nvtx-kernel` range containing them, so the
+//! report can be read per model layer or step.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::analysis::heatmap::span_ns;
+use crate::error::{ConvertError, Result};
+use crate::linker::nvtx_linker::nvtx_kernel_device;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+use crate::routing::csv_field;
+
+/// Start, end and name of a span, in nanoseconds of trace time
+type Span<'a> = (i64, i64, &'a str);
+
+/// Kernels at most this long count as short: 20 us
+pub const DEFAULT_FUSION_MAX_KERNEL_NS: i64 = 20_000;
+
+/// Fewest kernels in a run worth reporting
+pub const MIN_FUSION_KERNELS: usize = 3;
+
+/// A run of short, launch-bound kernels on one stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FusionRegion {
+    /// Process track of the stream (e.g. "Device 0")
+    pub device: String,
+    /// Stream track (e.g. "Stream 7")
+    pub stream: String,
+    /// Innermost NVTX range containing the region, if any
+    pub range: Option<String>,
+    pub start_ns: i64,
+    pub end_ns: i64,
+    pub kernels: usize,
+    /// Time spent running the kernels
+    pub kernel_ns: i64,
+    /// Idle time between the kernels
+    pub gap_ns: i64,
+    /// Time saved by launching the run as one kernel (the gaps)
+    pub estimated_savings_ns: i64,
+    /// Distinct kernel names, in launch order
+    pub kernel_names: Vec<String>,
+}
+
+/// Fusion candidates within one NVTX range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FusionRangeSummary {
+    /// NVTX range name; `None` for regions outside any range
+    pub range: Option<String>,
+    pub regions: usize,
+    pub kernels: usize,
+    pub estimated_savings_ns: i64,
+}
+
+/// Fusion candidates, one entry per region and per NVTX range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FusionReport {
+    pub max_kernel_ns: i64,
+    /// Regions, largest savings first
+    pub regions: Vec<FusionRegion>,
+    /// Per-range totals, largest savings first
+    pub ranges: Vec<FusionRangeSummary>,
+}
+
+/// Find launch-bound runs of kernels no longer than `max_kernel_ns`
+pub fn fusion_report(events: &[ChromeTraceEvent], max_kernel_ns: i64) -> Result<FusionReport> {
+    if max_kernel_ns <= 0 {
+        return Err(ConvertError::InvalidOption(format!(
+            "Fusion kernel threshold must be positive, got {} ns",
+            max_kernel_ns
+        )));
+    }
+
+    let mut per_stream: BTreeMap<(&str, &str), Vec<Span>> = BTreeMap::new();
+    let mut per_device_ranges: BTreeMap<String, Vec<Span>> = BTreeMap::new();
+    for event in events {
+        if event.ph != ChromeTracePhase::Complete {
+            continue;
+        }
+        let Some((start, end)) = span_ns(event) else {
+            continue;
+        };
+        match &*event.cat {
+            "kernel" => per_stream
+                .entry((&event.pid, &event.tid))
+                .or_default()
+                .push((start, end, &event.name)),
+            "nvtx-kernel" => {
+                // Ranges may sit beside their annotation rather than on the device
+                let device = nvtx_kernel_device(event)
+                    .map_or_else(|| event.pid.to_string(), |d| format!("Device {}", d));
+                per_device_ranges
+                    .entry(device)
+                    .or_default()
+                    .push((start, end, &event.name))
+            }
+            _ => {}
+        }
+    }
+
+    let mut regions = Vec::new();
+    for ((device, stream), mut kernels) in per_stream {
+        kernels.sort_unstable();
+        let ranges = per_device_ranges
+            .get(device)
+            .map_or(&[][..], |r| r.as_slice());
+        let mut run: Vec<Span> = Vec::new();
+        for kernel in kernels {
+            // A run continues while the previous kernel was shorter than the gap after it
+            let continues = run
+                .last()
+                .is_none_or(|&(start, end, _)| kernel.0 - end > end - start);
+            if !continues {
+                regions.extend(region(device, stream, &run, ranges));
+                run.clear();
+            }
+            if kernel.1 - kernel.0 <= max_kernel_ns {
+                run.push(kernel);
+            } else {
+                regions.extend(region(device, stream, &run, ranges));
+                run.clear();
+            }
+        }
+        regions.extend(region(device, stream, &run, ranges));
+    }
+    regions.sort_by(|a, b| {
+        b.estimated_savings_ns
+            .cmp(&a.estimated_savings_ns)
+            .then_with(|| a.start_ns.cmp(&b.start_ns))
+    });
+
+    let mut per_range: BTreeMap<Option<&str>, FusionRangeSummary> = BTreeMap::new();
+    for region in &regions {
+        let summary =
+            per_range
+                .entry(region.range.as_deref())
+                .or_insert_with(|| FusionRangeSummary {
+                    range: region.range.clone(),
+                    regions: 0,
+                    kernels: 0,
+                    estimated_savings_ns: 0,
+                });
+        summary.regions += 1;
+        summary.kernels += region.kernels;
+        summary.estimated_savings_ns += region.estimated_savings_ns;
+    }
+    let mut ranges: Vec<FusionRangeSummary> = per_range.into_values().collect();
+    ranges.sort_by_key(|summary| std::cmp::Reverse(summary.estimated_savings_ns));
+
+    Ok(FusionReport {
+        max_kernel_ns,
+        regions,
+        ranges,
+    })
+}
+
+/// Build a region from a run, if it is long enough to report
+fn region(
+    device: &str,
+    stream: &str,
+    run: &[Span],
+    ranges: &[Span],
+) -> Option<FusionRegion> {
+    if run.len() < MIN_FUSION_KERNELS {
+        return None;
+    }
+    let start_ns = run[0].0;
+    let end_ns = run[run.len() - 1].1;
+    let kernel_ns: i64 = run.iter().map(|&(start, end, _)| end - start).sum();
+    let gap_ns = end_ns - start_ns - kernel_ns;
+
+    let mut kernel_names: Vec<String> = Vec::new();
+    for &(_, _, name) in run {
+        if !kernel_names.iter().any(|n| n == name) {
+            kernel_names.push(name.to_string());
+        }
+    }
+    // Innermost range: the shortest one containing the whole region
+    let range = ranges
+        .iter()
+        .filter(|&&(start, end, _)| start <= start_ns && end_ns <= end)
+        .min_by_key(|&&(start, end, _)| end - start)
+        .map(|&(_, _, name)| name.to_string());
+
+    Some(FusionRegion {
+        device: device.to_string(),
+        stream: stream.to_string(),
+        range,
+        start_ns,
+        end_ns,
+        kernels: run.len(),
+        kernel_ns,
+        gap_ns,
+        estimated_savings_ns: gap_ns,
+        kernel_names,
+    })
+}
+
+impl FusionReport {
+    /// Savings summed over all regions
+    pub fn total_savings_ns(&self) -> i64 {
+        self.regions.iter().map(|r| r.estimated_savings_ns).sum()
+    }
+
+    /// Write as CSV or JSON, chosen by the path's extension
+    pub fn write(&self, path: &str) -> Result<()> {
+        let csv = if path.ends_with(".csv") {
+            true
+        } else if path.ends_with(".json") {
+            false
+        } else {
+            return Err(ConvertError::InvalidOption(format!(
+                "Fusion report path '{}' must end in .csv or .json",
+                path
+            )));
+        };
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        let mut writer = BufWriter::new(file);
+        if csv {
+            self.write_csv(&mut writer).map_err(ConvertError::Output)
+        } else {
+            serde_json::to_writer(&mut writer, self).map_err(|e| ConvertError::Output(e.into()))?;
+            writer.flush().map_err(ConvertError::Output)
+        }
+    }
+
+    /// One row per region, largest savings first
+    ///
+    /// Kernel names are joined with `;`.
+    pub fn write_csv(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "device,stream,range,start_ns,end_ns,kernels,kernel_ns,gap_ns,estimated_savings_ns,kernel_names"
+        )?;
+        for region in &self.regions {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{}",
+                csv_field(&region.device),
+                csv_field(&region.stream),
+                csv_field(region.range.as_deref().unwrap_or("")),
+                region.start_ns,
+                region.end_ns,
+                region.kernels,
+                region.kernel_ns,
+                region.gap_ns,
+                region.estimated_savings_ns,
+                csv_field(&region.kernel_names.join(";"))
+            )?;
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,210 @@
+//! GPU idle gap analysis
+//!
+//! Finds stretches where a device runs no kernels and measures how much of each
+//! gap had work waiting in WDDM queues. On Windows that queue time explains launch
+//! delays that are otherwise invisible in the CUDA timeline.
+//!
+//! The same merged kernel intervals give each device's active/idle runs, embedded
+//! as a `device_activity` metadata event so a capture's GPU utilization can be
+//! read without loading every kernel.
+
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::linker::adapters::NsysEventAdapter;
+use crate::linker::algorithms::merge_intervals;
+use crate::models::{ns_to_us, ChromeTraceEvent};
+use crate::parsers::wddm::WDDM_QUEUE_KIND;
+
+/// Thread name for GPU idle gap ranges
+pub const GAP_TRACK: &str = "GPU Idle";
+
+/// Gaps shorter than this are launch jitter, not idle time worth reporting
+pub const MIN_GAP_NS: i64 = 10_000;
+
+/// A stretch of time with no kernel running on a device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuGap {
+    pub device_id: i64,
+    pub start_ns: i64,
+    pub end_ns: i64,
+    /// Time within the gap covered by at least one WDDM queue packet
+    pub wddm_queued_ns: i64,
+}
+
+impl GpuGap {
+    /// Gap length in nanoseconds
+    pub fn duration_ns(&self) -> i64 {
+        self.end_ns - self.start_ns
+    }
+}
+
+/// Find gaps between kernels on each device that are at least `min_gap_ns` long
+pub fn find_kernel_gaps(kernel_events: &[ChromeTraceEvent], min_gap_ns: i64) -> Vec<GpuGap> {
+    let adapter = NsysEventAdapter;
+
+    let mut per_device: BTreeMap<i64, Vec<&ChromeTraceEvent>> = BTreeMap::new();
+    for event in kernel_events {
+        if let Some(device_id) = event.args.get("deviceId").and_then(|v| v.as_i64()) {
+            per_device.entry(device_id).or_default().push(event);
+        }
+    }
+
+    let mut gaps = Vec::new();
+    for (device_id, kernels) in per_device {
+        let busy = merge_intervals(&kernels, &adapter);
+        for pair in busy.windows(2) {
+            let (start_ns, end_ns) = (pair[0].1, pair[1].0);
+            if end_ns - start_ns >= min_gap_ns {
+                gaps.push(GpuGap {
+                    device_id,
+                    start_ns,
+                    end_ns,
+                    wddm_queued_ns: 0,
+                });
+            }
+        }
+    }
+
+    gaps
+}
+
+/// Fill in `wddm_queued_ns` for each gap from WDDM queue packet events
+///
+/// WDDM adapters are not mapped to CUDA devices, so queue packets from any
+/// adapter count towards every device's gaps.
+pub fn attribute_wddm_queue_time(gaps: &mut [GpuGap], wddm_events: &[ChromeTraceEvent]) {
+    let adapter = NsysEventAdapter;
+    let queue_packets: Vec<&ChromeTraceEvent> = wddm_events
+        .iter()
+        .filter(|e| e.args.get("packet_kind").and_then(|v| v.as_str()) == Some(WDDM_QUEUE_KIND))
+        .collect();
+    let queued = merge_intervals(&queue_packets, &adapter);
+
+    for gap in gaps.iter_mut() {
+        // Ranges are sorted and disjoint: skip those ending before the gap
+        let first = queued.partition_point(|&(_, end)| end <= gap.start_ns);
+        gap.wddm_queued_ns = queued[first..]
+            .iter()
+            .take_while(|&&(start, _)| start < gap.end_ns)
+            .map(|&(start, end)| end.min(gap.end_ns) - start.max(gap.start_ns))
+            .sum();
+    }
+}
+
+/// Build `GPU Idle` complete events for gaps
+pub fn gap_events(gaps: &[GpuGap]) -> Vec<ChromeTraceEvent> {
+    gaps.iter()
+        .map(|gap| {
+            let queued_pct = gap.wddm_queued_ns as f64 / gap.duration_ns().max(1) as f64 * 100.0;
+            ChromeTraceEvent::complete(
+                "GPU idle".to_string(),
+                ns_to_us(gap.start_ns),
+                ns_to_us(gap.duration_ns()),
+                format!("Device {}", gap.device_id),
+                GAP_TRACK.to_string(),
+                "gap".to_string(),
+            )
+            .with_arg("deviceId", json!(gap.device_id))
+            .with_arg("start_ns", json!(gap.start_ns))
+            .with_arg("end_ns", json!(gap.end_ns))
+            .with_arg("wddm_queued_ns", json!(gap.wddm_queued_ns))
+            .with_arg("wddm_queued_pct", json!((queued_pct * 10.0).round() / 10.0))
+        })
+        .collect()
+}
+
+/// Name of the metadata event holding a device's active/idle summary
+pub const DEVICE_ACTIVITY_EVENT: &str = "device_activity";
+
+/// Top-level active/idle segmentation of one device, run-length encoded
+///
+/// `runs_ns` alternates active and idle run lengths, starting and ending with
+/// an active run, between the first kernel start and the last kernel end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceActivity {
+    pub device_id: i64,
+    pub start_ns: i64,
+    pub end_ns: i64,
+    pub runs_ns: Vec<i64>,
+}
+
+impl DeviceActivity {
+    /// Time with at least one kernel running
+    pub fn active_ns(&self) -> i64 {
+        self.runs_ns.iter().step_by(2).sum()
+    }
+
+    /// Time between kernels
+    pub fn idle_ns(&self) -> i64 {
+        self.runs_ns.iter().skip(1).step_by(2).sum()
+    }
+
+    /// Share of the span with a kernel running, in percent
+    pub fn utilization_pct(&self) -> f64 {
+        let span = self.end_ns - self.start_ns;
+        if span <= 0 {
+            return 100.0;
+        }
+        let pct = self.active_ns() as f64 / span as f64 * 100.0;
+        (pct * 10.0).round() / 10.0
+    }
+}
+
+/// Segment each device's time into active and idle runs from merged kernel intervals
+pub fn device_activity(kernel_events: &[ChromeTraceEvent]) -> Vec<DeviceActivity> {
+    let adapter = NsysEventAdapter;
+
+    let mut per_device: BTreeMap<i64, Vec<&ChromeTraceEvent>> = BTreeMap::new();
+    for event in kernel_events {
+        if let Some(device_id) = event.args.get("deviceId").and_then(|v| v.as_i64()) {
+            per_device.entry(device_id).or_default().push(event);
+        }
+    }
+
+    per_device
+        .into_iter()
+        .filter_map(|(device_id, kernels)| {
+            let busy = merge_intervals(&kernels, &adapter);
+            let (start_ns, end_ns) = (busy.first()?.0, busy.last()?.1);
+            let mut runs_ns = Vec::with_capacity(busy.len() * 2);
+            for (idx, &(start, end)) in busy.iter().enumerate() {
+                if idx > 0 {
+                    runs_ns.push(start - busy[idx - 1].1);
+                }
+                runs_ns.push(end - start);
+            }
+            Some(DeviceActivity {
+                device_id,
+                start_ns,
+                end_ns,
+                runs_ns,
+            })
+        })
+        .collect()
+}
+
+/// Build one `device_activity` metadata event per device
+pub fn device_activity_events(activity: &[DeviceActivity]) -> Vec<ChromeTraceEvent> {
+    activity
+        .iter()
+        .map(|device| {
+            let mut args = HashMap::new();
+            args.insert("start_ns".to_string(), json!(device.start_ns));
+            args.insert("end_ns".to_string(), json!(device.end_ns));
+            args.insert("active_ns".to_string(), json!(device.active_ns()));
+            args.insert("idle_ns".to_string(), json!(device.idle_ns()));
+            args.insert(
+                "utilization_pct".to_string(),
+                json!(device.utilization_pct()),
+            );
+            args.insert("runs_ns".to_string(), json!(device.runs_ns));
+            ChromeTraceEvent::metadata(
+                DEVICE_ACTIVITY_EVENT.to_string(),
+                format!("Device {}", device.device_id),
+                String::new(),
+                args,
+            )
+        })
+        .collect()
+}
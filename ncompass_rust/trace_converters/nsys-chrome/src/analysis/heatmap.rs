@@ -0,0 +1,172 @@
+//! Kernel time per stream per fixed time bin, for heatmaps of long runs
+//!
+//! A multi-hour capture is too large to open as a trace, but a matrix of GPU
+//! busy time per (stream, bin) still shows where each stream was active. Bins
+//! are aligned to the first kernel of the trace, so rows are comparable, and
+//! overlapping kernels on one stream are only counted once.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Default bin width: 10 ms
+pub const DEFAULT_HEATMAP_BIN_NS: i64 = 10_000_000;
+
+/// Busy time of one stream, one entry per bin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HeatmapRow {
+    /// Process track of the stream (e.g. "Device 0")
+    pub device: String,
+    /// Stream track (e.g. "Stream 7")
+    pub stream: String,
+    pub busy_ns: Vec<i64>,
+}
+
+/// Kernel busy time per stream and bin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heatmap {
+    pub bin_ns: i64,
+    /// Start of the first bin, in trace time
+    pub start_ns: i64,
+    pub bins: usize,
+    pub rows: Vec<HeatmapRow>,
+}
+
+/// Event time range in nanoseconds of trace time
+pub(crate) fn span_ns(event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+    let dur = event.dur?;
+    if !event.ts.is_finite() || !dur.is_finite() {
+        return None;
+    }
+    let start = (event.ts * 1000.0).round() as i64;
+    Some((start, start + (dur * 1000.0).round() as i64))
+}
+
+/// Bucket the busy time of kernel events into bins of `bin_ns`
+pub fn kernel_heatmap(events: &[ChromeTraceEvent], bin_ns: i64) -> Result<Heatmap> {
+    if bin_ns <= 0 {
+        return Err(ConvertError::InvalidOption(format!(
+            "Heatmap bin must be positive, got {} ns",
+            bin_ns
+        )));
+    }
+
+    let mut per_stream: BTreeMap<(&str, &str), Vec<(i64, i64)>> = BTreeMap::new();
+    for event in events {
+        if event.ph != ChromeTracePhase::Complete || &*event.cat != "kernel" {
+            continue;
+        }
+        if let Some(span) = span_ns(event) {
+            per_stream
+                .entry((&event.pid, &event.tid))
+                .or_default()
+                .push(span);
+        }
+    }
+
+    let start_ns = per_stream
+        .values()
+        .flatten()
+        .map(|&(start, _)| start)
+        .min()
+        .unwrap_or(0);
+    let end_ns = per_stream
+        .values()
+        .flatten()
+        .map(|&(_, end)| end)
+        .max()
+        .unwrap_or(start_ns);
+    let bins = ((end_ns - start_ns + bin_ns - 1) / bin_ns).max(0) as usize;
+
+    let rows = per_stream
+        .into_iter()
+        .map(|((device, stream), mut spans)| {
+            let mut busy_ns = vec![0; bins];
+            spans.sort_unstable();
+            let mut merged: Vec<(i64, i64)> = Vec::with_capacity(spans.len());
+            for (start, end) in spans {
+                match merged.last_mut() {
+                    Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                    _ => merged.push((start, end)),
+                }
+            }
+            for (start, end) in merged {
+                let mut t = start;
+                while t < end {
+                    let bin = ((t - start_ns) / bin_ns) as usize;
+                    let bin_end = start_ns + (bin as i64 + 1) * bin_ns;
+                    let until = end.min(bin_end);
+                    busy_ns[bin] += until - t;
+                    t = until;
+                }
+            }
+            HeatmapRow {
+                device: device.to_string(),
+                stream: stream.to_string(),
+                busy_ns,
+            }
+        })
+        .collect();
+
+    Ok(Heatmap {
+        bin_ns,
+        start_ns,
+        bins,
+        rows,
+    })
+}
+
+impl Heatmap {
+    /// Fraction of a bin a stream was busy
+    pub fn utilization(&self, row: &HeatmapRow, bin: usize) -> f64 {
+        row.busy_ns.get(bin).copied().unwrap_or(0) as f64 / self.bin_ns as f64
+    }
+
+    /// Write as CSV or JSON, chosen by the path's extension
+    pub fn write(&self, path: &str) -> Result<()> {
+        let csv = if path.ends_with(".csv") {
+            true
+        } else if path.ends_with(".json") {
+            false
+        } else {
+            return Err(ConvertError::InvalidOption(format!(
+                "Heatmap path '{}' must end in .csv or .json",
+                path
+            )));
+        };
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        let mut writer = BufWriter::new(file);
+        if csv {
+            self.write_csv(&mut writer).map_err(ConvertError::Output)
+        } else {
+            serde_json::to_writer(&mut writer, self).map_err(|e| ConvertError::Output(e.into()))?;
+            writer.flush().map_err(ConvertError::Output)
+        }
+    }
+
+    /// One row per stream, one column of utilization (0-1) per bin
+    ///
+    /// Column headers are bin start offsets in milliseconds.
+    pub fn write_csv(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        write!(writer, "device,stream")?;
+        for bin in 0..self.bins {
+            write!(writer, ",{}", (bin as i64 * self.bin_ns) as f64 / 1e6)?;
+        }
+        writeln!(writer)?;
+        for row in &self.rows {
+            write!(writer, "{},{}", row.device, row.stream)?;
+            for bin in 0..self.bins {
+                write!(writer, ",{:.3}", self.utilization(row, bin))?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,328 @@
+//! Heuristic kernel-to-layer grouping for traces without NVTX annotations
+//!
+//! Framework layers launch recognizable kernel sequences: a GEMM followed by
+//! bias and activation kernels for a linear layer, one or more cuDNN kernels
+//! (with layout transforms) for a convolution, a single fused kernel for
+//! attention or normalization. This pass walks each stream in launch order,
+//! starts a group at every anchor kernel (GEMM, convolution, attention,
+//! normalization, softmax), extends it with the epilogue kernels that follow,
+//! and emits one range per group. Ranges are marked `synthetic=true` with
+//! `heuristic="kernel-pattern"`, since they are guesses, not annotations.
+
+use regex::Regex;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::intern::InternedStr;
+use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
+use crate::models::{ns_to_us, ChromeTraceEvent};
+
+/// Thread name prefix for inferred layer ranges, followed by the stream
+pub const LAYER_TRACK: &str = "Inferred Layers";
+
+/// Idle time after which the next kernel no longer belongs to the current layer
+const MAX_GROUP_GAP_NS: i64 = 100_000;
+
+/// Activation functions, as they appear in kernel names
+const ACTIVATION_PATTERN: &str = r"(?i)(relu|gelu|silu|swish|sigmoid|tanh)";
+
+/// Kernel with its start and end in nanoseconds
+type TimedKernel<'a> = (&'a ChromeTraceEvent, i64, i64);
+
+/// Part a kernel plays in a layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelRole {
+    /// Fused attention (flash attention, fMHA)
+    Attention,
+    /// Convolution, including cuDNN helper kernels (Winograd transforms)
+    Conv,
+    /// Matrix multiply (cuBLAS, CUTLASS)
+    Gemm,
+    /// Layer, batch, RMS or group normalization
+    Norm,
+    /// Softmax
+    Softmax,
+    /// Tensor layout conversion launched around cuDNN convolutions
+    Layout,
+    /// Bias addition
+    Bias,
+    /// Activation function
+    Activation,
+    /// Anything else
+    Other,
+}
+
+impl KernelRole {
+    /// Classify a kernel by its name
+    pub fn classify(name: &str) -> KernelRole {
+        static PATTERNS: OnceLock<RolePatterns> = OnceLock::new();
+        PATTERNS.get_or_init(RolePatterns::new).role(name)
+    }
+
+    /// Name recorded in the `pattern` arg of inferred ranges
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KernelRole::Attention => "attention",
+            KernelRole::Conv => "conv",
+            KernelRole::Gemm => "gemm",
+            KernelRole::Norm => "norm",
+            KernelRole::Softmax => "softmax",
+            KernelRole::Layout => "layout",
+            KernelRole::Bias => "bias",
+            KernelRole::Activation => "activation",
+            KernelRole::Other => "other",
+        }
+    }
+
+    /// Whether this role starts a new layer
+    fn is_anchor(&self) -> bool {
+        matches!(
+            self,
+            KernelRole::Attention
+                | KernelRole::Conv
+                | KernelRole::Gemm
+                | KernelRole::Norm
+                | KernelRole::Softmax
+        )
+    }
+}
+
+/// Compiled kernel name patterns, in the order they are tried
+struct RolePatterns {
+    roles: Vec<(KernelRole, Regex)>,
+    activation: Regex,
+    norm_kind: Regex,
+}
+
+impl RolePatterns {
+    fn new() -> Self {
+        let patterns = [
+            (KernelRole::Attention, r"(?i)(flash|fmha|attention|sdpa)"),
+            // cuDNN convolutions often run as implicit GEMMs, so match them first
+            (
+                KernelRole::Conv,
+                r"(?i)(conv|winograd|fprop|dgrad|wgrad|implicit_convolve)",
+            ),
+            (KernelRole::Gemm, r"(?i)(gemm|matmul|cublas|cutlass|xmma)"),
+            (
+                KernelRole::Norm,
+                r"(?i)(layer_?norm|batch_?norm|rms_?norm|group_?norm|instance_?norm|bn_fw|bn_bw)",
+            ),
+            (KernelRole::Softmax, r"(?i)softmax"),
+            (KernelRole::Layout, r"(?i)(nchwtonhwc|nhwctonchw|transpose)"),
+            (KernelRole::Bias, r"(?i)(bias|addfunctor|add_kernel)"),
+            (KernelRole::Activation, ACTIVATION_PATTERN),
+        ];
+        Self {
+            roles: patterns
+                .iter()
+                .map(|(role, p)| (*role, Regex::new(p).expect("layer pattern is valid")))
+                .collect(),
+            activation: Regex::new(ACTIVATION_PATTERN).expect("activation pattern is valid"),
+            norm_kind: Regex::new(r"(?i)(layer|batch|rms|group|instance|bn_)")
+                .expect("norm pattern is valid"),
+        }
+    }
+
+    fn role(&self, name: &str) -> KernelRole {
+        self.roles
+            .iter()
+            .find(|(_, re)| re.is_match(name))
+            .map(|(role, _)| *role)
+            .unwrap_or(KernelRole::Other)
+    }
+
+    /// Display name of the first activation in a kernel name ("GELU")
+    fn activation_name(&self, name: &str) -> Option<&'static str> {
+        let found = self.activation.find(name)?.as_str().to_ascii_lowercase();
+        Some(match found.as_str() {
+            "relu" => "ReLU",
+            "gelu" => "GELU",
+            "silu" | "swish" => "SiLU",
+            "sigmoid" => "Sigmoid",
+            _ => "Tanh",
+        })
+    }
+
+    /// Display name of a normalization kernel ("LayerNorm")
+    fn norm_name(&self, name: &str) -> &'static str {
+        let kind = self
+            .norm_kind
+            .find(name)
+            .map(|m| m.as_str().to_ascii_lowercase());
+        match kind.as_deref() {
+            Some("layer") => "LayerNorm",
+            Some("batch") | Some("bn_") => "BatchNorm",
+            Some("rms") => "RMSNorm",
+            Some("group") => "GroupNorm",
+            Some("instance") => "InstanceNorm",
+            _ => "Norm",
+        }
+    }
+}
+
+/// Kernels inferred to belong to one layer
+struct LayerGroup<'a> {
+    anchor: Option<KernelRole>,
+    kernels: Vec<(&'a ChromeTraceEvent, KernelRole)>,
+    start_ns: i64,
+    end_ns: i64,
+    has_epilogue: bool,
+}
+
+impl<'a> LayerGroup<'a> {
+    fn new(event: &'a ChromeTraceEvent, role: KernelRole, start_ns: i64, end_ns: i64) -> Self {
+        Self {
+            anchor: role.is_anchor().then_some(role),
+            kernels: vec![(event, role)],
+            start_ns,
+            end_ns,
+            has_epilogue: false,
+        }
+    }
+
+    fn push(&mut self, event: &'a ChromeTraceEvent, role: KernelRole, end_ns: i64) {
+        self.kernels.push((event, role));
+        self.end_ns = self.end_ns.max(end_ns);
+        if matches!(role, KernelRole::Bias | KernelRole::Activation) {
+            self.has_epilogue = true;
+        }
+    }
+
+    /// Whether a convolution kernel continues this group (multi-kernel cuDNN convs)
+    fn accepts_conv(&self) -> bool {
+        !self.has_epilogue && matches!(self.anchor, None | Some(KernelRole::Conv))
+    }
+
+    /// Roles in launch order with repeats collapsed ("conv+bias+activation")
+    fn pattern(&self) -> String {
+        let mut roles: Vec<&str> = Vec::new();
+        for (_, role) in &self.kernels {
+            if roles.last() != Some(&role.as_str()) {
+                roles.push(role.as_str());
+            }
+        }
+        roles.join("+")
+    }
+
+    fn label(&self, patterns: &RolePatterns) -> String {
+        let anchor_name = self
+            .kernels
+            .iter()
+            .find(|(_, role)| Some(*role) == self.anchor)
+            .map(|(event, _)| event.name.as_str())
+            .unwrap_or_default();
+        let base = match self.anchor {
+            Some(KernelRole::Attention) => "Attention",
+            Some(KernelRole::Conv) => "Conv",
+            Some(KernelRole::Gemm) => "Linear",
+            Some(KernelRole::Norm) => patterns.norm_name(anchor_name),
+            _ => "Softmax",
+        };
+        // Activations may run as their own kernel or be fused into the anchor's epilogue
+        let activation = self
+            .kernels
+            .iter()
+            .filter(|(_, role)| {
+                matches!(role, KernelRole::Activation) || Some(*role) == self.anchor
+            })
+            .find_map(|(event, _)| patterns.activation_name(&event.name));
+        match activation {
+            Some(activation)
+                if matches!(self.anchor, Some(KernelRole::Conv | KernelRole::Gemm)) =>
+            {
+                format!("{}+{}", base, activation)
+            }
+            _ => base.to_string(),
+        }
+    }
+}
+
+/// Group one stream's kernels (sorted by start) into layers
+fn group_stream<'a>(kernels: &[TimedKernel<'a>], patterns: &RolePatterns) -> Vec<LayerGroup<'a>> {
+    let mut groups = Vec::new();
+    let mut current: Option<LayerGroup<'a>> = None;
+
+    for &(event, start, end) in kernels {
+        let role = patterns.role(&event.name);
+
+        // A long idle gap ends the current layer
+        if let Some(group) = &current {
+            if start - group.end_ns > MAX_GROUP_GAP_NS {
+                groups.extend(current.take());
+            }
+        }
+
+        match (&mut current, role) {
+            (Some(group), KernelRole::Conv | KernelRole::Layout) if group.accepts_conv() => {
+                group.push(event, role, end);
+                if role == KernelRole::Conv {
+                    group.anchor = Some(KernelRole::Conv);
+                }
+            }
+            (Some(group), KernelRole::Bias | KernelRole::Activation) if group.anchor.is_some() => {
+                group.push(event, role, end);
+            }
+            (_, role) if role.is_anchor() || role == KernelRole::Layout => {
+                groups.extend(current.take());
+                current = Some(LayerGroup::new(event, role, start, end));
+            }
+            _ => {
+                groups.extend(current.take());
+            }
+        }
+    }
+    groups.extend(current);
+
+    // Layout transforms that never led into a convolution are not layers
+    groups.retain(|group| group.anchor.is_some());
+    groups
+}
+
+/// Infer layer ranges per device and stream from kernel events
+///
+/// Meant for traces without NVTX annotations; ranges use the "nvtx" category
+/// so range-level tooling treats them like annotations, on one
+/// `Inferred Layers (Stream N)` track per stream.
+pub fn infer_layer_ranges(kernel_events: &[ChromeTraceEvent]) -> Vec<ChromeTraceEvent> {
+    let adapter = NsysEventAdapter;
+    let patterns = RolePatterns::new();
+
+    // Kernel time ranges grouped by device and stream, in order for deterministic output
+    let mut per_stream: BTreeMap<(i64, InternedStr), Vec<TimedKernel>> = BTreeMap::new();
+    for event in kernel_events {
+        let device_id = event.args.get("deviceId").and_then(|v| v.as_i64());
+        if let (Some(device_id), Some((start, end))) = (device_id, adapter.get_time_range_ns(event)) {
+            per_stream
+                .entry((device_id, event.tid.clone()))
+                .or_default()
+                .push((event, start, end));
+        }
+    }
+
+    let mut ranges = Vec::new();
+    for ((device_id, stream), mut kernels) in per_stream {
+        kernels.sort_by_key(|&(_, start, end)| (start, end));
+        for group in group_stream(&kernels, &patterns) {
+            let event = ChromeTraceEvent::complete(
+                group.label(&patterns),
+                ns_to_us(group.start_ns),
+                ns_to_us(group.end_ns - group.start_ns),
+                format!("Device {}", device_id),
+                format!("{} ({})", LAYER_TRACK, stream),
+                "nvtx".to_string(),
+            )
+            .with_arg("deviceId", json!(device_id))
+            .with_arg("start_ns", json!(group.start_ns))
+            .with_arg("end_ns", json!(group.end_ns))
+            .with_arg("kernels", json!(group.kernels.len()))
+            .with_arg("pattern", json!(group.pattern()))
+            .with_arg("synthetic", json!(true))
+            .with_arg("heuristic", json!("kernel-pattern"));
+            ranges.push(event);
+        }
+    }
+
+    ranges
+}
@@ -0,0 +1,163 @@
+//! Kernels recorded without a stream ID
+//!
+//! Some driver versions write kernel records whose `streamId` is null. The
+//! kernel parser puts them on an `Unknown Stream` track with a
+//! `stream_missing: true` arg; this pass then applies the
+//! [`MissingStreamPolicy`]: leave them there, infer the stream from the
+//! launching thread, or drop them. Inference follows the kernel's
+//! `correlationId` to the CUDA API call that launched it and takes the stream
+//! of the nearest launch from the same thread onto the same device, preferring
+//! the one just before it. Inferred kernels get a `stream_inferred: true` arg.
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::error::{ConvertError, Result};
+use crate::frontends::FrontendTrace;
+use crate::models::{ChromeTraceEvent, MissingStreamPolicy};
+use crate::parsers::cupti::kernel_track;
+
+/// Arg marking a kernel recorded without a stream ID
+pub const STREAM_MISSING_ARG: &str = "stream_missing";
+
+/// Arg marking a kernel whose stream was inferred
+pub const STREAM_INFERRED_ARG: &str = "stream_inferred";
+
+/// Parse `unknown`, `infer` or `drop` into a missing-stream policy
+pub fn parse_missing_stream_policy(value: &str) -> Result<MissingStreamPolicy> {
+    match value.trim() {
+        "unknown" => Ok(MissingStreamPolicy::Unknown),
+        "infer" => Ok(MissingStreamPolicy::Infer),
+        "drop" => Ok(MissingStreamPolicy::Drop),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid missing-stream policy '{}' (use unknown, infer or drop)",
+            value
+        ))),
+    }
+}
+
+/// What [`resolve_missing_streams`] did with streamless kernels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MissingStreamStats {
+    /// Kernels moved to the stream of a neighbouring launch
+    pub inferred: usize,
+    /// Kernels left on the `Unknown Stream` track
+    pub unknown: usize,
+    /// Kernels removed
+    pub dropped: usize,
+}
+
+impl MissingStreamStats {
+    /// Number of kernels recorded without a stream ID
+    pub fn total(&self) -> usize {
+        self.inferred + self.unknown + self.dropped
+    }
+}
+
+fn is_streamless(event: &ChromeTraceEvent) -> bool {
+    event.args.contains_key(STREAM_MISSING_ARG)
+}
+
+fn arg_i64(event: &ChromeTraceEvent, key: &str) -> Option<i64> {
+    event.args.get(key).and_then(|v| v.as_i64())
+}
+
+/// Launching thread (raw pid, raw tid) and device of a kernel
+type LaunchKey = ((i64, i64), i64);
+
+/// Launching thread (raw pid, raw tid) of each correlation ID
+fn launching_threads(api_events: &[ChromeTraceEvent]) -> HashMap<i64, (i64, i64)> {
+    api_events
+        .iter()
+        .filter_map(|api| {
+            Some((
+                arg_i64(api, "correlationId")?,
+                (arg_i64(api, "raw_pid")?, arg_i64(api, "raw_tid")?),
+            ))
+        })
+        .collect()
+}
+
+/// Apply `policy` to kernels recorded without a stream ID
+pub fn resolve_missing_streams(
+    trace: &mut FrontendTrace,
+    policy: MissingStreamPolicy,
+) -> MissingStreamStats {
+    let mut stats = MissingStreamStats::default();
+    let streamless = trace
+        .kernel_events
+        .iter()
+        .filter(|e| is_streamless(e))
+        .count();
+    if streamless == 0 {
+        return stats;
+    }
+
+    match policy {
+        MissingStreamPolicy::Unknown => stats.unknown = streamless,
+        MissingStreamPolicy::Drop => {
+            trace.kernel_events.retain(|e| !is_streamless(e));
+            stats.dropped = streamless;
+        }
+        MissingStreamPolicy::Infer => {
+            let launchers = launching_threads(&trace.api_events);
+            let launcher_of = |kernel: &ChromeTraceEvent| -> Option<LaunchKey> {
+                let thread = launchers.get(&arg_i64(kernel, "correlationId")?)?;
+                Some((*thread, arg_i64(kernel, "deviceId")?))
+            };
+
+            // Streams of known launches per thread and device, in launch order
+            let mut launches: HashMap<LaunchKey, Vec<(i64, i64)>> = HashMap::new();
+            for kernel in trace.kernel_events.iter().filter(|e| !is_streamless(e)) {
+                if let (Some(key), Some(correlation_id), Some(stream_id)) = (
+                    launcher_of(kernel),
+                    arg_i64(kernel, "correlationId"),
+                    arg_i64(kernel, "streamId"),
+                ) {
+                    launches
+                        .entry(key)
+                        .or_default()
+                        .push((correlation_id, stream_id));
+                }
+            }
+            for known in launches.values_mut() {
+                known.sort_unstable();
+            }
+
+            let inferred: Vec<Option<i64>> = trace
+                .kernel_events
+                .iter()
+                .map(|kernel| {
+                    if !is_streamless(kernel) {
+                        return None;
+                    }
+                    let known = launches.get(&launcher_of(kernel)?)?;
+                    let correlation_id = arg_i64(kernel, "correlationId")?;
+                    let idx = known.partition_point(|&(c, _)| c < correlation_id);
+                    let neighbour = idx.checked_sub(1).and_then(|i| known.get(i));
+                    neighbour
+                        .or_else(|| known.get(idx))
+                        .map(|&(_, stream)| stream)
+                })
+                .collect();
+
+            for (kernel, stream_id) in trace.kernel_events.iter_mut().zip(inferred) {
+                if !is_streamless(kernel) {
+                    continue;
+                }
+                let Some(stream_id) = stream_id else {
+                    stats.unknown += 1;
+                    continue;
+                };
+                kernel.args.remove(STREAM_MISSING_ARG);
+                kernel.args.insert("streamId".into(), json!(stream_id));
+                kernel.args.insert(STREAM_INFERRED_ARG.into(), json!(true));
+                let green_context_id = arg_i64(kernel, "greenContextId");
+                kernel.tid = kernel_track(green_context_id, Some(stream_id)).into();
+                stats.inferred += 1;
+            }
+        }
+    }
+    stats
+}
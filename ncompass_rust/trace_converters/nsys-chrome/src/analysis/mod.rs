@@ -0,0 +1,64 @@
+//! Analysis passes that derive new events from converted trace events
+
+pub mod concurrency;
+pub mod duration_filter;
+pub mod fusion;
+pub mod gaps;
+pub mod heatmap;
+pub mod layers;
+pub mod missing_streams;
+pub mod outliers;
+pub mod skew;
+pub mod step_stats;
+pub mod steps;
+pub mod thread_states;
+pub mod throttling;
+pub mod time_origin;
+pub mod transfers;
+pub mod truncation;
+pub mod window;
+
+pub use concurrency::{
+    concurrency_counter_events, concurrency_summary_events, kernel_concurrency, KernelConcurrency,
+    CONCURRENCY_CATEGORY, CONCURRENCY_COUNTER, KERNEL_CONCURRENCY_EVENT,
+};
+pub use duration_filter::{filter_short_kernels, parse_duration_ns, DurationFilterStats};
+pub use fusion::{
+    fusion_report, FusionRangeSummary, FusionRegion, FusionReport, DEFAULT_FUSION_MAX_KERNEL_NS,
+    MIN_FUSION_KERNELS,
+};
+pub use gaps::{
+    attribute_wddm_queue_time, device_activity, device_activity_events, find_kernel_gaps,
+    gap_events, DeviceActivity, GpuGap, DEVICE_ACTIVITY_EVENT,
+};
+pub use heatmap::{kernel_heatmap, Heatmap, HeatmapRow, DEFAULT_HEATMAP_BIN_NS};
+pub use layers::{infer_layer_ranges, KernelRole};
+pub use missing_streams::{
+    parse_missing_stream_policy, resolve_missing_streams, MissingStreamStats, STREAM_INFERRED_ARG,
+    STREAM_MISSING_ARG,
+};
+pub use outliers::{
+    flag_kernel_outliers, parse_outlier_factor, DEFAULT_OUTLIER_FACTOR, MIN_OUTLIER_SAMPLES,
+    OUTLIER_COLOR,
+};
+pub use skew::{
+    merge_rank_traces, skew_report, straggler_events, CollectiveSkew, RankArrival, RankMerger, SkewKind,
+    SkewReport, DEFAULT_STRAGGLER_MIN_SKEW_NS, SKEW_CATEGORY, STRAGGLER_EVENT,
+};
+pub use step_stats::{parse_step_pattern, step_stats, StepStats, StepSummary, DEFAULT_STEP_PATTERN};
+pub use steps::{detect_step_boundaries, synthesize_step_markers, StepHeuristic};
+pub use thread_states::{classify_api_call, color_api_thread_states, ApiThreadState};
+pub use throttling::{
+    throttled_regions, THROTTLE_CATEGORY, THROTTLE_CLOCK_FRACTION, THROTTLE_EVENT, THROTTLE_TRACK,
+};
+pub use time_origin::{
+    apply_time_origin, parse_time_origin, rebase_timestamps, resolve_time_origin,
+    TIME_ORIGIN_EVENT,
+};
+pub use transfers::{
+    throughput_counter_events, transfer_throughput, transfer_totals_events, TransferDirection,
+    TransferThroughput, TransferTotals, THROUGHPUT_CATEGORY, THROUGHPUT_COUNTER,
+    TRANSFER_TOTALS_EVENT,
+};
+pub use truncation::{repair_truncated, RepairStats};
+pub use window::{apply_time_window, clamp_to_window, parse_time_window, ClampStats};
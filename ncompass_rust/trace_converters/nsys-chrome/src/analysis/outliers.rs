@@ -0,0 +1,106 @@
+//! Kernel duration outliers
+//!
+//! Most kernels of a given name take about the same time from one launch to
+//! the next, so an instance running several times longer than the typical
+//! one points at something outside the kernel: clock throttling, contention
+//! with other streams or processes, a page fault storm. Such instances are
+//! marked with an `outlier` arg and a distinct color so they stand out on the
+//! timeline. The typical duration is the median per kernel name, which a few
+//! slow launches cannot drag up the way they would a mean.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::analysis::duration_filter::duration_ns;
+use crate::error::{ConvertError, Result};
+use crate::linker::adapters::NsysEventAdapter;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Instances this many times slower than the median are outliers
+pub const DEFAULT_OUTLIER_FACTOR: f64 = 5.0;
+
+/// Fewest instances of a kernel name for its median to be trusted
+pub const MIN_OUTLIER_SAMPLES: usize = 5;
+
+/// Reserved Chrome trace color name given to outliers
+pub const OUTLIER_COLOR: &str = "terrible";
+
+/// Parse an outlier factor, which must be a number above 1
+pub fn parse_outlier_factor(value: &str) -> Result<f64> {
+    match value.trim().parse::<f64>() {
+        Ok(factor) if factor > 1.0 && factor.is_finite() => Ok(factor),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid outlier factor '{}' (expected a number above 1, e.g. 5)",
+            value
+        ))),
+    }
+}
+
+/// Median of `values`, which must not be empty; reorders them
+fn median(values: &mut [i64]) -> f64 {
+    let mid = values.len() / 2;
+    let (_, &mut upper, _) = values.select_nth_unstable(mid);
+    if values.len() % 2 == 1 {
+        return upper as f64;
+    }
+    let lower = *values[..mid]
+        .iter()
+        .max()
+        .expect("even length is at least 2");
+    (lower as f64 + upper as f64) / 2.0
+}
+
+fn is_kernel(event: &ChromeTraceEvent) -> bool {
+    event.ph == ChromeTracePhase::Complete && event.cat == "kernel"
+}
+
+/// Median duration in nanoseconds per kernel name with at least
+/// [`MIN_OUTLIER_SAMPLES`] instances
+pub(crate) fn kernel_medians(events: &[ChromeTraceEvent]) -> HashMap<String, f64> {
+    let adapter = NsysEventAdapter;
+    let mut durations: HashMap<&str, Vec<i64>> = HashMap::new();
+    for event in events.iter().filter(|e| is_kernel(e)) {
+        durations
+            .entry(&event.name)
+            .or_default()
+            .push(duration_ns(event, &adapter));
+    }
+    durations
+        .into_iter()
+        .filter(|(_, samples)| samples.len() >= MIN_OUTLIER_SAMPLES)
+        .map(|(name, mut samples)| (name.to_string(), median(&mut samples)))
+        .filter(|&(_, median)| median > 0.0)
+        .collect()
+}
+
+/// Mark kernels running more than `factor` times their name's median duration
+///
+/// Outliers get `outlier: true`, the `median_ns` of their name and their
+/// `outlier_ratio` to it as args, and [`OUTLIER_COLOR`]. Names with fewer than
+/// [`MIN_OUTLIER_SAMPLES`] instances are skipped. Returns the number marked.
+pub fn flag_kernel_outliers(events: &mut [ChromeTraceEvent], factor: f64) -> usize {
+    let adapter = NsysEventAdapter;
+    let medians = kernel_medians(events);
+
+    let mut flagged = 0;
+    for event in events.iter_mut().filter(|e| is_kernel(e)) {
+        let Some(&median) = medians.get(&event.name) else {
+            continue;
+        };
+        let ratio = duration_ns(event, &adapter) as f64 / median;
+        if ratio <= factor {
+            continue;
+        }
+        event.cname = Some(OUTLIER_COLOR.to_string());
+        event.args.insert("outlier".into(), json!(true));
+        event
+            .args
+            .insert("median_ns".into(), json!(median.round() as i64));
+        event.args.insert(
+            "outlier_ratio".into(),
+            json!((ratio * 100.0).round() / 100.0),
+        );
+        flagged += 1;
+    }
+    flagged
+}
@@ -0,0 +1,368 @@
+//! Per-rank arrival skew at collectives
+//!
+//! In a multi-rank job every rank must reach a collective before any can
+//! leave it, so the rank arriving last holds up all the others. Matching the
+//! n-th occurrence of each MPI collective, NCCL kernel and training step
+//! across ranks gives each rank's offset from the first arrival. The report
+//! lists the offsets of every rank at every occurrence, and the trace gets an
+//! instant `straggler` marker where the last rank arrived noticeably late.
+//!
+//! Ranks come from the `rank` arg of MPI calls and the `mpi_rank` arg of
+//! linked NCCL kernels; other events on a device take the rank of the MPI
+//! calls issued for that device.
+//!
+//! Ranks usually write one capture each; [`merge_rank_traces`] combines them
+//! into the single trace the report is taken over.
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::error::{ConvertError, Result};
+use crate::linker::mpi_linker::{is_nccl_kernel, MPI_CATEGORY};
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase, InstantScope, StringOrInt};
+use crate::routing::csv_field;
+
+/// Name of the straggler markers
+pub const STRAGGLER_EVENT: &str = "straggler";
+
+/// Category of the straggler markers
+pub const SKEW_CATEGORY: &str = "skew";
+
+/// Skew below which no straggler marker is emitted: 100 us
+pub const DEFAULT_STRAGGLER_MIN_SKEW_NS: i64 = 100_000;
+
+/// Name fragments (lowercase) of MPI collective calls
+const MPI_COLLECTIVES: &[&str] = &[
+    "allreduce",
+    "reduce",
+    "bcast",
+    "allgather",
+    "gather",
+    "scatter",
+    "alltoall",
+    "barrier",
+];
+
+/// What kind of event a collective occurrence was matched on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkewKind {
+    /// MPI collective call
+    Mpi,
+    /// NCCL kernel
+    Nccl,
+    /// Training step marker
+    Step,
+}
+
+impl SkewKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkewKind::Mpi => "mpi",
+            SkewKind::Nccl => "nccl",
+            SkewKind::Step => "step",
+        }
+    }
+}
+
+/// When one rank arrived at a collective occurrence
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RankArrival {
+    pub rank: i64,
+    pub arrival_ns: i64,
+    /// Time after the first rank arrived
+    pub offset_ns: i64,
+}
+
+/// One occurrence of a collective, matched across ranks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CollectiveSkew {
+    pub kind: SkewKind,
+    pub name: String,
+    pub communicator: Option<String>,
+    /// 0-based occurrence of this collective on each rank
+    pub occurrence: usize,
+    /// Arrivals, earliest first
+    pub arrivals: Vec<RankArrival>,
+}
+
+impl CollectiveSkew {
+    /// Time between the first and the last arrival
+    pub fn skew_ns(&self) -> i64 {
+        self.arrivals.last().map_or(0, |a| a.offset_ns)
+    }
+
+    /// Rank arriving last
+    pub fn straggler(&self) -> Option<i64> {
+        self.arrivals.last().map(|a| a.rank)
+    }
+}
+
+/// Arrival offsets of every rank at every collective occurrence
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SkewReport {
+    /// Occurrences reached by at least two ranks, in time order
+    pub collectives: Vec<CollectiveSkew>,
+}
+
+/// Kind, name and communicator of a collective
+type CollectiveKey<'a> = (SkewKind, &'a str, Option<&'a str>);
+
+/// Arrivals at a collective per rank
+type RankArrivals<'a> = BTreeMap<i64, Vec<Arrival<'a>>>;
+
+/// A rank's arrival, with the event it was matched on to place its marker
+#[derive(Clone, Copy)]
+struct Arrival<'a> {
+    rank: i64,
+    arrival_ns: i64,
+    event: &'a ChromeTraceEvent,
+}
+
+fn arg_i64(event: &ChromeTraceEvent, key: &str) -> Option<i64> {
+    event.args.get(key).and_then(|v| v.as_i64())
+}
+
+fn arg_str<'a>(event: &'a ChromeTraceEvent, key: &str) -> Option<&'a str> {
+    event.args.get(key).and_then(|v| v.as_str())
+}
+
+fn start_ns(event: &ChromeTraceEvent) -> i64 {
+    arg_i64(event, "start_ns").unwrap_or_else(|| (event.ts * 1000.0).round() as i64)
+}
+
+fn is_mpi_collective(event: &ChromeTraceEvent) -> bool {
+    let name = event.name.to_ascii_lowercase();
+    event.cat == MPI_CATEGORY && MPI_COLLECTIVES.iter().any(|c| name.contains(c))
+}
+
+/// Kind, name and communicator of an event taking part in a collective
+fn classify(event: &ChromeTraceEvent) -> Option<(SkewKind, &str, Option<&str>)> {
+    if event.ph != ChromeTracePhase::Complete {
+        return None;
+    }
+    if is_mpi_collective(event) {
+        Some((SkewKind::Mpi, &event.name, arg_str(event, "communicator")))
+    } else if is_nccl_kernel(event) {
+        Some((
+            SkewKind::Nccl,
+            &event.name,
+            arg_str(event, "mpi_communicator"),
+        ))
+    } else if arg_i64(event, "step").is_some() {
+        Some((SkewKind::Step, &event.name, None))
+    } else {
+        None
+    }
+}
+
+/// Rank of each device, from the MPI calls issued for it
+fn device_ranks(events: &[ChromeTraceEvent]) -> HashMap<i64, i64> {
+    events
+        .iter()
+        .filter(|e| e.cat == MPI_CATEGORY)
+        .filter_map(|e| Some((arg_i64(e, "deviceId")?, arg_i64(e, "rank")?)))
+        .collect()
+}
+
+/// Merge per-rank captures into one trace, keeping their tracks and flows apart
+///
+/// Each capture is labelled (e.g. by its file name) and its pids are
+/// prefixed with the label, since device and process IDs repeat across
+/// hosts. Integer flow IDs are shifted past those of the captures before it
+/// and string ones prefixed. Collective events that only know their device
+/// get the `rank` of their capture's MPI calls, which the merged trace could
+/// no longer tell apart.
+pub fn merge_rank_traces(traces: Vec<(String, Vec<ChromeTraceEvent>)>) -> Vec<ChromeTraceEvent> {
+    let mut merged = Vec::with_capacity(traces.iter().map(|(_, events)| events.len()).sum());
+    let mut merger = RankMerger::default();
+    for (label, events) in traces {
+        merged.extend(merger.merge(&label, events));
+    }
+    merged
+}
+
+/// [`merge_rank_traces`] one capture at a time
+///
+/// Lets each capture be written out before the next is converted, so only
+/// one rank's events are held at once.
+#[derive(Debug, Default)]
+pub struct RankMerger {
+    id_offset: i64,
+}
+
+impl RankMerger {
+    /// Relabel the next capture's events for the merged trace
+    pub fn merge(
+        &mut self,
+        label: &str,
+        mut events: Vec<ChromeTraceEvent>,
+    ) -> Vec<ChromeTraceEvent> {
+        let ranks = device_ranks(&events);
+        let mut max_id = 0;
+        for event in &mut events {
+            if classify(event).is_some() && arg_i64(event, "rank").is_none() {
+                let rank = arg_i64(event, "deviceId").and_then(|d| ranks.get(&d).copied());
+                if let Some(rank) = rank {
+                    event.args.insert("rank".into(), json!(rank));
+                }
+            }
+            event.pid = format!("{}: {}", label, event.pid).into();
+            for id in [&mut event.id, &mut event.bind_id].into_iter().flatten() {
+                match id {
+                    StringOrInt::Int(value) => {
+                        max_id = max_id.max(*value);
+                        *value += self.id_offset;
+                    }
+                    StringOrInt::String(value) => *value = format!("{}: {}", label, value),
+                }
+            }
+        }
+        self.id_offset += max_id;
+        events
+    }
+}
+
+/// Match collectives across ranks
+pub fn skew_report(events: &[ChromeTraceEvent]) -> SkewReport {
+    skew_arrivals(events).0
+}
+
+/// The report, with the straggler's event of each occurrence
+fn skew_arrivals(events: &[ChromeTraceEvent]) -> (SkewReport, Vec<&ChromeTraceEvent>) {
+    let device_ranks = device_ranks(events);
+    let rank_of = |event: &ChromeTraceEvent| {
+        arg_i64(event, "rank")
+            .or_else(|| arg_i64(event, "mpi_rank"))
+            .or_else(|| device_ranks.get(&arg_i64(event, "deviceId")?).copied())
+    };
+
+    // Arrivals per collective and rank, in time order
+    let mut per_rank: BTreeMap<CollectiveKey, RankArrivals> = BTreeMap::new();
+    for event in events {
+        let (Some(key), Some(rank)) = (classify(event), rank_of(event)) else {
+            continue;
+        };
+        per_rank
+            .entry(key)
+            .or_default()
+            .entry(rank)
+            .or_default()
+            .push(Arrival {
+                rank,
+                arrival_ns: start_ns(event),
+                event,
+            });
+    }
+
+    let mut matched: Vec<(CollectiveSkew, &ChromeTraceEvent)> = Vec::new();
+    for ((kind, name, communicator), mut ranks) in per_rank {
+        for arrivals in ranks.values_mut() {
+            arrivals.sort_by_key(|a| a.arrival_ns);
+        }
+        let occurrences = ranks.values().map(Vec::len).max().unwrap_or(0);
+        for occurrence in 0..occurrences {
+            let mut arrivals: Vec<Arrival> = ranks
+                .values()
+                .filter_map(|list| list.get(occurrence).copied())
+                .collect();
+            if arrivals.len() < 2 {
+                continue;
+            }
+            arrivals.sort_by_key(|a| (a.arrival_ns, a.rank));
+            let first = arrivals[0].arrival_ns;
+            let straggler = arrivals[arrivals.len() - 1].event;
+            matched.push((
+                CollectiveSkew {
+                    kind,
+                    name: name.to_string(),
+                    communicator: communicator.map(str::to_string),
+                    occurrence,
+                    arrivals: arrivals
+                        .iter()
+                        .map(|a| RankArrival {
+                            rank: a.rank,
+                            arrival_ns: a.arrival_ns,
+                            offset_ns: a.arrival_ns - first,
+                        })
+                        .collect(),
+                },
+                straggler,
+            ));
+        }
+    }
+    matched.sort_by_key(|(c, _)| (c.arrivals[0].arrival_ns, c.kind, c.occurrence));
+
+    let (collectives, stragglers) = matched.into_iter().unzip();
+    (SkewReport { collectives }, stragglers)
+}
+
+/// Instant `straggler` markers where the last rank arrived at least
+/// `min_skew_ns` after the first, on the last rank's own track
+pub fn straggler_events(events: &[ChromeTraceEvent], min_skew_ns: i64) -> Vec<ChromeTraceEvent> {
+    let (report, stragglers) = skew_arrivals(events);
+    report
+        .collectives
+        .iter()
+        .zip(stragglers)
+        .filter(|(collective, _)| collective.skew_ns() >= min_skew_ns)
+        .map(|(collective, event)| {
+            let last = &collective.arrivals[collective.arrivals.len() - 1];
+            ChromeTraceEvent::new(
+                STRAGGLER_EVENT.to_string(),
+                ChromeTracePhase::Instant,
+                ns_to_us(last.arrival_ns),
+                event.pid.clone(),
+                event.tid.clone(),
+                SKEW_CATEGORY,
+            )
+            .with_scope(InstantScope::Thread)
+            .with_arg("collective", json!(collective.name))
+            .with_arg("occurrence", json!(collective.occurrence))
+            .with_arg("rank", json!(last.rank))
+            .with_arg("first_rank", json!(collective.arrivals[0].rank))
+            .with_arg("skew_ns", json!(collective.skew_ns()))
+        })
+        .collect()
+}
+
+impl SkewReport {
+    /// Write as CSV, one row per rank per collective occurrence
+    pub fn write(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        let mut writer = BufWriter::new(file);
+        self.write_csv(&mut writer).map_err(ConvertError::Output)
+    }
+
+    /// Rows in time order of the occurrences, earliest rank first
+    pub fn write_csv(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "kind,name,communicator,occurrence,rank,arrival_ns,offset_ns,straggler"
+        )?;
+        for collective in &self.collectives {
+            let straggler = collective.straggler();
+            for arrival in &collective.arrivals {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{},{}",
+                    collective.kind.as_str(),
+                    csv_field(&collective.name),
+                    csv_field(collective.communicator.as_deref().unwrap_or("")),
+                    collective.occurrence,
+                    arrival.rank,
+                    arrival.arrival_ns,
+                    arrival.offset_ns,
+                    straggler == Some(arrival.rank)
+                )?;
+            }
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,271 @@
+//! Per-iteration statistics from step annotations
+//!
+//! Training loops usually wrap each iteration in an NVTX range such as
+//! `step 12` or `train_step`. For every such range this pass measures the GPU
+//! work inside it: time with at least one kernel running, kernel count, time
+//! spent in NCCL communication kernels and the remaining idle time. Comparing
+//! steps exposes step-to-step variance, e.g. an iteration stalled on the data
+//! loader shows up as one step with much more idle time than its neighbours.
+//!
+//! Steps synthesized by `--synthesize-steps` carry a `step` arg and are
+//! included whatever their name; they are limited to the kernels of their own
+//! device. Annotated steps are CPU ranges and count kernels on every device.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::analysis::heatmap::span_ns;
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::linker::mpi_linker::is_nccl_kernel;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+use crate::routing::csv_field;
+
+/// Default pattern for NVTX ranges marking one training iteration
+pub const DEFAULT_STEP_PATTERN: &str = r"(?i)^(train[_ ]?)?(step|iteration|iter)([^a-z]|$)";
+
+/// Work inside one step range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StepSummary {
+    /// Step number: the `step` arg, else the last number in the name, else
+    /// the position among the steps
+    pub step: i64,
+    pub name: String,
+    /// Device the step is limited to, for synthesized steps
+    pub device_id: Option<i64>,
+    pub start_ns: i64,
+    pub end_ns: i64,
+    /// Time within the step with at least one kernel running
+    pub gpu_ns: i64,
+    /// Kernels starting within the step
+    pub kernels: usize,
+    /// Time within the step with at least one NCCL kernel running
+    pub comm_ns: i64,
+    /// Time within the step with no kernel running
+    pub idle_ns: i64,
+    /// Position of the step range in the events it was computed from
+    #[serde(skip)]
+    pub event_index: usize,
+}
+
+impl StepSummary {
+    /// Step length in nanoseconds
+    pub fn duration_ns(&self) -> i64 {
+        self.end_ns - self.start_ns
+    }
+}
+
+/// Statistics of every step range, in time order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StepStats {
+    pub steps: Vec<StepSummary>,
+}
+
+/// Compile a `--step-pattern` value
+pub fn parse_step_pattern(value: &str) -> Result<Regex> {
+    Regex::new(value).map_err(|e| {
+        ConvertError::InvalidOption(format!("Invalid step pattern '{}': {}", value, e))
+    })
+}
+
+/// Last run of digits in a step name
+fn number_in_name(name: &str) -> Option<i64> {
+    let end = name.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = name[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    name[start..end].parse().ok()
+}
+
+/// Whether an event is a step range: an NVTX range matching `pattern`, or a
+/// synthesized step
+fn is_step(event: &ChromeTraceEvent, pattern: &Regex) -> bool {
+    event.ph == ChromeTracePhase::Complete
+        && event.cat == EventCategory::Nvtx
+        && (event.args.contains_key("step") || pattern.is_match(&event.name))
+}
+
+/// Total length of the union of spans clipped to `[start, end)`
+fn covered_within(spans: &mut [(i64, i64)], start: i64, end: i64) -> i64 {
+    spans.sort_unstable();
+    let mut covered = 0;
+    let mut reached = start;
+    for &(span_start, span_end) in spans.iter() {
+        let span_start = span_start.max(reached);
+        let span_end = span_end.min(end);
+        if span_end > span_start {
+            covered += span_end - span_start;
+            reached = span_end;
+        }
+    }
+    covered
+}
+
+/// Measure the GPU work inside every step range matching `pattern`
+pub fn step_stats(events: &[ChromeTraceEvent], pattern: &Regex) -> StepStats {
+    // Kernel spans with their device and whether they communicate, by start
+    let mut kernels: Vec<(i64, i64, Option<i64>, bool)> = events
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Complete && e.cat == EventCategory::Kernel)
+        .filter_map(|e| {
+            let (start, end) = span_ns(e)?;
+            let device_id = e.args.get("deviceId").and_then(|v| v.as_i64());
+            Some((start, end, device_id, is_nccl_kernel(e)))
+        })
+        .collect();
+    kernels.sort_unstable_by_key(|k| k.0);
+    let longest = kernels.iter().map(|k| k.1 - k.0).max().unwrap_or(0);
+
+    let mut ranges: Vec<(usize, &ChromeTraceEvent, i64, i64)> = events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| is_step(e, pattern))
+        .filter_map(|(index, e)| {
+            let (start, end) = span_ns(e)?;
+            Some((index, e, start, end))
+        })
+        .collect();
+    ranges.sort_by_key(|&(index, _, start, _)| (start, index));
+
+    let steps = ranges
+        .iter()
+        .enumerate()
+        .map(|(position, &(event_index, event, start_ns, end_ns))| {
+            let device_id = event
+                .args
+                .get("step")
+                .and(event.args.get("deviceId"))
+                .and_then(|v| v.as_i64());
+
+            // Only kernels starting less than the longest kernel before the
+            // step can reach into it
+            let first = kernels.partition_point(|k| k.0 < start_ns - longest);
+            let last = kernels.partition_point(|k| k.0 < end_ns);
+            let mut busy = Vec::new();
+            let mut comm = Vec::new();
+            let mut count = 0;
+            for &(start, end, kernel_device, nccl) in &kernels[first..last] {
+                if end <= start_ns || (device_id.is_some() && kernel_device != device_id) {
+                    continue;
+                }
+                if start >= start_ns {
+                    count += 1;
+                }
+                busy.push((start, end));
+                if nccl {
+                    comm.push((start, end));
+                }
+            }
+            let gpu_ns = covered_within(&mut busy, start_ns, end_ns);
+
+            StepSummary {
+                step: event
+                    .args
+                    .get("step")
+                    .and_then(|v| v.as_i64())
+                    .or_else(|| number_in_name(&event.name))
+                    .unwrap_or(position as i64),
+                name: event.name.clone(),
+                device_id,
+                start_ns,
+                end_ns,
+                gpu_ns,
+                kernels: count,
+                comm_ns: covered_within(&mut comm, start_ns, end_ns),
+                idle_ns: end_ns - start_ns - gpu_ns,
+                event_index,
+            }
+        })
+        .collect();
+
+    StepStats { steps }
+}
+
+fn deviation_pct(step: &StepSummary, median_ns: i64) -> f64 {
+    if median_ns == 0 {
+        return 0.0;
+    }
+    (step.duration_ns() - median_ns) as f64 * 100.0 / median_ns as f64
+}
+
+impl StepStats {
+    /// Median step length, 0 without steps
+    pub fn median_duration_ns(&self) -> i64 {
+        let mut durations: Vec<i64> = self.steps.iter().map(StepSummary::duration_ns).collect();
+        durations.sort_unstable();
+        match durations.len() {
+            0 => 0,
+            n if n % 2 == 1 => durations[n / 2],
+            n => (durations[n / 2 - 1] + durations[n / 2]) / 2,
+        }
+    }
+
+    /// Percentage by which a step is longer (or shorter) than the median step
+    pub fn deviation_pct(&self, step: &StepSummary) -> f64 {
+        deviation_pct(step, self.median_duration_ns())
+    }
+
+    /// Add the step's statistics as args on the step ranges they came from
+    pub fn annotate(&self, events: &mut [ChromeTraceEvent]) {
+        let median = self.median_duration_ns();
+        for step in &self.steps {
+            let deviation = (deviation_pct(step, median) * 10.0).round() / 10.0;
+            if let Some(event) = events.get_mut(step.event_index) {
+                event.args.insert("step_gpu_ns".into(), json!(step.gpu_ns));
+                event
+                    .args
+                    .insert("step_kernels".into(), json!(step.kernels));
+                event
+                    .args
+                    .insert("step_comm_ns".into(), json!(step.comm_ns));
+                event
+                    .args
+                    .insert("step_idle_ns".into(), json!(step.idle_ns));
+                event
+                    .args
+                    .insert("step_deviation_pct".into(), json!(deviation));
+            }
+        }
+    }
+
+    /// Write as CSV, one row per step
+    pub fn write(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        let mut writer = BufWriter::new(file);
+        self.write_csv(&mut writer).map_err(ConvertError::Output)
+    }
+
+    /// Rows in time order; `deviation_pct` compares the step's length with the
+    /// median step
+    pub fn write_csv(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "step,name,device_id,start_ns,end_ns,duration_ns,gpu_ns,kernels,comm_ns,idle_ns,deviation_pct"
+        )?;
+        let median = self.median_duration_ns();
+        for step in &self.steps {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{:.1}",
+                step.step,
+                csv_field(&step.name),
+                step.device_id.map_or(String::new(), |d| d.to_string()),
+                step.start_ns,
+                step.end_ns,
+                step.duration_ns(),
+                step.gpu_ns,
+                step.kernels,
+                step.comm_ns,
+                step.idle_ns,
+                deviation_pct(step, median)
+            )?;
+        }
+        writer.flush()
+    }
+}
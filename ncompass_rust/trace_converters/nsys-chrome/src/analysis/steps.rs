@@ -0,0 +1,185 @@
+//! Synthetic training-step markers for traces without NVTX step annotations
+//!
+//! Training iterations end with a recognizable burst of GPU work: the optimizer
+//! update kernels, or the gradient all-reduce in data-parallel jobs. This pass finds
+//! those bursts per device, checks that they recur periodically, and emits one
+//! `step N` range per iteration.
+
+use regex::Regex;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
+use crate::models::{ns_to_us, ChromeTraceEvent};
+
+/// Thread name for synthesized step ranges
+pub const STEP_TRACK: &str = "Synthetic Steps";
+
+/// Minimum ratio between inter-burst and intra-burst gaps to split anchors into bursts
+const BURST_GAP_RATIO: f64 = 4.0;
+
+/// Maximum coefficient of variation of step durations for the trace to count as periodic
+const MAX_PERIOD_CV: f64 = 0.5;
+
+/// Minimum number of detected iterations before markers are emitted
+const MIN_STEPS: usize = 2;
+
+/// Kernel pattern used to find the end of each iteration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepHeuristic {
+    /// Optimizer update kernels (Adam, SGD, multi-tensor apply)
+    OptimizerKernels,
+    /// NCCL all-reduce kernels (gradient synchronization)
+    NcclAllReduce,
+}
+
+impl StepHeuristic {
+    /// Heuristics in the order they are tried
+    pub const ALL: [StepHeuristic; 2] = [StepHeuristic::OptimizerKernels, StepHeuristic::NcclAllReduce];
+
+    /// Name recorded in the `heuristic` arg of synthesized steps
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepHeuristic::OptimizerKernels => "optimizer",
+            StepHeuristic::NcclAllReduce => "nccl-allreduce",
+        }
+    }
+
+    fn pattern(&self) -> Regex {
+        let pattern = match self {
+            StepHeuristic::OptimizerKernels => r"(?i)(adam|sgd|lamb|optimizer|multi_tensor_apply)",
+            StepHeuristic::NcclAllReduce => r"(?i)nccl.*all_?reduce",
+        };
+        Regex::new(pattern).expect("step heuristic pattern is valid")
+    }
+}
+
+/// Detect iteration boundaries from anchor kernel time ranges
+///
+/// Anchors are grouped into bursts separated by gaps much larger than the gaps inside
+/// a burst. Returns the end time of each burst if at least `MIN_STEPS` bursts recur
+/// with a roughly constant period, otherwise None.
+pub fn detect_step_boundaries(anchors: &[(i64, i64)]) -> Option<Vec<i64>> {
+    if anchors.len() < MIN_STEPS {
+        return None;
+    }
+
+    let mut sorted = anchors.to_vec();
+    sorted.sort_unstable();
+
+    // Gap between each anchor and the next, measured from the latest end so far
+    let mut gaps = Vec::with_capacity(sorted.len() - 1);
+    let mut latest_end = sorted[0].1;
+    for &(start, end) in &sorted[1..] {
+        gaps.push((start - latest_end).max(0));
+        latest_end = latest_end.max(end);
+    }
+
+    // Find the largest jump in the sorted gap distribution
+    let mut sorted_gaps = gaps.clone();
+    sorted_gaps.sort_unstable();
+    let mut split_threshold = 0;
+    let mut best_ratio = 0.0;
+    for pair in sorted_gaps.windows(2) {
+        let ratio = pair[1] as f64 / pair[0].max(1) as f64;
+        if ratio > best_ratio {
+            best_ratio = ratio;
+            split_threshold = pair[1];
+        }
+    }
+    // Without a clear jump every anchor is its own burst
+    if best_ratio < BURST_GAP_RATIO {
+        split_threshold = 0;
+    }
+
+    let mut boundaries = Vec::new();
+    let mut burst_end = sorted[0].1;
+    for (i, &(_, end)) in sorted.iter().enumerate().skip(1) {
+        if gaps[i - 1] >= split_threshold {
+            boundaries.push(burst_end);
+            burst_end = end;
+        } else {
+            burst_end = burst_end.max(end);
+        }
+    }
+    boundaries.push(burst_end);
+
+    if boundaries.len() < MIN_STEPS {
+        return None;
+    }
+
+    // Require periodicity: step durations should not vary wildly
+    let periods: Vec<f64> = boundaries.windows(2).map(|w| (w[1] - w[0]) as f64).collect();
+    if periods.len() > 1 {
+        let mean = periods.iter().sum::<f64>() / periods.len() as f64;
+        let variance = periods.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / periods.len() as f64;
+        if mean <= 0.0 || variance.sqrt() / mean > MAX_PERIOD_CV {
+            return None;
+        }
+    }
+
+    Some(boundaries)
+}
+
+/// Synthesize `step N` ranges per device from kernel events
+///
+/// Tries each heuristic in order and uses the first one that yields periodic steps on
+/// a device. Synthesized ranges use the "nvtx" category so step-level aggregation
+/// treats them like annotations, and carry `synthetic=true` plus the heuristic name.
+pub fn synthesize_step_markers(kernel_events: &[ChromeTraceEvent]) -> Vec<ChromeTraceEvent> {
+    let adapter = NsysEventAdapter;
+
+    // Kernel time ranges grouped by device, in device order for deterministic output
+    let mut per_device: BTreeMap<i64, Vec<(&ChromeTraceEvent, i64, i64)>> = BTreeMap::new();
+    for event in kernel_events {
+        let device_id = event.args.get("deviceId").and_then(|v| v.as_i64());
+        if let (Some(device_id), Some((start, end))) = (device_id, adapter.get_time_range_ns(event)) {
+            per_device.entry(device_id).or_default().push((event, start, end));
+        }
+    }
+
+    let heuristics: Vec<(StepHeuristic, Regex)> = StepHeuristic::ALL
+        .iter()
+        .map(|h| (*h, h.pattern()))
+        .collect();
+
+    let mut markers = Vec::new();
+    for (device_id, kernels) in &per_device {
+        let first_start = kernels.iter().map(|&(_, start, _)| start).min().unwrap_or(0);
+
+        for (heuristic, pattern) in &heuristics {
+            let anchors: Vec<(i64, i64)> = kernels
+                .iter()
+                .filter(|(event, _, _)| pattern.is_match(&event.name))
+                .map(|&(_, start, end)| (start, end))
+                .collect();
+
+            let Some(boundaries) = detect_step_boundaries(&anchors) else {
+                continue;
+            };
+
+            let mut step_start = first_start;
+            for (step, &step_end) in boundaries.iter().enumerate() {
+                let event = ChromeTraceEvent::complete(
+                    format!("step {}", step),
+                    ns_to_us(step_start),
+                    ns_to_us(step_end - step_start),
+                    format!("Device {}", device_id),
+                    STEP_TRACK.to_string(),
+                    "nvtx".to_string(),
+                )
+                .with_arg("deviceId", json!(device_id))
+                .with_arg("start_ns", json!(step_start))
+                .with_arg("end_ns", json!(step_end))
+                .with_arg("step", json!(step))
+                .with_arg("synthetic", json!(true))
+                .with_arg("heuristic", json!(heuristic.as_str()));
+                markers.push(event);
+                step_start = step_end;
+            }
+            break;
+        }
+    }
+
+    markers
+}
@@ -0,0 +1,92 @@
+//! Thread-state coloring of CUDA API calls
+//!
+//! Colors each runtime/driver API call by what the calling thread is doing
+//! while it runs, so CPU tracks read like a thread-state timeline:
+//! - synchronization calls (`*Synchronize`, `*StreamWaitEvent`, ...) block the
+//!   thread -> waiting,
+//! - memory copies and sets move data -> I/O,
+//! - kernel and graph launches hand work to the GPU -> running.
+//!
+//! Colors are the Chrome trace viewer's reserved thread-state names. The state
+//! is also recorded as a `thread_state` arg, since Perfetto ignores `cname`.
+//! Calls that already carry a color are left alone.
+
+use serde_json::json;
+
+use crate::models::ChromeTraceEvent;
+
+/// What a thread is doing during an API call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiThreadState {
+    /// Submitting GPU work
+    Running,
+    /// Blocked until the GPU catches up
+    Waiting,
+    /// Moving data between host and device memory
+    Io,
+}
+
+impl ApiThreadState {
+    /// Value of the `thread_state` arg
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiThreadState::Running => "running",
+            ApiThreadState::Waiting => "waiting",
+            ApiThreadState::Io => "io",
+        }
+    }
+
+    /// Reserved Chrome trace color name for the state
+    pub fn color(&self) -> &'static str {
+        match self {
+            ApiThreadState::Running => "thread_state_running",
+            ApiThreadState::Waiting => "thread_state_sleeping",
+            ApiThreadState::Io => "thread_state_iowait",
+        }
+    }
+}
+
+/// Name fragments of calls that block until device work completes
+const WAITING_PATTERNS: &[&str] = &["Synchronize", "WaitEvent", "StreamWait", "WaitExternal"];
+
+/// Name fragments of data movement calls
+const IO_PATTERNS: &[&str] = &["Memcpy", "Memset", "MemPrefetch", "CopyTo", "CopyFrom"];
+
+/// Name fragments of calls that submit kernels or graphs
+const RUNNING_PATTERNS: &[&str] = &["Launch"];
+
+/// Classify an API call by name
+///
+/// Works for CUDA runtime and driver calls (including `_v7000`-style
+/// suffixes) as well as HIP and Level Zero equivalents. Returns `None` for
+/// calls with no thread-state meaning (allocations, queries, ...).
+pub fn classify_api_call(name: &str) -> Option<ApiThreadState> {
+    let matches = |patterns: &[&str]| patterns.iter().any(|p| name.contains(p));
+    if matches(WAITING_PATTERNS) {
+        Some(ApiThreadState::Waiting)
+    } else if matches(IO_PATTERNS) {
+        Some(ApiThreadState::Io)
+    } else if matches(RUNNING_PATTERNS) {
+        Some(ApiThreadState::Running)
+    } else {
+        None
+    }
+}
+
+/// Color API events by thread state; returns the number of events colored
+pub fn color_api_thread_states(events: &mut [ChromeTraceEvent]) -> usize {
+    let mut colored = 0;
+    for event in events
+        .iter_mut()
+        .filter(|e| e.cat == "cuda_api" && e.cname.is_none())
+    {
+        if let Some(state) = classify_api_call(&event.name) {
+            event.cname = Some(state.color().to_string());
+            event
+                .args
+                .insert("thread_state".into(), json!(state.as_str()));
+            colored += 1;
+        }
+    }
+    colored
+}
@@ -0,0 +1,126 @@
+//! Clock throttling regions derived from GPU clock samples
+//!
+//! A GPU slowing its clocks for power or thermal reasons stretches every
+//! kernel running at the time, which shows up as unexplained slow launches.
+//! Periods where a device's graphics clock sits below a fraction of the
+//! highest clock it reached are emitted as `throttled` ranges on a
+//! `Throttling` track of the device, carrying how much slower the kernels
+//! overlapping them ran than their usual (median) duration.
+
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::analysis::duration_filter::duration_ns;
+use crate::analysis::heatmap::span_ns;
+use crate::analysis::outliers::kernel_medians;
+use crate::linker::adapters::NsysEventAdapter;
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase};
+use crate::parsers::gpu_metrics::GPU_METRICS_CATEGORY;
+
+/// Category of derived throttling ranges
+pub const THROTTLE_CATEGORY: &str = "throttle";
+
+/// Name of the throttling ranges
+pub const THROTTLE_EVENT: &str = "throttled";
+
+/// Track holding a device's throttling ranges
+pub const THROTTLE_TRACK: &str = "Throttling";
+
+/// A clock below this fraction of the device's peak counts as throttled
+pub const THROTTLE_CLOCK_FRACTION: f64 = 0.9;
+
+/// Name fragments of the graphics clock metric, across GPU generations
+const GRAPHICS_CLOCK_METRICS: &[&str] = &["GPC Clock", "SM Clock", "Graphics Clock"];
+
+fn is_graphics_clock(name: &str) -> bool {
+    GRAPHICS_CLOCK_METRICS.iter().any(|m| name.contains(m))
+}
+
+/// Throttling ranges of each device, from the clock counters in `metrics`
+///
+/// A range starts at the first sample below [`THROTTLE_CLOCK_FRACTION`] of
+/// the device's peak and ends at the next sample back above it (or the last
+/// sample). Its args hold the clock metric, the lowest and peak clocks, the
+/// number of `kernels` overlapping it and their mean `kernel_slowdown`
+/// against the median duration of their name.
+pub fn throttled_regions(
+    metrics: &[ChromeTraceEvent],
+    kernels: &[ChromeTraceEvent],
+) -> Vec<ChromeTraceEvent> {
+    // Clock samples (ns, value) per device and metric, in time order
+    let mut clocks: BTreeMap<(&str, &str), Vec<(i64, f64)>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|e| {
+        e.ph == ChromeTracePhase::Counter
+            && e.cat == GPU_METRICS_CATEGORY
+            && is_graphics_clock(&e.name)
+    }) {
+        if let Some(value) = sample.args.get("value").and_then(|v| v.as_f64()) {
+            clocks
+                .entry((&sample.pid, &sample.name))
+                .or_default()
+                .push(((sample.ts * 1000.0).round() as i64, value));
+        }
+    }
+
+    let medians = kernel_medians(kernels);
+    let adapter = NsysEventAdapter;
+    let mut regions = Vec::new();
+    for ((device, metric), mut samples) in clocks {
+        samples.sort_by_key(|&(ts, _)| ts);
+        let peak = samples.iter().map(|&(_, v)| v).fold(0.0, f64::max);
+        let threshold = peak * THROTTLE_CLOCK_FRACTION;
+
+        let mut runs = Vec::new();
+        let mut run: Option<(i64, f64)> = None;
+        for &(ts, value) in &samples {
+            match (&mut run, value < threshold) {
+                (None, true) => run = Some((ts, value)),
+                (Some((_, lowest)), true) => *lowest = lowest.min(value),
+                (Some((start, lowest)), false) => {
+                    runs.push((*start, ts, *lowest));
+                    run = None;
+                }
+                (None, false) => {}
+            }
+        }
+        if let (Some((start, lowest)), Some(&(last, _))) = (run, samples.last()) {
+            runs.push((start, last, lowest));
+        }
+
+        for (start, end, lowest) in runs.into_iter().filter(|&(s, e, _)| e > s) {
+            let overlapping: Vec<&ChromeTraceEvent> = kernels
+                .iter()
+                .filter(|k| k.ph == ChromeTracePhase::Complete && k.pid == device)
+                .filter(|k| span_ns(k).is_some_and(|(s, e)| s < end && e > start))
+                .collect();
+            let ratios: Vec<f64> = overlapping
+                .iter()
+                .filter_map(|k| {
+                    let median = medians.get(&k.name)?;
+                    Some(duration_ns(k, &adapter) as f64 / median)
+                })
+                .collect();
+
+            let mut region = ChromeTraceEvent::complete(
+                THROTTLE_EVENT.to_string(),
+                ns_to_us(start),
+                ns_to_us(end - start),
+                device.to_string(),
+                THROTTLE_TRACK.to_string(),
+                THROTTLE_CATEGORY.to_string(),
+            )
+            .with_color("bad".to_string())
+            .with_arg("clock_metric", metric)
+            .with_arg("min_clock", lowest)
+            .with_arg("peak_clock", peak)
+            .with_arg("kernels", overlapping.len());
+            if !ratios.is_empty() {
+                let slowdown = ratios.iter().sum::<f64>() / ratios.len() as f64;
+                region =
+                    region.with_arg("kernel_slowdown", json!((slowdown * 100.0).round() / 100.0));
+            }
+            regions.push(region);
+        }
+    }
+    regions
+}
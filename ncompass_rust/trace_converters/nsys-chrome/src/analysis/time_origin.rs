@@ -0,0 +1,131 @@
+//! Timestamp rebasing to a chosen origin
+//!
+//! Profilers count nanoseconds from an arbitrary epoch, so `ts` values are large
+//! and hard to read. This pass shifts every event by the same offset, keeping
+//! flows, nvtx-kernel ranges and derived events aligned with what they refer to.
+//! The `start_ns`/`end_ns` args keep their absolute values so events can still
+//! be matched against the profiler's own reports, and a `trace_time_origin`
+//! metadata event records the epoch that was subtracted.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::error::{ConvertError, Result};
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase, TimeOrigin};
+
+/// Name of the metadata event recording the subtracted epoch
+pub const TIME_ORIGIN_EVENT: &str = "trace_time_origin";
+
+/// Parse `absolute`, `capture-start` or `nvtx:NAME` into a time origin
+pub fn parse_time_origin(value: &str) -> Result<TimeOrigin> {
+    match value.trim() {
+        "absolute" => Ok(TimeOrigin::Absolute),
+        "capture-start" => Ok(TimeOrigin::CaptureStart),
+        other => match other.strip_prefix("nvtx:") {
+            Some(name) if !name.is_empty() => Ok(TimeOrigin::NvtxRange(name.to_string())),
+            _ => Err(ConvertError::InvalidOption(format!(
+                "Invalid time origin '{}' (use absolute, capture-start or nvtx:NAME)",
+                value
+            ))),
+        },
+    }
+}
+
+/// How a time origin is spelled on the command line
+pub(crate) fn origin_label(origin: &TimeOrigin) -> String {
+    match origin {
+        TimeOrigin::Absolute => "absolute".to_string(),
+        TimeOrigin::CaptureStart => "capture-start".to_string(),
+        TimeOrigin::NvtxRange(name) => format!("nvtx:{}", name),
+    }
+}
+
+/// Event start in nanoseconds, preferring the exact `start_ns` arg
+fn start_ns(event: &ChromeTraceEvent) -> i64 {
+    event
+        .args
+        .get("start_ns")
+        .and_then(|v| v.as_i64())
+        .unwrap_or_else(|| (event.ts * 1000.0).round() as i64)
+}
+
+/// Earliest start of the events with the given category and name
+fn first_range<'a>(
+    events: &'a [ChromeTraceEvent],
+    cat: &str,
+    name: &str,
+) -> Option<&'a ChromeTraceEvent> {
+    events
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Complete && e.cat == cat && e.name == name)
+        .min_by_key(|e| start_ns(e))
+}
+
+/// Event whose start `origin` refers to
+fn origin_event<'a>(
+    events: &'a [ChromeTraceEvent],
+    origin: &TimeOrigin,
+) -> Option<&'a ChromeTraceEvent> {
+    match origin {
+        TimeOrigin::Absolute => None,
+        TimeOrigin::CaptureStart => events
+            .iter()
+            .filter(|e| e.ph != ChromeTracePhase::Metadata)
+            .min_by_key(|e| start_ns(e)),
+        TimeOrigin::NvtxRange(name) => {
+            first_range(events, "nvtx", name).or_else(|| first_range(events, "nvtx-kernel", name))
+        }
+    }
+}
+
+/// Find the absolute timestamp (ns) that `origin` refers to
+///
+/// NVTX ranges already folded into nvtx-kernel ranges are found through their
+/// GPU projection. Returns `None` for `Absolute` or when nothing matches.
+pub fn resolve_time_origin(events: &[ChromeTraceEvent], origin: &TimeOrigin) -> Option<i64> {
+    origin_event(events, origin).map(start_ns)
+}
+
+/// Shift every non-metadata event so `origin_ns` becomes time zero
+pub fn rebase_timestamps(events: &mut [ChromeTraceEvent], origin_ns: i64) {
+    let offset = ns_to_us(origin_ns);
+    for event in events
+        .iter_mut()
+        .filter(|e| e.ph != ChromeTracePhase::Metadata)
+    {
+        event.ts -= offset;
+    }
+}
+
+/// Rebase events to `origin` and record the epoch in a metadata event
+///
+/// The metadata event is scoped to the process of the event that defines the
+/// origin, so it does not add a track of its own. Returns the subtracted epoch
+/// in nanoseconds, or `None` when timestamps were left absolute.
+pub fn apply_time_origin(events: &mut Vec<ChromeTraceEvent>, origin: &TimeOrigin) -> Option<i64> {
+    let (origin_ns, pid) = match origin_event(events, origin) {
+        Some(event) => (start_ns(event), event.pid.clone()),
+        None => {
+            if let TimeOrigin::NvtxRange(name) = origin {
+                log::warn!(
+                    "No NVTX range named '{}'; keeping absolute timestamps",
+                    name
+                );
+            }
+            return None;
+        }
+    };
+
+    rebase_timestamps(events, origin_ns);
+
+    let mut args = HashMap::default();
+    args.insert("origin".to_string(), json!(origin_label(origin)));
+    args.insert("epoch_ns".to_string(), json!(origin_ns));
+    events.push(ChromeTraceEvent::metadata(
+        TIME_ORIGIN_EVENT.to_string(),
+        pid,
+        String::new(),
+        args,
+    ));
+    Some(origin_ns)
+}
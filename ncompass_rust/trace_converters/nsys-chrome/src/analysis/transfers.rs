@@ -0,0 +1,235 @@
+//! Memory transfer throughput per device
+//!
+//! Individual memcpy slices show how long each copy took, not whether the
+//! copy engines were saturated. This pass spreads each copy's bytes evenly
+//! over its duration and sweeps each device's copies per direction (host to
+//! device, device to host, device to device), emitting the summed rate as a
+//! `Memcpy throughput` counter track with one series per direction, plus a
+//! per-device summary of copies, bytes and busy time.
+
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase};
+use crate::parsers::memcpy::MEMCPY_CATEGORY;
+
+/// Name of the per-device throughput counter
+pub const THROUGHPUT_COUNTER: &str = "Memcpy throughput";
+
+/// Name of the metadata event holding a device's transfer totals
+pub const TRANSFER_TOTALS_EVENT: &str = "memcpy_transfer_totals";
+
+/// Category of throughput counter events
+pub const THROUGHPUT_CATEGORY: &str = "transfer_throughput";
+
+/// Direction of a copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TransferDirection {
+    HostToDevice,
+    DeviceToHost,
+    DeviceToDevice,
+}
+
+impl TransferDirection {
+    pub const ALL: [TransferDirection; 3] = [
+        TransferDirection::HostToDevice,
+        TransferDirection::DeviceToHost,
+        TransferDirection::DeviceToDevice,
+    ];
+
+    /// Direction of a `copyKind` arg (see [`crate::parsers::memcpy::copy_kind_name`]);
+    /// arrays count as device memory, host-to-host copies have none
+    pub fn from_copy_kind(copy_kind: &str) -> Option<Self> {
+        match copy_kind {
+            "HtoD" | "HtoA" => Some(TransferDirection::HostToDevice),
+            "DtoH" | "AtoH" => Some(TransferDirection::DeviceToHost),
+            "DtoD" | "DtoA" | "AtoD" | "AtoA" => Some(TransferDirection::DeviceToDevice),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TransferDirection::HostToDevice => "H2D",
+            TransferDirection::DeviceToHost => "D2H",
+            TransferDirection::DeviceToDevice => "D2D",
+        }
+    }
+}
+
+/// Copies of one direction on one device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferTotals {
+    pub copies: usize,
+    pub bytes: i64,
+    /// Time with at least one copy of this direction running
+    pub busy_ns: i64,
+}
+
+impl TransferTotals {
+    /// Average throughput while busy, in GB/s
+    pub fn avg_gbps(&self) -> f64 {
+        if self.busy_ns <= 0 {
+            return 0.0;
+        }
+        round_gbps(self.bytes as f64 / self.busy_ns as f64)
+    }
+}
+
+/// Transfer throughput of one device over time
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferThroughput {
+    pub device_id: i64,
+    /// `(timestamp, GB/s per direction from then on)` at every change, ending at 0
+    pub steps: Vec<(i64, BTreeMap<TransferDirection, f64>)>,
+    pub totals: BTreeMap<TransferDirection, TransferTotals>,
+}
+
+/// Bytes per nanosecond are GB/s; keep three decimals like `bandwidth_gbps`
+fn round_gbps(gbps: f64) -> f64 {
+    (gbps * 1000.0).round() / 1000.0
+}
+
+/// Rate and copy-count changes of one direction at one instant
+#[derive(Debug, Default, Clone, Copy)]
+struct Delta {
+    rate: f64,
+    copies: i64,
+}
+
+/// Sweep each device's memcpy events into per-direction throughput
+pub fn transfer_throughput(events: &[ChromeTraceEvent]) -> Vec<TransferThroughput> {
+    let adapter = NsysEventAdapter;
+
+    let mut deltas: BTreeMap<i64, BTreeMap<i64, HashMap<TransferDirection, Delta>>> =
+        BTreeMap::new();
+    let mut totals: BTreeMap<i64, BTreeMap<TransferDirection, TransferTotals>> = BTreeMap::new();
+    for event in events.iter().filter(|e| &*e.cat == MEMCPY_CATEGORY) {
+        let direction = event
+            .args
+            .get("copyKind")
+            .and_then(|v| v.as_str())
+            .and_then(TransferDirection::from_copy_kind);
+        let (Some(direction), Some(device_id), Some(bytes), Some((start, end))) = (
+            direction,
+            event.args.get("deviceId").and_then(|v| v.as_i64()),
+            event.args.get("bytes").and_then(|v| v.as_i64()),
+            adapter.get_time_range_ns(event),
+        ) else {
+            continue;
+        };
+        let total = totals
+            .entry(device_id)
+            .or_default()
+            .entry(direction)
+            .or_default();
+        total.copies += 1;
+        total.bytes += bytes;
+        if end <= start {
+            continue;
+        }
+        let rate = bytes as f64 / (end - start) as f64;
+        let device = deltas.entry(device_id).or_default();
+        let at_start = device
+            .entry(start)
+            .or_default()
+            .entry(direction)
+            .or_default();
+        at_start.rate += rate;
+        at_start.copies += 1;
+        let at_end = device.entry(end).or_default().entry(direction).or_default();
+        at_end.rate -= rate;
+        at_end.copies -= 1;
+    }
+
+    totals
+        .into_iter()
+        .map(|(device_id, mut totals)| {
+            let mut steps = Vec::new();
+            let mut running: BTreeMap<TransferDirection, (f64, i64)> = BTreeMap::new();
+            let mut previous: Option<i64> = None;
+            for (ts_ns, changes) in deltas.remove(&device_id).unwrap_or_default() {
+                if let Some(since) = previous {
+                    for (direction, &(_, copies)) in &running {
+                        if copies > 0 {
+                            totals.entry(*direction).or_default().busy_ns += ts_ns - since;
+                        }
+                    }
+                }
+                previous = Some(ts_ns);
+                for (direction, delta) in changes {
+                    let (rate, copies) = running.entry(direction).or_default();
+                    *rate += delta.rate;
+                    *copies += delta.copies;
+                    // No drift once the last copy of a direction ends
+                    if *copies <= 0 {
+                        *rate = 0.0;
+                    }
+                }
+                let rates = running
+                    .iter()
+                    .map(|(&direction, &(rate, _))| (direction, round_gbps(rate)))
+                    .collect();
+                steps.push((ts_ns, rates));
+            }
+            TransferThroughput {
+                device_id,
+                steps,
+                totals,
+            }
+        })
+        .collect()
+}
+
+/// Build `Memcpy throughput` counter events, one per change of each device's rates
+pub fn throughput_counter_events(throughput: &[TransferThroughput]) -> Vec<ChromeTraceEvent> {
+    throughput
+        .iter()
+        .flat_map(|device| {
+            device.steps.iter().map(move |(ts_ns, rates)| {
+                let series: HashMap<String, Value> = rates
+                    .iter()
+                    .map(|(direction, gbps)| (format!("{} GB/s", direction.label()), json!(gbps)))
+                    .collect();
+                ChromeTraceEvent::new(
+                    THROUGHPUT_COUNTER.to_string(),
+                    ChromeTracePhase::Counter,
+                    ns_to_us(*ts_ns),
+                    format!("Device {}", device.device_id),
+                    String::new(),
+                    THROUGHPUT_CATEGORY.to_string(),
+                )
+                .with_args(series)
+            })
+        })
+        .collect()
+}
+
+/// Build one `memcpy_transfer_totals` metadata event per device with its
+/// copies, bytes, busy time and average throughput per direction
+pub fn transfer_totals_events(throughput: &[TransferThroughput]) -> Vec<ChromeTraceEvent> {
+    throughput
+        .iter()
+        .map(|device| {
+            let args = device
+                .totals
+                .iter()
+                .map(|(direction, totals)| {
+                    let mut summary = Map::new();
+                    summary.insert("copies".to_string(), json!(totals.copies));
+                    summary.insert("bytes".to_string(), json!(totals.bytes));
+                    summary.insert("busy_ns".to_string(), json!(totals.busy_ns));
+                    summary.insert("avg_gbps".to_string(), json!(totals.avg_gbps()));
+                    (direction.label().to_string(), Value::Object(summary))
+                })
+                .collect();
+            ChromeTraceEvent::metadata(
+                TRANSFER_TOTALS_EVENT.to_string(),
+                format!("Device {}", device.device_id),
+                String::new(),
+                args,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,118 @@
+//! Repair of records left unfinished by an interrupted capture
+//!
+//! When nsys is killed mid-run, ranges that were still open have no end
+//! timestamp and some activity records end before they start. Parsers emit
+//! the former as complete events without a duration; this pass closes them at
+//! the end of the capture (the latest timestamp seen in any event), clamps
+//! negative durations to zero and drops records whose timestamps are not
+//! finite, so the written trace never contains NaN or negative `dur` values.
+//! Repaired events get a `truncated: true` arg.
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::frontends::FrontendTrace;
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase};
+
+/// Number of records changed by [`repair_truncated`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RepairStats {
+    /// Ranges without an end, closed at capture end
+    pub closed_at_capture_end: usize,
+    /// Records ending before they start, clamped to zero duration
+    pub clamped_negative: usize,
+    /// Records with non-finite timestamps, dropped
+    pub dropped: usize,
+}
+
+impl RepairStats {
+    /// Total number of repaired records
+    pub fn total(&self) -> usize {
+        self.closed_at_capture_end + self.clamped_negative + self.dropped
+    }
+}
+
+/// Event start in nanoseconds, preferring the exact `start_ns` arg
+pub(crate) fn start_ns(event: &ChromeTraceEvent) -> i64 {
+    event
+        .args
+        .get("start_ns")
+        .and_then(|v| v.as_i64())
+        .unwrap_or_else(|| (event.ts * 1000.0).round() as i64)
+}
+
+/// Event end in nanoseconds, if it has one
+pub(crate) fn end_ns(event: &ChromeTraceEvent) -> Option<i64> {
+    event
+        .args
+        .get("end_ns")
+        .and_then(|v| v.as_i64())
+        .or_else(|| {
+            event
+                .dur
+                .map(|dur| ((event.ts + dur) * 1000.0).round() as i64)
+        })
+}
+
+/// Latest timestamp of any event, in nanoseconds
+fn capture_end_ns<'a>(events: impl Iterator<Item = &'a ChromeTraceEvent>) -> Option<i64> {
+    events
+        .filter(|e| e.ph != ChromeTracePhase::Metadata && e.ts.is_finite())
+        .map(|e| end_ns(e).unwrap_or_else(|| start_ns(e)).max(start_ns(e)))
+        .max()
+}
+
+/// Set an event's end, keeping its `end_ns` arg in step
+fn set_end(event: &mut ChromeTraceEvent, end_ns: i64) {
+    let start_ns = start_ns(event);
+    event.dur = Some(ns_to_us(end_ns - start_ns));
+    if event.args.contains_key("start_ns") {
+        event.args.insert("end_ns".into(), json!(end_ns));
+    }
+    event.args.insert("truncated".into(), json!(true));
+}
+
+fn repair_events(events: &mut Vec<ChromeTraceEvent>, capture_end: i64, stats: &mut RepairStats) {
+    events.retain_mut(|event| {
+        if event.ph != ChromeTracePhase::Complete {
+            return true;
+        }
+        if !event.ts.is_finite() || event.dur.is_some_and(|dur| !dur.is_finite()) {
+            stats.dropped += 1;
+            return false;
+        }
+        match end_ns(event) {
+            None => {
+                set_end(event, capture_end.max(start_ns(event)));
+                stats.closed_at_capture_end += 1;
+            }
+            Some(end) if end < start_ns(event) || event.dur.is_some_and(|dur| dur < 0.0) => {
+                set_end(event, start_ns(event));
+                stats.clamped_negative += 1;
+            }
+            Some(_) => {}
+        }
+        true
+    });
+}
+
+/// Close unterminated ranges and fix impossible durations in place
+pub fn repair_truncated(trace: &mut FrontendTrace) -> RepairStats {
+    let mut stats = RepairStats::default();
+    let Some(capture_end) = capture_end_ns(
+        trace
+            .kernel_events
+            .iter()
+            .chain(&trace.api_events)
+            .chain(&trace.annotation_events)
+            .chain(&trace.other_events),
+    ) else {
+        return stats;
+    };
+
+    repair_events(&mut trace.kernel_events, capture_end, &mut stats);
+    repair_events(&mut trace.api_events, capture_end, &mut stats);
+    repair_events(&mut trace.annotation_events, capture_end, &mut stats);
+    repair_events(&mut trace.other_events, capture_end, &mut stats);
+    stats
+}
@@ -0,0 +1,151 @@
+//! Time-accurate clamping of events to a time window
+//!
+//! Cutting a trace down to a window of interest must not distort what is left
+//! in it. Ranges straddling a boundary are clamped to the window instead of
+//! being dropped or kept whole, so the time they spent inside it stays exact,
+//! and get `truncated_start` / `truncated_end` args marking the cut side.
+//! Point events (instants, counters, flows) are kept when they fall inside
+//! the window; flow arrows losing either end are removed as a pair. Metadata
+//! events are always kept. Every filtering stage that restricts a trace to a
+//! time range goes through [`clamp_to_window`].
+
+use serde_json::json;
+use std::collections::HashSet;
+
+use crate::analysis::duration_filter::parse_duration_ns;
+use crate::analysis::time_origin::resolve_time_origin;
+use crate::analysis::truncation::{end_ns, start_ns};
+use crate::error::{ConvertError, Result};
+use crate::models::{
+    ns_to_us, ChromeTraceEvent, ChromeTracePhase, StringOrInt, TimeOrigin, TimeWindow,
+};
+
+/// What clamping to a window changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClampStats {
+    /// Ranges cut at one or both window boundaries
+    pub clamped: usize,
+    /// Events entirely outside the window, removed
+    pub dropped: usize,
+}
+
+/// Parse `START..END` (e.g. `2s..3.5s`) into a window
+///
+/// Either side may be left out to leave that end open: `..500ms`, `10s..`.
+pub fn parse_time_window(value: &str) -> Result<TimeWindow> {
+    let Some((start, end)) = value.trim().split_once("..") else {
+        return Err(ConvertError::InvalidOption(format!(
+            "Invalid time window '{}' (use START..END, e.g. 2s..3.5s)",
+            value
+        )));
+    };
+    let bound = |text: &str, open: i64| match text.trim() {
+        "" => Ok(open),
+        text => parse_duration_ns(text),
+    };
+    let window = TimeWindow {
+        start_ns: bound(start, 0)?,
+        end_ns: bound(end, i64::MAX)?,
+    };
+    if window.end_ns <= window.start_ns {
+        return Err(ConvertError::InvalidOption(format!(
+            "Time window '{}' ends before it starts",
+            value
+        )));
+    }
+    Ok(window)
+}
+
+/// Restrict events to `window` (absolute nanoseconds), clamping straddling ranges
+pub fn clamp_to_window(
+    events: Vec<ChromeTraceEvent>,
+    window: TimeWindow,
+) -> (Vec<ChromeTraceEvent>, ClampStats) {
+    let mut stats = ClampStats::default();
+    let mut dropped_flows: HashSet<StringOrInt> = HashSet::new();
+    let mut kept: Vec<ChromeTraceEvent> = Vec::with_capacity(events.len());
+
+    for mut event in events {
+        if event.ph == ChromeTracePhase::Metadata {
+            kept.push(event);
+            continue;
+        }
+        let start = start_ns(&event);
+        let end = match event.ph {
+            ChromeTracePhase::Complete => end_ns(&event).unwrap_or(start).max(start),
+            _ => start,
+        };
+        let inside = if end > start {
+            start < window.end_ns && end > window.start_ns
+        } else {
+            window.contains(start)
+        };
+        if !inside {
+            stats.dropped += 1;
+            if is_flow(&event) {
+                dropped_flows.extend(event.id.clone());
+            }
+            continue;
+        }
+        if end > start && (start < window.start_ns || end > window.end_ns) {
+            clamp_event(&mut event, start, end, window);
+            stats.clamped += 1;
+        }
+        kept.push(event);
+    }
+
+    // A flow whose other end was cut off would point at nothing
+    if !dropped_flows.is_empty() {
+        let before = kept.len();
+        kept.retain(|e| {
+            !(is_flow(e) && e.id.as_ref().is_some_and(|id| dropped_flows.contains(id)))
+        });
+        stats.dropped += before - kept.len();
+    }
+
+    (kept, stats)
+}
+
+/// Restrict events to `window`, given as offsets from the start of the capture
+///
+/// Returns the events unchanged when there is nothing to measure from.
+pub fn apply_time_window(
+    events: Vec<ChromeTraceEvent>,
+    window: TimeWindow,
+) -> (Vec<ChromeTraceEvent>, ClampStats) {
+    let Some(capture_start) = resolve_time_origin(&events, &TimeOrigin::CaptureStart) else {
+        return (events, ClampStats::default());
+    };
+    let absolute = TimeWindow {
+        start_ns: capture_start.saturating_add(window.start_ns),
+        end_ns: capture_start.saturating_add(window.end_ns),
+    };
+    clamp_to_window(events, absolute)
+}
+
+fn is_flow(event: &ChromeTraceEvent) -> bool {
+    matches!(
+        event.ph,
+        ChromeTracePhase::FlowStart | ChromeTracePhase::FlowFinish
+    )
+}
+
+/// Cut a range at the window boundaries it crosses, keeping its args in step
+fn clamp_event(event: &mut ChromeTraceEvent, start: i64, end: i64, window: TimeWindow) {
+    let new_start = start.max(window.start_ns);
+    let new_end = end.min(window.end_ns);
+    event.ts = ns_to_us(new_start);
+    event.dur = Some(ns_to_us(new_end - new_start));
+    if event.args.contains_key("start_ns") {
+        event.args.insert("start_ns".into(), json!(new_start));
+    }
+    if event.args.contains_key("end_ns") {
+        event.args.insert("end_ns".into(), json!(new_end));
+    }
+    if new_start > start {
+        event.args.insert("truncated_start".into(), json!(true));
+    }
+    if new_end < end {
+        event.args.insert("truncated_end".into(), json!(true));
+    }
+}
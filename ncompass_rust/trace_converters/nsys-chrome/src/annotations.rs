@@ -0,0 +1,124 @@
+//! NVTX-only traces for auditing instrumentation
+//!
+//! Before spending minutes on a full conversion it is worth checking that a
+//! workload's NVTX ranges are where they should be: named right, in the right
+//! domains, nested as intended, and closed. The annotations-only mode reads
+//! just the NVTX tables (never the kernel or API tables, which dominate large
+//! exports) and lays the ranges out per process and thread. Each range gets an
+//! `nvtx_depth` arg (0 for outermost) so nesting can be checked without
+//! eyeballing the timeline; ranges left open at capture end carry the usual
+//! `truncated` arg from the repair pass.
+
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::analysis::truncation::{end_ns, start_ns};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Arg holding a range's nesting depth on its thread (0 = outermost)
+pub const NVTX_DEPTH_ARG: &str = "nvtx_depth";
+
+/// Process track of an annotation, by OS process ID
+pub fn annotation_process(raw_pid: i64) -> String {
+    format!("Process {}", raw_pid)
+}
+
+fn raw_id(event: &ChromeTraceEvent, key: &str) -> Option<i64> {
+    event.args.get(key).and_then(|v| v.as_i64())
+}
+
+/// Move annotations from device tracks to per-process tracks
+///
+/// Without kernel tables there is no process-to-device mapping, so the
+/// device numbering of a full conversion (and the `deviceId` arg, which
+/// falls back to the process ID) would be meaningless here.
+pub fn group_by_process(events: &mut [ChromeTraceEvent]) {
+    for event in events {
+        if let Some(pid) = raw_id(event, "raw_pid") {
+            event.pid = annotation_process(pid).into();
+            event.args.remove("deviceId");
+        }
+    }
+}
+
+/// Record each range's nesting depth within its track
+///
+/// Ranges are nested by time on each (process, track) pair: a range is a
+/// child of every earlier range on the track that is still open when it
+/// starts. Returns the deepest nesting level seen.
+pub fn annotate_nesting_depth(events: &mut [ChromeTraceEvent]) -> usize {
+    let mut per_track: HashMap<(String, String), Vec<usize>> = HashMap::new();
+    for (idx, event) in events.iter().enumerate() {
+        if event.ph == ChromeTracePhase::Complete {
+            per_track
+                .entry((event.pid.to_string(), event.tid.to_string()))
+                .or_default()
+                .push(idx);
+        }
+    }
+
+    let mut deepest = 0;
+    for mut indices in per_track.into_values() {
+        // Outer ranges first when two start together
+        indices.sort_by_key(|&idx| {
+            let event = &events[idx];
+            (start_ns(event), std::cmp::Reverse(end_ns(event)))
+        });
+        let mut open_ends: Vec<i64> = Vec::new();
+        for idx in indices {
+            let start = start_ns(&events[idx]);
+            let end = end_ns(&events[idx]).unwrap_or(start);
+            while open_ends.last().is_some_and(|&open_end| open_end <= start) {
+                open_ends.pop();
+            }
+            let depth = open_ends.len();
+            deepest = deepest.max(depth);
+            events[idx].args.insert(NVTX_DEPTH_ARG.into(), json!(depth));
+            open_ends.push(end);
+        }
+    }
+    deepest
+}
+
+/// Process and thread name metadata for annotation tracks
+///
+/// Threads are named after the OS thread name when nsys recorded one.
+pub fn annotation_metadata(
+    events: &[ChromeTraceEvent],
+    thread_names: &HashMap<i32, String>,
+) -> Vec<ChromeTraceEvent> {
+    let mut processes = BTreeSet::new();
+    let mut tracks = BTreeSet::new();
+    for event in events {
+        if let (Some(pid), Some(tid)) = (raw_id(event, "raw_pid"), raw_id(event, "raw_tid")) {
+            processes.insert(pid);
+            tracks.insert((pid, event.tid.to_string(), tid));
+        }
+    }
+
+    let mut metadata: Vec<ChromeTraceEvent> = processes
+        .into_iter()
+        .map(|pid| {
+            let process = annotation_process(pid);
+            ChromeTraceEvent::metadata(
+                "process_name".to_string(),
+                process.clone(),
+                String::new(),
+                HashMap::from([("name".to_string(), json!(process))]),
+            )
+        })
+        .collect();
+    for (pid, track, tid) in tracks {
+        let name = match i32::try_from(tid).ok().and_then(|t| thread_names.get(&t)) {
+            Some(thread_name) => format!("{} ({})", track, thread_name),
+            None => track.clone(),
+        };
+        metadata.push(ChromeTraceEvent::metadata(
+            "thread_name".to_string(),
+            annotation_process(pid),
+            track,
+            HashMap::from([("name".to_string(), json!(name))]),
+        ));
+    }
+    metadata
+}
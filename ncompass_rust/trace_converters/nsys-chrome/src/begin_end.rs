@@ -0,0 +1,174 @@
+//! Conversion of complete (`X`) events into Begin/End (`B`/`E`) pairs
+//!
+//! Some consumers only understand duration events. Each complete event becomes
+//! a `B` event carrying its args and an `E` event at its end, written in time
+//! order. Pairs are guaranteed to be balanced and properly nested per track:
+//! - an event that would outlive the enclosing event on its track is cut at
+//!   the enclosing event's end,
+//! - an event without a duration is closed with the enclosing event, or at
+//!   the end of the trace if there is none,
+//!
+//! and both cases are marked with a `truncated` arg on the `B` event. Ends at
+//! the same timestamp close the innermost event first.
+
+use serde_json::json;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use crate::intern::InternedStr;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Iterator adapter turning complete events into Begin/End pairs
+///
+/// Input must be sorted by timestamp. Events other than complete events are
+/// passed through unchanged.
+pub struct BeginEndEvents<I> {
+    inner: I,
+    ready: VecDeque<ChromeTraceEvent>,
+    /// Pending ends, earliest first; among equal ends the latest begun first
+    pending: BinaryHeap<Reverse<(EndKey, Reverse<u64>)>>,
+    end_events: HashMap<u64, ChromeTraceEvent>,
+    /// Ends of the events still open on each track, outermost first
+    open: HashMap<(InternedStr, InternedStr), Vec<f64>>,
+    next_seq: u64,
+    /// Latest timestamp seen, where events without a duration are closed
+    trace_end: f64,
+    done: bool,
+}
+
+/// Total order over end timestamps; `None` (no duration) sorts last
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct EndKey(Option<f64>);
+
+impl Eq for EndKey {}
+
+impl PartialOrd for EndKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EndKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }
+    }
+}
+
+impl<I: Iterator<Item = ChromeTraceEvent>> BeginEndEvents<I> {
+    pub fn new(events: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            inner: events.into_iter(),
+            ready: VecDeque::new(),
+            pending: BinaryHeap::new(),
+            end_events: HashMap::new(),
+            open: HashMap::new(),
+            next_seq: 0,
+            trace_end: f64::NEG_INFINITY,
+            done: false,
+        }
+    }
+
+    /// Queue the ends due at or before `ts`
+    fn close_until(&mut self, ts: Option<f64>) {
+        while let Some(Reverse((EndKey(end), Reverse(seq)))) = self.pending.peek().copied() {
+            let due = match (end, ts) {
+                (_, None) => true,
+                (Some(end), Some(ts)) => end <= ts,
+                (None, Some(_)) => false,
+            };
+            if !due {
+                break;
+            }
+            self.pending.pop();
+            let mut end_event = self
+                .end_events
+                .remove(&seq)
+                .expect("every pending end has an event");
+            if end.is_none() {
+                end_event.ts = self.trace_end;
+            }
+            self.ready.push_back(end_event);
+        }
+    }
+
+    fn split(&mut self, mut event: ChromeTraceEvent) {
+        let ts = event.ts;
+        self.trace_end = self.trace_end.max(ts);
+        self.close_until(Some(ts));
+
+        if event.ph != ChromeTracePhase::Complete {
+            self.ready.push_back(event);
+            return;
+        }
+
+        // Forget events on this track that have already ended
+        let track = (event.pid.clone(), event.tid.clone());
+        let stack = self.open.entry(track).or_default();
+        while stack.last().is_some_and(|&end| end <= ts) {
+            stack.pop();
+        }
+
+        // Clamp to the innermost open event, which is itself clamped to its parent
+        let parent_end = stack.last().copied().unwrap_or(f64::INFINITY);
+        let full_end = event.dur.map_or(f64::INFINITY, |dur| ts + dur);
+        let truncated = event.dur.is_none() || full_end > parent_end;
+        let clamped_end = full_end.min(parent_end);
+        stack.push(clamped_end);
+        let end = Some(clamped_end).filter(|end| end.is_finite());
+        if let Some(end) = end {
+            self.trace_end = self.trace_end.max(end);
+        }
+
+        let end_event = ChromeTraceEvent::new(
+            event.name.clone(),
+            ChromeTracePhase::DurationEnd,
+            end.unwrap_or(ts),
+            event.pid.clone(),
+            event.tid.clone(),
+            event.cat.clone(),
+        );
+        event.ph = ChromeTracePhase::DurationBegin;
+        event.dur = None;
+        if truncated {
+            event.args.insert("truncated".into(), json!(true));
+        }
+        self.ready.push_back(event);
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.end_events.insert(seq, end_event);
+        self.pending.push(Reverse((EndKey(end), Reverse(seq))));
+    }
+}
+
+impl<I: Iterator<Item = ChromeTraceEvent>> Iterator for BeginEndEvents<I> {
+    type Item = ChromeTraceEvent;
+
+    fn next(&mut self) -> Option<ChromeTraceEvent> {
+        loop {
+            if let Some(event) = self.ready.pop_front() {
+                return Some(event);
+            }
+            if self.done {
+                return None;
+            }
+            match self.inner.next() {
+                Some(event) => self.split(event),
+                None => {
+                    self.done = true;
+                    self.close_until(None);
+                }
+            }
+        }
+    }
+}
+
+/// Convert sorted events into Begin/End pairs
+pub fn to_begin_end(events: Vec<ChromeTraceEvent>) -> Vec<ChromeTraceEvent> {
+    BeginEndEvents::new(events).collect()
+}
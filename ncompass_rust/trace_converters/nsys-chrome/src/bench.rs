@@ -0,0 +1,318 @@
+//! Output writer benchmark on synthetic events
+//!
+//! Compression trades CPU time for bytes written, and which side wins depends
+//! on the machine and the storage behind the output. [`run_write_bench`]
+//! writes the same synthetic trace with each [`BenchWriter`] and reports the
+//! time (including syncing the file to storage), throughput and output size,
+//! so the choice can be made from measurements rather than guesses.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::error::{ConvertError, Result};
+use crate::models::{ns_to_us, ChromeTraceEvent};
+use crate::writer::{ChromeTraceWriter, WriteOptions};
+
+/// Kernel names of the synthetic trace, short and templated like real ones
+const KERNEL_NAMES: &[&str] = &[
+    "ampere_sgemm_128x64_tn",
+    "void at::native::vectorized_elementwise_kernel<4, at::native::GeluCUDAKernelImpl(at::TensorIteratorBase&, at::native::GeluType)::{lambda()#1}::operator()() const::{lambda()#2}::operator()() const::{lambda(float)#1}, at::detail::Array<char*, 2> >(int, at::native::GeluCUDAKernelImpl(at::TensorIteratorBase&, at::native::GeluType)::{lambda()#1}::operator()() const::{lambda()#2}::operator()() const::{lambda(float)#1}, at::detail::Array<char*, 2>)",
+    "void cutlass::Kernel<cutlass_80_tensorop_bf16_s16816gemm_relu_bf16_64x64_64x4_tn_align8>(cutlass_80_tensorop_bf16_s16816gemm_relu_bf16_64x64_64x4_tn_align8::Params)",
+    "ncclDevKernel_AllReduce_Sum_bf16_RING_LL(ncclDevComm*, unsigned long, ncclWork*)",
+    "flash_fwd_kernel",
+];
+
+/// Writer measured by the benchmark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchWriter {
+    /// Uncompressed JSON
+    Plain,
+    /// Single-threaded gzip
+    Gzip,
+    /// Multi-threaded gzip, as used for `.json.gz` output
+    ParallelGzip,
+    /// Zstandard; not available in this build
+    Zstd,
+}
+
+impl BenchWriter {
+    /// All writers, in the order they are reported
+    pub const ALL: [BenchWriter; 4] = [
+        BenchWriter::Plain,
+        BenchWriter::Gzip,
+        BenchWriter::ParallelGzip,
+        BenchWriter::Zstd,
+    ];
+
+    /// Name used on the command line and in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            BenchWriter::Plain => "plain",
+            BenchWriter::Gzip => "gz",
+            BenchWriter::ParallelGzip => "parallel-gz",
+            BenchWriter::Zstd => "zstd",
+        }
+    }
+
+    /// Whether this build can write with it
+    pub fn is_available(&self) -> bool {
+        !matches!(self, BenchWriter::Zstd)
+    }
+
+    /// File extension of its output
+    fn extension(&self) -> &'static str {
+        match self {
+            BenchWriter::Plain => "json",
+            BenchWriter::Gzip | BenchWriter::ParallelGzip => "json.gz",
+            BenchWriter::Zstd => "json.zst",
+        }
+    }
+}
+
+/// Parse `plain`, `gz`, `parallel-gz` or `zstd`
+pub fn parse_bench_writer(value: &str) -> Result<BenchWriter> {
+    BenchWriter::ALL
+        .into_iter()
+        .find(|writer| writer.name() == value.trim())
+        .ok_or_else(|| {
+            ConvertError::InvalidOption(format!(
+                "Unknown writer '{}' (use plain, gz, parallel-gz or zstd)",
+                value
+            ))
+        })
+}
+
+/// Generate `count` kernel, CUDA API and NVTX events resembling a training trace
+///
+/// The same `count` always gives the same events.
+pub fn synthetic_events(count: usize) -> Vec<ChromeTraceEvent> {
+    // xorshift, so sizes vary without an RNG dependency
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let mut events = Vec::with_capacity(count);
+    let mut time_ns: i64 = 1_000_000;
+    let mut correlation_id: i64 = 0;
+    while events.len() < count {
+        let step = events.len() / 3;
+        let device_id = (step % 2) as i64;
+        let pid = format!("Device {}", device_id);
+        let launch_ns = time_ns;
+        let start_ns = launch_ns + 2_000 + (next() % 3_000) as i64;
+        let end_ns = start_ns + 1_000 + (next() % 200_000) as i64;
+        correlation_id += 1;
+
+        let name = KERNEL_NAMES[(next() % KERNEL_NAMES.len() as u64) as usize];
+        events.push(
+            ChromeTraceEvent::complete(
+                name.to_string(),
+                ns_to_us(start_ns),
+                ns_to_us(end_ns - start_ns),
+                pid.clone(),
+                format!("Stream {}", 7 + next() % 4),
+                "kernel".to_string(),
+            )
+            .with_arg("deviceId", device_id)
+            .with_arg("correlationId", correlation_id)
+            .with_arg("start_ns", start_ns)
+            .with_arg("end_ns", end_ns)
+            .with_arg("grid", json!([next() % 1024 + 1, 1, 1]))
+            .with_arg("block", json!([128, 1, 1]))
+            .with_arg("registersPerThread", 32 + next() % 96),
+        );
+        if events.len() < count {
+            events.push(
+                ChromeTraceEvent::complete(
+                    "cudaLaunchKernel".to_string(),
+                    ns_to_us(launch_ns),
+                    ns_to_us(1_500),
+                    pid.clone(),
+                    "CUDA API Thread 4242".to_string(),
+                    "cuda_api".to_string(),
+                )
+                .with_arg("deviceId", device_id)
+                .with_arg("correlationId", correlation_id),
+            );
+        }
+        if events.len() < count {
+            events.push(ChromeTraceEvent::complete(
+                format!("layer.{}.forward", step % 48),
+                ns_to_us(launch_ns - 500),
+                ns_to_us(end_ns - launch_ns + 500),
+                pid,
+                "NVTX Thread 4242".to_string(),
+                "nvtx".to_string(),
+            ));
+        }
+        time_ns = end_ns + 1_000;
+    }
+    events
+}
+
+/// Measurement of one writer
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteBenchResult {
+    pub writer: BenchWriter,
+    /// Fastest of the repeated runs
+    pub elapsed: Duration,
+    /// Size of the written file
+    pub output_bytes: u64,
+}
+
+/// Measurements of every requested writer on one set of events
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteBenchReport {
+    pub events: usize,
+    /// Size of the trace as plain JSON, the input every writer encodes
+    pub json_bytes: u64,
+    pub results: Vec<WriteBenchResult>,
+    /// Requested writers this build cannot write with
+    pub unavailable: Vec<BenchWriter>,
+}
+
+impl WriteBenchResult {
+    /// Events written per second
+    pub fn events_per_sec(&self, events: usize) -> f64 {
+        events as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Megabytes of plain JSON encoded per second
+    pub fn json_mb_per_sec(&self, json_bytes: u64) -> f64 {
+        json_bytes as f64 / 1e6 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl WriteBenchReport {
+    /// Aligned table with one row per writer
+    pub fn render_table(&self) -> String {
+        let mut out = format!(
+            "{} events, {:.1} MB as plain JSON\n\n{:<12} {:>10} {:>12} {:>14} {:>11} {:>7}\n",
+            self.events,
+            self.json_bytes as f64 / 1e6,
+            "writer",
+            "time (s)",
+            "JSON MB/s",
+            "events/s",
+            "output MB",
+            "ratio"
+        );
+        for result in &self.results {
+            out.push_str(&format!(
+                "{:<12} {:>10.3} {:>12.1} {:>14.0} {:>11.1} {:>7.2}\n",
+                result.writer.name(),
+                result.elapsed.as_secs_f64(),
+                result.json_mb_per_sec(self.json_bytes),
+                result.events_per_sec(self.events),
+                result.output_bytes as f64 / 1e6,
+                result.output_bytes as f64 / self.json_bytes.max(1) as f64,
+            ));
+        }
+        for writer in &self.unavailable {
+            out.push_str(&format!(
+                "{:<12} not available in this build\n",
+                writer.name()
+            ));
+        }
+        out
+    }
+}
+
+/// Sink counting the bytes written to it
+#[derive(Default)]
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write `events` once to `path` with `writer`, syncing it to storage
+fn write_once(writer: BenchWriter, events: Vec<ChromeTraceEvent>, path: &Path) -> Result<()> {
+    let create_error = |source| ConvertError::CreateOutput {
+        path: path.to_path_buf(),
+        source,
+    };
+    let file = File::create(path).map_err(create_error)?;
+    let sync = file.try_clone().map_err(create_error)?;
+    match writer {
+        BenchWriter::Plain | BenchWriter::ParallelGzip => {
+            let options = WriteOptions {
+                gzip: writer == BenchWriter::ParallelGzip,
+                ..Default::default()
+            };
+            ChromeTraceWriter::write_to(file, events, options)?;
+        }
+        BenchWriter::Gzip => {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            ChromeTraceWriter::write_impl(&mut encoder, events, None, None, false)?;
+            encoder.finish().map_err(ConvertError::Output)?;
+        }
+        BenchWriter::Zstd => {
+            return Err(ConvertError::InvalidOption(
+                "zstd compression is not available in this build".to_string(),
+            ))
+        }
+    }
+    sync.sync_all().map_err(ConvertError::Output)
+}
+
+/// Time each available writer on `events`, keeping the fastest of `repeat` runs
+///
+/// Outputs are written to `dir`, one file per writer, replacing earlier runs.
+pub fn run_write_bench(
+    events: &[ChromeTraceEvent],
+    writers: &[BenchWriter],
+    dir: &Path,
+    repeat: usize,
+) -> Result<WriteBenchReport> {
+    let mut json_bytes = ByteCounter::default();
+    ChromeTraceWriter::write_impl(&mut json_bytes, events.to_vec(), None, None, false)?;
+
+    let mut results = Vec::new();
+    let mut unavailable = Vec::new();
+    for &writer in writers {
+        if !writer.is_available() {
+            unavailable.push(writer);
+            continue;
+        }
+        let path = dir.join(format!("bench-{}.{}", writer.name(), writer.extension()));
+        let mut elapsed = Duration::MAX;
+        for _ in 0..repeat.max(1) {
+            let events = events.to_vec();
+            let start = Instant::now();
+            write_once(writer, events, &path)?;
+            elapsed = elapsed.min(start.elapsed());
+        }
+        let output_bytes = std::fs::metadata(&path)
+            .map_err(ConvertError::Output)?
+            .len();
+        results.push(WriteBenchResult {
+            writer,
+            elapsed,
+            output_bytes,
+        });
+    }
+
+    Ok(WriteBenchReport {
+        events: events.len(),
+        json_bytes: json_bytes.0,
+        results,
+        unavailable,
+    })
+}
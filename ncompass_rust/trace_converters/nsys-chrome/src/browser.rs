@@ -0,0 +1,539 @@
+//! Terminal event browser for inspecting traces without Chrome
+//!
+//! Loads the complete (`X`) events of a converted trace, or events converted
+//! in memory, and provides the views of the `view` subcommand: lists of
+//! kernels or NVTX ranges searched by name and sorted by duration, start or
+//! name, per-track totals, and per-track ASCII timelines. The full-screen UI
+//! in [`crate::tui`] draws them; [`run_browser`] answers the same queries as
+//! line commands, for pipes and terminals the UI cannot drive. Either only
+//! needs a plain terminal, so it works over SSH on remote servers where no
+//! browser is available.
+//!
+//! Trace files are read through [`RawTrace`], which leaves every event's args
+//! unparsed, and only the name, category, track and times of complete events
+//! are kept.
+
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, RawTrace};
+
+/// Rows shown by default in event lists
+pub const DEFAULT_LIST_LIMIT: usize = 20;
+
+/// Columns used for timeline bars
+pub const DEFAULT_TIMELINE_WIDTH: usize = 60;
+
+/// Width of the name column in lists and timelines
+pub(crate) const NAME_WIDTH: usize = 48;
+
+/// Gzip magic bytes
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Read a Chrome trace JSON file, plain or gzip-compressed
+pub(crate) fn read_trace_json(path: &Path) -> Result<Value> {
+    let bytes = read_trace_bytes(path)?;
+    serde_json::from_slice(&bytes).map_err(|e| {
+        ConvertError::InvalidInput(format!(
+            "Failed to parse trace JSON {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Read a Chrome trace JSON file with its args left unparsed (see [`RawTrace`])
+pub(crate) fn read_raw_trace(path: &Path) -> Result<RawTrace> {
+    let bytes = read_trace_bytes(path)?;
+    RawTrace::from_slice(&bytes).map_err(|e| match e {
+        ConvertError::Json(e) => ConvertError::InvalidInput(format!(
+            "Failed to parse trace JSON {}: {}",
+            path.display(),
+            e
+        )),
+        e => e,
+    })
+}
+
+/// Read the JSON text of a trace file, decompressing gzip
+pub(crate) fn read_trace_bytes(path: &Path) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut bytes))
+        .map_err(|e| ConvertError::open_input(path, e))?;
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut text = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut text)
+            .map_err(|e| ConvertError::InputRead {
+                path: path.to_path_buf(),
+                source: e,
+            })?;
+        bytes = text;
+    }
+    Ok(bytes)
+}
+
+/// A complete event as shown by the browser
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrowserEvent {
+    pub name: String,
+    pub cat: String,
+    /// Track as "pid / tid"
+    pub track: String,
+    pub start_us: f64,
+    pub dur_us: f64,
+}
+
+impl BrowserEvent {
+    fn end_us(&self) -> f64 {
+        self.start_us + self.dur_us
+    }
+}
+
+/// Which events a list shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// GPU kernels
+    Kernels,
+    /// NVTX and nvtx-kernel ranges
+    Nvtx,
+    /// Every complete event
+    All,
+}
+
+impl EventKind {
+    fn matches(&self, cat: &str) -> bool {
+        match self {
+            EventKind::Kernels => cat == "kernel",
+            EventKind::Nvtx => cat == "nvtx" || cat == "nvtx-kernel",
+            EventKind::All => true,
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            EventKind::Kernels => "kernels",
+            EventKind::Nvtx => "NVTX ranges",
+            EventKind::All => "events",
+        }
+    }
+}
+
+/// Order of an event list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Longest first
+    Duration,
+    /// Earliest first
+    Start,
+    /// Alphabetical
+    Name,
+}
+
+impl SortKey {
+    /// Parse `duration`, `start` or `name` (or their first letter)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "duration" | "dur" | "d" => Some(SortKey::Duration),
+            "start" | "ts" | "s" => Some(SortKey::Start),
+            "name" | "n" => Some(SortKey::Name),
+            _ => None,
+        }
+    }
+}
+
+/// List selection: kind, name filter, order and row limit
+#[derive(Debug, Clone)]
+pub struct ListQuery {
+    pub kind: EventKind,
+    /// Case-insensitive name substring
+    pub search: Option<String>,
+    pub sort: SortKey,
+    pub limit: usize,
+}
+
+impl Default for ListQuery {
+    fn default() -> Self {
+        Self {
+            kind: EventKind::Kernels,
+            search: None,
+            sort: SortKey::Duration,
+            limit: DEFAULT_LIST_LIMIT,
+        }
+    }
+}
+
+fn value_label(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+pub(crate) fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let mut short: String = text.chars().take(width.saturating_sub(1)).collect();
+        short.push('…');
+        short
+    }
+}
+
+/// Format microseconds with a readable unit
+pub fn format_duration_us(us: f64) -> String {
+    if us >= 1e6 {
+        format!("{:.3} s", us / 1e6)
+    } else if us >= 1e3 {
+        format!("{:.3} ms", us / 1e3)
+    } else {
+        format!("{:.3} us", us)
+    }
+}
+
+/// Column titles of an event list
+pub(crate) fn list_header() -> String {
+    format!(
+        "{:>12}  {:>14}  {:<name$}  {}",
+        "duration",
+        "start",
+        "name",
+        "track",
+        name = NAME_WIDTH
+    )
+}
+
+/// One event of a list, in the columns of [`list_header`]
+pub(crate) fn list_row(event: &BrowserEvent) -> String {
+    format!(
+        "{:>12}  {:>14.3}  {:<name$}  {}",
+        format_duration_us(event.dur_us),
+        event.start_us,
+        truncate(&event.name, NAME_WIDTH),
+        event.track,
+        name = NAME_WIDTH
+    )
+}
+
+/// Event count, busy time and name of a track
+pub(crate) fn track_row(track: &str, count: usize, busy_us: f64) -> String {
+    format!(
+        "{:>8}  {:>12}  {}",
+        count,
+        format_duration_us(busy_us),
+        track
+    )
+}
+
+/// Loaded trace with list and timeline views
+#[derive(Debug, Default)]
+pub struct TraceBrowser {
+    events: Vec<BrowserEvent>,
+}
+
+impl TraceBrowser {
+    /// Browse events converted in memory
+    pub fn from_events(events: &[ChromeTraceEvent]) -> Self {
+        let events = events
+            .iter()
+            .filter(|e| e.ph == ChromeTracePhase::Complete)
+            .map(|e| BrowserEvent {
+                name: e.name.to_string(),
+                cat: e.cat.to_string(),
+                track: format!("{} / {}", e.pid, e.tid),
+                start_us: e.ts,
+                dur_us: e.dur.unwrap_or(0.0),
+            })
+            .collect();
+        Self { events }
+    }
+
+    /// Browse a Chrome trace JSON file, plain or gzip-compressed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::from_raw(&read_raw_trace(path.as_ref())?))
+    }
+
+    /// Browse a trace read with unparsed args
+    fn from_raw(trace: &RawTrace) -> Self {
+        let events = trace
+            .events
+            .iter()
+            .filter(|e| e.get_str("ph") == Some("X"))
+            .map(|e| BrowserEvent {
+                name: value_label(e.get("name")),
+                cat: value_label(e.get("cat")),
+                track: format!(
+                    "{} / {}",
+                    value_label(e.get("pid")),
+                    value_label(e.get("tid"))
+                ),
+                start_us: e.get("ts").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                dur_us: e.get("dur").and_then(|v| v.as_f64()).unwrap_or(0.0),
+            })
+            .collect();
+        Self { events }
+    }
+
+    /// Browse an already-parsed trace (`{"traceEvents": [...]}` or a bare array)
+    pub fn from_value(root: &Value) -> Result<Self> {
+        let Some(trace_events) = root
+            .as_array()
+            .or_else(|| root.get("traceEvents").and_then(|v| v.as_array()))
+        else {
+            return Err(ConvertError::InvalidInput(
+                "Not a Chrome trace: missing 'traceEvents' array".to_string(),
+            ));
+        };
+
+        let events = trace_events
+            .iter()
+            .filter(|e| e.get("ph").and_then(|v| v.as_str()) == Some("X"))
+            .map(|e| BrowserEvent {
+                name: value_label(e.get("name")),
+                cat: value_label(e.get("cat")),
+                track: format!(
+                    "{} / {}",
+                    value_label(e.get("pid")),
+                    value_label(e.get("tid"))
+                ),
+                start_us: e.get("ts").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                dur_us: e.get("dur").and_then(|v| v.as_f64()).unwrap_or(0.0),
+            })
+            .collect();
+        Ok(Self { events })
+    }
+
+    /// Number of complete events loaded
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether no complete events were loaded
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Events selected by a query, in its order
+    pub fn list(&self, query: &ListQuery) -> Vec<&BrowserEvent> {
+        let needle = query.search.as_deref().map(str::to_lowercase);
+        let mut selected: Vec<&BrowserEvent> = self
+            .events
+            .iter()
+            .filter(|e| query.kind.matches(&e.cat))
+            .filter(|e| {
+                needle
+                    .as_deref()
+                    .is_none_or(|n| e.name.to_lowercase().contains(n))
+            })
+            .collect();
+        match query.sort {
+            SortKey::Duration => selected.sort_by(|a, b| b.dur_us.total_cmp(&a.dur_us)),
+            SortKey::Start => selected.sort_by(|a, b| a.start_us.total_cmp(&b.start_us)),
+            SortKey::Name => selected.sort_by(|a, b| a.name.cmp(&b.name)),
+        }
+        selected
+    }
+
+    /// Event list as a text table, with a header naming the total
+    pub fn render_list(&self, query: &ListQuery) -> String {
+        let selected = self.list(query);
+        let mut out = format!(
+            "{} {}{}\n",
+            selected.len(),
+            query.kind.label(),
+            query
+                .search
+                .as_deref()
+                .map(|s| format!(" matching '{}'", s))
+                .unwrap_or_default()
+        );
+        out.push_str(&list_header());
+        out.push('\n');
+        for event in selected.iter().take(query.limit) {
+            out.push_str(&list_row(event));
+            out.push('\n');
+        }
+        if selected.len() > query.limit {
+            out.push_str(&format!("... {} more\n", selected.len() - query.limit));
+        }
+        out
+    }
+
+    /// Event count and busy time per track, in track order
+    pub fn tracks(&self) -> Vec<(String, usize, f64)> {
+        let mut tracks: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+        for event in &self.events {
+            let entry = tracks.entry(&event.track).or_default();
+            entry.0 += 1;
+            entry.1 += event.dur_us;
+        }
+        tracks
+            .into_iter()
+            .map(|(track, (count, busy))| (track.to_string(), count, busy))
+            .collect()
+    }
+
+    /// One ASCII bar per track over the whole trace span
+    ///
+    /// `#` marks columns where an event is running, `.` idle columns. Only
+    /// tracks containing `filter` are drawn when one is given.
+    pub fn render_timeline(&self, width: usize, filter: Option<&str>) -> String {
+        let width = width.max(1);
+        let start = self
+            .events
+            .iter()
+            .map(|e| e.start_us)
+            .fold(f64::INFINITY, f64::min);
+        let end = self
+            .events
+            .iter()
+            .map(|e| e.end_us())
+            .fold(f64::NEG_INFINITY, f64::max);
+        if !start.is_finite() || !end.is_finite() {
+            return "No events\n".to_string();
+        }
+        let span = (end - start).max(f64::EPSILON);
+        let position = |ts: f64| ((ts - start) / span) * width as f64;
+
+        let mut bars: BTreeMap<&str, Vec<bool>> = BTreeMap::new();
+        for event in &self.events {
+            if filter.is_some_and(|f| !event.track.contains(f)) {
+                continue;
+            }
+            let bar = bars
+                .entry(&event.track)
+                .or_insert_with(|| vec![false; width]);
+            // Columns are half-open, so an event ending on a boundary stops before it
+            let first = (position(event.start_us).floor() as usize).min(width - 1);
+            let last = (position(event.end_us()).ceil() as usize)
+                .saturating_sub(1)
+                .clamp(first, width - 1);
+            for cell in &mut bar[first..=last] {
+                *cell = true;
+            }
+        }
+
+        let mut out = format!(
+            "{:<name$}  {} .. {} ({})\n",
+            "track",
+            format_duration_us(start),
+            format_duration_us(end),
+            format_duration_us(end - start),
+            name = NAME_WIDTH
+        );
+        for (track, bar) in bars {
+            let line: String = bar
+                .iter()
+                .map(|&busy| if busy { '#' } else { '.' })
+                .collect();
+            out.push_str(&format!(
+                "{:<name$}  |{}|\n",
+                truncate(track, NAME_WIDTH),
+                line,
+                name = NAME_WIDTH
+            ));
+        }
+        out
+    }
+}
+
+const HELP: &str = "\
+Commands:
+  kernels | nvtx | all     choose which events to list
+  search TEXT              filter by name (no TEXT clears the filter)
+  sort duration|start|name change the order
+  top N                    show N rows
+  list                     show the current list
+  tracks                   event count and busy time per track
+  timeline [TRACK]         ASCII timeline, optionally of matching tracks only
+  help                     show this help
+  quit                     exit
+";
+
+/// Read commands from `input` until `quit` or end of input
+pub fn run_browser<R: BufRead, W: Write>(
+    browser: &TraceBrowser,
+    input: R,
+    mut output: W,
+) -> Result<()> {
+    let mut query = ListQuery::default();
+    writeln!(
+        output,
+        "Loaded {} events. Type 'help' for commands.",
+        browser.len()
+    )?;
+    write!(output, "{}", browser.render_list(&query))?;
+
+    let mut lines = input.lines();
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        let reply = match command {
+            "" => continue,
+            "quit" | "q" | "exit" => break,
+            "help" | "h" | "?" => HELP.to_string(),
+            "kernels" | "nvtx" | "all" => {
+                query.kind = match command {
+                    "kernels" => EventKind::Kernels,
+                    "nvtx" => EventKind::Nvtx,
+                    _ => EventKind::All,
+                };
+                browser.render_list(&query)
+            }
+            "search" | "/" => {
+                query.search = (!rest.is_empty()).then(|| rest.to_string());
+                browser.render_list(&query)
+            }
+            "sort" => match SortKey::parse(rest) {
+                Some(sort) => {
+                    query.sort = sort;
+                    browser.render_list(&query)
+                }
+                None => "Usage: sort duration|start|name\n".to_string(),
+            },
+            "top" => match rest.parse() {
+                Ok(limit) => {
+                    query.limit = limit;
+                    browser.render_list(&query)
+                }
+                Err(_) => "Usage: top N\n".to_string(),
+            },
+            "list" | "ls" => browser.render_list(&query),
+            "tracks" => browser
+                .tracks()
+                .iter()
+                .map(|(track, count, busy)| track_row(track, *count, *busy) + "\n")
+                .collect(),
+            "timeline" | "t" => {
+                browser.render_timeline(DEFAULT_TIMELINE_WIDTH, (!rest.is_empty()).then_some(rest))
+            }
+            other => format!("Unknown command '{}'. Type 'help' for commands.\n", other),
+        };
+        write!(output, "{}", reply)?;
+    }
+    Ok(())
+}
+
+/// Answer line commands on stdin/stdout
+pub fn run_interactive(browser: &TraceBrowser) -> Result<()> {
+    let stdin = std::io::stdin();
+    run_browser(
+        browser,
+        BufReader::new(stdin.lock()),
+        std::io::stdout().lock(),
+    )
+}
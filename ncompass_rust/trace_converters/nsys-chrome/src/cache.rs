@@ -0,0 +1,469 @@
+//! Binary cache of extracted (pre-link) events
+//!
+//! Extraction from SQLite dominates conversion time, while linking, coloring
+//! and filtering are cheap. Caching the extracted [`FrontendTrace`] lets those
+//! later stages be re-run with different options without touching SQLite.
+//!
+//! Layout (integers are LEB128 varints unless noted):
+//! - magic `NSCACHE\0`, format version (u32 LE)
+//! - string table: count, then length-prefixed UTF-8 strings
+//! - four event lists (kernels, API calls, annotations, pass-through events),
+//!   each a count followed by events
+//!
+//! Every string (names, track IDs, categories, arg keys and string values) is
+//! stored once in the table and referenced by index, so the repetitive track
+//! names and kernel names of large traces cost a few bytes per event.
+
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::error::{ConvertError, Result};
+use crate::frontends::FrontendTrace;
+use crate::models::{BindingPoint, ChromeTraceEvent, ChromeTracePhase, InstantScope, StringOrInt};
+
+/// Leading bytes of every event cache file
+pub const CACHE_MAGIC: &[u8; 8] = b"NSCACHE\0";
+
+/// Format version, bumped on incompatible layout changes
+pub const CACHE_VERSION: u32 = 2;
+
+/// Phases by their encoded tag
+const PHASES: [ChromeTracePhase; 22] = [
+    ChromeTracePhase::DurationBegin,
+    ChromeTracePhase::DurationEnd,
+    ChromeTracePhase::Complete,
+    ChromeTracePhase::Instant,
+    ChromeTracePhase::Counter,
+    ChromeTracePhase::AsyncNestableStart,
+    ChromeTracePhase::AsyncNestableInstant,
+    ChromeTracePhase::AsyncNestableEnd,
+    ChromeTracePhase::FlowStart,
+    ChromeTracePhase::FlowStep,
+    ChromeTracePhase::FlowFinish,
+    ChromeTracePhase::Sample,
+    ChromeTracePhase::ObjectCreated,
+    ChromeTracePhase::ObjectSnapshot,
+    ChromeTracePhase::ObjectDestroyed,
+    ChromeTracePhase::Metadata,
+    ChromeTracePhase::MemoryDumpGlobal,
+    ChromeTracePhase::MemoryDumpProcess,
+    ChromeTracePhase::Mark,
+    ChromeTracePhase::ClockSync,
+    ChromeTracePhase::ContextBegin,
+    ChromeTracePhase::ContextEnd,
+];
+
+// Optional-field flags
+const HAS_DUR: u8 = 1;
+const HAS_CNAME: u8 = 1 << 1;
+const HAS_STRING_ID: u8 = 1 << 2;
+const HAS_INT_ID: u8 = 1 << 3;
+const HAS_BP: u8 = 1 << 4;
+const HAS_SCOPE: u8 = 1 << 5;
+
+// JSON value tags
+const VALUE_NULL: u8 = 0;
+const VALUE_FALSE: u8 = 1;
+const VALUE_TRUE: u8 = 2;
+const VALUE_I64: u8 = 3;
+const VALUE_U64: u8 = 4;
+const VALUE_F64: u8 = 5;
+const VALUE_STRING: u8 = 6;
+const VALUE_ARRAY: u8 = 7;
+const VALUE_OBJECT: u8 = 8;
+
+/// Check whether a file starts with the event cache magic
+pub fn is_event_cache(path: &Path) -> bool {
+    let mut head = [0u8; 8];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut head))
+        .map(|_| &head == CACHE_MAGIC)
+        .unwrap_or(false)
+}
+
+/// Write extracted events to a cache file
+pub fn write_event_cache(path: &str, trace: &FrontendTrace) -> Result<()> {
+    let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+        path: path.into(),
+        source,
+    })?;
+    let mut writer = BufWriter::new(file);
+    writer
+        .write_all(&encode_trace(trace))
+        .and_then(|_| writer.flush())
+        .map_err(ConvertError::Output)
+}
+
+/// Read extracted events from a cache file
+pub fn read_event_cache(path: &str) -> Result<FrontendTrace> {
+    let bytes = std::fs::read(path).map_err(|e| ConvertError::open_input(path, e))?;
+    decode_trace(&bytes).map_err(|e| match e {
+        ConvertError::InvalidInput(message) => {
+            ConvertError::InvalidInput(format!("Invalid event cache {}: {}", path, message))
+        }
+        other => other,
+    })
+}
+
+/// Serialize extracted events to the cache format
+pub fn encode_trace(trace: &FrontendTrace) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    for events in [
+        &trace.kernel_events,
+        &trace.api_events,
+        &trace.annotation_events,
+        &trace.other_events,
+    ] {
+        write_varint(&mut encoder.body, events.len() as u64);
+        for event in events {
+            encoder.event(event);
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(encoder.body.len() + 16);
+    bytes.extend_from_slice(CACHE_MAGIC);
+    bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    write_varint(&mut bytes, encoder.strings.len() as u64);
+    for string in &encoder.strings {
+        write_varint(&mut bytes, string.len() as u64);
+        bytes.extend_from_slice(string.as_bytes());
+    }
+    bytes.extend_from_slice(&encoder.body);
+    bytes
+}
+
+/// Deserialize extracted events from the cache format
+pub fn decode_trace(bytes: &[u8]) -> Result<FrontendTrace> {
+    let mut decoder = Decoder {
+        bytes,
+        pos: 0,
+        strings: Vec::new(),
+    };
+    if decoder.take(CACHE_MAGIC.len())? != CACHE_MAGIC {
+        return Err(invalid("Not an event cache (bad magic)"));
+    }
+    let version = u32::from_le_bytes(decoder.take(4)?.try_into().expect("took 4 bytes"));
+    if version != CACHE_VERSION {
+        return Err(invalid(format!(
+            "Unsupported event cache version {} (expected {})",
+            version, CACHE_VERSION
+        )));
+    }
+
+    let string_count = decoder.varint()?;
+    for _ in 0..string_count {
+        let len = decoder.length()?;
+        let string = std::str::from_utf8(decoder.take(len)?)
+            .map_err(|_| invalid("String table entry is not UTF-8"))?;
+        decoder.strings.push(string.to_string());
+    }
+
+    let mut lists: Vec<Vec<ChromeTraceEvent>> = Vec::with_capacity(4);
+    for _ in 0..4 {
+        let count = decoder.length()?;
+        // Bound the preallocation by the remaining input
+        let mut events = Vec::with_capacity(count.min(decoder.remaining()));
+        for _ in 0..count {
+            events.push(decoder.event()?);
+        }
+        lists.push(events);
+    }
+    if decoder.remaining() != 0 {
+        return Err(invalid("Trailing bytes after event lists"));
+    }
+
+    let other_events = lists.pop().unwrap_or_default();
+    let annotation_events = lists.pop().unwrap_or_default();
+    let api_events = lists.pop().unwrap_or_default();
+    let kernel_events = lists.pop().unwrap_or_default();
+    let mut trace = FrontendTrace {
+        kernel_events,
+        api_events,
+        annotation_events,
+        other_events,
+    };
+    // IDs are not cached; numbering in list order gives the extracted ones back
+    trace.assign_event_ids();
+    Ok(trace)
+}
+
+/// Error for malformed cache contents
+fn invalid(message: impl Into<String>) -> ConvertError {
+    ConvertError::InvalidInput(message.into())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+#[derive(Default)]
+struct Encoder {
+    strings: Vec<String>,
+    string_ids: HashMap<String, u64>,
+    body: Vec<u8>,
+}
+
+impl Encoder {
+    fn string(&mut self, value: &str) {
+        let id = match self.string_ids.get(value) {
+            Some(&id) => id,
+            None => {
+                let id = self.strings.len() as u64;
+                self.strings.push(value.to_string());
+                self.string_ids.insert(value.to_string(), id);
+                id
+            }
+        };
+        write_varint(&mut self.body, id);
+    }
+
+    fn event(&mut self, event: &ChromeTraceEvent) {
+        self.string(&event.name);
+        let phase = PHASES.iter().position(|p| *p == event.ph).unwrap_or(0);
+        self.body.push(phase as u8);
+        self.body.extend_from_slice(&event.ts.to_le_bytes());
+        self.string(&event.pid);
+        self.string(&event.tid);
+        self.string(&event.cat);
+
+        let mut flags = 0;
+        if event.dur.is_some() {
+            flags |= HAS_DUR;
+        }
+        if event.cname.is_some() {
+            flags |= HAS_CNAME;
+        }
+        match event.id {
+            Some(StringOrInt::String(_)) => flags |= HAS_STRING_ID,
+            Some(StringOrInt::Int(_)) => flags |= HAS_INT_ID,
+            None => {}
+        }
+        if event.bp.is_some() {
+            flags |= HAS_BP;
+        }
+        if event.s.is_some() {
+            flags |= HAS_SCOPE;
+        }
+        self.body.push(flags);
+
+        if let Some(dur) = event.dur {
+            self.body.extend_from_slice(&dur.to_le_bytes());
+        }
+        if let Some(cname) = &event.cname {
+            self.string(cname);
+        }
+        match &event.id {
+            Some(StringOrInt::String(id)) => self.string(id),
+            Some(StringOrInt::Int(id)) => write_varint(&mut self.body, zigzag(*id)),
+            None => {}
+        }
+        if let Some(bp) = event.bp {
+            self.body.push(matches!(bp, BindingPoint::Same) as u8);
+        }
+        if let Some(scope) = event.s {
+            self.body.push(match scope {
+                InstantScope::Global => 0,
+                InstantScope::Process => 1,
+                InstantScope::Thread => 2,
+            });
+        }
+
+        write_varint(&mut self.body, event.args.len() as u64);
+        for (key, value) in &event.args {
+            self.string(key);
+            self.value(value);
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.body.push(VALUE_NULL),
+            Value::Bool(false) => self.body.push(VALUE_FALSE),
+            Value::Bool(true) => self.body.push(VALUE_TRUE),
+            Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    self.body.push(VALUE_I64);
+                    write_varint(&mut self.body, zigzag(i));
+                } else if let Some(u) = n.as_u64() {
+                    self.body.push(VALUE_U64);
+                    write_varint(&mut self.body, u);
+                } else {
+                    self.body.push(VALUE_F64);
+                    let f = n.as_f64().unwrap_or(0.0);
+                    self.body.extend_from_slice(&f.to_le_bytes());
+                }
+            }
+            Value::String(s) => {
+                self.body.push(VALUE_STRING);
+                self.string(s);
+            }
+            Value::Array(items) => {
+                self.body.push(VALUE_ARRAY);
+                write_varint(&mut self.body, items.len() as u64);
+                for item in items {
+                    self.value(item);
+                }
+            }
+            Value::Object(map) => {
+                self.body.push(VALUE_OBJECT);
+                write_varint(&mut self.body, map.len() as u64);
+                for (key, item) in map {
+                    self.string(key);
+                    self.value(item);
+                }
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    strings: Vec<String>,
+}
+
+impl<'a> Decoder<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(invalid("Unexpected end of event cache"));
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().expect("took 8 bytes")))
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("Varint too long"))
+    }
+
+    /// A varint used as a length or index, which must fit this target's usize
+    fn length(&mut self) -> Result<usize> {
+        let value = self.varint()?;
+        usize::try_from(value)
+            .map_err(|_| invalid(format!("Length {} exceeds this platform's address space", value)))
+    }
+
+    fn owned_string(&mut self) -> Result<String> {
+        let id = self.length()?;
+        self.strings
+            .get(id)
+            .cloned()
+            .ok_or_else(|| invalid(format!("String index {} out of range", id)))
+    }
+
+    fn event(&mut self) -> Result<ChromeTraceEvent> {
+        let name = self.owned_string()?;
+        let phase = self.byte()? as usize;
+        let ph = *PHASES
+            .get(phase)
+            .ok_or_else(|| invalid(format!("Unknown phase tag {}", phase)))?;
+        let ts = self.f64()?;
+        let pid = self.owned_string()?;
+        let tid = self.owned_string()?;
+        let cat = self.owned_string()?;
+        let mut event = ChromeTraceEvent::new(name, ph, ts, pid, tid, cat);
+
+        let flags = self.byte()?;
+        if flags & HAS_DUR != 0 {
+            event.dur = Some(self.f64()?);
+        }
+        if flags & HAS_CNAME != 0 {
+            event.cname = Some(self.owned_string()?);
+        }
+        if flags & HAS_STRING_ID != 0 {
+            event.id = Some(StringOrInt::String(self.owned_string()?));
+        } else if flags & HAS_INT_ID != 0 {
+            event.id = Some(StringOrInt::Int(unzigzag(self.varint()?)));
+        }
+        if flags & HAS_BP != 0 {
+            event.bp = Some(if self.byte()? == 1 {
+                BindingPoint::Same
+            } else {
+                BindingPoint::Enclosing
+            });
+        }
+        if flags & HAS_SCOPE != 0 {
+            event.s = Some(match self.byte()? {
+                0 => InstantScope::Global,
+                1 => InstantScope::Process,
+                2 => InstantScope::Thread,
+                tag => return Err(invalid(format!("Unknown instant scope tag {}", tag))),
+            });
+        }
+
+        let arg_count = self.length()?;
+        for _ in 0..arg_count {
+            let key = self.owned_string()?;
+            let value = self.value()?;
+            event.args.insert(key.into(), value);
+        }
+        Ok(event)
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        Ok(match self.byte()? {
+            VALUE_NULL => Value::Null,
+            VALUE_FALSE => Value::Bool(false),
+            VALUE_TRUE => Value::Bool(true),
+            VALUE_I64 => Value::from(unzigzag(self.varint()?)),
+            VALUE_U64 => Value::from(self.varint()?),
+            VALUE_F64 => Number::from_f64(self.f64()?)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            VALUE_STRING => Value::String(self.owned_string()?),
+            VALUE_ARRAY => {
+                let len = self.length()?;
+                let mut items = Vec::with_capacity(len.min(self.remaining()));
+                for _ in 0..len {
+                    items.push(self.value()?);
+                }
+                Value::Array(items)
+            }
+            VALUE_OBJECT => {
+                let len = self.length()?;
+                let mut map = Map::new();
+                for _ in 0..len {
+                    let key = self.owned_string()?;
+                    map.insert(key, self.value()?);
+                }
+                Value::Object(map)
+            }
+            tag => return Err(invalid(format!("Unknown value tag {}", tag))),
+        })
+    }
+}
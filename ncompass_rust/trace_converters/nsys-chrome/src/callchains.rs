@@ -0,0 +1,319 @@
+//! CUDA launch backtrace (callchain) extraction and kernel source correlation
+//!
+//! When nsys captures with `--cudabacktrace`, each CUDA runtime API row carries a
+//! `callchainId` that references frames in the `CUDA_CALLCHAINS` table. Kernels are
+//! matched to their launching API call via correlationId, so the frames of that call
+//! identify the source line that launched the kernel.
+//!
+//! The same frames can be attached to the API calls themselves, and summed per
+//! distinct stack into folded-stack lines (`root;...;leaf;cudaMemcpy 1200`) that
+//! flamegraph tools such as `inferno-flamegraph` read directly.
+
+use rusqlite::Connection;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::error::{ConvertError, Result};
+use crate::models::ChromeTraceEvent;
+use crate::schema::table_exists;
+
+/// Table holding the symbolized frames of every captured backtrace
+pub const CALLCHAINS_TABLE: &str = "CUDA_CALLCHAINS";
+
+/// Module name fragments of CUDA runtime/driver libraries.
+/// Frames inside these libraries are skipped so the reported frames point at user code.
+const CUDA_LIBRARY_MODULES: &[&str] = &["libcuda.so", "libcudart", "libcupti"];
+
+/// A single symbolized frame of a launch backtrace
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallchainFrame {
+    /// Function (symbol) name
+    pub function: String,
+    /// Module (shared object / executable) containing the frame
+    pub module: Option<String>,
+    /// Source file, when the export carries line information
+    pub file: Option<String>,
+    /// Source line, when the export carries line information
+    pub line: Option<i64>,
+}
+
+impl CallchainFrame {
+    /// Format the frame as "file:line function", falling back to "function (module)"
+    pub fn label(&self) -> String {
+        match (&self.file, self.line, &self.module) {
+            (Some(file), Some(line), _) => format!("{}:{} {}", file, line, self.function),
+            (Some(file), None, _) => format!("{} {}", file, self.function),
+            (None, _, Some(module)) => format!("{} ({})", self.function, module),
+            (None, _, None) => self.function.clone(),
+        }
+    }
+
+    /// Whether the frame belongs to a CUDA runtime/driver library
+    pub fn is_cuda_library_frame(&self) -> bool {
+        self.module
+            .as_deref()
+            .map(|m| CUDA_LIBRARY_MODULES.iter().any(|lib| m.contains(lib)))
+            .unwrap_or(false)
+    }
+}
+
+/// Resolve a column value that may be either a StringIds reference or inline text
+fn resolve_string(value: rusqlite::types::Value, strings: &HashMap<i32, String>) -> Option<String> {
+    match value {
+        rusqlite::types::Value::Integer(id) => strings.get(&(id as i32)).cloned(),
+        rusqlite::types::Value::Text(text) => Some(text),
+        _ => None,
+    }
+}
+
+/// Extract all backtraces from CUDA_CALLCHAINS
+///
+/// Returns mapping from callchain ID to frames ordered by stack depth
+/// (innermost frame first).
+pub fn extract_callchains(
+    conn: &Connection,
+    strings: &HashMap<i32, String>,
+) -> Result<HashMap<i64, Vec<CallchainFrame>>> {
+    let mut callchains: HashMap<i64, Vec<(i64, CallchainFrame)>> = HashMap::default();
+
+    if !table_exists(conn, CALLCHAINS_TABLE)? {
+        return Ok(HashMap::default());
+    }
+
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {}", CALLCHAINS_TABLE))?;
+    let column_names: Vec<String> = stmt
+        .column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    let idx_id = column_names.iter().position(|n| n == "id");
+    let idx_symbol = column_names.iter().position(|n| n == "symbol");
+    let (Some(idx_id), Some(idx_symbol)) = (idx_id, idx_symbol) else {
+        return Ok(HashMap::default());
+    };
+    let idx_module = column_names.iter().position(|n| n == "module");
+    let idx_depth = column_names.iter().position(|n| n == "stackDepth");
+    let idx_file = column_names.iter().position(|n| n == "fileName");
+    let idx_line = column_names.iter().position(|n| n == "lineNumber");
+
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(idx_id)?;
+        let function = resolve_string(row.get(idx_symbol)?, strings)
+            .unwrap_or_else(|| "[Unknown symbol]".to_string());
+        let module = match idx_module {
+            Some(idx) => resolve_string(row.get(idx)?, strings),
+            None => None,
+        };
+        let file = match idx_file {
+            Some(idx) => resolve_string(row.get(idx)?, strings),
+            None => None,
+        };
+        let line: Option<i64> = match idx_line {
+            Some(idx) => row.get(idx)?,
+            None => None,
+        };
+        let depth: i64 = match idx_depth {
+            Some(idx) => row.get::<_, Option<i64>>(idx)?.unwrap_or(0),
+            None => 0,
+        };
+
+        callchains.entry(id).or_default().push((
+            depth,
+            CallchainFrame {
+                function,
+                module,
+                file,
+                line,
+            },
+        ));
+    }
+
+    Ok(callchains
+        .into_iter()
+        .map(|(id, mut frames)| {
+            frames.sort_by_key(|(depth, _)| *depth);
+            (id, frames.into_iter().map(|(_, frame)| frame).collect())
+        })
+        .collect())
+}
+
+/// Extract mapping from correlation ID to callchain ID from the CUDA runtime table
+pub fn extract_launch_callchain_ids(conn: &Connection) -> Result<HashMap<i64, i64>> {
+    let mut corr_to_callchain = HashMap::default();
+
+    if !table_exists(conn, "CUPTI_ACTIVITY_KIND_RUNTIME")? {
+        return Ok(corr_to_callchain);
+    }
+
+    let stmt = conn.prepare("SELECT * FROM CUPTI_ACTIVITY_KIND_RUNTIME LIMIT 1")?;
+    if !stmt.column_names().contains(&"callchainId") {
+        return Ok(corr_to_callchain);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT correlationId, callchainId FROM CUPTI_ACTIVITY_KIND_RUNTIME \
+         WHERE callchainId IS NOT NULL",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let correlation_id: i64 = row.get(0)?;
+        let callchain_id: i64 = row.get(1)?;
+        corr_to_callchain.insert(correlation_id, callchain_id);
+    }
+
+    Ok(corr_to_callchain)
+}
+
+/// Select up to `max_frames` frames describing the launch site.
+///
+/// Frames inside CUDA runtime/driver libraries are skipped; if every frame is a
+/// library frame the innermost frames are used instead.
+pub fn select_launch_frames(frames: &[CallchainFrame], max_frames: usize) -> Vec<&CallchainFrame> {
+    let user_frames: Vec<&CallchainFrame> = frames
+        .iter()
+        .filter(|f| !f.is_cuda_library_frame())
+        .take(max_frames)
+        .collect();
+
+    if user_frames.is_empty() {
+        frames.iter().take(max_frames).collect()
+    } else {
+        user_frames
+    }
+}
+
+/// Attach launch source frames to kernel events
+///
+/// Adds `source_location` (top user frame) and `source_frames` (up to `max_frames`
+/// frame labels) args to every kernel whose launching API call has a backtrace.
+/// Returns the number of kernels annotated.
+pub fn attach_kernel_source_frames(
+    conn: &Connection,
+    strings: &HashMap<i32, String>,
+    kernel_events: &mut [ChromeTraceEvent],
+    max_frames: usize,
+) -> Result<usize> {
+    if max_frames == 0 || kernel_events.is_empty() {
+        return Ok(0);
+    }
+
+    let corr_to_callchain = extract_launch_callchain_ids(conn)?;
+    if corr_to_callchain.is_empty() {
+        return Ok(0);
+    }
+    let callchains = extract_callchains(conn, strings)?;
+
+    let mut annotated = 0;
+    for event in kernel_events.iter_mut() {
+        let Some(corr_id) = event.args.get("correlationId").and_then(|v| v.as_i64()) else {
+            continue;
+        };
+        let Some(frames) = corr_to_callchain
+            .get(&corr_id)
+            .and_then(|callchain_id| callchains.get(callchain_id))
+        else {
+            continue;
+        };
+
+        let selected = select_launch_frames(frames, max_frames);
+        if let Some(top) = selected.first() {
+            event
+                .args
+                .insert("source_location".into(), json!(top.label()));
+            let labels: Vec<String> = selected.iter().map(|f| f.label()).collect();
+            event
+                .args
+                .insert("source_frames".into(), json!(labels));
+            annotated += 1;
+        }
+    }
+
+    Ok(annotated)
+}
+
+/// Attach the full call stack of each CUDA API call to its event
+///
+/// Adds a `call_stack` arg holding every frame label, innermost first, to API
+/// events whose call has a backtrace. Unlike the kernel source frames, CUDA
+/// library frames are kept, since they show which runtime path was taken.
+/// Returns the number of API events annotated.
+pub fn attach_api_call_stacks(
+    conn: &Connection,
+    strings: &HashMap<i32, String>,
+    api_events: &mut [ChromeTraceEvent],
+) -> Result<usize> {
+    if api_events.is_empty() {
+        return Ok(0);
+    }
+
+    let corr_to_callchain = extract_launch_callchain_ids(conn)?;
+    if corr_to_callchain.is_empty() {
+        return Ok(0);
+    }
+    let callchains = extract_callchains(conn, strings)?;
+
+    let mut annotated = 0;
+    for event in api_events.iter_mut() {
+        let Some(corr_id) = event.args.get("correlationId").and_then(|v| v.as_i64()) else {
+            continue;
+        };
+        let Some(frames) = corr_to_callchain
+            .get(&corr_id)
+            .and_then(|callchain_id| callchains.get(callchain_id))
+        else {
+            continue;
+        };
+
+        let labels: Vec<String> = frames.iter().map(|f| f.label()).collect();
+        event.args.insert("call_stack".into(), json!(labels));
+        annotated += 1;
+    }
+
+    Ok(annotated)
+}
+
+/// Sum API call durations per distinct call stack, in folded-stack format
+///
+/// Each key is the stack from the outermost frame to the API call itself,
+/// separated by `;`; each value is the total time in nanoseconds spent in
+/// calls with that stack. Events without a `call_stack` arg are skipped.
+pub fn folded_api_stacks(events: &[ChromeTraceEvent]) -> BTreeMap<String, u64> {
+    let mut folded: BTreeMap<String, u64> = BTreeMap::new();
+
+    for event in events {
+        let Some(stack) = event.args.get("call_stack").and_then(|v| v.as_array()) else {
+            continue;
+        };
+        // `;` separates frames, so it must not appear inside one
+        let mut frames: Vec<String> = stack
+            .iter()
+            .rev()
+            .filter_map(|frame| frame.as_str())
+            .map(|frame| frame.replace(';', ":"))
+            .collect();
+        frames.push(event.name.replace(';', ":"));
+
+        let duration_ns = (event.dur.unwrap_or(0.0) * 1000.0).round().max(0.0) as u64;
+        *folded.entry(frames.join(";")).or_default() += duration_ns;
+    }
+
+    folded
+}
+
+/// Write folded API call stacks, one `stack weight` line per distinct stack
+pub fn write_folded_stacks(path: &str, events: &[ChromeTraceEvent]) -> Result<()> {
+    let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+        path: path.into(),
+        source,
+    })?;
+    let mut writer = BufWriter::new(file);
+    for (stack, weight) in folded_api_stacks(events) {
+        writeln!(writer, "{} {}", stack, weight).map_err(ConvertError::Output)?;
+    }
+    writer.flush().map_err(ConvertError::Output)?;
+    Ok(())
+}
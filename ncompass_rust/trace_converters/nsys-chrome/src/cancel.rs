@@ -0,0 +1,55 @@
+//! Cooperative cancellation of long conversions
+//!
+//! A [`CancellationToken`] is shared between whoever may abort a conversion
+//! (the CLI's Ctrl-C handler, the service's `DELETE /jobs/{id}`) and the
+//! conversion itself. The converter checks it between pipeline stages, and
+//! SQLite checks it every few thousand virtual-machine steps while reading a
+//! table, so even a single large query stops promptly. Either way the
+//! conversion fails with [`ConvertError::Cancelled`].
+
+use rusqlite::Connection;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::{ConvertError, Result};
+
+/// SQLite virtual-machine steps between two cancellation checks
+const QUERY_CHECK_INTERVAL: i32 = 10_000;
+
+/// Flag shared by a conversion and whoever may cancel it
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every holder of the token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Fail with [`ConvertError::Cancelled`] once cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(ConvertError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Interrupt queries running on `conn` once cancelled
+    ///
+    /// An interrupted query fails with [`ConvertError::Cancelled`].
+    pub(crate) fn interrupt_queries(&self, conn: &Connection) {
+        let token = self.clone();
+        conn.progress_handler(QUERY_CHECK_INTERVAL, Some(move || token.is_cancelled()));
+    }
+}
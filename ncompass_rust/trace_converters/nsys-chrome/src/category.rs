@@ -0,0 +1,228 @@
+//! Event categories
+//!
+//! Categories select what is parsed (`ConversionOptions::activity_types`),
+//! what linkers and filters look at and where routed events go. They used to
+//! be plain strings, so a typo such as `kernal` silently selected nothing.
+//! [`EventCategory`] names the built-in ones and keeps anything else, e.g.
+//! the activity type of a custom extractor, in [`EventCategory::Other`].
+//!
+//! Events keep their `cat` as an interned string, which compares equal to an
+//! `EventCategory`, and categories (de)serialize as the same strings as
+//! before. The CUDA API category is written `cuda_api` on events and
+//! `cuda-api` as an activity type; both spellings parse to
+//! [`EventCategory::CudaApi`].
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{ConvertError, Result};
+use crate::intern::InternedStr;
+
+/// Largest edit distance at which an unknown category is taken as a typo
+const MAX_TYPO_DISTANCE: usize = 2;
+
+/// Category of an event, or activity type selecting events
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EventCategory {
+    Kernel,
+    CudaApi,
+    Nvtx,
+    /// NVTX ranges projected onto the GPU through the kernels they launched
+    NvtxKernel,
+    Osrt,
+    Sched,
+    Memcpy,
+    Mpi,
+    ThreadState,
+    GpuMetrics,
+    Wddm,
+    /// Vulkan and DX12 submissions, GPU workloads and debug labels
+    Graphics,
+    Nvlink,
+    Composite,
+    /// Any other category, e.g. of a custom extractor or a derived event
+    Other(String),
+}
+
+impl EventCategory {
+    /// Every built-in category, in the order activity types are listed
+    pub const BUILT_IN: &'static [EventCategory] = &[
+        EventCategory::Kernel,
+        EventCategory::CudaApi,
+        EventCategory::Nvtx,
+        EventCategory::NvtxKernel,
+        EventCategory::Osrt,
+        EventCategory::Sched,
+        EventCategory::Memcpy,
+        EventCategory::Mpi,
+        EventCategory::ThreadState,
+        EventCategory::GpuMetrics,
+        EventCategory::Wddm,
+        EventCategory::Graphics,
+        EventCategory::Nvlink,
+        EventCategory::Composite,
+    ];
+
+    /// The `cat` string of events in this category
+    pub fn as_str(&self) -> &str {
+        match self {
+            EventCategory::Kernel => "kernel",
+            EventCategory::CudaApi => "cuda_api",
+            EventCategory::Nvtx => "nvtx",
+            EventCategory::NvtxKernel => "nvtx-kernel",
+            EventCategory::Osrt => "osrt",
+            EventCategory::Sched => "sched",
+            EventCategory::Memcpy => "memcpy",
+            EventCategory::Mpi => "mpi",
+            EventCategory::ThreadState => "thread-state",
+            EventCategory::GpuMetrics => "gpu-metrics",
+            EventCategory::Wddm => "wddm",
+            EventCategory::Graphics => "graphics",
+            EventCategory::Nvlink => "nvlink",
+            EventCategory::Composite => "composite",
+            EventCategory::Other(other) => other,
+        }
+    }
+
+    /// The name selecting this category in `--types` and `activity_types`
+    pub fn activity_type(&self) -> &str {
+        match self {
+            EventCategory::CudaApi => "cuda-api",
+            other => other.as_str(),
+        }
+    }
+
+    /// Whether this is a built-in category
+    pub fn is_built_in(&self) -> bool {
+        !matches!(self, EventCategory::Other(_))
+    }
+
+    /// Parse a category, refusing names one or two edits away from a
+    /// built-in one as typos; any other name becomes [`EventCategory::Other`]
+    pub fn parse(value: &str) -> Result<Self> {
+        let category = Self::from(value.trim());
+        if let EventCategory::Other(name) = &category {
+            if let Some(known) = Self::closest_built_in(name) {
+                return Err(ConvertError::InvalidOption(format!(
+                    "Unknown category '{}' (did you mean '{}'?)",
+                    name,
+                    known.activity_type()
+                )));
+            }
+        }
+        Ok(category)
+    }
+
+    /// Built-in category whose name is nearest to `name`, if close enough
+    /// to be a typo of it
+    pub fn closest_built_in(name: &str) -> Option<&'static EventCategory> {
+        let name = name.to_ascii_lowercase();
+        Self::BUILT_IN
+            .iter()
+            .map(|known| {
+                let distance = edit_distance(&name, known.as_str())
+                    .min(edit_distance(&name, known.activity_type()));
+                (distance, known)
+            })
+            .filter(|&(distance, known)| {
+                distance <= MAX_TYPO_DISTANCE && distance < known.as_str().len() / 2 + 1
+            })
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, known)| known)
+    }
+}
+
+/// Levenshtein distance between two short ASCII names
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.bytes().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+impl From<&str> for EventCategory {
+    fn from(value: &str) -> Self {
+        match value {
+            "kernel" => EventCategory::Kernel,
+            "cuda_api" | "cuda-api" => EventCategory::CudaApi,
+            "nvtx" => EventCategory::Nvtx,
+            "nvtx-kernel" => EventCategory::NvtxKernel,
+            "osrt" => EventCategory::Osrt,
+            "sched" => EventCategory::Sched,
+            "memcpy" => EventCategory::Memcpy,
+            "mpi" => EventCategory::Mpi,
+            "thread-state" => EventCategory::ThreadState,
+            "gpu-metrics" => EventCategory::GpuMetrics,
+            "wddm" => EventCategory::Wddm,
+            "graphics" => EventCategory::Graphics,
+            "nvlink" => EventCategory::Nvlink,
+            "composite" => EventCategory::Composite,
+            other => EventCategory::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for EventCategory {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl FromStr for EventCategory {
+    type Err = ConvertError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::parse(value)
+    }
+}
+
+impl fmt::Display for EventCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for EventCategory {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for EventCategory {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value: std::borrow::Cow<str> = Deserialize::deserialize(deserializer)?;
+        Ok(Self::from(&*value))
+    }
+}
+
+impl From<EventCategory> for InternedStr {
+    fn from(category: EventCategory) -> Self {
+        InternedStr::new(category.as_str())
+    }
+}
+
+impl PartialEq<EventCategory> for InternedStr {
+    fn eq(&self, category: &EventCategory) -> bool {
+        self.as_str() == category.as_str()
+    }
+}
+
+impl PartialEq<str> for EventCategory {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other || self.activity_type() == other
+    }
+}
+
+impl PartialEq<&str> for EventCategory {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
@@ -0,0 +1,197 @@
+//! Output compatibility with older Chrome trace viewers
+//!
+//! Traces are written for Perfetto by default, with every field it reads.
+//! Older viewers choke on some of them, so a [`CompatTarget`] narrows the
+//! output to what the viewer supports:
+//! - `perfetto`: full fidelity, nothing changes.
+//! - `chrome` (chrome://tracing): flows bound to slices through `bind_id`
+//!   with `flow_out`/`flow_in` are written as `s`/`f` events at the slices
+//!   instead, and colors outside catapult's reserved `cname` set are dropped.
+//! - `legacy-catapult`: as `chrome`, and also drops every `cname`, the flow
+//!   binding point `bp` (a finish then binds to the next slice, which is the
+//!   slice it was placed at) and nestable async events (`b`/`n`/`e`).
+//!
+//! Whichever target is chosen is recorded with [`TRACE_SCHEMA_VERSION`] in a
+//! `trace_schema` metadata event, so tools reading the trace know which
+//! subset they got.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::error::{ConvertError, Result};
+use crate::models::{BindingPoint, ChromeTraceEvent, ChromeTracePhase};
+
+/// Version of the event layout this converter writes
+pub const TRACE_SCHEMA_VERSION: u32 = 1;
+
+/// Metadata event recording the schema version and compatibility target
+pub const TRACE_SCHEMA_EVENT: &str = "trace_schema";
+
+/// Color names catapult's trace viewer reserves for `cname`
+pub const CATAPULT_COLORS: &[&str] = &[
+    "thread_state_uninterruptible",
+    "thread_state_iowait",
+    "thread_state_running",
+    "thread_state_runnable",
+    "thread_state_sleeping",
+    "thread_state_unknown",
+    "background_memory_dump",
+    "light_memory_dump",
+    "detailed_memory_dump",
+    "vsync_highlight_color",
+    "generic_work",
+    "good",
+    "bad",
+    "terrible",
+    "black",
+    "grey",
+    "white",
+    "yellow",
+    "olive",
+    "rail_response",
+    "rail_animation",
+    "rail_idle",
+    "rail_load",
+    "startup",
+    "heap_dump_stack_frame",
+    "heap_dump_object_type",
+    "heap_dump_child_node_highlight",
+    "cq_build_running",
+    "cq_build_passed",
+    "cq_build_failed",
+    "cq_build_abandoned",
+    "cq_build_attempt_runnig",
+    "cq_build_attempt_passed",
+    "cq_build_attempt_failed",
+];
+
+/// Viewer the output is written for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompatTarget {
+    /// Perfetto UI; full fidelity
+    #[default]
+    Perfetto,
+    /// chrome://tracing
+    Chrome,
+    /// Catapult trace viewers predating flow binding points and nestable async events
+    LegacyCatapult,
+}
+
+impl CompatTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CompatTarget::Perfetto => "perfetto",
+            CompatTarget::Chrome => "chrome",
+            CompatTarget::LegacyCatapult => "legacy-catapult",
+        }
+    }
+}
+
+/// Parse `perfetto`, `chrome` or `legacy-catapult` into a compatibility target
+pub fn parse_compat_target(value: &str) -> Result<CompatTarget> {
+    match value.trim() {
+        "perfetto" => Ok(CompatTarget::Perfetto),
+        "chrome" => Ok(CompatTarget::Chrome),
+        "legacy-catapult" => Ok(CompatTarget::LegacyCatapult),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid compatibility target '{}' (use perfetto, chrome or legacy-catapult)",
+            value
+        ))),
+    }
+}
+
+/// What [`apply_compat`] changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompatStats {
+    /// Slices whose `bind_id` flow became an `s` or `f` event
+    pub flows_rewritten: usize,
+    pub colors_dropped: usize,
+    /// Events of phases the target does not support
+    pub events_dropped: usize,
+}
+
+/// Whether the target reads nestable async events
+fn supports_phase(target: CompatTarget, ph: ChromeTracePhase) -> bool {
+    target != CompatTarget::LegacyCatapult
+        || !matches!(
+            ph,
+            ChromeTracePhase::AsyncNestableStart
+                | ChromeTracePhase::AsyncNestableInstant
+                | ChromeTracePhase::AsyncNestableEnd
+        )
+}
+
+/// Flow event replacing the `bind_id` of a slice, if it has one
+fn unbind_flow(event: &mut ChromeTraceEvent) -> Option<ChromeTraceEvent> {
+    let id = event.bind_id.take()?;
+    let flow_in = event.flow_in.take().unwrap_or(false);
+    let flow_out = event.flow_out.take().unwrap_or(false);
+    let (ts, pid, tid) = (event.ts, event.pid.clone(), event.tid.clone());
+    if flow_out {
+        Some(ChromeTraceEvent::flow_start(ts, pid, tid, id))
+    } else if flow_in {
+        Some(ChromeTraceEvent::flow_finish(
+            ts,
+            pid,
+            tid,
+            id,
+            BindingPoint::Enclosing,
+        ))
+    } else {
+        None
+    }
+}
+
+/// Narrow events to what `target` supports and record the schema version
+///
+/// Events must be sorted by timestamp; flow events created from slices
+/// follow their slice, so the order is kept.
+pub fn apply_compat(
+    events: Vec<ChromeTraceEvent>,
+    target: CompatTarget,
+) -> (Vec<ChromeTraceEvent>, CompatStats) {
+    let mut stats = CompatStats::default();
+    let mut out = Vec::with_capacity(events.len() + 1);
+    if let Some(pid) = events.iter().map(|e| &e.pid).min() {
+        let mut args = HashMap::new();
+        args.insert("version".to_string(), json!(TRACE_SCHEMA_VERSION));
+        args.insert("compat".to_string(), json!(target.as_str()));
+        out.push(ChromeTraceEvent::metadata(
+            TRACE_SCHEMA_EVENT.to_string(),
+            pid.to_string(),
+            String::new(),
+            args,
+        ));
+    }
+    if target == CompatTarget::Perfetto {
+        out.extend(events);
+        return (out, stats);
+    }
+
+    for mut event in events {
+        if !supports_phase(target, event.ph) {
+            stats.events_dropped += 1;
+            continue;
+        }
+        let keep_color = |cname: &str| {
+            target != CompatTarget::LegacyCatapult && CATAPULT_COLORS.contains(&cname)
+        };
+        if event.cname.as_deref().is_some_and(|c| !keep_color(c)) {
+            event.cname = None;
+            stats.colors_dropped += 1;
+        }
+        let mut flow = unbind_flow(&mut event);
+        if flow.is_some() {
+            stats.flows_rewritten += 1;
+        }
+        if target == CompatTarget::LegacyCatapult {
+            event.bp = None;
+            if let Some(flow) = flow.as_mut() {
+                flow.bp = None;
+            }
+        }
+        out.push(event);
+        out.extend(flow);
+    }
+    (out, stats)
+}
@@ -1,24 +1,56 @@
 //! Main converter class for nsys SQLite to Chrome Trace conversion
 
-use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::serialize::OwnedData;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
 use serde_json::json;
-use std::collections::{HashMap, HashSet};
-
-use crate::linker::link_nvtx_to_kernels;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::analysis::gaps::MIN_GAP_NS;
+use crate::analysis::{
+    apply_time_origin, apply_time_window, attribute_wddm_queue_time, color_api_thread_states,
+    concurrency_counter_events, concurrency_summary_events, device_activity,
+    device_activity_events, filter_short_kernels, find_kernel_gaps, flag_kernel_outliers,
+    gap_events, infer_layer_ranges, kernel_concurrency, repair_truncated, resolve_missing_streams,
+    synthesize_step_markers, throttled_regions, throughput_counter_events, transfer_throughput,
+    transfer_totals_events,
+};
+use crate::annotations::{annotate_nesting_depth, annotation_metadata, group_by_process};
+use crate::callchains::{attach_api_call_stacks, attach_kernel_source_frames};
+use crate::cancel::CancellationToken;
+use crate::category::EventCategory;
+use crate::cost_model::{DefaultCostModel, KernelCostModel};
+use crate::devices::{device_properties_events, extract_device_properties};
+use crate::diagnostics::ConversionDiagnostics;
+use crate::dropped::{extract_dropped_events, DroppedEventStats};
+use crate::effective_config::config_metadata_event;
+use crate::error::{ConvertError, Result};
+use crate::frontends::{filter_annotations, filter_marks, FrontendTrace};
+use crate::graph_nodes::name_graph_kernels;
+use crate::linker::{
+    align_annotations, apply_flow_options, link_copies_to_api_calls, link_mpi_to_nccl_kernels,
+    link_nvtx_to_kernels_windowed, link_nvtx_to_kernels_with_stats, tag_unattributed,
+    unattributed_gpu_work, unattributed_work_events, LinkStats, NvtxCoverage, NvtxIdentifier,
+};
 use crate::mapping::{extract_device_mapping, extract_thread_names, get_all_devices};
-use crate::models::{ChromeTraceEvent, ConversionOptions};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use crate::parsers::nvtx::collapse_nested_ranges;
 use crate::parsers::{
-    CUPTIKernelParser, CUPTIRuntimeParser, EventParser, NVTXParser, OSRTParser, ParseContext,
-    SchedParser,
+    validate_extractor, ActivityExtractor, ActivityLinkRole, CUPTIKernelParser, CUPTIRuntimeParser,
+    CustomParser, EventParser, GpuMetricsParser, GraphicsParser, MPIParser, MemcpyParser,
+    NVTXParser, NvtxMarkParser, OSRTParser, P2PParser, ParseContext, SchedParser,
+    ThreadStateParser, WDDMParser,
 };
-use crate::schema::detect_event_types;
+use crate::schema::SchemaProbe;
+use crate::self_profile::{current_context, phase};
+use crate::tid_allocator::virtualize_tids;
 
 /// Filter out NVTX events that have been mapped to kernels, keeping only unmapped ones.
 /// Consumes the input nvtx_events vector and returns only the unmapped events.
 fn filter_unmapped_nvtx_events(
     nvtx_events: Vec<ChromeTraceEvent>,
-    mapped_nvtx_identifiers: &HashSet<(i32, i32, i64, String)>,
+    mapped_nvtx_identifiers: &HashSet<NvtxIdentifier>,
 ) -> Vec<ChromeTraceEvent> {
     if mapped_nvtx_identifiers.is_empty() {
         return nvtx_events;
@@ -42,53 +74,290 @@ fn filter_unmapped_nvtx_events(
         .collect()
 }
 
+/// Result of NVTX-kernel linking: (events_to_add, remaining_nvtx_events,
+/// link_stats, coverage), with no coverage when linking was skipped
+pub(crate) type NvtxLinking = (
+    Vec<ChromeTraceEvent>,
+    Vec<ChromeTraceEvent>,
+    LinkStats,
+    Option<NvtxCoverage>,
+);
+
 /// Process NVTX-kernel linking if all required events are available.
-/// Returns (events_to_add, remaining_nvtx_events).
-fn process_nvtx_kernel_linking(
+///
+/// Fails only if windowed linking was requested and cannot run.
+pub(crate) fn process_nvtx_kernel_linking(
     kernel_events: &[ChromeTraceEvent],
     cuda_api_events: &[ChromeTraceEvent],
-    nvtx_events: Vec<ChromeTraceEvent>,
+    mut nvtx_events: Vec<ChromeTraceEvent>,
     options: &ConversionOptions,
-) -> (Vec<ChromeTraceEvent>, Vec<ChromeTraceEvent>) {
+) -> Result<NvtxLinking> {
     if kernel_events.is_empty() || cuda_api_events.is_empty() || nvtx_events.is_empty() {
-        eprintln!(
-            "Warning: nvtx-kernel requested but requires kernel, cuda-api, and nvtx events. Skipping."
+        log::warn!("nvtx-kernel requested but requires kernel, cuda-api, and nvtx events. Skipping.");
+        return Ok((Vec::new(), nvtx_events, LinkStats::default(), None));
+    }
+
+    // Align injected annotations with the work they cover before detecting overlaps
+    if !options.annotation_time_shifts.is_empty() {
+        let applied = align_annotations(
+            &mut nvtx_events,
+            cuda_api_events,
+            kernel_events,
+            &options.annotation_time_shifts,
         );
-        return (Vec::new(), nvtx_events);
+        for (key, shift_ns) in applied {
+            log::debug!("Shifted '{}' annotations by {} ns", key, shift_ns);
+        }
     }
 
-    let (nvtx_kernel_events, mapped_nvtx_identifiers, flow_events) =
-        link_nvtx_to_kernels(&nvtx_events, cuda_api_events, kernel_events, options);
+    let ((nvtx_kernel_events, mapped_nvtx_identifiers, flow_events), stats) =
+        match options.link_window_ns {
+            Some(window_ns) => link_nvtx_to_kernels_windowed(
+                &nvtx_events,
+                cuda_api_events,
+                kernel_events,
+                options,
+                window_ns,
+            )?,
+            None => link_nvtx_to_kernels_with_stats(
+                &nvtx_events,
+                cuda_api_events,
+                kernel_events,
+                options,
+            ),
+        };
 
     let mut events_to_add = Vec::with_capacity(nvtx_kernel_events.len() + flow_events.len());
+    if options.unattributed_work {
+        let work = unattributed_gpu_work(kernel_events, &nvtx_kernel_events);
+        let mut per_device: BTreeMap<i64, i64> = BTreeMap::new();
+        for stretch in &work {
+            *per_device.entry(stretch.device_id).or_default() += stretch.duration_ns();
+        }
+        for (device_id, unattributed_ns) in per_device {
+            log::info!(
+                "Device {}: {:.3} ms of kernel time outside NVTX ranges",
+                device_id,
+                unattributed_ns as f64 / 1e6
+            );
+        }
+        events_to_add.extend(unattributed_work_events(&work));
+    }
     events_to_add.extend(nvtx_kernel_events);
     events_to_add.extend(flow_events);
 
     // Filter out mapped NVTX events, keep unmapped ones
-    let remaining_nvtx = filter_unmapped_nvtx_events(nvtx_events, &mapped_nvtx_identifiers);
+    let ranges = nvtx_events.len();
+    let mut remaining_nvtx = filter_unmapped_nvtx_events(nvtx_events, &mapped_nvtx_identifiers);
+    let coverage = NvtxCoverage::measure(
+        ranges,
+        remaining_nvtx.len(),
+        kernel_events,
+        stats.attributed_kernel_ns,
+    );
+    if options.tag_unattributed {
+        tag_unattributed(&mut remaining_nvtx);
+    }
+
+    Ok((events_to_add, remaining_nvtx, stats, Some(coverage)))
+}
+
+/// Where an export was opened from, so worker threads can open it again
+#[derive(Debug, Clone)]
+enum SqliteSource {
+    Path(PathBuf),
+    Uri(String),
+    /// Deserialized bytes, only reachable through the main connection
+    Memory,
+}
+
+impl SqliteSource {
+    /// Whether other connections can be opened to the same database
+    fn can_reopen(&self) -> bool {
+        !matches!(self, SqliteSource::Memory)
+    }
 
-    (events_to_add, remaining_nvtx)
+    /// Open another read-only connection to the database
+    fn reopen(&self) -> Result<Connection> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let conn = match self {
+            SqliteSource::Path(path) => Connection::open_with_flags(path, flags)?,
+            SqliteSource::Uri(uri) => {
+                Connection::open_with_flags(uri, flags | OpenFlags::SQLITE_OPEN_URI)?
+            }
+            SqliteSource::Memory => {
+                return Err(ConvertError::InvalidInput(
+                    "In-memory databases cannot be reopened".to_string(),
+                ))
+            }
+        };
+        Ok(conn)
+    }
+}
+
+/// SQLite URI opening `path` read-only and immutable
+///
+/// `immutable=1` tells SQLite the file cannot change, so it takes no locks
+/// and never looks for a journal; this is what read-only network mounts need.
+pub fn read_only_uri(path: &Path) -> String {
+    let mut uri = file_uri(path);
+    uri.push_str("?mode=ro&immutable=1");
+    uri
+}
+
+/// SQLite URI of `path`, without parameters
+pub(crate) fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file:");
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '%' => uri.push_str("%25"),
+            '?' => uri.push_str("%3f"),
+            '#' => uri.push_str("%23"),
+            c => uri.push(c),
+        }
+    }
+    uri
 }
 
 /// Main converter class for nsys SQLite to Chrome Trace conversion
 pub struct NsysChromeConverter {
     conn: Connection,
+    source: SqliteSource,
     options: ConversionOptions,
+    cost_model: Option<Arc<dyn KernelCostModel>>,
+    extractors: Vec<Arc<dyn ActivityExtractor>>,
+    cancellation: Option<CancellationToken>,
 }
 
 impl NsysChromeConverter {
     /// Create a new converter
+    ///
+    /// Fails with [`ConvertError::InputNotFound`] if `sqlite_path` does not
+    /// exist, rather than creating an empty database there.
     pub fn new(sqlite_path: &str, options: Option<ConversionOptions>) -> Result<Self> {
-        let conn = Connection::open(sqlite_path)
-            .with_context(|| format!("Failed to open SQLite database: {}", sqlite_path))?;
+        if !Path::new(sqlite_path).exists() {
+            return Err(ConvertError::InputNotFound(sqlite_path.into()));
+        }
+        let conn = Connection::open(sqlite_path)?;
 
         let options = options.unwrap_or_default();
 
-        Ok(Self { conn, options })
+        Ok(Self {
+            conn,
+            source: SqliteSource::Path(PathBuf::from(sqlite_path)),
+            options,
+            cost_model: None,
+            extractors: Vec::new(),
+            cancellation: None,
+        })
+    }
+
+    /// Create a converter reading the export through an SQLite URI
+    ///
+    /// The database is always opened read-only; URI parameters are honored,
+    /// e.g. `file:/mnt/traces/run.sqlite?immutable=1`.
+    pub fn from_uri(uri: &str, options: Option<ConversionOptions>) -> Result<Self> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_URI
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let conn = Connection::open_with_flags(uri, flags)?;
+
+        Ok(Self {
+            conn,
+            source: SqliteSource::Uri(uri.to_string()),
+            options: options.unwrap_or_default(),
+            cost_model: None,
+            extractors: Vec::new(),
+            cancellation: None,
+        })
+    }
+
+    /// Create a converter for an export on a read-only file system
+    ///
+    /// The file is opened immutable (see [`read_only_uri`]), so nothing is
+    /// written next to it. Fails with [`ConvertError::InputNotFound`] if
+    /// `sqlite_path` does not exist.
+    pub fn open_read_only(sqlite_path: &str, options: Option<ConversionOptions>) -> Result<Self> {
+        if !Path::new(sqlite_path).exists() {
+            return Err(ConvertError::InputNotFound(sqlite_path.into()));
+        }
+        Self::from_uri(&read_only_uri(Path::new(sqlite_path)), options)
+    }
+
+    /// Create a converter for an export held in memory, e.g. received over the network
+    ///
+    /// The bytes are copied into an in-memory database; nothing touches disk.
+    /// Bytes that are not an SQLite database fail at conversion with
+    /// [`ConvertError::InvalidInput`]. Tables are read on one connection
+    /// whatever `jobs` is set to; linking still runs per device.
+    pub fn from_bytes(bytes: &[u8], options: Option<ConversionOptions>) -> Result<Self> {
+        let mut conn = Connection::open_in_memory()?;
+        if !bytes.is_empty() {
+            // SAFETY: SQLite takes ownership of the buffer, so it must come
+            // from sqlite3_malloc64; it is fully initialized before handing over.
+            let data = unsafe {
+                let ptr = rusqlite::ffi::sqlite3_malloc64(bytes.len() as u64) as *mut u8;
+                let ptr = std::ptr::NonNull::new(ptr).ok_or_else(|| {
+                    ConvertError::InvalidInput(format!(
+                        "Cannot allocate {} bytes for the in-memory database",
+                        bytes.len()
+                    ))
+                })?;
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), bytes.len());
+                OwnedData::from_raw_nonnull(ptr, bytes.len())
+            };
+            conn.deserialize(DatabaseName::Main, data, true)?;
+        }
+
+        Ok(Self {
+            conn,
+            source: SqliteSource::Memory,
+            options: options.unwrap_or_default(),
+            cost_model: None,
+            extractors: Vec::new(),
+            cancellation: None,
+        })
+    }
+
+    /// Estimate kernel FLOPs/bytes with a custom cost model
+    ///
+    /// Takes precedence over `ConversionOptions::estimate_kernel_costs`.
+    pub fn with_cost_model(mut self, cost_model: Arc<dyn KernelCostModel>) -> Self {
+        self.cost_model = Some(cost_model);
+        self
+    }
+
+    /// Read a custom activity type with an extractor
+    ///
+    /// Its events are extracted when the activity type is listed in
+    /// `ConversionOptions::activity_types` and its table exists. Extractors
+    /// named after a built-in activity type fail the conversion with
+    /// [`ConvertError::InvalidOption`].
+    pub fn with_activity_extractor(mut self, extractor: Arc<dyn ActivityExtractor>) -> Self {
+        self.extractors.push(extractor);
+        self
+    }
+
+    /// Stop the conversion once `token` is cancelled
+    ///
+    /// The token is checked between stages and while tables are read; the
+    /// conversion then fails with [`ConvertError::Cancelled`].
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        token.interrupt_queries(&self.conn);
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Fail with [`ConvertError::Cancelled`] if the conversion was cancelled
+    fn checkpoint(&self) -> Result<()> {
+        self.cancellation
+            .as_ref()
+            .map_or(Ok(()), |token| token.check())
     }
 
     /// Load StringIds table into HashMap
     fn load_strings(&self) -> Result<HashMap<i32, String>> {
+        let _phase = phase("read StringIds", "read");
         let mut strings = HashMap::default();
 
         // Check if StringIds table exists
@@ -113,92 +382,277 @@ impl NsysChromeConverter {
         Ok(strings)
     }
 
-    /// Detect available event types based on tables
-    fn detect_event_types(&self) -> Result<HashSet<String>> {
-        detect_event_types(&self.conn)
-    }
-
-    /// Parse all events based on options and available tables
-    fn parse_all_events(
+    /// Extract events from the database, before linking
+    ///
+    /// Everything that needs SQLite happens here: kernels (with launch frames),
+    /// CUDA API calls, NVTX ranges, and pass-through events (WDDM packets with
+    /// the GPU idle gaps they explain, NVLink transfers, copies, MPI calls, OS runtime
+    /// and scheduling events, and markers where the profiler dropped data).
+    fn extract_events(
         &self,
+        options: &ConversionOptions,
         strings: &HashMap<i32, String>,
         device_map: &HashMap<i32, i32>,
         thread_names: &HashMap<i32, String>,
-    ) -> Result<Vec<ChromeTraceEvent>> {
-        let mut events = Vec::new();
-        let available_activities = self.detect_event_types()?;
+        schema: &SchemaProbe,
+    ) -> Result<FrontendTrace> {
+        let mut trace = FrontendTrace::default();
+        let available_activities = schema.activity_types();
 
         // Filter requested activities by what's actually available
-        let requested_activities: HashSet<String> =
-            self.options.activity_types.iter().cloned().collect();
-        let activities_to_parse: HashSet<String> = requested_activities
-            .intersection(&available_activities)
+        let activities_to_parse: HashSet<EventCategory> = options
+            .activity_types
+            .iter()
+            .filter(|t| available_activities.contains(t.activity_type()))
             .cloned()
             .collect();
 
+        // Custom cost model, or the default one when estimates are requested
+        let default_cost_model = DefaultCostModel;
+        let cost_model: Option<&dyn KernelCostModel> = match &self.cost_model {
+            Some(model) => Some(model.as_ref()),
+            None if options.estimate_kernel_costs => Some(&default_cost_model),
+            None => None,
+        };
+
         // Create parse context
-        let context = ParseContext::new(&self.conn, strings, &self.options, device_map, thread_names);
+        let context = ParseContext::new(&self.conn, strings, options, device_map, thread_names)
+            .with_schema(schema)
+            .with_cost_model(cost_model);
+
+        // Parse kernels, CUDA API calls and NVTX ranges, the bulk of most
+        // captures; with several jobs each table is read on its own connection
+        // (in-memory databases have only the one)
+        let wants_kernels = activities_to_parse.contains(&EventCategory::Kernel);
+        let wants_api = activities_to_parse.contains(&EventCategory::CudaApi);
+        let wants_nvtx = activities_to_parse.contains(&EventCategory::Nvtx);
+        let (kernel_events, api_events, annotation_events) = if options.worker_threads() > 1
+            && self.source.can_reopen()
+        {
+            let source = &self.source;
+            let cancellation = &self.cancellation;
+            let parent_phase = current_context();
+            let parse_on_own_connection =
+                |wanted: bool, parser: &dyn EventParser| -> Result<Vec<ChromeTraceEvent>> {
+                    if !wanted {
+                        return Ok(Vec::new());
+                    }
+                    let _phase_context = parent_phase.enter();
+                    let conn = source.reopen()?;
+                    if let Some(token) = cancellation {
+                        token.interrupt_queries(&conn);
+                    }
+                    let context =
+                        ParseContext::new(&conn, strings, options, device_map, thread_names)
+                            .with_schema(schema)
+                            .with_cost_model(cost_model);
+                    parser.safe_parse(&context)
+                };
+            std::thread::scope(|scope| {
+                let kernels =
+                    scope.spawn(|| parse_on_own_connection(wants_kernels, &CUPTIKernelParser));
+                let api = scope.spawn(|| parse_on_own_connection(wants_api, &CUPTIRuntimeParser));
+                let annotations = parse_on_own_connection(wants_nvtx, &NVTXParser);
+                let join = |handle: std::thread::ScopedJoinHandle<'_, _>| {
+                    handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e))
+                };
+                (join(kernels), join(api), annotations)
+            })
+        } else {
+            let parse = |wanted: bool, parser: &dyn EventParser| -> Result<Vec<ChromeTraceEvent>> {
+                if wanted {
+                    parser.safe_parse(&context)
+                } else {
+                    Ok(Vec::new())
+                }
+            };
+            (
+                parse(wants_kernels, &CUPTIKernelParser),
+                parse(wants_api, &CUPTIRuntimeParser),
+                parse(wants_nvtx, &NVTXParser),
+            )
+        };
+        trace.kernel_events = kernel_events?;
+        trace.api_events = api_events?;
+        trace.annotation_events = annotation_events?;
+        self.checkpoint()?;
+
+        // Fold recursive ranges before linking multiplies their attribution
+        let folded = collapse_nested_ranges(&mut trace.annotation_events, options.nvtx_nesting);
+        if folded > 0 {
+            log::debug!("Folded {} nested NVTX ranges", folded);
+        }
 
-        // Track parsed events for nvtx-kernel linking
-        let mut kernel_events = Vec::new();
-        let mut cuda_api_events = Vec::new();
-        let mut nvtx_events = Vec::new();
+        // Attach launch call-site frames when backtraces were captured
+        if wants_kernels {
+            attach_kernel_source_frames(
+                &self.conn,
+                strings,
+                &mut trace.kernel_events,
+                options.source_frame_depth,
+            )?;
+        }
 
-        // Parse kernel events
-        if activities_to_parse.contains("kernel") {
-            let parser = CUPTIKernelParser;
-            kernel_events = parser.safe_parse(&context)?;
+        // Attach full CPU call stacks when backtraces were captured
+        if wants_api && options.api_call_stacks {
+            attach_api_call_stacks(&self.conn, strings, &mut trace.api_events)?;
         }
 
-        // Parse CUDA API events
-        if activities_to_parse.contains("cuda-api") {
-            let parser = CUPTIRuntimeParser;
-            cuda_api_events = parser.safe_parse(&context)?;
+        // Show kernels replayed from CUDA graphs under the NVTX names of their nodes
+        let renamed = name_graph_kernels(
+            &self.conn,
+            &mut trace.kernel_events,
+            &trace.annotation_events,
+        )?;
+        if renamed > 0 {
+            log::debug!("Named {} graph kernels after their nodes", renamed);
         }
 
-        // Parse NVTX events
-        if activities_to_parse.contains("nvtx") {
-            let parser = NVTXParser;
-            nvtx_events = parser.safe_parse(&context)?;
+        // Parse WDDM queue/DMA packets and explain GPU idle gaps with them
+        if activities_to_parse.contains(&EventCategory::Wddm) {
+            let parser = WDDMParser;
+            let wddm_events = parser.safe_parse(&context)?;
+            if !trace.kernel_events.is_empty() && !wddm_events.is_empty() {
+                let mut gaps = find_kernel_gaps(&trace.kernel_events, MIN_GAP_NS);
+                attribute_wddm_queue_time(&mut gaps, &wddm_events);
+                trace.other_events.extend(gap_events(&gaps));
+            }
+            trace.other_events.extend(wddm_events);
         }
 
-        // Parse nvtx-kernel events (requires linking) - uses references, no cloning
-        if activities_to_parse.contains("nvtx-kernel") {
-            let (nvtx_kernel_events, remaining_nvtx) = process_nvtx_kernel_linking(
-                &kernel_events,
-                &cuda_api_events,
-                nvtx_events,
-                &self.options,
-            );
-            events.extend(nvtx_kernel_events);
-            nvtx_events = remaining_nvtx;
+        // Parse Vulkan/DX12 workloads and project debug labels onto the GPU
+        if activities_to_parse.contains(&EventCategory::Graphics) {
+            let parser = GraphicsParser;
+            trace.other_events.extend(parser.safe_parse(&context)?);
         }
 
-        // Add kernel events (move, not clone)
-        events.extend(kernel_events);
+        // Parse peer-to-peer copies onto per-link tracks
+        if activities_to_parse.contains(&EventCategory::Nvlink) {
+            let parser = P2PParser;
+            trace.other_events.extend(parser.safe_parse(&context)?);
+        }
 
-        // Add CUDA API events (move, not clone)
-        events.extend(cuda_api_events);
+        // Parse power, temperature and clock samples and mark where clocks throttled
+        if activities_to_parse.contains(&EventCategory::GpuMetrics) {
+            let parser = GpuMetricsParser;
+            let metrics = parser.safe_parse(&context)?;
+            trace
+                .other_events
+                .extend(throttled_regions(&metrics, &trace.kernel_events));
+            trace.other_events.extend(metrics);
+        }
+
+        // Parse host and device copies onto copy engine tracks
+        if activities_to_parse.contains(&EventCategory::Memcpy) {
+            let parser = MemcpyParser;
+            trace.other_events.extend(parser.safe_parse(&context)?);
+        }
+
+        // Parse NVTX marks and their payload counters; they are not linked
+        if wants_nvtx {
+            let parser = NvtxMarkParser;
+            trace.other_events.extend(parser.safe_parse(&context)?);
+        }
 
-        // Add any remaining NVTX events (move, not clone)
-        events.extend(nvtx_events);
+        // Parse MPI calls onto per-rank tracks
+        if activities_to_parse.contains(&EventCategory::Mpi) {
+            let parser = MPIParser;
+            trace.other_events.extend(parser.safe_parse(&context)?);
+        }
 
         // Parse OS runtime events
-        if activities_to_parse.contains("osrt") {
+        if activities_to_parse.contains(&EventCategory::Osrt) {
             let parser = OSRTParser;
-            events.extend(parser.safe_parse(&context)?);
+            trace.other_events.extend(parser.safe_parse(&context)?);
         }
 
         // Parse scheduling events
-        if activities_to_parse.contains("sched") {
+        if activities_to_parse.contains(&EventCategory::Sched) {
             let parser = SchedParser;
-            events.extend(parser.safe_parse(&context)?);
+            trace.other_events.extend(parser.safe_parse(&context)?);
         }
 
-        Ok(events)
+        // Turn context switches into running/preempted/waiting slices
+        if activities_to_parse.contains(&EventCategory::ThreadState) {
+            let parser = ThreadStateParser;
+            trace.other_events.extend(parser.safe_parse(&context)?);
+        }
+
+        // Parse custom activities and hand them to linking in the role they play
+        for extractor in &self.extractors {
+            if !activities_to_parse.contains(&EventCategory::from(extractor.activity_type())) {
+                continue;
+            }
+            let events = CustomParser(extractor.as_ref()).safe_parse(&context)?;
+            match extractor.link_role() {
+                ActivityLinkRole::None => trace.other_events.extend(events),
+                ActivityLinkRole::Annotation => trace.annotation_events.extend(events),
+                ActivityLinkRole::Kernel => trace.kernel_events.extend(events),
+                ActivityLinkRole::CudaApi => trace.api_events.extend(events),
+            }
+        }
+
+        self.checkpoint()?;
+
+        // Mark where the profiler dropped data, whatever activities were requested
+        trace
+            .other_events
+            .extend(extract_dropped_events(&self.conn, device_map)?);
+
+        // Number events for linking, so passes can move and clone them
+        trace.assign_event_ids();
+
+        Ok(trace)
+    }
+
+    /// Probe the schema, rejecting exports no requested activity can be read from
+    ///
+    /// A database that is not SQLite is invalid input. One whose relevant tables
+    /// all lack required columns comes from an nsys version this converter does
+    /// not support; returning an empty trace would hide that.
+    fn probe_schema(&self) -> Result<(SchemaProbe, ConversionDiagnostics)> {
+        let _phase = phase("probe schema", "setup");
+        let mut schema = SchemaProbe::probe(&self.conn).map_err(|e| match e {
+            ConvertError::Sqlite(rusqlite::Error::SqliteFailure(err, _))
+                if err.code == rusqlite::ErrorCode::NotADatabase =>
+            {
+                ConvertError::InvalidInput("Input is not an SQLite database".to_string())
+            }
+            other => other,
+        })?;
+        for extractor in &self.extractors {
+            validate_extractor(extractor.as_ref())?;
+            schema.register_custom(
+                extractor.activity_type(),
+                extractor.table_name(),
+                extractor.link_role() == ActivityLinkRole::Annotation,
+            );
+        }
+        let diagnostics = ConversionDiagnostics::from_schema(&schema, &self.options.activity_types);
+
+        let requested: HashSet<&str> = self
+            .options
+            .activity_types
+            .iter()
+            .map(|t| t.activity_type())
+            .collect();
+        let nothing_available = requested
+            .iter()
+            .all(|activity| diagnostics.missing_activities.iter().any(|a| a == activity));
+        let incompatible: Vec<String> = diagnostics
+            .incompatible_tables
+            .iter()
+            .filter(|table| requested.contains(table.activity_type.as_str()))
+            .map(|table| format!("{} lacks {}", table.table, table.missing_columns.join(", ")))
+            .collect();
+        if nothing_available && !incompatible.is_empty() {
+            return Err(ConvertError::UnsupportedSchema(incompatible.join("; ")));
+        }
+
+        Ok((schema, diagnostics))
     }
 
-    /// Add metadata events for process and thread names
+    /// Add metadata events for process and thread names and device properties
     fn add_metadata_events(&self, thread_names: &HashMap<i32, String>) -> Result<Vec<ChromeTraceEvent>> {
         if !self.options.include_metadata {
             return Ok(Vec::new());
@@ -237,11 +691,14 @@ impl NsysChromeConverter {
             }
         }
 
+        // Add the hardware behind each device track
+        events.extend(device_properties_events(&extract_device_properties(&self.conn)?));
+
         Ok(events)
     }
 
     /// Sort events by timestamp, then pid, then tid
-    fn sort_events(mut events: Vec<ChromeTraceEvent>) -> Vec<ChromeTraceEvent> {
+    pub fn sort_events(mut events: Vec<ChromeTraceEvent>) -> Vec<ChromeTraceEvent> {
         events.sort_by(|a, b| {
             a.ts
                 .partial_cmp(&b.ts)
@@ -254,24 +711,293 @@ impl NsysChromeConverter {
 
     /// Perform the conversion
     pub fn convert(self) -> Result<Vec<ChromeTraceEvent>> {
-        // Load required data
-        
+        self.convert_with_diagnostics().map(|(events, _)| events)
+    }
+
+    /// Extract events without linking them, e.g. to write an event cache
+    ///
+    /// NVTX prefix filtering and coloring are left to
+    /// [`Self::convert_extracted`], so the extracted annotations can be filtered
+    /// and recolored without re-reading the database. Track-name metadata is
+    /// included in `other_events` when `include_metadata` is set.
+    pub fn extract(self) -> Result<(FrontendTrace, ConversionDiagnostics)> {
+        let options = ConversionOptions {
+            nvtx_event_prefix: None,
+            nvtx_color_scheme: HashMap::new(),
+            ..self.options.clone()
+        };
+        self.extract_with(&options)
+    }
+
+    /// Extract events with `options`, repairing records cut short by an
+    /// interrupted capture and adding track-name metadata
+    fn extract_with(&self, options: &ConversionOptions) -> Result<(FrontendTrace, ConversionDiagnostics)> {
+        let (schema, mut diagnostics) = self.probe_schema()?;
+
         let strings = self.load_strings()?;
         let device_map = extract_device_mapping(&self.conn)?;
         let thread_names = extract_thread_names(&self.conn)?;
 
-        // Parse all events
-        let mut events = self.parse_all_events(&strings, &device_map, &thread_names)?;
+        let mut trace =
+            self.extract_events(options, &strings, &device_map, &thread_names, &schema)?;
+        diagnostics.repaired_records = repair_truncated(&mut trace);
+        diagnostics.missing_streams =
+            resolve_missing_streams(&mut trace, self.options.missing_stream_policy);
+        diagnostics.dropped_events = DroppedEventStats::from_events(&trace.other_events);
+        trace
+            .other_events
+            .extend(self.add_metadata_events(&thread_names)?);
+        self.checkpoint()?;
+
+        Ok((trace, diagnostics))
+    }
+
+    /// Link, filter and sort extracted events into a finished trace
+    ///
+    /// The second half of every conversion, so events read back from an event
+    /// cache (see [`crate::cache`]) give the same trace as converting the
+    /// database they came from. `diagnostics` holds what extraction found;
+    /// linking diagnostics are added to it. Events without an ID are numbered.
+    pub fn convert_extracted(
+        mut trace: FrontendTrace,
+        options: &ConversionOptions,
+        diagnostics: ConversionDiagnostics,
+    ) -> Result<(Vec<ChromeTraceEvent>, ConversionDiagnostics)> {
+        trace.assign_event_ids();
+        finish_trace(trace, options, diagnostics, &|| Ok(()))
+    }
 
-        // Add metadata events
+    /// Convert only NVTX ranges and marks, for auditing instrumentation
+    ///
+    /// Kernel, API and other activity tables are never read (see
+    /// [`crate::annotations`]), so this stays fast on very large exports.
+    pub fn convert_annotations(self) -> Result<(Vec<ChromeTraceEvent>, ConversionDiagnostics)> {
+        let (schema, _) = self.probe_schema()?;
+        let mut diagnostics = ConversionDiagnostics::from_schema(&schema, &[EventCategory::Nvtx]);
+
+        let strings = self.load_strings()?;
+        let thread_names = extract_thread_names(&self.conn)?;
+        let device_map = HashMap::new();
+        let context = ParseContext::new(
+            &self.conn,
+            &strings,
+            &self.options,
+            &device_map,
+            &thread_names,
+        )
+        .with_schema(&schema);
+        let mut trace = FrontendTrace {
+            annotation_events: NVTXParser.safe_parse(&context)?,
+            other_events: NvtxMarkParser.safe_parse(&context)?,
+            ..Default::default()
+        };
+        diagnostics.repaired_records = repair_truncated(&mut trace);
+        self.checkpoint()?;
+        collapse_nested_ranges(&mut trace.annotation_events, self.options.nvtx_nesting);
+
+        let mut events = trace.annotation_events;
+        events.extend(trace.other_events);
+        group_by_process(&mut events);
+        let deepest = annotate_nesting_depth(&mut events);
+        log::debug!("NVTX ranges nest {} levels deep", deepest + 1);
+
+        if let Some(window) = self.options.time_window {
+            let (clamped, stats) = apply_time_window(events, window);
+            log::debug!("Time window: {:?}", stats);
+            events = clamped;
+        }
         if self.options.include_metadata {
-            events.extend(self.add_metadata_events(&thread_names)?);
+            events.extend(annotation_metadata(&events, &thread_names));
+            events.extend(config_metadata_event(&events, &self.options));
+        }
+        if let Some(grouping) = self.options.virtual_tids {
+            let renumbered = virtualize_tids(&mut events, grouping);
+            log::debug!("Assigned virtual tids to {} tracks", renumbered);
+        }
+        if let Some(epoch_ns) = apply_time_origin(&mut events, &self.options.time_origin) {
+            log::debug!("Rebased timestamps to epoch {} ns", epoch_ns);
         }
 
-        // Sort events
-        events = Self::sort_events(events);
+        let mut sort_phase = phase("sort", "post");
+        sort_phase.set_events(events.len());
+        Ok((Self::sort_events(events), diagnostics))
+    }
 
-        Ok(events)
+    /// Perform the conversion and return diagnostics collected along the way
+    pub fn convert_with_diagnostics(self) -> Result<(Vec<ChromeTraceEvent>, ConversionDiagnostics)> {
+        if self.options.annotations_only {
+            return self.convert_annotations();
+        }
+
+        let (trace, diagnostics) = self.extract_with(&self.options)?;
+        finish_trace(trace, &self.options, diagnostics, &|| self.checkpoint())
     }
 }
 
+/// Link, filter and sort extracted events, checking for cancellation between passes
+fn finish_trace(
+    trace: FrontendTrace,
+    options: &ConversionOptions,
+    mut diagnostics: ConversionDiagnostics,
+    checkpoint: &dyn Fn() -> Result<()>,
+) -> Result<(Vec<ChromeTraceEvent>, ConversionDiagnostics)> {
+    let wants = |activity: EventCategory| options.activity_types.contains(&activity);
+    let FrontendTrace {
+        mut kernel_events,
+        api_events: mut cuda_api_events,
+        annotation_events,
+        mut other_events,
+    } = trace;
+    // A fresh extraction holds only requested activities and matching NVTX
+    // names; a cache may hold more
+    for list in [&mut kernel_events, &mut cuda_api_events] {
+        list.retain(|e| wants(e.category()));
+    }
+    let mut nvtx_events = filter_annotations(annotation_events, options);
+    nvtx_events.retain(|e| wants(e.category()));
+    filter_marks(&mut other_events, options);
+    if !options.include_metadata {
+        other_events.retain(|e| e.ph != ChromeTracePhase::Metadata);
+    }
+    diagnostics.dropped_events = DroppedEventStats::from_events(&other_events);
+    let mut events = Vec::new();
+
+    if options.api_thread_states {
+        color_api_thread_states(&mut cuda_api_events);
+    }
+    if let Some(factor) = options.kernel_outlier_factor {
+        let flagged = flag_kernel_outliers(&mut kernel_events, factor);
+        log::debug!("Flagged {} kernel duration outliers", flagged);
+    }
+    let has_annotations = !nvtx_events.is_empty();
+
+    // Tag NCCL kernels with the MPI call, rank and communicator they ran under
+    {
+        let mut link_phase = phase("link MPI calls", "link");
+        let flows = link_mpi_to_nccl_kernels(&mut other_events, &mut kernel_events);
+        link_phase.set_events(flows.len());
+        events.extend(flows);
+    }
+
+    // Link copies to the calls that issued them
+    {
+        let mut link_phase = phase("link copies", "link");
+        let flows = link_copies_to_api_calls(&mut other_events, &cuda_api_events, &kernel_events);
+        link_phase.set_events(flows.len());
+        events.extend(flows);
+    }
+
+    // Parse nvtx-kernel events (requires linking) - uses references, no cloning
+    checkpoint()?;
+    if wants(EventCategory::NvtxKernel) {
+        let mut link_phase = phase("link NVTX ranges", "link");
+        let (nvtx_kernel_events, remaining_nvtx, stats, coverage) =
+            process_nvtx_kernel_linking(&kernel_events, &cuda_api_events, nvtx_events, options)?;
+        link_phase.set_events(nvtx_kernel_events.len());
+        diagnostics.ambiguous_links = stats.ambiguous_calls;
+        diagnostics.distant_links = stats.distant_kernels;
+        diagnostics.nvtx_coverage = coverage;
+        events.extend(nvtx_kernel_events);
+        nvtx_events = remaining_nvtx;
+        checkpoint()?;
+    }
+
+    // Synthesize step markers from kernel periodicity, unless the user annotated steps
+    if options.synthesize_steps && !has_annotations {
+        events.extend(synthesize_step_markers(&kernel_events));
+    }
+
+    // Group kernels into heuristic layers, unless the user annotated them
+    if options.infer_layers && !has_annotations {
+        events.extend(infer_layer_ranges(&kernel_events));
+    }
+
+    // Count concurrently running kernels per device
+    if options.kernel_concurrency {
+        let concurrency = kernel_concurrency(&kernel_events);
+        for device in &concurrency {
+            log::info!(
+                "Device {}: {:.1}% of busy time with 2+ kernels running (max {})",
+                device.device_id,
+                device.concurrent_pct(),
+                device.max_concurrency
+            );
+        }
+        events.extend(concurrency_counter_events(&concurrency));
+        events.extend(concurrency_summary_events(&concurrency));
+    }
+
+    // Sum memcpy rates per direction into throughput counters
+    if options.transfer_throughput {
+        let throughput = transfer_throughput(&other_events);
+        for device in &throughput {
+            for (direction, totals) in &device.totals {
+                log::info!(
+                    "Device {}: {} copies moved {} bytes {} ({:.3} GB/s while busy)",
+                    device.device_id,
+                    totals.copies,
+                    totals.bytes,
+                    direction.label(),
+                    totals.avg_gbps()
+                );
+            }
+        }
+        events.extend(throughput_counter_events(&throughput));
+        events.extend(transfer_totals_events(&throughput));
+    }
+
+    // Summarize each device's active/idle runs before short kernels are dropped
+    if options.include_metadata {
+        events.extend(device_activity_events(&device_activity(&kernel_events)));
+    }
+
+    // Add kernel, CUDA API, remaining NVTX and pass-through events (move, not clone)
+    events.extend(kernel_events);
+    events.extend(cuda_api_events);
+    events.extend(nvtx_events);
+    events.extend(other_events);
+    checkpoint()?;
+
+    // Drop short kernels once links exist, so the filter can keep them intact
+    if options.min_kernel_duration_ns > 0 {
+        let _phase = phase("filter short kernels", "post");
+        let (filtered, stats) = filter_short_kernels(events, options.min_kernel_duration_ns);
+        log::debug!("Duration filter: {:?}", stats);
+        events = filtered;
+    }
+
+    // Cut the trace down to the requested window before flows are rewritten
+    if let Some(window) = options.time_window {
+        let _phase = phase("time window", "post");
+        let (clamped, stats) = apply_time_window(events, window);
+        log::debug!("Time window: {:?}", stats);
+        events = clamped;
+    }
+
+    // Rewrite flow arrows once their slices are final
+    events = apply_flow_options(events, &options.flows);
+
+    // Describe the options the trace was converted with
+    if options.include_metadata {
+        let _phase = phase("metadata", "post");
+        events.extend(config_metadata_event(&events, options));
+    }
+
+    // Number tracks once their names are known, so thread_name events move along
+    if let Some(grouping) = options.virtual_tids {
+        let renumbered = virtualize_tids(&mut events, grouping);
+        log::debug!("Assigned virtual tids to {} tracks", renumbered);
+    }
+
+    // Rebase timestamps once every event, linked or derived, is in place
+    if let Some(epoch_ns) = apply_time_origin(&mut events, &options.time_origin) {
+        log::debug!("Rebased timestamps to epoch {} ns", epoch_ns);
+    }
+
+    // Sort events
+    checkpoint()?;
+    let mut sort_phase = phase("sort", "post");
+    sort_phase.set_events(events.len());
+    Ok((NsysChromeConverter::sort_events(events), diagnostics))
+
+}
@@ -0,0 +1,274 @@
+//! Kernel FLOP and byte estimates
+//!
+//! A `KernelCostModel` looks at a kernel's name, launch configuration and args
+//! and estimates the work it performed. The converter attaches the estimates to
+//! kernel events together with the achieved throughput over the kernel duration.
+
+use regex::Regex;
+use serde_json::json;
+use std::sync::OnceLock;
+
+use crate::models::{ChromeTraceEvent, EventArgs};
+
+/// Kernel launch description passed to cost models
+#[derive(Debug, Clone)]
+pub struct KernelLaunch<'a> {
+    /// Kernel (short) name
+    pub name: &'a str,
+    /// Grid dimensions (x, y, z)
+    pub grid: [i64; 3],
+    /// Block dimensions (x, y, z)
+    pub block: [i64; 3],
+    /// Kernel duration in nanoseconds
+    pub duration_ns: i64,
+    /// All event args, for models that use extra annotations such as shapes
+    pub args: &'a EventArgs,
+}
+
+impl<'a> KernelLaunch<'a> {
+    /// Build a launch description from a kernel event's args
+    ///
+    /// Returns None if the event has no `start_ns`/`end_ns`.
+    pub fn from_event(event: &'a ChromeTraceEvent) -> Option<Self> {
+        let start = event.args.get("start_ns")?.as_i64()?;
+        let end = event.args.get("end_ns")?.as_i64()?;
+        let dims = |key: &str| -> [i64; 3] {
+            let values = event.args.get(key).and_then(|v| v.as_array());
+            let get = |i: usize| {
+                values
+                    .and_then(|v| v.get(i))
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(1)
+            };
+            [get(0), get(1), get(2)]
+        };
+
+        Some(Self {
+            name: &event.name,
+            grid: dims("grid"),
+            block: dims("block"),
+            duration_ns: end - start,
+            args: &event.args,
+        })
+    }
+
+    /// Number of thread blocks in the grid
+    pub fn num_blocks(&self) -> i64 {
+        self.grid.iter().product()
+    }
+
+    /// Read a positive integer arg
+    pub fn arg_i64(&self, key: &str) -> Option<i64> {
+        self.args
+            .get(key)
+            .and_then(|v| v.as_i64())
+            .filter(|v| *v > 0)
+    }
+}
+
+/// Estimated work of a single kernel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct KernelCost {
+    /// Floating point operations
+    pub flops: Option<f64>,
+    /// Bytes read and written from device memory
+    pub bytes: Option<f64>,
+    /// True when derived from exact problem sizes rather than name/launch heuristics
+    pub exact: bool,
+}
+
+/// Estimates FLOPs and bytes for kernels
+///
+/// Implementations must be thread-safe; return None for kernels the model does
+/// not recognize.
+pub trait KernelCostModel: Send + Sync {
+    /// Name recorded in the `cost_model` arg
+    fn name(&self) -> &str;
+
+    /// Estimate the cost of a kernel launch
+    fn estimate(&self, launch: &KernelLaunch) -> Option<KernelCost>;
+}
+
+/// Attach a cost model's estimates to a kernel event
+///
+/// Adds `est_flops`, `est_bytes`, `achieved_tflops`, `achieved_gbps`,
+/// `cost_model` and `cost_exact` args as available. Returns true if an estimate
+/// was attached.
+pub fn attach_cost_estimate(event: &mut ChromeTraceEvent, model: &dyn KernelCostModel) -> bool {
+    let Some(launch) = KernelLaunch::from_event(event) else {
+        return false;
+    };
+    let duration_ns = launch.duration_ns;
+    let Some(cost) = model.estimate(&launch) else {
+        return false;
+    };
+    if cost.flops.is_none() && cost.bytes.is_none() {
+        return false;
+    }
+
+    if let Some(flops) = cost.flops {
+        event.args.insert("est_flops".into(), json!(flops));
+        if duration_ns > 0 {
+            // FLOP/ns = GFLOP/s
+            let tflops = flops / duration_ns as f64 / 1e3;
+            event
+                .args
+                .insert("achieved_tflops".into(), json!(round3(tflops)));
+        }
+    }
+    if let Some(bytes) = cost.bytes {
+        event.args.insert("est_bytes".into(), json!(bytes));
+        if duration_ns > 0 {
+            // bytes/ns = GB/s
+            let gbps = bytes / duration_ns as f64;
+            event
+                .args
+                .insert("achieved_gbps".into(), json!(round3(gbps)));
+        }
+    }
+    event.args.insert("cost_model".into(), json!(model.name()));
+    event.args.insert("cost_exact".into(), json!(cost.exact));
+    true
+}
+
+fn round3(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// Default model for common GEMM and attention kernel names
+///
+/// GEMMs use `m`/`n`/`k` args when present. Otherwise, kernels whose name carries
+/// a CTA tile (e.g. `ampere_h16816gemm_128x128_ldg8`) are estimated from the
+/// output coverage `blocks * tile_m * tile_n` with K assumed to be the geometric
+/// mean of M and N, which is only a rough guide.
+///
+/// Attention kernels need `batch`, `heads`, `seq_len` (or `seq_len_q` and
+/// `seq_len_k`) and `head_dim` args; their names carry no usable sizes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultCostModel;
+
+fn gemm_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)(gemm|matmul|cublas|cutlass|xmma|s1688|h1688|h16816|i8816)")
+            .expect("gemm pattern is valid")
+    })
+}
+
+fn attention_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)(flash_?(fwd|bwd|attn)|fmha|attention|sdpa|mha_)")
+            .expect("attention pattern is valid")
+    })
+}
+
+fn tile_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"_(\d{2,3})x(\d{2,3})").expect("tile pattern is valid"))
+}
+
+/// Bytes per element inferred from a kernel name (defaults to half precision)
+pub fn element_size_from_name(name: &str) -> f64 {
+    let lower = name.to_ascii_lowercase();
+    if ["f16", "bf16", "hgemm", "h1688", "h16816"]
+        .iter()
+        .any(|p| lower.contains(p))
+    {
+        2.0
+    } else if lower.contains("fp8")
+        || lower.contains("e4m3")
+        || lower.contains("e5m2")
+        || lower.contains("i8")
+    {
+        1.0
+    } else if lower.contains("sgemm")
+        || lower.contains("f32")
+        || lower.contains("fp32")
+        || lower.contains("tf32")
+        || lower.contains("s1688")
+    {
+        4.0
+    } else if lower.contains("dgemm") || lower.contains("f64") || lower.contains("fp64") {
+        8.0
+    } else {
+        2.0
+    }
+}
+
+impl DefaultCostModel {
+    fn estimate_gemm(launch: &KernelLaunch) -> Option<KernelCost> {
+        let elem = element_size_from_name(launch.name);
+
+        let (m, n, k, exact) = match (
+            launch.arg_i64("m"),
+            launch.arg_i64("n"),
+            launch.arg_i64("k"),
+        ) {
+            (Some(m), Some(n), Some(k)) => (m as f64, n as f64, k as f64, true),
+            _ => {
+                let captures = tile_pattern().captures(launch.name)?;
+                let tile_m: f64 = captures[1].parse().ok()?;
+                let tile_n: f64 = captures[2].parse().ok()?;
+                // Split-K kernels reuse the output tile across grid.z
+                let output_blocks = (launch.grid[0] * launch.grid[1]) as f64;
+                let m = tile_m * output_blocks.sqrt();
+                let n = tile_n * output_blocks.sqrt();
+                (m, n, (m * n).sqrt(), false)
+            }
+        };
+
+        let batch = launch.arg_i64("batch").unwrap_or(1) as f64;
+        Some(KernelCost {
+            flops: Some(2.0 * m * n * k * batch),
+            bytes: Some((m * k + k * n + m * n) * elem * batch),
+            exact,
+        })
+    }
+
+    fn estimate_attention(launch: &KernelLaunch) -> Option<KernelCost> {
+        let batch = launch.arg_i64("batch")? as f64;
+        let heads = launch.arg_i64("heads")? as f64;
+        let head_dim = launch.arg_i64("head_dim")? as f64;
+        let seq_q = launch
+            .arg_i64("seq_len_q")
+            .or_else(|| launch.arg_i64("seq_len"))? as f64;
+        let seq_k = launch
+            .arg_i64("seq_len_k")
+            .or_else(|| launch.arg_i64("seq_len"))? as f64;
+        let elem = element_size_from_name(launch.name);
+
+        // QK^T and PV matmuls; backward does roughly 2.5x the forward work
+        let forward_flops = 4.0 * batch * heads * seq_q * seq_k * head_dim;
+        let is_backward = launch.name.to_ascii_lowercase().contains("bwd");
+        let flops = if is_backward {
+            forward_flops * 2.5
+        } else {
+            forward_flops
+        };
+        // Q, K, V in and O out
+        let bytes = batch * heads * head_dim * (2.0 * seq_q + 2.0 * seq_k) * elem;
+
+        Some(KernelCost {
+            flops: Some(flops),
+            bytes: Some(bytes),
+            exact: true,
+        })
+    }
+}
+
+impl KernelCostModel for DefaultCostModel {
+    fn name(&self) -> &str {
+        "default"
+    }
+
+    fn estimate(&self, launch: &KernelLaunch) -> Option<KernelCost> {
+        if attention_pattern().is_match(launch.name) {
+            Self::estimate_attention(launch)
+        } else if gemm_pattern().is_match(launch.name) {
+            Self::estimate_gemm(launch)
+        } else {
+            None
+        }
+    }
+}
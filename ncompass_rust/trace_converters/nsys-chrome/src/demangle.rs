@@ -0,0 +1,765 @@
+//! Demangling of C++ kernel symbols, with a shared cache
+//!
+//! Some exports carry kernel names as Itanium-mangled symbols (`_Z...`), e.g.
+//! rocprof kernel symbols or nsys captures taken without demangling. The same
+//! few thousand symbols repeat across millions of launches, so each distinct
+//! name is demangled once into a process-wide [`DemangleCache`] shared by all
+//! devices and worker threads. Parsers warm the cache over the unique names of
+//! a capture in parallel before building events, leaving only lookups on the
+//! per-event path.
+//!
+//! The demangler covers what kernel symbols use: nested and local names,
+//! templates and literals, lambdas, operators, substitutions and cv/ref
+//! qualifiers, printed the way `c++filt` does. Symbols using anything else
+//! (expressions, function types, ...) are kept mangled.
+
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Demangled form of an Itanium C++ symbol, or `None` if it is not one
+///
+/// Clone suffixes are kept the way `c++filt` shows them, so
+/// `_Z6matmulPfS_S_.kd` becomes `matmul(float*, float*, float*) [clone .kd]`.
+pub fn demangle(symbol: &str) -> Option<String> {
+    let mangled = symbol.strip_prefix("_Z")?;
+    let (mangled, suffix) = match mangled.find('.') {
+        Some(dot) => mangled.split_at(dot),
+        None => (mangled, ""),
+    };
+
+    let mut parser = Parser::new(mangled);
+    let mut demangled = parser.encoding(true)?;
+    if !parser.at_end() {
+        return None;
+    }
+    if !suffix.is_empty() {
+        demangled.push_str(&format!(" [clone {}]", suffix));
+    }
+    Some(demangled)
+}
+
+/// Process-wide cache of demangled names, keyed by mangled name
+///
+/// Names that are not mangled, or use constructs the demangler does not cover,
+/// map to themselves.
+#[derive(Debug, Default)]
+pub struct DemangleCache {
+    names: RwLock<HashMap<Box<str>, Arc<str>>>,
+}
+
+impl DemangleCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cache shared by every parser and device
+    pub fn global() -> &'static DemangleCache {
+        static CACHE: OnceLock<DemangleCache> = OnceLock::new();
+        CACHE.get_or_init(DemangleCache::new)
+    }
+
+    /// Demangled form of `name`, demangling and caching it on first use
+    pub fn get(&self, name: &str) -> Arc<str> {
+        if let Some(demangled) = self.read().get(name) {
+            return demangled.clone();
+        }
+        let demangled: Arc<str> = demangle(name).map_or_else(|| Arc::from(name), Arc::from);
+        self.write()
+            .entry(Box::from(name))
+            .or_insert(demangled)
+            .clone()
+    }
+
+    /// Demangle the mangled names among `names` that are not cached yet, in parallel
+    ///
+    /// Returns the number of names added.
+    pub fn warm<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> usize {
+        let missing: Vec<&str> = {
+            let cached = self.read();
+            let mut missing: Vec<&str> = names
+                .into_iter()
+                .filter(|name| name.starts_with("_Z") && !cached.contains_key(*name))
+                .collect();
+            missing.sort_unstable();
+            missing.dedup();
+            missing
+        };
+        if missing.is_empty() {
+            return 0;
+        }
+
+        let demangled: Vec<(Box<str>, Arc<str>)> = missing
+            .par_iter()
+            .map(|&name| {
+                let demangled = demangle(name).map_or_else(|| Arc::from(name), Arc::from);
+                (Box::from(name), demangled)
+            })
+            .collect();
+        let mut cached = self.write();
+        let before = cached.len();
+        cached.extend(demangled);
+        cached.len() - before
+    }
+
+    /// Number of cached names
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<Box<str>, Arc<str>>> {
+        self.names.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<Box<str>, Arc<str>>> {
+        self.names.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A parsed (possibly qualified) name
+struct Name {
+    text: String,
+    /// Template arguments of the last component, if it has any
+    template_args: Option<Vec<String>>,
+    /// Innermost template arguments anywhere in the name, which `T_` refers to
+    scope_args: Option<Vec<String>>,
+    /// Constructor, destructor or conversion operator: never has a return type
+    no_return_type: bool,
+    /// Member function qualifiers (` const`, ` &`, ...) from a nested name
+    qualifiers: String,
+}
+
+/// Recursive-descent parser over the text after `_Z`
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    substitutions: Vec<String>,
+    /// Template arguments `T_`, `T0_`, ... refer to
+    template_args: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input: input.as_bytes(),
+            pos: 0,
+            substitutions: Vec::new(),
+            template_args: Vec::new(),
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.input.get(self.pos + offset).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Option<()> {
+        self.eat(c).then_some(())
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// `<encoding> ::= <name> [<bare-function-type>]`
+    ///
+    /// Function templates encode their return type, which is only shown when
+    /// `show_return_type` is set (not for functions enclosing a local name).
+    fn encoding(&mut self, show_return_type: bool) -> Option<String> {
+        let outer_args = std::mem::take(&mut self.template_args);
+        let name = self.name()?;
+        if let Some(args) = &name.scope_args {
+            self.template_args = args.clone();
+        }
+
+        let result = if self.at_end() || matches!(self.peek(), Some(b'E' | b'.')) {
+            name.text
+        } else {
+            let return_type = match name.template_args.is_some() && !name.no_return_type {
+                true => Some(self.type_()?),
+                false => None,
+            };
+            let mut params = Vec::new();
+            while !self.at_end() && !matches!(self.peek(), Some(b'E' | b'.')) {
+                params.push(self.type_()?);
+            }
+            if params == ["void"] {
+                params.clear();
+            }
+            let signature = format!("{}({}){}", name.text, params.join(", "), name.qualifiers);
+            match return_type {
+                Some(ret) if show_return_type => format!("{} {}", ret, signature),
+                _ => signature,
+            }
+        };
+        self.template_args = outer_args;
+        Some(result)
+    }
+
+    /// `<name>`: nested, local, unscoped or substituted, with optional template args
+    fn name(&mut self) -> Option<Name> {
+        match self.peek()? {
+            b'N' => self.nested_name(),
+            b'Z' => self.local_name(),
+            b'S' if self.peek_at(1) != Some(b't') => {
+                let prefix = self.substitution()?;
+                self.name_template_args(prefix, false, false)
+            }
+            _ => {
+                let std_prefix = self.input[self.pos..].starts_with(b"St");
+                if std_prefix {
+                    self.pos += 2;
+                }
+                let (text, special) = self.unqualified_name(None)?;
+                let text = if std_prefix {
+                    format!("std::{}", text)
+                } else {
+                    text
+                };
+                self.name_template_args(text, special, true)
+            }
+        }
+    }
+
+    /// Attach template args following an unscoped or substituted name
+    fn name_template_args(&mut self, text: String, special: bool, subst: bool) -> Option<Name> {
+        let mut name = Name {
+            text,
+            template_args: None,
+            scope_args: None,
+            no_return_type: special,
+            qualifiers: String::new(),
+        };
+        if self.peek() == Some(b'I') {
+            if subst {
+                self.substitutions.push(name.text.clone());
+            }
+            let args = self.template_args()?;
+            name.text = with_template_args(&name.text, &args);
+            name.scope_args = Some(args.clone());
+            name.template_args = Some(args);
+        }
+        Some(name)
+    }
+
+    /// `N [<CV-qualifiers>] [<ref-qualifier>] <prefix> <unqualified-name> E`
+    fn nested_name(&mut self) -> Option<Name> {
+        self.expect(b'N')?;
+        let mut qualifiers = self.cv_qualifiers();
+        if self.eat(b'R') {
+            qualifiers.push_str(" &");
+        } else if self.eat(b'O') {
+            qualifiers.push_str(" &&");
+        }
+
+        let mut text = String::new();
+        let mut last_component = String::new();
+        let mut template_args = None;
+        let mut scope_args = None;
+        let mut special = false;
+        loop {
+            let peek = self.peek()?;
+            if peek == b'E' {
+                break;
+            }
+            template_args = None;
+            special = false;
+            match peek {
+                b'I' => {
+                    if text.is_empty() {
+                        return None;
+                    }
+                    let args = self.template_args()?;
+                    text = with_template_args(&text, &args);
+                    scope_args = Some(args.clone());
+                    template_args = Some(args);
+                }
+                b'S' if self.peek_at(1) == Some(b't') => {
+                    self.pos += 2;
+                    text = "std".to_string();
+                }
+                b'S' => text = self.substitution()?,
+                b'T' => text = self.template_param()?,
+                _ => {
+                    let (component, is_special) = self.unqualified_name(Some(&last_component))?;
+                    text = match text.is_empty() {
+                        true => component.clone(),
+                        false => format!("{}::{}", text, component),
+                    };
+                    last_component = component;
+                    special = is_special;
+                }
+            }
+            if peek != b'S' && self.peek() != Some(b'E') {
+                self.substitutions.push(text.clone());
+            }
+        }
+        self.expect(b'E')?;
+        Some(Name {
+            text,
+            template_args,
+            scope_args,
+            no_return_type: special,
+            qualifiers,
+        })
+    }
+
+    /// `Z <function encoding> E <entity name> [<discriminator>]`
+    fn local_name(&mut self) -> Option<Name> {
+        self.expect(b'Z')?;
+        let function = self.encoding(false)?;
+        self.expect(b'E')?;
+        if self.eat(b's') {
+            self.discriminator();
+            return Some(Name {
+                text: format!("{}::string literal", function),
+                template_args: None,
+                scope_args: None,
+                no_return_type: false,
+                qualifiers: String::new(),
+            });
+        }
+        let mut entity = self.name()?;
+        self.discriminator();
+        entity.text = format!("{}::{}", function, entity.text);
+        Some(entity)
+    }
+
+    /// `_ <digit>` or `__ <number> _`, which `c++filt` does not show
+    fn discriminator(&mut self) {
+        if self.peek() != Some(b'_') {
+            return;
+        }
+        if self.peek_at(1) == Some(b'_') {
+            self.pos += 2;
+            let _ = self.number();
+            self.eat(b'_');
+        } else if self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 2;
+        }
+    }
+
+    /// Source, operator, constructor/destructor or closure name
+    ///
+    /// `enclosing` is the previous component of a nested name, which names
+    /// constructors and destructors. The flag marks names without a return type.
+    fn unqualified_name(&mut self, enclosing: Option<&str>) -> Option<(String, bool)> {
+        let (mut name, special) = match self.peek()? {
+            b'0'..=b'9' => (self.source_name()?, false),
+            b'C' if matches!(self.peek_at(1), Some(b'1'..=b'5')) => {
+                self.pos += 2;
+                (base_name(enclosing?).to_string(), true)
+            }
+            b'D' if matches!(self.peek_at(1), Some(b'0'..=b'5')) => {
+                self.pos += 2;
+                (format!("~{}", base_name(enclosing?)), true)
+            }
+            b'U' if self.peek_at(1) == Some(b'l') => {
+                self.pos += 2;
+                let mut params = Vec::new();
+                while self.peek()? != b'E' {
+                    params.push(self.type_()?);
+                }
+                self.pos += 1;
+                if params == ["void"] {
+                    params.clear();
+                }
+                let index = self.closure_index()?;
+                (
+                    format!("{{lambda({})#{}}}", params.join(", "), index),
+                    false,
+                )
+            }
+            b'U' if self.peek_at(1) == Some(b't') => {
+                self.pos += 2;
+                let index = self.closure_index()?;
+                (format!("{{unnamed type#{}}}", index), false)
+            }
+            b'c' if self.peek_at(1) == Some(b'v') => {
+                self.pos += 2;
+                (format!("operator {}", self.type_()?), true)
+            }
+            b'a'..=b'z' => {
+                let code = self.input.get(self.pos..self.pos + 2)?;
+                let op = operator_name(code)?;
+                self.pos += 2;
+                (format!("operator{}", op), false)
+            }
+            _ => return None,
+        };
+        // ABI tags: B5cxx11 -> [abi:cxx11]
+        while self.eat(b'B') {
+            name.push_str(&format!("[abi:{}]", self.source_name()?));
+        }
+        Some((name, special))
+    }
+
+    /// `[<number>] _` after a closure or unnamed type, as a 1-based index
+    fn closure_index(&mut self) -> Option<usize> {
+        let index = match self.peek()? {
+            b'_' => 1,
+            _ => self.number()? + 2,
+        };
+        self.expect(b'_')?;
+        Some(index)
+    }
+
+    /// `<length> <identifier>`
+    fn source_name(&mut self) -> Option<String> {
+        let len = self.number()?;
+        let bytes = self.input.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        let name = std::str::from_utf8(bytes).ok()?;
+        Some(match name.starts_with("_GLOBAL__N") {
+            true => "(anonymous namespace)".to_string(),
+            false => name.to_string(),
+        })
+    }
+
+    /// `S_`, `S <seq-id> _` or a standard abbreviation (`St`, `Sa`, ...)
+    fn substitution(&mut self) -> Option<String> {
+        self.expect(b'S')?;
+        let abbreviation = match self.peek()? {
+            b'a' => Some("std::allocator"),
+            b'b' => Some("std::basic_string"),
+            b's' => Some("std::string"),
+            b'i' => Some("std::istream"),
+            b'o' => Some("std::ostream"),
+            b'd' => Some("std::iostream"),
+            _ => None,
+        };
+        if let Some(abbreviation) = abbreviation {
+            self.pos += 1;
+            return Some(abbreviation.to_string());
+        }
+
+        let mut index = 0;
+        if !self.eat(b'_') {
+            let mut seq = 0usize;
+            loop {
+                let c = self.peek()?;
+                self.pos += 1;
+                seq = match c {
+                    b'0'..=b'9' => seq * 36 + (c - b'0') as usize,
+                    b'A'..=b'Z' => seq * 36 + (c - b'A') as usize + 10,
+                    b'_' => break,
+                    _ => return None,
+                };
+            }
+            index = seq + 1;
+        }
+        self.substitutions.get(index).cloned()
+    }
+
+    /// `T_` or `T <number> _`
+    fn template_param(&mut self) -> Option<String> {
+        self.expect(b'T')?;
+        let index = match self.eat(b'_') {
+            true => 0,
+            false => {
+                let n = self.number()?;
+                self.expect(b'_')?;
+                n + 1
+            }
+        };
+        self.template_args.get(index).cloned()
+    }
+
+    /// `I <template-arg>+ E`
+    fn template_args(&mut self) -> Option<Vec<String>> {
+        self.expect(b'I')?;
+        let mut args = Vec::new();
+        while !self.eat(b'E') {
+            args.push(self.template_arg()?);
+        }
+        Some(args)
+    }
+
+    fn template_arg(&mut self) -> Option<String> {
+        match self.peek()? {
+            b'L' => self.literal(),
+            b'J' => {
+                self.pos += 1;
+                let mut pack = Vec::new();
+                while !self.eat(b'E') {
+                    pack.push(self.template_arg()?);
+                }
+                Some(pack.join(", "))
+            }
+            _ => self.type_(),
+        }
+    }
+
+    /// `L <type> <value> E`
+    fn literal(&mut self) -> Option<String> {
+        self.expect(b'L')?;
+        if matches!(self.peek()?, b'_' | b'Z') {
+            // External names need the full expression grammar
+            return None;
+        }
+        let ty = self.type_()?;
+        let negative = self.eat(b'n');
+        let start = self.pos;
+        while self.peek()? != b'E' {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(&self.input[start..self.pos]).ok()?;
+        self.pos += 1;
+        let value = format!("{}{}", if negative { "-" } else { "" }, digits);
+        Some(match ty.as_str() {
+            "bool" if value == "0" => "false".to_string(),
+            "bool" if value == "1" => "true".to_string(),
+            "int" => value,
+            "unsigned int" => format!("{}u", value),
+            "long" => format!("{}l", value),
+            "unsigned long" => format!("{}ul", value),
+            "long long" => format!("{}ll", value),
+            "unsigned long long" => format!("{}ull", value),
+            _ => format!("({}){}", ty, value),
+        })
+    }
+
+    /// `<type>`; every non-builtin type becomes a substitution candidate
+    fn type_(&mut self) -> Option<String> {
+        let c = self.peek()?;
+        if let Some(builtin) = builtin_type(c) {
+            self.pos += 1;
+            return Some(builtin.to_string());
+        }
+
+        let ty = match c {
+            b'r' | b'V' | b'K' | b'U' => {
+                let mut vendor = String::new();
+                while self.peek() == Some(b'U') {
+                    self.pos += 1;
+                    vendor.push_str(&format!(" {}", self.source_name()?));
+                }
+                let qualifiers = self.cv_qualifiers();
+                let inner = self.type_()?;
+                format!("{}{}{}", inner, qualifiers, vendor)
+            }
+            b'P' | b'R' | b'O' => {
+                self.pos += 1;
+                let inner = self.type_()?;
+                match c {
+                    b'P' => indirect(&inner, "*"),
+                    // Reference collapsing: T& & and T&& & are T&, T&& && is T&&
+                    b'R' if inner.ends_with("&&") => inner[..inner.len() - 1].to_string(),
+                    _ if inner.ends_with('&') => inner,
+                    b'R' => indirect(&inner, "&"),
+                    _ => indirect(&inner, "&&"),
+                }
+            }
+            b'A' => {
+                self.pos += 1;
+                let len = self.number();
+                self.expect(b'_')?;
+                let element = self.type_()?;
+                match len {
+                    Some(len) => format!("{} [{}]", element, len),
+                    None => format!("{} []", element),
+                }
+            }
+            b'D' => match self.peek_at(1)? {
+                b'n' => {
+                    self.pos += 2;
+                    return Some("decltype(nullptr)".to_string());
+                }
+                b'p' => {
+                    // Template params name whole packs, already spelled out
+                    self.pos += 2;
+                    self.type_()?
+                }
+                b'F' => {
+                    // DF16_ and friends: _FloatN
+                    self.pos += 2;
+                    let bits = self.number()?;
+                    self.expect(b'_')?;
+                    return Some(format!("_Float{}", bits));
+                }
+                other => {
+                    let builtin = match other {
+                        b'h' => "half",
+                        b's' => "char16_t",
+                        b'i' => "char32_t",
+                        b'u' => "char8_t",
+                        b'a' => "auto",
+                        _ => return None,
+                    };
+                    self.pos += 2;
+                    return Some(builtin.to_string());
+                }
+            },
+            b'T' => {
+                let param = self.template_param()?;
+                if self.peek() == Some(b'I') {
+                    self.substitutions.push(param.clone());
+                    let args = self.template_args()?;
+                    with_template_args(&param, &args)
+                } else {
+                    param
+                }
+            }
+            b'S' if self.peek_at(1) != Some(b't') => {
+                let sub = self.substitution()?;
+                if self.peek() != Some(b'I') {
+                    return Some(sub);
+                }
+                let args = self.template_args()?;
+                with_template_args(&sub, &args)
+            }
+            b'N' | b'Z' | b'S' | b'0'..=b'9' => self.name()?.text,
+            _ => return None,
+        };
+        self.substitutions.push(ty.clone());
+        Some(ty)
+    }
+
+    /// `[r] [V] [K]`, printed after the type they qualify
+    fn cv_qualifiers(&mut self) -> String {
+        let restrict = self.eat(b'r');
+        let volatile = self.eat(b'V');
+        let constant = self.eat(b'K');
+        let mut qualifiers = String::new();
+        if constant {
+            qualifiers.push_str(" const");
+        }
+        if volatile {
+            qualifiers.push_str(" volatile");
+        }
+        if restrict {
+            qualifiers.push_str(" restrict");
+        }
+        qualifiers
+    }
+}
+
+/// Append template args, spacing out `> >` like `c++filt`
+fn with_template_args(name: &str, args: &[String]) -> String {
+    let args: Vec<&str> = args
+        .iter()
+        .map(|a| a.as_str())
+        .filter(|a| !a.is_empty())
+        .collect();
+    let joined = args.join(", ");
+    let close = if joined.ends_with('>') { " >" } else { ">" };
+    format!("{}<{}{}", name, joined, close)
+}
+
+/// Pointer or reference to `inner`, parenthesized for arrays: `float (*) [3]`
+fn indirect(inner: &str, sigil: &str) -> String {
+    match inner.rfind(" [") {
+        Some(i) if inner.ends_with(']') && !inner[..i].ends_with(')') => {
+            format!("{} ({}){}", &inner[..i], sigil, &inner[i..])
+        }
+        _ => format!("{}{}", inner, sigil),
+    }
+}
+
+/// Name of a class without its template args, for constructors and destructors
+fn base_name(name: &str) -> &str {
+    name.find('<').map_or(name, |i| &name[..i])
+}
+
+fn builtin_type(c: u8) -> Option<&'static str> {
+    Some(match c {
+        b'v' => "void",
+        b'w' => "wchar_t",
+        b'b' => "bool",
+        b'c' => "char",
+        b'a' => "signed char",
+        b'h' => "unsigned char",
+        b's' => "short",
+        b't' => "unsigned short",
+        b'i' => "int",
+        b'j' => "unsigned int",
+        b'l' => "long",
+        b'm' => "unsigned long",
+        b'x' => "long long",
+        b'y' => "unsigned long long",
+        b'n' => "__int128",
+        b'o' => "unsigned __int128",
+        b'f' => "float",
+        b'd' => "double",
+        b'e' => "long double",
+        b'g' => "__float128",
+        b'z' => "...",
+        _ => return None,
+    })
+}
+
+fn operator_name(code: &[u8]) -> Option<&'static str> {
+    Some(match code {
+        b"nw" => " new",
+        b"na" => " new[]",
+        b"dl" => " delete",
+        b"da" => " delete[]",
+        b"ps" | b"pl" => "+",
+        b"ng" | b"mi" => "-",
+        b"ad" | b"an" => "&",
+        b"de" | b"ml" => "*",
+        b"co" => "~",
+        b"dv" => "/",
+        b"rm" => "%",
+        b"or" => "|",
+        b"eo" => "^",
+        b"aS" => "=",
+        b"pL" => "+=",
+        b"mI" => "-=",
+        b"mL" => "*=",
+        b"dV" => "/=",
+        b"rM" => "%=",
+        b"aN" => "&=",
+        b"oR" => "|=",
+        b"eO" => "^=",
+        b"ls" => "<<",
+        b"rs" => ">>",
+        b"lS" => "<<=",
+        b"rS" => ">>=",
+        b"eq" => "==",
+        b"ne" => "!=",
+        b"lt" => "<",
+        b"gt" => ">",
+        b"le" => "<=",
+        b"ge" => ">=",
+        b"ss" => "<=>",
+        b"nt" => "!",
+        b"aa" => "&&",
+        b"oo" => "||",
+        b"pp" => "++",
+        b"mm" => "--",
+        b"cm" => ",",
+        b"pm" => "->*",
+        b"pt" => "->",
+        b"cl" => "()",
+        b"ix" => "[]",
+        _ => return None,
+    })
+}
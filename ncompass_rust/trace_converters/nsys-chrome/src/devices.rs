@@ -0,0 +1,225 @@
+//! GPU device properties from TARGET_INFO_GPU
+//!
+//! nsys records the hardware of every GPU in the capture: name, SM count,
+//! clocks, memory size and bandwidth, compute capability. The properties are
+//! embedded in the trace as one `device_properties` metadata event per device
+//! (next to a `process_labels` event naming the GPU in Perfetto) and can be
+//! written to a `devices.json` file, so occupancy or roofline analyses have
+//! the hardware context at hand.
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+use crate::schema::table_exists;
+
+/// Name of the metadata event holding a device's properties
+pub const DEVICE_PROPERTIES_EVENT: &str = "device_properties";
+
+/// Table names used by nsys versions for GPU properties, in order of preference
+const DEVICE_TABLES: &[&str] = &["TARGET_INFO_GPU", "TARGET_INFOGPU"];
+
+/// Hardware properties of one GPU; fields the export lacks are left out
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceProperties {
+    /// CUDA device ID, as in kernel `deviceId` args
+    pub id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chip_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bus_location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// Compute capability as "major.minor"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compute_capability: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sm_count: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_rate_hz: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_memory_bytes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_bandwidth_bytes_per_s: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l2_cache_bytes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads_per_warp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_warps_per_sm: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_blocks_per_sm: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_registers_per_sm: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_shared_memory_per_sm: Option<i64>,
+}
+
+impl DeviceProperties {
+    /// Short label for the device's track, e.g. "NVIDIA H100 (132 SMs, sm_90)"
+    pub fn label(&self) -> Option<String> {
+        let name = self.name.as_ref()?;
+        let mut details = Vec::new();
+        if let Some(sm_count) = self.sm_count {
+            details.push(format!("{} SMs", sm_count));
+        }
+        if let Some(cc) = &self.compute_capability {
+            details.push(format!("sm_{}", cc.replace('.', "")));
+        }
+        if details.is_empty() {
+            Some(name.clone())
+        } else {
+            Some(format!("{} ({})", name, details.join(", ")))
+        }
+    }
+}
+
+/// Read a column as text, whatever type the export stored it as
+fn text_value(value: SqlValue) -> Option<String> {
+    match value {
+        SqlValue::Text(text) => Some(text),
+        SqlValue::Integer(i) => Some(i.to_string()),
+        SqlValue::Real(f) => Some(f.to_string()),
+        SqlValue::Blob(bytes) => Some(bytes.iter().map(|b| format!("{:02x}", b)).collect()),
+        SqlValue::Null => None,
+    }
+}
+
+fn int_value(value: SqlValue) -> Option<i64> {
+    match value {
+        SqlValue::Integer(i) => Some(i),
+        SqlValue::Real(f) => Some(f as i64),
+        SqlValue::Text(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Extract the properties of every GPU, ordered by device ID
+///
+/// Returns an empty list for exports without a device table. When a device is
+/// listed more than once (one row per context or VM), the first row is kept.
+pub fn extract_device_properties(conn: &Connection) -> Result<Vec<DeviceProperties>> {
+    let mut table = None;
+    for candidate in DEVICE_TABLES {
+        if table_exists(conn, candidate)? {
+            table = Some(*candidate);
+            break;
+        }
+    }
+    let Some(table) = table else {
+        return Ok(Vec::new());
+    };
+
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table))?;
+    let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+
+    let mut devices: BTreeMap<i64, DeviceProperties> = BTreeMap::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let mut values: HashMap<&str, SqlValue> = HashMap::new();
+        for (index, name) in column_names.iter().enumerate() {
+            values.insert(name.as_str(), row.get(index)?);
+        }
+        let mut int = |name: &str| values.remove(name).and_then(int_value);
+
+        // cuDevice is the CUDA ordinal kernels report; older exports only have id
+        let Some(id) = int("cuDevice").or_else(|| int("id")) else {
+            continue;
+        };
+        let compute_capability = match (int("computeMajor"), int("computeMinor")) {
+            (Some(major), Some(minor)) => Some(format!("{}.{}", major, minor)),
+            _ => None,
+        };
+        let mut properties = DeviceProperties {
+            id,
+            compute_capability,
+            sm_count: int("smCount"),
+            clock_rate_hz: int("clockRate"),
+            total_memory_bytes: int("totalMemory"),
+            memory_bandwidth_bytes_per_s: int("memoryBandwidth"),
+            l2_cache_bytes: int("l2CacheSize"),
+            threads_per_warp: int("threadsPerWarp"),
+            max_warps_per_sm: int("maxWarpsPerSm"),
+            max_blocks_per_sm: int("maxBlocksPerSm"),
+            max_registers_per_sm: int("maxRegistersPerSm"),
+            max_shared_memory_per_sm: int("maxShmemPerSm"),
+            ..Default::default()
+        };
+        let mut text = |name: &str| values.remove(name).and_then(text_value);
+        properties.name = text("name");
+        properties.chip_name = text("chipName");
+        properties.bus_location = text("busLocation");
+        properties.uuid = text("uuid");
+
+        devices.entry(id).or_insert(properties);
+    }
+
+    Ok(devices.into_values().collect())
+}
+
+/// Metadata events carrying the properties and a track label for each device
+pub fn device_properties_events(devices: &[DeviceProperties]) -> Vec<ChromeTraceEvent> {
+    let mut events = Vec::new();
+    for device in devices {
+        let pid = format!("Device {}", device.id);
+        let args = match serde_json::to_value(device) {
+            Ok(Value::Object(map)) => map.into_iter().collect(),
+            _ => HashMap::new(),
+        };
+        events.push(ChromeTraceEvent::metadata(
+            DEVICE_PROPERTIES_EVENT.to_string(),
+            pid.clone(),
+            String::new(),
+            args,
+        ));
+        if let Some(label) = device.label() {
+            let mut args = HashMap::new();
+            args.insert("labels".to_string(), json!(label));
+            events.push(ChromeTraceEvent::metadata(
+                "process_labels".to_string(),
+                pid,
+                String::new(),
+                args,
+            ));
+        }
+    }
+    events
+}
+
+/// Recover device properties from the metadata events of a converted trace
+pub fn device_properties_from_events(events: &[ChromeTraceEvent]) -> Vec<DeviceProperties> {
+    let mut devices: BTreeMap<i64, DeviceProperties> = BTreeMap::new();
+    for event in events {
+        if event.ph != ChromeTracePhase::Metadata || event.name != DEVICE_PROPERTIES_EVENT {
+            continue;
+        }
+        let args: serde_json::Map<String, Value> = event
+            .args
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        if let Ok(device) = serde_json::from_value::<DeviceProperties>(Value::Object(args)) {
+            devices.entry(device.id).or_insert(device);
+        }
+    }
+    devices.into_values().collect()
+}
+
+/// Write `{"devices": [...]}`
+pub fn write_devices_json(path: &str, devices: &[DeviceProperties]) -> Result<()> {
+    let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+        path: path.into(),
+        source,
+    })?;
+    serde_json::to_writer_pretty(BufWriter::new(file), &json!({ "devices": devices }))
+        .map_err(|e| ConvertError::Output(e.into()))?;
+    Ok(())
+}
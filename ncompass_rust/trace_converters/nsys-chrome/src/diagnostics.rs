@@ -0,0 +1,170 @@
+//! Diagnostics collected during conversion
+
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::analysis::{MissingStreamStats, RepairStats};
+use crate::category::EventCategory;
+use crate::dropped::DroppedEventStats;
+use crate::linker::NvtxCoverage;
+use crate::parsers::cupti::UNKNOWN_STREAM_TRACK;
+use crate::schema::{IncompatibleTable, SchemaProbe};
+
+/// Non-fatal findings reported alongside converted events
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConversionDiagnostics {
+    /// Requested activity types with no compatible table in the input
+    pub missing_activities: Vec<String>,
+    /// Tables skipped because they lack columns required by their extractor
+    pub incompatible_tables: Vec<IncompatibleTable>,
+    /// Profiling tables present in the input that no extractor handles
+    pub unknown_tables: Vec<String>,
+    /// Records left unfinished by an interrupted capture and repaired
+    pub repaired_records: RepairStats,
+    /// Kernels recorded without a stream ID, by how they were handled
+    pub missing_streams: MissingStreamStats,
+    /// Data the profiler reported dropping during capture
+    pub dropped_events: DroppedEventStats,
+    /// CUDA API calls whose best-fitting NVTX ranges tie under the link policy
+    pub ambiguous_links: usize,
+    /// Kernels left unlinked for starting too long after their CUDA API call
+    pub distant_links: usize,
+    /// How many NVTX ranges and how much kernel time linking covered
+    pub nvtx_coverage: Option<NvtxCoverage>,
+}
+
+impl ConversionDiagnostics {
+    /// Build diagnostics from a schema probe and the requested activity types
+    pub fn from_schema(schema: &SchemaProbe, requested_activities: &[EventCategory]) -> Self {
+        let available = schema.activity_types();
+        let mut seen = HashSet::new();
+        let missing_activities = requested_activities
+            .iter()
+            .map(|a| a.activity_type())
+            .filter(|a| !available.contains(*a) && seen.insert(*a))
+            .map(str::to_string)
+            .collect();
+
+        Self {
+            missing_activities,
+            incompatible_tables: schema.incompatible.clone(),
+            unknown_tables: schema.unknown_tables.clone(),
+            repaired_records: RepairStats::default(),
+            missing_streams: MissingStreamStats::default(),
+            dropped_events: DroppedEventStats::default(),
+            ambiguous_links: 0,
+            distant_links: 0,
+            nvtx_coverage: None,
+        }
+    }
+
+    /// Check whether any findings were recorded
+    pub fn is_empty(&self) -> bool {
+        self.missing_activities.is_empty()
+            && self.incompatible_tables.is_empty()
+            && self.unknown_tables.is_empty()
+            && self.repaired_records.total() == 0
+            && self.missing_streams.total() == 0
+            && self.dropped_events.reports == 0
+            && self.ambiguous_links == 0
+            && self.distant_links == 0
+            && self.uncovered_ranges() == 0
+    }
+
+    /// NVTX ranges that received no kernels
+    pub fn uncovered_ranges(&self) -> usize {
+        self.nvtx_coverage
+            .map_or(0, |coverage| coverage.uncovered_ranges())
+    }
+
+    /// Human-readable summary, one finding per line
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        if !self.missing_activities.is_empty() {
+            lines.push(format!(
+                "Requested activity types not available in input: {}",
+                self.missing_activities.join(", ")
+            ));
+        }
+        for table in &self.incompatible_tables {
+            lines.push(format!(
+                "Skipped table {} ({}): missing columns {}",
+                table.table,
+                table.activity_type,
+                table.missing_columns.join(", ")
+            ));
+        }
+        if !self.unknown_tables.is_empty() {
+            lines.push(format!(
+                "Tables without an extractor: {}",
+                self.unknown_tables.join(", ")
+            ));
+        }
+        let repaired = &self.repaired_records;
+        if repaired.total() > 0 {
+            lines.push(format!(
+                "Repaired {} truncated records: {} closed at capture end, \
+                 {} negative durations clamped, {} dropped",
+                repaired.total(),
+                repaired.closed_at_capture_end,
+                repaired.clamped_negative,
+                repaired.dropped
+            ));
+        }
+        let streams = &self.missing_streams;
+        if streams.total() > 0 {
+            lines.push(format!(
+                "{} kernels had no stream ID: {} inferred from their launching thread, \
+                 {} on the '{}' track, {} dropped",
+                streams.total(),
+                streams.inferred,
+                streams.unknown,
+                UNKNOWN_STREAM_TRACK,
+                streams.dropped
+            ));
+        }
+        let dropped = &self.dropped_events;
+        if dropped.reports > 0 {
+            let mut lost = Vec::new();
+            if dropped.events > 0 {
+                lost.push(format!("{} events", dropped.events));
+            }
+            if dropped.buffers > 0 {
+                lost.push(format!("{} buffers", dropped.buffers));
+            }
+            if dropped.uncounted_reports > 0 {
+                lost.push(format!("{} uncounted reports", dropped.uncounted_reports));
+            }
+            lines.push(format!(
+                "Profiler dropped data {} times ({}); gaps near the \
+                 'profiler dropped' markers may not be idle time",
+                dropped.reports,
+                lost.join(", ")
+            ));
+        }
+        if self.ambiguous_links > 0 {
+            lines.push(format!(
+                "{} CUDA API calls fit several NVTX ranges equally well; \
+                 see link_confidence on nvtx-kernel events",
+                self.ambiguous_links
+            ));
+        }
+        if self.distant_links > 0 {
+            lines.push(format!(
+                "{} kernels started too long after their CUDA API call and were not \
+                 linked (stale correlation IDs?)",
+                self.distant_links
+            ));
+        }
+        if self.uncovered_ranges() > 0 {
+            lines.push(format!(
+                "{} NVTX ranges have no kernels attributed (pushed on a thread that \
+                 launches nothing?); --tag-unattributed marks them",
+                self.uncovered_ranges()
+            ));
+        }
+
+        lines
+    }
+}
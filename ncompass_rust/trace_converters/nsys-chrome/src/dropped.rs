@@ -0,0 +1,166 @@
+//! Events the profiler dropped during capture
+//!
+//! When CUPTI runs out of activity buffers, or nsys cannot keep up, records
+//! are lost and nsys logs a message in DIAGNOSTIC_EVENT. Without them a GPU
+//! gap looks like idle time. Each such message becomes an instant
+//! `profiler dropped N events` marker at the time it was logged, on the
+//! affected device when the message names a process, and the totals are
+//! reported in the conversion diagnostics.
+
+use regex::Regex;
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::error::Result;
+use crate::mapping::decompose_global_tid;
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase};
+use crate::schema::{table_columns, table_exists};
+
+/// Table where nsys logs capture warnings and errors
+pub const DIAGNOSTIC_TABLE: &str = "DIAGNOSTIC_EVENT";
+
+/// Category of dropped-event markers; kept whatever activity types are requested
+pub const DROPPED_CATEGORY: &str = "profiler";
+
+/// Process holding markers that cannot be tied to a device
+pub const PROFILER_PROCESS: &str = "Profiler";
+
+/// Thread holding the dropped-event markers
+pub const DROPPED_TRACK: &str = "Dropped Events";
+
+/// Messages reporting lost data
+fn drop_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)\b(drop(ped|s)?|lost|discard(ed)?|overflow(ed)?)\b").unwrap()
+    })
+}
+
+/// A count followed by what was lost, e.g. "1024 activity records" or "3 buffers"
+fn count_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)(\d+)\s+(?:[a-z]+\s+){0,2}?(events?|records?|buffers?|activities)\b")
+            .unwrap()
+    })
+}
+
+/// Number and kind of lost items in a diagnostic message, if it states them
+///
+/// The kind is `"buffers"` when whole buffers were lost, `"events"` otherwise.
+pub fn parse_dropped_count(text: &str) -> Option<(u64, &'static str)> {
+    let captures = count_pattern().captures(text)?;
+    let count = captures[1].parse().ok()?;
+    let unit = if captures[2].to_ascii_lowercase().starts_with("buffer") {
+        "buffers"
+    } else {
+        "events"
+    };
+    Some((count, unit))
+}
+
+/// Totals of the dropped-data reports found in a capture
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DroppedEventStats {
+    /// Diagnostic messages reporting dropped data
+    pub reports: usize,
+    /// Events stated as lost across all reports
+    pub events: u64,
+    /// Buffers stated as lost across all reports
+    pub buffers: u64,
+    /// Reports that did not say how much was lost
+    pub uncounted_reports: usize,
+}
+
+impl DroppedEventStats {
+    /// Add up the markers built by [`extract_dropped_events`] among `events`
+    pub fn from_events(events: &[ChromeTraceEvent]) -> Self {
+        let mut stats = Self::default();
+        let markers = events
+            .iter()
+            .filter(|e| e.ph == ChromeTracePhase::Instant && &*e.cat == DROPPED_CATEGORY);
+        for marker in markers {
+            stats.reports += 1;
+            let count = marker.args.get("count").and_then(|v| v.as_u64());
+            match (count, marker.args.get("unit").and_then(|v| v.as_str())) {
+                (Some(count), Some("buffers")) => stats.buffers += count,
+                (Some(count), _) => stats.events += count,
+                (None, _) => stats.uncounted_reports += 1,
+            }
+        }
+        stats
+    }
+}
+
+/// Build a marker for each DIAGNOSTIC_EVENT message reporting dropped data
+pub fn extract_dropped_events(
+    conn: &Connection,
+    device_map: &HashMap<i32, i32>,
+) -> Result<Vec<ChromeTraceEvent>> {
+    let mut markers = Vec::new();
+    if !table_exists(conn, DIAGNOSTIC_TABLE)? {
+        return Ok(markers);
+    }
+    let columns = table_columns(conn, DIAGNOSTIC_TABLE)?;
+    if !columns.contains("timestamp") || !columns.contains("text") {
+        return Ok(markers);
+    }
+    let global_pid = if columns.contains("globalPid") {
+        "globalPid"
+    } else {
+        "NULL"
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT timestamp, text, {} FROM {} ORDER BY timestamp",
+        global_pid, DIAGNOSTIC_TABLE
+    ))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let timestamp: Option<i64> = row.get(0)?;
+        let text: Option<String> = row.get(1)?;
+        let global_pid: Option<i64> = row.get(2)?;
+        let (Some(timestamp), Some(text)) = (timestamp, text) else {
+            continue;
+        };
+        if !drop_pattern().is_match(&text) {
+            continue;
+        }
+
+        let raw_pid = global_pid.map(|g| decompose_global_tid(g).0);
+        let pid = match raw_pid.and_then(|p| device_map.get(&p)) {
+            Some(device_id) => format!("Device {}", device_id),
+            None => PROFILER_PROCESS.to_string(),
+        };
+        let counted = parse_dropped_count(&text);
+        let name = match counted {
+            Some((count, unit)) => format!("profiler dropped {} {}", count, unit),
+            None => "profiler dropped events".to_string(),
+        };
+
+        let mut marker = ChromeTraceEvent::new(
+            name,
+            ChromeTracePhase::Instant,
+            ns_to_us(timestamp),
+            pid,
+            DROPPED_TRACK.to_string(),
+            DROPPED_CATEGORY.to_string(),
+        )
+        .with_arg("message", json!(text))
+        .with_arg("start_ns", json!(timestamp));
+        if let Some((count, unit)) = counted {
+            marker = marker
+                .with_arg("count", json!(count))
+                .with_arg("unit", json!(unit));
+        }
+        if let Some(raw_pid) = raw_pid {
+            marker = marker.with_arg("raw_pid", json!(raw_pid));
+        }
+        markers.push(marker);
+    }
+
+    Ok(markers)
+}
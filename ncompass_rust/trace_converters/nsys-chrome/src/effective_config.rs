@@ -0,0 +1,286 @@
+//! Export of the fully-resolved conversion options
+//!
+//! Options reach the converter through presets, config files and command-line
+//! flags. [`effective_config`] writes the result back as a JSON object keyed
+//! like a config file (see [`crate::presets`]), with every option spelled out
+//! and keys sorted, so overlaying it on the defaults reproduces the
+//! conversion. The same object can be rendered as text protobuf against the
+//! `ConversionOptions` message in `proto/conversion_options.proto`, and is
+//! embedded in the trace as a `conversion_config` metadata event.
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+use crate::analysis::time_origin::origin_label;
+use crate::error::{ConvertError, Result};
+use crate::models::{
+    ChromeTraceEvent, ConversionOptions, FlowBind, FlowLink, FlowStyle, LinkPolicy,
+    MissingStreamPolicy, NvtxKernelLayout, NvtxNesting, TidGrouping, TimeShift,
+};
+
+/// Metadata event carrying the effective options of the conversion
+pub const CONVERSION_CONFIG_EVENT: &str = "conversion_config";
+
+/// Rendering of the effective options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// A JSON config file
+    #[default]
+    Json,
+    /// Text protobuf of the `ConversionOptions` message
+    TextProto,
+}
+
+/// Parse `json` or `textproto` into a config format
+pub fn parse_config_format(value: &str) -> Result<ConfigFormat> {
+    match value.trim() {
+        "json" => Ok(ConfigFormat::Json),
+        "textproto" => Ok(ConfigFormat::TextProto),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid config format '{}' (use json or textproto)",
+            value
+        ))),
+    }
+}
+
+/// Durations are written in nanoseconds, which parse back exactly
+fn duration(ns: i64) -> String {
+    format!("{}ns", ns)
+}
+
+fn time_shift(shift: TimeShift) -> String {
+    match shift {
+        TimeShift::Fixed(ns) => duration(ns),
+        TimeShift::Estimated => "auto".to_string(),
+    }
+}
+
+fn link_policy(policy: LinkPolicy) -> &'static str {
+    match policy {
+        LinkPolicy::All => "all",
+        LinkPolicy::Innermost => "innermost",
+        LinkPolicy::LongestOverlap => "longest-overlap",
+    }
+}
+
+fn missing_stream(policy: MissingStreamPolicy) -> &'static str {
+    match policy {
+        MissingStreamPolicy::Unknown => "unknown",
+        MissingStreamPolicy::Infer => "infer",
+        MissingStreamPolicy::Drop => "drop",
+    }
+}
+
+fn nvtx_nesting(nesting: NvtxNesting) -> String {
+    match nesting {
+        NvtxNesting::Keep => "keep".to_string(),
+        NvtxNesting::CollapseSameName => "collapse".to_string(),
+        NvtxNesting::MaxDepth(depth) => format!("depth:{}", depth),
+    }
+}
+
+fn nvtx_kernel_layout(layout: NvtxKernelLayout) -> &'static str {
+    match layout {
+        NvtxKernelLayout::Device => "device",
+        NvtxKernelLayout::Annotation => "annotation",
+    }
+}
+
+fn tid_grouping(grouping: TidGrouping) -> &'static str {
+    match grouping {
+        TidGrouping::Category => "category",
+        TidGrouping::Thread => "thread",
+    }
+}
+
+fn flow_style(style: FlowStyle) -> &'static str {
+    match style {
+        FlowStyle::Events => "events",
+        FlowStyle::Bound => "bound",
+    }
+}
+
+fn flow_bind(bind: FlowBind) -> &'static str {
+    match bind {
+        FlowBind::Enclosing => "enclosing",
+        FlowBind::Next => "next",
+    }
+}
+
+fn flow_link(link: FlowLink) -> &'static str {
+    match link {
+        FlowLink::Launch => "launch",
+        FlowLink::Copy => "copy",
+        FlowLink::Mpi => "mpi",
+    }
+}
+
+/// Every option of `options` as config-file keys
+///
+/// Options that are unset (`None`) are left out, so the object reproduces
+/// `options` when overlaid on [`ConversionOptions::default`].
+pub fn effective_config(options: &ConversionOptions) -> Map<String, Value> {
+    let mut config = Map::new();
+    let mut set = |key: &str, value: Value| {
+        config.insert(key.to_string(), value);
+    };
+
+    let activity_types: Vec<&str> = options
+        .activity_types
+        .iter()
+        .map(|t| t.activity_type())
+        .collect();
+    set("activity_types", json!(activity_types));
+    if let Some(prefix) = &options.nvtx_event_prefix {
+        set("nvtx_prefix", json!(prefix));
+    }
+    set("nvtx_colors", json!(options.nvtx_color_scheme));
+    if let Some(domains) = &options.nvtx_domains {
+        set("nvtx_domains", json!(domains));
+    }
+    set("nvtx_domain_prefix", json!(options.nvtx_domain_prefix));
+    set("nvtx_domain_tracks", json!(options.nvtx_domain_tracks));
+    set("nvtx_nesting", json!(nvtx_nesting(options.nvtx_nesting)));
+    set("include_metadata", json!(options.include_metadata));
+    set("source_frames", json!(options.source_frame_depth));
+    set("api_call_stacks", json!(options.api_call_stacks));
+    set("synthesize_steps", json!(options.synthesize_steps));
+    set("infer_layers", json!(options.infer_layers));
+    set("kernel_concurrency", json!(options.kernel_concurrency));
+    set("transfer_throughput", json!(options.transfer_throughput));
+    set("estimate_costs", json!(options.estimate_kernel_costs));
+    set(
+        "min_duration",
+        json!(duration(options.min_kernel_duration_ns)),
+    );
+    set("api_thread_states", json!(options.api_thread_states));
+    if let Some(factor) = options.kernel_outlier_factor {
+        set("outlier_factor", json!(factor));
+    }
+    set("time_origin", json!(origin_label(&options.time_origin)));
+    if let Some(window) = options.time_window {
+        let end = match window.end_ns {
+            i64::MAX => String::new(),
+            end_ns => duration(end_ns),
+        };
+        set(
+            "time_window",
+            json!(format!("{}..{}", duration(window.start_ns), end)),
+        );
+    }
+    let routes: Vec<String> = options
+        .output_routes
+        .iter()
+        .map(|route| format!("{}={}", route.categories.join(","), route.path))
+        .collect();
+    set("routes", json!(routes));
+    set("jobs", json!(options.jobs));
+    set("source_rows", json!(options.source_rows));
+    let shifts: HashMap<&str, String> = options
+        .annotation_time_shifts
+        .iter()
+        .map(|(key, &shift)| (key.as_str(), time_shift(shift)))
+        .collect();
+    set("time_shifts", json!(shifts));
+    set("link_policy", json!(link_policy(options.link_policy)));
+    if let Some(gap_ns) = options.max_link_gap_ns {
+        set("max_link_gap", json!(duration(gap_ns)));
+    }
+    if let Some(window_ns) = options.link_window_ns {
+        set("link_window", json!(duration(window_ns)));
+    }
+    if let Some(lookback_ns) = options.sticky_lookback_ns {
+        set("sticky_lookback", json!(duration(lookback_ns)));
+    }
+    set(
+        "nvtx_kernel_per_stream",
+        json!(options.nvtx_kernel_per_stream),
+    );
+    set(
+        "nvtx_kernel_layout",
+        json!(nvtx_kernel_layout(options.nvtx_kernel_layout)),
+    );
+    set("tag_unattributed", json!(options.tag_unattributed));
+    set("unattributed_work", json!(options.unattributed_work));
+    set(
+        "missing_stream",
+        json!(missing_stream(options.missing_stream_policy)),
+    );
+    if let Some(grouping) = options.virtual_tids {
+        set("virtual_tids", json!(tid_grouping(grouping)));
+    }
+    set("annotations_only", json!(options.annotations_only));
+    set("flow_style", json!(flow_style(options.flows.style)));
+    let binds: Map<String, Value> = FlowLink::ALL
+        .into_iter()
+        .map(|link| {
+            let bind = flow_bind(options.flows.bind(link));
+            (flow_link(link).to_string(), json!(bind))
+        })
+        .collect();
+    set("flow_bind", Value::Object(binds));
+    config
+}
+
+/// Append one field to a text protobuf
+fn write_textproto_field(out: &mut String, key: &str, value: &Value) {
+    match value {
+        Value::Null => {}
+        Value::Array(items) => {
+            for item in items {
+                write_textproto_field(out, key, item);
+            }
+        }
+        Value::Object(entries) => {
+            for (entry_key, entry_value) in entries {
+                out.push_str(&format!(
+                    "{} {{ key: {} value: {} }}\n",
+                    key,
+                    Value::from(entry_key.as_str()),
+                    entry_value
+                ));
+            }
+        }
+        // JSON string escapes are valid text protobuf escapes
+        scalar => out.push_str(&format!("{}: {}\n", key, scalar)),
+    }
+}
+
+/// Render the effective options of a conversion
+pub fn render_config(options: &ConversionOptions, format: ConfigFormat) -> String {
+    let config = effective_config(options);
+    match format {
+        ConfigFormat::Json => {
+            let mut text = serde_json::to_string_pretty(&config).unwrap_or_default();
+            text.push('\n');
+            text
+        }
+        ConfigFormat::TextProto => {
+            let mut text = String::new();
+            for (key, value) in &config {
+                write_textproto_field(&mut text, key, value);
+            }
+            text
+        }
+    }
+}
+
+/// Metadata event embedding the effective options in a trace
+///
+/// The event is scoped to the first process of the trace, so it does not add
+/// a track of its own; None when there are no events. `jobs` is left out, as
+/// the output does not depend on it.
+pub fn config_metadata_event(
+    events: &[ChromeTraceEvent],
+    options: &ConversionOptions,
+) -> Option<ChromeTraceEvent> {
+    let pid = events.iter().map(|e| &e.pid).min()?;
+    let mut config = effective_config(options);
+    config.remove("jobs");
+    Some(ChromeTraceEvent::metadata(
+        CONVERSION_CONFIG_EVENT.to_string(),
+        pid.to_string(),
+        String::new(),
+        HashMap::from([("options".to_string(), Value::Object(config))]),
+    ))
+}
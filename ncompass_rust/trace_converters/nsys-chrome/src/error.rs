@@ -0,0 +1,127 @@
+//! Error type returned by the library API
+//!
+//! Library functions return [`ConvertError`] so callers can tell a missing
+//! input apart from an unsupported export or a failed write, and react to
+//! each programmatically. The CLI wraps these errors in `anyhow` for display.
+
+use std::error::Error as StdError;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Errors raised while reading, converting or writing traces
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    /// Input file does not exist
+    #[error("Input file not found: {}", .0.display())]
+    InputNotFound(PathBuf),
+
+    /// Input file exists but could not be opened or read
+    #[error("Failed to read input: {}", path.display())]
+    InputRead {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// SQLite export lacks the tables or columns needed for conversion
+    #[error("Unsupported nsys schema: {0}")]
+    UnsupportedSchema(String),
+
+    /// Input was read but its contents are not in the expected format
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// Output file could not be created
+    #[error("Failed to create output file: {}", path.display())]
+    CreateOutput {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// Output file exists and overwriting was not allowed
+    #[error("Output file already exists: {}", .0.display())]
+    OutputExists(PathBuf),
+
+    /// Writing or compressing the output failed
+    #[error("Failed to write output")]
+    Output(#[source] io::Error),
+
+    /// Option value that cannot be parsed, e.g. a malformed duration
+    #[error("{0}")]
+    InvalidOption(String),
+
+    /// Query against the nsys SQLite export failed
+    #[error(transparent)]
+    Sqlite(rusqlite::Error),
+
+    /// JSON serialization or parsing failed
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// Server socket could not be bound
+    #[error("Failed to bind address: {addr}")]
+    Bind {
+        addr: SocketAddr,
+        #[source]
+        source: io::Error,
+    },
+
+    /// Any other I/O failure (sockets, temporary files)
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// Conversion was stopped through a [`CancellationToken`](crate::cancel::CancellationToken)
+    #[error("Conversion cancelled")]
+    Cancelled,
+}
+
+impl From<rusqlite::Error> for ConvertError {
+    /// Queries are only interrupted on cancellation (see [`crate::cancel`])
+    fn from(error: rusqlite::Error) -> Self {
+        match error {
+            rusqlite::Error::SqliteFailure(err, _)
+                if err.code == rusqlite::ErrorCode::OperationInterrupted =>
+            {
+                ConvertError::Cancelled
+            }
+            error => ConvertError::Sqlite(error),
+        }
+    }
+}
+
+/// Result alias for library functions
+pub type Result<T, E = ConvertError> = std::result::Result<T, E>;
+
+impl ConvertError {
+    /// Classify a failure to open an input file
+    pub fn open_input(path: impl AsRef<Path>, source: io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        if source.kind() == io::ErrorKind::NotFound {
+            ConvertError::InputNotFound(path)
+        } else {
+            ConvertError::InputRead { path, source }
+        }
+    }
+
+    /// Reclassify plain I/O failures as output failures
+    pub(crate) fn into_output(self) -> Self {
+        match self {
+            ConvertError::Io(source) => ConvertError::Output(source),
+            other => other,
+        }
+    }
+
+    /// Message with every cause appended, e.g. for HTTP responses
+    pub fn display_chain(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = self.source();
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        message
+    }
+}
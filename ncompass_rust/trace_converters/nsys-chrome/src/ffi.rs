@@ -0,0 +1,299 @@
+//! C ABI for converting and linking in-process
+//!
+//! Built into the `nsys_chrome` cdylib; `include/nsys_chrome.h` declares these
+//! functions for C and C++ callers. Every function returns a status code
+//! (`NSYS_CHROME_OK` on success) and records the message of a failure for
+//! [`nsys_chrome_last_error`]. Options are a JSON object whose keys mirror the
+//! CLI flags (`{"activity_types": ["kernel", "nvtx"], "min_duration": "5us"}`);
+//! NULL or an empty string selects the defaults. Events can also cross the
+//! boundary as delimited protobuf streams (see [`crate::interop`]), which
+//! avoids formatting and parsing JSON on both sides.
+
+use serde_json::Value;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::interop::{export_events_pb, import_events_pb};
+use crate::linker::link_nvtx_to_kernels;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use crate::presets::overlay_options;
+use crate::{convert_file, convert_file_gz};
+
+/// The call succeeded
+pub const NSYS_CHROME_OK: c_int = 0;
+/// An argument was NULL, not UTF-8, or an invalid option
+pub const NSYS_CHROME_INVALID_ARGUMENT: c_int = 1;
+/// Conversion or linking failed
+pub const NSYS_CHROME_FAILED: c_int = 2;
+/// The library panicked; the message is recorded as for any other failure
+pub const NSYS_CHROME_PANIC: c_int = 3;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // Interior NULs would truncate the message, so replace them
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run `f`, turning errors and panics into status codes
+fn guarded(f: impl FnOnce() -> Result<()>) -> c_int {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => NSYS_CHROME_OK,
+        Ok(Err(error @ ConvertError::InvalidOption(_))) => {
+            set_last_error(error.display_chain());
+            NSYS_CHROME_INVALID_ARGUMENT
+        }
+        Ok(Err(error)) => {
+            set_last_error(error.display_chain());
+            NSYS_CHROME_FAILED
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("Panic: {}", message));
+            NSYS_CHROME_PANIC
+        }
+    }
+}
+
+/// Borrow a C string argument, treating NULL as absent
+///
+/// # Safety
+/// `ptr` must be NULL or point to a NUL-terminated string valid for `'a`.
+unsafe fn c_str<'a>(ptr: *const c_char, name: &str) -> Result<Option<&'a str>> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(Some)
+        .map_err(|_| ConvertError::InvalidOption(format!("{} is not valid UTF-8", name)))
+}
+
+/// Borrow a required C string argument
+///
+/// # Safety
+/// As for [`c_str`].
+unsafe fn required<'a>(ptr: *const c_char, name: &str) -> Result<&'a str> {
+    c_str(ptr, name)?.ok_or_else(|| ConvertError::InvalidOption(format!("{} is NULL", name)))
+}
+
+/// Build conversion options from a JSON object keyed like the CLI flags
+///
+/// See [`crate::presets`] for the keys; unknown keys are rejected.
+pub fn options_from_json(text: &str) -> Result<ConversionOptions> {
+    overlay_options(ConversionOptions::default(), text)
+}
+
+/// Read a complete event of a Chrome trace; other phases are skipped
+fn complete_event(value: &Value) -> Option<ChromeTraceEvent> {
+    if value.get("ph")?.as_str()? != "X" {
+        return None;
+    }
+    let text = |key: &str| match value.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    };
+    let args = value
+        .get("args")
+        .and_then(|a| a.as_object())
+        .map(|a| a.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default();
+    Some(
+        ChromeTraceEvent::complete(
+            text("name"),
+            value.get("ts")?.as_f64()?,
+            value.get("dur")?.as_f64()?,
+            text("pid"),
+            text("tid"),
+            text("cat"),
+        )
+        .with_args(args),
+    )
+}
+
+/// Link the `nvtx` ranges of a Chrome trace to its `kernel` events
+///
+/// Returns the nvtx-kernel and flow events as a JSON array.
+pub fn link_trace_json(events_json: &str, options: &ConversionOptions) -> Result<String> {
+    let root: Value = serde_json::from_str(events_json)?;
+    let Some(values) = root
+        .as_array()
+        .or_else(|| root.get("traceEvents").and_then(|v| v.as_array()))
+    else {
+        return Err(ConvertError::InvalidInput(
+            "Not a Chrome trace: missing 'traceEvents' array".to_string(),
+        ));
+    };
+
+    let linked = link_complete_events(values.iter().filter_map(complete_event), options);
+    Ok(serde_json::to_string(&linked)?)
+}
+
+/// Link the `nvtx` ranges among complete events to the `kernel` events
+fn link_complete_events(
+    events: impl IntoIterator<Item = ChromeTraceEvent>,
+    options: &ConversionOptions,
+) -> Vec<ChromeTraceEvent> {
+    let (mut nvtx, mut api, mut kernels) = (Vec::new(), Vec::new(), Vec::new());
+    for event in events {
+        match event.category() {
+            EventCategory::Nvtx => nvtx.push(event),
+            EventCategory::CudaApi => api.push(event),
+            EventCategory::Kernel => kernels.push(event),
+            _ => {}
+        }
+    }
+    let (mut linked, _, flows) = link_nvtx_to_kernels(&nvtx, &api, &kernels, options);
+    linked.extend(flows);
+    linked
+}
+
+/// Like [`link_trace_json`], reading and returning delimited protobuf streams
+pub fn link_events_pb(events_pb: &[u8], options: &ConversionOptions) -> Result<Vec<u8>> {
+    let events = import_events_pb(events_pb)?
+        .into_iter()
+        .filter(|event| event.ph == ChromeTracePhase::Complete);
+    let mut linked = Vec::new();
+    export_events_pb(&link_complete_events(events, options), &mut linked)?;
+    Ok(linked)
+}
+
+/// Convert an nsys SQLite export to a Chrome trace (gzip-compressed when
+/// `output` ends in `.gz`)
+///
+/// # Safety
+/// `input` and `output` must be NUL-terminated strings; `options_json` must
+/// be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nsys_chrome_convert(
+    input: *const c_char,
+    output: *const c_char,
+    options_json: *const c_char,
+) -> c_int {
+    guarded(|| {
+        let input = required(input, "input")?;
+        let output = required(output, "output")?;
+        let options = options_from_json(c_str(options_json, "options_json")?.unwrap_or(""))?;
+        if output.ends_with(".gz") {
+            convert_file_gz(input, output, Some(options))
+        } else {
+            convert_file(input, output, Some(options))
+        }
+    })
+}
+
+/// Link NVTX ranges to kernels in a Chrome trace held in memory
+///
+/// `events_json` is a trace (`{"traceEvents": [...]}` or a bare array) whose
+/// complete events with categories `nvtx`, `cuda_api` and `kernel` carry the
+/// args the converter writes (`start_ns`, `end_ns`, `deviceId`, `raw_tid`,
+/// `correlationId`). On success `*result` receives a JSON array of the
+/// nvtx-kernel and flow events, to be released with
+/// [`nsys_chrome_free_string`].
+///
+/// # Safety
+/// `events_json` must be a NUL-terminated string, `options_json` NULL or a
+/// NUL-terminated string, and `result` a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn nsys_chrome_link(
+    events_json: *const c_char,
+    options_json: *const c_char,
+    result: *mut *mut c_char,
+) -> c_int {
+    guarded(|| {
+        if result.is_null() {
+            return Err(ConvertError::InvalidOption("result is NULL".to_string()));
+        }
+        *result = std::ptr::null_mut();
+        let events_json = required(events_json, "events_json")?;
+        let options = options_from_json(c_str(options_json, "options_json")?.unwrap_or(""))?;
+        let linked = link_trace_json(events_json, &options)?;
+        // Serialized JSON escapes NUL, so this cannot fail
+        *result = CString::new(linked).unwrap_or_default().into_raw();
+        Ok(())
+    })
+}
+
+/// Link NVTX ranges to kernels in events passed as a protobuf stream
+///
+/// Like [`nsys_chrome_link`], but `events` holds `events_len` bytes of
+/// delimited `ChromeTraceEvent` messages (`proto/chrome_trace_event.proto`)
+/// and the result is a stream in the same format, `*result_len` bytes long,
+/// to be released with [`nsys_chrome_free_buffer`].
+///
+/// # Safety
+/// `events` must point to `events_len` readable bytes (or be NULL with a
+/// length of 0), `options_json` must be NULL or a NUL-terminated string, and
+/// `result` and `result_len` valid pointers to write to.
+#[no_mangle]
+pub unsafe extern "C" fn nsys_chrome_link_pb(
+    events: *const u8,
+    events_len: usize,
+    options_json: *const c_char,
+    result: *mut *mut u8,
+    result_len: *mut usize,
+) -> c_int {
+    guarded(|| {
+        if result.is_null() || result_len.is_null() {
+            return Err(ConvertError::InvalidOption("result is NULL".to_string()));
+        }
+        *result = std::ptr::null_mut();
+        *result_len = 0;
+        let events = match (events.is_null(), events_len) {
+            (true, 0) => &[][..],
+            (true, _) => return Err(ConvertError::InvalidOption("events is NULL".to_string())),
+            (false, len) => std::slice::from_raw_parts(events, len),
+        };
+        let options = options_from_json(c_str(options_json, "options_json")?.unwrap_or(""))?;
+        let linked = link_events_pb(events, &options)?.into_boxed_slice();
+        *result_len = linked.len();
+        *result = Box::into_raw(linked).cast::<u8>();
+        Ok(())
+    })
+}
+
+/// Release a buffer returned by [`nsys_chrome_link_pb`]
+///
+/// # Safety
+/// `buf` must be NULL or a pointer returned by this library together with
+/// its length `len`, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn nsys_chrome_free_buffer(buf: *mut u8, len: usize) {
+    if !buf.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(buf, len)));
+    }
+}
+
+/// Release a string returned by [`nsys_chrome_link`]
+///
+/// # Safety
+/// `s` must be NULL or a pointer returned by this library, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn nsys_chrome_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Message of the last failure on the calling thread, or NULL
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn nsys_chrome_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
@@ -0,0 +1,196 @@
+//! Live tail of an SQLite export that is still being written
+//!
+//! During long runs nsys can export periodically, rewriting or extending the
+//! same SQLite file. A [`TraceFollower`] re-converts the file whenever its
+//! size or modification time changes and hands on only the events it has not
+//! handed on before, so a [`TraceSink`] receives an ever-growing trace. Paired
+//! with a [`JsonArraySink`], the output can be opened mid-run to inspect
+//! progress.
+//!
+//! Events are recognized across scans by name, phase, start, track, category
+//! and flow ID. A range that grows between two scans (an NVTX range whose end
+//! was not yet exported, say) keeps the duration it had when first seen.
+//! Since earlier events are already written, each scan's new events are
+//! sorted among themselves only, and writer passes that need the whole trace
+//! (overlap fixing, outlines, analysis passes) do not run.
+//!
+//! [`JsonArraySink`]: crate::sink::JsonArraySink
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::cancel::CancellationToken;
+use crate::converter::{file_uri, NsysChromeConverter};
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ConversionOptions};
+use crate::sink::TraceSink;
+
+/// Time between two checks of the export for changes: 2 s
+pub const DEFAULT_FOLLOW_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Longest sleep between two cancellation checks while waiting
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often and for how long to follow an export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FollowOptions {
+    /// Time between two checks of the export for changes
+    pub interval: Duration,
+    /// Stop once no new events arrived for this long; `None` follows until
+    /// cancelled
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for FollowOptions {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_FOLLOW_INTERVAL,
+            idle_timeout: None,
+        }
+    }
+}
+
+/// Converter of an export that keeps growing
+pub struct TraceFollower {
+    sqlite_path: PathBuf,
+    options: ConversionOptions,
+    cancellation: Option<CancellationToken>,
+    /// Size and modification time of the export at the last scan
+    last_scanned: Option<(u64, SystemTime)>,
+    /// Keys of the events handed on so far
+    seen: HashSet<u64>,
+}
+
+/// Key recognizing an event across scans
+fn event_key(event: &ChromeTraceEvent) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    event.name.hash(&mut hasher);
+    format!("{:?}", event.ph).hash(&mut hasher);
+    event.ts.to_bits().hash(&mut hasher);
+    event.pid.as_str().hash(&mut hasher);
+    event.tid.as_str().hash(&mut hasher);
+    event.cat.as_str().hash(&mut hasher);
+    // Flow events differ only by ID
+    format!("{:?}", event.id).hash(&mut hasher);
+    hasher.finish()
+}
+
+impl TraceFollower {
+    pub fn new(sqlite_path: &str, options: ConversionOptions) -> Self {
+        Self {
+            sqlite_path: PathBuf::from(sqlite_path),
+            options,
+            cancellation: None,
+            last_scanned: None,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Stop following, and interrupt a running scan, once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Events handed on so far
+    pub fn events_seen(&self) -> usize {
+        self.seen.len()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Convert the export if it changed since the last scan, returning the
+    /// events not returned before, sorted
+    ///
+    /// Returns no events while the export is unchanged. Fails with
+    /// [`ConvertError::InputNotFound`] while the export does not exist yet,
+    /// and with whatever SQLite reports while it is half written; the next
+    /// poll simply tries again.
+    pub fn poll(&mut self) -> Result<Vec<ChromeTraceEvent>> {
+        let metadata = std::fs::metadata(&self.sqlite_path)
+            .map_err(|e| ConvertError::open_input(&self.sqlite_path, e))?;
+        let stamp = (metadata.len(), metadata.modified()?);
+        if self.last_scanned == Some(stamp) {
+            return Ok(Vec::new());
+        }
+
+        let events = self.scan()?;
+        self.last_scanned = Some(stamp);
+        let new_events: Vec<ChromeTraceEvent> = events
+            .into_iter()
+            .filter(|event| self.seen.insert(event_key(event)))
+            .collect();
+        Ok(NsysChromeConverter::sort_events(new_events))
+    }
+
+    /// Convert the export as it is now
+    fn scan(&self) -> Result<Vec<ChromeTraceEvent>> {
+        // Read-only but not immutable: the file is expected to change
+        let uri = format!("{}?mode=ro", file_uri(&self.sqlite_path));
+        let mut converter = NsysChromeConverter::from_uri(&uri, Some(self.options.clone()))?;
+        if let Some(token) = &self.cancellation {
+            converter = converter.with_cancellation(token.clone());
+        }
+        converter.convert()
+    }
+
+    /// Poll the export every `options.interval`, appending new events to
+    /// `sink` and flushing it after each scan
+    ///
+    /// Runs until cancelled or, with an idle timeout, until no new events
+    /// arrived for that long; either way the trace is finished and the
+    /// number of events written returned. Failed scans are logged and
+    /// retried. `on_events` is called with the number of events appended by
+    /// each scan that found any.
+    pub fn follow(
+        &mut self,
+        sink: &mut dyn TraceSink,
+        options: &FollowOptions,
+        mut on_events: impl FnMut(usize),
+    ) -> Result<usize> {
+        sink.begin()?;
+        let mut written = 0;
+        let mut last_new = Instant::now();
+        loop {
+            match self.poll() {
+                Ok(events) if !events.is_empty() => {
+                    for event in &events {
+                        sink.write_event(event)?;
+                    }
+                    sink.flush()?;
+                    written += events.len();
+                    last_new = Instant::now();
+                    on_events(events.len());
+                }
+                Ok(_) => {}
+                Err(_) if self.is_cancelled() => break,
+                Err(e) => log::warn!("Cannot convert {}: {}", self.sqlite_path.display(), e),
+            }
+
+            if options
+                .idle_timeout
+                .is_some_and(|timeout| last_new.elapsed() >= timeout)
+            {
+                break;
+            }
+            let wake = Instant::now() + options.interval;
+            while !self.is_cancelled() && Instant::now() < wake {
+                std::thread::sleep(
+                    CANCEL_CHECK_INTERVAL.min(wake.saturating_duration_since(Instant::now())),
+                );
+            }
+            if self.is_cancelled() {
+                break;
+            }
+        }
+        sink.finish()?;
+        Ok(written)
+    }
+}
@@ -0,0 +1,321 @@
+//! Input front-ends for profilers other than nsys
+//!
+//! A front-end reads another profiler's output and builds kernel, API and
+//! annotation events with the same args the nsys parsers emit (`start_ns`,
+//! `end_ns`, `deviceId`, `raw_pid`, `raw_tid`, `correlationId`, ...). Categories
+//! follow the internal model too: API calls use `cuda_api` and annotations use
+//! `nvtx`, whatever the vendor. `assemble_trace` then applies the usual option
+//! handling and nvtx-kernel linking, so the linker and writers work unchanged.
+
+pub mod nsys_stats;
+pub mod rocprof;
+pub mod unitrace;
+
+pub use nsys_stats::NsysStatsReader;
+pub use rocprof::RocprofReader;
+pub use unitrace::UnitraceReader;
+
+use log::warn;
+use regex::Regex;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::analysis::{
+    apply_time_origin, apply_time_window, color_api_thread_states, device_activity,
+    device_activity_events, filter_short_kernels, flag_kernel_outliers, infer_layer_ranges,
+    synthesize_step_markers, THROTTLE_CATEGORY,
+};
+use crate::category::EventCategory;
+use crate::converter::{process_nvtx_kernel_linking, NsysChromeConverter};
+use crate::dropped::DROPPED_CATEGORY;
+use crate::effective_config::config_metadata_event;
+use crate::error::Result;
+use crate::linker::{apply_flow_options, link_copies_to_api_calls};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions, EventId};
+use crate::parsers::nvtx::{collapse_nested_ranges, NvtxNameFilter};
+
+/// Events read by a front-end, in the internal model
+#[derive(Debug, Default)]
+pub struct FrontendTrace {
+    /// GPU kernel executions (activity type "kernel")
+    pub kernel_events: Vec<ChromeTraceEvent>,
+    /// Host API calls that launch GPU work (activity type "cuda-api")
+    pub api_events: Vec<ChromeTraceEvent>,
+    /// User annotation ranges (activity type "nvtx")
+    pub annotation_events: Vec<ChromeTraceEvent>,
+    /// Events passed through unlinked: OS runtime, scheduling, WDDM, GPU idle
+    /// gaps, NVLink transfers and track-name metadata; copies are only linked
+    /// to the API calls issuing them
+    pub other_events: Vec<ChromeTraceEvent>,
+}
+
+impl FrontendTrace {
+    /// Give every event without an [`EventId`] the next one in sequence
+    ///
+    /// Numbering continues after the largest ID already assigned, so events
+    /// added by a later pass never share an ID with earlier ones. A copy
+    /// sharing an earlier event's ID is renumbered too, so no two events link
+    /// under the same key. Returns the number of events numbered.
+    pub fn assign_event_ids(&mut self) -> usize {
+        let events = || {
+            self.kernel_events
+                .iter()
+                .chain(&self.api_events)
+                .chain(&self.annotation_events)
+                .chain(&self.other_events)
+        };
+        let first = events()
+            .filter_map(|e| e.event_id)
+            .map(|id| id.0 + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut seen = HashSet::new();
+        let unassigned = self
+            .kernel_events
+            .iter_mut()
+            .chain(&mut self.api_events)
+            .chain(&mut self.annotation_events)
+            .chain(&mut self.other_events)
+            .filter(|e| !e.event_id.is_some_and(|id| seen.insert(id)));
+        let mut assigned = 0;
+        for (id, event) in (first..).zip(unassigned) {
+            event.event_id = Some(EventId(id));
+            assigned += 1;
+        }
+        assigned
+    }
+}
+
+/// Map each process to the device its first kernel ran on
+///
+/// Plays the role of nsys' PID-to-device mapping for API and annotation events.
+fn process_device_map(kernel_events: &[ChromeTraceEvent]) -> HashMap<i64, i64> {
+    let mut device_map = HashMap::new();
+    let mut kernels: Vec<&ChromeTraceEvent> = kernel_events.iter().collect();
+    kernels.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap_or(std::cmp::Ordering::Equal));
+    for kernel in kernels {
+        let pid = kernel.args.get("raw_pid").and_then(|v| v.as_i64());
+        let device_id = kernel.args.get("deviceId").and_then(|v| v.as_i64());
+        if let (Some(pid), Some(device_id)) = (pid, device_id) {
+            device_map.entry(pid).or_insert(device_id);
+        }
+    }
+    device_map
+}
+
+/// Place API and annotation events on the device of their process (by `raw_pid`)
+pub(crate) fn assign_host_devices(trace: &mut FrontendTrace) {
+    let device_map = process_device_map(&trace.kernel_events);
+    for event in trace
+        .api_events
+        .iter_mut()
+        .chain(trace.annotation_events.iter_mut())
+    {
+        let pid = event
+            .args
+            .get("raw_pid")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0);
+        let device_id = device_map.get(&pid).copied().unwrap_or(0);
+        event.args.insert("deviceId".into(), json!(device_id));
+        event.pid = format!("Device {}", device_id).into();
+    }
+}
+
+/// Filter, link and sort front-end events into a finished trace
+///
+/// Honors `activity_types`, `nvtx_event_prefix`, `nvtx_color_scheme`, `nvtx_nesting`,
+/// `include_metadata`, `synthesize_steps`, `infer_layers`, `min_kernel_duration_ns`,
+/// `api_thread_states`, `kernel_outlier_factor`, `time_window`, `time_origin` and `flows`
+/// the same way the nsys converter does. Fails only if windowed linking was
+/// requested and cannot run.
+pub fn assemble_trace(
+    mut trace: FrontendTrace,
+    options: &ConversionOptions,
+) -> Result<Vec<ChromeTraceEvent>> {
+    trace.assign_event_ids();
+    let wants = |activity: EventCategory| options.activity_types.contains(&activity);
+    let has_annotations = !trace.annotation_events.is_empty();
+
+    let mut kernel_events = if wants(EventCategory::Kernel) || wants(EventCategory::NvtxKernel) {
+        trace.kernel_events
+    } else {
+        Vec::new()
+    };
+    let mut api_events = if wants(EventCategory::CudaApi) || wants(EventCategory::NvtxKernel) {
+        trace.api_events
+    } else {
+        Vec::new()
+    };
+    let mut nvtx_events = if wants(EventCategory::Nvtx) || wants(EventCategory::NvtxKernel) {
+        filter_annotations(trace.annotation_events, options)
+    } else {
+        Vec::new()
+    };
+
+    if options.api_thread_states {
+        color_api_thread_states(&mut api_events);
+    }
+    if let Some(factor) = options.kernel_outlier_factor {
+        flag_kernel_outliers(&mut kernel_events, factor);
+    }
+
+    let mut events = Vec::new();
+    let mut other_events: Vec<ChromeTraceEvent> = trace
+        .other_events
+        .into_iter()
+        .filter(|event| match event.ph {
+            ChromeTracePhase::Metadata => options.include_metadata,
+            // GPU idle gaps are derived from WDDM packets
+            _ if &*event.cat == "gap" => wants(EventCategory::Wddm),
+            // Throttling ranges are derived from GPU clock samples
+            _ if &*event.cat == THROTTLE_CATEGORY => wants(EventCategory::GpuMetrics),
+            // Dropped-data markers explain gaps in every activity
+            _ if &*event.cat == DROPPED_CATEGORY => true,
+            _ => options.activity_types.iter().any(|t| event.cat == *t),
+        })
+        .collect();
+
+    events.extend(link_copies_to_api_calls(&mut other_events, &api_events, &kernel_events));
+
+    if wants(EventCategory::NvtxKernel) {
+        let (linked_events, remaining_nvtx, _, _) =
+            process_nvtx_kernel_linking(&kernel_events, &api_events, nvtx_events, options)?;
+        events.extend(linked_events);
+        nvtx_events = remaining_nvtx;
+    }
+
+    if options.synthesize_steps && !has_annotations {
+        events.extend(synthesize_step_markers(&kernel_events));
+    }
+    if options.infer_layers && !has_annotations {
+        events.extend(infer_layer_ranges(&kernel_events));
+    }
+
+    if options.include_metadata {
+        // Keep process names that came with the trace
+        let named: BTreeSet<&str> = other_events
+            .iter()
+            .filter(|e| e.ph == ChromeTracePhase::Metadata && e.name == "process_name")
+            .map(|e| &*e.pid)
+            .collect();
+        events.extend(
+            device_metadata_events(&kernel_events)
+                .into_iter()
+                .filter(|e| !named.contains(&*e.pid)),
+        );
+        events.extend(device_activity_events(&device_activity(&kernel_events)));
+    }
+    if wants(EventCategory::Kernel) {
+        events.extend(kernel_events);
+    }
+    if wants(EventCategory::CudaApi) {
+        events.extend(api_events);
+    }
+    if wants(EventCategory::Nvtx) {
+        events.extend(nvtx_events);
+    }
+    events.extend(other_events);
+
+    if options.min_kernel_duration_ns > 0 {
+        events = filter_short_kernels(events, options.min_kernel_duration_ns).0;
+    }
+    if let Some(window) = options.time_window {
+        events = apply_time_window(events, window).0;
+    }
+    events = apply_flow_options(events, &options.flows);
+    if options.include_metadata {
+        events.extend(config_metadata_event(&events, options));
+    }
+    apply_time_origin(&mut events, &options.time_origin);
+
+    Ok(NsysChromeConverter::sort_events(events))
+}
+
+/// Annotation name without the `"{domain}: "` prefix added by `nvtx_domain_prefix`
+fn undecorated_name(event: &ChromeTraceEvent) -> &str {
+    event
+        .args
+        .get("domain")
+        .and_then(|v| v.as_str())
+        .and_then(|domain| event.name.strip_prefix(domain))
+        .and_then(|rest| rest.strip_prefix(": "))
+        .unwrap_or(&event.name)
+}
+
+/// NVTX name filter of `options`; invalid patterns are rejected on the command
+/// line, so here they are skipped like colors
+fn nvtx_name_filter(options: &ConversionOptions) -> Option<NvtxNameFilter> {
+    NvtxNameFilter::new(&options.nvtx_event_prefix).unwrap_or_else(|e| {
+        warn!("Ignoring NVTX filter: {}", e);
+        None
+    })
+}
+
+/// Drop NVTX marks and their payload counters whose name the NVTX filter rejects
+pub(crate) fn filter_marks(events: &mut Vec<ChromeTraceEvent>, options: &ConversionOptions) {
+    let Some(name_filter) = nvtx_name_filter(options) else {
+        return;
+    };
+    events.retain(|event| {
+        !matches!(&*event.cat, "nvtx-mark" | "nvtx-counter") || name_filter.matches(&event.name)
+    });
+}
+
+/// Apply NVTX prefix filtering and color scheme to annotation events
+pub(crate) fn filter_annotations(
+    events: Vec<ChromeTraceEvent>,
+    options: &ConversionOptions,
+) -> Vec<ChromeTraceEvent> {
+    let color_patterns: Vec<(Regex, &String)> = options
+        .nvtx_color_scheme
+        .iter()
+        .filter_map(|(pattern, color)| Regex::new(pattern).ok().map(|re| (re, color)))
+        .collect();
+
+    let name_filter = nvtx_name_filter(options);
+
+    let mut events: Vec<ChromeTraceEvent> = events
+        .into_iter()
+        .filter(|event| {
+            name_filter
+                .as_ref()
+                .is_none_or(|f| f.matches(undecorated_name(event)))
+        })
+        .map(|event| {
+            match color_patterns
+                .iter()
+                .find(|(re, _)| re.is_match(undecorated_name(&event)))
+            {
+                Some((_, color)) => event.with_color((*color).clone()),
+                None => event,
+            }
+        })
+        .collect();
+    // A cache extracted with other options may still hold the nested ranges
+    collapse_nested_ranges(&mut events, options.nvtx_nesting);
+    events
+}
+
+/// Build `process_name` metadata for each device that ran kernels
+fn device_metadata_events(kernel_events: &[ChromeTraceEvent]) -> Vec<ChromeTraceEvent> {
+    let devices: BTreeSet<i64> = kernel_events
+        .iter()
+        .filter_map(|e| e.args.get("deviceId").and_then(|v| v.as_i64()))
+        .collect();
+
+    devices
+        .into_iter()
+        .map(|device_id| {
+            let mut args = HashMap::default();
+            args.insert("name".to_string(), json!(format!("Device {}", device_id)));
+            ChromeTraceEvent::metadata(
+                "process_name".to_string(),
+                format!("Device {}", device_id),
+                String::new(),
+                args,
+            )
+        })
+        .collect()
+}
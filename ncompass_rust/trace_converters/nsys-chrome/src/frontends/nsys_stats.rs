@@ -0,0 +1,333 @@
+//! `nsys stats` CSV front-end
+//!
+//! Reads the summary reports `nsys stats --format csv` writes when only they,
+//! and not the SQLite export, are at hand: `cuda_gpu_kern_sum` (kernels) and
+//! `cuda_api_sum` (CUDA API calls). Both older (`gpukernsum`, `cudaapisum`) and
+//! current report names and column headers are accepted.
+//!
+//! The reports hold one row per name, so the trace is synthetic: each name
+//! becomes one aggregate bar as long as its total time, laid end to end from
+//! time zero in decreasing total time on a summary track per report. The row's
+//! statistics travel as args (`instances`, `avg_ns`, `min_ns`, ...), which
+//! [`duration_stats`](crate::routing::duration_stats) reads back so stats
+//! routes report the original counts rather than one event per name. The
+//! bars carry no correlation IDs, so nothing is linked.
+
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::frontends::FrontendTrace;
+use crate::models::{ns_to_us, ChromeTraceEvent};
+
+/// Arg holding the number of calls or launches an aggregate bar stands for
+pub const INSTANCES_ARG: &str = "instances";
+
+/// Track of the kernel summary bars
+pub const KERNEL_SUMMARY_TRACK: &str = "Kernel Summary";
+
+/// Track of the CUDA API summary bars
+pub const API_SUMMARY_TRACK: &str = "CUDA API Summary";
+
+/// Report an `nsys stats` CSV holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsReportKind {
+    /// `cuda_gpu_kern_sum` / `gpukernsum`
+    Kernel,
+    /// `cuda_api_sum` / `cudaapisum`
+    Api,
+}
+
+impl StatsReportKind {
+    /// Identify a report from its file name
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name.contains("gpu_kern_sum") || name.contains("gpukernsum") {
+            Some(StatsReportKind::Kernel)
+        } else if name.contains("cuda_api_sum") || name.contains("cudaapisum") {
+            Some(StatsReportKind::Api)
+        } else {
+            None
+        }
+    }
+
+    /// Identify a report from its header: kernels are counted as instances,
+    /// API calls as calls
+    fn from_header(header: &[String]) -> Option<Self> {
+        if header.iter().any(|h| h == "Instances") {
+            Some(StatsReportKind::Kernel)
+        } else if header.iter().any(|h| h == "Num Calls" || h == "Calls") {
+            Some(StatsReportKind::Api)
+        } else {
+            None
+        }
+    }
+}
+
+/// One row of a summary report
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsRow {
+    pub name: String,
+    pub total_ns: i64,
+    pub instances: i64,
+    pub time_percent: Option<f64>,
+    pub avg_ns: Option<f64>,
+    pub med_ns: Option<f64>,
+    pub min_ns: Option<f64>,
+    pub max_ns: Option<f64>,
+    pub stddev_ns: Option<f64>,
+}
+
+/// Split CSV text into records, honoring quoted fields
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Whether a record is a summary report header
+fn is_header(record: &[String]) -> bool {
+    record.iter().any(|h| h == "Name") && record.iter().any(|h| h.starts_with("Total Time"))
+}
+
+/// Parse the rows of a summary report
+///
+/// Lines before the header (`nsys stats` status output) are skipped. `kind`
+/// is taken from the header when not known from the file name.
+pub fn parse_stats_csv(
+    text: &str,
+    kind: Option<StatsReportKind>,
+) -> Result<(StatsReportKind, Vec<StatsRow>)> {
+    let records = parse_csv(text);
+    let Some(header_idx) = records.iter().position(|r| is_header(r)) else {
+        return Err(ConvertError::InvalidInput(
+            "Not an nsys stats report: missing 'Name' and 'Total Time' columns".to_string(),
+        ));
+    };
+    let header: Vec<String> = records[header_idx]
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+    let Some(kind) = kind.or_else(|| StatsReportKind::from_header(&header)) else {
+        return Err(ConvertError::InvalidInput(
+            "Unsupported nsys stats report (expected cuda_gpu_kern_sum or cuda_api_sum)"
+                .to_string(),
+        ));
+    };
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let name_col = column(&["Name"]);
+    let total_col = column(&["Total Time (ns)", "Total Time"]);
+    let count_col = column(&["Instances", "Num Calls", "Calls"]);
+    let (Some(name_col), Some(total_col), Some(count_col)) = (name_col, total_col, count_col)
+    else {
+        return Err(ConvertError::InvalidInput(
+            "nsys stats report is missing the name, total time or count column".to_string(),
+        ));
+    };
+    let percent_col = column(&["Time (%)", "Time(%)"]);
+    let avg_col = column(&["Avg (ns)", "Average"]);
+    let med_col = column(&["Med (ns)", "Median"]);
+    let min_col = column(&["Min (ns)", "Minimum"]);
+    let max_col = column(&["Max (ns)", "Maximum"]);
+    let stddev_col = column(&["StdDev (ns)", "StdDev"]);
+
+    let mut rows = Vec::new();
+    for (line, record) in records.iter().enumerate().skip(header_idx + 1) {
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let number = |col: Option<usize>| -> Option<f64> {
+            record.get(col?)?.trim().replace(',', "").parse().ok()
+        };
+        let (Some(total), Some(instances), Some(name)) = (
+            number(Some(total_col)),
+            number(Some(count_col)),
+            record.get(name_col),
+        ) else {
+            return Err(ConvertError::InvalidInput(format!(
+                "Malformed nsys stats row on line {}",
+                line + 1
+            )));
+        };
+        rows.push(StatsRow {
+            name: name.clone(),
+            total_ns: total.round() as i64,
+            instances: instances.round() as i64,
+            time_percent: number(percent_col),
+            avg_ns: number(avg_col),
+            med_ns: number(med_col),
+            min_ns: number(min_col),
+            max_ns: number(max_col),
+            stddev_ns: number(stddev_col),
+        });
+    }
+    Ok((kind, rows))
+}
+
+/// Check whether a path holds `nsys stats` CSV reports
+///
+/// A `.csv` file qualifies when its header has the summary columns, a
+/// directory when it holds a kernel or API summary report.
+pub fn is_nsys_stats_csv(path: &Path) -> bool {
+    if path.is_dir() {
+        return fs::read_dir(path).is_ok_and(|entries| {
+            entries.flatten().any(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                name.ends_with(".csv") && StatsReportKind::from_file_name(&name).is_some()
+            })
+        });
+    }
+    path.extension().is_some_and(|ext| ext == "csv")
+        && fs::read_to_string(path).is_ok_and(|text| parse_csv(&text).iter().any(|r| is_header(r)))
+}
+
+/// Reader for `nsys stats` summary CSVs
+#[derive(Debug, Default)]
+pub struct NsysStatsReader {
+    reports: Vec<(StatsReportKind, Vec<StatsRow>)>,
+}
+
+impl NsysStatsReader {
+    /// Load a report file, or every kernel and API summary report in a directory
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = Self::default();
+        if path.is_dir() {
+            let mut files: Vec<_> = fs::read_dir(path)
+                .map_err(|e| ConvertError::open_input(path, e))?
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "csv"))
+                .collect();
+            files.sort();
+            for file in files {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                match StatsReportKind::from_file_name(&name) {
+                    Some(kind) => reader.add_file(&file, Some(kind))?,
+                    None => log::debug!("Skipping {}: not a kernel or API summary", name),
+                }
+            }
+            if reader.reports.is_empty() {
+                return Err(ConvertError::InvalidInput(format!(
+                    "No cuda_gpu_kern_sum or cuda_api_sum CSV in {}",
+                    path.display()
+                )));
+            }
+        } else {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            reader.add_file(path, StatsReportKind::from_file_name(&name))?;
+        }
+        Ok(reader)
+    }
+
+    fn add_file(&mut self, path: &Path, kind: Option<StatsReportKind>) -> Result<()> {
+        let text = fs::read_to_string(path).map_err(|e| ConvertError::open_input(path, e))?;
+        self.add_csv(&text, kind)
+            .map_err(|e| ConvertError::InvalidInput(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Add a report from CSV text
+    pub fn add_csv(&mut self, text: &str, kind: Option<StatsReportKind>) -> Result<()> {
+        self.reports.push(parse_stats_csv(text, kind)?);
+        Ok(())
+    }
+
+    /// Convert the reports into aggregate bars, one per name
+    pub fn read(&self) -> Result<FrontendTrace> {
+        let mut trace = FrontendTrace::default();
+        let mut tracks = Vec::new();
+        for (kind, rows) in &self.reports {
+            let (cat, track, events) = match kind {
+                StatsReportKind::Kernel => {
+                    (EventCategory::Kernel, KERNEL_SUMMARY_TRACK, &mut trace.kernel_events)
+                }
+                StatsReportKind::Api => {
+                    (EventCategory::CudaApi, API_SUMMARY_TRACK, &mut trace.api_events)
+                }
+            };
+            if !tracks.contains(&track) {
+                tracks.push(track);
+            }
+            let mut rows: Vec<&StatsRow> = rows.iter().collect();
+            rows.sort_by(|a, b| {
+                b.total_ns
+                    .cmp(&a.total_ns)
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+
+            // Continue after the bars of an earlier report of the same kind
+            let mut start_ns = events
+                .iter()
+                .filter_map(|e| e.args.get("end_ns").and_then(|v| v.as_i64()))
+                .max()
+                .unwrap_or(0);
+            for row in rows {
+                let end_ns = start_ns + row.total_ns;
+                let mut event = ChromeTraceEvent::complete(
+                    row.name.clone(),
+                    ns_to_us(start_ns),
+                    ns_to_us(row.total_ns),
+                    "Device 0".to_string(),
+                    track.to_string(),
+                    cat.clone(),
+                )
+                .with_arg("start_ns", start_ns)
+                .with_arg("end_ns", end_ns)
+                .with_arg("deviceId", 0)
+                .with_arg("raw_pid", 0)
+                .with_arg(INSTANCES_ARG, row.instances);
+                let optional = [
+                    ("time_percent", row.time_percent),
+                    ("avg_ns", row.avg_ns),
+                    ("med_ns", row.med_ns),
+                    ("min_ns", row.min_ns),
+                    ("max_ns", row.max_ns),
+                    ("stddev_ns", row.stddev_ns),
+                ];
+                for (key, value) in optional {
+                    if let Some(value) = value {
+                        event = event.with_arg(key, value);
+                    }
+                }
+                events.push(event);
+                start_ns = end_ns;
+            }
+        }
+
+        for track in tracks {
+            trace.other_events.push(ChromeTraceEvent::metadata(
+                "thread_name".to_string(),
+                "Device 0".to_string(),
+                track.to_string(),
+                HashMap::from([("name".to_string(), json!(track))]),
+            ));
+        }
+        Ok(trace)
+    }
+}
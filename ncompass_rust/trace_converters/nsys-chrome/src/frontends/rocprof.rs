@@ -0,0 +1,394 @@
+//! rocprofiler-sdk (rocprofv3) JSON front-end for AMD GPUs
+//!
+//! Reads the `--output-format json` results file. Each entry of the top-level
+//! `rocprofiler-sdk-tool` array covers one profiled process:
+//! - `agents` lists CPU and GPU agents; GPUs become `Device N` using their
+//!   `logical_node_type_id` (or their order among GPU agents).
+//! - `kernel_symbols` names kernels by `kernel_id`.
+//! - `buffer_records.kernel_dispatch`, `.hip_api` and `.marker_api` hold kernel,
+//!   HIP API and ROCTX range records with `start_timestamp`/`end_timestamp` in ns.
+//! - `strings.buffer_records` names API operations per tracing kind and
+//!   `strings.marker_api` maps correlation IDs to ROCTX messages.
+//!
+//! Kernels and HIP calls share `correlation_id.internal`, which becomes
+//! `correlationId`. Each HSA queue becomes a stream, numbered in first-seen order.
+//!
+//! rocprof's CSV traces (`kernel_trace.csv`, `hip_api_trace.csv`, v2
+//! `results.csv`) and rocpd SQLite databases are not read; they are
+//! recognized and rejected with an error naming the format (see
+//! [`unsupported_rocprof_format`]).
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::category::EventCategory;
+use crate::demangle::DemangleCache;
+use crate::error::{ConvertError, Result};
+use crate::frontends::{assign_host_devices, FrontendTrace};
+use crate::models::{ns_to_us, ChromeTraceEvent};
+
+/// Top-level key identifying rocprofv3 JSON output
+pub const ROCPROF_TOOL_KEY: &str = "rocprofiler-sdk-tool";
+
+/// rocprofiler agent type value for GPUs
+const AGENT_TYPE_GPU: i64 = 2;
+
+/// Tracing kind used to name HIP API records that carry a numeric kind
+const HIP_RUNTIME_API_KIND: &str = "HIP_RUNTIME_API";
+
+/// Check whether a file looks like rocprofv3 JSON output
+///
+/// Only the first few KiB are read, so this is cheap for large traces.
+pub fn is_rocprof_json(path: &Path) -> bool {
+    let mut head = vec![0u8; 4096];
+    let read = File::open(path).and_then(|mut f| f.read(&mut head));
+    match read {
+        Ok(n) => String::from_utf8_lossy(&head[..n]).contains(ROCPROF_TOOL_KEY),
+        Err(_) => false,
+    }
+}
+
+/// Leading bytes of every SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Name of a rocprof output format this front-end cannot read, if `path` is one
+///
+/// Recognizes rocprof CSV traces by their `Start_Timestamp` and kernel-name or
+/// correlation columns, and rocpd SQLite databases by their `rocpd_` tables.
+pub fn unsupported_rocprof_format(path: &Path) -> Option<&'static str> {
+    let mut head = vec![0u8; 4096];
+    let n = File::open(path).and_then(|mut f| f.read(&mut head)).ok()?;
+    let head = &head[..n];
+    if head.starts_with(SQLITE_HEADER) {
+        let conn =
+            rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .ok()?;
+        let rocpd = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE name LIKE 'rocpd%' LIMIT 1",
+                [],
+                |_| Ok(()),
+            )
+            .is_ok();
+        return rocpd.then_some("rocpd SQLite database");
+    }
+    let header = String::from_utf8_lossy(head);
+    let header = header.lines().next().unwrap_or("");
+    let rocprof_csv = header.contains("Start_Timestamp")
+        && (header.contains("Kernel_Name") || header.contains("Correlation_I"));
+    rocprof_csv.then_some("rocprof CSV trace")
+}
+
+/// Read an integer that rocprofiler may wrap as `{"handle": N}`
+fn handle(value: Option<&Value>) -> Option<i64> {
+    let value = value?;
+    value
+        .as_i64()
+        .or_else(|| value.get("handle").and_then(|v| v.as_i64()))
+}
+
+fn field_i64(record: &Value, key: &str) -> Option<i64> {
+    record.get(key).and_then(|v| v.as_i64())
+}
+
+/// Internal correlation ID of a record
+fn correlation_id(record: &Value) -> Option<i64> {
+    let value = record.get("correlation_id")?;
+    value
+        .as_i64()
+        .or_else(|| value.get("internal").and_then(|v| v.as_i64()))
+}
+
+fn records<'a>(process: &'a Value, kind: &str) -> &'a [Value] {
+    process
+        .get("buffer_records")
+        .and_then(|b| b.get(kind))
+        .and_then(|v| v.as_array())
+        .map(|v| v.as_slice())
+        .unwrap_or(&[])
+}
+
+/// Reader for rocprofv3 JSON output
+pub struct RocprofReader {
+    root: Value,
+}
+
+impl RocprofReader {
+    /// Load a rocprofv3 JSON results file
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(format) = unsupported_rocprof_format(path) {
+            return Err(ConvertError::InvalidInput(format!(
+                "Unsupported rocprof format: {} is a {}; re-run rocprofv3 with \
+                 --output-format json",
+                path.display(),
+                format
+            )));
+        }
+        let file = File::open(path).map_err(|e| ConvertError::open_input(path, e))?;
+        let root: Value = serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            ConvertError::InvalidInput(format!(
+                "Failed to parse rocprof JSON {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_value(root)
+    }
+
+    /// Wrap already-parsed rocprofv3 JSON
+    pub fn from_value(root: Value) -> Result<Self> {
+        if !root.get(ROCPROF_TOOL_KEY).is_some_and(|v| v.is_array()) {
+            return Err(ConvertError::InvalidInput(format!(
+                "Not rocprofv3 JSON output: missing '{}' array",
+                ROCPROF_TOOL_KEY
+            )));
+        }
+        Ok(Self { root })
+    }
+
+    /// Convert all processes into internal-model events
+    pub fn read(&self) -> Result<FrontendTrace> {
+        let mut trace = FrontendTrace::default();
+        let mut streams: HashMap<i64, i64> = HashMap::new();
+
+        let processes = self.root[ROCPROF_TOOL_KEY]
+            .as_array()
+            .map(|v| v.as_slice())
+            .unwrap_or(&[]);
+        for process in processes {
+            let pid = process
+                .get("metadata")
+                .and_then(|m| field_i64(m, "pid"))
+                .unwrap_or(0);
+            let devices = gpu_devices(process);
+            let kernel_names = kernel_names(process);
+
+            for record in records(process, "kernel_dispatch") {
+                if let Some(event) =
+                    kernel_event(record, pid, &devices, &kernel_names, &mut streams)
+                {
+                    trace.kernel_events.push(event);
+                }
+            }
+
+            let operations = operation_names(process);
+            for record in records(process, "hip_api") {
+                let name = api_name(record, &operations);
+                if let Some(event) = host_event(record, pid, name, "HIP API Thread", EventCategory::CudaApi) {
+                    trace.api_events.push(event);
+                }
+            }
+
+            let messages = marker_messages(process);
+            for record in records(process, "marker_api") {
+                let name = correlation_id(record)
+                    .and_then(|id| messages.get(&id).cloned())
+                    .unwrap_or_else(|| "[No name]".to_string());
+                if let Some(mut event) = host_event(record, pid, name, "ROCTX Thread", EventCategory::Nvtx) {
+                    event.args.remove("correlationId");
+                    trace.annotation_events.push(event);
+                }
+            }
+        }
+
+        assign_host_devices(&mut trace);
+        Ok(trace)
+    }
+}
+
+/// Map GPU agent handles to device indices
+fn gpu_devices(process: &Value) -> HashMap<i64, i64> {
+    let agents = process.get("agents").and_then(|v| v.as_array());
+    let mut devices = HashMap::new();
+    let mut next_index = 0;
+    for agent in agents.into_iter().flatten() {
+        if field_i64(agent, "type") != Some(AGENT_TYPE_GPU) {
+            continue;
+        }
+        let Some(agent_handle) = handle(agent.get("id")) else {
+            continue;
+        };
+        let index = field_i64(agent, "logical_node_type_id").unwrap_or(next_index);
+        devices.insert(agent_handle, index);
+        next_index += 1;
+    }
+    devices
+}
+
+/// Kernel names by kernel ID, preferring the truncated (short) name
+///
+/// Symbols only available mangled are demangled.
+fn kernel_names(process: &Value) -> HashMap<i64, String> {
+    let symbols: Vec<&Value> = match process.get("kernel_symbols") {
+        Some(Value::Array(items)) => items.iter().collect(),
+        Some(Value::Object(items)) => items.values().collect(),
+        _ => Vec::new(),
+    };
+
+    symbols
+        .into_iter()
+        .filter_map(|symbol| {
+            let id = field_i64(symbol, "kernel_id")?;
+            let name = [
+                "truncated_kernel_name",
+                "formatted_kernel_name",
+                "kernel_name",
+            ]
+            .iter()
+            .filter_map(|key| symbol.get(*key).and_then(|v| v.as_str()))
+            .find(|name| !name.is_empty())?;
+            Some((id, DemangleCache::global().get(name).to_string()))
+        })
+        .collect()
+}
+
+/// Operation names per tracing kind from `strings.buffer_records`
+fn operation_names(process: &Value) -> HashMap<String, Vec<String>> {
+    let entries = process
+        .get("strings")
+        .and_then(|s| s.get("buffer_records"))
+        .and_then(|v| v.as_array());
+
+    entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let kind = entry.get("kind")?.as_str()?.to_string();
+            let operations = entry
+                .get("operations")?
+                .as_array()?
+                .iter()
+                .map(|op| op.as_str().unwrap_or_default().to_string())
+                .collect();
+            Some((kind, operations))
+        })
+        .collect()
+}
+
+/// ROCTX messages by correlation ID from `strings.marker_api`
+fn marker_messages(process: &Value) -> HashMap<i64, String> {
+    let entries = process
+        .get("strings")
+        .and_then(|s| s.get("marker_api"))
+        .and_then(|v| v.as_array());
+
+    entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let id = field_i64(entry, "key")?;
+            let message = entry.get("value")?.as_str()?;
+            Some((id, message.to_string()))
+        })
+        .collect()
+}
+
+fn api_name(record: &Value, operations: &HashMap<String, Vec<String>>) -> String {
+    let kind = record
+        .get("kind")
+        .and_then(|v| v.as_str())
+        .unwrap_or(HIP_RUNTIME_API_KIND);
+    let operation = field_i64(record, "operation").unwrap_or(-1);
+    operations
+        .get(kind)
+        .and_then(|ops| ops.get(usize::try_from(operation).ok()?))
+        .filter(|name| !name.is_empty())
+        .cloned()
+        .unwrap_or_else(|| format!("HIP API {}", operation))
+}
+
+fn kernel_event(
+    record: &Value,
+    pid: i64,
+    devices: &HashMap<i64, i64>,
+    kernel_names: &HashMap<i64, String>,
+    streams: &mut HashMap<i64, i64>,
+) -> Option<ChromeTraceEvent> {
+    let start = field_i64(record, "start_timestamp")?;
+    let end = field_i64(record, "end_timestamp")?;
+    let info = record.get("dispatch_info")?;
+    let agent = handle(info.get("agent_id"))?;
+    let device_id = devices.get(&agent).copied().unwrap_or(agent);
+    let queue = handle(info.get("queue_id")).unwrap_or(0);
+    let next_stream = streams.len() as i64;
+    let stream_id = *streams.entry(queue).or_insert(next_stream);
+    let kernel_id = field_i64(info, "kernel_id").unwrap_or(-1);
+    let name = kernel_names
+        .get(&kernel_id)
+        .cloned()
+        .unwrap_or_else(|| "Unknown Kernel".to_string());
+
+    let dims = |key: &str| -> [i64; 3] {
+        let dim = info.get(key);
+        let get = |axis: &str| dim.and_then(|d| field_i64(d, axis)).unwrap_or(1).max(1);
+        [get("x"), get("y"), get("z")]
+    };
+    let block = dims("workgroup_size");
+    // HSA grid sizes count work-items; the internal model counts blocks
+    let grid_items = dims("grid_size");
+    let grid: Vec<i64> = (0..3)
+        .map(|i| (grid_items[i] + block[i] - 1) / block[i])
+        .collect();
+
+    let mut args = HashMap::default();
+    args.insert("grid".to_string(), json!(grid));
+    args.insert("block".to_string(), json!(block));
+    if let Some(id) = correlation_id(record) {
+        args.insert("correlationId".to_string(), json!(id));
+    }
+    args.insert("deviceId".to_string(), json!(device_id));
+    args.insert("streamId".to_string(), json!(stream_id));
+    args.insert("raw_pid".to_string(), json!(pid));
+    args.insert("start_ns".to_string(), json!(start));
+    args.insert("end_ns".to_string(), json!(end));
+
+    Some(
+        ChromeTraceEvent::complete(
+            name,
+            ns_to_us(start),
+            ns_to_us(end - start),
+            format!("Device {}", device_id),
+            format!("Stream {}", stream_id),
+            EventCategory::Kernel,
+        )
+        .with_args(args),
+    )
+}
+
+/// Build a host-side (API or marker) event; the device is filled in later
+fn host_event(
+    record: &Value,
+    pid: i64,
+    name: String,
+    track_prefix: &str,
+    category: EventCategory,
+) -> Option<ChromeTraceEvent> {
+    let start = field_i64(record, "start_timestamp")?;
+    let end = field_i64(record, "end_timestamp")?;
+    let tid = field_i64(record, "thread_id").unwrap_or(0);
+
+    let mut args = HashMap::default();
+    if let Some(id) = correlation_id(record) {
+        args.insert("correlationId".to_string(), json!(id));
+    }
+    args.insert("raw_pid".to_string(), json!(pid));
+    args.insert("raw_tid".to_string(), json!(tid));
+    args.insert("start_ns".to_string(), json!(start));
+    args.insert("end_ns".to_string(), json!(end));
+
+    Some(
+        ChromeTraceEvent::complete(
+            name,
+            ns_to_us(start),
+            ns_to_us(end - start),
+            String::new(),
+            format!("{} {}", track_prefix, tid),
+            category,
+        )
+        .with_args(args),
+    )
+}
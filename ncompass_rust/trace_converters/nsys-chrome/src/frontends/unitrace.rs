@@ -0,0 +1,280 @@
+//! Intel unitrace (Level Zero / SYCL) front-end
+//!
+//! Reads the Chrome trace JSON unitrace writes with `--chrome-call-logging`,
+//! `--chrome-kernel-logging` and `--chrome-sycl-logging`. Complete (`X`) events
+//! are classified as:
+//! - GPU operations (`cat` `gpu_op`, or a `gpu`/`device` thread) -> kernels. The
+//!   device comes from a `device`/`device_id` arg, else `0`; each (pid, tid)
+//!   track becomes a stream.
+//! - Level Zero calls (names starting with `ze`) -> API events.
+//! - SYCL tasks (`cat` `sycl`, or names starting with `sycl`) -> annotations.
+//!
+//! A `zeCommandListAppendLaunchKernel*` call and its kernel are correlated by a
+//! shared `args.id` when present. Otherwise unitrace's flow arrows are used: a
+//! flow start (`s`) inside the call and the matching finish (`f`) inside the kernel
+//! give both sides the flow ID as `correlationId`. Timestamps are microseconds.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::frontends::{assign_host_devices, FrontendTrace};
+use crate::models::{ns_to_us, ChromeTraceEvent};
+
+/// Prefix of the Level Zero calls that launch kernels
+pub const LAUNCH_API_PREFIX: &str = "zeCommandListAppendLaunch";
+
+/// Check whether a file looks like unitrace Chrome trace output
+///
+/// Only the first few KiB are read: a `traceEvents` array containing Level
+/// Zero calls is taken as unitrace.
+pub fn is_unitrace_json(path: &Path) -> bool {
+    let mut head = vec![0u8; 4096];
+    let read = File::open(path).and_then(|mut f| f.read(&mut head));
+    match read {
+        Ok(n) => {
+            let head = String::from_utf8_lossy(&head[..n]);
+            head.contains("\"traceEvents\"") && head.contains("\"ze")
+        }
+        Err(_) => false,
+    }
+}
+
+/// Kind of a unitrace complete event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EventKind {
+    Kernel,
+    Api,
+    Annotation,
+}
+
+fn classify(event: &Value) -> Option<EventKind> {
+    let name = event
+        .get("name")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let cat = event
+        .get("cat")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let tid = match event.get("tid") {
+        Some(Value::String(tid)) => tid.to_ascii_lowercase(),
+        _ => String::new(),
+    };
+
+    if cat == "gpu_op" || tid.contains("gpu") || tid.contains("device") {
+        Some(EventKind::Kernel)
+    } else if cat == "sycl" || name.starts_with("sycl") {
+        Some(EventKind::Annotation)
+    } else if name.starts_with("ze") {
+        Some(EventKind::Api)
+    } else {
+        None
+    }
+}
+
+/// Convert microsecond timestamps to nanoseconds
+fn us_to_ns(value: Option<&Value>) -> Option<i64> {
+    value
+        .and_then(|v| v.as_f64())
+        .map(|us| (us * 1000.0).round() as i64)
+}
+
+/// Track key (pid, tid) as strings, since unitrace uses both numbers and names
+fn track_key(event: &Value) -> (String, String) {
+    let part = |key: &str| match event.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    (part("pid"), part("tid"))
+}
+
+fn arg_i64(event: &Value, keys: &[&str]) -> Option<i64> {
+    let args = event.get("args")?;
+    keys.iter().find_map(|key| {
+        let value = args.get(*key)?;
+        value
+            .as_i64()
+            .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+    })
+}
+
+/// Reader for unitrace Chrome trace JSON
+pub struct UnitraceReader {
+    root: Value,
+}
+
+impl UnitraceReader {
+    /// Load a unitrace JSON trace
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| ConvertError::open_input(path, e))?;
+        let root: Value = serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            ConvertError::InvalidInput(format!(
+                "Failed to parse unitrace JSON {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_value(root)
+    }
+
+    /// Wrap an already-parsed trace (`{"traceEvents": [...]}` or a bare array)
+    pub fn from_value(root: Value) -> Result<Self> {
+        let is_trace = root.is_array() || root.get("traceEvents").is_some_and(|v| v.is_array());
+        if !is_trace {
+            return Err(ConvertError::InvalidInput(
+                "Not unitrace output: missing 'traceEvents' array".to_string(),
+            ));
+        }
+        Ok(Self { root })
+    }
+
+    fn trace_events(&self) -> &[Value] {
+        self.root
+            .as_array()
+            .or_else(|| self.root["traceEvents"].as_array())
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Convert the trace into internal-model events
+    pub fn read(&self) -> Result<FrontendTrace> {
+        let trace_events = self.trace_events();
+
+        // Flow arrows by track: (timestamp ns, flow id)
+        let mut flow_starts: HashMap<(String, String), Vec<(i64, i64)>> = HashMap::new();
+        let mut flow_finishes: HashMap<(String, String), Vec<(i64, i64)>> = HashMap::new();
+        for event in trace_events {
+            let ph = event.get("ph").and_then(|v| v.as_str());
+            let target = match ph {
+                Some("s") => &mut flow_starts,
+                Some("f") | Some("t") => &mut flow_finishes,
+                _ => continue,
+            };
+            let id = event.get("id").and_then(|v| {
+                v.as_i64()
+                    .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+            });
+            if let (Some(ts), Some(id)) = (us_to_ns(event.get("ts")), id) {
+                target.entry(track_key(event)).or_default().push((ts, id));
+            }
+        }
+
+        let mut trace = FrontendTrace::default();
+        let mut streams: HashMap<(String, String), i64> = HashMap::new();
+
+        for event in trace_events {
+            if event.get("ph").and_then(|v| v.as_str()) != Some("X") {
+                continue;
+            }
+            let Some(kind) = classify(event) else {
+                continue;
+            };
+            let (Some(start), Some(duration)) =
+                (us_to_ns(event.get("ts")), us_to_ns(event.get("dur")))
+            else {
+                continue;
+            };
+            let end = start + duration;
+            let name = event
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("[No name]")
+                .to_string();
+            let key = track_key(event);
+            let pid = key.0.parse::<i64>().unwrap_or(0);
+            let tid = key.1.parse::<i64>().unwrap_or(0);
+
+            let mut args = HashMap::default();
+            args.insert("raw_pid".to_string(), json!(pid));
+            args.insert("start_ns".to_string(), json!(start));
+            args.insert("end_ns".to_string(), json!(end));
+
+            match kind {
+                EventKind::Kernel => {
+                    let device_id =
+                        arg_i64(event, &["device_id", "device", "deviceId"]).unwrap_or(0);
+                    let next_stream = streams.len() as i64;
+                    let stream_id = *streams.entry(key.clone()).or_insert(next_stream);
+                    let correlation = arg_i64(event, &["id", "correlation_id"]).or_else(|| {
+                        flow_finishes
+                            .get(&key)
+                            .and_then(|flows| {
+                                flows.iter().find(|(ts, _)| *ts >= start && *ts <= end)
+                            })
+                            .map(|(_, id)| *id)
+                    });
+                    if let Some(id) = correlation {
+                        args.insert("correlationId".to_string(), json!(id));
+                    }
+                    args.insert("deviceId".to_string(), json!(device_id));
+                    args.insert("streamId".to_string(), json!(stream_id));
+
+                    trace.kernel_events.push(
+                        ChromeTraceEvent::complete(
+                            name,
+                            ns_to_us(start),
+                            ns_to_us(duration),
+                            format!("Device {}", device_id),
+                            format!("Stream {}", stream_id),
+                            EventCategory::Kernel,
+                        )
+                        .with_args(args),
+                    );
+                }
+                EventKind::Api => {
+                    if name.starts_with(LAUNCH_API_PREFIX) {
+                        let correlation = arg_i64(event, &["id", "correlation_id"]).or_else(|| {
+                            flow_starts
+                                .get(&key)
+                                .and_then(|flows| {
+                                    flows.iter().find(|(ts, _)| *ts >= start && *ts <= end)
+                                })
+                                .map(|(_, id)| *id)
+                        });
+                        if let Some(id) = correlation {
+                            args.insert("correlationId".to_string(), json!(id));
+                        }
+                    }
+                    args.insert("raw_tid".to_string(), json!(tid));
+
+                    trace.api_events.push(
+                        ChromeTraceEvent::complete(
+                            name,
+                            ns_to_us(start),
+                            ns_to_us(duration),
+                            String::new(),
+                            format!("Level Zero API Thread {}", tid),
+                            EventCategory::CudaApi,
+                        )
+                        .with_args(args),
+                    );
+                }
+                EventKind::Annotation => {
+                    args.insert("raw_tid".to_string(), json!(tid));
+
+                    trace.annotation_events.push(
+                        ChromeTraceEvent::complete(
+                            name,
+                            ns_to_us(start),
+                            ns_to_us(duration),
+                            String::new(),
+                            format!("SYCL Thread {}", tid),
+                            EventCategory::Nvtx,
+                        )
+                        .with_args(args),
+                    );
+                }
+            }
+        }
+
+        assign_host_devices(&mut trace);
+        Ok(trace)
+    }
+}
@@ -0,0 +1,174 @@
+//! CUDA graph node names from NVTX ranges
+//!
+//! Kernels replayed from a CUDA graph all carry their raw kernel symbol, so a
+//! graph of hundreds of nodes is hard to read. Users name nodes by wrapping
+//! the calls that create them (during stream capture or explicit graph
+//! construction) in NVTX ranges, including nvtx3 scoped ranges. nsys records
+//! node creation in CUDA_GRAPH_NODE_EVENTS, and instantiated nodes keep the ID
+//! of the node they were cloned from; the innermost NVTX range enclosing a
+//! node's creation names it and every clone of it.
+//!
+//! Named kernels are shown under the node name, with the kernel symbol kept
+//! in the `kernel_symbol` arg.
+
+use rusqlite::Connection;
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::mapping::decompose_global_tid;
+use crate::models::ChromeTraceEvent;
+use crate::schema::{table_columns, table_exists};
+
+/// Table recording the creation of every graph node
+pub const GRAPH_NODE_TABLE: &str = "CUDA_GRAPH_NODE_EVENTS";
+
+/// Creation of one graph node, by capture, construction or instantiation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphNodeCreation {
+    pub start_ns: i64,
+    /// Raw thread ID of the creating thread
+    pub tid: i32,
+    pub node_id: i64,
+    /// Node this one was cloned from, for instantiated graphs
+    pub original_node_id: Option<i64>,
+}
+
+/// Load node creation records; empty when the export has none
+pub fn load_graph_node_creations(conn: &Connection) -> Result<Vec<GraphNodeCreation>> {
+    let mut creations = Vec::new();
+    if !table_exists(conn, GRAPH_NODE_TABLE)? {
+        return Ok(creations);
+    }
+    let columns = table_columns(conn, GRAPH_NODE_TABLE)?;
+    if !["start", "globalTid", "graphNodeId"]
+        .iter()
+        .all(|c| columns.contains(*c))
+    {
+        return Ok(creations);
+    }
+    let original = if columns.contains("originalGraphNodeId") {
+        "originalGraphNodeId"
+    } else {
+        "NULL"
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT start, globalTid, graphNodeId, {} FROM {}",
+        original, GRAPH_NODE_TABLE
+    ))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let start_ns: i64 = row.get(0)?;
+        let global_tid: i64 = row.get(1)?;
+        let Some(node_id) = row.get::<_, Option<i64>>(2)? else {
+            continue;
+        };
+        creations.push(GraphNodeCreation {
+            start_ns,
+            tid: decompose_global_tid(global_tid).1,
+            node_id,
+            original_node_id: row.get(3)?,
+        });
+    }
+    Ok(creations)
+}
+
+/// Name each node after the innermost NVTX range enclosing its creation
+///
+/// Nodes created outside any range inherit the name of the node they were
+/// cloned from.
+pub fn graph_node_names(
+    creations: &[GraphNodeCreation],
+    annotation_events: &[ChromeTraceEvent],
+) -> HashMap<i64, String> {
+    // (start, end, name) of each range, per thread
+    let mut ranges: HashMap<i64, Vec<(i64, i64, &str)>> = HashMap::new();
+    for event in annotation_events {
+        let tid = event.args.get("raw_tid").and_then(|v| v.as_i64());
+        let start = event.args.get("start_ns").and_then(|v| v.as_i64());
+        if let (Some(tid), Some(start)) = (tid, start) {
+            // Ranges still open at capture end enclose everything after them
+            let end = event
+                .args
+                .get("end_ns")
+                .and_then(|v| v.as_i64())
+                .unwrap_or(i64::MAX);
+            ranges
+                .entry(tid)
+                .or_default()
+                .push((start, end, &event.name));
+        }
+    }
+
+    let mut names = HashMap::new();
+    for creation in creations {
+        let innermost = ranges.get(&(creation.tid as i64)).and_then(|ranges| {
+            ranges
+                .iter()
+                .filter(|&&(start, end, _)| start <= creation.start_ns && creation.start_ns <= end)
+                .max_by_key(|&&(start, end, _)| (start, std::cmp::Reverse(end)))
+        });
+        if let Some(&(_, _, name)) = innermost {
+            names.insert(creation.node_id, name.to_string());
+        }
+    }
+
+    // Clones may themselves be cloned, so follow chains until nothing changes
+    loop {
+        let inherited: Vec<(i64, String)> = creations
+            .iter()
+            .filter(|c| !names.contains_key(&c.node_id))
+            .filter_map(|c| {
+                let name = names.get(&c.original_node_id?)?;
+                Some((c.node_id, name.clone()))
+            })
+            .collect();
+        if inherited.is_empty() {
+            break;
+        }
+        names.extend(inherited);
+    }
+    names
+}
+
+/// Rename kernels launched from named graph nodes, keeping the symbol in args
+///
+/// Returns the number of renamed kernels.
+pub fn apply_graph_node_names(
+    kernel_events: &mut [ChromeTraceEvent],
+    names: &HashMap<i64, String>,
+) -> usize {
+    if names.is_empty() {
+        return 0;
+    }
+    let mut renamed = 0;
+    for kernel in kernel_events {
+        let node_id = kernel.args.get("graphNodeId").and_then(|v| v.as_i64());
+        let Some(name) = node_id.and_then(|id| names.get(&id)) else {
+            continue;
+        };
+        let symbol = std::mem::replace(&mut kernel.name, name.clone());
+        kernel.args.insert("kernel_symbol".into(), json!(symbol));
+        renamed += 1;
+    }
+    renamed
+}
+
+/// Name graph-launched kernels after the NVTX ranges their nodes were created in
+pub fn name_graph_kernels(
+    conn: &Connection,
+    kernel_events: &mut [ChromeTraceEvent],
+    annotation_events: &[ChromeTraceEvent],
+) -> Result<usize> {
+    if annotation_events.is_empty()
+        || !kernel_events
+            .iter()
+            .any(|k| k.args.contains_key("graphNodeId"))
+    {
+        return Ok(0);
+    }
+    let creations = load_graph_node_creations(conn)?;
+    let names = graph_node_names(&creations, annotation_events);
+    Ok(apply_graph_node_names(kernel_events, &names))
+}
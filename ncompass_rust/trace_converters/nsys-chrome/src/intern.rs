@@ -0,0 +1,261 @@
+//! Interned strings for high-cardinality event fields
+//!
+//! Traces with tens of millions of events repeat a handful of track names
+//! ("Device 0", "Stream 14", "kernel") in every event. Interning stores each
+//! distinct string once and gives events a cheap reference-counted handle.
+//!
+//! The pool is split into shards behind read-write locks, so parser and
+//! linker threads interning strings that are already present only take a
+//! shared lock on one shard. Strings no event refers to any more are dropped
+//! by [`StringInterner::purge`], which long-running processes such as
+//! `serve` call between conversions.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::{Borrow, Cow};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Number of independently locked shards
+const SHARDS: usize = 16;
+
+/// Pool of interned strings
+///
+/// Only low-cardinality fields (track names, categories and arg keys) should
+/// be interned.
+#[derive(Debug)]
+pub struct StringInterner {
+    shards: Vec<RwLock<HashSet<Arc<str>>>>,
+}
+
+impl Default for StringInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StringInterner {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| RwLock::default()).collect(),
+        }
+    }
+
+    /// The pool behind [`InternedStr::new`]
+    pub fn global() -> &'static StringInterner {
+        static POOL: OnceLock<StringInterner> = OnceLock::new();
+        POOL.get_or_init(StringInterner::new)
+    }
+
+    /// Intern a string, reusing the existing allocation if already present
+    pub fn intern(&self, value: &str) -> InternedStr {
+        let shard = self.shard(value);
+        if let Some(existing) = read(shard).get(value) {
+            return InternedStr(existing.clone());
+        }
+        let mut pool = write(shard);
+        if let Some(existing) = pool.get(value) {
+            return InternedStr(existing.clone());
+        }
+        let interned: Arc<str> = Arc::from(value);
+        pool.insert(interned.clone());
+        InternedStr(interned)
+    }
+
+    /// Number of distinct strings currently interned
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+
+    /// Whether no string is interned
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop strings that only the pool still refers to
+    ///
+    /// Returns the number of strings dropped.
+    pub fn purge(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                let mut pool = write(shard);
+                let before = pool.len();
+                pool.retain(|interned| Arc::strong_count(interned) > 1);
+                before - pool.len()
+            })
+            .sum()
+    }
+
+    fn shard(&self, value: &str) -> &RwLock<HashSet<Arc<str>>> {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+}
+
+fn read(shard: &RwLock<HashSet<Arc<str>>>) -> RwLockReadGuard<'_, HashSet<Arc<str>>> {
+    shard.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write(shard: &RwLock<HashSet<Arc<str>>>) -> RwLockWriteGuard<'_, HashSet<Arc<str>>> {
+    shard.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Number of distinct strings currently interned
+pub fn interned_count() -> usize {
+    StringInterner::global().len()
+}
+
+/// Shared handle to an interned string
+///
+/// Derefs to `str` and (de)serializes as a plain JSON string, so it can stand in
+/// for `String` in event fields without changing the output.
+#[derive(Clone, PartialOrd, Ord)]
+pub struct InternedStr(Arc<str>);
+
+impl InternedStr {
+    /// Intern a string in the process-wide pool
+    pub fn new(value: &str) -> Self {
+        StringInterner::global().intern(value)
+    }
+
+    /// Borrow the string contents
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Check whether two handles share the same allocation
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Default for InternedStr {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl PartialEq for InternedStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.0 == other.0
+    }
+}
+
+impl Eq for InternedStr {}
+
+impl Hash for InternedStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Must hash like `str` so `Borrow<str>` lookups work
+        (*self.0).hash(state)
+    }
+}
+
+impl Deref for InternedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for InternedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for InternedStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for InternedStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for InternedStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value: Cow<str> = Deserialize::deserialize(deserializer)?;
+        Ok(Self::new(&value))
+    }
+}
+
+impl From<&str> for InternedStr {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for InternedStr {
+    fn from(value: String) -> Self {
+        Self::new(&value)
+    }
+}
+
+impl From<&String> for InternedStr {
+    fn from(value: &String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<InternedStr> for String {
+    fn from(value: InternedStr) -> Self {
+        value.0.to_string()
+    }
+}
+
+impl PartialEq<str> for InternedStr {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for InternedStr {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for InternedStr {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<InternedStr> for str {
+    fn eq(&self, other: &InternedStr) -> bool {
+        self == &*other.0
+    }
+}
+
+impl PartialEq<InternedStr> for &str {
+    fn eq(&self, other: &InternedStr) -> bool {
+        *self == &*other.0
+    }
+}
+
+impl PartialEq<InternedStr> for String {
+    fn eq(&self, other: &InternedStr) -> bool {
+        **self == *other.0
+    }
+}
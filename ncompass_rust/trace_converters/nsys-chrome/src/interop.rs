@@ -0,0 +1,398 @@
+//! Compact protobuf exchange of events with other tools
+//!
+//! Python tooling that post-processes events before they are written would
+//! otherwise round-trip them through JSON text. This module encodes
+//! [`ChromeTraceEvent`]s in the protobuf schema of
+//! `proto/chrome_trace_event.proto`, so any protobuf runtime can read them
+//! with generated, typed classes. Streams use the usual delimited framing:
+//! each message is preceded by its length as a varint, so events can be
+//! written and read one at a time. The wire format is implemented here
+//! directly; it needs nothing beyond varints and length-delimited fields.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufReader, ErrorKind, Read, Write};
+
+use crate::error::{ConvertError, Result};
+use crate::intern::InternedStr;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, StringOrInt};
+
+/// Largest message accepted when reading a stream
+pub const MAX_PB_MESSAGE_BYTES: u64 = 64 << 20;
+
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LEN: u8 = 2;
+const FIXED32: u8 = 5;
+
+fn malformed(reason: &str) -> ConvertError {
+    ConvertError::InvalidInput(format!("Malformed protobuf event: {}", reason))
+}
+
+// ==========================
+// Encoding
+// ==========================
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_key(buf: &mut Vec<u8>, field: u32, wire_type: u8) {
+    put_varint(buf, (u64::from(field) << 3) | u64::from(wire_type));
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_key(buf, field, LEN);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_f64(buf: &mut Vec<u8>, field: u32, value: f64) {
+    put_key(buf, field, FIXED64);
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_bool(buf: &mut Vec<u8>, field: u32, value: bool) {
+    put_key(buf, field, VARINT);
+    put_varint(buf, u64::from(value));
+}
+
+/// Write a proto3 string field, leaving out the empty default
+fn put_str(buf: &mut Vec<u8>, field: u32, value: &str) {
+    if !value.is_empty() {
+        put_bytes(buf, field, value.as_bytes());
+    }
+}
+
+/// Letter of a serde-renamed enum such as a phase, scope or binding point
+fn letter<T: serde::Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(letter)) => letter,
+        _ => String::new(),
+    }
+}
+
+fn put_string_or_int(buf: &mut Vec<u8>, fields: (u32, u32), value: &Option<StringOrInt>) {
+    match value {
+        Some(StringOrInt::String(s)) => put_bytes(buf, fields.0, s.as_bytes()),
+        Some(StringOrInt::Int(i)) => {
+            put_key(buf, fields.1, VARINT);
+            put_varint(buf, *i as u64);
+        }
+        None => {}
+    }
+}
+
+fn encode_arg_value(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    match value {
+        Value::Null => put_bool(&mut buf, 1, true),
+        Value::Bool(b) => put_bool(&mut buf, 2, *b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                // sint64 uses zigzag encoding
+                put_key(&mut buf, 3, VARINT);
+                put_varint(&mut buf, ((i << 1) ^ (i >> 63)) as u64);
+            } else if n.is_f64() {
+                put_f64(&mut buf, 4, n.as_f64().unwrap_or_default());
+            } else {
+                put_bytes(&mut buf, 6, n.to_string().as_bytes());
+            }
+        }
+        Value::String(s) => put_bytes(&mut buf, 5, s.as_bytes()),
+        Value::Array(_) | Value::Object(_) => put_bytes(&mut buf, 6, value.to_string().as_bytes()),
+    }
+    buf
+}
+
+/// Encode one event as a protobuf message, without framing
+pub fn encode_event_pb(event: &ChromeTraceEvent) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(64 + event.name.len());
+    put_str(&mut buf, 1, &event.name);
+    put_str(&mut buf, 2, &letter(&event.ph));
+    if event.ts != 0.0 {
+        put_f64(&mut buf, 3, event.ts);
+    }
+    put_str(&mut buf, 4, &event.pid);
+    put_str(&mut buf, 5, &event.tid);
+    put_str(&mut buf, 6, &event.cat);
+    for (key, value) in &event.args {
+        let mut entry = Vec::new();
+        put_bytes(&mut entry, 1, key.as_bytes());
+        put_bytes(&mut entry, 2, &encode_arg_value(value));
+        put_bytes(&mut buf, 7, &entry);
+    }
+    if let Some(dur) = event.dur {
+        put_f64(&mut buf, 8, dur);
+    }
+    if let Some(cname) = &event.cname {
+        put_bytes(&mut buf, 9, cname.as_bytes());
+    }
+    put_string_or_int(&mut buf, (10, 11), &event.id);
+    if let Some(bp) = &event.bp {
+        put_bytes(&mut buf, 12, letter(bp).as_bytes());
+    }
+    put_string_or_int(&mut buf, (13, 14), &event.bind_id);
+    if let Some(flow_in) = event.flow_in {
+        put_bool(&mut buf, 15, flow_in);
+    }
+    if let Some(flow_out) = event.flow_out {
+        put_bool(&mut buf, 16, flow_out);
+    }
+    if let Some(scope) = &event.s {
+        put_bytes(&mut buf, 17, letter(scope).as_bytes());
+    }
+    buf
+}
+
+/// Write one event with its varint length prefix
+pub fn write_event_pb<W: Write>(writer: &mut W, event: &ChromeTraceEvent) -> Result<()> {
+    let message = encode_event_pb(event);
+    let mut prefix = Vec::with_capacity(10);
+    put_varint(&mut prefix, message.len() as u64);
+    writer.write_all(&prefix)?;
+    writer.write_all(&message)?;
+    Ok(())
+}
+
+/// Write events as a delimited protobuf stream
+pub fn export_events_pb<W: Write>(events: &[ChromeTraceEvent], mut writer: W) -> Result<()> {
+    for event in events {
+        write_event_pb(&mut writer, event)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// ==========================
+// Decoding
+// ==========================
+
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .buf
+                .get(self.pos)
+                .ok_or_else(|| malformed("truncated varint"))?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(malformed("varint longer than 10 bytes"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.buf.len())
+            .ok_or_else(|| malformed("field runs past the end of the message"))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn key(&mut self) -> Result<(u32, u8)> {
+        let key = self.varint()?;
+        Ok(((key >> 3) as u32, (key & 7) as u8))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| malformed("string is not UTF-8"))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+    }
+
+    /// Skip a field this version does not know, for forward compatibility
+    fn skip(&mut self, wire_type: u8) -> Result<()> {
+        match wire_type {
+            VARINT => self.varint().map(drop),
+            FIXED64 => self.take(8).map(drop),
+            LEN => self.bytes().map(drop),
+            FIXED32 => self.take(4).map(drop),
+            _ => Err(malformed("unsupported wire type")),
+        }
+    }
+}
+
+/// Parse a serde-renamed enum from its letter
+fn from_letter<T: serde::de::DeserializeOwned>(letter: String, what: &str) -> Result<T> {
+    serde_json::from_value(Value::String(letter.clone()))
+        .map_err(|_| malformed(&format!("unknown {} '{}'", what, letter)))
+}
+
+fn decode_arg_value(buf: &[u8]) -> Result<Value> {
+    let mut cursor = Cursor::new(buf);
+    let mut value = Value::Null;
+    while !cursor.done() {
+        value = match cursor.key()? {
+            (1, VARINT) => {
+                cursor.varint()?;
+                Value::Null
+            }
+            (2, VARINT) => Value::Bool(cursor.varint()? != 0),
+            (3, VARINT) => {
+                let zigzag = cursor.varint()?;
+                Value::from((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64))
+            }
+            (4, FIXED64) => Value::from(cursor.f64()?),
+            (5, LEN) => Value::String(cursor.string()?),
+            (6, LEN) => serde_json::from_slice(cursor.bytes()?)?,
+            (_, wire_type) => {
+                cursor.skip(wire_type)?;
+                continue;
+            }
+        };
+    }
+    Ok(value)
+}
+
+fn decode_arg(buf: &[u8]) -> Result<(String, Value)> {
+    let mut cursor = Cursor::new(buf);
+    let (mut key, mut value) = (String::new(), Value::Null);
+    while !cursor.done() {
+        match cursor.key()? {
+            (1, LEN) => key = cursor.string()?,
+            (2, LEN) => value = decode_arg_value(cursor.bytes()?)?,
+            (_, wire_type) => cursor.skip(wire_type)?,
+        }
+    }
+    Ok((key, value))
+}
+
+/// Decode one protobuf message, without framing
+pub fn decode_event_pb(buf: &[u8]) -> Result<ChromeTraceEvent> {
+    let mut cursor = Cursor::new(buf);
+    let mut event = ChromeTraceEvent::new(
+        String::new(),
+        ChromeTracePhase::Complete,
+        0.0,
+        String::new(),
+        String::new(),
+        String::new(),
+    );
+    let mut phase = None;
+    let mut args = HashMap::new();
+    while !cursor.done() {
+        match cursor.key()? {
+            (1, LEN) => event.name = cursor.string()?,
+            (2, LEN) => phase = Some(cursor.string()?),
+            (3, FIXED64) => event.ts = cursor.f64()?,
+            (4, LEN) => event.pid = InternedStr::new(&cursor.string()?),
+            (5, LEN) => event.tid = InternedStr::new(&cursor.string()?),
+            (6, LEN) => event.cat = InternedStr::new(&cursor.string()?),
+            (7, LEN) => {
+                let (key, value) = decode_arg(cursor.bytes()?)?;
+                args.insert(key.into(), value);
+            }
+            (8, FIXED64) => event.dur = Some(cursor.f64()?),
+            (9, LEN) => event.cname = Some(cursor.string()?),
+            (10, LEN) => event.id = Some(StringOrInt::String(cursor.string()?)),
+            (11, VARINT) => event.id = Some(StringOrInt::Int(cursor.varint()? as i64)),
+            (12, LEN) => event.bp = Some(from_letter(cursor.string()?, "binding point")?),
+            (13, LEN) => event.bind_id = Some(StringOrInt::String(cursor.string()?)),
+            (14, VARINT) => event.bind_id = Some(StringOrInt::Int(cursor.varint()? as i64)),
+            (15, VARINT) => event.flow_in = Some(cursor.varint()? != 0),
+            (16, VARINT) => event.flow_out = Some(cursor.varint()? != 0),
+            (17, LEN) => event.s = Some(from_letter(cursor.string()?, "instant scope")?),
+            (_, wire_type) => cursor.skip(wire_type)?,
+        }
+    }
+    let phase = phase.ok_or_else(|| malformed("missing phase"))?;
+    event.ph = from_letter(phase, "phase")?;
+    event.args = args;
+    Ok(event)
+}
+
+/// Reads events one at a time from a delimited protobuf stream
+pub struct PbEventReader<R: Read> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> PbEventReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Length prefix of the next message, or None at a clean end of stream
+    fn read_length(&mut self) -> Result<Option<u64>> {
+        let mut value = 0u64;
+        for (idx, shift) in (0..64).step_by(7).enumerate() {
+            let mut byte = [0u8];
+            match self.reader.read_exact(&mut byte) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof && idx == 0 => return Ok(None),
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return Err(malformed("stream ends inside a length prefix"))
+                }
+                Err(e) => return Err(e.into()),
+            }
+            value |= u64::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(value));
+            }
+        }
+        Err(malformed("length prefix longer than 10 bytes"))
+    }
+}
+
+impl<R: Read> Iterator for PbEventReader<R> {
+    type Item = Result<ChromeTraceEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = match self.read_length() {
+            Ok(Some(len)) => len,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        if len > MAX_PB_MESSAGE_BYTES {
+            return Some(Err(malformed(&format!("message of {} bytes", len))));
+        }
+        self.buf.resize(len as usize, 0);
+        if let Err(e) = self.reader.read_exact(&mut self.buf) {
+            return Some(Err(match e.kind() {
+                ErrorKind::UnexpectedEof => malformed("stream ends inside a message"),
+                _ => e.into(),
+            }));
+        }
+        Some(decode_event_pb(&self.buf))
+    }
+}
+
+/// Read all events of a delimited protobuf stream
+pub fn import_events_pb<R: Read>(reader: R) -> Result<Vec<ChromeTraceEvent>> {
+    PbEventReader::new(reader).collect()
+}
@@ -0,0 +1,160 @@
+//! Keeping oversized args out of the trace
+//!
+//! A few events can carry args far larger than the rest of the trace put
+//! together: full launch backtraces, long parameter dumps. Compaction replaces
+//! every string, array or object arg whose text (JSON for arrays and objects)
+//! exceeds a size limit with a short preview, its size and a content hash,
+//! e.g. `"at train.py:12 ... [48213 bytes, id 9f3c0d1e2a4b5c6d]"`. The full
+//! values can be kept in a sidecar JSON file, `{"args": {"<id>": value, ...}}`,
+//! and looked up by ID when needed.
+//!
+//! IDs are FNV-1a hashes of that text, so they are stable across runs
+//! and identical values (the same backtrace on many launches) are stored once.
+//! Metadata events are left alone, as their args name tracks or hold
+//! dictionaries a reader needs whole.
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Characters of the value kept as a preview
+const PREVIEW_CHARS: usize = 64;
+
+/// Replaces arg values above a size limit, optionally keeping the originals
+#[derive(Debug, Clone)]
+pub struct LargeArgs {
+    limit: usize,
+    keep_values: bool,
+    values: BTreeMap<String, Value>,
+}
+
+/// Parse an args size limit: bytes, or KiB/MiB with a `k`/`m` suffix
+pub fn parse_args_limit(value: &str) -> Result<usize> {
+    let lower = value.trim().to_ascii_lowercase();
+    let (digits, scale) = match lower.as_bytes().last() {
+        Some(b'k') => (&lower[..lower.len() - 1], 1 << 10),
+        Some(b'm') => (&lower[..lower.len() - 1], 1 << 20),
+        _ => (lower.as_str(), 1),
+    };
+    match digits.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n * scale),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid args size limit '{}' (use a positive number of bytes, e.g. 4096 or 4k)",
+            value
+        ))),
+    }
+}
+
+/// FNV-1a hash of `bytes`, as 16 hex digits
+fn content_id(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+impl LargeArgs {
+    /// Truncate values above `limit` bytes, dropping the originals
+    pub fn truncating(limit: usize) -> Self {
+        Self {
+            limit,
+            keep_values: false,
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Replace values above `limit` bytes, keeping the originals for
+    /// [`LargeArgs::save`]
+    pub fn with_sidecar(limit: usize) -> Self {
+        Self {
+            keep_values: true,
+            ..Self::truncating(limit)
+        }
+    }
+
+    /// Load a sidecar written by [`LargeArgs::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| ConvertError::open_input(path, e))?;
+        let mut root: Value = serde_json::from_reader(BufReader::new(file))?;
+        let Some(Value::Object(args)) = root.get_mut("args").map(Value::take) else {
+            return Err(ConvertError::InvalidInput(format!(
+                "Invalid args sidecar {}: missing 'args' object",
+                path.display()
+            )));
+        };
+        Ok(Self {
+            limit: usize::MAX,
+            keep_values: true,
+            values: args.into_iter().collect(),
+        })
+    }
+
+    /// Save the kept values as `{"args": {"<id>": value, ...}}`
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        serde_json::to_writer(BufWriter::new(file), &json!({ "args": self.values }))
+            .map_err(|e| ConvertError::Output(e.into()))?;
+        Ok(())
+    }
+
+    /// Original value for an ID
+    pub fn get(&self, id: &str) -> Option<&Value> {
+        self.values.get(id)
+    }
+
+    /// Number of distinct values kept
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Replace every arg value above the limit, returning how many were replaced
+    pub fn compact(&mut self, events: &mut [ChromeTraceEvent]) -> usize {
+        let mut replaced = 0;
+        for event in events
+            .iter_mut()
+            .filter(|e| e.ph != ChromeTracePhase::Metadata)
+        {
+            for value in event.args.values_mut() {
+                if let Some(short) = self.replacement(value) {
+                    *value = Value::String(short);
+                    replaced += 1;
+                }
+            }
+        }
+        replaced
+    }
+
+    /// Preview, size and ID standing in for `value`, if it is over the limit
+    fn replacement(&mut self, value: &mut Value) -> Option<String> {
+        let text = match value {
+            Value::String(s) if s.len() <= self.limit => return None,
+            Value::String(s) => s.clone(),
+            Value::Array(_) | Value::Object(_) => serde_json::to_string(value).ok()?,
+            _ => return None,
+        };
+        if text.len() <= self.limit {
+            return None;
+        }
+
+        let id = content_id(text.as_bytes());
+        let preview: String = text.chars().take(PREVIEW_CHARS).collect();
+        let short = format!("{} ... [{} bytes, id {}]", preview, text.len(), id);
+        if self.keep_values {
+            self.values.entry(id).or_insert_with(|| value.take());
+        }
+        Some(short)
+    }
+}
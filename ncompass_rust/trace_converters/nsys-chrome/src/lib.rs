@@ -3,16 +3,62 @@
 //! This library provides functionality to convert NVIDIA Nsight Systems (nsys)
 //! SQLite exports to Chrome Trace JSON format (Perfetto-compatible).
 
+pub mod analysis;
+pub mod annotations;
+pub mod begin_end;
+pub mod bench;
+pub mod browser;
+pub mod cache;
+pub mod callchains;
+pub mod cancel;
+pub mod category;
+pub mod compat;
 pub mod converter;
+pub mod cost_model;
+pub mod demangle;
+pub mod devices;
+pub mod diagnostics;
+pub mod dropped;
+pub mod effective_config;
+pub mod error;
+pub mod ffi;
+pub mod follow;
+pub mod frontends;
+pub mod graph_nodes;
+pub mod intern;
+pub mod interop;
+pub mod large_args;
 pub mod linker;
+pub mod logging;
 pub mod mapping;
 pub mod models;
+pub mod name_dictionary;
+pub mod otlp;
+pub mod outline;
 pub mod parsers;
+pub mod pipeline;
+pub mod presets;
+pub mod query;
+pub mod reader;
+pub mod report;
+pub mod routing;
 pub mod schema;
+pub mod seekable;
+pub mod self_profile;
+pub mod service;
+pub mod sessions;
+pub mod sink;
+pub mod tid_allocator;
+pub mod track_ids;
+pub mod tui;
+pub mod viewer;
 pub mod writer;
 
 pub use converter::NsysChromeConverter;
+pub use diagnostics::ConversionDiagnostics;
+pub use error::ConvertError;
 pub use models::{ChromeTraceEvent, ConversionOptions};
+pub use reader::ChromeTraceReader;
 pub use writer::ChromeTraceWriter;
 
 /// Convert nsys SQLite file to Chrome Trace JSON
@@ -20,7 +66,7 @@ pub fn convert_file(
     sqlite_path: &str,
     output_path: &str,
     options: Option<ConversionOptions>,
-) -> anyhow::Result<()> {
+) -> error::Result<()> {
     let converter = NsysChromeConverter::new(sqlite_path, options)?;
     let events = converter.convert()?;
     ChromeTraceWriter::write(output_path, events)?;
@@ -32,7 +78,7 @@ pub fn convert_file_gz(
     sqlite_path: &str,
     output_path: &str,
     options: Option<ConversionOptions>,
-) -> anyhow::Result<()> {
+) -> error::Result<()> {
     let converter = NsysChromeConverter::new(sqlite_path, options)?;
     let events = converter.convert()?;
     ChromeTraceWriter::write_gz(output_path, events)?;
@@ -2,29 +2,87 @@
 
 use log::debug;
 
+use serde_json::Value;
+
 use crate::models::{ChromeTraceEvent, ChromeTracePhase};
 
+pub use crate::models::EventId;
+
+/// Unit a trace source records its timestamps in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeUnit {
+    /// nsys SQLite exports (`start_ns`/`end_ns` args)
+    #[default]
+    Nanoseconds,
+    /// Chrome trace JSON, including Kineto (`ts`/`dur`)
+    Microseconds,
+}
+
+impl TimeUnit {
+    /// Nanoseconds in one unit
+    pub fn ns_per_unit(self) -> i64 {
+        match self {
+            TimeUnit::Nanoseconds => 1,
+            TimeUnit::Microseconds => 1_000,
+        }
+    }
+
+    /// Convert a time in this unit to nanoseconds, rounding to the nearest ns
+    pub fn to_ns(self, value: f64) -> i64 {
+        (value * self.ns_per_unit() as f64).round() as i64
+    }
+
+    /// Convert a JSON time in this unit to nanoseconds
+    ///
+    /// Integers are scaled exactly; floats are rounded to the nearest ns.
+    pub fn value_to_ns(self, value: &Value) -> Option<i64> {
+        match value.as_i64() {
+            Some(v) => v.checked_mul(self.ns_per_unit()),
+            None => value.as_f64().map(|v| self.to_ns(v)),
+        }
+    }
+}
+
 /// Event adapter trait for extracting event properties
+///
+/// Sources record times in different units; adapters normalize them so the
+/// linking algorithms only ever see nanoseconds, and events read through
+/// different adapters can be compared directly.
 pub trait EventAdapter {
+    /// Unit the source records times in
+    fn time_unit(&self) -> TimeUnit {
+        TimeUnit::Nanoseconds
+    }
+
+    /// Get time range (start, end) from an event, normalized to nanoseconds
+    fn get_time_range_ns(&self, event: &ChromeTraceEvent) -> Option<(i64, i64)>;
+
     /// Get time range (start, end) from an event in nanoseconds
-    fn get_time_range(&self, event: &ChromeTraceEvent) -> Option<(i64, i64)>;
+    ///
+    /// Same as [`EventAdapter::get_time_range_ns`].
+    fn get_time_range(&self, event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+        self.get_time_range_ns(event)
+    }
 
     /// Get correlation ID from an event
-    fn get_correlation_id(&self, event: &ChromeTraceEvent) -> Option<i32>;
+    ///
+    /// IDs are kept as i64: long captures exceed the i32 range and must not wrap.
+    fn get_correlation_id(&self, event: &ChromeTraceEvent) -> Option<i64>;
 
     /// Get unique event identifier
     fn get_event_id(&self, event: &ChromeTraceEvent) -> EventId;
-}
 
-/// Unique identifier for an event (for indexing in overlap maps)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct EventId(pub usize);
+    /// Get the originating thread ID used to partition overlap detection
+    fn get_thread_id(&self, _event: &ChromeTraceEvent) -> Option<i64> {
+        None
+    }
+}
 
 /// Default event adapter for ChromeTraceEvent from nsys SQLite
 pub struct NsysEventAdapter;
 
 impl EventAdapter for NsysEventAdapter {
-    fn get_time_range(&self, event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+    fn get_time_range_ns(&self, event: &ChromeTraceEvent) -> Option<(i64, i64)> {
         // Only complete events ("X") have meaningful time ranges for overlap detection
         if event.ph != ChromeTracePhase::Complete {
             debug!(
@@ -59,12 +117,8 @@ impl EventAdapter for NsysEventAdapter {
         Some((start_ns, end_ns))
     }
 
-    fn get_correlation_id(&self, event: &ChromeTraceEvent) -> Option<i32> {
-        let corr_id = event
-            .args
-            .get("correlationId")
-            .and_then(|v| v.as_i64())
-            .map(|v| v as i32);
+    fn get_correlation_id(&self, event: &ChromeTraceEvent) -> Option<i64> {
+        let corr_id = event.args.get("correlationId").and_then(|v| v.as_i64());
 
         if corr_id.is_none() {
             debug!(
@@ -77,8 +131,92 @@ impl EventAdapter for NsysEventAdapter {
     }
 
     fn get_event_id(&self, event: &ChromeTraceEvent) -> EventId {
-        // Use pointer address as unique ID
-        EventId(event as *const ChromeTraceEvent as usize)
+        // Assigned at extraction, so stable across passes
+        EventId::of(event)
+    }
+
+    fn get_thread_id(&self, event: &ChromeTraceEvent) -> Option<i64> {
+        event.args.get("raw_tid").and_then(|v| v.as_i64())
+    }
+}
+
+
+/// Event adapter for Chrome trace JSON sources such as PyTorch Kineto
+///
+/// Times come from `ts`/`dur` in microseconds and correlation IDs from the
+/// `correlation` arg Kineto writes on runtime calls and kernels.
+pub struct KinetoEventAdapter;
+
+impl EventAdapter for KinetoEventAdapter {
+    fn time_unit(&self) -> TimeUnit {
+        TimeUnit::Microseconds
+    }
+
+    fn get_time_range_ns(&self, event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+        if event.ph != ChromeTracePhase::Complete {
+            debug!(
+                "Skipping event '{}': phase {:?} is not Complete",
+                event.name, event.ph
+            );
+            return None;
+        }
+
+        let Some(dur) = event.dur else {
+            debug!("Skipping event '{}': missing 'dur'", event.name);
+            return None;
+        };
+
+        let unit = self.time_unit();
+        Some((unit.to_ns(event.ts), unit.to_ns(event.ts + dur)))
+    }
+
+    fn get_correlation_id(&self, event: &ChromeTraceEvent) -> Option<i64> {
+        event
+            .args
+            .get("correlation")
+            .or_else(|| event.args.get("correlationId"))
+            .and_then(|v| v.as_i64())
+    }
+
+    fn get_event_id(&self, event: &ChromeTraceEvent) -> EventId {
+        EventId::of(event)
+    }
+
+    fn get_thread_id(&self, event: &ChromeTraceEvent) -> Option<i64> {
+        event.tid.parse().ok()
+    }
+}
+
+/// Event adapter for traces mixing nsys and Chrome JSON events
+///
+/// Events carrying `start_ns` are read as nsys events, all others as
+/// Kineto events, so both end up on the same nanosecond timeline.
+pub struct MixedEventAdapter;
+
+impl MixedEventAdapter {
+    fn adapter_for(event: &ChromeTraceEvent) -> &'static dyn EventAdapter {
+        if event.args.contains_key("start_ns") {
+            &NsysEventAdapter
+        } else {
+            &KinetoEventAdapter
+        }
     }
 }
 
+impl EventAdapter for MixedEventAdapter {
+    fn get_time_range_ns(&self, event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+        Self::adapter_for(event).get_time_range_ns(event)
+    }
+
+    fn get_correlation_id(&self, event: &ChromeTraceEvent) -> Option<i64> {
+        Self::adapter_for(event).get_correlation_id(event)
+    }
+
+    fn get_event_id(&self, event: &ChromeTraceEvent) -> EventId {
+        EventId::of(event)
+    }
+
+    fn get_thread_id(&self, event: &ChromeTraceEvent) -> Option<i64> {
+        Self::adapter_for(event).get_thread_id(event)
+    }
+}
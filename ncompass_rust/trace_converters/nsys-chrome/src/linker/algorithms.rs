@@ -1,23 +1,31 @@
 //! Core algorithms for linking events via correlation IDs
+//!
+//! Each algorithm has a generic form over the [`HasTimeRange`] and
+//! [`HasCorrelation`] traits, usable with any event struct, and a form taking
+//! `ChromeTraceEvent`s and an [`EventAdapter`] that the converter uses.
 
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
 use log::debug;
+use rayon::prelude::*;
 
+use crate::error::{ConvertError, Result};
 use crate::linker::adapters::{EventAdapter, EventId};
+use crate::linker::interval::{Adapted, HasCorrelation, HasTimeRange};
 use crate::models::ChromeTraceEvent;
 
 /// Event for the sweep-line algorithm
-#[derive(Debug, Clone)]
-struct SweepEvent<'a> {
+#[derive(Debug, Clone, Copy)]
+struct SweepEvent {
     timestamp: i64,
     event_type: i32, // 1 for start, -1 for end
     origin: EventOrigin,
-    event_ref: &'a ChromeTraceEvent,
+    /// Index into the source or target slice, depending on `origin`
+    index: usize,
 }
 
-impl<'a> Ord for SweepEvent<'a> {
+impl Ord for SweepEvent {
     /// Sort by timestamp, then starts before ends, then source before target.
     /// Uses lazy evaluation via `then_with` to avoid unnecessary comparisons.
     fn cmp(&self, other: &Self) -> Ordering {
@@ -32,19 +40,19 @@ impl<'a> Ord for SweepEvent<'a> {
     }
 }
 
-impl<'a> PartialOrd for SweepEvent<'a> {
+impl PartialOrd for SweepEvent {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<'a> PartialEq for SweepEvent<'a> {
+impl PartialEq for SweepEvent {
     fn eq(&self, other: &Self) -> bool {
         self.cmp(other) == Ordering::Equal
     }
 }
 
-impl<'a> Eq for SweepEvent<'a> {}
+impl Eq for SweepEvent {}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum EventOrigin {
@@ -56,25 +64,24 @@ enum EventOrigin {
 ///
 /// Creates two sweep events per input event: one for start, one for end.
 /// Uses mutable reference to avoid extra allocations.
-fn append_sweep_events<'a>(
-    events: &[&'a ChromeTraceEvent],
+fn append_sweep_events<T: HasTimeRange>(
+    events: &[&T],
     origin: EventOrigin,
-    adapter: &dyn EventAdapter,
-    dest: &mut Vec<SweepEvent<'a>>,
+    dest: &mut Vec<SweepEvent>,
 ) {
-    for &event in events {
-        if let Some((start, end)) = adapter.get_time_range(event) {
+    for (index, event) in events.iter().enumerate() {
+        if let Some((start, end)) = event.time_range_ns() {
             dest.push(SweepEvent {
                 timestamp: start,
                 event_type: 1,
                 origin,
-                event_ref: event,
+                index,
             });
             dest.push(SweepEvent {
                 timestamp: end,
                 event_type: -1,
                 origin,
-                event_ref: event,
+                index,
             });
         }
     }
@@ -82,28 +89,23 @@ fn append_sweep_events<'a>(
 
 /// Process sorted sweep events using sweep-line algorithm.
 ///
-/// Returns mapping from source index to list of overlapping target events.
-fn process_sweep_line<'a>(
-    sorted_events: &[SweepEvent<'a>],
-    source_index_map: &HashMap<usize, usize>,
-) -> HashMap<usize, Vec<&'a ChromeTraceEvent>> {
-    let mut active_source_intervals: Vec<&ChromeTraceEvent> = Vec::new();
-    let mut result_by_index: HashMap<usize, Vec<&ChromeTraceEvent>> = HashMap::default();
+/// Returns mapping from source index to list of overlapping target indices.
+fn process_sweep_line(sorted_events: &[SweepEvent]) -> HashMap<usize, Vec<usize>> {
+    let mut active_source_intervals: Vec<usize> = Vec::new();
+    let mut result_by_index: HashMap<usize, Vec<usize>> = HashMap::default();
 
     for sweep_event in sorted_events {
         if sweep_event.event_type == 1 {
             // Start event
             if sweep_event.origin == EventOrigin::Source {
-                active_source_intervals.push(sweep_event.event_ref);
+                active_source_intervals.push(sweep_event.index);
             } else {
                 // Target start - add to all currently active source ranges
-                for &source_event in &active_source_intervals {
-                    let source_idx =
-                        source_index_map[&(source_event as *const ChromeTraceEvent as usize)];
+                for &source_idx in &active_source_intervals {
                     result_by_index
                         .entry(source_idx)
                         .or_default()
-                        .push(sweep_event.event_ref);
+                        .push(sweep_event.index);
                 }
             }
         } else {
@@ -112,7 +114,7 @@ fn process_sweep_line<'a>(
                 // Remove from active intervals
                 if let Some(pos) = active_source_intervals
                     .iter()
-                    .position(|&e| std::ptr::eq(e, sweep_event.event_ref))
+                    .position(|&idx| idx == sweep_event.index)
                 {
                     active_source_intervals.remove(pos);
                 }
@@ -123,39 +125,20 @@ fn process_sweep_line<'a>(
     result_by_index
 }
 
-/// Convert index-based results to EventId-based mapping.
-fn convert_to_event_id_map<'a>(
-    result_by_index: HashMap<usize, Vec<&'a ChromeTraceEvent>>,
-    source_events: &[&'a ChromeTraceEvent],
-    adapter: &dyn EventAdapter,
-) -> HashMap<EventId, Vec<&'a ChromeTraceEvent>> {
-    result_by_index
-        .into_iter()
-        .map(|(idx, target_list)| (adapter.get_event_id(source_events[idx]), target_list))
-        .collect()
-}
-
-/// Find overlapping intervals using sweep-line algorithm
+/// Find overlapping intervals between any two kinds of timed events
 ///
-/// Generic implementation that works with any event format via adapter.
-/// Accepts slices of references to avoid cloning.
-pub fn find_overlapping_intervals<'a>(
-    source_events: &[&'a ChromeTraceEvent],
-    target_events: &[&'a ChromeTraceEvent],
-    adapter: &dyn EventAdapter,
-) -> HashMap<EventId, Vec<&'a ChromeTraceEvent>> {
-    // Build index map for source events
-    let source_index_map: HashMap<usize, usize> = source_events
-        .iter()
-        .enumerate()
-        .map(|(i, &e)| ((e as *const ChromeTraceEvent as usize), i))
-        .collect();
-
+/// Returns, for each source index with at least one overlap, the targets
+/// overlapping it in start order. Touching intervals count as overlapping;
+/// events without a time range are skipped.
+pub fn overlapping_intervals<'a, S: HasTimeRange, T: HasTimeRange>(
+    source_events: &[&S],
+    target_events: &[&'a T],
+) -> HashMap<usize, Vec<&'a T>> {
     // Create sweep events with pre-allocated capacity
     let mut mixed_events = Vec::with_capacity((source_events.len() + target_events.len()) * 2);
-    append_sweep_events(source_events, EventOrigin::Source, adapter, &mut mixed_events);
+    append_sweep_events(source_events, EventOrigin::Source, &mut mixed_events);
     let source_sweep_count = mixed_events.len();
-    append_sweep_events(target_events, EventOrigin::Target, adapter, &mut mixed_events);
+    append_sweep_events(target_events, EventOrigin::Target, &mut mixed_events);
     let target_sweep_count = mixed_events.len() - source_sweep_count;
 
     // Log summary of events processed vs skipped
@@ -172,9 +155,10 @@ pub fn find_overlapping_intervals<'a>(
     // Sort using Ord implementation (timestamp -> event_type -> origin)
     mixed_events.sort();
 
-    // Process sweep events and convert to final result
-    let result_by_index = process_sweep_line(&mixed_events, &source_index_map);
-    let result = convert_to_event_id_map(result_by_index, source_events, adapter);
+    let result: HashMap<usize, Vec<&T>> = process_sweep_line(&mixed_events)
+        .into_iter()
+        .map(|(idx, targets)| (idx, targets.into_iter().map(|t| target_events[t]).collect()))
+        .collect();
 
     debug!(
         "find_overlapping_intervals: found {} source events with overlapping targets",
@@ -184,21 +168,155 @@ pub fn find_overlapping_intervals<'a>(
     result
 }
 
-/// Build mapping from correlation ID to list of kernels
-/// Accepts a slice of references to avoid cloning.
-pub fn build_correlation_map<'a>(
-    kernel_events: &[&'a ChromeTraceEvent],
+/// Find overlapping intervals, sweeping each thread partition in parallel
+///
+/// Sources and targets are partitioned by thread ID and each source is only
+/// matched against targets from the same thread. Sources without a thread ID
+/// are matched against all targets; targets without one are included in every
+/// partition. Results are keyed by source index, as in [`overlapping_intervals`].
+pub fn overlapping_intervals_by_thread<'a, S, T>(
+    source_events: &[&S],
+    target_events: &[&'a T],
+) -> HashMap<usize, Vec<&'a T>>
+where
+    S: HasTimeRange + Sync,
+    T: HasTimeRange + Sync,
+{
+    let mut sources_by_thread: HashMap<Option<i64>, (Vec<usize>, Vec<&S>)> = HashMap::default();
+    for (idx, &event) in source_events.iter().enumerate() {
+        let (indices, sources) = sources_by_thread.entry(event.thread_id()).or_default();
+        indices.push(idx);
+        sources.push(event);
+    }
+
+    let mut targets_by_thread: HashMap<Option<i64>, Vec<&T>> = HashMap::default();
+    for &event in target_events {
+        targets_by_thread
+            .entry(event.thread_id())
+            .or_default()
+            .push(event);
+    }
+    let unthreaded_targets = targets_by_thread.remove(&None).unwrap_or_default();
+
+    let partitions: Vec<(Vec<usize>, Vec<&S>, Vec<&T>)> = sources_by_thread
+        .into_iter()
+        .map(|(thread_id, (indices, sources))| {
+            let targets = match thread_id {
+                None => target_events.to_vec(),
+                Some(_) => {
+                    let mut targets = targets_by_thread.get(&thread_id).cloned().unwrap_or_default();
+                    targets.extend(unthreaded_targets.iter().copied());
+                    targets
+                }
+            };
+            (indices, sources, targets)
+        })
+        .filter(|(_, _, targets)| !targets.is_empty())
+        .collect();
+
+    debug!(
+        "find_overlapping_intervals_by_thread: sweeping {} thread partitions",
+        partitions.len()
+    );
+
+    partitions
+        .par_iter()
+        .map(|(indices, sources, targets)| {
+            overlapping_intervals(sources, targets)
+                .into_iter()
+                .map(|(local, found)| (indices[local], found))
+                .collect::<HashMap<_, _>>()
+        })
+        .reduce(HashMap::default, |mut merged, partial| {
+            merged.extend(partial);
+            merged
+        })
+}
+
+/// References to every element, as the generic algorithms take them
+fn refs<T>(items: &[T]) -> Vec<&T> {
+    items.iter().collect()
+}
+
+/// Key index-based overlap results by the adapter's event IDs
+fn by_event_id<'a, A: EventAdapter + ?Sized>(
+    result_by_index: HashMap<usize, Vec<&Adapted<'a, '_, A>>>,
+    source_events: &[&'a ChromeTraceEvent],
+    adapter: &A,
+) -> HashMap<EventId, Vec<&'a ChromeTraceEvent>> {
+    result_by_index
+        .into_iter()
+        .map(|(idx, targets)| {
+            let targets = targets.into_iter().map(|t| t.event).collect();
+            (adapter.get_event_id(source_events[idx]), targets)
+        })
+        .collect()
+}
+
+/// Fail unless every source event has an [`EventId`] to key results by
+fn check_numbered(source_events: &[&ChromeTraceEvent]) -> Result<()> {
+    match source_events.iter().find(|e| e.event_id.is_none()) {
+        Some(event) => Err(ConvertError::InvalidInput(format!(
+            "Event '{}' has no EventId; number events with FrontendTrace::assign_event_ids first",
+            event.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Find overlapping intervals using sweep-line algorithm
+///
+/// Works with any event format via adapter; see [`overlapping_intervals`] for
+/// events that carry their own time ranges. Accepts slices of references to
+/// avoid cloning. Results are keyed by source [`EventId`], so fails if a
+/// source event was never numbered.
+pub fn find_overlapping_intervals<'a>(
+    source_events: &[&'a ChromeTraceEvent],
+    target_events: &[&'a ChromeTraceEvent],
     adapter: &dyn EventAdapter,
-) -> HashMap<i32, Vec<&'a ChromeTraceEvent>> {
-    let mut correlation_map: HashMap<i32, Vec<&ChromeTraceEvent>> = HashMap::default();
+) -> Result<HashMap<EventId, Vec<&'a ChromeTraceEvent>>> {
+    check_numbered(source_events)?;
+    let sources = Adapted::all(source_events, adapter);
+    let targets = Adapted::all(target_events, adapter);
+    let result = overlapping_intervals(&refs(&sources), &refs(&targets));
+    Ok(by_event_id(result, source_events, adapter))
+}
+
+/// Find overlapping intervals, sweeping each thread partition in parallel
+///
+/// Sources and targets are partitioned by the adapter's thread ID and each source is
+/// only matched against targets from the same thread. Sources without a thread ID are
+/// matched against all targets; targets without one are included in every partition.
+/// Fails if a source event was never numbered.
+pub fn find_overlapping_intervals_by_thread<'a>(
+    source_events: &[&'a ChromeTraceEvent],
+    target_events: &[&'a ChromeTraceEvent],
+    adapter: &(dyn EventAdapter + Sync),
+) -> Result<HashMap<EventId, Vec<&'a ChromeTraceEvent>>> {
+    check_numbered(source_events)?;
+    Ok(overlapping_events_by_thread(source_events, target_events, adapter))
+}
+
+/// [`find_overlapping_intervals_by_thread`] for sources the linker numbered
+pub(crate) fn overlapping_events_by_thread<'a>(
+    source_events: &[&'a ChromeTraceEvent],
+    target_events: &[&'a ChromeTraceEvent],
+    adapter: &(dyn EventAdapter + Sync),
+) -> HashMap<EventId, Vec<&'a ChromeTraceEvent>> {
+    let sources = Adapted::all(source_events, adapter);
+    let targets = Adapted::all(target_events, adapter);
+    let result = overlapping_intervals_by_thread(&refs(&sources), &refs(&targets));
+    by_event_id(result, source_events, adapter)
+}
+
+/// Group events by correlation ID, skipping events without one
+pub fn correlation_map<'a, T: HasCorrelation>(events: &[&'a T]) -> HashMap<i64, Vec<&'a T>> {
+    let mut correlation_map: HashMap<i64, Vec<&T>> = HashMap::default();
     let mut skipped_count = 0;
 
-    for &kernel_event in kernel_events {
-        if let Some(corr_id) = adapter.get_correlation_id(kernel_event) {
-            correlation_map
-                .entry(corr_id)
-                .or_insert_with(Vec::new)
-                .push(kernel_event);
+    for &event in events {
+        if let Some(corr_id) = event.correlation_id() {
+            correlation_map.entry(corr_id).or_default().push(event);
         } else {
             skipped_count += 1;
         }
@@ -214,24 +332,32 @@ pub fn build_correlation_map<'a>(
     debug!(
         "build_correlation_map: built map with {} unique correlation IDs from {} kernels",
         correlation_map.len(),
-        kernel_events.len() - skipped_count
+        events.len() - skipped_count
     );
 
     correlation_map
 }
 
-/// Aggregate kernel execution times across multiple kernels
-///
-/// Finds the minimum start time and maximum end time across all kernels.
-pub fn aggregate_kernel_times(
-    kernels: &[&ChromeTraceEvent],
+/// Build mapping from correlation ID to list of kernels
+/// Accepts a slice of references to avoid cloning.
+pub fn build_correlation_map<'a>(
+    kernel_events: &[&'a ChromeTraceEvent],
     adapter: &dyn EventAdapter,
-) -> Option<(i64, i64)> {
+) -> HashMap<i64, Vec<&'a ChromeTraceEvent>> {
+    let kernels = Adapted::all(kernel_events, adapter);
+    correlation_map(&refs(&kernels))
+        .into_iter()
+        .map(|(corr_id, found)| (corr_id, found.into_iter().map(|k| k.event).collect()))
+        .collect()
+}
+
+/// Earliest start and latest end across events with a time range
+pub fn time_span<T: HasTimeRange>(events: &[&T]) -> Option<(i64, i64)> {
     let mut kernel_start_time: Option<i64> = None;
     let mut kernel_end_time: Option<i64> = None;
 
-    for &kernel_event in kernels {
-        if let Some((kernel_start, kernel_end)) = adapter.get_time_range(kernel_event) {
+    for event in events {
+        if let Some((kernel_start, kernel_end)) = event.time_range_ns() {
             kernel_start_time = Some(
                 kernel_start_time
                     .map(|t| t.min(kernel_start))
@@ -251,26 +377,78 @@ pub fn aggregate_kernel_times(
     }
 }
 
-/// Find all kernels associated with an annotation event via overlapping API events
-pub fn find_kernels_for_annotation<'a>(
-    overlapping_api_events: &[&'a ChromeTraceEvent],
-    correlation_map: &HashMap<i32, Vec<&'a ChromeTraceEvent>>,
+/// Aggregate kernel execution times across multiple kernels
+///
+/// Finds the minimum start time and maximum end time across all kernels.
+pub fn aggregate_kernel_times(
+    kernels: &[&ChromeTraceEvent],
     adapter: &dyn EventAdapter,
-) -> Vec<&'a ChromeTraceEvent> {
+) -> Option<(i64, i64)> {
+    time_span(&refs(&Adapted::all(kernels, adapter)))
+}
+
+/// Merge time ranges into sorted, non-overlapping intervals
+///
+/// Overlapping and touching ranges are combined. Events without a valid time
+/// range (or with end before start) are skipped.
+pub fn merged_ranges<T: HasTimeRange>(events: &[&T]) -> Vec<(i64, i64)> {
+    let mut ranges: Vec<(i64, i64)> = events
+        .iter()
+        .filter_map(|event| event.time_range_ns())
+        .filter(|(start, end)| end >= start)
+        .collect();
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(i64, i64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Merge event time ranges into sorted, non-overlapping intervals
+///
+/// Overlapping and touching ranges are combined. Events without a valid time
+/// range (or with end before start) are skipped.
+pub fn merge_intervals(
+    events: &[&ChromeTraceEvent],
+    adapter: &dyn EventAdapter,
+) -> Vec<(i64, i64)> {
+    merged_ranges(&refs(&Adapted::all(events, adapter)))
+}
+
+/// Time covered by at least one of the ranges, counting overlaps once
+pub fn covered_time<T: HasTimeRange>(events: &[&T]) -> i64 {
+    merged_ranges(events)
+        .iter()
+        .map(|(start, end)| end - start)
+        .sum()
+}
+
+/// Total time covered by at least one event, counting overlaps once
+pub fn total_covered_time(events: &[&ChromeTraceEvent], adapter: &dyn EventAdapter) -> i64 {
+    covered_time(&refs(&Adapted::all(events, adapter)))
+}
+
+/// Collect the events correlated with each caller, in caller order
+pub fn correlated_events<'a, C: HasCorrelation, T>(
+    callers: &[&C],
+    correlation_map: &HashMap<i64, Vec<&'a T>>,
+) -> Vec<&'a T> {
     let mut found_kernels = Vec::new();
     let mut api_without_corr_id = 0;
     let mut api_without_kernels = 0;
 
-    for &api_event in overlapping_api_events {
-        if let Some(corr_id) = adapter.get_correlation_id(api_event) {
-            if let Some(kernels) = correlation_map.get(&corr_id) {
-                if !kernels.is_empty() {
-                    found_kernels.extend(kernels.iter().copied());
-                } else {
-                    api_without_kernels += 1;
+    for caller in callers {
+        if let Some(corr_id) = caller.correlation_id() {
+            match correlation_map.get(&corr_id) {
+                Some(kernels) if !kernels.is_empty() => {
+                    found_kernels.extend(kernels.iter().copied())
                 }
-            } else {
-                api_without_kernels += 1;
+                _ => api_without_kernels += 1,
             }
         } else {
             api_without_corr_id += 1;
@@ -293,3 +471,12 @@ pub fn find_kernels_for_annotation<'a>(
     found_kernels
 }
 
+/// Find all kernels associated with an annotation event via overlapping API events
+pub fn find_kernels_for_annotation<'a>(
+    overlapping_api_events: &[&'a ChromeTraceEvent],
+    correlation_map: &HashMap<i64, Vec<&'a ChromeTraceEvent>>,
+    adapter: &dyn EventAdapter,
+) -> Vec<&'a ChromeTraceEvent> {
+    let callers = Adapted::all(overlapping_api_events, adapter);
+    correlated_events(&refs(&callers), correlation_map)
+}
@@ -0,0 +1,252 @@
+//! Attribution of CUDA API calls to the NVTX ranges overlapping them
+//!
+//! A call launched inside nested ranges overlaps all of them, possibly
+//! running past the end of the inner ones. [`LinkPolicy`] decides which
+//! ranges receive such a call; every link gets a confidence (the fraction of
+//! the call inside the range, shared between ranges that score equally), and
+//! calls whose best ranges tie, such as identical ranges from two domains,
+//! are counted as ambiguous.
+//!
+//! Frameworks that pop a range before synchronizing leave the launches after
+//! it outside every range. With a sticky lookback ([`attribute_sticky_calls`])
+//! such calls go to the range that ended last on their thread, if it ended
+//! recently enough; their links have confidence 0, as no part of the call is
+//! inside the range.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{ConvertError, Result};
+use crate::linker::adapters::{EventAdapter, EventId};
+use crate::models::{ChromeTraceEvent, LinkPolicy};
+
+/// Parse `all`, `innermost` or `longest-overlap` into a link policy
+pub fn parse_link_policy(value: &str) -> Result<LinkPolicy> {
+    match value.trim() {
+        "all" => Ok(LinkPolicy::All),
+        "innermost" => Ok(LinkPolicy::Innermost),
+        "longest-overlap" => Ok(LinkPolicy::LongestOverlap),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid link policy '{}' (use all, innermost or longest-overlap)",
+            value
+        ))),
+    }
+}
+
+/// Counts from attributing calls to ranges
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    /// Calls overlapping more than one range
+    pub shared_calls: usize,
+    /// Calls whose best-scoring ranges tie under the policy
+    pub ambiguous_calls: usize,
+    /// Kernels left unlinked for starting more than `max_link_gap_ns` after their call ended
+    pub distant_kernels: usize,
+    /// Kernel time linked to at least one range, each kernel counted once
+    pub attributed_kernel_ns: i64,
+    /// Calls outside every range attributed to the range preceding them
+    pub sticky_calls: usize,
+}
+
+impl LinkStats {
+    /// Add the counts of another device
+    pub fn merge(&mut self, other: LinkStats) {
+        self.shared_calls += other.shared_calls;
+        self.ambiguous_calls += other.ambiguous_calls;
+        self.distant_kernels += other.distant_kernels;
+        self.attributed_kernel_ns += other.attributed_kernel_ns;
+        self.sticky_calls += other.sticky_calls;
+    }
+}
+
+/// A call attributed to a range, with the confidence of the link in [0, 1]
+pub(crate) type Attributed<'a> = (&'a ChromeTraceEvent, f64);
+
+/// Ranking of one range for one call, compared lexicographically; higher is better
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+struct Score {
+    primary: f64,
+    secondary: f64,
+}
+
+/// Decide which ranges receive each call they overlap
+///
+/// `overlaps` maps each range to the calls overlapping it. Returns the calls
+/// attributed to each range, in their original order, and the ambiguity
+/// counts. Under [`LinkPolicy::All`] every overlapping range keeps the call
+/// and ambiguity is judged as for [`LinkPolicy::Innermost`].
+pub(crate) fn attribute_calls<'a>(
+    ranges: &[&'a ChromeTraceEvent],
+    overlaps: &HashMap<EventId, Vec<&'a ChromeTraceEvent>>,
+    adapter: &dyn EventAdapter,
+    policy: LinkPolicy,
+) -> (HashMap<EventId, Vec<Attributed<'a>>>, LinkStats) {
+    // Candidate ranges of each call, in range order
+    let mut call_order: Vec<&'a ChromeTraceEvent> = Vec::new();
+    let mut candidates: HashMap<EventId, Vec<usize>> = HashMap::new();
+    for (idx, range) in ranges.iter().enumerate() {
+        for &call in overlaps
+            .get(&adapter.get_event_id(range))
+            .into_iter()
+            .flatten()
+        {
+            let entry = candidates.entry(adapter.get_event_id(call)).or_default();
+            if entry.is_empty() {
+                call_order.push(call);
+            }
+            entry.push(idx);
+        }
+    }
+
+    // Ranges receiving each call, with the confidence of each link
+    let mut stats = LinkStats::default();
+    let mut links: HashMap<EventId, Vec<(usize, f64)>> = HashMap::new();
+    for &call in &call_order {
+        let call_id = adapter.get_event_id(call);
+        let scored: Vec<(usize, f64, Score)> = candidates[&call_id]
+            .iter()
+            .map(|&idx| {
+                let (fraction, score) = score(ranges[idx], call, adapter, policy);
+                (idx, fraction, score)
+            })
+            .collect();
+        let best = scored
+            .iter()
+            .map(|&(_, _, score)| score)
+            .reduce(|best, score| if score > best { score } else { best });
+        let winners: Vec<(usize, f64)> = scored
+            .iter()
+            .filter(|&&(_, _, score)| Some(score) == best)
+            .map(|&(idx, fraction, _)| (idx, fraction))
+            .collect();
+        if scored.len() > 1 {
+            stats.shared_calls += 1;
+        }
+        if winners.len() > 1 {
+            stats.ambiguous_calls += 1;
+        }
+
+        let receivers = match policy {
+            LinkPolicy::All => scored.iter().map(|&(idx, f, _)| (idx, f)).collect(),
+            // Ties go to the first range, sharing the confidence
+            LinkPolicy::Innermost | LinkPolicy::LongestOverlap => {
+                let (idx, fraction) = winners[0];
+                vec![(idx, fraction / winners.len() as f64)]
+            }
+        };
+        links.insert(call_id, receivers);
+    }
+
+    // Each range keeps its attributed calls in overlap order
+    let mut attributed = HashMap::new();
+    for (idx, range) in ranges.iter().enumerate() {
+        let range_id = adapter.get_event_id(range);
+        let calls: Vec<Attributed<'a>> = overlaps
+            .get(&range_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|&call| {
+                let receivers = links.get(&adapter.get_event_id(call))?;
+                let &(_, confidence) = receivers.iter().find(|&&(r, _)| r == idx)?;
+                Some((call, confidence))
+            })
+            .collect();
+        if !calls.is_empty() {
+            attributed.insert(range_id, calls);
+        }
+    }
+    (attributed, stats)
+}
+
+/// Attribute calls overlapping no range to the last range that ended before them
+///
+/// A call goes to the range on its thread with the latest end at or before the
+/// call's start, the innermost of ranges ending together, when that end is at
+/// most `lookback_ns` before the call. Links are appended to `attributed` with
+/// confidence 0. Returns the IDs of the calls attributed.
+pub(crate) fn attribute_sticky_calls<'a>(
+    ranges: &[&'a ChromeTraceEvent],
+    calls: &[&'a ChromeTraceEvent],
+    overlaps: &HashMap<EventId, Vec<&'a ChromeTraceEvent>>,
+    attributed: &mut HashMap<EventId, Vec<Attributed<'a>>>,
+    adapter: &dyn EventAdapter,
+    lookback_ns: i64,
+) -> HashSet<EventId> {
+    let overlapping: HashSet<EventId> = overlaps
+        .values()
+        .flatten()
+        .map(|&call| adapter.get_event_id(call))
+        .collect();
+
+    // Ranges of each thread by (end, start), so the last one ending before a
+    // call is found by binary search
+    let mut by_thread: HashMap<i64, Vec<(i64, i64, usize)>> = HashMap::new();
+    for (idx, range) in ranges.iter().enumerate() {
+        if let (Some(tid), Some((start, end))) =
+            (adapter.get_thread_id(range), adapter.get_time_range_ns(range))
+        {
+            by_thread.entry(tid).or_default().push((end, start, idx));
+        }
+    }
+    for thread_ranges in by_thread.values_mut() {
+        thread_ranges.sort_unstable();
+    }
+
+    let mut sticky = HashSet::new();
+    for &call in calls {
+        let call_id = adapter.get_event_id(call);
+        if overlapping.contains(&call_id) {
+            continue;
+        }
+        let (Some(tid), Some((call_start, _))) =
+            (adapter.get_thread_id(call), adapter.get_time_range_ns(call))
+        else {
+            continue;
+        };
+        let Some(thread_ranges) = by_thread.get(&tid) else {
+            continue;
+        };
+        let before = thread_ranges.partition_point(|&(end, _, _)| end <= call_start);
+        let Some(&(end, _, idx)) = before.checked_sub(1).map(|i| &thread_ranges[i]) else {
+            continue;
+        };
+        if call_start - end > lookback_ns {
+            continue;
+        }
+        attributed
+            .entry(adapter.get_event_id(ranges[idx]))
+            .or_default()
+            .push((call, 0.0));
+        sticky.insert(call_id);
+    }
+    sticky
+}
+
+/// Fraction of the call inside the range, and the range's score for the call
+fn score(
+    range: &ChromeTraceEvent,
+    call: &ChromeTraceEvent,
+    adapter: &dyn EventAdapter,
+    policy: LinkPolicy,
+) -> (f64, Score) {
+    let (range_start, range_end) = adapter.get_time_range_ns(range).unwrap_or((0, 0));
+    let (call_start, call_end) = adapter.get_time_range_ns(call).unwrap_or((0, 0));
+    let overlap_ns = (range_end.min(call_end) - range_start.max(call_start)).max(0);
+    let fraction = if call_end > call_start {
+        overlap_ns as f64 / (call_end - call_start) as f64
+    } else {
+        1.0
+    };
+    // Negated so that shorter (more deeply nested) ranges score higher
+    let shortness = -((range_end - range_start) as f64);
+    let score = match policy {
+        LinkPolicy::LongestOverlap => Score {
+            primary: overlap_ns as f64,
+            secondary: shortness,
+        },
+        LinkPolicy::All | LinkPolicy::Innermost => Score {
+            primary: shortness,
+            secondary: overlap_ns as f64,
+        },
+    };
+    (fraction, score)
+}
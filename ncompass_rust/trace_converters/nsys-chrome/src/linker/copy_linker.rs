@@ -0,0 +1,99 @@
+//! Link copies on copy engine tracks to the CUDA API calls issuing them
+//!
+//! A `cudaMemcpyAsync` and the DMA transfer it enqueues share a correlation
+//! ID on their device, like a launch and its kernel. Each linked copy gets
+//! the issuing call's name and the share of its time overlapped by kernels
+//! on the same device, so copy/compute overlap can be read per engine.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::linker::nvtx_linker::{create_flow_events, flow_id};
+use crate::models::ChromeTraceEvent;
+use crate::parsers::memcpy::MEMCPY_CATEGORY;
+
+fn span_ns(event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+    let start = event.args.get("start_ns").and_then(|v| v.as_i64())?;
+    let end = event.args.get("end_ns").and_then(|v| v.as_i64())?;
+    Some((start, end))
+}
+
+fn arg_i64(event: &ChromeTraceEvent, key: &str) -> Option<i64> {
+    event.args.get(key).and_then(|v| v.as_i64())
+}
+
+/// Link memcpy events among `events` to their API calls and kernels
+///
+/// Copies get an `api` arg naming the issuing call and a
+/// `compute_overlap_pct` arg. Returns flow events drawing an arrow from each
+/// call to its copy.
+pub fn link_copies_to_api_calls(
+    events: &mut [ChromeTraceEvent],
+    api_events: &[ChromeTraceEvent],
+    kernel_events: &[ChromeTraceEvent],
+) -> Vec<ChromeTraceEvent> {
+    let mut calls: HashMap<(i64, i64), &ChromeTraceEvent> = HashMap::new();
+    for call in api_events {
+        if let (Some(device), Some(corr_id)) =
+            (arg_i64(call, "deviceId"), arg_i64(call, "correlationId"))
+        {
+            calls.insert((device, corr_id), call);
+        }
+    }
+
+    // Merged kernel intervals per device, sorted by start
+    let mut busy: HashMap<i64, Vec<(i64, i64)>> = HashMap::new();
+    for kernel in kernel_events {
+        if let (Some(device), Some(span)) = (arg_i64(kernel, "deviceId"), span_ns(kernel)) {
+            busy.entry(device).or_default().push(span);
+        }
+    }
+    for intervals in busy.values_mut() {
+        intervals.sort_unstable();
+        let mut merged: Vec<(i64, i64)> = Vec::with_capacity(intervals.len());
+        for &(start, end) in intervals.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *intervals = merged;
+    }
+
+    let mut flow_events = Vec::new();
+    for copy in events.iter_mut().filter(|e| e.cat == MEMCPY_CATEGORY) {
+        let Some(device) = arg_i64(copy, "deviceId") else {
+            continue;
+        };
+
+        if let Some((start, end)) = span_ns(copy).filter(|(start, end)| end > start) {
+            let overlap_ns: i64 = busy
+                .get(&device)
+                .map(|intervals| {
+                    let first = intervals.partition_point(|&(_, k_end)| k_end <= start);
+                    intervals[first..]
+                        .iter()
+                        .take_while(|&&(k_start, _)| k_start < end)
+                        .map(|&(k_start, k_end)| k_end.min(end) - k_start.max(start))
+                        .sum()
+                })
+                .unwrap_or(0);
+            let pct = overlap_ns as f64 / (end - start) as f64 * 100.0;
+            copy.args.insert(
+                "compute_overlap_pct".into(),
+                json!((pct * 10.0).round() / 10.0),
+            );
+        }
+
+        let Some(corr_id) = arg_i64(copy, "correlationId") else {
+            continue;
+        };
+        if let Some(call) = calls.get(&(device, corr_id)) {
+            copy.args.insert("api".into(), json!(call.name));
+            let (start, finish) = create_flow_events(call, copy, flow_id(device as i32, corr_id));
+            flow_events.push(start);
+            flow_events.push(finish);
+        }
+    }
+    flow_events
+}
@@ -0,0 +1,194 @@
+//! Sorted correlation index for looking up a subset of kernels
+//!
+//! [`build_correlation_map`](crate::linker::build_correlation_map) groups every
+//! kernel of a device by correlation ID, although linking often queries only a
+//! few of them: the calls inside NVTX-filtered ranges, or one window of calls.
+//! On full-job captures the map costs a vector per correlation ID. The index
+//! instead keeps one sorted vector: kernels are first narrowed to those
+//! starting within the time span of the queried API calls (a kernel never
+//! starts before the call that launched it, nor later than `max_link_gap_ns`
+//! after it ended when a maximum gap is set) and launched by one of them, then
+//! sorted by correlation ID for binary-search lookups.
+//!
+//! Windowed linking queries many small sets of calls in time order, so it
+//! sorts the kernels by start once into a [`KernelTimeline`] and builds each
+//! window's index from that. The timeline also keeps the smallest correlation
+//! ID starting at or after each kernel, so a lookup stops scanning once no
+//! later kernel can carry one of the queried IDs.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::linker::adapters::EventAdapter;
+use crate::models::ChromeTraceEvent;
+
+/// Kernels sorted by correlation ID, then start time
+#[derive(Debug, Default)]
+pub struct CorrelationIndex<'a> {
+    kernels: Vec<(i64, i64, &'a ChromeTraceEvent)>,
+}
+
+impl<'a> CorrelationIndex<'a> {
+    /// Index every kernel with a correlation ID
+    pub fn new(kernel_events: &[&'a ChromeTraceEvent], adapter: &dyn EventAdapter) -> Self {
+        let kernels = kernel_events
+            .iter()
+            .filter_map(|&kernel| {
+                let correlation_id = adapter.get_correlation_id(kernel)?;
+                let start = adapter.get_time_range_ns(kernel).map_or(i64::MIN, |r| r.0);
+                Some((correlation_id, start, kernel))
+            })
+            .collect();
+        Self::sorted(kernels)
+    }
+
+    /// Index only the kernels `api_events` can have launched
+    ///
+    /// Returns an empty index without touching the kernels when no call has a
+    /// correlation ID.
+    pub fn for_calls(
+        kernel_events: &[&'a ChromeTraceEvent],
+        api_events: &[&ChromeTraceEvent],
+        adapter: &dyn EventAdapter,
+        max_link_gap_ns: Option<i64>,
+    ) -> Self {
+        if !api_events
+            .iter()
+            .any(|&call| adapter.get_correlation_id(call).is_some())
+        {
+            return Self::default();
+        }
+        KernelTimeline::new(kernel_events, adapter).index_for(api_events, adapter, max_link_gap_ns)
+    }
+
+    fn sorted(mut kernels: Vec<(i64, i64, &'a ChromeTraceEvent)>) -> Self {
+        kernels.sort_by_key(|&(correlation_id, start, _)| (correlation_id, start));
+        log::debug!("Correlation index holds {} kernels", kernels.len());
+        Self { kernels }
+    }
+
+    /// Number of indexed kernels
+    pub fn len(&self) -> usize {
+        self.kernels.len()
+    }
+
+    /// Whether no kernel is indexed
+    pub fn is_empty(&self) -> bool {
+        self.kernels.is_empty()
+    }
+
+    /// Kernels with a correlation ID, in start order
+    pub fn get(&self, correlation_id: i64) -> impl Iterator<Item = &'a ChromeTraceEvent> + '_ {
+        let first = self.kernels.partition_point(|k| k.0 < correlation_id);
+        let last = self.kernels.partition_point(|k| k.0 <= correlation_id);
+        self.kernels[first..last]
+            .iter()
+            .map(|&(_, _, kernel)| kernel)
+    }
+
+    /// Kernels launched by each call, in call order
+    pub fn kernels_for(
+        &self,
+        api_events: &[&ChromeTraceEvent],
+        adapter: &dyn EventAdapter,
+    ) -> Vec<&'a ChromeTraceEvent> {
+        api_events
+            .iter()
+            .filter_map(|&call| adapter.get_correlation_id(call))
+            .flat_map(|correlation_id| self.get(correlation_id))
+            .collect()
+    }
+
+    /// Group the indexed kernels by correlation ID
+    pub fn into_map(self) -> HashMap<i64, Vec<&'a ChromeTraceEvent>> {
+        let mut map: HashMap<i64, Vec<&ChromeTraceEvent>> = HashMap::new();
+        for (correlation_id, _, kernel) in self.kernels {
+            map.entry(correlation_id).or_default().push(kernel);
+        }
+        map
+    }
+}
+
+/// Kernels with a correlation ID in start order, for indexing one set of
+/// calls at a time
+#[derive(Debug, Default)]
+pub struct KernelTimeline<'a> {
+    /// (start, correlation ID, kernel), sorted by start
+    kernels: Vec<(i64, i64, &'a ChromeTraceEvent)>,
+    /// Smallest correlation ID among the kernels from each position on
+    min_after: Vec<i64>,
+}
+
+impl<'a> KernelTimeline<'a> {
+    pub fn new(kernel_events: &[&'a ChromeTraceEvent], adapter: &dyn EventAdapter) -> Self {
+        let mut kernels: Vec<(i64, i64, &ChromeTraceEvent)> = kernel_events
+            .iter()
+            .filter_map(|&kernel| {
+                let start = adapter.get_time_range_ns(kernel)?.0;
+                Some((start, adapter.get_correlation_id(kernel)?, kernel))
+            })
+            .collect();
+        kernels.sort_unstable_by_key(|&(start, correlation_id, _)| (start, correlation_id));
+
+        let mut min_after = vec![i64::MAX; kernels.len()];
+        let mut min = i64::MAX;
+        for (slot, &(_, correlation_id, _)) in min_after.iter_mut().zip(&kernels).rev() {
+            min = min.min(correlation_id);
+            *slot = min;
+        }
+        Self { kernels, min_after }
+    }
+
+    /// Index only the kernels `api_events` can have launched
+    ///
+    /// Scans the kernels starting after the first call, up to `max_link_gap_ns`
+    /// after the last call ended and no further than the last kernel that can
+    /// carry one of the calls' correlation IDs.
+    pub fn index_for(
+        &self,
+        api_events: &[&ChromeTraceEvent],
+        adapter: &dyn EventAdapter,
+        max_link_gap_ns: Option<i64>,
+    ) -> CorrelationIndex<'a> {
+        let mut wanted = HashSet::new();
+        let mut span: Option<(i64, i64)> = None;
+        for &call in api_events {
+            let (Some(correlation_id), Some((start, end))) = (
+                adapter.get_correlation_id(call),
+                adapter.get_time_range_ns(call),
+            ) else {
+                continue;
+            };
+            wanted.insert(correlation_id);
+            span = Some(span.map_or((start, end), |(lo, hi)| (lo.min(start), hi.max(end))));
+        }
+        let (Some((span_start, span_end)), Some(&max_wanted)) = (span, wanted.iter().max()) else {
+            return CorrelationIndex::default();
+        };
+        let latest_start = max_link_gap_ns.map_or(i64::MAX, |gap| span_end.saturating_add(gap));
+
+        let first = self
+            .kernels
+            .partition_point(|&(start, _, _)| start < span_start);
+        let kernels = self.kernels[first..]
+            .iter()
+            .zip(&self.min_after[first..])
+            .take_while(|&(&(start, _, _), &min)| start <= latest_start && min <= max_wanted)
+            .filter(|&(&(_, correlation_id, _), _)| wanted.contains(&correlation_id))
+            .map(|(&(start, correlation_id, kernel), _)| (correlation_id, start, kernel))
+            .collect();
+        CorrelationIndex::sorted(kernels)
+    }
+}
+
+/// Build the correlation map of only the kernels `api_events` can have launched
+///
+/// Same lookups as [`build_correlation_map`](crate::linker::build_correlation_map)
+/// for the correlation IDs of `api_events`, without grouping the rest.
+pub fn build_correlation_map_for_calls<'a>(
+    kernel_events: &[&'a ChromeTraceEvent],
+    api_events: &[&ChromeTraceEvent],
+    adapter: &dyn EventAdapter,
+    max_link_gap_ns: Option<i64>,
+) -> HashMap<i64, Vec<&'a ChromeTraceEvent>> {
+    CorrelationIndex::for_calls(kernel_events, api_events, adapter, max_link_gap_ns).into_map()
+}
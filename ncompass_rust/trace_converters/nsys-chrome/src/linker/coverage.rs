@@ -0,0 +1,212 @@
+//! How much of a trace NVTX linking explained
+//!
+//! Broken instrumentation rarely fails loudly: a range pushed on the wrong
+//! thread or popped before its launches simply ends up with no kernels.
+//! Coverage counts the ranges that received at least one kernel and the
+//! share of kernel time that landed in any range, so a low number points at
+//! missing or misplaced annotations right after conversion.
+//!
+//! The kernel time outside every range can also be drawn: stretches where a
+//! device runs kernels but no nvtx-kernel range is open become `Unattributed
+//! GPU work` slices on their own track, showing where instrumentation misses.
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::category::EventCategory;
+use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
+use crate::linker::algorithms::{merge_intervals, merged_ranges};
+use crate::linker::nvtx_linker::nvtx_kernel_device;
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase};
+
+/// Arg set on NVTX ranges that received no kernels when tagging is enabled
+pub const UNATTRIBUTED_ARG: &str = "unattributed";
+
+/// Name of the slices covering kernel time outside every NVTX range
+pub const UNATTRIBUTED_WORK_EVENT: &str = "Unattributed GPU work";
+
+/// Thread name for unattributed GPU work slices
+pub const UNATTRIBUTED_WORK_TRACK: &str = "Unattributed GPU Work";
+
+/// Category of unattributed GPU work slices
+pub const UNATTRIBUTED_WORK_CATEGORY: &str = "coverage_gap";
+
+/// Coverage of NVTX-kernel linking
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct NvtxCoverage {
+    /// NVTX ranges given to the linker
+    pub ranges: usize,
+    /// Ranges that received at least one kernel
+    pub covered_ranges: usize,
+    /// Total kernel time
+    pub kernel_ns: i64,
+    /// Kernel time attributed to at least one range
+    pub attributed_kernel_ns: i64,
+}
+
+fn pct(part: i64, whole: i64) -> f64 {
+    if whole <= 0 {
+        return 0.0;
+    }
+    (part as f64 / whole as f64 * 1000.0).round() / 10.0
+}
+
+impl NvtxCoverage {
+    /// Measure coverage from the linker's inputs and outputs
+    ///
+    /// `uncovered_ranges` is the number of ranges the linker handed back
+    /// unmapped.
+    pub fn measure(
+        ranges: usize,
+        uncovered_ranges: usize,
+        kernel_events: &[ChromeTraceEvent],
+        attributed_kernel_ns: i64,
+    ) -> Self {
+        let adapter = NsysEventAdapter;
+        let kernel_ns = kernel_events
+            .iter()
+            .filter_map(|kernel| adapter.get_time_range_ns(kernel))
+            .map(|(start, end)| end - start)
+            .sum();
+        Self {
+            ranges,
+            covered_ranges: ranges.saturating_sub(uncovered_ranges),
+            kernel_ns,
+            attributed_kernel_ns,
+        }
+    }
+
+    /// Ranges that received no kernels
+    pub fn uncovered_ranges(&self) -> usize {
+        self.ranges - self.covered_ranges
+    }
+
+    /// Share of ranges that received a kernel, in percent
+    pub fn range_pct(&self) -> f64 {
+        pct(self.covered_ranges as i64, self.ranges as i64)
+    }
+
+    /// Share of kernel time attributed to a range, in percent
+    pub fn kernel_time_pct(&self) -> f64 {
+        pct(self.attributed_kernel_ns, self.kernel_ns)
+    }
+
+    /// One-line summary for the CLI
+    pub fn summary(&self) -> String {
+        format!(
+            "NVTX coverage: {}/{} ranges ({:.1}%) have kernels, {:.1}% of kernel time is in a range",
+            self.covered_ranges,
+            self.ranges,
+            self.range_pct(),
+            self.kernel_time_pct()
+        )
+    }
+}
+
+/// Tag NVTX ranges that received no kernels with `unattributed: true`
+pub fn tag_unattributed(unmapped_ranges: &mut [ChromeTraceEvent]) {
+    for range in unmapped_ranges {
+        range.args.insert(UNATTRIBUTED_ARG.into(), json!(true));
+    }
+}
+
+/// A stretch of time where a device ran kernels outside every NVTX range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnattributedWork {
+    pub device_id: i64,
+    pub start_ns: i64,
+    pub end_ns: i64,
+}
+
+impl UnattributedWork {
+    pub fn duration_ns(&self) -> i64 {
+        self.end_ns - self.start_ns
+    }
+}
+
+/// Find kernel time on each device not covered by any nvtx-kernel range
+///
+/// `nvtx_kernel_events` are the linker's output; events of other categories
+/// (such as its flow events) are ignored. A stretch counts as attributed while
+/// any range is open on the device, even if the kernel running then belongs
+/// to none, so the result is what the timeline shows outside the ranges.
+pub fn unattributed_gpu_work(
+    kernel_events: &[ChromeTraceEvent],
+    nvtx_kernel_events: &[ChromeTraceEvent],
+) -> Vec<UnattributedWork> {
+    let adapter = NsysEventAdapter;
+
+    let mut kernels: BTreeMap<i64, Vec<&ChromeTraceEvent>> = BTreeMap::new();
+    for kernel in kernel_events {
+        if let Some(device_id) = kernel.args.get("deviceId").and_then(|v| v.as_i64()) {
+            kernels.entry(device_id).or_default().push(kernel);
+        }
+    }
+    let mut ranges: BTreeMap<i64, Vec<(i64, i64)>> = BTreeMap::new();
+    for range in nvtx_kernel_events
+        .iter()
+        .filter(|e| e.cat == EventCategory::NvtxKernel && e.ph == ChromeTracePhase::Complete)
+    {
+        let (Some(device_id), Some(dur)) = (nvtx_kernel_device(range), range.dur) else {
+            continue;
+        };
+        let start_ns = (range.ts * 1000.0).round() as i64;
+        let end_ns = ((range.ts + dur) * 1000.0).round() as i64;
+        ranges
+            .entry(device_id)
+            .or_default()
+            .push((start_ns, end_ns));
+    }
+
+    let mut work = Vec::new();
+    for (device_id, kernels) in kernels {
+        let ranges = ranges.remove(&device_id).unwrap_or_default();
+        let covered = merged_ranges(&ranges.iter().collect::<Vec<_>>());
+        for (busy_start, busy_end) in merge_intervals(&kernels, &adapter) {
+            // Ranges are sorted and disjoint: skip those ending before the busy run
+            let first = covered.partition_point(|&(_, end)| end <= busy_start);
+            let mut cursor = busy_start;
+            for &(start, end) in covered[first..]
+                .iter()
+                .take_while(|&&(start, _)| start < busy_end)
+            {
+                if start > cursor {
+                    work.push(UnattributedWork {
+                        device_id,
+                        start_ns: cursor,
+                        end_ns: start,
+                    });
+                }
+                cursor = cursor.max(end);
+            }
+            if cursor < busy_end {
+                work.push(UnattributedWork {
+                    device_id,
+                    start_ns: cursor,
+                    end_ns: busy_end,
+                });
+            }
+        }
+    }
+    work
+}
+
+/// Build `Unattributed GPU work` complete events for unattributed stretches
+pub fn unattributed_work_events(work: &[UnattributedWork]) -> Vec<ChromeTraceEvent> {
+    work.iter()
+        .map(|stretch| {
+            ChromeTraceEvent::complete(
+                UNATTRIBUTED_WORK_EVENT.to_string(),
+                ns_to_us(stretch.start_ns),
+                ns_to_us(stretch.duration_ns()),
+                format!("Device {}", stretch.device_id),
+                UNATTRIBUTED_WORK_TRACK.to_string(),
+                UNATTRIBUTED_WORK_CATEGORY.to_string(),
+            )
+            .with_arg("deviceId", json!(stretch.device_id))
+            .with_arg("start_ns", json!(stretch.start_ns))
+            .with_arg("end_ns", json!(stretch.end_ns))
+        })
+        .collect()
+}
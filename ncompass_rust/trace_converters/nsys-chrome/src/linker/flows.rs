@@ -0,0 +1,187 @@
+//! Flow arrow representation, applied once every link exists
+//!
+//! Linkers emit each arrow as a `s`/`f` event pair whose finish binds to the
+//! enclosing slice. Perfetto draws an arrow bound to the next slice from the
+//! finish timestamp instead, and also reads the newer representation where
+//! the linked slices carry a `bind_id` with `flow_out`/`flow_in`. Both are
+//! chosen per kind of link through [`FlowOptions`].
+
+use std::collections::HashMap;
+
+use crate::error::{ConvertError, Result};
+use crate::linker::mpi_linker::MPI_CATEGORY;
+use crate::models::{
+    BindingPoint, ChromeTraceEvent, ChromeTracePhase, FlowBind, FlowLink, FlowOptions, FlowStyle,
+    StringOrInt,
+};
+use crate::parsers::memcpy::MEMCPY_CATEGORY;
+
+/// Parse `events` or `bound` into a flow style
+pub fn parse_flow_style(value: &str) -> Result<FlowStyle> {
+    match value.trim() {
+        "events" => Ok(FlowStyle::Events),
+        "bound" => Ok(FlowStyle::Bound),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid flow style '{}' (use events or bound)",
+            value
+        ))),
+    }
+}
+
+/// Parse `enclosing` or `next` into a flow binding
+pub fn parse_flow_bind(value: &str) -> Result<FlowBind> {
+    match value.trim() {
+        "enclosing" => Ok(FlowBind::Enclosing),
+        "next" => Ok(FlowBind::Next),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid flow binding '{}' (use enclosing or next)",
+            value
+        ))),
+    }
+}
+
+/// Parse `launch`, `copy`, `mpi` or `all` into the link kinds it names
+pub fn parse_flow_links(value: &str) -> Result<Vec<FlowLink>> {
+    match value.trim() {
+        "launch" => Ok(vec![FlowLink::Launch]),
+        "copy" => Ok(vec![FlowLink::Copy]),
+        "mpi" => Ok(vec![FlowLink::Mpi]),
+        "all" => Ok(FlowLink::ALL.to_vec()),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid flow link '{}' (use launch, copy, mpi or all)",
+            value
+        ))),
+    }
+}
+
+/// Parse a `LINK=BIND` spec such as `launch=next` or `all=enclosing`
+pub fn parse_flow_bind_spec(value: &str) -> Result<(Vec<FlowLink>, FlowBind)> {
+    let (links, bind) = value.split_once('=').ok_or_else(|| {
+        ConvertError::InvalidOption(format!(
+            "Invalid flow binding '{}' (expected LINK=BIND, e.g. launch=next)",
+            value
+        ))
+    })?;
+    Ok((parse_flow_links(links)?, parse_flow_bind(bind)?))
+}
+
+/// Kind of link between a flow's source and target slices
+pub fn classify_flow(
+    source: Option<&ChromeTraceEvent>,
+    target: Option<&ChromeTraceEvent>,
+) -> FlowLink {
+    if source.is_some_and(|s| s.cat == MPI_CATEGORY) {
+        FlowLink::Mpi
+    } else if target.is_some_and(|t| t.cat == MEMCPY_CATEGORY) {
+        FlowLink::Copy
+    } else {
+        FlowLink::Launch
+    }
+}
+
+/// Indices of a flow event pair and of the slices it links
+struct FlowPair {
+    start: usize,
+    finish: usize,
+    source: Option<usize>,
+    target: Option<usize>,
+    link: FlowLink,
+}
+
+/// Rewrite `s`/`f` flow pairs as `options` asks
+///
+/// Each pair's source and target are the complete events starting at the
+/// flow's timestamps on its tracks. Finishes get the binding of their kind of
+/// link. Under [`FlowStyle::Bound`] a pair whose slices are both found and
+/// not yet bound moves onto the slices; a slice holds one `bind_id`, so any
+/// further arrow through it stays a flow event pair.
+pub fn apply_flow_options(
+    events: Vec<ChromeTraceEvent>,
+    options: &FlowOptions,
+) -> Vec<ChromeTraceEvent> {
+    if *options == FlowOptions::default() {
+        return events;
+    }
+    let mut events = events;
+
+    let mut slices: HashMap<(&str, &str, u64), usize> = HashMap::new();
+    let mut starts: HashMap<&StringOrInt, Vec<usize>> = HashMap::new();
+    let mut finishes: HashMap<&StringOrInt, Vec<usize>> = HashMap::new();
+    for (index, event) in events.iter().enumerate() {
+        match (event.ph, &event.id) {
+            (ChromeTracePhase::Complete, _) => {
+                slices
+                    .entry((event.pid.as_str(), event.tid.as_str(), event.ts.to_bits()))
+                    .or_insert(index);
+            }
+            (ChromeTracePhase::FlowStart, Some(id)) => starts.entry(id).or_default().push(index),
+            (ChromeTracePhase::FlowFinish, Some(id)) => finishes.entry(id).or_default().push(index),
+            _ => {}
+        }
+    }
+    let slice_at = |event: &ChromeTraceEvent| {
+        slices
+            .get(&(event.pid.as_str(), event.tid.as_str(), event.ts.to_bits()))
+            .copied()
+    };
+
+    let mut pairs = Vec::new();
+    for (id, start_indices) in &starts {
+        let Some(finish_indices) = finishes.get(id) else {
+            continue;
+        };
+        for (&start, &finish) in start_indices.iter().zip(finish_indices) {
+            let source = slice_at(&events[start]);
+            let target = slice_at(&events[finish]);
+            let link = classify_flow(source.map(|i| &events[i]), target.map(|i| &events[i]));
+            pairs.push(FlowPair {
+                start,
+                finish,
+                source,
+                target,
+                link,
+            });
+        }
+    }
+    // Earlier arrows claim slices first
+    pairs.sort_unstable_by_key(|pair| pair.start);
+
+    let mut removed = vec![false; events.len()];
+    for FlowPair {
+        start,
+        finish,
+        source,
+        target,
+        link,
+    } in pairs
+    {
+        if options.style == FlowStyle::Bound {
+            if let (Some(source), Some(target)) = (source, target) {
+                if source != target
+                    && events[source].bind_id.is_none()
+                    && events[target].bind_id.is_none()
+                {
+                    let id = events[start].id.clone();
+                    events[source].bind_id = id.clone();
+                    events[source].flow_out = Some(true);
+                    events[target].bind_id = id;
+                    events[target].flow_in = Some(true);
+                    removed[start] = true;
+                    removed[finish] = true;
+                    continue;
+                }
+            }
+        }
+        events[finish].bp = match options.bind(link) {
+            FlowBind::Enclosing => Some(BindingPoint::Enclosing),
+            FlowBind::Next => None,
+        };
+    }
+
+    events
+        .into_iter()
+        .zip(removed)
+        .filter(|(_, removed)| !removed)
+        .map(|(event, _)| event)
+        .collect()
+}
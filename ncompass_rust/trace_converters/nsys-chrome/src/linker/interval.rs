@@ -0,0 +1,83 @@
+//! Minimal traits the linking algorithms need from an event
+//!
+//! The sweep-line and correlation algorithms in [`crate::linker::algorithms`]
+//! only look at an event's time range, thread and correlation ID. Types
+//! implementing [`HasTimeRange`] (and [`HasCorrelation`] where IDs matter) can
+//! use them directly, so other tools can link their own event structs without
+//! building `ChromeTraceEvent`s. Chrome trace events go through an
+//! [`EventAdapter`] instead, which [`Adapted`] bridges to these traits.
+
+use crate::linker::adapters::EventAdapter;
+use crate::models::ChromeTraceEvent;
+
+/// Event with a time range in nanoseconds
+pub trait HasTimeRange {
+    /// Start and end in nanoseconds, or None if the event has no usable range
+    fn time_range_ns(&self) -> Option<(i64, i64)>;
+
+    /// Thread the event ran on, used to partition overlap detection
+    fn thread_id(&self) -> Option<i64> {
+        None
+    }
+}
+
+/// Event carrying a correlation ID that ties a launch to the work it started
+pub trait HasCorrelation {
+    /// Correlation ID, or None if the event has none
+    fn correlation_id(&self) -> Option<i64>;
+}
+
+/// A plain `(start, end)` pair in nanoseconds
+impl HasTimeRange for (i64, i64) {
+    fn time_range_ns(&self) -> Option<(i64, i64)> {
+        Some(*self)
+    }
+}
+
+impl<T: HasTimeRange + ?Sized> HasTimeRange for &T {
+    fn time_range_ns(&self) -> Option<(i64, i64)> {
+        (**self).time_range_ns()
+    }
+
+    fn thread_id(&self) -> Option<i64> {
+        (**self).thread_id()
+    }
+}
+
+impl<T: HasCorrelation + ?Sized> HasCorrelation for &T {
+    fn correlation_id(&self) -> Option<i64> {
+        (**self).correlation_id()
+    }
+}
+
+/// A Chrome trace event read through an adapter
+pub struct Adapted<'e, 'x, A: ?Sized> {
+    pub event: &'e ChromeTraceEvent,
+    pub adapter: &'x A,
+}
+
+impl<'e, 'x, A: EventAdapter + ?Sized> Adapted<'e, 'x, A> {
+    /// Pair every event of a slice with `adapter`
+    pub fn all(events: &[&'e ChromeTraceEvent], adapter: &'x A) -> Vec<Self> {
+        events
+            .iter()
+            .map(|&event| Adapted { event, adapter })
+            .collect()
+    }
+}
+
+impl<A: EventAdapter + ?Sized> HasTimeRange for Adapted<'_, '_, A> {
+    fn time_range_ns(&self) -> Option<(i64, i64)> {
+        self.adapter.get_time_range_ns(self.event)
+    }
+
+    fn thread_id(&self) -> Option<i64> {
+        self.adapter.get_thread_id(self.event)
+    }
+}
+
+impl<A: EventAdapter + ?Sized> HasCorrelation for Adapted<'_, '_, A> {
+    fn correlation_id(&self) -> Option<i64> {
+        self.adapter.get_correlation_id(self.event)
+    }
+}
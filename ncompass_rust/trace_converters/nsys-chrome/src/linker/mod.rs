@@ -2,12 +2,47 @@
 
 pub mod adapters;
 pub mod algorithms;
+pub mod attribution;
+pub mod copy_linker;
+pub mod correlation_index;
+pub mod coverage;
+pub mod flows;
+pub mod interval;
+pub mod mpi_linker;
 pub mod nvtx_linker;
+pub mod time_shift;
+pub mod windowed;
 
-pub use adapters::{EventAdapter, NsysEventAdapter};
+pub use adapters::{
+    EventAdapter, KinetoEventAdapter, MixedEventAdapter, NsysEventAdapter, TimeUnit,
+};
 pub use algorithms::{
-    aggregate_kernel_times, build_correlation_map, find_kernels_for_annotation,
-    find_overlapping_intervals,
+    aggregate_kernel_times, build_correlation_map, correlated_events, correlation_map,
+    covered_time, find_kernels_for_annotation, find_overlapping_intervals,
+    find_overlapping_intervals_by_thread, merge_intervals, merged_ranges, overlapping_intervals,
+    overlapping_intervals_by_thread, time_span, total_covered_time,
+};
+pub use attribution::{parse_link_policy, LinkStats};
+pub use copy_linker::link_copies_to_api_calls;
+pub use correlation_index::{build_correlation_map_for_calls, CorrelationIndex, KernelTimeline};
+pub use coverage::{
+    tag_unattributed, unattributed_gpu_work, unattributed_work_events, NvtxCoverage,
+    UnattributedWork, UNATTRIBUTED_ARG, UNATTRIBUTED_WORK_CATEGORY, UNATTRIBUTED_WORK_EVENT,
+    UNATTRIBUTED_WORK_TRACK,
+};
+pub use flows::{
+    apply_flow_options, classify_flow, parse_flow_bind, parse_flow_bind_spec, parse_flow_links,
+    parse_flow_style,
+};
+pub use interval::{Adapted, HasCorrelation, HasTimeRange};
+pub use mpi_linker::{is_nccl_kernel, link_mpi_to_nccl_kernels};
+pub use nvtx_linker::{
+    flow_id, link_nvtx_to_kernels, link_nvtx_to_kernels_with_stats, parse_nvtx_kernel_layout,
+    NvtxIdentifier,
+};
+pub use time_shift::{
+    align_annotations, apply_time_shift, estimate_time_shift, parse_time_shift,
+    parse_time_shift_spec,
 };
-pub use nvtx_linker::link_nvtx_to_kernels;
+pub use windowed::link_nvtx_to_kernels_windowed;
 
@@ -0,0 +1,116 @@
+//! Link MPI calls to the NCCL kernels running during them
+//!
+//! Collective imbalance across ranks shows up as NCCL kernels on one rank
+//! waiting for the others. Tagging each NCCL kernel with the MPI call, rank and
+//! communicator it ran under lets those kernels be compared rank by rank.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::category::EventCategory;
+use crate::models::{BindingPoint, ChromeTraceEvent, StringOrInt};
+
+/// Category of MPI call events
+pub const MPI_CATEGORY: &str = "mpi";
+
+/// MPI call args copied onto linked kernels, with their kernel arg names
+const LINKED_ARGS: &[(&str, &str)] = &[
+    ("rank", "mpi_rank"),
+    ("communicator", "mpi_communicator"),
+    ("comm_size", "mpi_comm_size"),
+];
+
+/// Whether a kernel was launched by NCCL
+pub fn is_nccl_kernel(event: &ChromeTraceEvent) -> bool {
+    event.cat == EventCategory::Kernel && event.name.to_ascii_lowercase().contains("nccl")
+}
+
+fn span_ns(event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+    let start = event.args.get("start_ns").and_then(|v| v.as_i64())?;
+    let end = event.args.get("end_ns").and_then(|v| v.as_i64())?;
+    Some((start, end))
+}
+
+fn device_id(event: &ChromeTraceEvent) -> Option<i64> {
+    event.args.get("deviceId").and_then(|v| v.as_i64())
+}
+
+/// Attach MPI call args to the NCCL kernels overlapping MPI calls on their device
+///
+/// A kernel overlapping several calls is linked to the one it overlaps most.
+/// Each linked MPI call gets an `nccl_kernels` count. Returns flow events
+/// drawing an arrow from each call to its kernels.
+pub fn link_mpi_to_nccl_kernels(
+    events: &mut [ChromeTraceEvent],
+    kernel_events: &mut [ChromeTraceEvent],
+) -> Vec<ChromeTraceEvent> {
+    // MPI call indices per device, sorted by start
+    let mut per_device: HashMap<i64, Vec<(i64, i64, usize)>> = HashMap::new();
+    for (index, event) in events.iter().enumerate() {
+        if event.cat != MPI_CATEGORY {
+            continue;
+        }
+        if let (Some(device), Some((start, end))) = (device_id(event), span_ns(event)) {
+            per_device
+                .entry(device)
+                .or_default()
+                .push((start, end, index));
+        }
+    }
+    for calls in per_device.values_mut() {
+        calls.sort_unstable();
+    }
+
+    let mut linked_counts: HashMap<usize, usize> = HashMap::new();
+    let mut flow_events = Vec::new();
+    for kernel in kernel_events.iter_mut().filter(|k| is_nccl_kernel(k)) {
+        let (Some(device), Some((start, end))) = (device_id(kernel), span_ns(kernel)) else {
+            continue;
+        };
+        let Some(calls) = per_device.get(&device) else {
+            continue;
+        };
+
+        let candidates = &calls[..calls.partition_point(|&(call_start, _, _)| call_start <= end)];
+        let best = candidates
+            .iter()
+            .filter(|&&(_, call_end, _)| call_end >= start)
+            .max_by_key(|&&(call_start, call_end, _)| {
+                (call_end.min(end) - call_start.max(start), call_start)
+            });
+        let Some(&(_, _, index)) = best else {
+            continue;
+        };
+
+        let call = &events[index];
+        kernel.args.insert("mpi_call".into(), json!(call.name));
+        for (call_arg, kernel_arg) in LINKED_ARGS {
+            if let Some(value) = call.args.get(*call_arg) {
+                kernel.args.insert((*kernel_arg).into(), value.clone());
+            }
+        }
+
+        let id = StringOrInt::String(format!("mpi-nccl-{}", flow_events.len() / 2));
+        flow_events.push(ChromeTraceEvent::flow_start(
+            call.ts,
+            call.pid.clone(),
+            call.tid.clone(),
+            id.clone(),
+        ));
+        flow_events.push(ChromeTraceEvent::flow_finish(
+            kernel.ts,
+            kernel.pid.clone(),
+            kernel.tid.clone(),
+            id,
+            BindingPoint::Enclosing,
+        ));
+        *linked_counts.entry(index).or_default() += 1;
+    }
+
+    for (index, count) in linked_counts {
+        events[index]
+            .args
+            .insert("nccl_kernels".into(), json!(count));
+    }
+    flow_events
+}
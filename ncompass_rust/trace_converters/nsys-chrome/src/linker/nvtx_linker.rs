@@ -1,70 +1,166 @@
 //! Link NVTX events to kernel events via CUDA API correlation
 
 use log::debug;
+use rayon::prelude::*;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use crate::category::EventCategory;
 use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
 use crate::linker::algorithms::{
-    aggregate_kernel_times, build_correlation_map, find_kernels_for_annotation,
-    find_overlapping_intervals,
+    aggregate_kernel_times, build_correlation_map, covered_time, find_kernels_for_annotation,
+    merge_intervals, overlapping_events_by_thread,
 };
-use crate::models::{BindingPoint, ChromeTraceEvent, ConversionOptions, StringOrInt, ns_to_us};
+use crate::linker::attribution::{attribute_calls, attribute_sticky_calls, LinkStats};
+use crate::error::{ConvertError, Result};
+use crate::frontends::FrontendTrace;
+use crate::intern::InternedStr;
+use crate::models::{
+    BindingPoint, ChromeTraceEvent, ConversionOptions, NvtxKernelLayout, StringOrInt, ns_to_us,
+};
+use crate::self_profile::{current_context, phase};
+
+/// Number of low bits of a flow ID reserved for the correlation ID
+pub const FLOW_ID_CORRELATION_BITS: u32 = 40;
+
+/// Identifies an NVTX event by (device_id, raw_tid, start_ns, name)
+pub type NvtxIdentifier = (i32, i32, i64, String);
+
+/// Numbered copies of the events, if any of them lacks a [`crate::models::EventId`]
+///
+/// Extraction numbers events already; events built by hand or read from a
+/// Chrome trace are numbered here, so linking never meets an unnumbered one.
+pub(crate) fn numbered_copies(
+    nvtx_events: &[ChromeTraceEvent],
+    cuda_api_events: &[ChromeTraceEvent],
+    kernel_events: &[ChromeTraceEvent],
+) -> Option<FrontendTrace> {
+    let mut events = nvtx_events.iter().chain(cuda_api_events).chain(kernel_events);
+    if events.all(|e| e.event_id.is_some()) {
+        return None;
+    }
+    let mut trace = FrontendTrace {
+        kernel_events: kernel_events.to_vec(),
+        api_events: cuda_api_events.to_vec(),
+        annotation_events: nvtx_events.to_vec(),
+        ..Default::default()
+    };
+    trace.assign_event_ids();
+    Some(trace)
+}
+
+/// Events grouped by device ID
+pub(crate) type DeviceEventMap<'a> = HashMap<i32, Vec<&'a ChromeTraceEvent>>;
+
+/// Result of linking: (nvtx-kernel events, mapped NVTX identifiers, flow events)
+pub type LinkResult = (
+    Vec<ChromeTraceEvent>,
+    HashSet<NvtxIdentifier>,
+    Vec<ChromeTraceEvent>,
+);
 
 /// Link NVTX events to kernel events via CUDA API correlation
+///
+/// Events without an [`crate::models::EventId`] are linked through numbered
+/// copies (see [`crate::frontends::FrontendTrace::assign_event_ids`]).
 pub fn link_nvtx_to_kernels<'a>(
     nvtx_events: &'a [ChromeTraceEvent],
     cuda_api_events: &'a [ChromeTraceEvent],
     kernel_events: &'a [ChromeTraceEvent],
     options: &ConversionOptions,
-) -> (
-    Vec<ChromeTraceEvent>,
-    HashSet<(i32, i32, i64, String)>,
-    Vec<ChromeTraceEvent>,
-) {
+) -> LinkResult {
+    link_nvtx_to_kernels_with_stats(nvtx_events, cuda_api_events, kernel_events, options).0
+}
+
+/// Link NVTX events to kernel events, also counting CUDA API calls shared
+/// between ranges under `options.link_policy`
+pub fn link_nvtx_to_kernels_with_stats<'a>(
+    nvtx_events: &'a [ChromeTraceEvent],
+    cuda_api_events: &'a [ChromeTraceEvent],
+    kernel_events: &'a [ChromeTraceEvent],
+    options: &ConversionOptions,
+) -> (LinkResult, LinkStats) {
+    let copies = numbered_copies(nvtx_events, cuda_api_events, kernel_events);
+    let (nvtx_events, cuda_api_events, kernel_events) = match &copies {
+        Some(trace) => (
+            &trace.annotation_events[..],
+            &trace.api_events[..],
+            &trace.kernel_events[..],
+        ),
+        None => (nvtx_events, cuda_api_events, kernel_events),
+    };
+
     // Group events by device ID
     let (per_device_nvtx, per_device_cuda_api, per_device_kernels) =
         group_events_by_device(nvtx_events, cuda_api_events, kernel_events);
 
-    // Get devices that have all three event types
-    let common_devices: HashSet<i32> = per_device_nvtx
+    // Get devices that have all three event types, in a fixed order so the
+    // merged output does not depend on hashing or thread scheduling
+    let mut common_devices: Vec<i32> = per_device_nvtx
         .keys()
         .copied()
-        .collect::<HashSet<_>>()
-        .intersection(&per_device_cuda_api.keys().copied().collect())
-        .copied()
-        .collect::<HashSet<_>>()
-        .intersection(&per_device_kernels.keys().copied().collect())
-        .copied()
+        .filter(|device_id| {
+            per_device_cuda_api.contains_key(device_id) && per_device_kernels.contains_key(device_id)
+        })
         .collect();
+    common_devices.sort_unstable();
 
     // Create adapter
     let adapter = NsysEventAdapter;
 
-    // Process each device
-    let mut all_nvtx_kernel_events = Vec::new();
-    let mut all_mapped_nvtx_identifiers = HashSet::new();
-    let mut all_flow_events = Vec::new();
-
-    for &device_id in &common_devices {
-        let (nvtx_kernel_events, mapped_nvtx_identifiers, flow_events) = process_device_nvtx_events(
-            &per_device_nvtx[&device_id],
-            &per_device_cuda_api[&device_id],
-            &per_device_kernels[&device_id],
-            device_id,
+    // Process each device, concurrently when more than one job is allowed
+    let parent_phase = current_context();
+    let process_device = |device_id: &i32| {
+        let _phase_context = parent_phase.enter();
+        let mut link_phase = phase(format!("link Device {}", device_id), "link");
+        let result = process_device_nvtx_events(
+            &per_device_nvtx[device_id],
+            &per_device_cuda_api[device_id],
+            &per_device_kernels[device_id],
+            *device_id,
             &adapter,
             options,
         );
+        link_phase.set_events(result.0 .0.len());
+        result
+    };
+    let jobs = options.worker_threads().min(common_devices.len());
+    let per_device_results: Vec<(LinkResult, LinkStats)> = if jobs > 1 {
+        match rayon::ThreadPoolBuilder::new().num_threads(jobs).build() {
+            Ok(pool) => pool.install(|| common_devices.par_iter().map(process_device).collect()),
+            Err(e) => {
+                debug!("Linking devices sequentially, thread pool unavailable: {}", e);
+                common_devices.iter().map(process_device).collect()
+            }
+        }
+    } else {
+        common_devices.iter().map(process_device).collect()
+    };
 
+    // Merge in device order
+    let mut all_nvtx_kernel_events = Vec::new();
+    let mut all_mapped_nvtx_identifiers = HashSet::new();
+    let mut all_flow_events = Vec::new();
+    let mut stats = LinkStats::default();
+
+    for ((nvtx_kernel_events, mapped_nvtx_identifiers, flow_events), device_stats) in
+        per_device_results
+    {
         all_nvtx_kernel_events.extend(nvtx_kernel_events);
         all_mapped_nvtx_identifiers.extend(mapped_nvtx_identifiers);
         all_flow_events.extend(flow_events);
+        stats.merge(device_stats);
     }
 
     (
-        all_nvtx_kernel_events,
-        all_mapped_nvtx_identifiers,
-        all_flow_events,
+        (
+            all_nvtx_kernel_events,
+            all_mapped_nvtx_identifiers,
+            all_flow_events,
+        ),
+        stats,
     )
 }
 
@@ -73,11 +169,7 @@ pub(crate) fn group_events_by_device<'a>(
     nvtx_events: &'a [ChromeTraceEvent],
     cuda_api_events: &'a [ChromeTraceEvent],
     kernel_events: &'a [ChromeTraceEvent],
-) -> (
-    HashMap<i32, Vec<&'a ChromeTraceEvent>>,
-    HashMap<i32, Vec<&'a ChromeTraceEvent>>,
-    HashMap<i32, Vec<&'a ChromeTraceEvent>>,
-) {
+) -> (DeviceEventMap<'a>, DeviceEventMap<'a>, DeviceEventMap<'a>) {
     let mut per_device_nvtx: HashMap<i32, Vec<&ChromeTraceEvent>> = HashMap::default();
     let mut per_device_cuda_api: HashMap<i32, Vec<&ChromeTraceEvent>> = HashMap::default();
     let mut per_device_kernels: HashMap<i32, Vec<&ChromeTraceEvent>> = HashMap::default();
@@ -86,11 +178,11 @@ pub(crate) fn group_events_by_device<'a>(
     let mut nvtx_no_times = 0;
     for event in nvtx_events {
         if let Some(device_id) = event.args.get("deviceId").and_then(|v| v.as_i64()) {
-            let has_times = event.args.get("start_ns").is_some() && event.args.get("end_ns").is_some();
+            let has_times = event.args.contains_key("start_ns") && event.args.contains_key("end_ns");
             if has_times {
                 per_device_nvtx
                     .entry(device_id as i32)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(event);
             } else {
                 nvtx_no_times += 1;
@@ -104,10 +196,10 @@ pub(crate) fn group_events_by_device<'a>(
     let mut cuda_api_no_corr = 0;
     for event in cuda_api_events {
         if let Some(device_id) = event.args.get("deviceId").and_then(|v| v.as_i64()) {
-            if event.args.get("correlationId").is_some() {
+            if event.args.contains_key("correlationId") {
                 per_device_cuda_api
                     .entry(device_id as i32)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(event);
             } else {
                 cuda_api_no_corr += 1;
@@ -121,10 +213,10 @@ pub(crate) fn group_events_by_device<'a>(
     let mut kernel_no_corr = 0;
     for event in kernel_events {
         if let Some(device_id) = event.args.get("deviceId").and_then(|v| v.as_i64()) {
-            if event.args.get("correlationId").is_some() {
+            if event.args.contains_key("correlationId") {
                 per_device_kernels
                     .entry(device_id as i32)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(event);
             } else {
                 kernel_no_corr += 1;
@@ -163,6 +255,56 @@ pub(crate) fn group_events_by_device<'a>(
     (per_device_nvtx, per_device_cuda_api, per_device_kernels)
 }
 
+/// Kernels spanned by one NVTX range on one stream (or on all streams)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct KernelGroup {
+    /// Stream of the kernels in per-stream mode, None when spanning all streams
+    pub stream_id: Option<i64>,
+    pub start_ns: i64,
+    pub end_ns: i64,
+    /// Merged intervals with a kernel running
+    pub busy: Vec<(i64, i64)>,
+}
+
+/// Kernels linked to one NVTX range, before they become nvtx-kernel events
+///
+/// Windowed linking spills these and merges the pieces of ranges whose calls
+/// fall into several windows with [`RangeLink::absorb`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RangeLink {
+    /// Index of the range in the device's NVTX list
+    pub range_idx: usize,
+    /// Confidence of the weakest call attributed to the range
+    pub confidence: f64,
+    /// Whether a call outside the range was attributed to it by the sticky lookback
+    #[serde(default)]
+    pub sticky: bool,
+    pub groups: Vec<KernelGroup>,
+}
+
+impl RangeLink {
+    /// Merge another piece of the same range
+    pub fn absorb(&mut self, other: RangeLink) {
+        self.confidence = self.confidence.min(other.confidence);
+        self.sticky |= other.sticky;
+        for group in other.groups {
+            match self
+                .groups
+                .iter_mut()
+                .find(|g| g.stream_id == group.stream_id)
+            {
+                Some(existing) => {
+                    existing.start_ns = existing.start_ns.min(group.start_ns);
+                    existing.end_ns = existing.end_ns.max(group.end_ns);
+                    existing.busy.extend(group.busy);
+                }
+                None => self.groups.push(group),
+            }
+        }
+        self.groups.sort_by_key(|g| g.stream_id);
+    }
+}
+
 /// Process NVTX events for a single device
 fn process_device_nvtx_events(
     nvtx_events_list: &[&ChromeTraceEvent],
@@ -171,59 +313,189 @@ fn process_device_nvtx_events(
     device_id: i32,
     adapter: &NsysEventAdapter,
     options: &ConversionOptions,
-) -> (
-    Vec<ChromeTraceEvent>,
-    HashSet<(i32, i32, i64, String)>,
-    Vec<ChromeTraceEvent>,
-) {
-    let mut nvtx_kernel_events = Vec::new();
-    let mut mapped_nvtx_identifiers = HashSet::new();
+) -> (LinkResult, LinkStats) {
+    let (links, flow_events, stats) = link_device_ranges(
+        nvtx_events_list,
+        cuda_api_events_list,
+        kernel_events_list,
+        device_id,
+        adapter,
+        options,
+    );
+    let (nvtx_kernel_events, mapped_nvtx_identifiers) =
+        finish_range_links(nvtx_events_list, links, device_id, options);
+    (
+        (nvtx_kernel_events, mapped_nvtx_identifiers, flow_events),
+        stats,
+    )
+}
 
-    // Find overlapping intervals between NVTX and CUDA API events
-    let overlap_map = find_overlapping_intervals(nvtx_events_list, cuda_api_events_list, adapter);
+/// Attribute a device's calls to its ranges and collect each range's kernels
+///
+/// Returns the linked ranges in NVTX list order, the flow events of every
+/// call → kernel link and the attribution counts.
+pub(crate) fn link_device_ranges(
+    nvtx_events_list: &[&ChromeTraceEvent],
+    cuda_api_events_list: &[&ChromeTraceEvent],
+    kernel_events_list: &[&ChromeTraceEvent],
+    device_id: i32,
+    adapter: &NsysEventAdapter,
+    options: &ConversionOptions,
+) -> (Vec<RangeLink>, Vec<ChromeTraceEvent>, LinkStats) {
+    let mut links = Vec::new();
+    let mut attributed_kernels = HashSet::new();
+
+    // Find overlapping intervals between NVTX and CUDA API events on the same thread
+    let overlap_map =
+        overlapping_events_by_thread(nvtx_events_list, cuda_api_events_list, adapter);
+
+    // Decide which ranges receive calls overlapping several of them
+    let (mut attributed_map, mut stats) =
+        attribute_calls(nvtx_events_list, &overlap_map, adapter, options.link_policy);
+
+    // Hand calls outside every range to the range that just ended on their thread
+    let sticky_calls = match options.sticky_lookback_ns {
+        Some(lookback_ns) => attribute_sticky_calls(
+            nvtx_events_list,
+            cuda_api_events_list,
+            &overlap_map,
+            &mut attributed_map,
+            adapter,
+            lookback_ns,
+        ),
+        None => HashSet::new(),
+    };
+    stats.sticky_calls = sticky_calls.len();
 
     // Build correlation ID map
-    let correlation_id_map = build_correlation_map_with_cuda_api(cuda_api_events_list, kernel_events_list, adapter);
+    let mut correlation_id_map = build_correlation_map_with_cuda_api(cuda_api_events_list, kernel_events_list, adapter);
+    if let Some(max_gap_ns) = options.max_link_gap_ns {
+        stats.distant_kernels = drop_distant_kernels(&mut correlation_id_map, max_gap_ns, adapter);
+    }
 
     // Generate flow events
-    let flow_events = generate_flow_events_for_correlation_map(&correlation_id_map);
+    let flow_events = generate_flow_events_for_correlation_map(&correlation_id_map, device_id);
 
-    // Extract kernel correlation map for finding kernels
-    let kernel_correlation_map: HashMap<i32, Vec<&ChromeTraceEvent>> = correlation_id_map
-        .iter()
-        .map(|(&corr_id, data)| (corr_id, data.kernels.clone()))
+    // Extract kernel correlation map for finding kernels, limited to attributed calls
+    let attributed_ids: HashSet<i64> = attributed_map
+        .values()
+        .flatten()
+        .filter_map(|&(call, _)| adapter.get_correlation_id(call))
+        .collect();
+    let kernel_correlation_map: HashMap<i64, Vec<&ChromeTraceEvent>> = attributed_ids
+        .into_iter()
+        .filter_map(|corr_id| Some((corr_id, correlation_id_map.get(&corr_id)?.kernels.clone())))
         .collect();
 
     // Process each NVTX event
-    for nvtx_event in nvtx_events_list {
+    for (range_idx, nvtx_event) in nvtx_events_list.iter().enumerate() {
         let nvtx_id = adapter.get_event_id(nvtx_event);
-        let cuda_api_events_overlapping = overlap_map.get(&nvtx_id).map(|v| v.as_slice()).unwrap_or(&[]);
-
-        if cuda_api_events_overlapping.is_empty() {
+        let Some(attributed) = attributed_map.get(&nvtx_id) else {
             continue;
-        }
+        };
+        let cuda_api_events_overlapping: Vec<&ChromeTraceEvent> =
+            attributed.iter().map(|&(call, _)| call).collect();
+        // A range is only as certain as its weakest link
+        let link_confidence = attributed
+            .iter()
+            .map(|&(_, confidence)| confidence)
+            .fold(1.0_f64, f64::min);
+        let sticky = attributed
+            .iter()
+            .any(|&(call, _)| sticky_calls.contains(&adapter.get_event_id(call)));
 
         // Find kernels using shared function
         let found_kernels = find_kernels_for_annotation(
-            cuda_api_events_overlapping,
+            &cuda_api_events_overlapping,
             &kernel_correlation_map,
             adapter,
         );
 
-        // Aggregate kernel times
-        if let Some((kernel_start_time, kernel_end_time)) =
-            aggregate_kernel_times(&found_kernels, adapter)
-        {
+        // One group spanning all kernels, or one per stream they ran on
+        let kernel_groups: Vec<(Option<i64>, Vec<&ChromeTraceEvent>)> =
+            if options.nvtx_kernel_per_stream {
+                group_kernels_by_stream(&found_kernels)
+                    .into_iter()
+                    .collect()
+            } else {
+                vec![(None, found_kernels)]
+            };
+
+        let mut groups = Vec::new();
+        for (stream_id, kernels) in kernel_groups {
+            // Aggregate kernel times
+            let Some((start_ns, end_ns)) = aggregate_kernel_times(&kernels, adapter) else {
+                continue;
+            };
+            for kernel in &kernels {
+                if attributed_kernels.insert(adapter.get_event_id(kernel)) {
+                    if let Some((start, end)) = adapter.get_time_range_ns(kernel) {
+                        stats.attributed_kernel_ns += end - start;
+                    }
+                }
+            }
+            groups.push(KernelGroup {
+                stream_id,
+                start_ns,
+                end_ns,
+                busy: merge_intervals(&kernels, adapter),
+            });
+        }
+
+        links.push(RangeLink {
+            range_idx,
+            confidence: link_confidence,
+            sticky,
+            groups,
+        });
+    }
+
+    (links, flow_events, stats)
+}
+
+/// Turn linked ranges into nvtx-kernel events
+///
+/// Returns the events and the identifiers of the ranges that received at
+/// least one kernel.
+pub(crate) fn finish_range_links(
+    nvtx_events_list: &[&ChromeTraceEvent],
+    links: impl IntoIterator<Item = RangeLink>,
+    device_id: i32,
+    options: &ConversionOptions,
+) -> (Vec<ChromeTraceEvent>, HashSet<NvtxIdentifier>) {
+    let mut nvtx_kernel_events = Vec::new();
+    let mut mapped_nvtx_identifiers = HashSet::new();
+
+    for link in links {
+        let nvtx_event = nvtx_events_list[link.range_idx];
+        for group in &link.groups {
             // Create nvtx-kernel event
-            let event = create_nvtx_kernel_event(
+            let busy_ns = covered_time(&group.busy.iter().collect::<Vec<_>>());
+            let busy_pct = busy_pct(busy_ns, group.start_ns, group.end_ns);
+            let mut event = create_nvtx_kernel_event(
                 nvtx_event,
-                kernel_start_time,
-                kernel_end_time,
+                group.start_ns,
+                group.end_ns,
                 device_id,
                 options,
+            )
+            .with_arg("gpu_busy_pct", json!(busy_pct))
+            .with_arg(
+                "link_confidence",
+                json!((link.confidence * 1000.0).round() / 1000.0),
             );
+            if link.sticky {
+                event = event.with_arg("attribution", "sticky");
+            }
+            if let Some(stream_id) = group.stream_id {
+                // Ranges on different streams overlap, so each needs its own track
+                event.tid = format!("{} Stream {}", event.tid, stream_id).into();
+                event = event.with_arg("streamId", stream_id);
+            }
             nvtx_kernel_events.push(event);
+        }
 
+        if !link.groups.is_empty() {
             // Track this NVTX event as successfully mapped
             if let (Some(tid), Some(start_ns)) = (
                 nvtx_event.args.get("raw_tid").and_then(|v| v.as_i64()),
@@ -235,7 +507,35 @@ fn process_device_nvtx_events(
         }
     }
 
-    (nvtx_kernel_events, mapped_nvtx_identifiers, flow_events)
+    (nvtx_kernel_events, mapped_nvtx_identifiers)
+}
+
+/// Kernels grouped by their `streamId` arg, in stream order
+///
+/// Kernels without a stream ID share the `None` group.
+fn group_kernels_by_stream<'a>(
+    kernels: &[&'a ChromeTraceEvent],
+) -> BTreeMap<Option<i64>, Vec<&'a ChromeTraceEvent>> {
+    let mut groups: BTreeMap<Option<i64>, Vec<&ChromeTraceEvent>> = BTreeMap::new();
+    for &kernel in kernels {
+        let stream_id = kernel.args.get("streamId").and_then(|v| v.as_i64());
+        groups.entry(stream_id).or_default().push(kernel);
+    }
+    groups
+}
+
+/// Percentage of [span_start, span_end) covered by at least one kernel
+///
+/// `busy_ns` counts overlapping kernels (e.g. on different streams) once, so
+/// the result is at most 100 even when kernels run concurrently.
+fn busy_pct(busy_ns: i64, span_start: i64, span_end: i64) -> f64 {
+    let span = span_end - span_start;
+    if span <= 0 {
+        return 100.0;
+    }
+
+    let pct = busy_ns as f64 / span as f64 * 100.0;
+    (pct * 10.0).round() / 10.0
 }
 
 /// Correlation data for CUDA API and kernels
@@ -249,8 +549,8 @@ fn build_correlation_map_with_cuda_api<'a>(
     cuda_api_events_list: &[&'a ChromeTraceEvent],
     kernel_events_list: &[&'a ChromeTraceEvent],
     adapter: &NsysEventAdapter,
-) -> HashMap<i32, CorrelationData<'a>> {
-    let mut correlation_id_map: HashMap<i32, CorrelationData> = HashMap::default();
+) -> HashMap<i64, CorrelationData<'a>> {
+    let mut correlation_id_map: HashMap<i64, CorrelationData> = HashMap::default();
 
     // Map CUDA API events by correlationId
     for &cuda_api_event in cuda_api_events_list {
@@ -280,9 +580,36 @@ fn build_correlation_map_with_cuda_api<'a>(
     correlation_id_map
 }
 
+/// Unlink kernels starting more than `max_gap_ns` after their call ended
+///
+/// Correlation IDs wrap around in long captures, so a stale ID can pair a call
+/// with a kernel launched seconds later. Returns the number of kernels unlinked.
+fn drop_distant_kernels(
+    correlation_id_map: &mut HashMap<i64, CorrelationData>,
+    max_gap_ns: i64,
+    adapter: &NsysEventAdapter,
+) -> usize {
+    let mut dropped = 0;
+    for data in correlation_id_map.values_mut() {
+        let Some((_, call_end)) = data.cuda_api.and_then(|call| adapter.get_time_range_ns(call))
+        else {
+            continue;
+        };
+        let before = data.kernels.len();
+        data.kernels.retain(|kernel| {
+            adapter
+                .get_time_range_ns(kernel)
+                .is_none_or(|(start, _)| start - call_end <= max_gap_ns)
+        });
+        dropped += before - data.kernels.len();
+    }
+    dropped
+}
+
 /// Generate flow events for all CUDA API → Kernel links
 fn generate_flow_events_for_correlation_map(
-    correlation_id_map: &HashMap<i32, CorrelationData>,
+    correlation_id_map: &HashMap<i64, CorrelationData>,
+    device_id: i32,
 ) -> Vec<ChromeTraceEvent> {
     let mut flow_events = Vec::new();
 
@@ -292,7 +619,7 @@ fn generate_flow_events_for_correlation_map(
                 // Create flow arrow to EACH kernel
                 for &kernel_event in &data.kernels {
                     let (flow_start, flow_finish) =
-                        create_flow_events(cuda_api_event, kernel_event, corr_id);
+                        create_flow_events(cuda_api_event, kernel_event, flow_id(device_id, corr_id));
                     flow_events.push(flow_start);
                     flow_events.push(flow_finish);
                 }
@@ -303,30 +630,61 @@ fn generate_flow_events_for_correlation_map(
     flow_events
 }
 
+/// Build a flow ID that is unique across devices
+///
+/// CUPTI correlation IDs are only unique per device, so the device ID occupies the
+/// bits above `FLOW_ID_CORRELATION_BITS`. The result stays below 2^53 for realistic
+/// device counts so JSON consumers can represent it exactly.
+pub fn flow_id(device_id: i32, correlation_id: i64) -> i64 {
+    ((device_id as i64) << FLOW_ID_CORRELATION_BITS)
+        | (correlation_id & ((1 << FLOW_ID_CORRELATION_BITS) - 1))
+}
+
 /// Create flow start/end events to show arrows in Perfetto
 pub(crate) fn create_flow_events(
     cuda_api_event: &ChromeTraceEvent,
     kernel_event: &ChromeTraceEvent,
-    correlation_id: i32,
+    flow_id: i64,
 ) -> (ChromeTraceEvent, ChromeTraceEvent) {
     let flow_start = ChromeTraceEvent::flow_start(
         cuda_api_event.ts,
         cuda_api_event.pid.clone(),
         cuda_api_event.tid.clone(),
-        StringOrInt::Int(correlation_id as i64),
+        StringOrInt::Int(flow_id),
     );
 
     let flow_finish = ChromeTraceEvent::flow_finish(
         kernel_event.ts,
         kernel_event.pid.clone(),
         kernel_event.tid.clone(),
-        StringOrInt::Int(correlation_id as i64),
+        StringOrInt::Int(flow_id),
         BindingPoint::Enclosing,
     );
 
     (flow_start, flow_finish)
 }
 
+/// Parse `device` or `annotation` into an nvtx-kernel layout
+pub fn parse_nvtx_kernel_layout(value: &str) -> Result<NvtxKernelLayout> {
+    match value.trim() {
+        "device" => Ok(NvtxKernelLayout::Device),
+        "annotation" => Ok(NvtxKernelLayout::Annotation),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid nvtx-kernel layout '{}' (use device or annotation)",
+            value
+        ))),
+    }
+}
+
+/// Device an nvtx-kernel range's kernels ran on, from its `deviceId` arg or
+/// else its `Device N` process
+pub(crate) fn nvtx_kernel_device(range: &ChromeTraceEvent) -> Option<i64> {
+    match range.args.get("deviceId").and_then(|v| v.as_i64()) {
+        Some(device) => Some(device),
+        None => range.pid.strip_prefix("Device ")?.parse().ok(),
+    }
+}
+
 /// Create a single nvtx-kernel event from an NVTX event and kernel time range
 pub(crate) fn create_nvtx_kernel_event(
     nvtx_event: &ChromeTraceEvent,
@@ -342,14 +700,27 @@ pub(crate) fn create_nvtx_kernel_event(
         .and_then(|v| v.as_i64())
         .unwrap_or(0);
 
+    let (pid, tid): (InternedStr, InternedStr) = match options.nvtx_kernel_layout {
+        NvtxKernelLayout::Device => (
+            format!("Device {}", device_id).into(),
+            format!("NVTX Kernel Thread {}", tid).into(),
+        ),
+        // The linked range is dropped, so its GPU time takes its place
+        NvtxKernelLayout::Annotation => (nvtx_event.pid.clone(), nvtx_event.tid.clone()),
+    };
+
     let mut event = ChromeTraceEvent::complete(
         nvtx_name.clone(),
         ns_to_us(kernel_start_time),
         ns_to_us(kernel_end_time - kernel_start_time),
-        format!("Device {}", device_id),
-        format!("NVTX Kernel Thread {}", tid),
-        "nvtx-kernel".to_string(),
+        pid,
+        tid,
+        EventCategory::NvtxKernel,
     );
+    if options.nvtx_kernel_layout == NvtxKernelLayout::Annotation {
+        // The process no longer names the device
+        event = event.with_arg("deviceId", device_id);
+    }
 
     // Apply color scheme if specified
     for (pattern_str, color) in &options.nvtx_color_scheme {
@@ -0,0 +1,211 @@
+//! Time-shift alignment of annotations with the GPU work they cover
+//!
+//! Annotations injected after the fact, e.g. by a wrapper library timing calls
+//! from outside the CUDA runtime, can sit systematically early or late
+//! relative to the API calls they wrap, so overlap detection links the wrong
+//! kernels or none at all. Before linking, every annotation of a key (its
+//! NVTX domain, or its category) can be moved by a fixed offset, or by an
+//! offset estimated as the one under which the annotations cover the most
+//! kernel launches.
+//!
+//! Shifted annotations keep their new times in the output and record the
+//! offset in the `time_shift_ns` arg.
+
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis::parse_duration_ns;
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, TimeShift};
+
+/// Largest offset considered when estimating a shift
+pub const MAX_ESTIMATED_SHIFT_NS: i64 = 10_000_000;
+
+/// Parse a shift: `auto` to estimate it, or a signed duration such as `-20us`
+pub fn parse_time_shift(value: &str) -> Result<TimeShift> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("auto") {
+        return Ok(TimeShift::Estimated);
+    }
+    let (sign, duration) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    Ok(TimeShift::Fixed(sign * parse_duration_ns(duration)?))
+}
+
+/// Parse a `KEY=SHIFT` pair, keyed by NVTX domain or event category
+pub fn parse_time_shift_spec(value: &str) -> Result<(String, TimeShift)> {
+    match value.rsplit_once('=') {
+        Some((key, shift)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), parse_time_shift(shift)?))
+        }
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid time shift '{}' (expected KEY=SHIFT, e.g. nvtx=-20us or nvtx=auto)",
+            value
+        ))),
+    }
+}
+
+/// Key of the shift applying to an annotation: its NVTX domain if one is
+/// configured, otherwise its category
+fn shift_key<'a>(
+    event: &'a ChromeTraceEvent,
+    shifts: &HashMap<String, TimeShift>,
+) -> Option<&'a str> {
+    let domain = event.args.get("domain").and_then(|v| v.as_str());
+    match domain {
+        Some(domain) if shifts.contains_key(domain) => Some(domain),
+        _ if shifts.contains_key(&*event.cat) => Some(&event.cat),
+        _ => None,
+    }
+}
+
+/// Thread an event ran on, as (device, raw thread ID)
+fn thread_of(event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+    let device = event.args.get("deviceId").and_then(|v| v.as_i64());
+    let tid = event.args.get("raw_tid").and_then(|v| v.as_i64());
+    Some((device?, tid?))
+}
+
+fn range_of(event: &ChromeTraceEvent) -> Option<(i64, i64)> {
+    let start = event.args.get("start_ns").and_then(|v| v.as_i64())?;
+    let end = event.args.get("end_ns").and_then(|v| v.as_i64())?;
+    Some((start, end))
+}
+
+/// Start times of API calls that launched a kernel, sorted per thread
+fn launch_times(
+    api_events: &[ChromeTraceEvent],
+    kernel_events: &[ChromeTraceEvent],
+) -> HashMap<(i64, i64), Vec<i64>> {
+    let launched: HashSet<(i64, i64)> = kernel_events
+        .iter()
+        .filter_map(|k| {
+            let device = k.args.get("deviceId").and_then(|v| v.as_i64())?;
+            let correlation = k.args.get("correlationId").and_then(|v| v.as_i64())?;
+            Some((device, correlation))
+        })
+        .collect();
+
+    let mut launches: HashMap<(i64, i64), Vec<i64>> = HashMap::new();
+    for api in api_events {
+        let correlation = api.args.get("correlationId").and_then(|v| v.as_i64());
+        let start = api.args.get("start_ns").and_then(|v| v.as_i64());
+        if let (Some(thread), Some(correlation), Some(start)) = (thread_of(api), correlation, start)
+        {
+            if launched.contains(&(thread.0, correlation)) {
+                launches.entry(thread).or_default().push(start);
+            }
+        }
+    }
+    for times in launches.values_mut() {
+        times.sort_unstable();
+    }
+    launches
+}
+
+/// Estimate the shift under which `annotations` cover the most kernel launches
+///
+/// Each launch near an annotation is covered by the annotation for a closed
+/// interval of shifts; a sweep over those intervals finds the shifts within
+/// [`MAX_ESTIMATED_SHIFT_NS`] covering the most launches, of which the one
+/// closest to zero is chosen, so annotations that already cover their
+/// launches are left in place.
+pub fn estimate_time_shift(
+    annotations: &[&ChromeTraceEvent],
+    api_events: &[ChromeTraceEvent],
+    kernel_events: &[ChromeTraceEvent],
+) -> i64 {
+    let launches = launch_times(api_events, kernel_events);
+
+    // (shift, +1) where a launch enters an annotation, (shift, -1) just past
+    // where it leaves
+    let mut boundaries: Vec<(i64, i32)> = Vec::new();
+    for annotation in annotations {
+        let (Some(times), Some((start, end))) = (
+            thread_of(annotation).and_then(|thread| launches.get(&thread)),
+            range_of(annotation),
+        ) else {
+            continue;
+        };
+        let first = times.partition_point(|&t| t < start - MAX_ESTIMATED_SHIFT_NS);
+        let last = times.partition_point(|&t| t <= end + MAX_ESTIMATED_SHIFT_NS);
+        for &t in &times[first..last] {
+            let lo = (t - end).max(-MAX_ESTIMATED_SHIFT_NS);
+            let hi = (t - start).min(MAX_ESTIMATED_SHIFT_NS);
+            if lo <= hi {
+                boundaries.push((lo, 1));
+                boundaries.push((hi + 1, -1));
+            }
+        }
+    }
+    boundaries.sort_unstable();
+
+    // Coverage is constant between consecutive boundaries
+    let mut best: (i32, i64) = (0, 0);
+    let mut covered = 0;
+    for (idx, &(shift, delta)) in boundaries.iter().enumerate() {
+        covered += delta;
+        let Some(&(next, _)) = boundaries.get(idx + 1) else {
+            break;
+        };
+        if next == shift || covered < best.0 {
+            continue;
+        }
+        let closest = 0.clamp(shift, next - 1);
+        if covered > best.0 || closest.abs() < best.1.abs() {
+            best = (covered, closest);
+        }
+    }
+    best.1
+}
+
+/// Move an annotation by `shift_ns`, recording the offset in its args
+pub fn apply_time_shift(event: &mut ChromeTraceEvent, shift_ns: i64) {
+    event.ts += shift_ns as f64 / 1000.0;
+    for key in ["start_ns", "end_ns"] {
+        if let Some(time) = event.args.get(key).and_then(|v| v.as_i64()) {
+            event.args.insert(key.into(), json!(time + shift_ns));
+        }
+    }
+    event.args.insert("time_shift_ns".into(), json!(shift_ns));
+}
+
+/// Shift annotations per key before linking
+///
+/// Returns the offset applied to each key; keys estimated to need no shift,
+/// and keys without annotations, are left out.
+pub fn align_annotations(
+    annotations: &mut [ChromeTraceEvent],
+    api_events: &[ChromeTraceEvent],
+    kernel_events: &[ChromeTraceEvent],
+    shifts: &HashMap<String, TimeShift>,
+) -> HashMap<String, i64> {
+    let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+    for (idx, event) in annotations.iter().enumerate() {
+        if let Some(key) = shift_key(event, shifts) {
+            by_key.entry(key.to_string()).or_default().push(idx);
+        }
+    }
+
+    let mut applied = HashMap::new();
+    for (key, indices) in by_key {
+        let shift_ns = match shifts[&key] {
+            TimeShift::Fixed(shift_ns) => shift_ns,
+            TimeShift::Estimated => {
+                let members: Vec<&ChromeTraceEvent> =
+                    indices.iter().map(|&idx| &annotations[idx]).collect();
+                estimate_time_shift(&members, api_events, kernel_events)
+            }
+        };
+        if shift_ns == 0 {
+            continue;
+        }
+        for idx in indices {
+            apply_time_shift(&mut annotations[idx], shift_ns);
+        }
+        applied.insert(key, shift_ns);
+    }
+    applied
+}
@@ -0,0 +1,217 @@
+//! NVTX-kernel linking over time windows
+//!
+//! In-memory linking builds the overlap, attribution and correlation maps of a
+//! whole device at once, which does not fit on small hosts for multi-hour
+//! captures. Windowed linking walks each device's CUDA API calls in time
+//! windows instead: every call belongs to exactly one window, and a window
+//! links its calls against the NVTX ranges overlapping them and the kernels
+//! they launched, looked up through a correlation index of that window only
+//! (see [`KernelTimeline`]). Each window's per-range results ([`RangeLink`])
+//! are spilled to a temporary file as JSON lines and its maps dropped; a final
+//! pass reads the spill back and stitches the pieces of ranges whose calls fell
+//! into several windows. The output matches in-memory linking.
+//!
+//! Only the linking state is bounded by the window. The input events and the
+//! output (nvtx-kernel ranges and flow events) are held in full, as the
+//! converter keeps the whole trace in memory anyway. Kernels launched by a
+//! window's calls are found by scanning forward from the window's start; set
+//! a maximum link gap to stop the scan early on captures whose correlation
+//! IDs do not grow with time.
+//!
+//! Ranges stay active across windows for as long as they last, so a window
+//! holds its own calls and kernels plus the ranges open during it.
+
+use serde_json::Deserializer;
+use std::collections::BTreeMap;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+
+use crate::error::{ConvertError, Result};
+use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
+use crate::linker::attribution::LinkStats;
+use crate::linker::correlation_index::KernelTimeline;
+use crate::linker::nvtx_linker::{
+    finish_range_links, group_events_by_device, link_device_ranges, numbered_copies, LinkResult,
+    RangeLink,
+};
+use crate::models::{ChromeTraceEvent, ConversionOptions};
+use crate::self_profile::phase;
+
+/// Link NVTX events to kernels one time window of CUDA API calls at a time
+///
+/// Produces the same events and counts as
+/// [`crate::linker::link_nvtx_to_kernels_with_stats`], holding only one
+/// window's linking state in memory. Devices are linked one after another.
+/// Unnumbered events are numbered the same way. Fails if `window_ns` is not
+/// positive or the spill file cannot be used.
+pub fn link_nvtx_to_kernels_windowed(
+    nvtx_events: &[ChromeTraceEvent],
+    cuda_api_events: &[ChromeTraceEvent],
+    kernel_events: &[ChromeTraceEvent],
+    options: &ConversionOptions,
+    window_ns: i64,
+) -> Result<(LinkResult, LinkStats)> {
+    if window_ns <= 0 {
+        return Err(ConvertError::InvalidOption(format!(
+            "Link window must be positive, got {} ns",
+            window_ns
+        )));
+    }
+    let copies = numbered_copies(nvtx_events, cuda_api_events, kernel_events);
+    let (nvtx_events, cuda_api_events, kernel_events) = match &copies {
+        Some(trace) => (
+            &trace.annotation_events[..],
+            &trace.api_events[..],
+            &trace.kernel_events[..],
+        ),
+        None => (nvtx_events, cuda_api_events, kernel_events),
+    };
+
+    let (per_device_nvtx, per_device_cuda_api, per_device_kernels) =
+        group_events_by_device(nvtx_events, cuda_api_events, kernel_events);
+
+    let mut common_devices: Vec<i32> = per_device_nvtx
+        .keys()
+        .copied()
+        .filter(|device_id| {
+            per_device_cuda_api.contains_key(device_id)
+                && per_device_kernels.contains_key(device_id)
+        })
+        .collect();
+    common_devices.sort_unstable();
+
+    let mut result: LinkResult = Default::default();
+    let mut stats = LinkStats::default();
+    for device_id in common_devices {
+        let mut link_phase = phase(format!("link Device {} (windowed)", device_id), "link");
+        let ((nvtx_kernel_events, mapped, flows), device_stats) = link_device_windowed(
+            &per_device_nvtx[&device_id],
+            &per_device_cuda_api[&device_id],
+            &per_device_kernels[&device_id],
+            device_id,
+            options,
+            window_ns,
+        )?;
+        link_phase.set_events(nvtx_kernel_events.len() + flows.len());
+        result.0.extend(nvtx_kernel_events);
+        result.1.extend(mapped);
+        result.2.extend(flows);
+        stats.merge(device_stats);
+    }
+    Ok((result, stats))
+}
+
+/// Link one device window by window, spilling and then stitching range links
+fn link_device_windowed(
+    nvtx_events_list: &[&ChromeTraceEvent],
+    cuda_api_events_list: &[&ChromeTraceEvent],
+    kernel_events_list: &[&ChromeTraceEvent],
+    device_id: i32,
+    options: &ConversionOptions,
+    window_ns: i64,
+) -> Result<(LinkResult, LinkStats)> {
+    let adapter = NsysEventAdapter;
+
+    // Ranges by start, remembering their position in the device list
+    let mut ranges: Vec<(usize, i64, i64)> = nvtx_events_list
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, range)| {
+            let (start, end) = adapter.get_time_range_ns(range)?;
+            Some((idx, start, end))
+        })
+        .collect();
+    ranges.sort_by_key(|&(idx, start, _)| (start, idx));
+
+    let mut calls: Vec<(i64, i64, &ChromeTraceEvent)> = cuda_api_events_list
+        .iter()
+        .filter_map(|&call| {
+            let (start, end) = adapter.get_time_range_ns(call)?;
+            Some((start, end, call))
+        })
+        .collect();
+    calls.sort_by_key(|&(start, end, _)| (start, end));
+
+    // Kernels in start order; each window indexes only the ones its calls launched
+    let timeline = KernelTimeline::new(kernel_events_list, &adapter);
+
+    let mut spill = BufWriter::new(tempfile::tempfile()?);
+    let mut flow_events = Vec::new();
+    let mut stats = LinkStats::default();
+    let mut active: Vec<(usize, i64, i64)> = Vec::new();
+    let mut next_range = 0;
+    let mut windows = 0;
+
+    let mut first_call = 0;
+    while first_call < calls.len() {
+        let window_start = calls[first_call].0;
+        let window_end = window_start.saturating_add(window_ns);
+        let last_call =
+            first_call + calls[first_call..].partition_point(|&(start, _, _)| start < window_end);
+        let owned = &calls[first_call..last_call];
+        first_call = last_call;
+        windows += 1;
+
+        // Ranges open at any point of the window's calls
+        let span_end = owned
+            .iter()
+            .map(|&(_, end, _)| end)
+            .max()
+            .unwrap_or(window_end);
+        while next_range < ranges.len() && ranges[next_range].1 <= span_end {
+            active.push(ranges[next_range]);
+            next_range += 1;
+        }
+        // Ranges that ended within the sticky lookback can still receive calls
+        let lookback_ns = options.sticky_lookback_ns.unwrap_or(0);
+        active.retain(|&(_, _, end)| end >= window_start.saturating_sub(lookback_ns));
+
+        let window_ranges: Vec<&ChromeTraceEvent> = active
+            .iter()
+            .map(|&(idx, _, _)| nvtx_events_list[idx])
+            .collect();
+        let window_calls: Vec<&ChromeTraceEvent> = owned.iter().map(|&(_, _, call)| call).collect();
+        let window_kernels = timeline
+            .index_for(&window_calls, &adapter, options.max_link_gap_ns)
+            .kernels_for(&window_calls, &adapter);
+
+        let (links, flows, window_stats) = link_device_ranges(
+            &window_ranges,
+            &window_calls,
+            &window_kernels,
+            device_id,
+            &adapter,
+            options,
+        );
+        for mut link in links {
+            link.range_idx = active[link.range_idx].0;
+            serde_json::to_writer(&mut spill, &link)?;
+            spill.write_all(b"\n")?;
+        }
+        flow_events.extend(flows);
+        stats.merge(window_stats);
+    }
+    log::debug!(
+        "Linked Device {} in {} windows of {} ns",
+        device_id,
+        windows,
+        window_ns
+    );
+
+    // Stitch the pieces of each range back together, in device list order
+    let mut spill = spill.into_inner().map_err(|e| e.into_error())?;
+    spill.seek(SeekFrom::Start(0))?;
+    let mut stitched: BTreeMap<usize, RangeLink> = BTreeMap::new();
+    for link in Deserializer::from_reader(BufReader::new(spill)).into_iter::<RangeLink>() {
+        let link = link?;
+        match stitched.get_mut(&link.range_idx) {
+            Some(existing) => existing.absorb(link),
+            None => {
+                stitched.insert(link.range_idx, link);
+            }
+        }
+    }
+
+    let (nvtx_kernel_events, mapped) =
+        finish_range_links(nvtx_events_list, stitched.into_values(), device_id, options);
+    Ok(((nvtx_kernel_events, mapped, flow_events), stats))
+}
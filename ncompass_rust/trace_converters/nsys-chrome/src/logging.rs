@@ -0,0 +1,111 @@
+//! Log output for humans or log pipelines
+//!
+//! Logging goes through the `log` facade. Text mode keeps env_logger's usual
+//! lines; JSON mode (`--log-format json`) writes one object per record with
+//! the record's key-value fields, so conversions can be ingested into
+//! pipeline logs. Phases timed with [`crate::self_profile::phase`] emit an
+//! `info` record under [`PHASE_TARGET`] when they end, carrying the phase
+//! name, category, duration and, where known, the number of events it handled.
+
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::sync::OnceLock;
+
+use crate::error::{ConvertError, Result};
+
+/// Target of the records emitted when a phase ends
+pub const PHASE_TARGET: &str = "nsys_chrome::phase";
+
+/// Target of CLI progress messages in JSON mode
+pub const STATUS_TARGET: &str = "nsys_chrome::status";
+
+/// How log records are written to stderr
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// env_logger's human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Parse `text` or `json`
+pub fn parse_log_format(value: &str) -> Result<LogFormat> {
+    match value.trim() {
+        "text" => Ok(LogFormat::Text),
+        "json" => Ok(LogFormat::Json),
+        other => Err(ConvertError::InvalidOption(format!(
+            "Unknown log format '{}' (use text or json)",
+            other
+        ))),
+    }
+}
+
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// Format chosen by [`init`] (text if logging was not initialized)
+pub fn log_format() -> LogFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Install the process-wide logger, filtered by `RUST_LOG`
+///
+/// Without `RUST_LOG`, text mode shows warnings and errors; JSON mode also
+/// shows info records, which include phase timings and progress messages.
+pub fn init(format: LogFormat) {
+    let _ = FORMAT.set(format);
+    let default_filter = match format {
+        LogFormat::Text => "warn",
+        LogFormat::Json => "info",
+    };
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter));
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let line = json_record(record, &buf.timestamp_millis().to_string());
+            writeln!(buf, "{}", line)
+        });
+    }
+    let _ = builder.try_init();
+}
+
+/// A log record as a JSON object: timestamp, level, target, message and fields
+pub fn json_record(record: &log::Record, timestamp: &str) -> Value {
+    let mut fields = FieldCollector(Map::new());
+    let _ = record.key_values().visit(&mut fields);
+    let mut object = json!({
+        "timestamp": timestamp,
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+    if !fields.0.is_empty() {
+        object["fields"] = Value::Object(fields.0);
+    }
+    object
+}
+
+/// Gathers a record's key-value pairs, keeping numbers and booleans typed
+struct FieldCollector(Map<String, Value>);
+
+impl<'kvs> log::kv::VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> std::result::Result<(), log::kv::Error> {
+        let value = if let Some(v) = value.to_u64() {
+            json!(v)
+        } else if let Some(v) = value.to_i64() {
+            json!(v)
+        } else if let Some(v) = value.to_f64() {
+            json!(v)
+        } else if let Some(v) = value.to_bool() {
+            json!(v)
+        } else {
+            json!(value.to_string())
+        };
+        self.0.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
+}
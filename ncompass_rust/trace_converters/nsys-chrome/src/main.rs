@@ -1,62 +1,1943 @@
 //! CLI for nsys to Chrome Trace converter
 
-use clap::Parser;
-use nsys_chrome::{convert_file_gz, ConversionOptions};
-use std::path::Path;
-use std::process::Command;
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use nsys_chrome::analysis::{
+    fusion_report, kernel_heatmap, parse_duration_ns, parse_missing_stream_policy,
+    merge_rank_traces, parse_outlier_factor, parse_step_pattern, parse_time_origin, parse_time_window, skew_report,
+    step_stats, straggler_events, RankMerger, DEFAULT_STEP_PATTERN,
+};
+use nsys_chrome::bench::{parse_bench_writer, run_write_bench, synthetic_events, BenchWriter};
+use nsys_chrome::browser::{run_interactive, TraceBrowser};
+use nsys_chrome::tui::run_tui;
+use nsys_chrome::cache::{read_event_cache, write_event_cache};
+use nsys_chrome::callchains::write_folded_stacks;
+use nsys_chrome::cancel::CancellationToken;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::compat::{apply_compat, parse_compat_target, CompatTarget};
+use nsys_chrome::devices::{device_properties_from_events, write_devices_json};
+use nsys_chrome::effective_config::{parse_config_format, render_config, ConfigFormat};
+use nsys_chrome::follow::{FollowOptions, TraceFollower};
+use nsys_chrome::frontends::nsys_stats::is_nsys_stats_csv;
+use nsys_chrome::frontends::rocprof::{is_rocprof_json, unsupported_rocprof_format};
+use nsys_chrome::frontends::unitrace::is_unitrace_json;
+use nsys_chrome::frontends::{assemble_trace, NsysStatsReader, RocprofReader, UnitraceReader};
+use nsys_chrome::large_args::{parse_args_limit, LargeArgs};
+use nsys_chrome::linker::{
+    parse_flow_bind_spec, parse_flow_style, parse_link_policy, parse_nvtx_kernel_layout,
+    parse_time_shift_spec,
+};
+use nsys_chrome::logging::{self, log_format, parse_log_format, LogFormat, STATUS_TARGET};
+use nsys_chrome::models::{
+    FlowBind, FlowLink, FlowOptions, FlowStyle, LinkPolicy, MissingStreamPolicy, NvtxKernelLayout,
+    NvtxNesting,
+    OutputRoute, TidGrouping, TimeOrigin, TimeShift, TimeWindow,
+};
+use nsys_chrome::name_dictionary::{expand_trace_file, NameDictionary};
+use nsys_chrome::otlp::{conversion_metrics, parse_otlp_endpoint, ConversionMetrics, OtlpEndpoint};
+use nsys_chrome::outline::outline_path;
+use nsys_chrome::parsers::nvtx::{parse_nvtx_nesting, NvtxNameFilter};
+use nsys_chrome::pipeline::{spawn_writer, write_pipelined, PipelineConfig};
+use nsys_chrome::presets::{load_overlay, parse_preset, Preset};
+use nsys_chrome::query::{run_query_interactive, TraceDatabase};
+use nsys_chrome::report::{
+    report_path, ReportOptions, TraceReport, DEFAULT_REPORT_BINS, DEFAULT_REPORT_TOP,
+};
+use nsys_chrome::routing::{summary_events, MultiSinkWriter};
+use nsys_chrome::seekable::seek_index_path;
+use nsys_chrome::self_profile::{self, phase};
+use nsys_chrome::sink::JsonArraySink;
+use nsys_chrome::service::{ConversionService, ServiceConfig};
+use nsys_chrome::sessions::{detect_sessions, select_session, session_path, split_sessions};
+use nsys_chrome::tid_allocator::parse_tid_grouping;
+use nsys_chrome::track_ids::sidecar_path;
+use nsys_chrome::viewer::{TraceServer, DEFAULT_TRACE_SERVER_ADDR};
+use nsys_chrome::writer::{OutputFile, OutputFileOptions, WriteOptions, WriteOutput};
+use nsys_chrome::{
+    ChromeTraceEvent, ChromeTraceReader, ChromeTraceWriter, ConversionOptions, ConvertError,
+    NsysChromeConverter,
+};
+use regex::Regex;
+use std::fs::File;
+use std::io::{IsTerminal, Read, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Print a progress message, or log it as an info record in JSON log mode
+macro_rules! status {
+    ($($arg:tt)*) => {
+        match log_format() {
+            LogFormat::Json => log::info!(target: STATUS_TARGET, $($arg)*),
+            LogFormat::Text => eprintln!($($arg)*),
+        }
+    };
+}
 
 #[derive(Parser)]
 #[command(
     name = "nsys-chrome",
     about = "Convert nsys reports to Chrome Trace format",
-    version
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
 )]
-struct Args {
-    /// Input file path (.nsys-rep or .sqlite)
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
+
+    /// Log format on stderr: text, or json (one object per line, with phase timings and
+    /// event counts; RUST_LOG still filters records)
+    #[arg(
+        long = "log-format",
+        value_name = "FORMAT",
+        default_value = "text",
+        global = true,
+        value_parser = parse_log_format_arg
+    )]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Run an HTTP conversion service (submit, poll, download)
+    Serve(ServeArgs),
+    /// Browse kernels, NVTX ranges and track timelines in a full-screen terminal UI
+    View(ViewArgs),
+    /// Run SQL over a trace's events (tables: events, slices)
+    Query(QueryArgs),
+    /// Write a standalone HTML report (top kernels, NVTX ranges, GPU utilization)
+    Report(ReportArgs),
+    /// Time the output writers on synthetic events, to choose a compression setting
+    BenchWrite(BenchWriteArgs),
+}
+
+#[derive(Args)]
+struct BenchWriteArgs {
+    /// Number of synthetic events to write
+    #[arg(short = 'n', long = "events", default_value_t = 1_000_000)]
+    events: usize,
+
+    /// Writers to time: plain, gz, parallel-gz, zstd
+    #[arg(
+        long = "writers",
+        value_delimiter = ',',
+        default_values = &["plain", "gz", "parallel-gz", "zstd"],
+        value_parser = parse_writer
+    )]
+    writers: Vec<BenchWriter>,
+
+    /// Runs per writer; the fastest is reported
+    #[arg(long = "repeat", default_value_t = 3)]
+    repeat: usize,
+
+    /// Directory to write to, e.g. on the storage traces will be written to
+    /// (default: a temporary directory, removed afterwards)
+    #[arg(long = "dir", value_name = "DIR")]
+    dir: Option<String>,
+}
+
+#[derive(Args)]
+struct QueryArgs {
+    /// Converted trace (.json or .json.gz), or a .nsys-rep / .sqlite to convert first
+    #[arg(value_name = "INPUT")]
+    input: String,
+
+    /// Statement to run, e.g. "SELECT name, SUM(dur) FROM slices GROUP BY name";
+    /// without one, statements are read interactively
+    #[arg(value_name = "SQL")]
+    sql: Option<String>,
+
+    /// Print results as CSV instead of an aligned table
+    #[arg(long = "csv")]
+    csv: bool,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    /// Converted trace (.json or .json.gz), or a .nsys-rep / .sqlite to convert first
+    #[arg(value_name = "INPUT")]
+    input: String,
+
+    /// HTML file to write (default: INPUT with a .report.html extension)
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<String>,
+
+    /// Kernels and NVTX ranges to list, by total time
+    #[arg(long = "top", default_value_t = DEFAULT_REPORT_TOP)]
+    top: usize,
+
+    /// Utilization samples per device
+    #[arg(long = "bins", default_value_t = DEFAULT_REPORT_BINS)]
+    bins: usize,
+
+    /// Page title (default: the input file name)
+    #[arg(long = "title")]
+    title: Option<String>,
+}
+
+#[derive(Args)]
+struct ViewArgs {
+    /// Converted trace (.json or .json.gz), or a .nsys-rep / .sqlite to convert first
     #[arg(value_name = "INPUT")]
     input: String,
 
-    /// Output file path (.json or .json.gz)
-    #[arg(short = 'o', long = "output", value_name = "OUTPUT")]
-    output: String,
+    /// Read line commands from stdin instead of drawing the full-screen viewer
+    /// (the default when stdin or stdout is not a terminal)
+    #[arg(long = "commands")]
+    commands: bool,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long = "addr", default_value = "127.0.0.1:8080")]
+    addr: SocketAddr,
+
+    /// Maximum number of conversions running at the same time
+    #[arg(long = "max-jobs", default_value_t = 2)]
+    max_jobs: usize,
+
+    /// Maximum number of jobs waiting to run; further submissions get 503
+    #[arg(long = "max-queued", default_value_t = 8)]
+    max_queued: usize,
+
+    /// Maximum number of connections handled at the same time
+    #[arg(long = "max-connections", default_value_t = 16)]
+    max_connections: usize,
 
-    /// Activity types to include
+    /// Seconds to keep finished jobs and their files
+    #[arg(long = "job-ttl", default_value_t = 3600)]
+    job_ttl_secs: u64,
+
+    /// Allow submitting jobs by server-side file path
+    #[arg(long = "allow-paths")]
+    allow_paths: bool,
+}
+
+/// Profiler that produced the input file
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    /// Detect from the file extension and contents
+    Auto,
+    /// Nsight Systems .nsys-rep or SQLite export
+    Nsys,
+    /// rocprofiler-sdk (rocprofv3) JSON output; rocprof CSV and rocpd SQLite are rejected
+    Rocprof,
+    /// Intel unitrace Chrome trace JSON (Level Zero / SYCL)
+    Unitrace,
+    /// `nsys stats` kernel/API summary CSVs (a file or a directory of them)
+    NsysStats,
+}
+
+impl InputFormat {
+    /// Resolve `Auto` for a given input path
+    fn resolve(self, input: &str) -> InputFormat {
+        match self {
+            InputFormat::Auto if input.ends_with(".json") && is_rocprof_json(Path::new(input)) => {
+                InputFormat::Rocprof
+            }
+            InputFormat::Auto if input.ends_with(".json") && is_unitrace_json(Path::new(input)) => {
+                InputFormat::Unitrace
+            }
+            // Handed to the rocprof reader, which names the format it cannot read
+            InputFormat::Auto if unsupported_rocprof_format(Path::new(input)).is_some() => {
+                InputFormat::Rocprof
+            }
+            InputFormat::Auto if is_nsys_stats_csv(Path::new(input)) => InputFormat::NsysStats,
+            InputFormat::Auto => InputFormat::Nsys,
+            other => other,
+        }
+    }
+}
+
+/// Output compression
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Compression {
+    /// Gzip for output files, none for stdout
+    Auto,
+    /// Gzip-compressed JSON
+    Gzip,
+    /// Plain JSON
+    None,
+}
+
+impl Compression {
+    /// Whether to gzip, given where the output goes
+    fn gzip(self, to_stdout: bool) -> bool {
+        match self {
+            Compression::Auto => !to_stdout,
+            Compression::Gzip => true,
+            Compression::None => false,
+        }
+    }
+}
+
+/// Leading bytes of every SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Input file path (.nsys-rep or .sqlite), or - for stdin
+    #[arg(
+        value_name = "INPUT",
+        required_unless_present = "print_effective_config"
+    )]
+    input: Option<String>,
+
+    /// Input format
+    #[arg(long = "input-format", value_enum, default_value_t = InputFormat::Auto)]
+    input_format: InputFormat,
+
+    /// Output file path (.json or .json.gz), or - for stdout
+    #[arg(
+        short = 'o',
+        long = "output",
+        value_name = "OUTPUT",
+        required_unless_present = "print_effective_config"
+    )]
+    output: Option<String>,
+
+    /// Output compression (chosen by this flag, not the file extension)
+    #[arg(long = "compression", value_enum, default_value_t = Compression::Auto)]
+    compression: Compression,
+
+    /// Suppress progress messages (implied when writing to stdout)
+    #[arg(short = 'q', long = "quiet")]
+    quiet: bool,
+
+    /// Activity types to include (add "wddm" for Windows captures, "graphics" for Vulkan/DX12
+    /// workloads and debug labels, "nvlink" for P2P copies, "memcpy" for copy engine tracks,
+    /// "mpi" for MPI calls, "gpu-metrics" for power, temperature and clock counters with
+    /// throttling ranges, "thread-state" for running/preempted/waiting slices from context
+    /// switches)
     #[arg(
         short = 't',
         long = "types",
         value_delimiter = ',',
-        default_values = &["kernel", "nvtx", "nvtx-kernel", "cuda-api", "osrt", "sched"]
+        default_values = &["kernel", "nvtx", "nvtx-kernel", "cuda-api", "osrt", "sched"],
+        value_parser = parse_activity_type
     )]
-    activity_types: Vec<String>,
+    activity_types: Vec<EventCategory>,
 
-    /// NVTX event name prefixes to filter (comma-separated)
-    #[arg(long = "nvtx-prefix", value_delimiter = ',')]
+    /// NVTX ranges to keep (comma-separated name prefixes, globs like "model/*", or "re:REGEX")
+    #[arg(
+        long = "nvtx-prefix",
+        alias = "nvtx-filter",
+        value_delimiter = ',',
+        value_parser = parse_nvtx_filter
+    )]
     nvtx_prefix: Option<Vec<String>>,
 
+    /// NVTX domains to keep (comma-separated names or IDs; "default" for the default domain)
+    #[arg(long = "nvtx-domains", value_delimiter = ',')]
+    nvtx_domains: Option<Vec<String>>,
+
+    /// Prefix NVTX range names with their domain
+    #[arg(long = "nvtx-domain-prefix")]
+    nvtx_domain_prefix: bool,
+
+    /// Put each NVTX domain on its own thread track
+    #[arg(long = "nvtx-domain-tracks")]
+    nvtx_domain_tracks: bool,
+
+    /// Same-name nested NVTX ranges (e.g. from recursion): keep, collapse into
+    /// the outermost instance, or depth:N to drop ranges nested deeper than N
+    #[arg(
+        long = "nvtx-nesting",
+        value_name = "POLICY",
+        default_value = "keep",
+        value_parser = parse_nesting
+    )]
+    nvtx_nesting: NvtxNesting,
+
     /// Include metadata events (process/thread names)
     #[arg(long = "metadata", default_value = "true")]
     include_metadata: bool,
 
+    /// Number of launch backtrace frames to attach to kernels (0 disables)
+    #[arg(long = "source-frames", default_value_t = 3)]
+    source_frames: usize,
+
+    /// Attach the captured CPU call stack to every CUDA API call
+    #[arg(long = "api-call-stacks")]
+    api_call_stacks: bool,
+
+    /// Also write CUDA API call stacks in folded format (weighted by ns) for flamegraph tools
+    #[arg(long = "folded-stacks", value_name = "PATH")]
+    folded_stacks: Option<String>,
+
+    /// Synthesize step markers from optimizer/all-reduce kernel periodicity
+    #[arg(long = "synthesize-steps")]
+    synthesize_steps: bool,
+
+    /// Infer layer ranges from kernel launch patterns when the trace has no NVTX ranges
+    #[arg(long = "infer-layers")]
+    infer_layers: bool,
+
+    /// Add a per-device counter of concurrently running kernels and a summary of
+    /// time spent with 0, 1 and 2+ kernels in flight
+    #[arg(long = "kernel-concurrency")]
+    kernel_concurrency: bool,
+
+    /// Add a per-device counter of memcpy throughput per direction (needs the
+    /// memcpy activity) and a summary of each direction's copies and bytes
+    #[arg(long = "transfer-throughput")]
+    transfer_throughput: bool,
+
+    /// Attach FLOP/byte estimates to GEMM and attention kernels
+    #[arg(long = "estimate-costs")]
+    estimate_costs: bool,
+
+    /// Drop kernels shorter than this (e.g. 5us, 500ns), keeping linked ranges intact
+    #[arg(long = "min-duration", value_name = "DURATION", value_parser = parse_min_duration)]
+    min_duration: Option<i64>,
+
+    /// Color CUDA API calls by thread state (sync = waiting, memcpy = I/O, launch = running)
+    #[arg(long = "api-thread-states")]
+    api_thread_states: bool,
+
+    /// Mark kernels running more than FACTOR times their name's median duration
+    /// (default 5) with an outlier arg and color
+    #[arg(
+        long = "flag-outliers",
+        value_name = "FACTOR",
+        num_args = 0..=1,
+        default_missing_value = "5",
+        value_parser = parse_factor
+    )]
+    flag_outliers: Option<f64>,
+
+    /// Rebase timestamps: absolute, capture-start, or nvtx:NAME (first range with that name)
+    #[arg(
+        long = "time-origin",
+        value_name = "ORIGIN",
+        default_value = "absolute",
+        value_parser = parse_origin
+    )]
+    time_origin: TimeOrigin,
+
+    /// Keep only START..END after the capture start (e.g. 2s..3.5s), clamping ranges
+    /// that straddle the window and marking them truncated_start/truncated_end
+    #[arg(long = "time-window", value_name = "START..END", value_parser = parse_window)]
+    time_window: Option<TimeWindow>,
+
+    /// Worker threads for reading tables, linking devices and serializing output
+    /// concurrently (0 = one per CPU core)
+    #[arg(short = 'j', long = "jobs", value_name = "N", default_value_t = 1)]
+    jobs: usize,
+
+    /// Shift annotations before linking them to kernels, per NVTX domain or category
+    /// (e.g. nvtx=-20us, or MyWrapper=auto to estimate the offset)
+    #[arg(
+        long = "time-shift",
+        value_name = "KEY=SHIFT",
+        value_delimiter = ',',
+        value_parser = parse_shift
+    )]
+    time_shifts: Vec<(String, TimeShift)>,
+
+    /// Which overlapping NVTX ranges a CUDA API call links to: all, innermost or longest-overlap
+    #[arg(
+        long = "link-policy",
+        value_name = "POLICY",
+        default_value = "all",
+        value_parser = parse_policy
+    )]
+    link_policy: LinkPolicy,
+
+    /// Leave kernels starting more than this after their CUDA API call ended unlinked
+    /// (e.g. 1s), so stale correlation IDs after wraparound do not link distant kernels
+    #[arg(long = "max-link-gap", value_name = "DURATION", value_parser = parse_min_duration)]
+    max_link_gap: Option<i64>,
+
+    /// Link NVTX ranges to kernels in time windows of this length (e.g. 10s), spilling
+    /// partial links to a temp file, to bound the linker's working state on hosts with
+    /// little RAM
+    #[arg(long = "link-window", value_name = "DURATION", value_parser = parse_link_window)]
+    link_window: Option<i64>,
+
+    /// Link kernels whose CUDA API call lies outside every NVTX range to the last range
+    /// that ended on the same thread at most this long before the call (e.g. 5ms), for
+    /// frameworks that pop ranges before synchronizing; such ranges get attribution=sticky
+    #[arg(long = "sticky-lookback", value_name = "DURATION", value_parser = parse_min_duration)]
+    sticky_lookback: Option<i64>,
+
+    /// What to do with kernels recorded without a stream ID: unknown (own track),
+    /// infer (from the launching thread's other launches) or drop
+    #[arg(
+        long = "missing-stream",
+        value_name = "POLICY",
+        default_value = "unknown",
+        value_parser = parse_stream_policy
+    )]
+    missing_stream: MissingStreamPolicy,
+
+    /// Emit one nvtx-kernel range per CUDA stream instead of one spanning all streams
+    #[arg(long = "nvtx-kernel-per-stream")]
+    nvtx_kernel_per_stream: bool,
+
+    /// Where nvtx-kernel ranges go: device (a track per annotating thread under
+    /// the GPU) or annotation (the range's own NVTX track, in place of the CPU range)
+    #[arg(
+        long = "nvtx-kernel-layout",
+        value_name = "LAYOUT",
+        default_value = "device",
+        value_parser = parse_kernel_layout
+    )]
+    nvtx_kernel_layout: NvtxKernelLayout,
+
+    /// Mark NVTX ranges that received no kernels during linking with unattributed=true
+    #[arg(long = "tag-unattributed")]
+    tag_unattributed: bool,
+
+    /// Add "Unattributed GPU work" slices where a device runs kernels outside every
+    /// NVTX range (needs nvtx-kernel)
+    #[arg(long = "unattributed-work")]
+    unattributed_work: bool,
+
+    /// Write only NVTX ranges and marks, per process and thread with their nesting
+    /// depth, without reading kernel or API tables (for auditing annotations)
+    #[arg(long = "annotations-only", conflicts_with_all = ["cache_events", "from_cache"])]
+    annotations_only: bool,
+
+    /// How flow arrows are written: events (s/f pairs) or bound (bind_id on the linked slices)
+    #[arg(
+        long = "flow-style",
+        value_name = "STYLE",
+        default_value = "events",
+        value_parser = parse_style
+    )]
+    flow_style: FlowStyle,
+
+    /// Slice each kind of flow arrow attaches to, per link (launch, copy, mpi or all):
+    /// enclosing or next (e.g. launch=next,mpi=enclosing)
+    #[arg(
+        long = "flow-bind",
+        value_name = "LINK=BIND",
+        value_delimiter = ',',
+        value_parser = parse_bind_spec
+    )]
+    flow_binds: Vec<(Vec<FlowLink>, FlowBind)>,
+
+    /// Record the source table and rowid of every event in its args, for debugging
+    #[arg(long = "source-rows")]
+    source_rows: bool,
+
+    /// Emit numeric pid/tid with track names in metadata events
+    #[arg(long = "numeric-ids")]
+    numeric_ids: bool,
+
+    /// Number the tracks of each process 1, 2, ... grouped by category (all NVTX
+    /// threads together, then all CUDA API threads) or by OS thread, with
+    /// thread_name and thread_sort_index metadata
+    #[arg(
+        long = "virtual-tids",
+        value_name = "GROUPING",
+        num_args = 0..=1,
+        default_missing_value = "category",
+        value_parser = parse_grouping
+    )]
+    virtual_tids: Option<TidGrouping>,
+
+    /// Path for the numeric ID mapping (default: OUTPUT with .ids.json extension)
+    #[arg(long = "id-map", value_name = "PATH", requires = "numeric_ids")]
+    id_map: Option<String>,
+
+    /// Also write the GPU properties embedded in the trace metadata to PATH (e.g. devices.json)
+    #[arg(long = "devices-json", value_name = "PATH")]
+    devices_json: Option<String>,
+
+    /// Also write kernel busy time per stream per time bin to PATH (.csv or .json)
+    #[arg(long = "heatmap", value_name = "PATH", value_parser = parse_heatmap_path)]
+    heatmap: Option<String>,
+
+    /// Heatmap bin width (e.g. 10ms, 1s)
+    #[arg(
+        long = "heatmap-bin",
+        value_name = "DURATION",
+        default_value = "10ms",
+        value_parser = parse_heatmap_bin
+    )]
+    heatmap_bin: i64,
+
+    /// Also write launch-bound runs of short kernels, with fusion savings estimates,
+    /// to PATH (.csv or .json)
+    #[arg(long = "fusion-report", value_name = "PATH", value_parser = parse_report_path)]
+    fusion_report: Option<String>,
+
+    /// Longest kernel counted as short in the fusion report (e.g. 20us)
+    #[arg(
+        long = "fusion-max-kernel",
+        value_name = "DURATION",
+        default_value = "20us",
+        value_parser = parse_fusion_max_kernel
+    )]
+    fusion_max_kernel: i64,
+
+    /// Also write each rank's arrival offset at every matched MPI collective, NCCL
+    /// kernel and step to PATH (.csv), and mark late ranks with straggler instants
+    #[arg(long = "skew-report", value_name = "PATH", value_parser = parse_skew_path)]
+    skew_report: Option<String>,
+
+    /// Merge another rank's capture (.nsys-rep or .sqlite) into the output, its
+    /// processes prefixed with its file name; repeat for each rank
+    #[arg(long = "merge-rank", value_name = "PATH")]
+    merge_ranks: Vec<String>,
+
+    /// Smallest first-to-last arrival skew marked with a straggler instant (e.g. 1ms)
+    #[arg(
+        long = "straggler-min-skew",
+        value_name = "DURATION",
+        default_value = "100us",
+        value_parser = parse_min_duration
+    )]
+    straggler_min_skew: i64,
+
+    /// Also write GPU time, kernel count, communication time and idle time of
+    /// every step range to PATH (.csv), and add them as args on the step ranges
+    #[arg(long = "step-stats", value_name = "PATH", value_parser = parse_step_stats_path)]
+    step_stats: Option<String>,
+
+    /// Regex matching the names of NVTX ranges that mark one training step
+    #[arg(
+        long = "step-pattern",
+        value_name = "REGEX",
+        default_value = DEFAULT_STEP_PATTERN,
+        value_parser = parse_step_pattern_arg
+    )]
+    step_pattern: Regex,
+
+    /// Also write the converter's own phase timings to PATH as a Chrome trace
+    #[arg(long = "self-profile", value_name = "PATH")]
+    self_profile: Option<String>,
+
+    /// After converting, push summary metrics (GPU time per device and kernel
+    /// class, utilization, p99 launch latency) to an OTLP/HTTP endpoint, e.g.
+    /// http://localhost:4318
+    #[arg(long = "otlp-metrics", value_name = "URL", value_parser = parse_endpoint)]
+    otlp_metrics: Option<OtlpEndpoint>,
+
+    /// Resource attribute attached to the OTLP metrics (repeatable), e.g. git.commit=abc123
+    #[arg(
+        long = "otlp-attribute",
+        value_name = "KEY=VALUE",
+        value_parser = parse_resource_attribute,
+        requires = "otlp_metrics"
+    )]
+    otlp_attributes: Vec<(String, String)>,
+
+    /// Also write OUTPUT.outline.json indexing NVTX ranges and steps by byte offset
+    #[arg(long = "outline")]
+    outline: bool,
+
+    /// Write gzip output as independently compressed blocks and OUTPUT.seek.json
+    /// indexing them by time, so viewers can fetch only the blocks of a time range
+    #[arg(long = "seekable")]
+    seekable: bool,
+
+    /// Write only linked nvtx-kernel ranges, step markers and counters to OUTPUT,
+    /// leaving out raw kernels and API calls (routed outputs still get every event)
+    #[arg(long = "summary-trace")]
+    summary_trace: bool,
+
+    /// Keep only capture session N (1-based), rebased to start at zero
+    #[arg(long = "session", value_name = "N")]
+    session: Option<usize>,
+
+    /// Write each capture session to its own OUTPUT.session-N.json[.gz], rebased to zero
+    #[arg(
+        long = "split-sessions",
+        conflicts_with_all = ["session", "numeric_ids", "outline", "seekable", "serve_trace"]
+    )]
+    split_sessions: bool,
+
+    /// Idle time with no events at all that separates capture sessions (e.g. 500ms)
+    #[arg(
+        long = "session-gap",
+        value_name = "DURATION",
+        default_value = "1s",
+        value_parser = parse_session_gap
+    )]
+    session_gap: i64,
+
+    /// Replace kernel names with short IDs plus one dictionary metadata event
+    #[arg(long = "compress-names")]
+    compress_names: bool,
+
+    /// Load and extend this kernel-name dictionary, so IDs are shared across traces
+    #[arg(long = "name-dictionary", value_name = "PATH", requires = "compress_names")]
+    name_dictionary: Option<String>,
+
+    /// Replace args larger than this (bytes, or e.g. 4k) with a preview, size and hash ID
+    #[arg(long = "large-args", value_name = "BYTES", value_parser = parse_large_args)]
+    large_args: Option<usize>,
+
+    /// Keep the replaced args in this JSON file, keyed by ID
+    #[arg(long = "large-args-sidecar", value_name = "PATH", requires = "large_args")]
+    large_args_sidecar: Option<String>,
+
+    /// Restore kernel names in a trace written with --compress-names (INPUT is that trace)
+    #[arg(
+        long = "expand-names",
+        conflicts_with_all = ["compress_names", "from_cache", "cache_events", "input_format"]
+    )]
+    expand_names: bool,
+
+    /// Send categories to another output instead (e.g. "counters=counters.json",
+    /// "kernel=stats.csv"; repeatable; "*" for all)
+    #[arg(long = "route", value_name = "CATEGORIES=PATH", value_parser = parse_route)]
+    routes: Vec<OutputRoute>,
+
+    /// Write Begin/End (B/E) event pairs instead of complete (X) events
+    #[arg(long = "begin-end", conflicts_with = "outline")]
+    begin_end: bool,
+
+    /// Write only the fields and phases this viewer supports: perfetto (default, full
+    /// fidelity), chrome or legacy-catapult
+    #[arg(long = "compat", value_name = "TARGET", value_parser = parse_compat_arg)]
+    compat: Option<CompatTarget>,
+
+    /// Write from a separate thread fed through a bounded channel of this many
+    /// event batches and report stall times; with --merge-rank, each rank is
+    /// written before the next is converted
+    #[arg(long = "channel-capacity", value_name = "BATCHES")]
+    channel_capacity: Option<usize>,
+
+    /// Serve the output on 127.0.0.1:9001 and print a ui.perfetto.dev link that opens it
+    #[arg(long = "serve-trace")]
+    serve_trace: bool,
+
+    /// Overwrite OUTPUT (and per-session outputs) if it already exists
+    #[arg(long = "force")]
+    force: bool,
+
     /// Keep intermediate SQLite file (if converting from .nsys-rep)
     #[arg(long = "keep-sqlite")]
     keep_sqlite: bool,
+
+    /// Also save the extracted (pre-link) nsys events to a binary cache at PATH
+    #[arg(long = "cache-events", value_name = "PATH")]
+    cache_events: Option<String>,
+
+    /// Read INPUT as an event cache written by --cache-events, skipping SQLite
+    #[arg(long = "from-cache", conflicts_with_all = ["cache_events", "input_format"])]
+    from_cache: bool,
+
+    /// Keep converting INPUT, an SQLite export nsys is still writing, whenever it
+    /// changes and append the new events to OUTPUT (plain JSON array) until Ctrl-C;
+    /// passes that need the whole trace (analyses, reports, routes) do not run
+    #[arg(
+        long = "follow",
+        conflicts_with_all = [
+            "from_cache", "cache_events", "expand_names", "split_sessions", "session",
+            "outline", "seekable", "numeric_ids", "serve_trace", "keep_sqlite"
+        ]
+    )]
+    follow: bool,
+
+    /// Time between two checks of INPUT for changes with --follow (e.g. 500ms)
+    #[arg(
+        long = "follow-interval",
+        value_name = "DURATION",
+        default_value = "2s",
+        value_parser = parse_follow_duration,
+        requires = "follow"
+    )]
+    follow_interval: Duration,
+
+    /// Stop following once INPUT has produced no new events for this long (e.g. 10m)
+    #[arg(
+        long = "follow-idle",
+        value_name = "DURATION",
+        value_parser = parse_follow_duration,
+        requires = "follow"
+    )]
+    follow_idle: Option<Duration>,
+
+    /// Start from a bundle of options for a use case: training, inference or comm-debug
+    /// (flags given on the command line still apply on top)
+    #[arg(long = "preset", value_name = "NAME", value_parser = parse_preset_name)]
+    preset: Option<Preset>,
+
+    /// Overlay options from a JSON file keyed like the flags, applied after --preset
+    /// (e.g. {"nvtx_colors": {"^loss": "bad"}, "min_duration": "2us"})
+    #[arg(long = "config", value_name = "PATH")]
+    config: Option<String>,
+
+    /// Print the options after --preset, --config and the flags are applied, as a JSON
+    /// config file or text protobuf (json or textproto), and exit without converting
+    #[arg(
+        long = "print-effective-config",
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = "json",
+        value_parser = parse_config_format_arg
+    )]
+    print_effective_config: Option<ConfigFormat>,
+}
+
+impl ConvertArgs {
+    /// Whether a pass after conversion needs every event of the trace at once
+    fn needs_whole_trace(&self, options: &ConversionOptions) -> bool {
+        self.session.is_some()
+            || self.split_sessions
+            || self.folded_stacks.is_some()
+            || self.devices_json.is_some()
+            || self.heatmap.is_some()
+            || self.fusion_report.is_some()
+            || self.step_stats.is_some()
+            || self.skew_report.is_some()
+            || self.otlp_metrics.is_some()
+            || self.large_args.is_some()
+            || self.compress_names
+            || !options.output_routes.is_empty()
+            || self.summary_trace
+            || self.compat.is_some()
+            || self.outline
+            || self.begin_end
+    }
+
+    /// Build conversion options from the preset, config file and command line
+    ///
+    /// Flags left at their defaults keep the value of the preset or config
+    /// file; flags given on the command line replace it.
+    fn conversion_options(&self) -> anyhow::Result<ConversionOptions> {
+        let flags = self.flag_options();
+        if self.preset.is_none() && self.config.is_none() {
+            return Ok(flags);
+        }
+        let mut base = self
+            .preset
+            .map_or_else(ConversionOptions::default, |preset| preset.options());
+        if let Some(path) = &self.config {
+            base = load_overlay(base, path)?;
+        }
+
+        let defaults = ConversionOptions::default();
+        let mut nvtx_color_scheme = base.nvtx_color_scheme;
+        nvtx_color_scheme.extend(flags.nvtx_color_scheme);
+        Ok(ConversionOptions {
+            activity_types: flag_or(
+                flags.activity_types,
+                defaults.activity_types,
+                base.activity_types,
+            ),
+            nvtx_event_prefix: flags.nvtx_event_prefix.or(base.nvtx_event_prefix),
+            nvtx_color_scheme,
+            nvtx_domains: flags.nvtx_domains.or(base.nvtx_domains),
+            nvtx_domain_prefix: flags.nvtx_domain_prefix || base.nvtx_domain_prefix,
+            nvtx_domain_tracks: flags.nvtx_domain_tracks || base.nvtx_domain_tracks,
+            nvtx_nesting: flag_or(flags.nvtx_nesting, defaults.nvtx_nesting, base.nvtx_nesting),
+            include_metadata: flags.include_metadata && base.include_metadata,
+            source_frame_depth: flag_or(
+                flags.source_frame_depth,
+                defaults.source_frame_depth,
+                base.source_frame_depth,
+            ),
+            api_call_stacks: flags.api_call_stacks || base.api_call_stacks,
+            synthesize_steps: flags.synthesize_steps || base.synthesize_steps,
+            infer_layers: flags.infer_layers || base.infer_layers,
+            kernel_concurrency: flags.kernel_concurrency || base.kernel_concurrency,
+            transfer_throughput: flags.transfer_throughput || base.transfer_throughput,
+            estimate_kernel_costs: flags.estimate_kernel_costs || base.estimate_kernel_costs,
+            min_kernel_duration_ns: flag_or(
+                flags.min_kernel_duration_ns,
+                defaults.min_kernel_duration_ns,
+                base.min_kernel_duration_ns,
+            ),
+            api_thread_states: flags.api_thread_states || base.api_thread_states,
+            kernel_outlier_factor: flags.kernel_outlier_factor.or(base.kernel_outlier_factor),
+            time_origin: flag_or(flags.time_origin, defaults.time_origin, base.time_origin),
+            time_window: flags.time_window.or(base.time_window),
+            output_routes: flag_or(
+                flags.output_routes,
+                defaults.output_routes,
+                base.output_routes,
+            ),
+            jobs: flag_or(flags.jobs, defaults.jobs, base.jobs),
+            source_rows: flags.source_rows || base.source_rows,
+            annotation_time_shifts: flag_or(
+                flags.annotation_time_shifts,
+                defaults.annotation_time_shifts,
+                base.annotation_time_shifts,
+            ),
+            link_policy: flag_or(flags.link_policy, defaults.link_policy, base.link_policy),
+            max_link_gap_ns: flags.max_link_gap_ns.or(base.max_link_gap_ns),
+            link_window_ns: flags.link_window_ns.or(base.link_window_ns),
+            sticky_lookback_ns: flags.sticky_lookback_ns.or(base.sticky_lookback_ns),
+            nvtx_kernel_per_stream: flags.nvtx_kernel_per_stream || base.nvtx_kernel_per_stream,
+            nvtx_kernel_layout: flag_or(
+                flags.nvtx_kernel_layout,
+                defaults.nvtx_kernel_layout,
+                base.nvtx_kernel_layout,
+            ),
+            tag_unattributed: flags.tag_unattributed || base.tag_unattributed,
+            unattributed_work: flags.unattributed_work || base.unattributed_work,
+            annotations_only: flags.annotations_only || base.annotations_only,
+            missing_stream_policy: flag_or(
+                flags.missing_stream_policy,
+                defaults.missing_stream_policy,
+                base.missing_stream_policy,
+            ),
+            virtual_tids: flags.virtual_tids.or(base.virtual_tids),
+            flows: FlowOptions {
+                style: flag_or(flags.flows.style, defaults.flows.style, base.flows.style),
+                launch: flag_or(flags.flows.launch, defaults.flows.launch, base.flows.launch),
+                copy: flag_or(flags.flows.copy, defaults.flows.copy, base.flows.copy),
+                mpi: flag_or(flags.flows.mpi, defaults.flows.mpi, base.flows.mpi),
+            },
+        })
+    }
+
+    /// Build conversion options from the command line flags alone
+    fn flag_options(&self) -> ConversionOptions {
+        ConversionOptions {
+            activity_types: self.activity_types.clone(),
+            nvtx_event_prefix: self.nvtx_prefix.clone(),
+            nvtx_color_scheme: Default::default(),
+            nvtx_domains: self.nvtx_domains.clone(),
+            nvtx_domain_prefix: self.nvtx_domain_prefix,
+            nvtx_domain_tracks: self.nvtx_domain_tracks,
+            nvtx_nesting: self.nvtx_nesting,
+            include_metadata: self.include_metadata,
+            source_frame_depth: self.source_frames,
+            api_call_stacks: self.api_call_stacks || self.folded_stacks.is_some(),
+            synthesize_steps: self.synthesize_steps,
+            infer_layers: self.infer_layers,
+            kernel_concurrency: self.kernel_concurrency,
+            transfer_throughput: self.transfer_throughput,
+            estimate_kernel_costs: self.estimate_costs,
+            min_kernel_duration_ns: self.min_duration.unwrap_or(0),
+            api_thread_states: self.api_thread_states,
+            kernel_outlier_factor: self.flag_outliers,
+            time_origin: self.time_origin.clone(),
+            time_window: self.time_window,
+            output_routes: self.routes.clone(),
+            jobs: self.jobs,
+            source_rows: self.source_rows,
+            annotation_time_shifts: self.time_shifts.iter().cloned().collect(),
+            link_policy: self.link_policy,
+            max_link_gap_ns: self.max_link_gap,
+            link_window_ns: self.link_window,
+            sticky_lookback_ns: self.sticky_lookback,
+            nvtx_kernel_per_stream: self.nvtx_kernel_per_stream,
+            nvtx_kernel_layout: self.nvtx_kernel_layout,
+            tag_unattributed: self.tag_unattributed,
+            unattributed_work: self.unattributed_work,
+            missing_stream_policy: self.missing_stream,
+            virtual_tids: self.virtual_tids,
+            annotations_only: self.annotations_only,
+            flows: self.flow_options(),
+        }
+    }
+
+    /// Flow options from `--flow-style` and `--flow-bind`, later bindings winning
+    fn flow_options(&self) -> FlowOptions {
+        let mut flows = FlowOptions {
+            style: self.flow_style,
+            ..Default::default()
+        };
+        for (links, bind) in &self.flow_binds {
+            for &link in links {
+                flows.set_bind(link, *bind);
+            }
+        }
+        flows
+    }
+}
+
+/// Parse a `--min-duration` or `--max-link-gap` value into nanoseconds
+fn parse_min_duration(value: &str) -> Result<i64, String> {
+    parse_duration_ns(value).map_err(|e| e.to_string())
+}
+
+/// Parse a `--follow-interval` or `--follow-idle` value
+fn parse_follow_duration(value: &str) -> Result<Duration, String> {
+    match parse_duration_ns(value).map_err(|e| e.to_string())? {
+        0 => Err("Follow durations must be positive".to_string()),
+        ns => Ok(Duration::from_nanos(ns as u64)),
+    }
+}
+
+/// Parse a `--link-window` value
+fn parse_link_window(value: &str) -> Result<i64, String> {
+    match parse_duration_ns(value).map_err(|e| e.to_string())? {
+        0 => Err("The link window must be positive".to_string()),
+        ns => Ok(ns),
+    }
+}
+
+fn parse_factor(value: &str) -> Result<f64, String> {
+    parse_outlier_factor(value).map_err(|e| e.to_string())
+}
+
+fn parse_heatmap_path(value: &str) -> Result<String, String> {
+    if value.ends_with(".csv") || value.ends_with(".json") {
+        Ok(value.to_string())
+    } else {
+        Err("Heatmap path must end in .csv or .json".to_string())
+    }
+}
+
+fn parse_report_path(value: &str) -> Result<String, String> {
+    if value.ends_with(".csv") || value.ends_with(".json") {
+        Ok(value.to_string())
+    } else {
+        Err("Fusion report path must end in .csv or .json".to_string())
+    }
+}
+
+fn parse_skew_path(value: &str) -> Result<String, String> {
+    if value.ends_with(".csv") {
+        Ok(value.to_string())
+    } else {
+        Err("Skew report path must end in .csv".to_string())
+    }
+}
+
+fn parse_step_stats_path(value: &str) -> Result<String, String> {
+    if value.ends_with(".csv") {
+        Ok(value.to_string())
+    } else {
+        Err("Step stats path must end in .csv".to_string())
+    }
+}
+
+fn parse_step_pattern_arg(value: &str) -> Result<Regex, String> {
+    parse_step_pattern(value).map_err(|e| e.to_string())
+}
+
+/// Parse a `--fusion-max-kernel` value into nanoseconds
+fn parse_fusion_max_kernel(value: &str) -> Result<i64, String> {
+    match parse_duration_ns(value).map_err(|e| e.to_string())? {
+        0 => Err("Fusion kernel threshold must be positive".to_string()),
+        max_ns => Ok(max_ns),
+    }
+}
+
+/// Parse a `--heatmap-bin` value into nanoseconds
+fn parse_heatmap_bin(value: &str) -> Result<i64, String> {
+    match parse_duration_ns(value).map_err(|e| e.to_string())? {
+        0 => Err("Heatmap bin must be positive".to_string()),
+        bin_ns => Ok(bin_ns),
+    }
+}
+
+/// Parse a `--session-gap` value into nanoseconds
+fn parse_session_gap(value: &str) -> Result<i64, String> {
+    match parse_duration_ns(value).map_err(|e| e.to_string())? {
+        0 => Err("Session gap must be positive".to_string()),
+        gap_ns => Ok(gap_ns),
+    }
+}
+
+/// Parse a `--time-origin` value
+fn parse_origin(value: &str) -> Result<TimeOrigin, String> {
+    parse_time_origin(value).map_err(|e| e.to_string())
+}
+
+/// Parse a `--time-window` value
+fn parse_window(value: &str) -> Result<TimeWindow, String> {
+    parse_time_window(value).map_err(|e| e.to_string())
+}
+
+fn parse_shift(value: &str) -> Result<(String, TimeShift), String> {
+    parse_time_shift_spec(value).map_err(|e| e.to_string())
+}
+
+fn parse_endpoint(value: &str) -> Result<OtlpEndpoint, String> {
+    parse_otlp_endpoint(value).map_err(|e| e.to_string())
+}
+
+fn parse_resource_attribute(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, attr)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), attr.to_string()))
+        }
+        _ => Err(format!(
+            "Invalid attribute '{}' (expected KEY=VALUE)",
+            value
+        )),
+    }
+}
+
+fn parse_policy(value: &str) -> Result<LinkPolicy, String> {
+    parse_link_policy(value).map_err(|e| e.to_string())
+}
+
+fn parse_stream_policy(value: &str) -> Result<MissingStreamPolicy, String> {
+    parse_missing_stream_policy(value).map_err(|e| e.to_string())
+}
+
+fn parse_large_args(value: &str) -> Result<usize, String> {
+    parse_args_limit(value).map_err(|e| e.to_string())
+}
+
+fn parse_nesting(value: &str) -> Result<NvtxNesting, String> {
+    parse_nvtx_nesting(value).map_err(|e| e.to_string())
+}
+
+fn parse_kernel_layout(value: &str) -> Result<NvtxKernelLayout, String> {
+    parse_nvtx_kernel_layout(value).map_err(|e| e.to_string())
+}
+
+fn parse_config_format_arg(value: &str) -> Result<ConfigFormat, String> {
+    parse_config_format(value).map_err(|e| e.to_string())
+}
+
+fn parse_grouping(value: &str) -> Result<TidGrouping, String> {
+    parse_tid_grouping(value).map_err(|e| e.to_string())
+}
+
+fn parse_style(value: &str) -> Result<FlowStyle, String> {
+    parse_flow_style(value).map_err(|e| e.to_string())
+}
+
+fn parse_bind_spec(value: &str) -> Result<(Vec<FlowLink>, FlowBind), String> {
+    parse_flow_bind_spec(value).map_err(|e| e.to_string())
+}
+
+fn parse_writer(value: &str) -> Result<BenchWriter, String> {
+    parse_bench_writer(value).map_err(|e| e.to_string())
+}
+
+fn parse_preset_name(value: &str) -> Result<Preset, String> {
+    parse_preset(value).map_err(|e| e.to_string())
+}
+
+/// The flag's value if it was changed from its default, otherwise the base value
+fn flag_or<T: PartialEq>(flag: T, default: T, base: T) -> T {
+    if flag != default {
+        flag
+    } else {
+        base
+    }
+}
+
+fn parse_route(value: &str) -> Result<OutputRoute, String> {
+    OutputRoute::parse(value).map_err(|e| e.to_string())
+}
+
+/// Parse a `--types` value, refusing likely typos of built-in types
+fn parse_activity_type(value: &str) -> Result<EventCategory, String> {
+    EventCategory::parse(value).map_err(|e| e.to_string())
+}
+
+/// Parse a `--compat` value
+fn parse_compat_arg(value: &str) -> Result<CompatTarget, String> {
+    parse_compat_target(value).map_err(|e| e.to_string())
+}
+
+/// Parse a `--log-format` value
+fn parse_log_format_arg(value: &str) -> Result<LogFormat, String> {
+    parse_log_format(value).map_err(|e| e.to_string())
+}
+
+fn parse_nvtx_filter(value: &str) -> Result<String, String> {
+    NvtxNameFilter::new(&Some(vec![value.to_string()])).map_err(|e| e.to_string())?;
+    Ok(value.to_string())
 }
 
 fn main() -> anyhow::Result<()> {
-    // Initialize logging from RUST_LOG environment variable
+    let cli = Cli::parse();
+
+    // Initialize logging, filtered by the RUST_LOG environment variable
     // This is inherited from the parent process when called via subprocess
-    env_logger::init();
+    logging::init(cli.log_format);
 
-    let args = Args::parse();
+    match cli.command {
+        Some(Commands::Serve(serve_args)) => {
+            run_serve(serve_args, cli.convert.conversion_options()?)
+        }
+        Some(Commands::View(view_args)) => run_view(view_args, cli.convert.conversion_options()?),
+        Some(Commands::Query(query_args)) => {
+            run_query(query_args, cli.convert.conversion_options()?)
+        }
+        Some(Commands::Report(report_args)) => {
+            run_report(report_args, cli.convert.conversion_options()?)
+        }
+        Some(Commands::BenchWrite(bench_args)) => run_bench_write(bench_args),
+        None => run_convert(cli.convert),
+    }
+}
 
+/// Run the HTTP conversion service
+fn run_serve(args: ServeArgs, options: ConversionOptions) -> anyhow::Result<()> {
+    let config = ServiceConfig {
+        addr: args.addr,
+        max_concurrent_jobs: args.max_jobs,
+        max_queued_jobs: args.max_queued,
+        max_connections: args.max_connections,
+        job_ttl: Duration::from_secs(args.job_ttl_secs),
+        allow_local_paths: args.allow_paths,
+        options,
+    };
+
+    let service = ConversionService::bind(config)?;
+    status!("Serving conversions on http://{}", service.local_addr()?);
+    service.run()?;
+    Ok(())
+}
+
+/// Browse a trace in the terminal
+fn run_view(args: ViewArgs, options: ConversionOptions) -> anyhow::Result<()> {
+    let input = &args.input;
+    let browser = if input.ends_with(".json") || input.ends_with(".json.gz") {
+        TraceBrowser::open(input)?
+    } else {
+        status!("Converting {} for viewing...", input);
+        TraceBrowser::from_events(&convert_nsys(input, false, None, true, options, None)?)
+    };
+    let terminal = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    if args.commands || !terminal || cfg!(not(unix)) {
+        run_interactive(&browser)?;
+    } else {
+        run_tui(&browser)?;
+    }
+    Ok(())
+}
+
+/// Run SQL over a trace's events
+fn run_query(args: QueryArgs, options: ConversionOptions) -> anyhow::Result<()> {
+    let input = &args.input;
+    let db = if input.ends_with(".json") || input.ends_with(".json.gz") {
+        TraceDatabase::open(input)?
+    } else {
+        status!("Converting {} for querying...", input);
+        TraceDatabase::from_events(&convert_nsys(input, false, None, true, options, None)?)?
+    };
+    match &args.sql {
+        Some(sql) => {
+            let result = db.query(sql)?;
+            let text = if args.csv {
+                result.render_csv()
+            } else {
+                result.render_table()
+            };
+            print!("{}", text);
+        }
+        None => run_query_interactive(&db)?,
+    }
+    Ok(())
+}
+
+/// Write a standalone HTML report of a trace
+fn run_report(args: ReportArgs, options: ConversionOptions) -> anyhow::Result<()> {
+    let input = &args.input;
+    let events = if input.ends_with(".json") || input.ends_with(".json.gz") {
+        ChromeTraceReader::read(Path::new(input))?
+    } else {
+        status!("Converting {} for the report...", input);
+        convert_nsys(input, false, None, true, options, None)?
+    };
+    let title = args.title.unwrap_or_else(|| {
+        let name = Path::new(input).file_name().unwrap_or_default();
+        format!("Trace report: {}", name.to_string_lossy())
+    });
+    let report_options = ReportOptions {
+        title,
+        top: args.top,
+        bins: args.bins,
+    };
+    let output = args.output.unwrap_or_else(|| report_path(input));
+    TraceReport::build(&events, &report_options).write(&output)?;
+    status!("Report: {}", output);
+    Ok(())
+}
+
+/// Time the output writers on synthetic events
+fn run_bench_write(args: BenchWriteArgs) -> anyhow::Result<()> {
+    let temp_dir;
+    let dir = match &args.dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            PathBuf::from(dir)
+        }
+        None => {
+            temp_dir = tempfile::tempdir()?;
+            temp_dir.path().to_path_buf()
+        }
+    };
+    status!("Generating {} synthetic events...", args.events);
+    let events = synthetic_events(args.events);
+    let report = run_write_bench(&events, &args.writers, &dir, args.repeat)?;
+    print!("{}", report.render_table());
+    Ok(())
+}
+
+/// Path that stands for stdin (as INPUT) or stdout (as OUTPUT)
+const STDIO_PATH: &str = "-";
+
+/// Cancel `token` on the first Ctrl-C; a second one exits right away
+#[cfg(unix)]
+fn cancel_on_interrupt(token: &CancellationToken) {
+    use std::sync::OnceLock;
+
+    static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        if let Some(token) = TOKEN.get() {
+            token.cancel();
+        }
+        // SAFETY: signal() is async-signal-safe
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+    }
+
+    if TOKEN.set(token.clone()).is_ok() {
+        // SAFETY: the handler only stores to atomics and calls signal()
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn cancel_on_interrupt(_token: &CancellationToken) {}
+
+/// Let Ctrl-C terminate the process again
+fn restore_interrupt() {
+    #[cfg(unix)]
+    // SAFETY: restores the default disposition
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+}
+
+/// Convert a single input file, stopping cleanly on Ctrl-C
+///
+/// An interrupted conversion leaves no output file behind: outputs are only
+/// moved into place once fully written.
+fn run_convert(args: ConvertArgs) -> anyhow::Result<()> {
+    let cancellation = CancellationToken::new();
+    cancel_on_interrupt(&cancellation);
+    match convert_input(args, &cancellation) {
+        // Whatever failed (a query, nsys export) failed because of the interrupt
+        Err(_) if cancellation.is_cancelled() => Err(ConvertError::Cancelled.into()),
+        result => result,
+    }
+}
+
+/// Convert a single input file
+fn convert_input(args: ConvertArgs, cancellation: &CancellationToken) -> anyhow::Result<()> {
+    let options = args.conversion_options()?;
+    if let Some(format) = args.print_effective_config {
+        print!("{}", render_config(&options, format));
+        return Ok(());
+    }
+    let input = args.input.clone().expect("INPUT is required");
+    let output = args.output.clone().expect("OUTPUT is required");
+    let to_stdout = output == STDIO_PATH;
+    // Progress messages stay off stderr in pipelines; warnings are still printed
+    let quiet = args.quiet || to_stdout;
+
+    if to_stdout && args.serve_trace {
+        anyhow::bail!("--serve-trace needs an output file, not stdout");
+    }
+    // Fail before converting rather than after
+    if !to_stdout && !args.force && Path::new(&output).exists() {
+        anyhow::bail!(
+            "Output file already exists: {} (pass --force to overwrite)",
+            output
+        );
+    }
+    if to_stdout && args.split_sessions {
+        anyhow::bail!("--split-sessions needs an output file, not stdout");
+    }
+    if to_stdout && args.outline {
+        anyhow::bail!("--outline needs an output file, not stdout");
+    }
+    if args.seekable && (to_stdout || !args.compression.gzip(to_stdout)) {
+        anyhow::bail!("--seekable needs gzip-compressed output to a file");
+    }
+    if to_stdout && args.numeric_ids && args.id_map.is_none() {
+        anyhow::bail!("--numeric-ids with stdout output needs --id-map");
+    }
+
+    if args.follow && (input == STDIO_PATH || input.ends_with(".nsys-rep")) {
+        anyhow::bail!("--follow needs an SQLite export file as INPUT");
+    }
+    if args.follow && args.compression == Compression::Gzip {
+        anyhow::bail!("--follow writes plain JSON; gzip output cannot be read mid-run");
+    }
+
+    if args.follow {
+        return follow_input(&args, &input, &output, options, cancellation, quiet);
+    }
+
+    if args.self_profile.is_some() {
+        self_profile::enable();
+    }
+
+    let mut sinks = MultiSinkWriter::new(&options.output_routes)?;
+    let serialize_threads = options.worker_threads();
+
+    // SQLite and the JSON readers need a seekable file, so stdin is spooled first
+    let stdin_dir = if input == STDIO_PATH {
+        Some(tempfile::Builder::new().prefix("nsys-chrome-").tempdir()?)
+    } else {
+        None
+    };
+    let input = match &stdin_dir {
+        Some(dir) => spool_stdin(dir.path())?,
+        None => input,
+    };
+
+    if args.expand_names {
+        let gzip = args.compression.gzip(to_stdout);
+        let renamed = if to_stdout {
+            expand_trace_file(Path::new(&input), std::io::stdout(), gzip)?
+        } else {
+            let file = create_output(&output, args.force)?;
+            let renamed = expand_trace_file(Path::new(&input), file.writer()?, gzip)?;
+            file.commit()?;
+            renamed
+        };
+        if !quiet {
+            status!("✓ Expanded {} kernel names: {}", renamed, output);
+        }
+        return Ok(());
+    }
+
+    let write_options = WriteOptions {
+        gzip: args.compression.gzip(to_stdout),
+        numeric_ids: args.numeric_ids,
+        outline: args.outline,
+        begin_end: args.begin_end,
+        serialize_threads,
+        seekable: args.seekable,
+    };
+    let pipeline = args.channel_capacity.map(|capacity| PipelineConfig {
+        capacity,
+        ..Default::default()
+    });
+
+    let mut convert_phase = phase("convert", "convert");
+    let mut events = match args.input_format.resolve(&input) {
+        _ if args.from_cache => {
+            if !quiet {
+                status!("Converting cached events to Chrome Trace format...");
+            }
+            let cached = read_event_cache(&input)?;
+            NsysChromeConverter::convert_extracted(cached, &options, Default::default())?.0
+        }
+        InputFormat::Rocprof => {
+            if !quiet {
+                status!("Converting rocprof output to Chrome Trace format...");
+            }
+            assemble_trace(RocprofReader::open(&input)?.read()?, &options)?
+        }
+        InputFormat::Unitrace => {
+            if !quiet {
+                status!("Converting unitrace output to Chrome Trace format...");
+            }
+            assemble_trace(UnitraceReader::open(&input)?.read()?, &options)?
+        }
+        InputFormat::NsysStats => {
+            if !quiet {
+                status!("Converting nsys stats reports to a summary trace...");
+            }
+            assemble_trace(NsysStatsReader::open(&input)?.read()?, &options)?
+        }
+        InputFormat::Nsys | InputFormat::Auto => {
+            let cache = args.cache_events.as_deref();
+            convert_nsys(
+                &input,
+                args.keep_sqlite,
+                cache,
+                quiet,
+                options.clone(),
+                Some(cancellation),
+            )?
+        }
+    };
+    // Rank captures stream through the writer one at a time unless a later
+    // pass needs all of them
+    let streamed = pipeline.filter(|_| !args.merge_ranks.is_empty() && !args.needs_whole_trace(&options));
+    if let Some(config) = streamed {
+        drop(convert_phase);
+        drop(stdin_dir);
+        let mut write_phase = phase("write output", "write");
+        write_phase.set_events(events.len());
+        let first = (rank_label(args.input.as_deref().unwrap_or(&input)), events);
+        let ranks = std::iter::once(Ok(first)).chain(args.merge_ranks.iter().map(|path| {
+            if !quiet {
+                status!("Converting rank capture {}...", path);
+            }
+            let events = convert_nsys(
+                path,
+                args.keep_sqlite,
+                None,
+                quiet,
+                options.clone(),
+                Some(cancellation),
+            )?;
+            Ok((rank_label(path), events))
+        }));
+        let written = if to_stdout {
+            stream_ranks(std::io::stdout(), ranks, write_options, config, quiet)?
+        } else {
+            let file = create_output(&output, args.force)?;
+            let written = stream_ranks(file.writer()?, ranks, write_options, config, quiet)?;
+            cancellation.check()?;
+            file.commit()?;
+            written
+        };
+        drop(write_phase);
+        return finish_conversion(&args, &output, written, sinks, None, quiet);
+    }
+    if !args.merge_ranks.is_empty() {
+        let mut traces = vec![(rank_label(args.input.as_deref().unwrap_or(&input)), events)];
+        for path in &args.merge_ranks {
+            if !quiet {
+                status!("Converting rank capture {}...", path);
+            }
+            let rank_events = convert_nsys(
+                path,
+                args.keep_sqlite,
+                None,
+                quiet,
+                options.clone(),
+                Some(cancellation),
+            )?;
+            traces.push((rank_label(path), rank_events));
+        }
+        events = NsysChromeConverter::sort_events(merge_rank_traces(traces));
+    }
+    convert_phase.set_events(events.len());
+    drop(convert_phase);
+    drop(stdin_dir);
+
+    if let Some(index) = args.session {
+        events = select_session(events, index, args.session_gap)?;
+    } else if !args.split_sessions && !quiet {
+        let sessions = detect_sessions(&events, args.session_gap).len();
+        if sessions > 1 {
+            status!(
+                "Note: input holds {} capture sessions; pick one with --session N \
+                 or write each with --split-sessions",
+                sessions
+            );
+        }
+    }
+
+    if let Some(path) = &args.folded_stacks {
+        write_folded_stacks(path, &events)?;
+        if !quiet {
+            status!("Folded API call stacks: {}", path);
+        }
+    }
+
+    if let Some(path) = &args.devices_json {
+        let devices = device_properties_from_events(&events);
+        write_devices_json(path, &devices)?;
+        if !quiet {
+            status!("Device properties ({} devices): {}", devices.len(), path);
+        }
+    }
+
+    if let Some(path) = &args.heatmap {
+        let heatmap = kernel_heatmap(&events, args.heatmap_bin)?;
+        heatmap.write(path)?;
+        if !quiet {
+            status!(
+                "Kernel heatmap ({} streams x {} bins): {}",
+                heatmap.rows.len(),
+                heatmap.bins,
+                path
+            );
+        }
+    }
+
+    if let Some(path) = &args.fusion_report {
+        let report = fusion_report(&events, args.fusion_max_kernel)?;
+        report.write(path)?;
+        if !quiet {
+            status!(
+                "Fusion report ({} regions, est. {:.3} ms savings): {}",
+                report.regions.len(),
+                report.total_savings_ns() as f64 / 1e6,
+                path
+            );
+        }
+    }
+
+    if let Some(path) = &args.step_stats {
+        let stats = step_stats(&events, &args.step_pattern);
+        stats.write(path)?;
+        stats.annotate(&mut events);
+        if !quiet {
+            status!(
+                "Step stats ({} steps, median {:.3} ms): {}",
+                stats.steps.len(),
+                stats.median_duration_ns() as f64 / 1e6,
+                path
+            );
+        }
+    }
+
+    if let Some(path) = &args.skew_report {
+        let report = skew_report(&events);
+        report.write(path)?;
+        let stragglers = straggler_events(&events, args.straggler_min_skew);
+        if !quiet {
+            status!(
+                "Skew report ({} collectives, {} stragglers): {}",
+                report.collectives.len(),
+                stragglers.len(),
+                path
+            );
+        }
+        if !stragglers.is_empty() {
+            events.extend(stragglers);
+            events = NsysChromeConverter::sort_events(events);
+        }
+    }
+
+    // Before names are compressed, which would hide the kernel classes
+    let metrics = args
+        .otlp_metrics
+        .as_ref()
+        .map(|_| conversion_metrics(&events));
+
+    if let Some(limit) = args.large_args {
+        let mut large_args = match &args.large_args_sidecar {
+            Some(_) => LargeArgs::with_sidecar(limit),
+            None => LargeArgs::truncating(limit),
+        };
+        let replaced = large_args.compact(&mut events);
+        if let Some(path) = &args.large_args_sidecar {
+            large_args.save(path)?;
+        }
+        if !quiet {
+            match &args.large_args_sidecar {
+                Some(path) => status!(
+                    "Moved {} large args ({} distinct) to {}",
+                    replaced,
+                    large_args.len(),
+                    path
+                ),
+                None => status!("Truncated {} large args", replaced),
+            }
+        }
+    }
+
+    if args.compress_names {
+        let mut dictionary = match &args.name_dictionary {
+            Some(path) if Path::new(path).exists() => NameDictionary::load(path)?,
+            _ => NameDictionary::new(),
+        };
+        let renamed = dictionary.compress(&mut events);
+        if let Some(path) = &args.name_dictionary {
+            dictionary.save(path)?;
+        }
+        if !quiet {
+            status!(
+                "Compressed {} kernel names into {} IDs",
+                renamed,
+                dictionary.len()
+            );
+        }
+    }
+
+    let events = sinks.route(events);
+    let events = if args.summary_trace {
+        summary_events(events)
+    } else {
+        events
+    };
+    let events = match args.compat {
+        Some(target) => {
+            let (events, stats) = apply_compat(events, target);
+            log::debug!("Compatibility ({}): {:?}", target.as_str(), stats);
+            events
+        }
+        None => events,
+    };
+
+    cancellation.check()?;
+    let mut write_phase = phase("write output", "write");
+    write_phase.set_events(events.len());
+    let written = if args.split_sessions {
+        for (idx, session_events) in split_sessions(events, args.session_gap)
+            .into_iter()
+            .enumerate()
+        {
+            let path = session_path(&output, idx + 1);
+            let file = create_output(&path, args.force)?;
+            write_output(
+                file.writer()?,
+                session_events,
+                write_options,
+                pipeline,
+                quiet,
+            )?;
+            cancellation.check()?;
+            file.commit()?;
+            if !quiet {
+                status!("Session {}: {}", idx + 1, path);
+            }
+        }
+        WriteOutput::default()
+    } else if to_stdout {
+        write_output(std::io::stdout(), events, write_options, pipeline, quiet)?
+    } else {
+        let file = create_output(&output, args.force)?;
+        let written = write_output(file.writer()?, events, write_options, pipeline, quiet)?;
+        cancellation.check()?;
+        file.commit()?;
+        written
+    };
+    drop(write_phase);
+    finish_conversion(&args, &output, written, sinks, metrics, quiet)
+}
+
+/// Write the sidecars of a finished conversion and report where everything went
+fn finish_conversion(
+    args: &ConvertArgs,
+    output: &str,
+    written: WriteOutput,
+    sinks: MultiSinkWriter,
+    metrics: Option<ConversionMetrics>,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    if let Some(track_ids) = written.track_ids {
+        let id_map = args.id_map.clone().unwrap_or_else(|| sidecar_path(output));
+        track_ids.write_sidecar(&id_map)?;
+        if !quiet {
+            status!("Track ID map: {}", id_map);
+        }
+    }
+    if let Some(outline) = written.outline {
+        let path = outline_path(output);
+        outline.write_sidecar(&path)?;
+        if !quiet {
+            status!("Trace outline: {}", path);
+        }
+    }
+    if let Some(seek_index) = written.seek_index {
+        let path = seek_index_path(output);
+        seek_index.write_sidecar(&path)?;
+        if !quiet {
+            status!("Seek index: {} ({} blocks)", path, seek_index.blocks.len());
+        }
+    }
+
+    for routed in sinks.finish()? {
+        if !quiet {
+            status!("Routed {} events: {}", routed.events, routed.path);
+        }
+    }
+
+    if let (Some(path), Some(profiler)) = (&args.self_profile, self_profile::global()) {
+        profiler.write(path)?;
+        if !quiet {
+            status!("Self profile ({} phases): {}", profiler.len(), path);
+        }
+    }
+
+    if let (Some(endpoint), Some(metrics)) = (&args.otlp_metrics, &metrics) {
+        metrics
+            .push(endpoint, &args.otlp_attributes)
+            .context("Failed to push OTLP metrics")?;
+        if !quiet {
+            status!(
+                "Pushed metrics for {} devices to http://{}:{}{}",
+                metrics.devices.len(),
+                endpoint.host,
+                endpoint.port,
+                endpoint.path
+            );
+        }
+    }
+
+    if !quiet {
+        status!("✓ Conversion complete: {}", output);
+    }
+
+    if args.serve_trace {
+        restore_interrupt();
+        let server = TraceServer::bind(output, SocketAddr::from(DEFAULT_TRACE_SERVER_ADDR))?;
+        status!("Open in Perfetto: {}", server.deep_link()?);
+        status!("Waiting for the trace to be loaded (Ctrl-C to stop)...");
+        server.serve_until_fetched()?;
+    }
+    Ok(())
+}
+
+/// Open an output file, refusing to replace an existing one without --force
+fn create_output(path: &str, force: bool) -> anyhow::Result<OutputFile> {
+    OutputFile::create(
+        path,
+        OutputFileOptions {
+            force,
+            atomic: true,
+        },
+    )
+    .map_err(|e| match e {
+        ConvertError::OutputExists(_) => anyhow::anyhow!("{} (pass --force to overwrite)", e),
+        e => e.into(),
+    })
+}
+
+/// Write events directly, or through a writer thread when a pipeline is configured
+///
+/// The events are already converted, so the channel offloads writing rather
+/// than bounding memory.
+fn write_output<W: Write + Send + 'static>(
+    output: W,
+    events: Vec<ChromeTraceEvent>,
+    write_options: WriteOptions,
+    pipeline: Option<PipelineConfig>,
+    quiet: bool,
+) -> anyhow::Result<WriteOutput> {
+    let Some(config) = pipeline else {
+        return Ok(ChromeTraceWriter::write_to(output, events, write_options)?);
+    };
+    let (written, metrics) = write_pipelined(output, events, write_options, config)?;
+    log::debug!("Write pipeline: {:?}", metrics);
+    if !quiet {
+        status!("Write pipeline: {}", metrics.summary(config.capacity));
+    }
+    Ok(written)
+}
+
+/// Merge rank captures as they are converted, writing each through the pipeline
+///
+/// `ranks` converts a capture when it is pulled, so only one rank's events are
+/// held at once. Events are sorted within each rank but not across ranks.
+fn stream_ranks<W: Write + Send + 'static>(
+    output: W,
+    ranks: impl Iterator<Item = anyhow::Result<(String, Vec<ChromeTraceEvent>)>>,
+    write_options: WriteOptions,
+    config: PipelineConfig,
+    quiet: bool,
+) -> anyhow::Result<WriteOutput> {
+    let (mut sender, writer) = spawn_writer(output, write_options, config);
+    let mut merger = RankMerger::default();
+    let mut sent = Ok(());
+    for rank in ranks {
+        sent = rank.and_then(|(label, events)| Ok(sender.send_all(merger.merge(&label, events))?));
+        if sent.is_err() {
+            break;
+        }
+    }
+    let sent = sent.and_then(|_| Ok(sender.flush()?));
+    drop(sender);
+    // A writer error explains a failed send better than the send itself
+    let (written, metrics) = match (sent, writer.finish()) {
+        (Err(e), Ok(_)) => return Err(e),
+        (_, Err(e)) => return Err(e.into()),
+        (Ok(()), Ok(result)) => result,
+    };
+    log::debug!("Write pipeline: {:?}", metrics);
+    if !quiet {
+        status!("Write pipeline: {}", metrics.summary(config.capacity));
+    }
+    Ok(written)
+}
+
+/// Copy stdin into `dir`, naming the file after its detected format
+///
+/// SQLite exports are recognized by their header and JSON by a leading `{` or
+/// `[`; anything else is taken as an .nsys-rep report.
+fn spool_stdin(dir: &Path) -> anyhow::Result<String> {
+    let mut stdin = std::io::stdin().lock();
+    let mut head = Vec::new();
+    (&mut stdin).take(4096).read_to_end(&mut head)?;
+
+    let first = head.iter().find(|b| !b.is_ascii_whitespace());
+    let extension = if head.starts_with(SQLITE_HEADER) {
+        "sqlite"
+    } else if matches!(first, Some(b'{') | Some(b'[')) {
+        "json"
+    } else {
+        "nsys-rep"
+    };
+
+    let path = dir.join(format!("stdin.{}", extension));
+    let mut file = File::create(&path)
+        .with_context(|| format!("Failed to spool stdin to {}", path.display()))?;
+    file.write_all(&head)?;
+    std::io::copy(&mut stdin, &mut file)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Append events converted from a growing SQLite export to OUTPUT until
+/// Ctrl-C or `--follow-idle`
+fn follow_input(
+    args: &ConvertArgs,
+    input: &str,
+    output: &str,
+    options: ConversionOptions,
+    cancellation: &CancellationToken,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let follow_options = FollowOptions {
+        interval: args.follow_interval,
+        idle_timeout: args.follow_idle,
+    };
+    let mut follower = TraceFollower::new(input, options).with_cancellation(cancellation.clone());
+    if !quiet {
+        status!("Following {} (Ctrl-C to stop)...", input);
+    }
+    let mut total = 0;
+    let progress = |appended: usize| {
+        total += appended;
+        if !quiet {
+            status!("Appended {} events ({} total): {}", appended, total, output);
+        }
+    };
+    let written = if output == STDIO_PATH {
+        follower.follow(
+            &mut JsonArraySink::new(std::io::stdout()),
+            &follow_options,
+            progress,
+        )?
+    } else {
+        let file = File::create(output)
+            .with_context(|| format!("Failed to create output file: {}", output))?;
+        follower.follow(&mut JsonArraySink::new(file), &follow_options, progress)?
+    };
+    if !quiet {
+        status!("✓ Followed {}: {} events in {}", input, written, output);
+    }
+    Ok(())
+}
+
+/// Label of a rank capture in a merged trace: its file name without extension
+fn rank_label(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map_or_else(|| path.to_string(), |s| s.to_string_lossy().into_owned())
+}
+
+/// Convert an nsys report or SQLite export, exporting .nsys-rep files first
+fn convert_nsys(
+    input: &str,
+    keep_sqlite: bool,
+    cache_events: Option<&str>,
+    quiet: bool,
+    options: ConversionOptions,
+    cancellation: Option<&CancellationToken>,
+) -> anyhow::Result<Vec<ChromeTraceEvent>> {
     // Determine if we need to convert .nsys-rep to SQLite first
-    let input_path = Path::new(&args.input);
+    let input_path = Path::new(input);
     let sqlite_path: String;
     let temp_sqlite: Option<tempfile::TempPath>;
 
-    if args.input.ends_with(".nsys-rep") {
+    if input.ends_with(".nsys-rep") {
         // Convert .nsys-rep to SQLite using nsys CLI
-        let sqlite_output = if args.keep_sqlite {
+        let sqlite_output = if keep_sqlite {
             input_path.with_extension("sqlite")
         } else {
             let temp_dir = tempfile::Builder::new()
@@ -66,7 +1947,11 @@ fn main() -> anyhow::Result<()> {
             temp_dir.path().to_path_buf()
         };
 
-        eprintln!("Converting .nsys-rep to SQLite...");
+        if !quiet {
+            status!("Converting .nsys-rep to SQLite...");
+        }
+        let _phase = phase("nsys export", "read");
+        // nsys reports progress on stdout, which may be carrying the trace
         let status = Command::new("nsys")
             .args([
                 "export",
@@ -76,15 +1961,16 @@ fn main() -> anyhow::Result<()> {
                 "true",
                 "-o",
                 sqlite_output.to_str().unwrap(),
-                &args.input,
+                input,
             ])
+            .stdout(Stdio::from(std::io::stderr()))
             .status()?;
 
         if !status.success() {
             anyhow::bail!("nsys export failed");
         }
 
-        if args.keep_sqlite {
+        if keep_sqlite {
             sqlite_path = sqlite_output.to_str().unwrap().to_string();
             temp_sqlite = None;
         } else {
@@ -97,26 +1983,44 @@ fn main() -> anyhow::Result<()> {
             temp_sqlite = Some(temp.into_temp_path());
         }
     } else {
-        sqlite_path = args.input.clone();
+        sqlite_path = input.to_string();
         temp_sqlite = None;
     }
 
-    // Build conversion options
-    let options = ConversionOptions {
-        activity_types: args.activity_types,
-        nvtx_event_prefix: args.nvtx_prefix,
-        nvtx_color_scheme: Default::default(),
-        include_metadata: args.include_metadata,
-    };
-
     // Convert to Chrome Trace
-    eprintln!("Converting to Chrome Trace format...");
-    convert_file_gz(&sqlite_path, &args.output, Some(options))?;
+    if !quiet {
+        status!("Converting to Chrome Trace format...");
+    }
+    let mut converter = NsysChromeConverter::new(&sqlite_path, Some(options.clone()))?;
+    if let Some(token) = cancellation {
+        converter = converter.with_cancellation(token.clone());
+    }
+    let (events, diagnostics) = match cache_events {
+        Some(cache_path) => {
+            // Link from the same extracted events a later --from-cache run would read
+            let (trace, diagnostics) = converter.extract()?;
+            write_event_cache(cache_path, &trace)?;
+            if !quiet {
+                status!("Event cache: {}", cache_path);
+            }
+            NsysChromeConverter::convert_extracted(trace, &options, diagnostics)?
+        }
+        None => converter.convert_with_diagnostics()?,
+    };
+    if !quiet {
+        if let Some(coverage) = diagnostics.nvtx_coverage {
+            status!("{}", coverage.summary());
+        }
+    }
+    for line in diagnostics.summary_lines() {
+        match log_format() {
+            LogFormat::Json => log::warn!(target: STATUS_TARGET, "{}", line),
+            LogFormat::Text => eprintln!("Warning: {}", line),
+        }
+    }
 
     // Clean up temp file if needed
     drop(temp_sqlite);
 
-    eprintln!("✓ Conversion complete: {}", args.output);
-    Ok(())
+    Ok(events)
 }
-
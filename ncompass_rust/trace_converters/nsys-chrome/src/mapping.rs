@@ -1,9 +1,9 @@
 //! Device and thread mapping utilities
 
-use anyhow::Result;
 use rusqlite::Connection;
 use std::collections::HashMap;
 
+use crate::error::Result;
 use crate::schema::table_exists;
 
 /// Extract PID and TID from globalTid
@@ -38,9 +38,11 @@ pub fn extract_device_mapping(conn: &Connection) -> Result<HashMap<i32, i32>> {
             if let Some(&existing_device) = pid_to_device.get(&pid) {
                 if existing_device != device_id {
                     // Warn but don't fail - some traces may have multiple devices per PID
-                    eprintln!(
-                        "Warning: PID {} mapped to multiple devices ({} and {})",
-                        pid, existing_device, device_id
+                    log::warn!(
+                        "PID {} mapped to multiple devices ({} and {})",
+                        pid,
+                        existing_device,
+                        device_id
                     );
                 }
             } else {
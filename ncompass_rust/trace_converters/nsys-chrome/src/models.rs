@@ -1,11 +1,19 @@
 //! Core data models for Chrome Trace events and conversion options
 
-use serde::Serialize;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
+use std::fmt;
+
+use crate::category::EventCategory;
+use crate::intern::InternedStr;
 
 /// All valid Chrome Trace event phases
 /// Based on Chrome Trace Format spec
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChromeTracePhase {
     // Duration Events
     #[serde(rename = "B")]
@@ -16,7 +24,7 @@ pub enum ChromeTracePhase {
     #[serde(rename = "X")]
     Complete,
     // Instant Events
-    #[serde(rename = "i")]
+    #[serde(rename = "i", alias = "I")]
     Instant,
     // Counter Events
     #[serde(rename = "C")]
@@ -67,7 +75,7 @@ pub enum ChromeTracePhase {
 }
 
 /// Binding point for flow events
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BindingPoint {
     #[serde(rename = "e")]
     Enclosing,
@@ -75,8 +83,19 @@ pub enum BindingPoint {
     Same,
 }
 
+/// Scope of an instant event: how far its marker line extends in the viewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstantScope {
+    #[serde(rename = "g")]
+    Global,
+    #[serde(rename = "p")]
+    Process,
+    #[serde(rename = "t")]
+    Thread,
+}
+
 /// Helper type for serializing values that can be string or int
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum StringOrInt {
     String(String),
@@ -101,24 +120,71 @@ impl From<i32> for StringOrInt {
     }
 }
 
+/// Deserialize a pid/tid written as a string or a number
+fn deserialize_track_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<InternedStr, D::Error> {
+    Ok(match StringOrInt::deserialize(deserializer)? {
+        StringOrInt::String(id) => InternedStr::new(&id),
+        StringOrInt::Int(id) => InternedStr::new(&id.to_string()),
+    })
+}
+
+/// Identifier of an event for linking, e.g. as the key of overlap maps
+///
+/// Assigned in sequence when events are extracted (see
+/// [`crate::frontends::FrontendTrace::assign_event_ids`]) and stored on the
+/// event, so it survives moving events between passes and means the same to
+/// every process reading the same extraction. Always 64 bits wide, whatever
+/// the target's pointer width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId(pub u64);
+
+impl EventId {
+    /// Identifier assigned to an event
+    ///
+    /// # Panics
+    ///
+    /// If the event was never numbered; linking such events would merge
+    /// unrelated ones under the same key. The linker's entry points number
+    /// events first, so this is reached only by calling an adapter directly.
+    pub fn of(event: &ChromeTraceEvent) -> Self {
+        event
+            .event_id
+            .expect("event linked before it was given an EventId")
+    }
+}
+
+/// Event args; the same few dozen names repeat on every event, so keys are interned
+pub type EventArgs = HashMap<InternedStr, serde_json::Value>;
+
+fn intern_args(args: HashMap<String, serde_json::Value>) -> EventArgs {
+    args.into_iter().map(|(key, value)| (key.into(), value)).collect()
+}
+
 /// Chrome Trace event model with validation
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChromeTraceEvent {
     /// Event name
+    #[serde(default)]
     pub name: String,
     /// Event phase
     pub ph: ChromeTracePhase,
     /// Timestamp in microseconds
+    #[serde(default)]
     pub ts: f64,
-    /// Process ID (e.g., "Device 0")
-    pub pid: String,
-    /// Thread ID (e.g., "Stream 1")
-    pub tid: String,
-    /// Category (e.g., "cuda", "nvtx", "osrt")
-    pub cat: String,
-    /// Optional metadata
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub args: HashMap<String, serde_json::Value>,
+    /// Process ID (e.g., "Device 0"), interned
+    #[serde(default, deserialize_with = "deserialize_track_id")]
+    pub pid: InternedStr,
+    /// Thread ID (e.g., "Stream 1"), interned
+    #[serde(default, deserialize_with = "deserialize_track_id")]
+    pub tid: InternedStr,
+    /// Category (e.g., "cuda", "nvtx", "osrt"), interned
+    #[serde(default)]
+    pub cat: InternedStr,
+    /// Optional metadata, keyed by interned arg names
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub args: EventArgs,
     /// Duration in microseconds (for 'X' events)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dur: Option<f64>,
@@ -131,6 +197,21 @@ pub struct ChromeTraceEvent {
     /// Binding point for flow events: 'e' (enclosing) or 's' (same)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bp: Option<BindingPoint>,
+    /// Flow ID bound to this slice, for flows written on the slices themselves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bind_id: Option<StringOrInt>,
+    /// Whether the flow with `bind_id` ends at this slice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_in: Option<bool>,
+    /// Whether the flow with `bind_id` starts at this slice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_out: Option<bool>,
+    /// Scope for instant ('i') events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub s: Option<InstantScope>,
+    /// Identifier for linking, assigned at extraction; not part of the trace
+    #[serde(skip)]
+    pub event_id: Option<EventId>,
 }
 
 impl ChromeTraceEvent {
@@ -139,22 +220,27 @@ impl ChromeTraceEvent {
         name: String,
         ph: ChromeTracePhase,
         ts: f64,
-        pid: String,
-        tid: String,
-        cat: String,
+        pid: impl Into<InternedStr>,
+        tid: impl Into<InternedStr>,
+        cat: impl Into<InternedStr>,
     ) -> Self {
         Self {
             name,
             ph,
             ts,
-            pid,
-            tid,
-            cat,
+            pid: pid.into(),
+            tid: tid.into(),
+            cat: cat.into(),
             args: HashMap::new(),
             dur: None,
             cname: None,
             id: None,
             bp: None,
+            bind_id: None,
+            flow_in: None,
+            flow_out: None,
+            s: None,
+            event_id: None,
         }
     }
 
@@ -163,88 +249,129 @@ impl ChromeTraceEvent {
         name: String,
         ts: f64,
         dur: f64,
-        pid: String,
-        tid: String,
-        cat: String,
+        pid: impl Into<InternedStr>,
+        tid: impl Into<InternedStr>,
+        cat: impl Into<InternedStr>,
     ) -> Self {
         Self {
             name,
             ph: ChromeTracePhase::Complete,
             ts,
-            pid,
-            tid,
-            cat,
+            pid: pid.into(),
+            tid: tid.into(),
+            cat: cat.into(),
             args: HashMap::new(),
             dur: Some(dur),
             cname: None,
             id: None,
             bp: None,
+            bind_id: None,
+            flow_in: None,
+            flow_out: None,
+            s: None,
+            event_id: None,
         }
     }
 
     /// Create a metadata event
-    pub fn metadata(name: String, pid: String, tid: String, args: HashMap<String, serde_json::Value>) -> Self {
+    pub fn metadata(
+        name: String,
+        pid: impl Into<InternedStr>,
+        tid: impl Into<InternedStr>,
+        args: HashMap<String, serde_json::Value>) -> Self {
         Self {
             name,
             ph: ChromeTracePhase::Metadata,
             ts: 0.0,
-            pid,
-            tid,
-            cat: "__metadata".to_string(),
-            args,
+            pid: pid.into(),
+            tid: tid.into(),
+            cat: InternedStr::new("__metadata"),
+            args: intern_args(args),
             dur: None,
             cname: None,
             id: None,
             bp: None,
+            bind_id: None,
+            flow_in: None,
+            flow_out: None,
+            s: None,
+            event_id: None,
         }
     }
 
     /// Create a flow start event
-    pub fn flow_start(ts: f64, pid: String, tid: String, id: StringOrInt) -> Self {
+    pub fn flow_start(ts: f64, pid: impl Into<InternedStr>, tid: impl Into<InternedStr>, id: StringOrInt) -> Self {
         Self {
             name: String::new(),
             ph: ChromeTracePhase::FlowStart,
             ts,
-            pid,
-            tid,
-            cat: "cuda_flow".to_string(),
+            pid: pid.into(),
+            tid: tid.into(),
+            cat: InternedStr::new("cuda_flow"),
             args: HashMap::new(),
             dur: None,
             cname: None,
             id: Some(id),
             bp: None,
+            bind_id: None,
+            flow_in: None,
+            flow_out: None,
+            s: None,
+            event_id: None,
         }
     }
 
     /// Create a flow finish event
-    pub fn flow_finish(ts: f64, pid: String, tid: String, id: StringOrInt, bp: BindingPoint) -> Self {
+    pub fn flow_finish(
+        ts: f64,
+        pid: impl Into<InternedStr>,
+        tid: impl Into<InternedStr>,
+        id: StringOrInt,
+        bp: BindingPoint,
+    ) -> Self {
         Self {
             name: String::new(),
             ph: ChromeTracePhase::FlowFinish,
             ts,
-            pid,
-            tid,
-            cat: "cuda_flow".to_string(),
+            pid: pid.into(),
+            tid: tid.into(),
+            cat: InternedStr::new("cuda_flow"),
             args: HashMap::new(),
             dur: None,
             cname: None,
             id: Some(id),
             bp: Some(bp),
+            bind_id: None,
+            flow_in: None,
+            flow_out: None,
+            s: None,
+            event_id: None,
         }
     }
 
+    /// Category of the event, parsed from `cat`
+    pub fn category(&self) -> EventCategory {
+        EventCategory::from(&*self.cat)
+    }
+
     /// Set event arguments
     pub fn with_args(mut self, args: HashMap<String, serde_json::Value>) -> Self {
-        self.args = args;
+        self.args = intern_args(args);
         self
     }
 
     /// Add a single argument
-    pub fn with_arg<K: Into<String>, V: Into<serde_json::Value>>(mut self, key: K, value: V) -> Self {
+    pub fn with_arg<K: Into<InternedStr>, V: Into<serde_json::Value>>(mut self, key: K, value: V) -> Self {
         self.args.insert(key.into(), value.into());
         self
     }
 
+    /// Set the scope of an instant event
+    pub fn with_scope(mut self, scope: InstantScope) -> Self {
+        self.s = Some(scope);
+        self
+    }
+
     /// Set color name
     pub fn with_color(mut self, cname: String) -> Self {
         self.cname = Some(cname);
@@ -252,33 +379,490 @@ impl ChromeTraceEvent {
     }
 }
 
+/// Args of a [`RawTraceEvent`], kept as JSON text until first accessed
+#[derive(Debug, Clone, Default)]
+enum LazyArgs {
+    #[default]
+    Absent,
+    Raw(Box<RawValue>),
+    Parsed(Map<String, Value>),
+}
+
+/// An event read back from a converted trace, for read-modify-write passes
+///
+/// Parsing every args map into JSON values and serializing it again dominates
+/// the time of passes that only look at names, categories or timestamps.
+/// The args are kept as raw JSON text, written back verbatim unless
+/// [`RawTraceEvent::args_mut`] parsed them; every other field is parsed.
+/// Read by `--expand-names` and `view` on converted traces.
+#[derive(Debug, Clone, Default)]
+pub struct RawTraceEvent {
+    fields: Map<String, Value>,
+    args: LazyArgs,
+}
+
+impl RawTraceEvent {
+    /// A field other than `args`
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.fields.get(key)
+    }
+
+    /// A string field other than `args`
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).and_then(|v| v.as_str())
+    }
+
+    /// Set a field other than `args`
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.fields.insert(key.to_string(), value);
+    }
+
+    /// Whether the args are still unparsed JSON text
+    pub fn args_are_raw(&self) -> bool {
+        matches!(self.args, LazyArgs::Raw(_))
+    }
+
+    /// Raw JSON text of the args, if they have not been parsed
+    pub fn raw_args(&self) -> Option<&str> {
+        match &self.args {
+            LazyArgs::Raw(raw) => Some(raw.get()),
+            _ => None,
+        }
+    }
+
+    /// Parse the args on first access, creating them if absent
+    pub fn args_mut(&mut self) -> serde_json::Result<&mut Map<String, Value>> {
+        let parsed = match std::mem::take(&mut self.args) {
+            LazyArgs::Absent => Map::new(),
+            LazyArgs::Raw(raw) => {
+                serde_json::from_str::<Option<Map<String, Value>>>(raw.get())?.unwrap_or_default()
+            }
+            LazyArgs::Parsed(args) => args,
+        };
+        self.args = LazyArgs::Parsed(parsed);
+        match &mut self.args {
+            LazyArgs::Parsed(args) => Ok(args),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Serialize for RawTraceEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for (key, value) in &self.fields {
+            map.serialize_entry(key, value)?;
+        }
+        match &self.args {
+            LazyArgs::Absent => {}
+            LazyArgs::Raw(raw) => map.serialize_entry("args", raw)?,
+            LazyArgs::Parsed(args) => map.serialize_entry("args", args)?,
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for RawTraceEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EventVisitor;
+
+        impl<'de> Visitor<'de> for EventVisitor {
+            type Value = RawTraceEvent;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a trace event object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<RawTraceEvent, A::Error> {
+                let mut event = RawTraceEvent::default();
+                while let Some(key) = access.next_key::<String>()? {
+                    if key == "args" {
+                        event.args = LazyArgs::Raw(access.next_value()?);
+                    } else {
+                        let value = access.next_value()?;
+                        event.fields.insert(key, value);
+                    }
+                }
+                Ok(event)
+            }
+        }
+
+        deserializer.deserialize_map(EventVisitor)
+    }
+}
+
+/// A converted trace read back with lazily parsed args
+///
+/// Keys next to `traceEvents` (`displayTimeUnit`, `metadata`, ...) are kept
+/// and written back after the events.
+#[derive(Debug, Clone, Default)]
+pub struct RawTrace {
+    pub events: Vec<RawTraceEvent>,
+    pub other: Map<String, Value>,
+}
+
+impl RawTrace {
+    /// Parse a `{"traceEvents": [...]}` object or a bare event array
+    pub fn from_slice(bytes: &[u8]) -> crate::error::Result<Self> {
+        let is_array = bytes
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|&b| b == b'[');
+        if is_array {
+            return Ok(Self {
+                events: serde_json::from_slice(bytes)?,
+                other: Map::new(),
+            });
+        }
+
+        let mut top: HashMap<String, Box<RawValue>> = serde_json::from_slice(bytes)?;
+        let Some(events) = top.remove("traceEvents") else {
+            return Err(crate::error::ConvertError::InvalidInput(
+                "Not a Chrome trace: missing 'traceEvents' array".to_string(),
+            ));
+        };
+        let mut other = Map::new();
+        for (key, raw) in top {
+            other.insert(key, serde_json::from_str(raw.get())?);
+        }
+        Ok(Self {
+            events: serde_json::from_str(events.get())?,
+            other,
+        })
+    }
+}
+
+impl Serialize for RawTrace {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("traceEvents", &self.events)?;
+        for (key, value) in &self.other {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Zero point of output timestamps
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TimeOrigin {
+    /// Keep the profiler's absolute timestamps
+    #[default]
+    Absolute,
+    /// Rebase to the earliest event of the capture
+    CaptureStart,
+    /// Rebase to the start of the first NVTX range with this name
+    NvtxRange(String),
+}
+
+/// Time range `[start_ns, end_ns)` a trace is restricted to (see [`crate::analysis::window`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start_ns: i64,
+    pub end_ns: i64,
+}
+
+impl TimeWindow {
+    /// Whether a timestamp falls inside the window
+    pub fn contains(&self, ts_ns: i64) -> bool {
+        ts_ns >= self.start_ns && ts_ns < self.end_ns
+    }
+}
+
+/// Offset applied to annotations before linking them to GPU work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeShift {
+    /// Move by this many nanoseconds (negative = earlier)
+    Fixed(i64),
+    /// Move by the offset under which the annotations cover the most kernel launches
+    Estimated,
+}
+
+/// Which overlapping NVTX ranges receive a CUDA API call (see [`crate::linker::attribution`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkPolicy {
+    /// Every overlapping range, nested or not
+    #[default]
+    All,
+    /// The shortest range overlapping the call, longest overlap on ties
+    Innermost,
+    /// The range overlapping the call for the longest time, shortest on ties
+    LongestOverlap,
+}
+
+/// How nested NVTX ranges are kept (see [`crate::parsers::nvtx::collapse_nested_ranges`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NvtxNesting {
+    /// Every range, however deep
+    #[default]
+    Keep,
+    /// Fold ranges nested in a range of the same name, as recursion creates,
+    /// into the outermost one
+    CollapseSameName,
+    /// Fold ranges nested this many levels deep (1 keeps only outermost
+    /// ranges) into their deepest remaining ancestor
+    MaxDepth(usize),
+}
+
+/// Where linked nvtx-kernel ranges are placed (see [`crate::linker::parse_nvtx_kernel_layout`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NvtxKernelLayout {
+    /// On the kernels' device, on an `NVTX Kernel Thread N` track per annotating thread
+    #[default]
+    Device,
+    /// On the range's own NVTX track, in place of the CPU range it replaces,
+    /// with the device in a `deviceId` arg
+    Annotation,
+}
+
+/// What happens to kernels recorded without a stream ID (see [`crate::analysis::missing_streams`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingStreamPolicy {
+    /// Put them on a synthetic `Unknown Stream` track
+    #[default]
+    Unknown,
+    /// Use the stream of the launching thread's neighbouring launches, falling
+    /// back to the `Unknown Stream` track
+    Infer,
+    /// Drop them, counting them in the diagnostics
+    Drop,
+}
+
+/// How virtual thread IDs order the tracks of a process (see [`crate::tid_allocator`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TidGrouping {
+    /// Tracks of one category together: all NVTX threads, then all CUDA API threads
+    #[default]
+    Category,
+    /// Tracks of one OS thread together: its NVTX, CUDA API and OS runtime tracks
+    Thread,
+}
+
+/// Slice the finish of a flow arrow attaches to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlowBind {
+    /// The slice enclosing the finish timestamp (`"bp": "e"`)
+    #[default]
+    Enclosing,
+    /// The next slice to begin on the track at or after the finish timestamp
+    Next,
+}
+
+/// How flow arrows between linked events are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlowStyle {
+    /// Separate `s`/`f` flow events sharing an `id`
+    #[default]
+    Events,
+    /// `bind_id` with `flow_out`/`flow_in` on the linked slices (flow v2)
+    Bound,
+}
+
+/// Kind of link a flow arrow shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowLink {
+    /// CUDA API call to the kernel it launched
+    Launch,
+    /// CUDA API call to the copy it issued
+    Copy,
+    /// MPI call to the NCCL kernel it ran
+    Mpi,
+}
+
+impl FlowLink {
+    /// All link kinds
+    pub const ALL: [FlowLink; 3] = [FlowLink::Launch, FlowLink::Copy, FlowLink::Mpi];
+}
+
+/// Representation of flow arrows, applied after linking (see [`crate::linker::flows`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlowOptions {
+    pub style: FlowStyle,
+    pub launch: FlowBind,
+    pub copy: FlowBind,
+    pub mpi: FlowBind,
+}
+
+impl FlowOptions {
+    /// Binding of one kind of link
+    pub fn bind(&self, link: FlowLink) -> FlowBind {
+        match link {
+            FlowLink::Launch => self.launch,
+            FlowLink::Copy => self.copy,
+            FlowLink::Mpi => self.mpi,
+        }
+    }
+
+    /// Set the binding of one kind of link
+    pub fn set_bind(&mut self, link: FlowLink, bind: FlowBind) {
+        match link {
+            FlowLink::Launch => self.launch = bind,
+            FlowLink::Copy => self.copy = bind,
+            FlowLink::Mpi => self.mpi = bind,
+        }
+    }
+}
+
+/// Extra output receiving the events of some categories
+///
+/// Categories are event `cat` values (`kernel`, `nvtx-kernel`, `cuda_api`, ...),
+/// `counters` for counter events or `*` for everything. The format follows the
+/// path: `.json`, `.json.gz`, or `.csv` for per-name duration statistics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputRoute {
+    pub categories: Vec<String>,
+    pub path: String,
+}
+
 /// Configuration options for conversion
 #[derive(Debug, Clone)]
 pub struct ConversionOptions {
     /// Event types to include
-    pub activity_types: Vec<String>,
-    /// Filter NVTX events by name prefix
+    pub activity_types: Vec<EventCategory>,
+    /// Keep only NVTX events matching a name prefix, glob or `re:` regex
+    /// (see [`crate::parsers::nvtx::NvtxNameFilter`])
     pub nvtx_event_prefix: Option<Vec<String>>,
     /// Color mapping for NVTX events (regex -> color name)
     pub nvtx_color_scheme: HashMap<String, String>,
+    /// Keep only NVTX ranges from these domains (names or IDs; "default" for the default domain)
+    pub nvtx_domains: Option<Vec<String>>,
+    /// Prefix NVTX range names with their domain ("NCCL: AllReduce")
+    pub nvtx_domain_prefix: bool,
+    /// Put each non-default NVTX domain on its own thread track
+    pub nvtx_domain_tracks: bool,
+    /// Fold recursive or deeply nested NVTX ranges into an enclosing range,
+    /// counting them in its `collapsed_ranges` arg
+    pub nvtx_nesting: NvtxNesting,
     /// Include process/thread name metadata events
     pub include_metadata: bool,
+    /// Number of launch backtrace frames attached to kernel events (0 disables)
+    pub source_frame_depth: usize,
+    /// Attach the full CPU call stack to CUDA API events (`call_stack` arg)
+    pub api_call_stacks: bool,
+    /// Synthesize `step N` ranges from iteration periodicity when annotations are missing
+    pub synthesize_steps: bool,
+    /// Group kernels into heuristic layer ranges (GEMM+bias+activation, ...) when annotations are missing
+    pub infer_layers: bool,
+    /// Emit a per-device counter of concurrently running kernels and a summary
+    /// of time at concurrency 0/1/2+
+    pub kernel_concurrency: bool,
+    /// Emit a per-device counter of memcpy throughput per direction (H2D, D2H,
+    /// D2D) and a summary of each direction's copies and bytes
+    pub transfer_throughput: bool,
+    /// Attach FLOP/byte estimates from the default kernel cost model
+    pub estimate_kernel_costs: bool,
+    /// Drop kernels shorter than this many nanoseconds, keeping linked ranges intact (0 disables)
+    pub min_kernel_duration_ns: i64,
+    /// Color CUDA API calls by thread state (sync = waiting, memcpy = I/O, launch = running)
+    pub api_thread_states: bool,
+    /// Mark kernels running more than this many times their name's median
+    /// duration as outliers (see [`crate::analysis::outliers`]; None disables)
+    pub kernel_outlier_factor: Option<f64>,
+    /// Rebase all timestamps to this origin, recording the original epoch in metadata
+    pub time_origin: TimeOrigin,
+    /// Keep only this window, as offsets from the capture start, clamping
+    /// ranges that straddle its boundaries (None keeps the whole capture)
+    pub time_window: Option<TimeWindow>,
+    /// Send some categories to extra outputs instead of the main one (see [`crate::routing`])
+    pub output_routes: Vec<OutputRoute>,
+    /// Worker threads for reading event tables and linking devices concurrently
+    /// (1 = sequential, 0 = one per CPU core); output is the same for any value
+    pub jobs: usize,
+    /// Record the source table and SQLite rowid of every parsed event in its args
+    pub source_rows: bool,
+    /// Shift annotations before NVTX-kernel linking, keyed by NVTX domain or
+    /// event category (see [`crate::linker::time_shift`])
+    pub annotation_time_shifts: HashMap<String, TimeShift>,
+    /// Which overlapping NVTX ranges a CUDA API call is linked to
+    pub link_policy: LinkPolicy,
+    /// Leave kernels starting more than this many nanoseconds after their CUDA API
+    /// call ended unlinked, guarding against stale correlation IDs (None disables)
+    pub max_link_gap_ns: Option<i64>,
+    /// Link NVTX ranges to kernels in windows of CUDA API calls this many
+    /// nanoseconds long, spilling to a temp file to bound memory (None links in memory)
+    pub link_window_ns: Option<i64>,
+    /// Attribute CUDA API calls outside every NVTX range to the last range that
+    /// ended on the same thread at most this many nanoseconds before them,
+    /// tagging the range `attribution: "sticky"` (None disables)
+    pub sticky_lookback_ns: Option<i64>,
+    /// Emit one nvtx-kernel range per stream the range's kernels ran on instead
+    /// of one spanning all of them, each on its own `Stream N` track
+    pub nvtx_kernel_per_stream: bool,
+    /// Process and track nvtx-kernel ranges are placed on
+    pub nvtx_kernel_layout: NvtxKernelLayout,
+    /// Tag NVTX ranges that received no kernels during linking with `unattributed: true`
+    pub tag_unattributed: bool,
+    /// Emit `Unattributed GPU work` slices covering each device's kernel time
+    /// outside every nvtx-kernel range
+    pub unattributed_work: bool,
+    /// What happens to kernels recorded without a stream ID
+    pub missing_stream_policy: MissingStreamPolicy,
+    /// Replace tid strings with numbers from 1 per process, ordered by this
+    /// grouping, with the names moved to metadata (None keeps the strings)
+    pub virtual_tids: Option<TidGrouping>,
+    /// Read and write only NVTX ranges and marks, skipping kernel and API
+    /// tables, to audit instrumentation quickly (see [`crate::annotations`])
+    pub annotations_only: bool,
+    /// How flow arrows are written and which slices they attach to
+    pub flows: FlowOptions,
+}
+
+impl ConversionOptions {
+    /// Number of worker threads `jobs` asks for, resolving 0 to the core count
+    pub fn worker_threads(&self) -> usize {
+        match self.jobs {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            jobs => jobs,
+        }
+    }
 }
 
 impl Default for ConversionOptions {
     fn default() -> Self {
         Self {
             activity_types: vec![
-                "kernel".to_string(),
-                "nvtx".to_string(),
-                "nvtx-kernel".to_string(),
-                "cuda-api".to_string(),
-                "osrt".to_string(),
-                "sched".to_string(),
+                EventCategory::Kernel,
+                EventCategory::Nvtx,
+                EventCategory::NvtxKernel,
+                EventCategory::CudaApi,
+                EventCategory::Osrt,
+                EventCategory::Sched,
             ],
             nvtx_event_prefix: None,
             nvtx_color_scheme: HashMap::new(),
+            nvtx_domains: None,
+            nvtx_domain_prefix: false,
+            nvtx_domain_tracks: false,
+            nvtx_nesting: NvtxNesting::Keep,
             include_metadata: true,
+            source_frame_depth: 3,
+            api_call_stacks: false,
+            synthesize_steps: false,
+            infer_layers: false,
+            kernel_concurrency: false,
+            transfer_throughput: false,
+            estimate_kernel_costs: false,
+            min_kernel_duration_ns: 0,
+            api_thread_states: false,
+            kernel_outlier_factor: None,
+            time_origin: TimeOrigin::Absolute,
+            time_window: None,
+            output_routes: Vec::new(),
+            jobs: 1,
+            source_rows: false,
+            annotation_time_shifts: HashMap::new(),
+            link_policy: LinkPolicy::All,
+            max_link_gap_ns: None,
+            nvtx_kernel_per_stream: false,
+            nvtx_kernel_layout: NvtxKernelLayout::Device,
+            tag_unattributed: false,
+            unattributed_work: false,
+            link_window_ns: None,
+            sticky_lookback_ns: None,
+            missing_stream_policy: MissingStreamPolicy::Unknown,
+            virtual_tids: None,
+            annotations_only: false,
+            flows: FlowOptions::default(),
         }
     }
 }
@@ -0,0 +1,248 @@
+//! Short IDs for long, repeated kernel names
+//!
+//! Templated kernel names often run to hundreds of characters and repeat for
+//! every launch, so they dominate the size of a trace. Compression replaces
+//! each kernel's name with a short ID (`k0`, `k1`, ...) and adds a single
+//! `kernel_name_dictionary` metadata event whose args map the IDs back to the
+//! full names. Viewers ignore unknown metadata events, so a compressed trace
+//! still loads; [`expand_names`] restores the original names.
+//!
+//! A dictionary can be saved and loaded again, so traces converted with the
+//! same dictionary (e.g. one per rank) share their IDs.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::browser::read_raw_trace;
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, RawTrace, RawTraceEvent};
+
+/// Name of the metadata event holding the dictionary
+pub const NAME_DICTIONARY_EVENT: &str = "kernel_name_dictionary";
+
+/// Prefix of short kernel names
+const SHORT_NAME_PREFIX: &str = "k";
+
+/// Assigns short IDs to kernel names in order of first appearance
+#[derive(Debug, Clone, Default)]
+pub struct NameDictionary {
+    ids: HashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl NameDictionary {
+    /// Create an empty dictionary
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a dictionary saved by [`NameDictionary::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| ConvertError::open_input(path, e))?;
+        let root: Value = serde_json::from_reader(BufReader::new(file))?;
+        let names = root
+            .get("names")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| {
+                ConvertError::InvalidInput(format!(
+                    "Invalid name dictionary {}: missing 'names' array",
+                    path.display()
+                ))
+            })?;
+
+        let mut dictionary = Self::new();
+        for name in names {
+            let name = name.as_str().ok_or_else(|| {
+                ConvertError::InvalidInput(format!(
+                    "Invalid name dictionary {}: names must be strings",
+                    path.display()
+                ))
+            })?;
+            dictionary.id(name);
+        }
+        Ok(dictionary)
+    }
+
+    /// Save the dictionary as `{"names": [...]}`, indexed by ID
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        serde_json::to_writer(BufWriter::new(file), &json!({ "names": self.names }))
+            .map_err(|e| ConvertError::Output(e.into()))?;
+        Ok(())
+    }
+
+    /// Get or assign the ID of a name
+    pub fn id(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.ids.insert(name.to_string(), id);
+        self.names.push(name.to_string());
+        id
+    }
+
+    /// Full name for an ID
+    pub fn name(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Replace kernel names with short IDs and add the dictionary event
+    ///
+    /// The dictionary event only lists the names used by these events. Returns
+    /// the number of renamed events.
+    pub fn compress(&mut self, events: &mut Vec<ChromeTraceEvent>) -> usize {
+        let mut used = BTreeMap::new();
+        let mut renamed = 0;
+        let mut dictionary_pid = None;
+        for event in events.iter_mut() {
+            if event.cat != EventCategory::Kernel || event.ph == ChromeTracePhase::Metadata {
+                continue;
+            }
+            let id = self.id(&event.name);
+            let short = short_name(id);
+            used.entry(id)
+                .or_insert_with(|| std::mem::take(&mut event.name));
+            event.name = short;
+            dictionary_pid.get_or_insert_with(|| event.pid.clone());
+            renamed += 1;
+        }
+
+        if let Some(pid) = dictionary_pid {
+            let names: Map<String, Value> = used
+                .into_iter()
+                .map(|(id, name)| (short_name(id), Value::String(name)))
+                .collect();
+            let mut args = HashMap::new();
+            args.insert("names".to_string(), Value::Object(names));
+            // Metadata leads the trace, keeping sorted events sorted
+            events.insert(
+                0,
+                ChromeTraceEvent::metadata(
+                    NAME_DICTIONARY_EVENT.to_string(),
+                    pid,
+                    String::new(),
+                    args,
+                ),
+            );
+        }
+        renamed
+    }
+}
+
+/// Short name for an ID
+fn short_name(id: usize) -> String {
+    format!("{}{}", SHORT_NAME_PREFIX, id)
+}
+
+/// Restore kernel names from the dictionary event of a parsed trace, removing it
+///
+/// Returns the number of renamed events; a trace without a dictionary is left
+/// unchanged.
+pub fn expand_names(root: &mut Value) -> Result<usize> {
+    let Some(events) = root.get_mut("traceEvents").and_then(|v| v.as_array_mut()) else {
+        return Err(ConvertError::InvalidInput(
+            "Not a Chrome trace: missing 'traceEvents' array".to_string(),
+        ));
+    };
+
+    let is_dictionary = |e: &Value| {
+        e.get("ph").and_then(|v| v.as_str()) == Some("M")
+            && e.get("name").and_then(|v| v.as_str()) == Some(NAME_DICTIONARY_EVENT)
+    };
+    let mut names = Map::new();
+    events.retain_mut(|e| {
+        if !is_dictionary(e) {
+            return true;
+        }
+        if let Some(Value::Object(entries)) = e.pointer_mut("/args/names").map(Value::take) {
+            names.extend(entries);
+        }
+        false
+    });
+
+    let mut renamed = 0;
+    for event in events.iter_mut() {
+        if event.get("cat").and_then(|v| v.as_str()) != Some("kernel") {
+            continue;
+        }
+        let Some(name) = event.get_mut("name") else {
+            continue;
+        };
+        if let Some(full) = name.as_str().and_then(|short| names.get(short)) {
+            *name = full.clone();
+            renamed += 1;
+        }
+    }
+    Ok(renamed)
+}
+
+/// Restore kernel names in a trace read with lazily parsed args
+///
+/// As [`expand_names`], but only the dictionary event's args are parsed.
+pub fn expand_raw_names(trace: &mut RawTrace) -> Result<usize> {
+    let is_dictionary = |e: &RawTraceEvent| {
+        e.get_str("ph") == Some("M") && e.get_str("name") == Some(NAME_DICTIONARY_EVENT)
+    };
+    let mut names = Map::new();
+    for event in trace.events.iter_mut().filter(|e| is_dictionary(e)) {
+        if let Some(Value::Object(entries)) = event.args_mut()?.remove("names") {
+            names.extend(entries);
+        }
+    }
+    trace.events.retain(|e| !is_dictionary(e));
+
+    let mut renamed = 0;
+    for event in trace.events.iter_mut() {
+        if event.get_str("cat") != Some("kernel") {
+            continue;
+        }
+        if let Some(full) = event.get_str("name").and_then(|short| names.get(short)) {
+            let full = full.clone();
+            event.set("name", full);
+            renamed += 1;
+        }
+    }
+    Ok(renamed)
+}
+
+/// Expand a compressed trace file into `output`, optionally gzip-compressed
+///
+/// Args other than the dictionary's are copied through without parsing.
+pub fn expand_trace_file(input: &Path, output: impl Write, gzip: bool) -> Result<usize> {
+    let mut root = read_raw_trace(input)?;
+    let renamed = expand_raw_names(&mut root)?;
+
+    let write = |writer: &mut dyn Write| -> std::io::Result<()> {
+        serde_json::to_writer(&mut *writer, &root)?;
+        writer.flush()
+    };
+    let mut output = BufWriter::new(output);
+    if gzip {
+        let mut encoder = GzEncoder::new(&mut output, Compression::default());
+        write(&mut encoder).map_err(ConvertError::Output)?;
+        encoder.finish().map_err(ConvertError::Output)?;
+    } else {
+        write(&mut output).map_err(ConvertError::Output)?;
+    }
+    output.flush().map_err(ConvertError::Output)?;
+    Ok(renamed)
+}
@@ -0,0 +1,324 @@
+//! Summary metrics of a conversion, pushed to an OTLP metrics endpoint
+//!
+//! After a conversion the CLI can compute a handful of headline numbers
+//! (GPU time per device and per kernel class, device utilization, p99 launch
+//! latency) and post them as OTLP/HTTP JSON to a collector, so every CI
+//! profiling run lands on a dashboard without anyone opening the trace.
+//! Only plain `http://` endpoints are supported; point TLS setups at a local
+//! collector.
+
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::analysis::gaps::device_activity;
+use crate::analysis::layers::KernelRole;
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::linker::adapters::{EventAdapter, NsysEventAdapter};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Instrumentation scope name reported with every metric
+pub const OTLP_SCOPE: &str = "nsys-chrome";
+
+/// Default port of OTLP over HTTP
+pub const DEFAULT_OTLP_PORT: u16 = 4318;
+
+/// Path metrics are posted to when the endpoint has none
+pub const DEFAULT_OTLP_METRICS_PATH: &str = "/v1/metrics";
+
+/// Timeout for connecting to and talking with the collector
+const OTLP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Kernel time and utilization of one device
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceMetrics {
+    pub device_id: i64,
+    /// Sum of kernel durations; overlapping kernels count twice
+    pub gpu_time_ns: i64,
+    /// Share of the device's kernel span with a kernel running
+    pub utilization_pct: f64,
+}
+
+/// Headline numbers of a converted trace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionMetrics {
+    pub devices: Vec<DeviceMetrics>,
+    /// Kernel time per device and kernel class ("gemm", "attention", ...)
+    pub kernel_class_ns: BTreeMap<(i64, &'static str), i64>,
+    /// 99th percentile of kernel start minus launching API call start
+    pub launch_latency_p99_ns: Option<i64>,
+    /// Kernels matched to their launching API call
+    pub launches: usize,
+}
+
+fn is_kernel(event: &ChromeTraceEvent) -> bool {
+    event.ph == ChromeTracePhase::Complete && event.cat == EventCategory::Kernel
+}
+
+/// Nearest-rank percentile of `values`, which it sorts
+fn percentile(values: &mut [i64], pct: f64) -> Option<i64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let rank = ((pct / 100.0) * values.len() as f64).ceil() as usize;
+    Some(values[rank.clamp(1, values.len()) - 1])
+}
+
+/// Compute summary metrics from converted events
+///
+/// Launch latency pairs each kernel with the CUDA API call sharing its
+/// process and `correlationId`.
+pub fn conversion_metrics(events: &[ChromeTraceEvent]) -> ConversionMetrics {
+    let adapter = NsysEventAdapter;
+    let kernels: Vec<ChromeTraceEvent> = events.iter().filter(|e| is_kernel(e)).cloned().collect();
+
+    let mut gpu_time: BTreeMap<i64, i64> = BTreeMap::new();
+    let mut kernel_class_ns = BTreeMap::new();
+    for kernel in &kernels {
+        let (Some(device_id), Some((start, end))) = (
+            kernel.args.get("deviceId").and_then(|v| v.as_i64()),
+            adapter.get_time_range_ns(kernel),
+        ) else {
+            continue;
+        };
+        *gpu_time.entry(device_id).or_default() += end - start;
+        let class = KernelRole::classify(&kernel.name).as_str();
+        *kernel_class_ns.entry((device_id, class)).or_default() += end - start;
+    }
+
+    let utilization: HashMap<i64, f64> = device_activity(&kernels)
+        .iter()
+        .map(|activity| (activity.device_id, activity.utilization_pct()))
+        .collect();
+    let devices = gpu_time
+        .into_iter()
+        .map(|(device_id, gpu_time_ns)| DeviceMetrics {
+            device_id,
+            gpu_time_ns,
+            utilization_pct: utilization.get(&device_id).copied().unwrap_or(0.0),
+        })
+        .collect();
+
+    let launch_starts: HashMap<(&str, i64), i64> = events
+        .iter()
+        .filter(|e| e.cat == EventCategory::CudaApi)
+        .filter_map(|api| {
+            let correlation_id = adapter.get_correlation_id(api)?;
+            let (start, _) = adapter.get_time_range_ns(api)?;
+            Some(((api.pid.as_ref(), correlation_id), start))
+        })
+        .collect();
+    let mut latencies: Vec<i64> = kernels
+        .iter()
+        .filter_map(|kernel| {
+            let correlation_id = adapter.get_correlation_id(kernel)?;
+            let launched = launch_starts.get(&(kernel.pid.as_ref(), correlation_id))?;
+            let (start, _) = adapter.get_time_range_ns(kernel)?;
+            Some(start - launched)
+        })
+        .collect();
+
+    ConversionMetrics {
+        devices,
+        kernel_class_ns,
+        launches: latencies.len(),
+        launch_latency_p99_ns: percentile(&mut latencies, 99.0),
+    }
+}
+
+fn attribute(key: &str, value: impl Into<Value>) -> Value {
+    match value.into() {
+        Value::Number(n) if n.is_i64() => {
+            json!({ "key": key, "value": { "intValue": n.to_string() } })
+        }
+        Value::Number(n) => json!({ "key": key, "value": { "doubleValue": n } }),
+        other => {
+            json!({ "key": key, "value": { "stringValue": other.as_str().unwrap_or_default() } })
+        }
+    }
+}
+
+fn gauge(name: &str, unit: &str, description: &str, points: Vec<Value>) -> Value {
+    json!({
+        "name": name,
+        "unit": unit,
+        "description": description,
+        "gauge": { "dataPoints": points },
+    })
+}
+
+fn int_point(attributes: Vec<Value>, time: &str, value: i64) -> Value {
+    // int64 fields are strings in the protobuf JSON mapping
+    json!({ "attributes": attributes, "timeUnixNano": time, "asInt": value.to_string() })
+}
+
+fn double_point(attributes: Vec<Value>, time: &str, value: f64) -> Value {
+    json!({ "attributes": attributes, "timeUnixNano": time, "asDouble": value })
+}
+
+impl ConversionMetrics {
+    /// OTLP/HTTP JSON export request, stamped with `time_unix_nano`
+    ///
+    /// `resource` holds extra resource attributes (commit, job, input name);
+    /// `service.name` defaults to the scope name.
+    pub fn to_otlp_json(&self, resource: &[(String, String)], time_unix_nano: u64) -> Value {
+        let time = time_unix_nano.to_string();
+        let mut resource_attributes = Vec::new();
+        if !resource.iter().any(|(key, _)| key == "service.name") {
+            resource_attributes.push(attribute("service.name", OTLP_SCOPE));
+        }
+        resource_attributes.extend(
+            resource
+                .iter()
+                .map(|(key, value)| attribute(key, value.as_str())),
+        );
+
+        let device = |id: i64| vec![attribute("device.id", id)];
+        let mut metrics = vec![
+            gauge(
+                "gpu.kernel.time",
+                "ns",
+                "Total kernel time per device",
+                self.devices
+                    .iter()
+                    .map(|d| int_point(device(d.device_id), &time, d.gpu_time_ns))
+                    .collect(),
+            ),
+            gauge(
+                "gpu.kernel_class.time",
+                "ns",
+                "Kernel time per device and kernel class",
+                self.kernel_class_ns
+                    .iter()
+                    .map(|(&(device_id, class), &ns)| {
+                        let mut attributes = device(device_id);
+                        attributes.push(attribute("kernel.class", class));
+                        int_point(attributes, &time, ns)
+                    })
+                    .collect(),
+            ),
+            gauge(
+                "gpu.utilization",
+                "%",
+                "Share of the device's kernel span with a kernel running",
+                self.devices
+                    .iter()
+                    .map(|d| double_point(device(d.device_id), &time, d.utilization_pct))
+                    .collect(),
+            ),
+        ];
+        if let Some(p99) = self.launch_latency_p99_ns {
+            metrics.push(gauge(
+                "cuda.launch_latency.p99",
+                "ns",
+                "99th percentile of kernel start minus launch API call start",
+                vec![int_point(Vec::new(), &time, p99)],
+            ));
+        }
+
+        json!({
+            "resourceMetrics": [{
+                "resource": { "attributes": resource_attributes },
+                "scopeMetrics": [{
+                    "scope": { "name": OTLP_SCOPE, "version": env!("CARGO_PKG_VERSION") },
+                    "metrics": metrics,
+                }],
+            }],
+        })
+    }
+}
+
+/// Host, port and request path of an OTLP/HTTP endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtlpEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+/// Parse `http://host[:port][/path]`; the path defaults to `/v1/metrics`
+pub fn parse_otlp_endpoint(value: &str) -> Result<OtlpEndpoint> {
+    let invalid = |reason: &str| {
+        ConvertError::InvalidOption(format!("Invalid OTLP endpoint '{}': {}", value, reason))
+    };
+    let rest = match value.trim().split_once("://") {
+        Some(("http", rest)) => rest,
+        Some((scheme, _)) => {
+            return Err(invalid(&format!(
+                "{}:// is not supported (use http://, e.g. a local collector)",
+                scheme
+            )))
+        }
+        None => return Err(invalid("expected http://host[:port][/path]")),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, ""),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().map_err(|_| invalid("bad port"))?),
+        None => (authority, DEFAULT_OTLP_PORT),
+    };
+    if host.is_empty() {
+        return Err(invalid("missing host"));
+    }
+    let path = match path.trim_end_matches('/') {
+        "" => DEFAULT_OTLP_METRICS_PATH.to_string(),
+        path => path.to_string(),
+    };
+    Ok(OtlpEndpoint {
+        host: host.to_string(),
+        port,
+        path,
+    })
+}
+
+/// Post an export request to the endpoint, failing on a non-2xx response
+pub fn push_otlp_metrics(endpoint: &OtlpEndpoint, request: &Value) -> Result<()> {
+    let body = serde_json::to_vec(request)?;
+    let addr = (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| {
+            ConvertError::InvalidOption(format!("Cannot resolve OTLP host '{}'", endpoint.host))
+        })?;
+    let mut stream = TcpStream::connect_timeout(&addr, OTLP_TIMEOUT)?;
+    stream.set_read_timeout(Some(OTLP_TIMEOUT))?;
+    stream.set_write_timeout(Some(OTLP_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        endpoint.path,
+        endpoint.host,
+        endpoint.port,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(ConvertError::Io(std::io::Error::other(format!(
+            "OTLP endpoint rejected metrics: {}",
+            status_line.trim()
+        ))));
+    }
+    Ok(())
+}
+
+impl ConversionMetrics {
+    /// Push the metrics to `endpoint`, stamped with the current time
+    pub fn push(&self, endpoint: &OtlpEndpoint, resource: &[(String, String)]) -> Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        push_otlp_metrics(endpoint, &self.to_otlp_json(resource, now))
+    }
+}
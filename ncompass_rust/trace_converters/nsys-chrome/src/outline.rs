@@ -0,0 +1,173 @@
+//! Trace outline: an index of NVTX ranges and steps into the written trace
+//!
+//! The writer emits one event per line in timestamp order, so every NVTX range,
+//! nvtx-kernel range and step marker can be located by byte offset, and the
+//! events inside its time range form one contiguous slice of the file. The
+//! outline records, for each of them:
+//! - `name`, `device`, `start_ns`, `end_ns`,
+//! - `offset` / `length`: where the range event itself is serialized,
+//! - `slice_start` / `slice_end`: the byte range holding every event with a
+//!   timestamp inside the range.
+//!
+//! Offsets count bytes of the uncompressed JSON text, also for `.json.gz`
+//! output. A viewer can decompress up to `slice_end` and parse only the slice.
+
+use serde::Serialize;
+use serde_json::json;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Outline format version, bumped on incompatible changes
+pub const OUTLINE_VERSION: u32 = 1;
+
+/// One indexed range in the outline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutlineEntry {
+    /// Event category: `nvtx` or `nvtx-kernel`
+    pub cat: String,
+    pub name: String,
+    pub device: Option<i64>,
+    pub start_ns: i64,
+    pub end_ns: i64,
+    /// Byte offset of the range event in the uncompressed trace
+    pub offset: u64,
+    /// Serialized length of the range event in bytes
+    pub length: u64,
+    /// Byte offset of the first event at or after `start_ns`
+    pub slice_start: u64,
+    /// Byte offset just past the last event at or before `end_ns`
+    pub slice_end: u64,
+}
+
+/// Builds the outline while the writer streams events out
+///
+/// Events must be recorded in the order they are written, which is sorted by
+/// timestamp.
+#[derive(Debug, Default)]
+pub struct TraceOutline {
+    ranges: Vec<OutlineEntry>,
+    steps: Vec<OutlineEntry>,
+    /// Entries whose slice is still open, by end time: (end_ns, is_step, index)
+    open: BinaryHeap<Reverse<(i64, bool, usize)>>,
+    /// Timestamp of the last recorded event and where events with it begin
+    current_ts_ns: Option<i64>,
+    current_ts_offset: u64,
+    /// Offset just past the last recorded event
+    last_end: u64,
+}
+
+fn us_to_ns(us: f64) -> i64 {
+    (us * 1000.0).round() as i64
+}
+
+impl TraceOutline {
+    /// Create an empty outline
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an event written at `offset` with `length` bytes
+    pub fn record(&mut self, event: &ChromeTraceEvent, offset: u64, length: u64) {
+        let ts_ns = us_to_ns(event.ts);
+        if self.current_ts_ns != Some(ts_ns) {
+            self.current_ts_ns = Some(ts_ns);
+            self.current_ts_offset = offset;
+        }
+        self.close_before(ts_ns);
+        self.last_end = offset + length;
+
+        let is_range = event.ph == ChromeTracePhase::Complete
+            && (event.cat == EventCategory::Nvtx || event.cat == EventCategory::NvtxKernel);
+        if !is_range {
+            return;
+        }
+        let end_ns = ts_ns + us_to_ns(event.dur.unwrap_or(0.0));
+        let entry = OutlineEntry {
+            cat: event.cat.to_string(),
+            name: event.name.clone(),
+            device: event.args.get("deviceId").and_then(|v| v.as_i64()),
+            start_ns: ts_ns,
+            end_ns,
+            offset,
+            length,
+            slice_start: self.current_ts_offset,
+            slice_end: offset + length,
+        };
+
+        // Synthesized and annotated steps both carry a `step` number
+        let is_step = event.args.contains_key("step");
+        let entries = if is_step {
+            &mut self.steps
+        } else {
+            &mut self.ranges
+        };
+        entries.push(entry);
+        self.open
+            .push(Reverse((end_ns, is_step, entries.len() - 1)));
+    }
+
+    /// Close every open slice after the last event has been recorded
+    pub fn finish(&mut self) {
+        self.close_before(i64::MAX);
+    }
+
+    /// Close slices of entries that end before `ts_ns` at the last event so far
+    fn close_before(&mut self, ts_ns: i64) {
+        while let Some(&Reverse((end_ns, is_step, index))) = self.open.peek() {
+            if end_ns >= ts_ns {
+                break;
+            }
+            self.open.pop();
+            let entries = if is_step {
+                &mut self.steps
+            } else {
+                &mut self.ranges
+            };
+            entries[index].slice_end = self.last_end;
+        }
+    }
+
+    /// NVTX and nvtx-kernel ranges in write order
+    pub fn ranges(&self) -> &[OutlineEntry] {
+        &self.ranges
+    }
+
+    /// Step markers in write order
+    pub fn steps(&self) -> &[OutlineEntry] {
+        &self.steps
+    }
+
+    /// Outline as JSON: `{"version", "offsets", "ranges": [...], "steps": [...]}`
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "version": OUTLINE_VERSION,
+            "offsets": "uncompressed",
+            "ranges": self.ranges,
+            "steps": self.steps,
+        })
+    }
+
+    /// Write the outline as a companion JSON file
+    pub fn write_sidecar(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        serde_json::to_writer(BufWriter::new(file), &self.to_json())
+            .map_err(|e| ConvertError::Output(e.into()))?;
+        Ok(())
+    }
+}
+
+/// Default outline path for an output trace: `trace.json.gz` -> `trace.outline.json`
+pub fn outline_path(output_path: &str) -> String {
+    let stem = output_path.strip_suffix(".gz").unwrap_or(output_path);
+    let stem = stem.strip_suffix(".json").unwrap_or(stem);
+    format!("{}.outline.json", stem)
+}
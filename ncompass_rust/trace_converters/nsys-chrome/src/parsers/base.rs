@@ -1,10 +1,20 @@
 //! Base parser trait and shared parsing context
 
-use anyhow::Result;
-use rusqlite::Connection;
+use rusqlite::{Connection, Statement};
+use serde_json::json;
 use std::collections::HashMap;
 
+use crate::cost_model::KernelCostModel;
+use crate::error::Result;
 use crate::models::{ChromeTraceEvent, ConversionOptions};
+use crate::schema::SchemaProbe;
+use crate::self_profile::phase;
+
+/// Arg naming the table an event was read from (see `ConversionOptions::source_rows`)
+pub const SOURCE_TABLE_ARG: &str = "source_table";
+
+/// Arg holding the SQLite rowid an event was read from
+pub const SOURCE_ROWID_ARG: &str = "source_rowid";
 
 /// Shared context for event parsing
 pub struct ParseContext<'a> {
@@ -18,6 +28,10 @@ pub struct ParseContext<'a> {
     pub device_map: &'a HashMap<i32, i32>,
     /// TID to thread name mapping
     pub thread_names: &'a HashMap<i32, String>,
+    /// Probed schema used to resolve renamed tables (None = canonical names only)
+    pub schema: Option<&'a SchemaProbe>,
+    /// Model used to attach FLOP/byte estimates to kernel events
+    pub cost_model: Option<&'a dyn KernelCostModel>,
 }
 
 impl<'a> ParseContext<'a> {
@@ -34,8 +48,49 @@ impl<'a> ParseContext<'a> {
             options,
             device_map,
             thread_names,
+            schema: None,
+            cost_model: None,
+        }
+    }
+
+    /// Resolve table names through a schema probe
+    pub fn with_schema(mut self, schema: &'a SchemaProbe) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Attach cost estimates to kernel events using a model
+    pub fn with_cost_model(mut self, cost_model: Option<&'a dyn KernelCostModel>) -> Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    /// Select-list suffix reading the rowid as the last column, when source
+    /// rows are requested
+    pub fn rowid_column(&self) -> &'static str {
+        if self.options.source_rows {
+            ", rowid"
+        } else {
+            ""
         }
     }
+
+    /// Index of the rowid column added by [`ParseContext::rowid_column`]
+    pub fn rowid_index(&self, stmt: &Statement) -> Option<usize> {
+        self.options
+            .source_rows
+            .then(|| stmt.column_count() - 1)
+    }
+}
+
+/// Record the SQLite row an event was read from, for round-trip debugging
+pub fn attach_source_row(event: &mut ChromeTraceEvent, table: &str, rowid: i64) {
+    event
+        .args
+        .insert(SOURCE_TABLE_ARG.into(), json!(table));
+    event
+        .args
+        .insert(SOURCE_ROWID_ARG.into(), json!(rowid));
 }
 
 /// Base trait for event parsers
@@ -43,6 +98,17 @@ pub trait EventParser {
     /// Get the table name this parser works with
     fn table_name(&self) -> &str;
 
+    /// Get the activity type this parser extracts
+    fn activity_type(&self) -> &str;
+
+    /// Resolve the table to read from, honoring table variants found by the schema probe
+    fn resolve_table<'c>(&'c self, context: &'c ParseContext) -> &'c str {
+        context
+            .schema
+            .and_then(|schema| schema.table_for(self.activity_type()))
+            .unwrap_or_else(|| self.table_name())
+    }
+
     /// Parse events from the table
     fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>>;
 
@@ -50,11 +116,22 @@ pub trait EventParser {
     fn safe_parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
         use crate::schema::table_exists;
 
-        if !table_exists(context.conn, self.table_name())? {
-            return Ok(Vec::new());
-        }
+        let mut read_phase = phase(format!("read {}", self.resolve_table(context)), "read");
+
+        // With a probe, only parse when a compatible table variant was found
+        let events = if let Some(schema) = context.schema {
+            match schema.table_for(self.activity_type()) {
+                Some(_) => self.parse(context)?,
+                None => Vec::new(),
+            }
+        } else if table_exists(context.conn, self.table_name())? {
+            self.parse(context)?
+        } else {
+            Vec::new()
+        };
 
-        self.parse(context)
+        read_phase.set_events(events.len());
+        Ok(events)
     }
 }
 
@@ -1,12 +1,48 @@
 //! CUPTI event parsers for CUDA kernel and runtime events
 
-use anyhow::Result;
 use serde_json::json;
 use std::collections::HashMap;
 
+use crate::category::EventCategory;
+use crate::cost_model::attach_cost_estimate;
+use crate::demangle::DemangleCache;
+use crate::error::Result;
 use crate::mapping::decompose_global_tid;
 use crate::models::{ChromeTraceEvent, ns_to_us};
-use crate::parsers::base::{EventParser, ParseContext};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
+
+/// Columns naming a kernel's green context, by nsys version
+const GREEN_CONTEXT_COLUMNS: &[&str] = &["greenContextId", "greenContext"];
+
+/// Track of a stream inside a green context
+///
+/// Green contexts partition a device's SMs, so each partition gets its own
+/// group of stream tracks under the device instead of sharing stream rows.
+pub fn green_context_track(green_context_id: i64, stream_id: i32) -> String {
+    format!("Green Context {} / Stream {}", green_context_id, stream_id)
+}
+
+/// Track of kernels whose record has no stream ID
+pub const UNKNOWN_STREAM_TRACK: &str = "Unknown Stream";
+
+/// Track of a kernel on a stream, inside its green context if it has one
+///
+/// Kernels without a stream ID go on the [`UNKNOWN_STREAM_TRACK`].
+pub fn kernel_track(green_context_id: Option<i64>, stream_id: Option<i64>) -> String {
+    match (green_context_id, stream_id) {
+        (Some(green_context_id), Some(stream_id)) => {
+            green_context_track(green_context_id, stream_id as i32)
+        }
+        (Some(green_context_id), None) => {
+            format!(
+                "Green Context {} / {}",
+                green_context_id, UNKNOWN_STREAM_TRACK
+            )
+        }
+        (None, Some(stream_id)) => format!("Stream {}", stream_id),
+        (None, None) => UNKNOWN_STREAM_TRACK.to_string(),
+    }
+}
 
 /// Parser for CUPTI_ACTIVITY_KIND_KERNEL table
 pub struct CUPTIKernelParser;
@@ -16,10 +52,17 @@ impl EventParser for CUPTIKernelParser {
         "CUPTI_ACTIVITY_KIND_KERNEL"
     }
 
+    fn activity_type(&self) -> &str {
+        "kernel"
+    }
+
     fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
         let mut events = Vec::new();
 
-        let mut stmt = context.conn.prepare(&format!("SELECT * FROM {}", self.table_name()))?;
+        let table = self.resolve_table(context);
+        let mut stmt = context
+            .conn
+            .prepare(&format!("SELECT *{} FROM {}", context.rowid_column(), table))?;
         let column_names: Vec<String> = stmt
             .column_names()
             .iter()
@@ -27,6 +70,7 @@ impl EventParser for CUPTIKernelParser {
             .collect();
 
         // Find column indices
+        let idx_rowid = context.rowid_index(&stmt);
         let idx_device = column_names.iter().position(|n| n == "deviceId").unwrap();
         let idx_stream = column_names.iter().position(|n| n == "streamId").unwrap();
         let idx_short_name = column_names.iter().position(|n| n == "shortName").unwrap();
@@ -42,14 +86,25 @@ impl EventParser for CUPTIKernelParser {
         let idx_static_smem = column_names.iter().position(|n| n == "staticSharedMemory").unwrap();
         let idx_dynamic_smem = column_names.iter().position(|n| n == "dynamicSharedMemory").unwrap();
         let idx_corr = column_names.iter().position(|n| n == "correlationId").unwrap();
+        // Only kernels launched from CUDA graphs have a node ID
+        let idx_graph_node = column_names.iter().position(|n| n == "graphNodeId");
+        // Only newer exports record green contexts
+        let idx_green_context = GREEN_CONTEXT_COLUMNS
+            .iter()
+            .find_map(|name| column_names.iter().position(|n| n == name));
+
+        // Demangle every distinct symbol up front, so rows only look names up
+        let demangled = DemangleCache::global();
+        demangled.warm(context.strings.values().map(String::as_str));
 
         let mut rows = stmt.query([])?;
         while let Some(row) = rows.next()? {
             let device_id: i32 = row.get(idx_device)?;
-            let stream_id: i32 = row.get(idx_stream)?;
+            // Some drivers record kernels without a stream
+            let stream_id: Option<i32> = row.get(idx_stream)?;
             let short_name_id: i32 = row.get(idx_short_name)?;
             let start: i64 = row.get(idx_start)?;
-            let end: i64 = row.get(idx_end)?;
+            let end: Option<i64> = row.get(idx_end)?;
             let grid_x: i32 = row.get(idx_grid_x)?;
             let grid_y: i32 = row.get(idx_grid_y)?;
             let grid_z: i32 = row.get(idx_grid_z)?;
@@ -59,13 +114,22 @@ impl EventParser for CUPTIKernelParser {
             let regs_per_thread: i32 = row.get(idx_regs)?;
             let static_smem: i32 = row.get(idx_static_smem)?;
             let dynamic_smem: i32 = row.get(idx_dynamic_smem)?;
-            let correlation_id: i32 = row.get(idx_corr)?;
+            let correlation_id: i64 = row.get(idx_corr)?;
+            let graph_node_id: Option<i64> = match idx_graph_node {
+                Some(idx) => row.get(idx)?,
+                None => None,
+            };
+            // 0 is the device's primary context
+            let green_context_id: Option<i64> = match idx_green_context {
+                Some(idx) => row.get::<_, Option<i64>>(idx)?.filter(|&id| id != 0),
+                None => None,
+            };
 
             let kernel_name = context
                 .strings
                 .get(&short_name_id)
-                .map(|s| s.as_str())
-                .unwrap_or("Unknown Kernel");
+                .map(|s| demangled.get(s))
+                .unwrap_or_else(|| "Unknown Kernel".into());
 
             let mut args = HashMap::default();
             args.insert("grid".to_string(), json!([grid_x, grid_y, grid_z]));
@@ -75,19 +139,47 @@ impl EventParser for CUPTIKernelParser {
             args.insert("dynamicSharedMemory".to_string(), json!(dynamic_smem));
             args.insert("correlationId".to_string(), json!(correlation_id));
             args.insert("deviceId".to_string(), json!(device_id));
-            args.insert("streamId".to_string(), json!(stream_id));
+            match stream_id {
+                Some(stream_id) => {
+                    args.insert("streamId".to_string(), json!(stream_id));
+                }
+                None => {
+                    args.insert("stream_missing".to_string(), json!(true));
+                }
+            }
             args.insert("start_ns".to_string(), json!(start));
-            args.insert("end_ns".to_string(), json!(end));
+            if let Some(end) = end {
+                args.insert("end_ns".to_string(), json!(end));
+            }
+            if let Some(graph_node_id) = graph_node_id {
+                args.insert("graphNodeId".to_string(), json!(graph_node_id));
+            }
+            if let Some(green_context_id) = green_context_id {
+                args.insert("greenContextId".to_string(), json!(green_context_id));
+            }
+            let tid = kernel_track(green_context_id, stream_id.map(i64::from));
 
-            let event = ChromeTraceEvent::complete(
+            let mut event = ChromeTraceEvent::complete(
                 kernel_name.to_string(),
                 ns_to_us(start),
-                ns_to_us(end - start),
+                ns_to_us(end.unwrap_or(start) - start),
                 format!("Device {}", device_id),
-                format!("Stream {}", stream_id),
-                "kernel".to_string(),
+                tid,
+                EventCategory::Kernel,
             )
             .with_args(args);
+            // Records without an end are closed by the repair pass
+            if end.is_none() {
+                event.dur = None;
+            }
+
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, table, row.get(idx)?);
+            }
+
+            if let Some(cost_model) = context.cost_model {
+                attach_cost_estimate(&mut event, cost_model);
+            }
 
             events.push(event);
         }
@@ -96,7 +188,7 @@ impl EventParser for CUPTIKernelParser {
     }
 }
 
-/// Parser for CUPTI_ACTIVITY_KIND_RUNTIME table
+/// Parser for CUPTI_ACTIVITY_KIND_RUNTIME and CUPTI_ACTIVITY_KIND_DRIVER tables
 pub struct CUPTIRuntimeParser;
 
 impl EventParser for CUPTIRuntimeParser {
@@ -104,51 +196,72 @@ impl EventParser for CUPTIRuntimeParser {
         "CUPTI_ACTIVITY_KIND_RUNTIME"
     }
 
+    fn activity_type(&self) -> &str {
+        "cuda-api"
+    }
+
     fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
         let mut events = Vec::new();
 
-        let query = format!(
-            "SELECT start, end, globalTid, correlationId, nameId FROM {}",
-            self.table_name()
-        );
-        let mut stmt = context.conn.prepare(&query)?;
+        // Driver API calls are in a table of their own; read it after the runtime's
+        let tables: Vec<&str> = match context.schema {
+            Some(schema) => schema.tables_for(self.activity_type()).collect(),
+            None => vec![self.table_name()],
+        };
+        for table in tables {
+            let query = format!(
+                "SELECT start, end, globalTid, correlationId, nameId{} FROM {}",
+                context.rowid_column(),
+                table
+            );
+            let mut stmt = context.conn.prepare(&query)?;
+            let idx_rowid = context.rowid_index(&stmt);
 
-        let mut rows = stmt.query([])?;
-        while let Some(row) = rows.next()? {
-            let start: i64 = row.get(0)?;
-            let end: i64 = row.get(1)?;
-            let global_tid: i64 = row.get(2)?;
-            let correlation_id: i32 = row.get(3)?;
-            let name_id: i32 = row.get(4)?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let start: i64 = row.get(0)?;
+                let end: Option<i64> = row.get(1)?;
+                let global_tid: i64 = row.get(2)?;
+                let correlation_id: i64 = row.get(3)?;
+                let name_id: i32 = row.get(4)?;
 
-            let (pid, tid) = decompose_global_tid(global_tid);
-            let device_id = context.device_map.get(&pid).copied().unwrap_or(pid);
+                let (pid, tid) = decompose_global_tid(global_tid);
+                let device_id = context.device_map.get(&pid).copied().unwrap_or(pid);
 
-            let api_name = context
-                .strings
-                .get(&name_id)
-                .map(|s| s.as_str())
-                .unwrap_or("Unknown API");
+                let api_name = context
+                    .strings
+                    .get(&name_id)
+                    .map(|s| s.as_str())
+                    .unwrap_or("Unknown API");
 
-            let mut args = HashMap::default();
-            args.insert("correlationId".to_string(), json!(correlation_id));
-            args.insert("deviceId".to_string(), json!(device_id));
-            args.insert("raw_pid".to_string(), json!(pid));
-            args.insert("raw_tid".to_string(), json!(tid));
-            args.insert("start_ns".to_string(), json!(start));
-            args.insert("end_ns".to_string(), json!(end));
+                let mut args = HashMap::default();
+                args.insert("correlationId".to_string(), json!(correlation_id));
+                args.insert("deviceId".to_string(), json!(device_id));
+                args.insert("raw_pid".to_string(), json!(pid));
+                args.insert("raw_tid".to_string(), json!(tid));
+                args.insert("start_ns".to_string(), json!(start));
+                if let Some(end) = end {
+                    args.insert("end_ns".to_string(), json!(end));
+                }
 
-            let event = ChromeTraceEvent::complete(
-                api_name.to_string(),
-                ns_to_us(start),
-                ns_to_us(end - start),
-                format!("Device {}", device_id),
-                format!("CUDA API Thread {}", tid),
-                "cuda_api".to_string(),
-            )
-            .with_args(args);
+                let mut event = ChromeTraceEvent::complete(
+                    api_name.to_string(),
+                    ns_to_us(start),
+                    ns_to_us(end.unwrap_or(start) - start),
+                    format!("Device {}", device_id),
+                    format!("CUDA API Thread {}", tid),
+                    EventCategory::CudaApi,
+                )
+                .with_args(args);
+                if end.is_none() {
+                    event.dur = None;
+                }
+                if let Some(idx) = idx_rowid {
+                    attach_source_row(&mut event, table, row.get(idx)?);
+                }
 
-            events.push(event);
+                events.push(event);
+            }
         }
 
         Ok(events)
@@ -0,0 +1,91 @@
+//! Activity types contributed by library users
+//!
+//! Organizations often record their own tables next to the nsys ones, e.g.
+//! framework annotations written by a custom NVTX-like injection library. An
+//! [`ActivityExtractor`] describes how to read such a table: the SQL query,
+//! how a result row becomes an event, and whether the events take part in
+//! linking. Register it on the converter with
+//! `NsysChromeConverter::with_activity_extractor` and request its activity
+//! type in `ConversionOptions::activity_types` like any built-in one.
+
+use rusqlite::Row;
+
+use crate::error::{ConvertError, Result};
+use crate::models::ChromeTraceEvent;
+use crate::parsers::base::{EventParser, ParseContext};
+use crate::schema::TableRegistry;
+
+/// How events of a custom activity take part in linking
+///
+/// Linked events must carry the args the built-in parsers set for the role
+/// they stand in for: `start_ns`, `end_ns`, `deviceId` and `raw_tid` for
+/// annotations, plus `correlationId` for kernels and API calls (and
+/// `streamId` for kernels).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ActivityLinkRole {
+    /// Passed through unlinked; set the event category to the activity type
+    /// so cached traces can filter them
+    #[default]
+    None,
+    /// Linked to the kernels launched inside them, like NVTX ranges
+    Annotation,
+    /// Linked to the API calls that launched them, like CUDA kernels
+    Kernel,
+    /// Linked to the kernels they launched, like CUDA runtime calls
+    CudaApi,
+}
+
+/// Extracts events of a custom activity type from one table
+pub trait ActivityExtractor: Send + Sync {
+    /// Activity type selecting this extractor in `activity_types`
+    fn activity_type(&self) -> &str;
+
+    /// Table the query reads; the activity is unavailable when it is absent
+    fn table_name(&self) -> &str;
+
+    /// Query producing one row per event
+    fn query(&self) -> String;
+
+    /// Turn one result row into an event, or None to skip the row
+    fn map_row(&self, row: &Row, context: &ParseContext) -> Result<Option<ChromeTraceEvent>>;
+
+    /// How the extracted events take part in linking
+    fn link_role(&self) -> ActivityLinkRole {
+        ActivityLinkRole::None
+    }
+}
+
+/// Reject extractors that would shadow a built-in activity type
+pub fn validate_extractor(extractor: &dyn ActivityExtractor) -> Result<()> {
+    let activity = extractor.activity_type();
+    if activity == "nvtx-kernel" || TableRegistry::table_activity_types().contains(&activity) {
+        return Err(ConvertError::InvalidOption(format!(
+            "Custom activity type '{}' is a built-in activity type",
+            activity
+        )));
+    }
+    Ok(())
+}
+
+/// Runs an [`ActivityExtractor`] as an [`EventParser`]
+pub struct CustomParser<'a>(pub &'a dyn ActivityExtractor);
+
+impl EventParser for CustomParser<'_> {
+    fn table_name(&self) -> &str {
+        self.0.table_name()
+    }
+
+    fn activity_type(&self) -> &str {
+        self.0.activity_type()
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let mut stmt = context.conn.prepare(&self.0.query())?;
+        let mut rows = stmt.query([])?;
+        let mut events = Vec::new();
+        while let Some(row) = rows.next()? {
+            events.extend(self.0.map_row(row, context)?);
+        }
+        Ok(events)
+    }
+}
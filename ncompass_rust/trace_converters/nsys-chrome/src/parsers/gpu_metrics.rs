@@ -0,0 +1,99 @@
+//! GPU power, temperature and clock sampling parser
+//!
+//! With `--gpu-metrics-devices`, nsys samples GPU counters into GPU_METRICS,
+//! naming each metric in TARGET_INFO_GPU_METRICS. The power, temperature and
+//! clock metrics among them are emitted as one counter track per device and
+//! metric; the throughput metrics are left out. The GPU index is the low byte
+//! of `typeId`.
+
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase};
+use crate::parsers::base::{EventParser, ParseContext};
+use crate::schema::table_exists;
+
+/// Category of GPU metric counters
+pub const GPU_METRICS_CATEGORY: &str = "gpu-metrics";
+
+/// Table naming the metrics sampled into GPU_METRICS
+const METRIC_NAMES_TABLE: &str = "TARGET_INFO_GPU_METRICS";
+
+/// Metric name fragments (lowercase) of the sampled metrics that are kept
+const SAMPLED_METRIC_KEYWORDS: &[&str] = &["power", "temperature", "clock", "frequency"];
+
+/// Whether a metric is a power, temperature or clock sample
+pub fn is_sampled_metric(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SAMPLED_METRIC_KEYWORDS.iter().any(|k| name.contains(k))
+}
+
+/// Parser for power, temperature and clock samples in GPU_METRICS
+pub struct GpuMetricsParser;
+
+impl GpuMetricsParser {
+    /// Metric names by (typeId, metricId); without them no metric is recognized
+    fn metric_names(context: &ParseContext) -> Result<HashMap<(i64, i64), String>> {
+        if !table_exists(context.conn, METRIC_NAMES_TABLE)? {
+            return Ok(HashMap::new());
+        }
+        let mut stmt = context.conn.prepare(&format!(
+            "SELECT DISTINCT typeId, metricId, metricName FROM {}",
+            METRIC_NAMES_TABLE
+        ))?;
+        let names = stmt
+            .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(names)
+    }
+}
+
+impl EventParser for GpuMetricsParser {
+    fn table_name(&self) -> &str {
+        "GPU_METRICS"
+    }
+
+    fn activity_type(&self) -> &str {
+        "gpu-metrics"
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let mut events = Vec::new();
+        let names = Self::metric_names(context)?;
+
+        let table = self.resolve_table(context);
+        let query = format!(
+            "SELECT timestamp, typeId, metricId, value FROM {} ORDER BY timestamp",
+            table
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let timestamp: i64 = row.get(0)?;
+            let type_id: i64 = row.get(1)?;
+            let metric_id: i64 = row.get(2)?;
+            let Some(value) = row.get::<_, Option<f64>>(3)? else {
+                continue;
+            };
+            let Some(name) = names.get(&(type_id, metric_id)) else {
+                continue;
+            };
+            if !is_sampled_metric(name) {
+                continue;
+            }
+
+            events.push(
+                ChromeTraceEvent::new(
+                    name.clone(),
+                    ChromeTracePhase::Counter,
+                    ns_to_us(timestamp),
+                    format!("Device {}", type_id & 0xff),
+                    String::new(),
+                    GPU_METRICS_CATEGORY.to_string(),
+                )
+                .with_arg("value", value),
+            );
+        }
+        Ok(events)
+    }
+}
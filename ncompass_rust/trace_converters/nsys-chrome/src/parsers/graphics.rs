@@ -0,0 +1,465 @@
+//! Vulkan and DX12 queue submissions, GPU workloads and debug labels
+//!
+//! Graphics captures record, per API, the host calls (`VULKAN_API`,
+//! `DX12_API`), the GPU execution of each submitted command buffer or command
+//! list (`VULKAN_WORKLOAD`, `DX12_WORKLOAD`) and the application's debug
+//! labels (`VULKAN_DEBUG_API`, `DX12_PIX_DEBUG_API`), the graphics
+//! counterpart of NVTX ranges. Workloads share a correlation ID with the call
+//! that submitted them.
+//!
+//! Workloads go on device tracks next to CUDA kernels, one track per queue.
+//! Submitting calls go on their thread's track and labels on a labels track
+//! of the same thread. Each label is then projected onto the GPU, like
+//! `nvtx-kernel` ranges: a range spanning the workloads submitted from within
+//! the label, on a labels track of each device they ran on. Events tell the
+//! kinds apart by their `graphics_kind` arg.
+
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::category::EventCategory;
+use crate::error::Result;
+use crate::mapping::decompose_global_tid;
+use crate::models::{ns_to_us, ChromeTraceEvent};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
+use crate::schema::{table_columns, table_exists};
+
+/// Arg telling graphics events apart
+pub const GRAPHICS_KIND_ARG: &str = "graphics_kind";
+
+/// `graphics_kind` of a host call that submitted GPU work
+pub const GRAPHICS_SUBMIT_KIND: &str = "submit";
+
+/// `graphics_kind` of a command buffer or command list running on the GPU
+pub const GRAPHICS_WORKLOAD_KIND: &str = "workload";
+
+/// `graphics_kind` of a debug label on its host thread
+pub const GRAPHICS_LABEL_KIND: &str = "label";
+
+/// `graphics_kind` of a debug label projected onto a device
+pub const GRAPHICS_GPU_LABEL_KIND: &str = "gpu-label";
+
+/// Columns a debug label table must provide
+const LABEL_COLUMNS: &[&str] = &["start", "end", "globalTid", "textId"];
+
+/// Tables and columns of one graphics API
+struct GraphicsApi {
+    name: &'static str,
+    api_table: &'static str,
+    workload_table: &'static str,
+    label_table: &'static str,
+    queue_column: &'static str,
+    buffer_column: &'static str,
+    buffer_prefix: &'static str,
+}
+
+const GRAPHICS_APIS: &[GraphicsApi] = &[
+    GraphicsApi {
+        name: "Vulkan",
+        api_table: "VULKAN_API",
+        workload_table: "VULKAN_WORKLOAD",
+        label_table: "VULKAN_DEBUG_API",
+        queue_column: "queue",
+        buffer_column: "commandBuffer",
+        buffer_prefix: "Command Buffer",
+    },
+    GraphicsApi {
+        name: "DX12",
+        api_table: "DX12_API",
+        workload_table: "DX12_WORKLOAD",
+        label_table: "DX12_PIX_DEBUG_API",
+        queue_column: "commandQueue",
+        buffer_column: "commandList",
+        buffer_prefix: "Command List",
+    },
+];
+
+/// Host call, label or workload span with what linking needs
+struct Span {
+    start: i64,
+    end: i64,
+    raw_pid: i32,
+    raw_tid: i32,
+    correlation_id: Option<i64>,
+    gpu: Option<i64>,
+}
+
+fn span_of(event: &ChromeTraceEvent) -> Option<Span> {
+    let get = |key: &str| event.args.get(key).and_then(|v| v.as_i64());
+    Some(Span {
+        start: get("start_ns")?,
+        end: get("end_ns")?,
+        raw_pid: get("raw_pid").unwrap_or(0) as i32,
+        raw_tid: get("raw_tid").unwrap_or(0) as i32,
+        correlation_id: get("correlationId"),
+        gpu: get("gpu"),
+    })
+}
+
+fn graphics_kind(event: &ChromeTraceEvent) -> Option<&str> {
+    event.args.get(GRAPHICS_KIND_ARG).and_then(|v| v.as_str())
+}
+
+/// Track of a thread's debug labels
+fn label_track(api: &str, tid: i32) -> String {
+    format!("{} Labels Thread {}", api, tid)
+}
+
+/// Parser for Vulkan and DX12 workload, API and debug label tables
+pub struct GraphicsParser;
+
+impl GraphicsParser {
+    /// Command buffers or command lists as they ran on the GPU
+    fn parse_workloads(
+        &self,
+        context: &ParseContext,
+        api: &GraphicsApi,
+    ) -> Result<Vec<ChromeTraceEvent>> {
+        let columns = table_columns(context.conn, api.workload_table)?;
+        let column_or_null = |name: &str| {
+            if columns.contains(name) {
+                name.to_string()
+            } else {
+                "NULL".to_string()
+            }
+        };
+        let query = format!(
+            "SELECT start, end, gpu, correlationId, {}, {}, {}{} FROM {}",
+            column_or_null(api.queue_column),
+            column_or_null(api.buffer_column),
+            column_or_null("globalTid"),
+            context.rowid_column(),
+            api.workload_table
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
+        let mut rows = stmt.query([])?;
+
+        let mut events = Vec::new();
+        while let Some(row) = rows.next()? {
+            let start: i64 = row.get(0)?;
+            let end: i64 = row.get(1)?;
+            let gpu: i64 = row.get(2)?;
+            let correlation_id: Option<i64> = row.get(3)?;
+            let queue: Option<i64> = row.get(4)?;
+            let buffer: Option<i64> = row.get(5)?;
+            let global_tid: Option<i64> = row.get(6)?;
+
+            let mut args = HashMap::default();
+            args.insert(GRAPHICS_KIND_ARG.to_string(), json!(GRAPHICS_WORKLOAD_KIND));
+            args.insert("graphics_api".to_string(), json!(api.name));
+            args.insert("gpu".to_string(), json!(gpu));
+            args.insert("start_ns".to_string(), json!(start));
+            args.insert("end_ns".to_string(), json!(end));
+            if let Some(correlation_id) = correlation_id {
+                args.insert("correlationId".to_string(), json!(correlation_id));
+            }
+            if let Some(queue) = queue {
+                args.insert(api.queue_column.to_string(), json!(format!("{:#x}", queue)));
+            }
+            if let Some(global_tid) = global_tid {
+                let (pid, tid) = decompose_global_tid(global_tid);
+                args.insert("raw_pid".to_string(), json!(pid));
+                args.insert("raw_tid".to_string(), json!(tid));
+            }
+
+            let name = match buffer {
+                Some(buffer) => format!("{} {:#x}", api.buffer_prefix, buffer),
+                None => format!("{} Workload", api.name),
+            };
+            let track = match queue {
+                Some(queue) => format!("{} Queue {:#x}", api.name, queue),
+                None => format!("{} Queue", api.name),
+            };
+            let mut event = ChromeTraceEvent::complete(
+                name,
+                ns_to_us(start),
+                ns_to_us(end - start),
+                format!("Device {}", gpu),
+                track,
+                EventCategory::Graphics,
+            )
+            .with_args(args);
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, api.workload_table, row.get(idx)?);
+            }
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    /// Host calls that submitted one of `correlation_ids`
+    fn parse_submits(
+        &self,
+        context: &ParseContext,
+        api: &GraphicsApi,
+        correlation_ids: &HashSet<i64>,
+    ) -> Result<Vec<ChromeTraceEvent>> {
+        let query = format!(
+            "SELECT start, end, globalTid, correlationId, nameId{} FROM {}",
+            context.rowid_column(),
+            api.api_table
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
+        let mut rows = stmt.query([])?;
+
+        let mut events = Vec::new();
+        while let Some(row) = rows.next()? {
+            let correlation_id: Option<i64> = row.get(3)?;
+            let Some(correlation_id) = correlation_id.filter(|id| correlation_ids.contains(id))
+            else {
+                continue;
+            };
+            let start: i64 = row.get(0)?;
+            let end: i64 = row.get(1)?;
+            let global_tid: i64 = row.get(2)?;
+            let name_id: Option<i32> = row.get(4)?;
+            let (pid, tid) = decompose_global_tid(global_tid);
+
+            let name = name_id
+                .and_then(|id| context.strings.get(&id))
+                .cloned()
+                .unwrap_or_else(|| format!("{} Submit", api.name));
+            let thread_name = context
+                .thread_names
+                .get(&tid)
+                .cloned()
+                .unwrap_or_else(|| format!("Thread {}", tid));
+
+            let mut args = HashMap::default();
+            args.insert(GRAPHICS_KIND_ARG.to_string(), json!(GRAPHICS_SUBMIT_KIND));
+            args.insert("graphics_api".to_string(), json!(api.name));
+            args.insert("correlationId".to_string(), json!(correlation_id));
+            args.insert("raw_pid".to_string(), json!(pid));
+            args.insert("raw_tid".to_string(), json!(tid));
+            args.insert("start_ns".to_string(), json!(start));
+            args.insert("end_ns".to_string(), json!(end));
+
+            let mut event = ChromeTraceEvent::complete(
+                name,
+                ns_to_us(start),
+                ns_to_us(end - start),
+                format!("Process {}", pid),
+                thread_name,
+                EventCategory::Graphics,
+            )
+            .with_args(args);
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, api.api_table, row.get(idx)?);
+            }
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    /// Debug label ranges on their host threads
+    fn parse_labels(
+        &self,
+        context: &ParseContext,
+        api: &GraphicsApi,
+    ) -> Result<Vec<ChromeTraceEvent>> {
+        let query = format!(
+            "SELECT start, end, globalTid, textId{} FROM {} WHERE end IS NOT NULL",
+            context.rowid_column(),
+            api.label_table
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
+        let mut rows = stmt.query([])?;
+
+        let mut events = Vec::new();
+        while let Some(row) = rows.next()? {
+            let start: i64 = row.get(0)?;
+            let end: i64 = row.get(1)?;
+            let global_tid: i64 = row.get(2)?;
+            let text_id: Option<i32> = row.get(3)?;
+            let (pid, tid) = decompose_global_tid(global_tid);
+
+            let name = text_id
+                .and_then(|id| context.strings.get(&id))
+                .cloned()
+                .unwrap_or_else(|| format!("{} Label", api.name));
+
+            let mut args = HashMap::default();
+            args.insert(GRAPHICS_KIND_ARG.to_string(), json!(GRAPHICS_LABEL_KIND));
+            args.insert("graphics_api".to_string(), json!(api.name));
+            args.insert("raw_pid".to_string(), json!(pid));
+            args.insert("raw_tid".to_string(), json!(tid));
+            args.insert("start_ns".to_string(), json!(start));
+            args.insert("end_ns".to_string(), json!(end));
+
+            let mut event = ChromeTraceEvent::complete(
+                name,
+                ns_to_us(start),
+                ns_to_us(end - start),
+                format!("Process {}", pid),
+                label_track(api.name, tid),
+                EventCategory::Graphics,
+            )
+            .with_args(args);
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, api.label_table, row.get(idx)?);
+            }
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+impl EventParser for GraphicsParser {
+    fn table_name(&self) -> &str {
+        "VULKAN_WORKLOAD"
+    }
+
+    fn activity_type(&self) -> &str {
+        "graphics"
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let mut events = Vec::new();
+
+        for api in GRAPHICS_APIS {
+            if !table_exists(context.conn, api.workload_table)? {
+                continue;
+            }
+            let workloads = self.parse_workloads(context, api)?;
+            let correlation_ids: HashSet<i64> = workloads
+                .iter()
+                .filter_map(|e| e.args.get("correlationId").and_then(|v| v.as_i64()))
+                .collect();
+            let submits = if table_exists(context.conn, api.api_table)? {
+                self.parse_submits(context, api, &correlation_ids)?
+            } else {
+                Vec::new()
+            };
+
+            let label_columns = if table_exists(context.conn, api.label_table)? {
+                table_columns(context.conn, api.label_table)?
+            } else {
+                HashSet::new()
+            };
+            let labels = if label_columns.is_empty() {
+                Vec::new()
+            } else if LABEL_COLUMNS.iter().all(|c| label_columns.contains(*c)) {
+                self.parse_labels(context, api)?
+            } else {
+                log::warn!(
+                    "Skipping {}: missing columns (needs {})",
+                    api.label_table,
+                    LABEL_COLUMNS.join(", ")
+                );
+                Vec::new()
+            };
+
+            events.extend(link_debug_labels(&labels, &submits, &workloads));
+            events.extend(workloads);
+            events.extend(submits);
+            events.extend(labels);
+        }
+
+        Ok(events)
+    }
+}
+
+/// Project debug labels onto the devices that ran the work submitted within them
+///
+/// A label covers the workloads whose submitting call started within the label
+/// on the same thread. For every label and device this returns one
+/// `gpu-label` range from the first such workload's start to the last one's
+/// end, on the device's labels track, with the number of workloads in the
+/// `workloads` arg. Events of other kinds among the inputs are ignored.
+pub fn link_debug_labels(
+    labels: &[ChromeTraceEvent],
+    submits: &[ChromeTraceEvent],
+    workloads: &[ChromeTraceEvent],
+) -> Vec<ChromeTraceEvent> {
+    let mut workloads_by_correlation: HashMap<i64, Vec<Span>> = HashMap::new();
+    for span in workloads
+        .iter()
+        .filter(|e| graphics_kind(e) == Some(GRAPHICS_WORKLOAD_KIND))
+        .filter_map(span_of)
+    {
+        if let Some(id) = span.correlation_id {
+            workloads_by_correlation.entry(id).or_default().push(span);
+        }
+    }
+
+    // Submitting calls by thread, in start order
+    let mut submits_by_thread: HashMap<(i32, i32), Vec<(i64, i64)>> = HashMap::new();
+    for span in submits
+        .iter()
+        .filter(|e| graphics_kind(e) == Some(GRAPHICS_SUBMIT_KIND))
+        .filter_map(span_of)
+    {
+        if let Some(id) = span.correlation_id {
+            submits_by_thread
+                .entry((span.raw_pid, span.raw_tid))
+                .or_default()
+                .push((span.start, id));
+        }
+    }
+    for calls in submits_by_thread.values_mut() {
+        calls.sort_unstable();
+    }
+
+    let mut projected = Vec::new();
+    for label in labels
+        .iter()
+        .filter(|e| graphics_kind(e) == Some(GRAPHICS_LABEL_KIND))
+    {
+        let Some(span) = span_of(label) else {
+            continue;
+        };
+        let Some(calls) = submits_by_thread.get(&(span.raw_pid, span.raw_tid)) else {
+            continue;
+        };
+        let first = calls.partition_point(|&(start, _)| start < span.start);
+        let last = calls.partition_point(|&(start, _)| start <= span.end);
+
+        // Device -> (first start, last end, workloads)
+        let mut per_device: BTreeMap<i64, (i64, i64, usize)> = BTreeMap::new();
+        for (_, id) in &calls[first..last] {
+            for workload in workloads_by_correlation.get(id).into_iter().flatten() {
+                let entry = per_device.entry(workload.gpu.unwrap_or(0)).or_insert((
+                    workload.start,
+                    workload.end,
+                    0,
+                ));
+                entry.0 = entry.0.min(workload.start);
+                entry.1 = entry.1.max(workload.end);
+                entry.2 += 1;
+            }
+        }
+
+        let api = label
+            .args
+            .get("graphics_api")
+            .cloned()
+            .unwrap_or_else(|| json!("Graphics"));
+        for (gpu, (start, end, count)) in per_device {
+            let mut args = HashMap::default();
+            args.insert(
+                GRAPHICS_KIND_ARG.to_string(),
+                json!(GRAPHICS_GPU_LABEL_KIND),
+            );
+            args.insert("graphics_api".to_string(), api.clone());
+            args.insert("gpu".to_string(), json!(gpu));
+            args.insert("workloads".to_string(), json!(count));
+            args.insert("start_ns".to_string(), json!(start));
+            args.insert("end_ns".to_string(), json!(end));
+            projected.push(
+                ChromeTraceEvent::complete(
+                    label.name.clone(),
+                    ns_to_us(start),
+                    ns_to_us(end - start),
+                    format!("Device {}", gpu),
+                    format!("{} Labels", api.as_str().unwrap_or("Graphics")),
+                    EventCategory::Graphics,
+                )
+                .with_args(args),
+            );
+        }
+    }
+    projected
+}
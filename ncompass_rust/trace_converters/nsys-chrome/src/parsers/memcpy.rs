@@ -0,0 +1,149 @@
+//! Memcpy parser placing copies on copy engine tracks
+//!
+//! Copy engines are DMA queues separate from the SMs, so a copy only
+//! overlaps compute when it runs on an engine while kernels run. When the
+//! export records the channel each copy used, copies are emitted on one
+//! `Copy Engine N` track per engine under their device; older exports
+//! without channel info fall back to the stream track. Peer-to-peer copies
+//! are left to the `nvlink` activity.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::models::{ns_to_us, ChromeTraceEvent};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
+
+/// Category of memcpy events
+pub const MEMCPY_CATEGORY: &str = "memcpy";
+
+/// Columns naming the copy engine channel, by nsys version
+const CHANNEL_COLUMNS: &[&str] = &["channelID", "channelId"];
+
+/// CUPTI_ACTIVITY_MEMCPY_KIND_PTOP
+const MEMCPY_KIND_PTOP: i64 = 10;
+
+/// Track name for copies run on one copy engine
+pub fn copy_engine_track(channel: i64) -> String {
+    format!("Copy Engine {}", channel)
+}
+
+/// Short name of a CUPTI memcpy kind, as shown by nsys
+pub fn copy_kind_name(copy_kind: i64) -> &'static str {
+    match copy_kind {
+        1 => "HtoD",
+        2 => "DtoH",
+        3 => "HtoA",
+        4 => "AtoH",
+        5 => "AtoA",
+        6 => "AtoD",
+        7 => "DtoA",
+        8 => "DtoD",
+        9 => "HtoH",
+        10 => "PtoP",
+        _ => "Unknown",
+    }
+}
+
+/// Parser for host and device copies in CUPTI_ACTIVITY_KIND_MEMCPY
+pub struct MemcpyParser;
+
+impl EventParser for MemcpyParser {
+    fn table_name(&self) -> &str {
+        "CUPTI_ACTIVITY_KIND_MEMCPY"
+    }
+
+    fn activity_type(&self) -> &str {
+        "memcpy"
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let mut events = Vec::new();
+
+        let table = self.resolve_table(context);
+        let mut stmt = context.conn.prepare(&format!(
+            "SELECT *{} FROM {}",
+            context.rowid_column(),
+            table
+        ))?;
+        let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+        let column = |name: &str| column_names.iter().position(|n| n == name);
+
+        let idx_rowid = context.rowid_index(&stmt);
+        let idx_start = column("start").unwrap();
+        let idx_end = column("end").unwrap();
+        let idx_device = column("deviceId").unwrap();
+        let idx_stream = column("streamId").unwrap();
+        let idx_corr = column("correlationId").unwrap();
+        let idx_bytes = column("bytes").unwrap();
+        let idx_kind = column("copyKind").unwrap();
+        let idx_src_device = column("srcDeviceId");
+        let idx_dst_device = column("dstDeviceId");
+        let idx_channel = CHANNEL_COLUMNS.iter().find_map(|name| column(name));
+
+        let optional = |row: &rusqlite::Row, idx: Option<usize>| -> Result<Option<i64>> {
+            Ok(match idx {
+                Some(idx) => row.get(idx)?,
+                None => None,
+            })
+        };
+
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let start: i64 = row.get(idx_start)?;
+            let end: i64 = row.get(idx_end)?;
+            let device_id: i64 = row.get(idx_device)?;
+            let stream_id: i64 = row.get(idx_stream)?;
+            let correlation_id: i64 = row.get(idx_corr)?;
+            let bytes: i64 = row.get(idx_bytes)?;
+            let copy_kind: i64 = row.get(idx_kind)?;
+            let src_device = optional(row, idx_src_device)?.unwrap_or(device_id);
+            let dst_device = optional(row, idx_dst_device)?.unwrap_or(device_id);
+            let channel = optional(row, idx_channel)?;
+
+            // Shown on NVLink tracks instead
+            if copy_kind == MEMCPY_KIND_PTOP || src_device != dst_device {
+                continue;
+            }
+
+            let mut args = HashMap::default();
+            args.insert("deviceId".to_string(), json!(device_id));
+            args.insert("streamId".to_string(), json!(stream_id));
+            args.insert("correlationId".to_string(), json!(correlation_id));
+            args.insert("start_ns".to_string(), json!(start));
+            args.insert("end_ns".to_string(), json!(end));
+            args.insert("bytes".to_string(), json!(bytes));
+            args.insert("copyKind".to_string(), json!(copy_kind_name(copy_kind)));
+            if end > start {
+                let gbps = bytes as f64 / (end - start) as f64;
+                args.insert(
+                    "bandwidth_gbps".to_string(),
+                    json!((gbps * 1000.0).round() / 1000.0),
+                );
+            }
+            let tid = match channel {
+                Some(channel) => {
+                    args.insert("copyEngine".to_string(), json!(channel));
+                    copy_engine_track(channel)
+                }
+                None => format!("Stream {}", stream_id),
+            };
+
+            let mut event = ChromeTraceEvent::complete(
+                format!("Memcpy {}", copy_kind_name(copy_kind)),
+                ns_to_us(start),
+                ns_to_us(end - start),
+                format!("Device {}", device_id),
+                tid,
+                MEMCPY_CATEGORY.to_string(),
+            )
+            .with_args(args);
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, table, row.get(idx)?);
+            }
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+}
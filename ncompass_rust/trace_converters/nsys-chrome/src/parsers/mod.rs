@@ -2,13 +2,36 @@
 
 pub mod base;
 pub mod cupti;
+pub mod custom;
+pub mod gpu_metrics;
+pub mod graphics;
+pub mod memcpy;
+pub mod mpi;
 pub mod nvtx;
 pub mod osrt;
+pub mod p2p;
 pub mod sched;
+pub mod thread_state;
+pub mod wddm;
 
-pub use base::{EventParser, ParseContext};
-pub use cupti::{CUPTIKernelParser, CUPTIRuntimeParser};
-pub use nvtx::NVTXParser;
+pub use base::{
+    attach_source_row, EventParser, ParseContext, SOURCE_ROWID_ARG, SOURCE_TABLE_ARG,
+};
+pub use cupti::{
+    green_context_track, kernel_track, CUPTIKernelParser, CUPTIRuntimeParser, UNKNOWN_STREAM_TRACK,
+};
+pub use custom::{validate_extractor, ActivityExtractor, ActivityLinkRole, CustomParser};
+pub use gpu_metrics::{is_sampled_metric, GpuMetricsParser, GPU_METRICS_CATEGORY};
+pub use graphics::{link_debug_labels, GraphicsParser};
+pub use memcpy::MemcpyParser;
+pub use mpi::MPIParser;
+pub use nvtx::{NVTXParser, NvtxMarkParser};
 pub use osrt::OSRTParser;
+pub use p2p::P2PParser;
 pub use sched::SchedParser;
+pub use thread_state::{
+    classify_switch_out, thread_state_track, CpuThreadState, ThreadStateParser,
+    THREAD_STATE_CATEGORY,
+};
+pub use wddm::WDDMParser;
 
@@ -0,0 +1,237 @@
+//! MPI API parser for multi-rank captures
+//!
+//! With `--trace=mpi`, nsys records MPI calls in MPI_COLLECTIVES_EVENTS,
+//! MPI_P2P_EVENTS, MPI_START_WAIT_EVENTS and MPI_OTHER_EVENTS, the rank of
+//! each process in MPI_RANKS and communicator membership in
+//! MPI_COMMUNICATORS. Calls are emitted on one `MPI Rank R Thread T` track per
+//! calling thread, next to the GPU tracks of the rank's device, with the rank,
+//! communicator and message details in args.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::category::EventCategory;
+use crate::error::Result;
+use crate::mapping::decompose_global_tid;
+use crate::models::{ns_to_us, ChromeTraceEvent};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
+use crate::schema::{table_columns, table_exists};
+use crate::self_profile::phase;
+
+/// Tables holding MPI calls, with the per-table columns copied into args
+const MPI_EVENT_TABLES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "MPI_COLLECTIVES_EVENTS",
+        &[
+            ("rootRank", "root_rank"),
+            ("size", "bytes"),
+            ("recvSize", "recv_bytes"),
+        ],
+    ),
+    (
+        "MPI_P2P_EVENTS",
+        &[
+            ("remoteRank", "remote_rank"),
+            ("tag", "tag"),
+            ("size", "bytes"),
+        ],
+    ),
+    ("MPI_START_WAIT_EVENTS", &[("requestHandle", "request")]),
+    ("MPI_OTHER_EVENTS", &[]),
+];
+
+/// Track name for the MPI calls of one thread
+pub fn mpi_track(rank: Option<i64>, tid: i32) -> String {
+    match rank {
+        Some(rank) => format!("MPI Rank {} Thread {}", rank, tid),
+        None => format!("MPI Thread {}", tid),
+    }
+}
+
+/// Communicator handles are opaque; show them the way MPI tools print them
+fn format_handle(handle: i64) -> String {
+    format!("0x{:x}", handle)
+}
+
+/// Size of a communicator and the process's rank within it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CommunicatorInfo {
+    local_rank: Option<i64>,
+    size: Option<i64>,
+}
+
+/// Parser for the nsys MPI tables
+pub struct MPIParser;
+
+impl MPIParser {
+    /// Global rank of each process, from MPI_RANKS
+    fn load_ranks(context: &ParseContext) -> Result<HashMap<i32, i64>> {
+        let mut ranks = HashMap::new();
+        if !table_exists(context.conn, "MPI_RANKS")? {
+            return Ok(ranks);
+        }
+        let mut stmt = context
+            .conn
+            .prepare("SELECT globalTid, rank FROM MPI_RANKS")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let global_tid: i64 = row.get(0)?;
+            let rank: i64 = row.get(1)?;
+            ranks.insert(decompose_global_tid(global_tid).0, rank);
+        }
+        Ok(ranks)
+    }
+
+    /// Communicators known to each process, from MPI_COMMUNICATORS
+    fn load_communicators(context: &ParseContext) -> Result<HashMap<(i32, i64), CommunicatorInfo>> {
+        let mut communicators = HashMap::new();
+        if !table_exists(context.conn, "MPI_COMMUNICATORS")? {
+            return Ok(communicators);
+        }
+        let columns = table_columns(context.conn, "MPI_COMMUNICATORS")?;
+        if !columns.contains("globalTid") || !columns.contains("commHandle") {
+            return Ok(communicators);
+        }
+        let optional = |name: &str| {
+            if columns.contains(name) {
+                name.to_string()
+            } else {
+                "NULL".to_string()
+            }
+        };
+        let query = format!(
+            "SELECT globalTid, commHandle, {}, {} FROM MPI_COMMUNICATORS",
+            optional("localRank"),
+            optional("size")
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let global_tid: i64 = row.get(0)?;
+            let handle: i64 = row.get(1)?;
+            let info = CommunicatorInfo {
+                local_rank: row.get(2)?,
+                size: row.get(3)?,
+            };
+            communicators.insert((decompose_global_tid(global_tid).0, handle), info);
+        }
+        Ok(communicators)
+    }
+}
+
+impl EventParser for MPIParser {
+    fn table_name(&self) -> &str {
+        "MPI_COLLECTIVES_EVENTS"
+    }
+
+    fn activity_type(&self) -> &str {
+        "mpi"
+    }
+
+    /// Every MPI table is optional, and `parse` skips the missing ones
+    fn safe_parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let _phase = phase("read MPI tables", "read");
+        self.parse(context)
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let mut events = Vec::new();
+        let ranks = Self::load_ranks(context)?;
+        let communicators = Self::load_communicators(context)?;
+
+        for &(table, extra_columns) in MPI_EVENT_TABLES {
+            if !table_exists(context.conn, table)? {
+                continue;
+            }
+            let columns = table_columns(context.conn, table)?;
+            let has_comm = columns.contains("commHandle");
+            let extras: Vec<(&str, &str)> = extra_columns
+                .iter()
+                .copied()
+                .filter(|(column, _)| columns.contains(*column))
+                .collect();
+
+            let mut select = vec!["start", "end", "globalTid", "textId"];
+            if has_comm {
+                select.push("commHandle");
+            }
+            select.extend(extras.iter().map(|(column, _)| *column));
+            let query = format!(
+                "SELECT {}{} FROM {}",
+                select.join(", "),
+                context.rowid_column(),
+                table
+            );
+
+            let mut stmt = context.conn.prepare(&query)?;
+            let idx_rowid = context.rowid_index(&stmt);
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let start: i64 = row.get(0)?;
+                let end: Option<i64> = row.get(1)?;
+                let global_tid: i64 = row.get(2)?;
+                let text_id: Option<i32> = row.get(3)?;
+                let comm_handle: Option<i64> = if has_comm { row.get(4)? } else { None };
+
+                let (pid, tid) = decompose_global_tid(global_tid);
+                let device_id = context.device_map.get(&pid).copied().unwrap_or(pid);
+                let rank = ranks.get(&pid).copied();
+
+                let api_name = text_id
+                    .and_then(|id| context.strings.get(&id))
+                    .map(|s| s.as_str())
+                    .unwrap_or("Unknown MPI API");
+
+                let mut args = HashMap::default();
+                args.insert("deviceId".to_string(), json!(device_id));
+                args.insert("raw_pid".to_string(), json!(pid));
+                args.insert("raw_tid".to_string(), json!(tid));
+                args.insert("start_ns".to_string(), json!(start));
+                if let Some(end) = end {
+                    args.insert("end_ns".to_string(), json!(end));
+                }
+                if let Some(rank) = rank {
+                    args.insert("rank".to_string(), json!(rank));
+                }
+                if let Some(handle) = comm_handle {
+                    args.insert("communicator".to_string(), json!(format_handle(handle)));
+                    if let Some(info) = communicators.get(&(pid, handle)) {
+                        if let Some(local_rank) = info.local_rank {
+                            args.insert("comm_rank".to_string(), json!(local_rank));
+                        }
+                        if let Some(size) = info.size {
+                            args.insert("comm_size".to_string(), json!(size));
+                        }
+                    }
+                }
+                let first_extra = if has_comm { 5 } else { 4 };
+                for (offset, (_, arg)) in extras.iter().enumerate() {
+                    if let Some(value) = row.get::<_, Option<i64>>(first_extra + offset)? {
+                        args.insert(arg.to_string(), json!(value));
+                    }
+                }
+
+                let mut event = ChromeTraceEvent::complete(
+                    api_name.to_string(),
+                    ns_to_us(start),
+                    ns_to_us(end.unwrap_or(start) - start),
+                    format!("Device {}", device_id),
+                    mpi_track(rank, tid),
+                    EventCategory::Mpi,
+                )
+                .with_args(args);
+                if end.is_none() {
+                    event.dur = None;
+                }
+                if let Some(idx) = idx_rowid {
+                    attach_source_row(&mut event, table, row.get(idx)?);
+                }
+
+                events.push(event);
+            }
+        }
+
+        events.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+        Ok(events)
+    }
+}
@@ -1,38 +1,296 @@
 //! NVTX event parser
 
-use anyhow::Result;
 use regex::Regex;
 use serde_json::json;
 use std::collections::HashMap;
 
+use crate::analysis::truncation::{end_ns, start_ns};
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
 use crate::mapping::decompose_global_tid;
-use crate::models::{ChromeTraceEvent, ns_to_us};
-use crate::parsers::base::{EventParser, ParseContext};
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase, InstantScope, NvtxNesting};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
+use crate::schema::table_columns;
 
 /// NVTX Push/Pop event type ID (corresponds to torch.cuda.nvtx.range APIs)
 const NVTX_PUSH_POP_EVENT_ID: i32 = 59;
 
+/// NVTX mark event type ID (nvtxMark, an instant marker)
+const NVTX_MARK_EVENT_ID: i32 = 34;
+
+/// Payload columns recorded by nsys for NVTX events, in order of preference
+const NVTX_PAYLOAD_COLUMNS: &[&str] = &[
+    "int64Value",
+    "uint64Value",
+    "doubleValue",
+    "int32Value",
+    "uint32Value",
+    "floatValue",
+];
+
+/// NVTX domain creation event type ID; `text` holds the domain name
+const NVTX_DOMAIN_CREATE_EVENT_ID: i32 = 75;
+
+/// Name of the domain ranges belong to when no domain was created for them
+pub const DEFAULT_NVTX_DOMAIN: &str = "default";
+
+/// NVTX range name filter built from `nvtx_event_prefix`
+///
+/// Each pattern is one of:
+/// - `re:PATTERN`: a regular expression matched anywhere in the name,
+/// - a glob when it contains `*`, `?` or `[`, matched against the whole name
+///   (`model/*` keeps `model/forward`),
+/// - otherwise a plain name prefix.
+///
+/// A name is kept if any pattern matches it. Domain prefixes added by
+/// `nvtx_domain_prefix` are not part of the matched name.
+#[derive(Debug, Clone, Default)]
+pub struct NvtxNameFilter {
+    prefixes: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl NvtxNameFilter {
+    /// Build a filter, or `None` if there are no patterns
+    pub fn new(patterns: &Option<Vec<String>>) -> Result<Option<Self>> {
+        let patterns = match patterns {
+            Some(patterns) if !patterns.is_empty() => patterns,
+            _ => return Ok(None),
+        };
+        let mut filter = Self::default();
+        for pattern in patterns {
+            if let Some(re) = pattern.strip_prefix("re:") {
+                filter.patterns.push(Self::compile(pattern, re)?);
+            } else if pattern.contains(['*', '?', '[']) {
+                filter.patterns.push(Self::compile(pattern, &glob_to_regex(pattern))?);
+            } else {
+                filter.prefixes.push(pattern.clone());
+            }
+        }
+        Ok(Some(filter))
+    }
+
+    fn compile(pattern: &str, re: &str) -> Result<Regex> {
+        Regex::new(re).map_err(|e| {
+            ConvertError::InvalidOption(format!("Invalid NVTX filter '{}': {}", pattern, e))
+        })
+    }
+
+    /// Whether a range with this name is kept
+    pub fn matches(&self, name: &str) -> bool {
+        self.prefixes.iter().any(|p| name.starts_with(p.as_str()))
+            || self.patterns.iter().any(|re| re.is_match(name))
+    }
+
+    /// Plain prefixes, if every pattern is one and can be pushed down to SQL
+    fn sql_prefixes(&self) -> Option<&[String]> {
+        self.patterns.is_empty().then_some(self.prefixes.as_slice())
+    }
+}
+
+/// Translate a glob into an anchored regex
+///
+/// `*` matches any run of characters (including `/`), `?` one character and
+/// `[...]` a character class, with `[!...]` negated.
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '[' => {
+                re.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    re.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        re.push('\\');
+                    }
+                    re.push(c);
+                }
+                re.push(']');
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// Resolve an event's text: prefer textId lookup, fallback to text column,
+/// then "[No name]" (like Python)
+fn resolve_name(context: &ParseContext, text: Option<String>, text_id: Option<i32>) -> String {
+    if let Some(tid) = text_id {
+        context
+            .strings
+            .get(&tid)
+            .cloned()
+            .unwrap_or_else(|| format!("[Unknown textId: {}]", tid))
+    } else {
+        text.unwrap_or_else(|| "[No name]".to_string())
+    }
+}
+
+/// Name of a domain; events outside created domains belong to the default domain
+fn resolve_domain(domains: &HashMap<i64, String>, domain_id: i64) -> String {
+    match domains.get(&domain_id) {
+        Some(name) => name.clone(),
+        None if domain_id == 0 => DEFAULT_NVTX_DOMAIN.to_string(),
+        None => format!("Domain {}", domain_id),
+    }
+}
+
+/// Whether `nvtx_domains` keeps a domain, given by name or ID
+fn domain_wanted(filter: Option<&Vec<String>>, domain: &str, domain_id: i64) -> bool {
+    filter.is_none_or(|wanted| {
+        let id = domain_id.to_string();
+        wanted.iter().any(|d| d == domain || *d == id)
+    })
+}
+
+/// Arg counting the nested ranges folded into a range by `nvtx_nesting`
+pub const COLLAPSED_RANGES_ARG: &str = "collapsed_ranges";
+
+/// Parse `keep`, `collapse` or `depth:N` into an NVTX nesting policy
+pub fn parse_nvtx_nesting(value: &str) -> Result<NvtxNesting> {
+    let invalid = || {
+        ConvertError::InvalidOption(format!(
+            "Invalid NVTX nesting '{}' (use keep, collapse or depth:N with N >= 1)",
+            value
+        ))
+    };
+    match value.trim() {
+        "keep" => Ok(NvtxNesting::Keep),
+        "collapse" => Ok(NvtxNesting::CollapseSameName),
+        other => match other.strip_prefix("depth:").map(str::parse::<usize>) {
+            Some(Ok(depth)) if depth > 0 => Ok(NvtxNesting::MaxDepth(depth)),
+            _ => Err(invalid()),
+        },
+    }
+}
+
+/// Fold nested NVTX ranges into an enclosing one, per `nesting`
+///
+/// Ranges nest by time on each (process, track) pair, as in
+/// [`crate::annotations::annotate_nesting_depth`]. With
+/// [`NvtxNesting::CollapseSameName`] a range nested in an open range of the
+/// same name (recursion) is removed and counted on the outermost instance;
+/// with [`NvtxNesting::MaxDepth`] a range nested that many levels deep is
+/// removed and counted on its deepest remaining ancestor. Counts go in the
+/// `collapsed_ranges` arg, including ranges folded into a folded range.
+/// Returns the number of ranges removed.
+pub fn collapse_nested_ranges(events: &mut Vec<ChromeTraceEvent>, nesting: NvtxNesting) -> usize {
+    if nesting == NvtxNesting::Keep {
+        return 0;
+    }
+    let mut per_track: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    for (idx, event) in events.iter().enumerate() {
+        if event.ph == ChromeTracePhase::Complete {
+            per_track
+                .entry((&event.pid, &event.tid))
+                .or_default()
+                .push(idx);
+        }
+    }
+
+    let mut collapsed = vec![0usize; events.len()];
+    let mut removed = vec![false; events.len()];
+    for mut indices in per_track.into_values() {
+        // Outer ranges first when two start together
+        indices.sort_by_key(|&idx| {
+            let event = &events[idx];
+            (start_ns(event), std::cmp::Reverse(end_ns(event)))
+        });
+        // Kept ranges still open: (end, index)
+        let mut open: Vec<(i64, usize)> = Vec::new();
+        for idx in indices {
+            let start = start_ns(&events[idx]);
+            let end = end_ns(&events[idx]).unwrap_or(start);
+            while open.last().is_some_and(|&(open_end, _)| open_end <= start) {
+                open.pop();
+            }
+            let target = match nesting {
+                NvtxNesting::Keep => None,
+                NvtxNesting::CollapseSameName => open
+                    .iter()
+                    .map(|&(_, outer)| outer)
+                    .find(|&outer| events[outer].name == events[idx].name),
+                NvtxNesting::MaxDepth(depth) if open.len() >= depth => {
+                    open.last().map(|&(_, outer)| outer)
+                }
+                NvtxNesting::MaxDepth(_) => None,
+            };
+            match target {
+                Some(outer) => {
+                    collapsed[outer] += 1;
+                    removed[idx] = true;
+                }
+                None => open.push((end, idx)),
+            }
+        }
+    }
+
+    for (event, &count) in events.iter_mut().zip(&collapsed) {
+        if count > 0 {
+            event
+                .args
+                .insert(COLLAPSED_RANGES_ARG.into(), json!(count));
+        }
+    }
+    let mut flags = removed.iter();
+    events.retain(|_| !flags.next().copied().unwrap_or(false));
+    removed.iter().filter(|&&r| r).count()
+}
+
 /// Parser for NVTX_EVENTS table
 pub struct NVTXParser;
 
 impl NVTXParser {
     /// Build SQL WHERE clause for event prefix filtering
-    fn build_filter_clause(event_prefix: &Option<Vec<String>>) -> String {
-        match event_prefix {
+    ///
+    /// Only plain prefixes are pushed down. Names stored by textId have no
+    /// `text` to compare, so those rows are kept and filtered after lookup.
+    fn build_filter_clause(filter: Option<&NvtxNameFilter>) -> String {
+        match filter.and_then(NvtxNameFilter::sql_prefixes) {
             None => String::new(),
-            Some(prefixes) if prefixes.is_empty() => String::new(),
-            Some(prefixes) if prefixes.len() == 1 => {
-                format!(" AND text LIKE '{}%'", prefixes[0])
-            }
             Some(prefixes) => {
                 let conditions: Vec<String> = prefixes
                     .iter()
-                    .map(|prefix| format!("text LIKE '{}%'", prefix))
+                    .map(|prefix| format!("text LIKE '{}%'", prefix.replace('\'', "''")))
                     .collect();
-                format!(" AND ({})", conditions.join(" OR "))
+                format!(" AND (textId IS NOT NULL OR {})", conditions.join(" OR "))
             }
         }
     }
+
+    /// Load domain names by domain ID from domain creation events
+    fn load_domains(context: &ParseContext, table: &str) -> Result<HashMap<i64, String>> {
+        let query = format!(
+            "SELECT domainId, text, textId FROM {} WHERE eventType = {}",
+            table, NVTX_DOMAIN_CREATE_EVENT_ID
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+
+        let mut domains = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let domain_id: Option<i64> = row.get(0)?;
+            let text: Option<String> = row.get(1)?;
+            let text_id: Option<i32> = row.get(2)?;
+            let name = text_id
+                .and_then(|id| context.strings.get(&id).cloned())
+                .or(text);
+            if let (Some(domain_id), Some(name)) = (domain_id, name) {
+                domains.insert(domain_id, name);
+            }
+        }
+        Ok(domains)
+    }
 }
 
 impl EventParser for NVTXParser {
@@ -40,6 +298,10 @@ impl EventParser for NVTXParser {
         "NVTX_EVENTS"
     }
 
+    fn activity_type(&self) -> &str {
+        "nvtx"
+    }
+
     fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
         let mut events = Vec::new();
 
@@ -55,17 +317,32 @@ impl EventParser for NVTXParser {
             })
             .collect();
 
-        // Build filter clause for prefix filtering (done in SQL like Python)
-        let filter_clause = Self::build_filter_clause(&context.options.nvtx_event_prefix);
+        // Prefix filters are done in SQL like Python; globs and regexes after name lookup
+        let name_filter = NvtxNameFilter::new(&context.options.nvtx_event_prefix)?;
+        let filter_clause = Self::build_filter_clause(name_filter.as_ref());
+
+        // Domains are only recorded by nsys versions with a domainId column
+        let table = self.resolve_table(context);
+        let has_domains = table_columns(context.conn, table)?.contains("domainId");
+        let domains = if has_domains {
+            Self::load_domains(context, table)?
+        } else {
+            HashMap::new()
+        };
+        let domain_column = if has_domains { "domainId" } else { "NULL" };
+        let domain_filter = context.options.nvtx_domains.as_ref();
 
         // Query with eventType filter (like Python) and optional prefix filter
         let query = format!(
-            "SELECT start, end, text, textId, globalTid, eventType FROM {} WHERE eventType = {}{}",
-            self.table_name(),
+            "SELECT start, end, text, textId, globalTid, eventType, {}{} FROM {} WHERE eventType = {}{}",
+            domain_column,
+            context.rowid_column(),
+            table,
             NVTX_PUSH_POP_EVENT_ID,
             filter_clause
         );
         let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
 
         let mut rows = stmt.query([])?;
         while let Some(row) = rows.next()? {
@@ -74,45 +351,62 @@ impl EventParser for NVTXParser {
             let text: Option<String> = row.get(2)?;
             let text_id: Option<i32> = row.get(3)?;
             let global_tid: i64 = row.get(4)?;
-
-            // Skip incomplete events (like Python)
-            let end_time = match end {
-                Some(e) => e,
-                None => continue,
-            };
+            let domain_id: i64 = row.get::<_, Option<i64>>(6)?.unwrap_or(0);
 
             let (pid, tid) = decompose_global_tid(global_tid);
             let device_id = context.device_map.get(&pid).copied().unwrap_or(pid);
 
-            // Resolve text: prefer textId lookup, fallback to text column, then "[No name]" (like Python)
-            let event_name = if let Some(tid) = text_id {
-                context
-                    .strings
-                    .get(&tid)
-                    .cloned()
-                    .unwrap_or_else(|| format!("[Unknown textId: {}]", tid))
-            } else if let Some(ref t) = text {
-                t.clone()
-            } else {
-                "[No name]".to_string()
-            };
+            let event_name = resolve_name(context, text, text_id);
+            if name_filter.as_ref().is_some_and(|f| !f.matches(&event_name)) {
+                continue;
+            }
+
+            let domain = resolve_domain(&domains, domain_id);
+            if !domain_wanted(domain_filter, &domain, domain_id) {
+                continue;
+            }
+            let is_default_domain = domain == DEFAULT_NVTX_DOMAIN;
 
             let mut args = HashMap::default();
             args.insert("deviceId".to_string(), json!(device_id));
             args.insert("raw_pid".to_string(), json!(pid));
             args.insert("raw_tid".to_string(), json!(tid));
             args.insert("start_ns".to_string(), json!(start));
-            args.insert("end_ns".to_string(), json!(end_time));
+            if let Some(end) = end {
+                args.insert("end_ns".to_string(), json!(end));
+            }
+            if has_domains {
+                args.insert("domain".to_string(), json!(domain));
+                args.insert("domainId".to_string(), json!(domain_id));
+            }
+
+            let name = if context.options.nvtx_domain_prefix && !is_default_domain {
+                format!("{}: {}", domain, event_name)
+            } else {
+                event_name.clone()
+            };
+            let track = if context.options.nvtx_domain_tracks && !is_default_domain {
+                format!("NVTX Thread {} [{}]", tid, domain)
+            } else {
+                format!("NVTX Thread {}", tid)
+            };
 
             let mut event = ChromeTraceEvent::complete(
-                event_name.clone(),
+                name,
                 ns_to_us(start),
-                ns_to_us(end_time - start),
+                ns_to_us(end.unwrap_or(start) - start),
                 format!("Device {}", device_id),
-                format!("NVTX Thread {}", tid),
-                "nvtx".to_string(),
+                track,
+                EventCategory::Nvtx,
             )
             .with_args(args);
+            // Ranges still open when the capture stopped are closed by the repair pass
+            if end.is_none() {
+                event.dur = None;
+            }
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, table, row.get(idx)?);
+            }
 
             // Apply color scheme if matches
             for (pattern, color) in &color_patterns {
@@ -129,3 +423,125 @@ impl EventParser for NVTXParser {
     }
 }
 
+
+/// Parser for NVTX marks in the NVTX_EVENTS table
+///
+/// Each `nvtxMark` becomes a thread-scoped instant event. Marks carrying a
+/// numeric payload also feed a counter track named after the mark, so
+/// progress markers emitted by frameworks (step counters, loss values) plot
+/// as series. Marks belong to the "nvtx" activity but are never linked to
+/// kernels.
+pub struct NvtxMarkParser;
+
+impl EventParser for NvtxMarkParser {
+    fn table_name(&self) -> &str {
+        "NVTX_EVENTS"
+    }
+
+    fn activity_type(&self) -> &str {
+        "nvtx"
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let mut events = Vec::new();
+
+        let name_filter = NvtxNameFilter::new(&context.options.nvtx_event_prefix)?;
+        let table = self.resolve_table(context);
+        let columns = table_columns(context.conn, table)?;
+        let has_domains = columns.contains("domainId");
+        let domains = if has_domains {
+            NVTXParser::load_domains(context, table)?
+        } else {
+            HashMap::new()
+        };
+        let domain_filter = context.options.nvtx_domains.as_ref();
+
+        // Older exports lack some payload columns; select NULL in their place
+        let payload_columns: Vec<&str> = NVTX_PAYLOAD_COLUMNS
+            .iter()
+            .map(|&c| if columns.contains(c) { c } else { "NULL" })
+            .collect();
+        let query = format!(
+            "SELECT start, text, textId, globalTid, {}, {}{} FROM {} WHERE eventType = {}",
+            if has_domains { "domainId" } else { "NULL" },
+            payload_columns.join(", "),
+            context.rowid_column(),
+            table,
+            NVTX_MARK_EVENT_ID
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
+
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let start: i64 = row.get(0)?;
+            let text: Option<String> = row.get(1)?;
+            let text_id: Option<i32> = row.get(2)?;
+            let global_tid: i64 = row.get(3)?;
+            let domain_id: i64 = row.get::<_, Option<i64>>(4)?.unwrap_or(0);
+            let mut payload = None;
+            for idx in 5..5 + NVTX_PAYLOAD_COLUMNS.len() {
+                if let Some(value) = row.get::<_, Option<f64>>(idx)? {
+                    payload = Some(value);
+                    break;
+                }
+            }
+
+            let name = resolve_name(context, text, text_id);
+            if name_filter.as_ref().is_some_and(|f| !f.matches(&name)) {
+                continue;
+            }
+            let domain = resolve_domain(&domains, domain_id);
+            if !domain_wanted(domain_filter, &domain, domain_id) {
+                continue;
+            }
+
+            let (pid, tid) = decompose_global_tid(global_tid);
+            let device_id = context.device_map.get(&pid).copied().unwrap_or(pid);
+            let process = format!("Device {}", device_id);
+            let track = format!("NVTX Thread {}", tid);
+
+            let mut mark = ChromeTraceEvent::new(
+                name.clone(),
+                ChromeTracePhase::Instant,
+                ns_to_us(start),
+                process.clone(),
+                track.clone(),
+                "nvtx-mark".to_string(),
+            )
+            .with_scope(InstantScope::Thread)
+            .with_arg("deviceId", device_id)
+            .with_arg("raw_pid", pid)
+            .with_arg("raw_tid", tid)
+            .with_arg("start_ns", start);
+            if has_domains {
+                mark = mark
+                    .with_arg("domain", domain)
+                    .with_arg("domainId", domain_id);
+            }
+            if let Some(value) = payload {
+                mark = mark.with_arg("payload", value);
+            }
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut mark, table, row.get(idx)?);
+            }
+            events.push(mark);
+
+            if let Some(value) = payload {
+                events.push(
+                    ChromeTraceEvent::new(
+                        name,
+                        ChromeTracePhase::Counter,
+                        ns_to_us(start),
+                        process,
+                        track,
+                        "nvtx-counter".to_string(),
+                    )
+                    .with_arg("value", value),
+                );
+            }
+        }
+
+        Ok(events)
+    }
+}
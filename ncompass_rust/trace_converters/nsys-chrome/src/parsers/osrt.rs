@@ -1,12 +1,13 @@
 //! OS Runtime API event parser
 
-use anyhow::Result;
 use serde_json::json;
 use std::collections::HashMap;
 
+use crate::category::EventCategory;
+use crate::error::Result;
 use crate::mapping::decompose_global_tid;
 use crate::models::{ChromeTraceEvent, ns_to_us};
-use crate::parsers::base::{EventParser, ParseContext};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
 
 /// Parser for OSRT_API table
 pub struct OSRTParser;
@@ -16,10 +17,17 @@ impl EventParser for OSRTParser {
         "OSRT_API"
     }
 
+    fn activity_type(&self) -> &str {
+        "osrt"
+    }
+
     fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
         let mut events = Vec::new();
 
-        let mut stmt = context.conn.prepare(&format!("SELECT * FROM {}", self.table_name()))?;
+        let table = self.resolve_table(context);
+        let mut stmt = context
+            .conn
+            .prepare(&format!("SELECT *{} FROM {}", context.rowid_column(), table))?;
         let column_names: Vec<String> = stmt
             .column_names()
             .iter()
@@ -27,6 +35,7 @@ impl EventParser for OSRTParser {
             .collect();
 
         // Find column indices
+        let idx_rowid = context.rowid_index(&stmt);
         let idx_start = column_names.iter().position(|n| n == "start").unwrap();
         let idx_end = column_names.iter().position(|n| n == "end").unwrap();
         let idx_global_tid = column_names.iter().position(|n| n == "globalTid").unwrap();
@@ -60,15 +69,18 @@ impl EventParser for OSRTParser {
             args.insert("start_ns".to_string(), json!(start));
             args.insert("end_ns".to_string(), json!(end));
 
-            let event = ChromeTraceEvent::complete(
+            let mut event = ChromeTraceEvent::complete(
                 api_name.to_string(),
                 ns_to_us(start),
                 ns_to_us(end - start),
                 format!("Process {}", pid),
                 thread_name,
-                "osrt".to_string(),
+                EventCategory::Osrt,
             )
             .with_args(args);
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, table, row.get(idx)?);
+            }
 
             events.push(event);
         }
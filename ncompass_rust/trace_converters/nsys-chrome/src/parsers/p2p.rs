@@ -0,0 +1,157 @@
+//! Peer-to-peer (NVLink) memcpy parser for multi-GPU captures
+//!
+//! nsys records every memcpy in CUPTI_ACTIVITY_KIND_MEMCPY. Transfers between
+//! two GPUs (copy kind PtoP, or any copy whose source and destination devices
+//! differ) are emitted on one `NVLink DeviceX→DeviceY` track per device pair,
+//! with the achieved bandwidth in args, plus an aggregate throughput counter
+//! summing all transfers in flight.
+
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::category::EventCategory;
+use crate::error::Result;
+use crate::models::{ns_to_us, ChromeTraceEvent, ChromeTracePhase};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
+
+/// Process grouping all peer-to-peer tracks
+pub const NVLINK_PROCESS: &str = "NVLink";
+
+/// Name of the aggregate throughput counter
+pub const NVLINK_COUNTER: &str = "NVLink Throughput";
+
+/// CUPTI_ACTIVITY_MEMCPY_KIND_PTOP
+const MEMCPY_KIND_PTOP: i64 = 10;
+
+/// Track name for transfers from one device to another
+pub fn nvlink_track(src_device: i64, dst_device: i64) -> String {
+    format!("NVLink Device{}→Device{}", src_device, dst_device)
+}
+
+/// Bandwidth in GB/s (bytes per nanosecond)
+fn bandwidth_gbps(bytes: i64, duration_ns: i64) -> f64 {
+    if duration_ns <= 0 {
+        return 0.0;
+    }
+    bytes as f64 / duration_ns as f64
+}
+
+fn round3(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// Parser for peer-to-peer copies in CUPTI_ACTIVITY_KIND_MEMCPY
+pub struct P2PParser;
+
+impl EventParser for P2PParser {
+    fn table_name(&self) -> &str {
+        "CUPTI_ACTIVITY_KIND_MEMCPY"
+    }
+
+    fn activity_type(&self) -> &str {
+        "nvlink"
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let mut events = Vec::new();
+
+        let table = self.resolve_table(context);
+        let query = format!(
+            "SELECT start, end, deviceId, streamId, correlationId, bytes, copyKind, \
+             srcDeviceId, dstDeviceId{} FROM {} \
+             WHERE copyKind = {} OR srcDeviceId != dstDeviceId",
+            context.rowid_column(),
+            table,
+            MEMCPY_KIND_PTOP
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let start: i64 = row.get(0)?;
+            let end: i64 = row.get(1)?;
+            let device_id: i64 = row.get(2)?;
+            let stream_id: i64 = row.get(3)?;
+            let correlation_id: i64 = row.get(4)?;
+            let bytes: i64 = row.get(5)?;
+            let copy_kind: i64 = row.get(6)?;
+            // Missing endpoints fall back to the device that issued the copy
+            let src_device: i64 = row.get::<_, Option<i64>>(7)?.unwrap_or(device_id);
+            let dst_device: i64 = row.get::<_, Option<i64>>(8)?.unwrap_or(device_id);
+
+            let mut args = HashMap::default();
+            args.insert("deviceId".to_string(), json!(device_id));
+            args.insert("streamId".to_string(), json!(stream_id));
+            args.insert("correlationId".to_string(), json!(correlation_id));
+            args.insert("start_ns".to_string(), json!(start));
+            args.insert("end_ns".to_string(), json!(end));
+            args.insert("srcDeviceId".to_string(), json!(src_device));
+            args.insert("dstDeviceId".to_string(), json!(dst_device));
+            args.insert("bytes".to_string(), json!(bytes));
+            args.insert("copyKind".to_string(), json!(copy_kind));
+            args.insert(
+                "bandwidth_gbps".to_string(),
+                json!(round3(bandwidth_gbps(bytes, end - start))),
+            );
+
+            let mut event = ChromeTraceEvent::complete(
+                "Memcpy PtoP".to_string(),
+                ns_to_us(start),
+                ns_to_us(end - start),
+                NVLINK_PROCESS.to_string(),
+                nvlink_track(src_device, dst_device),
+                EventCategory::Nvlink,
+            )
+            .with_args(args);
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, table, row.get(idx)?);
+            }
+            events.push(event);
+        }
+
+        let counter = nvlink_throughput_counter(&events);
+        events.extend(counter);
+        Ok(events)
+    }
+}
+
+/// Build counter events tracking the total bandwidth of transfers in flight
+///
+/// Each transfer contributes its average bandwidth between its start and end;
+/// one counter sample is emitted wherever the total changes.
+pub fn nvlink_throughput_counter(transfers: &[ChromeTraceEvent]) -> Vec<ChromeTraceEvent> {
+    // Bandwidth change at each timestamp (ns); BTreeMap keeps them in time order
+    let mut deltas: BTreeMap<i64, f64> = BTreeMap::new();
+    for transfer in transfers {
+        let start = transfer.args.get("start_ns").and_then(|v| v.as_i64());
+        let end = transfer.args.get("end_ns").and_then(|v| v.as_i64());
+        let bytes = transfer.args.get("bytes").and_then(|v| v.as_i64());
+        if let (Some(start), Some(end), Some(bytes)) = (start, end, bytes) {
+            if end <= start {
+                continue;
+            }
+            let bandwidth = bandwidth_gbps(bytes, end - start);
+            *deltas.entry(start).or_default() += bandwidth;
+            *deltas.entry(end).or_default() -= bandwidth;
+        }
+    }
+
+    let mut total = 0.0;
+    deltas
+        .into_iter()
+        .map(|(ts_ns, delta)| {
+            total += delta;
+            // Clamp float residue once every transfer has ended
+            let value = round3(total).max(0.0);
+            ChromeTraceEvent::new(
+                NVLINK_COUNTER.to_string(),
+                ChromeTracePhase::Counter,
+                ns_to_us(ts_ns),
+                NVLINK_PROCESS.to_string(),
+                String::new(),
+                EventCategory::Nvlink,
+            )
+            .with_arg("GB/s", json!(value))
+        })
+        .collect()
+}
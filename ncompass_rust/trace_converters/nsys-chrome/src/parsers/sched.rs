@@ -1,12 +1,13 @@
 //! Thread scheduling event parser
 
-use anyhow::Result;
 use serde_json::json;
 use std::collections::HashMap;
 
+use crate::category::EventCategory;
+use crate::error::Result;
 use crate::mapping::decompose_global_tid;
 use crate::models::{ChromeTraceEvent, ns_to_us};
-use crate::parsers::base::{EventParser, ParseContext};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
 
 /// Parser for SCHED_EVENTS table
 pub struct SchedParser;
@@ -16,14 +17,21 @@ impl EventParser for SchedParser {
         "SCHED_EVENTS"
     }
 
+    fn activity_type(&self) -> &str {
+        "sched"
+    }
+
     fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
         let mut events = Vec::new();
 
+        let table = self.resolve_table(context);
         let query = format!(
-            "SELECT start, cpu, isSchedIn, globalTid, threadState, threadBlock FROM {}",
-            self.table_name()
+            "SELECT start, cpu, isSchedIn, globalTid, threadState, threadBlock{} FROM {}",
+            context.rowid_column(),
+            table
         );
         let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
 
         let mut rows = stmt.query([])?;
         while let Some(row) = rows.next()? {
@@ -51,12 +59,12 @@ impl EventParser for SchedParser {
                 .unwrap_or_else(|| format!("Thread {}", tid));
 
             let mut args = HashMap::default();
-            args.insert("cpu".to_string(), json!(cpu));
+            args.insert("cpu".into(), json!(cpu));
             if let Some(ts) = thread_state {
-                args.insert("threadState".to_string(), json!(ts));
+                args.insert("threadState".into(), json!(ts));
             }
             if let Some(tb) = thread_block {
-                args.insert("threadBlock".to_string(), json!(tb));
+                args.insert("threadBlock".into(), json!(tb));
             }
 
             // Instant event (like Python uses ph="i")
@@ -66,9 +74,12 @@ impl EventParser for SchedParser {
                 ns_to_us(start),
                 format!("Process {}", pid),
                 thread_name,
-                "sched".to_string(),
+                EventCategory::Sched,
             );
             event.args = args;
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, table, row.get(idx)?);
+            }
 
             events.push(event);
         }
@@ -0,0 +1,272 @@
+//! CPU thread-state parser for Windows context switch data
+//!
+//! Windows captures record each context switch in `SCHED_EVENTS` with the
+//! thread's state (`KTHREAD_STATE`) and, for waits, its wait reason
+//! (`KWAIT_REASON`) at switch-out. Pairing every switch-out with the thread's
+//! next switch-in gives a timeline of when the thread ran, when it was
+//! preempted while still runnable, and what it was waiting for otherwise.
+//! Slices use the Chrome trace viewer's thread-state colors and sit on a
+//! `<thread> (state)` track next to the thread's other events, so a launcher
+//! thread losing its CPU shows up right above the GPU gap it caused.
+//!
+//! Linux captures leave the state columns empty; their off-CPU slices are
+//! named `Descheduled`.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::mapping::decompose_global_tid;
+use crate::models::{ns_to_us, ChromeTraceEvent};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
+
+/// Category (and activity type) of thread-state slices
+pub const THREAD_STATE_CATEGORY: &str = "thread-state";
+
+/// Track holding the state slices of a thread
+pub fn thread_state_track(thread_name: &str) -> String {
+    format!("{} (state)", thread_name)
+}
+
+/// What a thread was doing between two context switches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuThreadState {
+    /// On a CPU
+    Running,
+    /// Switched out while still runnable
+    Preempted,
+    /// Blocked on an object or request
+    Waiting,
+    /// Blocked on paging
+    Io,
+    /// Switched out for a reason the capture does not say
+    Unknown,
+}
+
+impl CpuThreadState {
+    /// Value of the `thread_state` arg
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CpuThreadState::Running => "running",
+            CpuThreadState::Preempted => "preempted",
+            CpuThreadState::Waiting => "waiting",
+            CpuThreadState::Io => "io",
+            CpuThreadState::Unknown => "unknown",
+        }
+    }
+
+    /// Reserved Chrome trace color name for the state
+    pub fn color(&self) -> &'static str {
+        match self {
+            CpuThreadState::Running => "thread_state_running",
+            CpuThreadState::Preempted => "thread_state_runnable",
+            CpuThreadState::Waiting => "thread_state_sleeping",
+            CpuThreadState::Io => "thread_state_iowait",
+            CpuThreadState::Unknown => "thread_state_unknown",
+        }
+    }
+}
+
+/// Name of a Windows thread state (KTHREAD_STATE)
+pub fn windows_thread_state_name(state: i32) -> String {
+    match state {
+        0 => "Initialized".to_string(),
+        1 => "Ready".to_string(),
+        2 => "Running".to_string(),
+        3 => "Standby".to_string(),
+        4 => "Terminated".to_string(),
+        5 => "Waiting".to_string(),
+        6 => "Transition".to_string(),
+        7 => "DeferredReady".to_string(),
+        other => format!("State {}", other),
+    }
+}
+
+/// Name of a Windows wait reason (KWAIT_REASON)
+pub fn windows_wait_reason_name(reason: i32) -> String {
+    const NAMES: &[&str] = &[
+        "Executive",
+        "FreePage",
+        "PageIn",
+        "PoolAllocation",
+        "DelayExecution",
+        "Suspended",
+        "UserRequest",
+        "WrExecutive",
+        "WrFreePage",
+        "WrPageIn",
+        "WrPoolAllocation",
+        "WrDelayExecution",
+        "WrSuspended",
+        "WrUserRequest",
+        "WrEventPair",
+        "WrQueue",
+        "WrLpcReceive",
+        "WrLpcReply",
+        "WrVirtualMemory",
+        "WrPageOut",
+        "WrRendezvous",
+        "WrKeyedEvent",
+        "WrTerminated",
+        "WrProcessInSwap",
+        "WrCpuRateControl",
+        "WrCalloutStack",
+        "WrKernel",
+        "WrResource",
+        "WrPushLock",
+        "WrMutex",
+        "WrQuantumEnd",
+        "WrDispatchInt",
+        "WrPreempted",
+        "WrYieldExecution",
+        "WrFastMutex",
+        "WrGuardedMutex",
+        "WrRundown",
+        "WrAlertByThreadId",
+        "WrDeferredPreempt",
+    ];
+    usize::try_from(reason)
+        .ok()
+        .and_then(|idx| NAMES.get(idx))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("Reason {}", reason))
+}
+
+/// Classify a switch-out by the thread state and wait reason it recorded
+pub fn classify_switch_out(thread_state: Option<i32>, wait_reason: Option<i32>) -> CpuThreadState {
+    match (thread_state, wait_reason) {
+        // Ready, Standby, DeferredReady: runnable but lost the CPU
+        (Some(1 | 3 | 7), _) => CpuThreadState::Preempted,
+        // WrQuantumEnd, WrPreempted, WrDeferredPreempt
+        (_, Some(30 | 32 | 38)) => CpuThreadState::Preempted,
+        // PageIn, WrPageIn, WrPageOut
+        (Some(5), Some(2 | 9 | 19)) => CpuThreadState::Io,
+        (Some(5), _) => CpuThreadState::Waiting,
+        _ => CpuThreadState::Unknown,
+    }
+}
+
+/// Name of an off-CPU slice
+fn switch_out_name(thread_state: Option<i32>, wait_reason: Option<i32>) -> String {
+    match (thread_state, wait_reason) {
+        (None, _) => "Descheduled".to_string(),
+        (Some(5), Some(reason)) => format!("Waiting ({})", windows_wait_reason_name(reason)),
+        (Some(state), _) => match classify_switch_out(thread_state, wait_reason) {
+            CpuThreadState::Preempted => "Preempted".to_string(),
+            _ => windows_thread_state_name(state),
+        },
+    }
+}
+
+/// A context switch of one thread
+struct Switch {
+    start: i64,
+    cpu: i32,
+    is_sched_in: bool,
+    thread_state: Option<i32>,
+    wait_reason: Option<i32>,
+    rowid: Option<i64>,
+}
+
+/// Parser turning SCHED_EVENTS context switches into thread-state slices
+pub struct ThreadStateParser;
+
+impl EventParser for ThreadStateParser {
+    fn table_name(&self) -> &str {
+        "SCHED_EVENTS"
+    }
+
+    fn activity_type(&self) -> &str {
+        THREAD_STATE_CATEGORY
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let table = self.resolve_table(context);
+        let query = format!(
+            "SELECT start, cpu, isSchedIn, globalTid, threadState, threadBlock{} FROM {} \
+             ORDER BY globalTid, start",
+            context.rowid_column(),
+            table
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
+
+        let mut per_thread: Vec<(i64, Vec<Switch>)> = Vec::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let global_tid: i64 = row.get(3)?;
+            let switch = Switch {
+                start: row.get(0)?,
+                cpu: row.get(1)?,
+                is_sched_in: row.get(2)?,
+                thread_state: row.get(4)?,
+                wait_reason: row.get(5)?,
+                rowid: idx_rowid.map(|idx| row.get(idx)).transpose()?,
+            };
+            match per_thread.last_mut() {
+                Some((tid, switches)) if *tid == global_tid => switches.push(switch),
+                _ => per_thread.push((global_tid, vec![switch])),
+            }
+        }
+
+        let mut events = Vec::new();
+        for (global_tid, switches) in per_thread {
+            let (pid, tid) = decompose_global_tid(global_tid);
+            let thread_name = context
+                .thread_names
+                .get(&tid)
+                .cloned()
+                .unwrap_or_else(|| format!("Thread {}", tid));
+            let track = thread_state_track(&thread_name);
+
+            // Each switch opens a slice that the thread's next switch closes
+            for pair in switches.windows(2) {
+                let (from, to) = (&pair[0], &pair[1]);
+                if to.start <= from.start {
+                    continue;
+                }
+                let (name, state) = if from.is_sched_in {
+                    ("Running".to_string(), CpuThreadState::Running)
+                } else {
+                    (
+                        switch_out_name(from.thread_state, from.wait_reason),
+                        classify_switch_out(from.thread_state, from.wait_reason),
+                    )
+                };
+
+                let mut args = HashMap::default();
+                args.insert("thread_state".to_string(), json!(state.as_str()));
+                args.insert("cpu".to_string(), json!(from.cpu));
+                args.insert("raw_pid".to_string(), json!(pid));
+                args.insert("raw_tid".to_string(), json!(tid));
+                args.insert("start_ns".to_string(), json!(from.start));
+                args.insert("end_ns".to_string(), json!(to.start));
+                if !from.is_sched_in {
+                    if let Some(reason) = from.wait_reason {
+                        args.insert(
+                            "wait_reason".to_string(),
+                            json!(windows_wait_reason_name(reason)),
+                        );
+                    }
+                }
+
+                let mut event = ChromeTraceEvent::complete(
+                    name,
+                    ns_to_us(from.start),
+                    ns_to_us(to.start - from.start),
+                    format!("Process {}", pid),
+                    track.clone(),
+                    THREAD_STATE_CATEGORY.to_string(),
+                )
+                .with_args(args)
+                .with_color(state.color().to_string());
+                if let Some(rowid) = from.rowid {
+                    attach_source_row(&mut event, table, rowid);
+                }
+                events.push(event);
+            }
+        }
+
+        Ok(events)
+    }
+}
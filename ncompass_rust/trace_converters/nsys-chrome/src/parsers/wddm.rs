@@ -0,0 +1,181 @@
+//! WDDM queue and DMA packet parser for Windows captures
+//!
+//! nsys records WDDM packets as separate start and stop tables. Queue packets
+//! cover the time a submission waits in the driver's software queue; DMA packets
+//! cover the time it runs on a GPU engine. Start and stop rows are paired by
+//! (gpu, context, submit sequence).
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::category::EventCategory;
+use crate::error::Result;
+use crate::mapping::decompose_global_tid;
+use crate::models::{ns_to_us, ChromeTraceEvent};
+use crate::parsers::base::{attach_source_row, EventParser, ParseContext};
+use crate::schema::table_exists;
+
+/// Queue packet stop events (paired with the resolved start table)
+pub const WDDM_QUEUE_STOP_TABLE: &str = "WDDM_QUEUE_PACKET_STOP_EVENTS";
+
+/// DMA packet start events
+pub const WDDM_DMA_START_TABLE: &str = "WDDM_DMA_PACKET_START_EVENTS";
+
+/// DMA packet stop events
+pub const WDDM_DMA_STOP_TABLE: &str = "WDDM_DMA_PACKET_STOP_EVENTS";
+
+/// Value of the `packet_kind` arg on queue packet events
+pub const WDDM_QUEUE_KIND: &str = "queue";
+
+/// Value of the `packet_kind` arg on DMA packet events
+pub const WDDM_DMA_KIND: &str = "dma";
+
+/// Name of a WDDM queue packet type (D3DKMT_QUEUEPACKET_TYPE)
+fn queue_packet_name(packet_type: i64) -> String {
+    match packet_type {
+        0 => "Render".to_string(),
+        1 => "Deferred".to_string(),
+        2 => "System".to_string(),
+        3 => "MMIO Flip".to_string(),
+        4 => "Wait".to_string(),
+        5 => "Signal".to_string(),
+        6 => "Device".to_string(),
+        7 => "Software".to_string(),
+        8 => "Paging".to_string(),
+        other => format!("Queue Packet {}", other),
+    }
+}
+
+/// Name of a WDDM DMA packet type (D3DKMT_DMAPACKET_TYPE)
+fn dma_packet_name(packet_type: i64) -> String {
+    match packet_type {
+        0 => "Client Render".to_string(),
+        1 => "Client Paging".to_string(),
+        2 => "System Paging".to_string(),
+        3 => "System Preemption".to_string(),
+        other => format!("DMA Packet {}", other),
+    }
+}
+
+/// Start/stop table pair describing one WDDM packet kind
+struct PacketSource<'a> {
+    start_table: &'a str,
+    stop_table: &'a str,
+    /// Column pairing start and stop rows within a (gpu, context)
+    sequence_column: &'a str,
+    kind: &'a str,
+    track_prefix: &'a str,
+    name_for: fn(i64) -> String,
+}
+
+/// Parser for WDDM_QUEUE_PACKET_* and WDDM_DMA_PACKET_* tables
+pub struct WDDMParser;
+
+impl WDDMParser {
+    /// Pair start/stop rows of one packet kind and build events
+    fn parse_packets(
+        &self,
+        context: &ParseContext,
+        source: &PacketSource,
+    ) -> Result<Vec<ChromeTraceEvent>> {
+        let mut events = Vec::new();
+
+        let query = format!(
+            "SELECT s.start, e.start, s.gpu, s.context, s.{seq}, s.packetType, s.globalTid{rowid} \
+             FROM {start} s JOIN {stop} e \
+             ON s.gpu = e.gpu AND s.context = e.context AND s.{seq} = e.{seq} \
+             WHERE e.start >= s.start",
+            seq = source.sequence_column,
+            start = source.start_table,
+            stop = source.stop_table,
+            // Start rows identify packets; stop rows are found by the join
+            rowid = if context.options.source_rows { ", s.rowid" } else { "" },
+        );
+        let mut stmt = context.conn.prepare(&query)?;
+        let idx_rowid = context.rowid_index(&stmt);
+        let mut rows = stmt.query([])?;
+
+        while let Some(row) = rows.next()? {
+            let start: i64 = row.get(0)?;
+            let end: i64 = row.get(1)?;
+            let gpu: i64 = row.get(2)?;
+            let gpu_context: i64 = row.get(3)?;
+            let sequence: i64 = row.get(4)?;
+            let packet_type: i64 = row.get(5)?;
+            let global_tid: Option<i64> = row.get(6)?;
+
+            let mut args = HashMap::default();
+            args.insert("gpu".to_string(), json!(gpu));
+            args.insert("context".to_string(), json!(format!("{:#x}", gpu_context)));
+            args.insert("submitSequence".to_string(), json!(sequence));
+            args.insert("packetType".to_string(), json!(packet_type));
+            args.insert("packet_kind".to_string(), json!(source.kind));
+            args.insert("start_ns".to_string(), json!(start));
+            args.insert("end_ns".to_string(), json!(end));
+            if let Some(global_tid) = global_tid {
+                let (pid, tid) = decompose_global_tid(global_tid);
+                args.insert("raw_pid".to_string(), json!(pid));
+                args.insert("raw_tid".to_string(), json!(tid));
+            }
+
+            let mut event = ChromeTraceEvent::complete(
+                (source.name_for)(packet_type),
+                ns_to_us(start),
+                ns_to_us(end - start),
+                format!("WDDM GPU {}", gpu),
+                format!("{} {:#x}", source.track_prefix, gpu_context),
+                EventCategory::Wddm,
+            )
+            .with_args(args);
+            if let Some(idx) = idx_rowid {
+                attach_source_row(&mut event, source.start_table, row.get(idx)?);
+            }
+
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+}
+
+impl EventParser for WDDMParser {
+    fn table_name(&self) -> &str {
+        "WDDM_QUEUE_PACKET_START_EVENTS"
+    }
+
+    fn activity_type(&self) -> &str {
+        "wddm"
+    }
+
+    fn parse(&self, context: &ParseContext) -> Result<Vec<ChromeTraceEvent>> {
+        let mut events = Vec::new();
+
+        if table_exists(context.conn, WDDM_QUEUE_STOP_TABLE)? {
+            let source = PacketSource {
+                start_table: self.resolve_table(context),
+                stop_table: WDDM_QUEUE_STOP_TABLE,
+                sequence_column: "submitSequence",
+                kind: WDDM_QUEUE_KIND,
+                track_prefix: "Queue Context",
+                name_for: queue_packet_name,
+            };
+            events.extend(self.parse_packets(context, &source)?);
+        }
+
+        if table_exists(context.conn, WDDM_DMA_START_TABLE)?
+            && table_exists(context.conn, WDDM_DMA_STOP_TABLE)?
+        {
+            let source = PacketSource {
+                start_table: WDDM_DMA_START_TABLE,
+                stop_table: WDDM_DMA_STOP_TABLE,
+                sequence_column: "ulQueueSubmitSequence",
+                kind: WDDM_DMA_KIND,
+                track_prefix: "DMA Context",
+                name_for: dma_packet_name,
+            };
+            events.extend(self.parse_packets(context, &source)?);
+        }
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,296 @@
+//! Writer thread fed through a bounded channel
+//!
+//! Serializing and gzip-compressing a large trace is slower than producing its
+//! events. The pipeline runs the writer on its own thread and feeds it through
+//! a bounded channel of event batches: producers block once `capacity` batches
+//! are queued, so a producer can run at most `capacity * batch_size` events
+//! ahead of the writer.
+//!
+//! This bounds memory only for producers that generate events incrementally.
+//! One conversion links and sorts its whole trace before writing, but the CLI
+//! streams `--merge-rank` captures: each rank is sent to the writer and dropped
+//! before the next is converted, so one rank is resident rather than all.
+//!
+//! Time spent blocked on either side is recorded in [`PipelineMetrics`]. Long
+//! producer stalls mean the writer is the bottleneck; long writer waits mean a
+//! larger capacity will not help.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::{ConvertError, Result};
+use crate::models::ChromeTraceEvent;
+use crate::writer::{ChromeTraceWriter, WriteOptions, WriteOutput};
+
+/// Default number of batches that may wait for the writer
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 64;
+
+/// Default number of events sent per batch
+pub const DEFAULT_BATCH_SIZE: usize = 4096;
+
+/// Channel sizing
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineConfig {
+    /// Batches queued before producers block
+    pub capacity: usize,
+    /// Events per batch
+    pub batch_size: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CHANNEL_CAPACITY,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+/// What happened on the channel, for tuning its capacity
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineMetrics {
+    /// Events written
+    pub events: u64,
+    /// Batches passed through the channel
+    pub batches: u64,
+    /// Sends that found the channel full
+    pub stalled_sends: u64,
+    /// Total time producers were blocked on a full channel
+    pub producer_stall: Duration,
+    /// Total time the writer waited on an empty channel
+    pub writer_wait: Duration,
+    /// Most batches queued at once
+    pub peak_queued: usize,
+}
+
+impl PipelineMetrics {
+    /// One-line summary for progress output
+    pub fn summary(&self, capacity: usize) -> String {
+        format!(
+            "{} events in {} batches; producers stalled {:.3}s ({} sends), \
+             writer waited {:.3}s; peak queue {}/{}",
+            self.events,
+            self.batches,
+            self.producer_stall.as_secs_f64(),
+            self.stalled_sends,
+            self.writer_wait.as_secs_f64(),
+            self.peak_queued,
+            capacity
+        )
+    }
+}
+
+/// Counters shared by producers and the writer thread
+#[derive(Default)]
+struct Counters {
+    events: AtomicU64,
+    batches: AtomicU64,
+    stalled_sends: AtomicU64,
+    producer_stall_ns: AtomicU64,
+    writer_wait_ns: AtomicU64,
+    queued: AtomicUsize,
+    peak_queued: AtomicUsize,
+}
+
+impl Counters {
+    fn snapshot(&self) -> PipelineMetrics {
+        PipelineMetrics {
+            events: self.events.load(Ordering::Relaxed),
+            batches: self.batches.load(Ordering::Relaxed),
+            stalled_sends: self.stalled_sends.load(Ordering::Relaxed),
+            producer_stall: Duration::from_nanos(self.producer_stall_ns.load(Ordering::Relaxed)),
+            writer_wait: Duration::from_nanos(self.writer_wait_ns.load(Ordering::Relaxed)),
+            peak_queued: self.peak_queued.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn elapsed_ns(start: Instant) -> u64 {
+    start.elapsed().as_nanos() as u64
+}
+
+/// Producer side of the pipeline
+///
+/// Clone it to feed the writer from several threads; each clone batches its
+/// own events, so ordering is only preserved within one sender. Remaining
+/// events are flushed when a sender is dropped.
+pub struct EventSender {
+    sender: SyncSender<Vec<ChromeTraceEvent>>,
+    batch: Vec<ChromeTraceEvent>,
+    batch_size: usize,
+    capacity: usize,
+    counters: Arc<Counters>,
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            batch: Vec::with_capacity(self.batch_size),
+            batch_size: self.batch_size,
+            capacity: self.capacity,
+            counters: Arc::clone(&self.counters),
+        }
+    }
+}
+
+impl EventSender {
+    /// Queue one event, blocking if the writer is `capacity` batches behind
+    pub fn send(&mut self, event: ChromeTraceEvent) -> Result<()> {
+        self.batch.push(event);
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Queue every event of an iterator
+    pub fn send_all(&mut self, events: impl IntoIterator<Item = ChromeTraceEvent>) -> Result<()> {
+        for event in events {
+            self.send(event)?;
+        }
+        Ok(())
+    }
+
+    /// Hand the current partial batch to the writer
+    pub fn flush(&mut self) -> Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(self.batch_size));
+
+        // Count the batch before sending so the writer never decrements past zero;
+        // a batch blocked on a full channel is not queued yet
+        let queued = self.counters.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        self.counters
+            .peak_queued
+            .fetch_max(queued.min(self.capacity), Ordering::Relaxed);
+
+        let sent = match self.sender.try_send(batch) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(batch)) => {
+                let start = Instant::now();
+                let sent = self.sender.send(batch).map_err(|_| ());
+                self.counters.stalled_sends.fetch_add(1, Ordering::Relaxed);
+                self.counters
+                    .producer_stall_ns
+                    .fetch_add(elapsed_ns(start), Ordering::Relaxed);
+                sent
+            }
+            Err(TrySendError::Disconnected(_)) => Err(()),
+        };
+        sent.map_err(|_| {
+            // The writer thread has exited; its own error is reported by `finish`
+            ConvertError::Output(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "Trace writer stopped",
+            ))
+        })
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Iterator over received events that records time spent waiting
+struct ReceivedEvents {
+    receiver: Receiver<Vec<ChromeTraceEvent>>,
+    current: std::vec::IntoIter<ChromeTraceEvent>,
+    counters: Arc<Counters>,
+}
+
+impl Iterator for ReceivedEvents {
+    type Item = ChromeTraceEvent;
+
+    fn next(&mut self) -> Option<ChromeTraceEvent> {
+        loop {
+            if let Some(event) = self.current.next() {
+                self.counters.events.fetch_add(1, Ordering::Relaxed);
+                return Some(event);
+            }
+            let start = Instant::now();
+            let batch = self.receiver.recv().ok()?;
+            self.counters
+                .writer_wait_ns
+                .fetch_add(elapsed_ns(start), Ordering::Relaxed);
+            self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+            self.counters.batches.fetch_add(1, Ordering::Relaxed);
+            self.current = batch.into_iter();
+        }
+    }
+}
+
+/// Writer thread of a running pipeline
+pub struct PipelineWriter {
+    handle: JoinHandle<Result<WriteOutput>>,
+    counters: Arc<Counters>,
+}
+
+impl PipelineWriter {
+    /// Wait for the writer to finish and collect its output and metrics
+    ///
+    /// Every [`EventSender`] must be dropped first, or this waits forever.
+    pub fn finish(self) -> Result<(WriteOutput, PipelineMetrics)> {
+        let output = self.handle.join().map_err(|_| {
+            ConvertError::Output(io::Error::other("Trace writer thread panicked"))
+        })??;
+        Ok((output, self.counters.snapshot()))
+    }
+}
+
+/// Start a writer thread fed through a bounded channel
+///
+/// Events are written in the order they are received, as by
+/// [`ChromeTraceWriter::write_to`]; the trace outline requires them sorted.
+pub fn spawn_writer<W: Write + Send + 'static>(
+    output: W,
+    write_options: WriteOptions,
+    config: PipelineConfig,
+) -> (EventSender, PipelineWriter) {
+    let capacity = config.capacity.max(1);
+    let (sender, receiver) = mpsc::sync_channel(capacity);
+    let batch_size = config.batch_size.max(1);
+    let counters = Arc::new(Counters::default());
+
+    let events = ReceivedEvents {
+        receiver,
+        current: Vec::new().into_iter(),
+        counters: Arc::clone(&counters),
+    };
+    let handle = thread::spawn(move || ChromeTraceWriter::write_to(output, events, write_options));
+
+    let sender = EventSender {
+        sender,
+        batch: Vec::with_capacity(batch_size),
+        batch_size,
+        capacity,
+        counters: Arc::clone(&counters),
+    };
+    (sender, PipelineWriter { handle, counters })
+}
+
+/// Write events through a pipeline, returning the writer's output and metrics
+pub fn write_pipelined<W: Write + Send + 'static>(
+    output: W,
+    events: impl IntoIterator<Item = ChromeTraceEvent>,
+    write_options: WriteOptions,
+    config: PipelineConfig,
+) -> Result<(WriteOutput, PipelineMetrics)> {
+    let (mut sender, writer) = spawn_writer(output, write_options, config);
+    let sent = sender.send_all(events).and_then(|_| sender.flush());
+    drop(sender);
+    let finished = writer.finish();
+    // A writer error explains a failed send better than the send itself
+    match (sent, finished) {
+        (_, Err(e)) => Err(e),
+        (Err(e), Ok(_)) => Err(e),
+        (Ok(()), Ok(result)) => Ok(result),
+    }
+}
@@ -0,0 +1,318 @@
+//! Named option bundles and JSON option overlays
+//!
+//! A [`Preset`] selects the activity types, NVTX colors and analyses suited
+//! to one use case. Options can then be overlaid from a JSON object whose
+//! keys mirror the CLI flags, read from a config file or passed through the
+//! C ABI:
+//!
+//! ```json
+//! {"preset": "training", "activity_types": ["kernel", "nvtx", "nvtx-kernel"],
+//!  "nvtx_colors": {"^loss": "bad"}, "min_duration": "2us"}
+//! ```
+//!
+//! A `preset` key picks the base before the other keys are applied; unknown
+//! keys are rejected so a typo does not silently fall back to a default.
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::analysis::{
+    parse_duration_ns, parse_missing_stream_policy, parse_outlier_factor, parse_time_origin,
+    parse_time_window,
+};
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::linker::{
+    parse_flow_bind, parse_flow_links, parse_flow_style, parse_link_policy,
+    parse_nvtx_kernel_layout, parse_time_shift,
+};
+use crate::models::{ConversionOptions, FlowOptions, LinkPolicy, OutputRoute, TimeShift};
+use crate::parsers::nvtx::parse_nvtx_nesting;
+use crate::tid_allocator::parse_tid_grouping;
+
+/// Option bundle for one use case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Training loops: steps, forward/backward/optimizer colors, API thread states
+    Training,
+    /// Serving: copies, inferred layers and cost estimates, prefill/decode colors
+    Inference,
+    /// Collectives: NVLink, copies and MPI, NVTX domains on their own tracks
+    CommDebug,
+}
+
+impl Preset {
+    /// All presets, in the order they are listed
+    pub const ALL: [Preset; 3] = [Preset::Training, Preset::Inference, Preset::CommDebug];
+
+    /// Name used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Training => "training",
+            Preset::Inference => "inference",
+            Preset::CommDebug => "comm-debug",
+        }
+    }
+
+    /// Conversion options of the preset, starting from the defaults
+    pub fn options(&self) -> ConversionOptions {
+        let defaults = ConversionOptions::default();
+        let types = |types: &[&str]| types.iter().map(|&t| EventCategory::from(t)).collect();
+        let colors = |colors: &[(&str, &str)]| {
+            colors
+                .iter()
+                .map(|(pattern, color)| (pattern.to_string(), color.to_string()))
+                .collect()
+        };
+        match self {
+            Preset::Training => ConversionOptions {
+                nvtx_color_scheme: colors(&[
+                    ("(?i)^(forward|fwd)", "rail_response"),
+                    ("(?i)^(backward|bwd)", "rail_animation"),
+                    ("(?i)^(optimizer|optim)", "rail_load"),
+                ]),
+                synthesize_steps: true,
+                api_thread_states: true,
+                ..defaults
+            },
+            Preset::Inference => ConversionOptions {
+                activity_types: types(&["kernel", "nvtx", "nvtx-kernel", "cuda-api", "memcpy"]),
+                nvtx_color_scheme: colors(&[
+                    ("(?i)prefill", "rail_load"),
+                    ("(?i)decode", "rail_response"),
+                ]),
+                infer_layers: true,
+                estimate_kernel_costs: true,
+                link_policy: LinkPolicy::Innermost,
+                ..defaults
+            },
+            Preset::CommDebug => ConversionOptions {
+                activity_types: types(&[
+                    "kernel",
+                    "nvtx",
+                    "nvtx-kernel",
+                    "cuda-api",
+                    "osrt",
+                    "nvlink",
+                    "memcpy",
+                    "mpi",
+                ]),
+                nvtx_color_scheme: colors(&[(
+                    "(?i)nccl|allreduce|allgather|reducescatter|broadcast",
+                    "bad",
+                )]),
+                nvtx_domain_prefix: true,
+                nvtx_domain_tracks: true,
+                api_thread_states: true,
+                ..defaults
+            },
+        }
+    }
+}
+
+/// Parse `training`, `inference` or `comm-debug`
+pub fn parse_preset(value: &str) -> Result<Preset> {
+    Preset::ALL
+        .into_iter()
+        .find(|preset| preset.name() == value.trim())
+        .ok_or_else(|| {
+            ConvertError::InvalidOption(format!(
+                "Unknown preset '{}' (use training, inference or comm-debug)",
+                value
+            ))
+        })
+}
+
+fn expect_bool(key: &str, value: &Value) -> Result<bool> {
+    value
+        .as_bool()
+        .ok_or_else(|| ConvertError::InvalidOption(format!("Option '{}' must be a boolean", key)))
+}
+
+fn expect_usize(key: &str, value: &Value) -> Result<usize> {
+    value.as_u64().map(|n| n as usize).ok_or_else(|| {
+        ConvertError::InvalidOption(format!("Option '{}' must be a non-negative integer", key))
+    })
+}
+
+fn expect_factor(key: &str, value: &Value) -> Result<f64> {
+    let factor = value
+        .as_f64()
+        .ok_or_else(|| ConvertError::InvalidOption(format!("Option '{}' must be a number", key)))?;
+    parse_outlier_factor(&factor.to_string())
+}
+
+fn expect_str<'a>(key: &str, value: &'a Value) -> Result<&'a str> {
+    value
+        .as_str()
+        .ok_or_else(|| ConvertError::InvalidOption(format!("Option '{}' must be a string", key)))
+}
+
+fn expect_strings(key: &str, value: &Value) -> Result<Vec<String>> {
+    let invalid =
+        || ConvertError::InvalidOption(format!("Option '{}' must be an array of strings", key));
+    value
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|item| item.as_str().map(str::to_string).ok_or_else(invalid))
+        .collect()
+}
+
+fn expect_string_map(key: &str, value: &Value) -> Result<HashMap<String, String>> {
+    let invalid =
+        || ConvertError::InvalidOption(format!("Option '{}' must map patterns to colors", key));
+    value
+        .as_object()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), v.as_str().ok_or_else(invalid)?.to_string())))
+        .collect()
+}
+
+/// Set flow bindings from `{"LINK": "BIND"}`, where `all` sorts before the single links
+fn set_flow_binds(flows: &mut FlowOptions, key: &str, value: &Value) -> Result<()> {
+    let invalid =
+        || ConvertError::InvalidOption(format!("Option '{}' must map links to bindings", key));
+    for (links, bind) in value.as_object().ok_or_else(invalid)? {
+        let bind = parse_flow_bind(bind.as_str().ok_or_else(invalid)?)?;
+        for link in parse_flow_links(links)? {
+            flows.set_bind(link, bind);
+        }
+    }
+    Ok(())
+}
+
+/// Read `{"KEY": "SHIFT"}` annotation time shifts, e.g. `{"nvtx": "-20us"}`
+fn expect_time_shifts(key: &str, value: &Value) -> Result<HashMap<String, TimeShift>> {
+    let invalid =
+        || ConvertError::InvalidOption(format!("Option '{}' must map keys to shifts", key));
+    value
+        .as_object()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), parse_time_shift(v.as_str().ok_or_else(invalid)?)?)))
+        .collect()
+}
+
+/// Apply the keys of a JSON object to `options`
+///
+/// `nvtx_colors` entries are added to the color scheme and `flow_bind`
+/// entries (`{"launch": "next"}`) to the flow bindings; every other key
+/// replaces its option.
+pub fn apply_overlay(
+    mut options: ConversionOptions,
+    map: &Map<String, Value>,
+) -> Result<ConversionOptions> {
+    for (key, value) in map {
+        let key = key.as_str();
+        match key {
+            "preset" => {}
+            "activity_types" => {
+                options.activity_types = expect_strings(key, value)?
+                    .iter()
+                    .map(|t| EventCategory::parse(t))
+                    .collect::<Result<_>>()?
+            }
+            "nvtx_prefix" => options.nvtx_event_prefix = Some(expect_strings(key, value)?),
+            "nvtx_colors" => options
+                .nvtx_color_scheme
+                .extend(expect_string_map(key, value)?),
+            "nvtx_domains" => options.nvtx_domains = Some(expect_strings(key, value)?),
+            "nvtx_domain_prefix" => options.nvtx_domain_prefix = expect_bool(key, value)?,
+            "nvtx_domain_tracks" => options.nvtx_domain_tracks = expect_bool(key, value)?,
+            "nvtx_nesting" => options.nvtx_nesting = parse_nvtx_nesting(expect_str(key, value)?)?,
+            "include_metadata" => options.include_metadata = expect_bool(key, value)?,
+            "source_frames" => options.source_frame_depth = expect_usize(key, value)?,
+            "api_call_stacks" => options.api_call_stacks = expect_bool(key, value)?,
+            "synthesize_steps" => options.synthesize_steps = expect_bool(key, value)?,
+            "infer_layers" => options.infer_layers = expect_bool(key, value)?,
+            "kernel_concurrency" => options.kernel_concurrency = expect_bool(key, value)?,
+            "transfer_throughput" => options.transfer_throughput = expect_bool(key, value)?,
+            "estimate_costs" => options.estimate_kernel_costs = expect_bool(key, value)?,
+            "min_duration" => {
+                options.min_kernel_duration_ns = parse_duration_ns(expect_str(key, value)?)?
+            }
+            "api_thread_states" => options.api_thread_states = expect_bool(key, value)?,
+            "outlier_factor" => options.kernel_outlier_factor = Some(expect_factor(key, value)?),
+            "time_origin" => options.time_origin = parse_time_origin(expect_str(key, value)?)?,
+            "time_window" => {
+                options.time_window = Some(parse_time_window(expect_str(key, value)?)?)
+            }
+            "jobs" => options.jobs = expect_usize(key, value)?,
+            "source_rows" => options.source_rows = expect_bool(key, value)?,
+            "max_link_gap" => {
+                options.max_link_gap_ns = Some(parse_duration_ns(expect_str(key, value)?)?)
+            }
+            "link_window" => {
+                options.link_window_ns = Some(parse_duration_ns(expect_str(key, value)?)?)
+            }
+            "sticky_lookback" => {
+                options.sticky_lookback_ns = Some(parse_duration_ns(expect_str(key, value)?)?)
+            }
+            "tag_unattributed" => options.tag_unattributed = expect_bool(key, value)?,
+            "unattributed_work" => options.unattributed_work = expect_bool(key, value)?,
+            "annotations_only" => options.annotations_only = expect_bool(key, value)?,
+            "nvtx_kernel_per_stream" => options.nvtx_kernel_per_stream = expect_bool(key, value)?,
+            "nvtx_kernel_layout" => {
+                options.nvtx_kernel_layout = parse_nvtx_kernel_layout(expect_str(key, value)?)?
+            }
+            "missing_stream" => {
+                options.missing_stream_policy =
+                    parse_missing_stream_policy(expect_str(key, value)?)?
+            }
+            "virtual_tids" => {
+                options.virtual_tids = Some(parse_tid_grouping(expect_str(key, value)?)?)
+            }
+            "link_policy" => options.link_policy = parse_link_policy(expect_str(key, value)?)?,
+            "flow_style" => options.flows.style = parse_flow_style(expect_str(key, value)?)?,
+            "flow_bind" => set_flow_binds(&mut options.flows, key, value)?,
+            "time_shifts" => options.annotation_time_shifts = expect_time_shifts(key, value)?,
+            "routes" => {
+                options.output_routes = expect_strings(key, value)?
+                    .iter()
+                    .map(|spec| OutputRoute::parse(spec))
+                    .collect::<Result<_>>()?
+            }
+            _ => {
+                return Err(ConvertError::InvalidOption(format!(
+                    "Unknown option '{}'",
+                    key
+                )))
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Build options from JSON text, starting from its `preset` or `base`
+///
+/// Empty text returns `base` unchanged.
+pub fn overlay_options(base: ConversionOptions, text: &str) -> Result<ConversionOptions> {
+    if text.trim().is_empty() {
+        return Ok(base);
+    }
+    let root: Value = serde_json::from_str(text)
+        .map_err(|e| ConvertError::InvalidOption(format!("Options are not valid JSON: {}", e)))?;
+    let Value::Object(map) = root else {
+        return Err(ConvertError::InvalidOption(
+            "Options must be a JSON object".to_string(),
+        ));
+    };
+    let base = match map.get("preset") {
+        Some(name) => parse_preset(expect_str("preset", name)?)?.options(),
+        None => base,
+    };
+    apply_overlay(base, &map)
+}
+
+/// Overlay options from a JSON config file
+pub fn load_overlay(base: ConversionOptions, path: impl AsRef<Path>) -> Result<ConversionOptions> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(|e| ConvertError::InputRead {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+    overlay_options(base, &text)
+}
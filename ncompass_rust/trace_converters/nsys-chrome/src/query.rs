@@ -0,0 +1,287 @@
+//! SQL queries over converted traces
+//!
+//! Loads the events of a converted trace, or events converted in memory,
+//! into an in-memory SQLite database so they can be summarized with SQL
+//! without leaving the tool:
+//!
+//! ```sql
+//! SELECT name, SUM(dur) FROM slices WHERE cat = 'kernel'
+//! GROUP BY name ORDER BY 2 DESC LIMIT 20
+//! ```
+//!
+//! `events` holds every event; `slices` is the view of complete (`X`)
+//! events. Times are in microseconds, as in the trace, and `args` is JSON
+//! text readable with `json_extract(args, '$.correlationId')`.
+
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use crate::browser::read_trace_json;
+use crate::error::{ConvertError, Result};
+use crate::models::ChromeTraceEvent;
+use crate::routing::csv_field;
+
+/// Table holding every event
+pub const EVENTS_TABLE: &str = "events";
+
+/// View of complete events
+pub const SLICES_VIEW: &str = "slices";
+
+const SCHEMA_SQL: &str = "
+    CREATE TABLE events (
+        id INTEGER PRIMARY KEY,
+        name TEXT,
+        cat TEXT,
+        ph TEXT,
+        ts REAL,
+        dur REAL,
+        pid TEXT,
+        tid TEXT,
+        args TEXT
+    );
+    CREATE VIEW slices AS
+        SELECT id, name, cat, ts, dur, pid, tid, args FROM events WHERE ph = 'X';
+";
+
+/// Rows and column names returned by a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl QueryResult {
+    /// Aligned text table, one row per line
+    pub fn render_table(&self) -> String {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(cell_text).collect())
+            .collect();
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| c.chars().count()).collect();
+        for row in &cells {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let line = |values: &[String]| -> String {
+            let padded: Vec<String> = values
+                .iter()
+                .zip(&widths)
+                .map(|(value, &width)| format!("{:<width$}", value, width = width))
+                .collect();
+            format!("{}\n", padded.join("  ").trim_end())
+        };
+        let mut out = line(&self.columns);
+        let rule: Vec<String> = widths.iter().map(|&w| "-".repeat(w)).collect();
+        out.push_str(&line(&rule));
+        for row in &cells {
+            out.push_str(&line(row));
+        }
+        out.push_str(&format!(
+            "({} row{})\n",
+            self.rows.len(),
+            if self.rows.len() == 1 { "" } else { "s" }
+        ));
+        out
+    }
+
+    /// CSV with a header row
+    pub fn render_csv(&self) -> String {
+        let line = |values: Vec<String>| -> String {
+            let quoted: Vec<String> = values.iter().map(|v| csv_field(v)).collect();
+            format!("{}\n", quoted.join(","))
+        };
+        let mut out = line(self.columns.clone());
+        for row in &self.rows {
+            out.push_str(&line(row.iter().map(cell_text).collect()));
+        }
+        out
+    }
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Events of one trace, loaded for querying
+pub struct TraceDatabase {
+    conn: Connection,
+}
+
+impl TraceDatabase {
+    /// Load events converted in memory
+    pub fn from_events(events: &[ChromeTraceEvent]) -> Result<Self> {
+        let values = events
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Self::load(&values)
+    }
+
+    /// Load a Chrome trace JSON file, plain or gzip-compressed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_value(&read_trace_json(path.as_ref())?)
+    }
+
+    /// Load an already-parsed trace (`{"traceEvents": [...]}` or a bare array)
+    pub fn from_value(root: &Value) -> Result<Self> {
+        let Some(trace_events) = root
+            .as_array()
+            .or_else(|| root.get("traceEvents").and_then(|v| v.as_array()))
+        else {
+            return Err(ConvertError::InvalidInput(
+                "Not a Chrome trace: missing 'traceEvents' array".to_string(),
+            ));
+        };
+        Self::load(trace_events)
+    }
+
+    fn load(events: &[Value]) -> Result<Self> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.execute_batch(SCHEMA_SQL)?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO events (name, cat, ph, ts, dur, pid, tid, args)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            let text = |event: &Value, key: &str| event.get(key).map(cell_text);
+            for event in events {
+                insert.execute(params![
+                    text(event, "name"),
+                    text(event, "cat"),
+                    text(event, "ph"),
+                    event.get("ts").and_then(|v| v.as_f64()),
+                    event.get("dur").and_then(|v| v.as_f64()),
+                    text(event, "pid"),
+                    text(event, "tid"),
+                    event.get("args").map(|args| args.to_string()),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(Self { conn })
+    }
+
+    /// Number of events loaded
+    pub fn len(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Whether no events were loaded
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Run one SQL statement and collect its rows
+    pub fn query(&self, sql: &str) -> Result<QueryResult> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = Vec::new();
+        let mut cursor = stmt.query([])?;
+        while let Some(row) = cursor.next()? {
+            let mut values = Vec::with_capacity(columns.len());
+            for idx in 0..columns.len() {
+                values.push(match row.get_ref(idx)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(i) => Value::from(i),
+                    ValueRef::Real(f) => Value::from(f),
+                    ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).into_owned()),
+                    ValueRef::Blob(b) => Value::String(format!("<{} bytes>", b.len())),
+                });
+            }
+            rows.push(values);
+        }
+        Ok(QueryResult { columns, rows })
+    }
+}
+
+const HELP: &str = "\
+Tables:
+  events   every event: id, name, cat, ph, ts, dur, pid, tid, args
+  slices   complete events: id, name, cat, ts, dur, pid, tid, args
+Times are in microseconds; args is JSON (json_extract(args, '$.key')).
+A statement continues onto the next line while a parenthesis is open.
+  help     show this help
+  quit     exit
+";
+
+/// Read SQL statements from `input` and print their results to `output`
+///
+/// A statement runs at the end of its line, or of a later line once its
+/// parentheses are closed (or the line ends with `;`). Errors are printed
+/// and the shell keeps going.
+pub fn run_query_shell<R: BufRead, W: Write>(
+    db: &TraceDatabase,
+    input: R,
+    mut output: W,
+) -> Result<()> {
+    writeln!(
+        output,
+        "Loaded {} events. Type 'help' for tables.",
+        db.len()?
+    )?;
+
+    let mut statement = String::new();
+    let mut lines = input.lines();
+    loop {
+        write!(
+            output,
+            "{}",
+            if statement.is_empty() {
+                "sql> "
+            } else {
+                "...> "
+            }
+        )?;
+        output.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let line = line.trim();
+        if statement.is_empty() {
+            match line {
+                "" => continue,
+                "quit" | "q" | "exit" => break,
+                "help" | "h" | "?" => {
+                    write!(output, "{}", HELP)?;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        statement.push_str(line);
+        statement.push('\n');
+        // An open parenthesis means more lines follow
+        let open = statement.matches('(').count() > statement.matches(')').count();
+        if open && !line.ends_with(';') {
+            continue;
+        }
+
+        let reply = match db.query(statement.trim()) {
+            Ok(result) => result.render_table(),
+            Err(e) => format!("Error: {}\n", e),
+        };
+        statement.clear();
+        write!(output, "{}", reply)?;
+    }
+    Ok(())
+}
+
+/// Run the query shell on stdin and stdout
+pub fn run_query_interactive(db: &TraceDatabase) -> Result<()> {
+    let stdin = std::io::stdin();
+    run_query_shell(db, stdin.lock(), std::io::stdout().lock())
+}
@@ -0,0 +1,59 @@
+//! Reading converted traces back into the event model
+//!
+//! [`ChromeTraceReader`] is the inverse of [`ChromeTraceWriter`]: it loads a
+//! Chrome trace, plain or gzip-compressed, in either the object form
+//! (`{"traceEvents": [...]}`) or as a bare event array, into
+//! [`ChromeTraceEvent`]s. Tools that post-process a converted trace can then
+//! work on typed events instead of raw JSON.
+//!
+//! [`ChromeTraceWriter`]: crate::writer::ChromeTraceWriter
+
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::browser::read_trace_bytes;
+use crate::error::{ConvertError, Result};
+use crate::models::ChromeTraceEvent;
+
+/// Object form of a trace; other top-level keys are ignored
+#[derive(Deserialize)]
+struct TraceObject {
+    #[serde(rename = "traceEvents")]
+    trace_events: Option<Vec<ChromeTraceEvent>>,
+}
+
+/// Reader of Chrome Trace files
+pub struct ChromeTraceReader;
+
+impl ChromeTraceReader {
+    /// Read the events of a trace file, plain or gzip-compressed
+    pub fn read(path: &Path) -> Result<Vec<ChromeTraceEvent>> {
+        let bytes = read_trace_bytes(path)?;
+        Self::from_slice(&bytes).map_err(|e| match e {
+            ConvertError::Json(e) => ConvertError::InvalidInput(format!(
+                "Failed to parse trace JSON {}: {}",
+                path.display(),
+                e
+            )),
+            e => e,
+        })
+    }
+
+    /// Parse the events of a `{"traceEvents": [...]}` object or a bare event array
+    pub fn from_slice(bytes: &[u8]) -> Result<Vec<ChromeTraceEvent>> {
+        let is_array = bytes
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|&b| b == b'[');
+        if is_array {
+            return Ok(serde_json::from_slice(bytes)?);
+        }
+
+        let trace: TraceObject = serde_json::from_slice(bytes)?;
+        trace.trace_events.ok_or_else(|| {
+            ConvertError::InvalidInput(
+                "Not a Chrome trace: missing 'traceEvents' array".to_string(),
+            )
+        })
+    }
+}
@@ -0,0 +1,403 @@
+//! Self-contained HTML report of a trace
+//!
+//! Not everyone a profile is shared with will open a trace viewer. A
+//! [`TraceReport`] condenses a trace into a single HTML file with no external
+//! resources: headline numbers, the kernels with the most total time, GPU and
+//! CPU time per NVTX range, and each device's utilization over time, drawn by
+//! a small embedded script from data embedded next to it.
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::browser::format_duration_us;
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+use crate::routing::{duration_stats, DurationStats};
+
+/// Kernels listed by default
+pub const DEFAULT_REPORT_TOP: usize = 20;
+
+/// Utilization samples per device by default
+pub const DEFAULT_REPORT_BINS: usize = 200;
+
+/// What a report shows
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Page title
+    pub title: String,
+    /// Kernels and NVTX ranges listed, by total time
+    pub top: usize,
+    /// Utilization samples per device across the kernel span
+    pub bins: usize,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            title: "Trace report".to_string(),
+            top: DEFAULT_REPORT_TOP,
+            bins: DEFAULT_REPORT_BINS,
+        }
+    }
+}
+
+/// Share of time one device ran at least one kernel, per sample
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceUtilization {
+    /// Process the device's kernels are on, e.g. `Device 0`
+    pub device: String,
+    /// Time with a kernel running, over the whole kernel span
+    pub busy_us: f64,
+    /// Busy percentage of each sample
+    pub busy_pct: Vec<f64>,
+}
+
+/// Summary of a trace, ready to render
+#[derive(Debug, Clone)]
+pub struct TraceReport {
+    pub title: String,
+    /// Complete events in the trace
+    pub events: usize,
+    pub kernels: usize,
+    pub kernel_us: f64,
+    /// First kernel start to last kernel end
+    pub span_us: (f64, f64),
+    /// Kernels with the most total time
+    pub top_kernels: Vec<DurationStats>,
+    /// `nvtx-kernel` (GPU time) and `nvtx` (CPU time) ranges by name
+    pub nvtx_ranges: Vec<DurationStats>,
+    /// Sample width of [`DeviceUtilization::busy_pct`]
+    pub bin_us: f64,
+    pub utilization: Vec<DeviceUtilization>,
+}
+
+/// Merge `(start, end)` intervals into disjoint ones, in start order
+fn merge_spans(mut spans: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f64, f64)> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+impl TraceReport {
+    /// Summarize converted events
+    pub fn build(events: &[ChromeTraceEvent], options: &ReportOptions) -> Self {
+        let complete: Vec<&ChromeTraceEvent> = events
+            .iter()
+            .filter(|e| e.ph == ChromeTracePhase::Complete && e.dur.is_some())
+            .collect();
+        let kernels: Vec<&ChromeTraceEvent> = complete
+            .iter()
+            .copied()
+            .filter(|e| e.cat == EventCategory::Kernel)
+            .collect();
+
+        let stats = duration_stats(events);
+        let kernel_us = stats
+            .iter()
+            .filter(|s| s.category == "kernel")
+            .map(|s| s.total_us)
+            .sum();
+        let top_kernels = stats
+            .iter()
+            .filter(|s| s.category == "kernel")
+            .take(options.top)
+            .cloned()
+            .collect();
+        let nvtx_ranges = stats
+            .iter()
+            .filter(|s| s.category == "nvtx-kernel" || s.category == "nvtx")
+            .take(options.top)
+            .cloned()
+            .collect();
+
+        let start_us = kernels.iter().map(|e| e.ts).fold(f64::INFINITY, f64::min);
+        let end_us = kernels
+            .iter()
+            .map(|e| e.ts + e.dur.unwrap_or(0.0))
+            .fold(f64::NEG_INFINITY, f64::max);
+        let span_us = if kernels.is_empty() {
+            (0.0, 0.0)
+        } else {
+            (start_us, end_us)
+        };
+        let bins = options.bins.max(1);
+        let bin_us = (span_us.1 - span_us.0) / bins as f64;
+
+        let mut per_device: BTreeMap<String, Vec<(f64, f64)>> = BTreeMap::new();
+        for kernel in &kernels {
+            let dur = kernel.dur.unwrap_or(0.0);
+            per_device
+                .entry(kernel.pid.to_string())
+                .or_default()
+                .push((kernel.ts, kernel.ts + dur));
+        }
+        let utilization = per_device
+            .into_iter()
+            .map(|(device, spans)| {
+                let mut busy = vec![0.0; bins];
+                let mut busy_us = 0.0;
+                for (start, end) in merge_spans(spans) {
+                    busy_us += end - start;
+                    if bin_us <= 0.0 {
+                        continue;
+                    }
+                    let first = (((start - span_us.0) / bin_us) as usize).min(bins - 1);
+                    let last = (((end - span_us.0) / bin_us) as usize).min(bins - 1);
+                    for (bin, bin_busy) in busy.iter_mut().enumerate().take(last + 1).skip(first) {
+                        let bin_start = span_us.0 + bin as f64 * bin_us;
+                        let overlap = end.min(bin_start + bin_us) - start.max(bin_start);
+                        *bin_busy += overlap.max(0.0);
+                    }
+                }
+                let busy_pct = busy
+                    .iter()
+                    .map(|us| {
+                        let pct = if bin_us > 0.0 {
+                            us / bin_us * 100.0
+                        } else {
+                            100.0
+                        };
+                        (pct.min(100.0) * 10.0).round() / 10.0
+                    })
+                    .collect();
+                DeviceUtilization {
+                    device,
+                    busy_us,
+                    busy_pct,
+                }
+            })
+            .collect();
+
+        Self {
+            title: options.title.clone(),
+            events: complete.len(),
+            kernels: kernels.len(),
+            kernel_us,
+            span_us,
+            top_kernels,
+            nvtx_ranges,
+            bin_us,
+            utilization,
+        }
+    }
+
+    /// Render the report as a standalone HTML page
+    pub fn render_html(&self) -> String {
+        let span = self.span_us.1 - self.span_us.0;
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>{}</title>\n", escape_html(&self.title)));
+        html.push_str(REPORT_STYLE);
+        html.push_str("</head>\n<body>\n");
+        html.push_str(&format!("<h1>{}</h1>\n", escape_html(&self.title)));
+
+        html.push_str("<section class=\"summary\">\n");
+        let cards = [
+            ("Kernels", self.kernels.to_string()),
+            ("Kernel time", format_duration_us(self.kernel_us)),
+            ("GPU span", format_duration_us(span)),
+            ("Devices", self.utilization.len().to_string()),
+            ("Events", self.events.to_string()),
+        ];
+        for (label, value) in cards {
+            html.push_str(&format!(
+                "<div class=\"card\"><div class=\"value\">{}</div><div class=\"label\">{}</div></div>\n",
+                escape_html(&value),
+                label
+            ));
+        }
+        html.push_str("</section>\n");
+
+        html.push_str("<h2>GPU utilization</h2>\n");
+        if self.utilization.is_empty() {
+            html.push_str("<p class=\"empty\">No kernels in this trace.</p>\n");
+        } else {
+            html.push_str("<table>\n<tr><th>Device</th><th>Busy</th><th>Busy %</th></tr>\n");
+            for device in &self.utilization {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}</td></tr>\n",
+                    escape_html(&device.device),
+                    format_duration_us(device.busy_us),
+                    device.busy_us / span.max(f64::MIN_POSITIVE) * 100.0
+                ));
+            }
+            html.push_str(
+                "</table>\n<canvas id=\"utilization\" width=\"960\" height=\"260\"></canvas>\n",
+            );
+        }
+
+        html.push_str("<h2>Top kernels</h2>\n");
+        html.push_str(&self.stats_table(&self.top_kernels, false));
+        html.push_str("<h2>NVTX ranges</h2>\n");
+        html.push_str(&self.stats_table(&self.nvtx_ranges, true));
+
+        let data = json!({
+            "start_us": self.span_us.0,
+            "bin_us": self.bin_us,
+            "devices": self.utilization,
+        });
+        html.push_str("<script>\nconst UTILIZATION = ");
+        html.push_str(&script_json(&data.to_string()));
+        html.push_str(";\n");
+        html.push_str(REPORT_SCRIPT);
+        html.push_str("</script>\n</body>\n</html>\n");
+        html
+    }
+
+    /// Table of per-name duration statistics
+    fn stats_table(&self, rows: &[DurationStats], with_kind: bool) -> String {
+        if rows.is_empty() {
+            return "<p class=\"empty\">None in this trace.</p>\n".to_string();
+        }
+        let mut html = String::from("<table>\n<tr><th>Name</th>");
+        if with_kind {
+            html.push_str("<th>Time</th>");
+        }
+        html.push_str(
+            "<th>Count</th><th>Total</th><th>Avg</th><th>Min</th><th>Max</th><th>% of kernel time</th></tr>\n",
+        );
+        for row in rows {
+            html.push_str(&format!(
+                "<tr><td class=\"name\">{}</td>",
+                escape_html(&row.name)
+            ));
+            if with_kind {
+                let kind = if row.category == "nvtx-kernel" {
+                    "GPU"
+                } else {
+                    "CPU"
+                };
+                html.push_str(&format!("<td>{}</td>", kind));
+            }
+            // CPU time of host-side ranges is not a share of kernel time
+            let pct = if row.category == "nvtx" {
+                String::new()
+            } else {
+                format!(
+                    "{:.1}",
+                    row.total_us / self.kernel_us.max(f64::MIN_POSITIVE) * 100.0
+                )
+            };
+            html.push_str(&format!(
+                "<td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+                 <td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+                row.count,
+                format_duration_us(row.total_us),
+                format_duration_us(row.avg_us()),
+                format_duration_us(row.min_us),
+                format_duration_us(row.max_us),
+                pct
+            ));
+        }
+        html.push_str("</table>\n");
+        html
+    }
+
+    /// Write the HTML page to `path`
+    pub fn write(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(self.render_html().as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(ConvertError::Output)
+    }
+}
+
+/// Escape text for HTML element content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Keep JSON embedded in a `<script>` element from closing it early
+fn script_json(json: &str) -> String {
+    json.replace("</", "<\\/")
+}
+
+/// Default report path for an input: `trace.json.gz` -> `trace.report.html`
+pub fn report_path(input: &str) -> String {
+    let stem = input.strip_suffix(".gz").unwrap_or(input);
+    let stem = [".json", ".nsys-rep", ".sqlite"]
+        .iter()
+        .find_map(|ext| stem.strip_suffix(ext))
+        .unwrap_or(stem);
+    format!("{}.report.html", stem)
+}
+
+const REPORT_STYLE: &str = r#"<style>
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2em auto; max-width: 1000px; color: #222; }
+h1 { font-size: 1.6em; }
+h2 { font-size: 1.2em; margin-top: 2em; border-bottom: 1px solid #ddd; padding-bottom: 0.2em; }
+.summary { display: flex; flex-wrap: wrap; gap: 1em; }
+.card { background: #f4f6f8; border-radius: 6px; padding: 0.8em 1.2em; min-width: 8em; }
+.card .value { font-size: 1.3em; font-weight: 600; }
+.card .label { color: #666; font-size: 0.85em; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9em; margin: 0.5em 0 1em; }
+th, td { padding: 0.3em 0.6em; border-bottom: 1px solid #eee; text-align: left; }
+th { background: #fafafa; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; white-space: nowrap; }
+td.name { font-family: monospace; word-break: break-all; }
+.empty { color: #888; }
+canvas { width: 100%; border: 1px solid #eee; }
+</style>
+"#;
+
+const REPORT_SCRIPT: &str = r##"(function () {
+  const canvas = document.getElementById("utilization");
+  if (!canvas || UTILIZATION.devices.length === 0) return;
+  const ctx = canvas.getContext("2d");
+  const w = canvas.width, h = canvas.height, left = 40, bottom = 24, top = 10, right = 10;
+  const colors = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f"];
+  const plotW = w - left - right, plotH = h - top - bottom;
+  ctx.font = "11px sans-serif";
+  ctx.strokeStyle = "#ccc";
+  ctx.fillStyle = "#666";
+  for (const pct of [0, 50, 100]) {
+    const y = top + plotH * (1 - pct / 100);
+    ctx.beginPath(); ctx.moveTo(left, y); ctx.lineTo(w - right, y); ctx.stroke();
+    ctx.fillText(pct + "%", 4, y + 4);
+  }
+  const bins = UTILIZATION.devices[0].busy_pct.length;
+  const spanMs = UTILIZATION.bin_us * bins / 1000;
+  ctx.fillText("0 ms", left, h - 6);
+  const endLabel = spanMs.toFixed(1) + " ms";
+  ctx.fillText(endLabel, w - right - ctx.measureText(endLabel).width, h - 6);
+  UTILIZATION.devices.forEach(function (device, i) {
+    ctx.strokeStyle = colors[i % colors.length];
+    ctx.lineWidth = 1.5;
+    ctx.beginPath();
+    device.busy_pct.forEach(function (pct, bin) {
+      const x = left + plotW * (bin + 0.5) / bins;
+      const y = top + plotH * (1 - pct / 100);
+      if (bin === 0) ctx.moveTo(x, y); else ctx.lineTo(x, y);
+    });
+    ctx.stroke();
+    ctx.fillStyle = colors[i % colors.length];
+    ctx.fillText(device.device, left + 8 + i * 90, top + 12);
+  });
+})();
+"##;
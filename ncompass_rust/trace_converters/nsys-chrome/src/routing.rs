@@ -0,0 +1,339 @@
+//! Routing of event categories to separate outputs
+//!
+//! A single conversion can feed several files: e.g. kernels and nvtx-kernel
+//! ranges to `trace.json.gz`, counters to `counters.json` and duration
+//! statistics to `stats.csv`. Each [`OutputRoute`] names the categories it
+//! takes; [`MultiSinkWriter`] collects the matching events per route and
+//! hands back the events no route took, which go to the main output.
+//!
+//! An event matching several routes is written to each of them. Metadata
+//! events name tracks, so they go to the main output and every trace route.
+//!
+//! [`summary_events`] instead keeps only the synthesized events of a trace,
+//! for a small summary that leaves out raw kernels and API calls.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::analysis::steps::STEP_TRACK;
+use crate::analysis::time_origin::TIME_ORIGIN_EVENT;
+use crate::category::EventCategory;
+use crate::error::{ConvertError, Result};
+use crate::frontends::nsys_stats::INSTANCES_ARG;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, OutputRoute};
+use crate::writer::ChromeTraceWriter;
+
+/// Category matching counter events, whatever their `cat`
+pub const COUNTERS_CATEGORY: &str = "counters";
+
+/// How a route's events are written, chosen by its path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteFormat {
+    /// Chrome Trace JSON
+    Json,
+    /// Gzip-compressed Chrome Trace JSON
+    JsonGz,
+    /// Per-name duration statistics (see [`duration_stats`])
+    StatsCsv,
+}
+
+impl OutputRoute {
+    /// Parse `CATEGORY[,CATEGORY...]=PATH`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = || {
+            ConvertError::InvalidOption(format!(
+                "Invalid output route '{}' (use CATEGORY[,CATEGORY...]=PATH)",
+                spec
+            ))
+        };
+        let (categories, path) = spec.split_once('=').ok_or_else(invalid)?;
+        let categories: Vec<String> = categories
+            .split(',')
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect();
+        let path = path.trim();
+        if categories.is_empty() || path.is_empty() {
+            return Err(invalid());
+        }
+
+        for category in categories
+            .iter()
+            .filter(|c| *c != "*" && *c != COUNTERS_CATEGORY)
+        {
+            EventCategory::parse(category)?;
+        }
+
+        let route = Self {
+            categories,
+            path: path.to_string(),
+        };
+        route.format()?;
+        Ok(route)
+    }
+
+    /// Output format implied by the path's extension
+    pub fn format(&self) -> Result<RouteFormat> {
+        if self.path.ends_with(".json.gz") {
+            Ok(RouteFormat::JsonGz)
+        } else if self.path.ends_with(".json") {
+            Ok(RouteFormat::Json)
+        } else if self.path.ends_with(".csv") {
+            Ok(RouteFormat::StatsCsv)
+        } else {
+            Err(ConvertError::InvalidOption(format!(
+                "Output route path '{}' must end in .json, .json.gz or .csv",
+                self.path
+            )))
+        }
+    }
+
+    /// Whether this route takes an event
+    pub fn matches(&self, event: &ChromeTraceEvent) -> bool {
+        self.categories.iter().any(|category| {
+            category == "*"
+                || *category == *event.cat
+                || (category == COUNTERS_CATEGORY && event.ph == ChromeTracePhase::Counter)
+        })
+    }
+}
+
+/// Where a route's events ended up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutedOutput {
+    pub path: String,
+    pub format: RouteFormat,
+    /// Routed events written, not counting metadata
+    pub events: usize,
+}
+
+struct Sink {
+    route: OutputRoute,
+    format: RouteFormat,
+    events: Vec<ChromeTraceEvent>,
+    routed: usize,
+}
+
+/// Writer splitting events across the outputs of several routes
+pub struct MultiSinkWriter {
+    sinks: Vec<Sink>,
+}
+
+impl MultiSinkWriter {
+    /// Check every route's format up front, before any conversion work
+    pub fn new(routes: &[OutputRoute]) -> Result<Self> {
+        let sinks = routes
+            .iter()
+            .map(|route| {
+                Ok(Sink {
+                    route: route.clone(),
+                    format: route.format()?,
+                    events: Vec::new(),
+                    routed: 0,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { sinks })
+    }
+
+    /// Take the routed events, returning those left for the main output
+    pub fn route(&mut self, events: Vec<ChromeTraceEvent>) -> Vec<ChromeTraceEvent> {
+        let mut unrouted = Vec::with_capacity(events.len());
+        for event in events {
+            if event.ph == ChromeTracePhase::Metadata {
+                for sink in &mut self.sinks {
+                    if sink.format != RouteFormat::StatsCsv {
+                        sink.events.push(event.clone());
+                    }
+                }
+                unrouted.push(event);
+                continue;
+            }
+
+            let mut matched = self
+                .sinks
+                .iter_mut()
+                .filter(|sink| sink.route.matches(&event))
+                .peekable();
+            if matched.peek().is_none() {
+                unrouted.push(event);
+                continue;
+            }
+            for sink in matched {
+                sink.routed += 1;
+                sink.events.push(event.clone());
+            }
+        }
+        unrouted
+    }
+
+    /// Write every route's output
+    pub fn finish(self) -> Result<Vec<RoutedOutput>> {
+        let mut outputs = Vec::with_capacity(self.sinks.len());
+        for sink in self.sinks {
+            let path = sink.route.path;
+            match sink.format {
+                RouteFormat::Json => ChromeTraceWriter::write(&path, sink.events)?,
+                RouteFormat::JsonGz => ChromeTraceWriter::write_gz(&path, sink.events)?,
+                RouteFormat::StatsCsv => write_stats_csv(&path, &sink.events)?,
+            }
+            outputs.push(RoutedOutput {
+                path,
+                format: sink.format,
+                events: sink.routed,
+            });
+        }
+        Ok(outputs)
+    }
+}
+
+/// Whether an event belongs in a summary trace: linked nvtx-kernel ranges,
+/// synthesized step markers and counters
+pub fn is_summary_event(event: &ChromeTraceEvent) -> bool {
+    event.cat == EventCategory::NvtxKernel
+        || &*event.tid == STEP_TRACK
+        || event.ph == ChromeTracePhase::Counter
+}
+
+/// Keep the summary events, and the metadata naming their tracks
+pub fn summary_events(events: Vec<ChromeTraceEvent>) -> Vec<ChromeTraceEvent> {
+    let (metadata, events): (Vec<_>, Vec<_>) = events
+        .into_iter()
+        .filter(|e| e.ph == ChromeTracePhase::Metadata || is_summary_event(e))
+        .partition(|e| e.ph == ChromeTracePhase::Metadata);
+
+    let processes: HashSet<&str> = events.iter().map(|e| &*e.pid).collect();
+    let threads: HashSet<(&str, &str)> = events.iter().map(|e| (&*e.pid, &*e.tid)).collect();
+    // Thread metadata describes a track and process metadata a process; the
+    // time origin is kept whatever process carries it
+    let kept: Vec<ChromeTraceEvent> = metadata
+        .into_iter()
+        .filter(|m| {
+            if m.tid.is_empty() {
+                processes.contains(&*m.pid) || m.name == TIME_ORIGIN_EVENT
+            } else {
+                threads.contains(&(&*m.pid, &*m.tid))
+            }
+        })
+        .collect();
+    kept.into_iter().chain(events).collect()
+}
+
+/// Duration statistics of the complete events sharing a category and name
+#[derive(Debug, Clone, PartialEq)]
+pub struct DurationStats {
+    pub category: String,
+    pub name: String,
+    pub count: usize,
+    pub total_us: f64,
+    pub min_us: f64,
+    pub max_us: f64,
+    /// Bytes moved, for events with a `bytes` arg (copies)
+    pub total_bytes: Option<i64>,
+}
+
+impl DurationStats {
+    pub fn avg_us(&self) -> f64 {
+        self.total_us / self.count as f64
+    }
+
+    /// Average throughput of copies in GB/s (bytes per nanosecond)
+    pub fn avg_gbps(&self) -> Option<f64> {
+        let total_ns = self.total_us * 1000.0;
+        let gbps = self.total_bytes? as f64 / total_ns;
+        (total_ns > 0.0).then(|| (gbps * 1000.0).round() / 1000.0)
+    }
+}
+
+/// Per-(category, name) statistics, largest total duration first
+///
+/// An aggregate bar read from an `nsys stats` report counts as its
+/// `instances`, with its `min_ns` and `max_ns` as extremes.
+pub fn duration_stats(events: &[ChromeTraceEvent]) -> Vec<DurationStats> {
+    let mut by_name: HashMap<(&str, &str), DurationStats> = HashMap::new();
+    for event in events {
+        let Some(dur) = event.dur.filter(|_| event.ph == ChromeTracePhase::Complete) else {
+            continue;
+        };
+        let stats = by_name
+            .entry((&event.cat, &event.name))
+            .or_insert_with(|| DurationStats {
+                category: event.cat.to_string(),
+                name: event.name.clone(),
+                count: 0,
+                total_us: 0.0,
+                min_us: f64::INFINITY,
+                max_us: f64::NEG_INFINITY,
+                total_bytes: None,
+            });
+        let arg_us = |key: &str| {
+            let ns = event.args.get(key).and_then(|v| v.as_f64());
+            ns.map(|ns| ns / 1000.0)
+        };
+        let instances = event.args.get(INSTANCES_ARG).and_then(|v| v.as_u64());
+        stats.count += instances.map_or(1, |n| n as usize);
+        stats.total_us += dur;
+        stats.min_us = stats.min_us.min(arg_us("min_ns").unwrap_or(dur));
+        stats.max_us = stats.max_us.max(arg_us("max_ns").unwrap_or(dur));
+        if let Some(bytes) = event.args.get("bytes").and_then(|v| v.as_i64()) {
+            *stats.total_bytes.get_or_insert(0) += bytes;
+        }
+    }
+
+    let mut stats: Vec<DurationStats> = by_name.into_values().collect();
+    stats.sort_by(|a, b| {
+        b.total_us
+            .total_cmp(&a.total_us)
+            .then_with(|| a.category.cmp(&b.category))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    stats
+}
+
+/// Quote a CSV field if it contains a separator, quote or newline
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write [`duration_stats`] as CSV
+pub fn write_stats_csv(path: &str, events: &[ChromeTraceEvent]) -> Result<()> {
+    let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+        path: path.into(),
+        source,
+    })?;
+    let mut writer = BufWriter::new(file);
+    let write = |writer: &mut BufWriter<File>| -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "category,name,count,total_us,avg_us,min_us,max_us,total_bytes,avg_gbps"
+        )?;
+        for stats in duration_stats(events) {
+            // Transfer totals are left empty for events that move no data
+            let bytes = stats.total_bytes.map(|b| b.to_string()).unwrap_or_default();
+            let gbps = stats
+                .avg_gbps()
+                .map(|g| format!("{:.3}", g))
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "{},{},{},{:.3},{:.3},{:.3},{:.3},{},{}",
+                csv_field(&stats.category),
+                csv_field(&stats.name),
+                stats.count,
+                stats.total_us,
+                stats.avg_us(),
+                stats.min_us,
+                stats.max_us,
+                bytes,
+                gbps
+            )?;
+        }
+        writer.flush()
+    };
+    write(&mut writer).map_err(ConvertError::Output)
+}
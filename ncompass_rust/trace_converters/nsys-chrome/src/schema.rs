@@ -1,8 +1,36 @@
 //! Schema detection and table discovery for nsys SQLite databases
 
-use anyhow::Result;
 use rusqlite::Connection;
-use std::collections::HashSet;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::Result;
+
+/// Table name prefixes that indicate profiling data the converter may care about
+const RELEVANT_TABLE_PREFIXES: &[&str] = &[
+    "CUPTI_ACTIVITY_KIND_",
+    "MPI_",
+    "NVTX_",
+    "OSRT_",
+    "SCHED_",
+    "WDDM_",
+    "VULKAN_",
+    "DX12_",
+];
+
+/// Tables with relevant prefixes that are consumed outside the activity parsers
+const AUXILIARY_TABLES: &[&str] = &[
+    "CUDA_CALLCHAINS",
+    "MPI_RANKS",
+    "MPI_COMMUNICATORS",
+    "WDDM_QUEUE_PACKET_STOP_EVENTS",
+    "WDDM_DMA_PACKET_START_EVENTS",
+    "WDDM_DMA_PACKET_STOP_EVENTS",
+    "VULKAN_API",
+    "VULKAN_DEBUG_API",
+    "DX12_API",
+    "DX12_PIX_DEBUG_API",
+];
 
 /// Detect all available tables in the SQLite database
 pub fn detect_available_tables(conn: &Connection) -> Result<HashSet<String>> {
@@ -30,11 +58,19 @@ impl TableRegistry {
     pub fn get_activity_type(table_name: &str) -> Option<&'static str> {
         match table_name {
             "CUPTI_ACTIVITY_KIND_KERNEL" => Some("kernel"),
-            "CUPTI_ACTIVITY_KIND_RUNTIME" => Some("cuda-api"),
+            "CUPTI_ACTIVITY_KIND_RUNTIME" | "CUPTI_ACTIVITY_KIND_DRIVER" => Some("cuda-api"),
             "NVTX_EVENTS" => Some("nvtx"),
             "OSRT_API" => Some("osrt"),
             "SCHED_EVENTS" => Some("sched"),
+            "WDDM_QUEUE_PACKET_START_EVENTS" => Some("wddm"),
+            "VULKAN_WORKLOAD" | "DX12_WORKLOAD" => Some("graphics"),
+            "CUPTI_ACTIVITY_KIND_MEMCPY" => Some("nvlink"),
             "COMPOSITE_EVENTS" => Some("composite"),
+            "GPU_METRICS" => Some("gpu-metrics"),
+            "MPI_COLLECTIVES_EVENTS"
+            | "MPI_P2P_EVENTS"
+            | "MPI_START_WAIT_EVENTS"
+            | "MPI_OTHER_EVENTS" => Some("mpi"),
             _ => None,
         }
     }
@@ -46,11 +82,263 @@ impl TableRegistry {
             "cuda-api" => vec!["CUPTI_ACTIVITY_KIND_RUNTIME"],
             "nvtx" => vec!["NVTX_EVENTS"],
             "osrt" => vec!["OSRT_API"],
-            "sched" => vec!["SCHED_EVENTS"],
+            "sched" | "thread-state" => vec!["SCHED_EVENTS"],
+            "wddm" => vec!["WDDM_QUEUE_PACKET_START_EVENTS"],
+            "graphics" => vec!["VULKAN_WORKLOAD"],
+            "nvlink" => vec!["CUPTI_ACTIVITY_KIND_MEMCPY"],
+            "memcpy" => vec!["CUPTI_ACTIVITY_KIND_MEMCPY"],
             "composite" => vec!["COMPOSITE_EVENTS"],
+            "gpu-metrics" => vec!["GPU_METRICS"],
+            "mpi" => vec!["MPI_COLLECTIVES_EVENTS"],
             _ => vec![],
         }
     }
+
+    /// Get all known names of an activity type's table, in order of preference.
+    ///
+    /// The first entry is the canonical name; later entries would be names the
+    /// same table has in other nsys versions, and the probe reads the first
+    /// compatible one. Separate tables of the same activity belong in
+    /// [`Self::get_merged_tables`] instead.
+    pub fn get_table_variants(activity_type: &str) -> Vec<&'static str> {
+        Self::get_tables_for_activity(activity_type)
+    }
+
+    /// Get the other tables whose records are merged into an activity type
+    ///
+    /// nsys writes CUDA driver API calls apart from runtime calls, MPI events
+    /// by kind and DX12 workloads apart from Vulkan ones; every compatible
+    /// table present is read.
+    pub fn get_merged_tables(activity_type: &str) -> Vec<&'static str> {
+        match activity_type {
+            "cuda-api" => vec!["CUPTI_ACTIVITY_KIND_DRIVER"],
+            "mpi" => vec!["MPI_P2P_EVENTS", "MPI_START_WAIT_EVENTS", "MPI_OTHER_EVENTS"],
+            "graphics" => vec!["DX12_WORKLOAD"],
+            _ => vec![],
+        }
+    }
+
+    /// Get the columns a table must provide for its activity extractor
+    pub fn get_required_columns(activity_type: &str) -> &'static [&'static str] {
+        match activity_type {
+            "kernel" => &[
+                "start",
+                "end",
+                "deviceId",
+                "streamId",
+                "correlationId",
+                "shortName",
+                "gridX",
+                "gridY",
+                "gridZ",
+                "blockX",
+                "blockY",
+                "blockZ",
+                "registersPerThread",
+                "staticSharedMemory",
+                "dynamicSharedMemory",
+            ],
+            "cuda-api" => &["start", "end", "globalTid", "correlationId", "nameId"],
+            "nvtx" => &["start", "end", "text", "textId", "globalTid", "eventType"],
+            "osrt" => &["start", "end", "globalTid", "nameId"],
+            "mpi" => &["start", "end", "globalTid", "textId"],
+            "sched" | "thread-state" => &[
+                "start",
+                "cpu",
+                "isSchedIn",
+                "globalTid",
+                "threadState",
+                "threadBlock",
+            ],
+            "wddm" => &["start", "gpu", "context", "submitSequence", "packetType", "globalTid"],
+            "graphics" => &["start", "end", "gpu", "correlationId"],
+            "nvlink" => &[
+                "start",
+                "end",
+                "deviceId",
+                "streamId",
+                "correlationId",
+                "bytes",
+                "copyKind",
+                "srcDeviceId",
+                "dstDeviceId",
+            ],
+            "memcpy" => &[
+                "start",
+                "end",
+                "deviceId",
+                "streamId",
+                "correlationId",
+                "bytes",
+                "copyKind",
+            ],
+            "gpu-metrics" => &["timestamp", "typeId", "metricId", "value"],
+            _ => &[],
+        }
+    }
+
+    /// All activity types backed directly by a table
+    pub fn table_activity_types() -> &'static [&'static str] {
+        &[
+            "kernel",
+            "cuda-api",
+            "nvtx",
+            "osrt",
+            "sched",
+            "thread-state",
+            "wddm",
+            "graphics",
+            "nvlink",
+            "memcpy",
+            "mpi",
+            "composite",
+            "gpu-metrics",
+        ]
+    }
+}
+
+/// Get the column names of a table
+pub fn table_columns(conn: &Connection, table_name: &str) -> Result<HashSet<String>> {
+    let stmt = conn.prepare(&format!("SELECT * FROM \"{}\" LIMIT 0", table_name))?;
+    Ok(stmt.column_names().iter().map(|s| s.to_string()).collect())
+}
+
+/// A table that exists but lacks columns required by its extractor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IncompatibleTable {
+    /// Table name
+    pub table: String,
+    /// Activity type the table would have provided
+    pub activity_type: String,
+    /// Required columns not present in the table
+    pub missing_columns: Vec<String>,
+}
+
+/// Result of probing an nsys SQLite database for known table layouts
+#[derive(Debug, Clone, Default)]
+pub struct SchemaProbe {
+    /// All tables present in the database
+    pub tables: HashSet<String>,
+    /// Activity type -> tables to extract it from, the chosen variant first
+    pub resolved: HashMap<String, Vec<String>>,
+    /// Tables skipped because required columns are missing
+    pub incompatible: Vec<IncompatibleTable>,
+    /// Tables that look like profiling data but have no extractor
+    pub unknown_tables: Vec<String>,
+    /// Custom activity types linked to kernels like NVTX ranges
+    pub custom_annotations: HashSet<String>,
+}
+
+impl SchemaProbe {
+    /// Inspect sqlite_master and map known table variants to activity types
+    pub fn probe(conn: &Connection) -> Result<Self> {
+        let tables = detect_available_tables(conn)?;
+        let mut resolved: HashMap<String, Vec<String>> = HashMap::new();
+        let mut incompatible = Vec::new();
+
+        for &activity_type in TableRegistry::table_activity_types() {
+            let mut compatible = |table: &str| -> Result<bool> {
+                if !tables.contains(table) {
+                    return Ok(false);
+                }
+                let columns = table_columns(conn, table)?;
+                let missing_columns: Vec<String> = TableRegistry::get_required_columns(activity_type)
+                    .iter()
+                    .filter(|c| !columns.contains(**c))
+                    .map(|c| c.to_string())
+                    .collect();
+                if missing_columns.is_empty() {
+                    return Ok(true);
+                }
+                incompatible.push(IncompatibleTable {
+                    table: table.to_string(),
+                    activity_type: activity_type.to_string(),
+                    missing_columns,
+                });
+                Ok(false)
+            };
+
+            let mut found = Vec::new();
+            for table in TableRegistry::get_table_variants(activity_type) {
+                if compatible(table)? {
+                    found.push(table.to_string());
+                    break;
+                }
+            }
+            for table in TableRegistry::get_merged_tables(activity_type) {
+                if compatible(table)? {
+                    found.push(table.to_string());
+                }
+            }
+            if !found.is_empty() {
+                resolved.insert(activity_type.to_string(), found);
+            }
+        }
+
+        let mut unknown_tables: Vec<String> = tables
+            .iter()
+            .filter(|t| RELEVANT_TABLE_PREFIXES.iter().any(|p| t.starts_with(p)))
+            .filter(|t| TableRegistry::get_activity_type(t).is_none())
+            .filter(|t| !AUXILIARY_TABLES.contains(&t.as_str()))
+            .cloned()
+            .collect();
+        unknown_tables.sort();
+        incompatible.sort_by(|a, b| a.table.cmp(&b.table));
+
+        Ok(Self {
+            tables,
+            resolved,
+            incompatible,
+            unknown_tables,
+            custom_annotations: HashSet::new(),
+        })
+    }
+
+    /// Resolve a custom activity type to its table, if the table exists
+    ///
+    /// Custom annotation activities make `nvtx-kernel` available even
+    /// without an NVTX table.
+    pub fn register_custom(&mut self, activity_type: &str, table: &str, annotation: bool) -> bool {
+        if !self.tables.contains(table) {
+            return false;
+        }
+        self.resolved
+            .insert(activity_type.to_string(), vec![table.to_string()]);
+        self.unknown_tables.retain(|t| t != table);
+        if annotation {
+            self.custom_annotations.insert(activity_type.to_string());
+        }
+        true
+    }
+
+    /// Get the table resolved for an activity type
+    pub fn table_for(&self, activity_type: &str) -> Option<&str> {
+        self.tables_for(activity_type).next()
+    }
+
+    /// Get every table resolved for an activity type, the chosen variant first
+    pub fn tables_for<'a>(&'a self, activity_type: &str) -> impl Iterator<Item = &'a str> {
+        self.resolved
+            .get(activity_type)
+            .into_iter()
+            .flatten()
+            .map(|s| s.as_str())
+    }
+
+    /// Check whether a table is present in the database
+    pub fn has_table(&self, table_name: &str) -> bool {
+        self.tables.contains(table_name)
+    }
+
+    /// Activity types that can be extracted, including synthetic ones
+    pub fn activity_types(&self) -> HashSet<String> {
+        let mut activities: HashSet<String> = self.resolved.keys().cloned().collect();
+        let annotated = activities.contains("nvtx") || !self.custom_annotations.is_empty();
+        if activities.contains("kernel") && activities.contains("cuda-api") && annotated {
+            activities.insert("nvtx-kernel".to_string());
+        }
+        activities
+    }
 }
 
 /// Detect available event types based on tables
@@ -0,0 +1,361 @@
+//! Seekable gzip output with a time index
+//!
+//! [`SeekableGzSink`] writes the trace as a series of independent gzip
+//! members, each holding whole event lines of about [`SEEKABLE_BLOCK_SIZE`]
+//! uncompressed bytes. The concatenation is still a standard gzip file, so
+//! every tool reading `.json.gz` reads it unchanged. Alongside, a
+//! [`SeekIndex`] records for each member:
+//! - `offset` / `length`: its byte range in the compressed file,
+//! - `text_offset` / `text_length`: the byte range of its text in the
+//!   uncompressed JSON,
+//! - `start_us` / `end_us`: the earliest start and latest end of its timed
+//!   events, and `max_end_us`, the latest end of any member up to this one,
+//! - `metadata_events`: events without a time (track names and the like),
+//!   which a viewer fetches whatever range it shows.
+//!
+//! Events are written in timestamp order, so `start_us` and `max_end_us`
+//! never decrease and a viewer binary searches both to find the members
+//! covering a time range ([`SeekIndex::blocks_in_range`]), fetching only
+//! those, e.g. with HTTP range requests against object storage. Members are
+//! compressed in parallel.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+use crate::sink::TraceSink;
+
+/// Seek index format version, bumped on incompatible changes
+pub const SEEK_INDEX_VERSION: u32 = 1;
+
+/// Uncompressed bytes after which a gzip member is closed: 1MB
+pub const SEEKABLE_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Opening of the JSON text, as written by the other sinks
+const JSON_OPENING: &[u8] = b"{\"traceEvents\":[\n";
+
+/// Closing of the JSON text
+const JSON_CLOSING: &[u8] = b"\n]}";
+
+/// One gzip member of a seekable trace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeekBlock {
+    /// Byte offset of the member in the compressed file
+    pub offset: u64,
+    /// Compressed length of the member in bytes
+    pub length: u64,
+    /// Byte offset of the member's text in the uncompressed trace
+    pub text_offset: u64,
+    /// Uncompressed length of the member's text in bytes
+    pub text_length: u64,
+    /// Events starting in the member
+    pub events: usize,
+    /// Earliest timestamp of the member's timed events; without any, the
+    /// start of the previous member
+    pub start_us: f64,
+    /// Latest end (timestamp plus duration) of the member's timed events;
+    /// without any, equal to `start_us`
+    pub end_us: f64,
+    /// Latest end of the timed events of this and all earlier members
+    pub max_end_us: f64,
+    /// Metadata events in the member, which have no time
+    pub metadata_events: usize,
+}
+
+impl SeekBlock {
+    /// Whether any timed event of the block may fall within `[start_us, end_us]`
+    fn overlaps(&self, start_us: f64, end_us: f64) -> bool {
+        self.events > self.metadata_events && self.start_us <= end_us && self.end_us >= start_us
+    }
+}
+
+/// Index of the gzip members of a seekable trace
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SeekIndex {
+    pub version: u32,
+    pub blocks: Vec<SeekBlock>,
+}
+
+impl SeekIndex {
+    /// Read an index written by [`Self::write_sidecar`]
+    pub fn read(path: &str) -> Result<Self> {
+        let file = File::open(path).map_err(|e| ConvertError::open_input(path, e))?;
+        let index: SeekIndex = serde_json::from_reader(BufReader::new(file))?;
+        if index.version != SEEK_INDEX_VERSION {
+            return Err(ConvertError::InvalidInput(format!(
+                "Unsupported seek index version {} in '{}' (expected {})",
+                index.version, path, SEEK_INDEX_VERSION
+            )));
+        }
+        Ok(index)
+    }
+
+    /// Write the index as a companion JSON file
+    pub fn write_sidecar(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        serde_json::to_writer(BufWriter::new(file), self)
+            .map_err(|e| ConvertError::Output(e.into()))?;
+        Ok(())
+    }
+
+    /// Blocks to fetch for the events overlapping `[start_us, end_us]`: those
+    /// whose events overlap it, plus those holding metadata events, in file
+    /// order
+    ///
+    /// The candidates are found by binary search on `start_us` and on
+    /// `max_end_us`, so long events starting before the range are not missed.
+    pub fn blocks_in_range(&self, start_us: f64, end_us: f64) -> Vec<&SeekBlock> {
+        let last = self.blocks.partition_point(|b| b.start_us <= end_us);
+        let first = self.blocks[..last].partition_point(|b| b.max_end_us < start_us);
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(idx, b)| {
+                b.metadata_events > 0
+                    || ((first..last).contains(idx) && b.overlaps(start_us, end_us))
+            })
+            .map(|(_, b)| b)
+            .collect()
+    }
+}
+
+/// Default index path for an output trace: `trace.json.gz` -> `trace.seek.json`
+pub fn seek_index_path(output_path: &str) -> String {
+    let stem = output_path.strip_suffix(".gz").unwrap_or(output_path);
+    let stem = stem.strip_suffix(".json").unwrap_or(stem);
+    format!("{}.seek.json", stem)
+}
+
+/// Uncompressed text of a member being filled
+#[derive(Default)]
+struct PendingBlock {
+    text: Vec<u8>,
+    events: usize,
+    start_us: Option<f64>,
+    end_us: Option<f64>,
+    metadata_events: usize,
+}
+
+impl PendingBlock {
+    fn record(&mut self, event: &ChromeTraceEvent) {
+        self.events += 1;
+        if event.ph == ChromeTracePhase::Metadata {
+            self.metadata_events += 1;
+            return;
+        }
+        let end = event.ts + event.dur.unwrap_or(0.0);
+        self.start_us = Some(self.start_us.map_or(event.ts, |s| s.min(event.ts)));
+        self.end_us = Some(self.end_us.map_or(end, |e| e.max(end)));
+    }
+}
+
+/// Gzip-compressed JSON written as independent members, indexed by time
+///
+/// See the [module documentation](self) for the layout. Members are closed
+/// at event boundaries only, so each decompresses to whole event lines.
+pub struct SeekableGzSink<W: Write> {
+    output: W,
+    block_size: usize,
+    /// Closed members waiting to be compressed together
+    closed: Vec<PendingBlock>,
+    current: PendingBlock,
+    written_events: usize,
+    compressed: u64,
+    text: u64,
+    /// Start and running maximum end of the members written so far
+    last_start_us: f64,
+    max_end_us: f64,
+    last_span: Option<(u64, u64)>,
+    index: SeekIndex,
+}
+
+impl<W: Write> SeekableGzSink<W> {
+    pub fn new(output: W) -> Self {
+        Self::with_block_size(output, SEEKABLE_BLOCK_SIZE)
+    }
+
+    /// Close members after `block_size` uncompressed bytes instead of
+    /// [`SEEKABLE_BLOCK_SIZE`]
+    pub fn with_block_size(output: W, block_size: usize) -> Self {
+        Self {
+            output,
+            block_size: block_size.max(1),
+            closed: Vec::new(),
+            current: PendingBlock::default(),
+            written_events: 0,
+            compressed: 0,
+            text: 0,
+            last_start_us: 0.0,
+            // Traces rebased to an origin can be entirely negative
+            max_end_us: f64::NEG_INFINITY,
+            last_span: None,
+            index: SeekIndex {
+                version: SEEK_INDEX_VERSION,
+                blocks: Vec::new(),
+            },
+        }
+    }
+
+    /// Index of the members written so far; complete once the trace is finished
+    pub fn index(&self) -> &SeekIndex {
+        &self.index
+    }
+
+    /// The index of the members written, once the trace is finished
+    pub fn into_index(self) -> SeekIndex {
+        self.index
+    }
+
+    /// The underlying writer, once the trace is finished
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    /// Text offset at which the current member's text starts
+    fn current_offset(&self) -> u64 {
+        self.text + self.closed.iter().map(|b| b.text.len() as u64).sum::<u64>()
+    }
+
+    /// Append one event's text to the current member
+    fn push(&mut self, event: &ChromeTraceEvent, json: &[u8]) {
+        if self.written_events > 0 {
+            self.current.text.extend_from_slice(b",\n");
+        }
+        let start = self.current.text.len();
+        self.current.text.extend_from_slice(json);
+        self.last_span = Some((
+            self.current_offset() + start as u64,
+            (self.current.text.len() - start) as u64,
+        ));
+        self.current.record(event);
+        self.written_events += 1;
+    }
+
+    /// Close the current member once it is large enough, or always if
+    /// `force`, compressing a batch of closed members in parallel
+    fn close_block(&mut self, force: bool) -> Result<()> {
+        if force || self.current.text.len() >= self.block_size {
+            let block = std::mem::take(&mut self.current);
+            if !block.text.is_empty() {
+                self.closed.push(block);
+            }
+        }
+        if force || self.closed.len() >= rayon::current_num_threads() {
+            self.compress_closed()?;
+        }
+        Ok(())
+    }
+
+    fn compress_closed(&mut self) -> Result<()> {
+        let members = self
+            .closed
+            .par_iter()
+            .map(|block| {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&block.text)?;
+                encoder.finish()
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        for (block, member) in self.closed.drain(..).zip(members) {
+            self.output.write_all(&member)?;
+            let start_us = block.start_us.unwrap_or(self.last_start_us);
+            let end_us = block.end_us.unwrap_or(start_us);
+            self.last_start_us = start_us;
+            self.max_end_us = self.max_end_us.max(end_us);
+            self.index.blocks.push(SeekBlock {
+                offset: self.compressed,
+                length: member.len() as u64,
+                text_offset: self.text,
+                text_length: block.text.len() as u64,
+                events: block.events,
+                start_us,
+                end_us,
+                max_end_us: self.max_end_us,
+                metadata_events: block.metadata_events,
+            });
+            self.compressed += member.len() as u64;
+            self.text += block.text.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> TraceSink for SeekableGzSink<W> {
+    fn begin(&mut self) -> Result<()> {
+        self.current.text.extend_from_slice(JSON_OPENING);
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &ChromeTraceEvent) -> Result<()> {
+        let json = serde_json::to_vec(event)?;
+        self.write_serialized(event, &json)
+    }
+
+    fn write_serialized(&mut self, event: &ChromeTraceEvent, json: &[u8]) -> Result<()> {
+        self.push(event, json);
+        self.close_block(false)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.current.text.extend_from_slice(JSON_CLOSING);
+        self.close_block(true)?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn last_event_span(&self) -> Option<(u64, u64)> {
+        self.last_span
+    }
+}
+
+/// Events of one member, decompressed from a seekable trace
+pub fn read_block<R: Read + Seek>(
+    trace: &mut R,
+    block: &SeekBlock,
+) -> Result<Vec<ChromeTraceEvent>> {
+    trace.seek(SeekFrom::Start(block.offset))?;
+    let member = trace.by_ref().take(block.length);
+    let mut events = Vec::with_capacity(block.events);
+    for line in BufReader::new(GzDecoder::new(member)).lines() {
+        let line = line?;
+        // Lines are one event each, apart from the separators and the
+        // opening and closing of the JSON text
+        let line = line.trim().trim_end_matches(',');
+        let line = line.strip_prefix("{\"traceEvents\":[").unwrap_or(line);
+        let line = line.strip_suffix("]}").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        events.push(serde_json::from_str(line)?);
+    }
+    Ok(events)
+}
+
+/// Events of a seekable trace overlapping `[start_us, end_us]`, plus its
+/// metadata events, reading only the members the index points at
+pub fn read_time_range(
+    trace_path: &str,
+    index: &SeekIndex,
+    start_us: f64,
+    end_us: f64,
+) -> Result<Vec<ChromeTraceEvent>> {
+    let file = File::open(trace_path).map_err(|e| ConvertError::open_input(trace_path, e))?;
+    let mut trace = BufReader::new(file);
+    let mut events = Vec::new();
+    for block in index.blocks_in_range(start_us, end_us) {
+        events.extend(read_block(&mut trace, block)?.into_iter().filter(|e| {
+            e.ph == ChromeTracePhase::Metadata
+                || (e.ts <= end_us && e.ts + e.dur.unwrap_or(0.0) >= start_us)
+        }));
+    }
+    Ok(events)
+}
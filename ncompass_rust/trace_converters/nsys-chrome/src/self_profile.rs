@@ -0,0 +1,321 @@
+//! Timings of the converter's own phases, as a Chrome trace
+//!
+//! `--self-profile out.json` records how long each phase of a conversion
+//! takes (schema probing, each table read, linking per device, writing) on
+//! the thread that ran it, so the phase dominating a slow input can be seen
+//! in the same viewer as any other trace and attached to an issue.
+//!
+//! Phases nest: a phase started while another is open on the same thread
+//! becomes its child, and its `path` (`convert/read CUPTI_ACTIVITY_KIND_KERNEL`)
+//! names every open ancestor. Work handed to other threads carries the
+//! parent along through [`current_context`] and [`PhaseContext::enter`].
+//!
+//! Recording is off unless [`enable`] is called. Finished phases are also
+//! logged as `info` records (see [`crate::logging`]), so [`phase`] is only a
+//! no-op when neither recording nor phase logging is enabled.
+
+use serde_json::json;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::logging::PHASE_TARGET;
+use crate::models::ChromeTraceEvent;
+
+/// Process holding the converter's phases
+pub const SELF_PROFILE_PROCESS: &str = "nsys-chrome";
+
+/// One finished phase
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    name: String,
+    path: Arc<str>,
+    parent: Option<Arc<str>>,
+    category: &'static str,
+    start_us: f64,
+    dur_us: f64,
+    thread: usize,
+    events: Option<usize>,
+}
+
+/// Recorder for phase timings, shared by every thread of a conversion
+#[derive(Debug)]
+pub struct SelfProfiler {
+    epoch: Instant,
+    spans: Mutex<Vec<Span>>,
+}
+
+impl Default for SelfProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SelfProfiler {
+    /// Start recording; timestamps are relative to now
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            spans: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Time a phase until the returned guard is dropped
+    pub fn phase(&self, name: impl Into<String>, category: &'static str) -> PhaseGuard<'_> {
+        PhaseGuard::start(Some(self), name.into(), category)
+    }
+
+    /// Number of phases recorded so far
+    pub fn len(&self) -> usize {
+        self.spans.lock().unwrap().len()
+    }
+
+    /// Whether no phase has finished yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Recorded phases as complete events, with process and thread names
+    ///
+    /// Threads are numbered in the order they first finished a phase, so the
+    /// thread driving the conversion is usually `Thread 1`.
+    pub fn events(&self) -> Vec<ChromeTraceEvent> {
+        let mut spans = self.spans.lock().unwrap().clone();
+        spans.sort_by(|a, b| a.start_us.total_cmp(&b.start_us));
+
+        let mut thread_numbers: HashMap<usize, usize> = HashMap::new();
+        for span in &spans {
+            let next = thread_numbers.len() + 1;
+            thread_numbers.entry(span.thread).or_insert(next);
+        }
+
+        let mut events = vec![ChromeTraceEvent::metadata(
+            "process_name".to_string(),
+            SELF_PROFILE_PROCESS.to_string(),
+            String::new(),
+            HashMap::from([("name".to_string(), json!(SELF_PROFILE_PROCESS))]),
+        )];
+        let mut numbers: Vec<usize> = thread_numbers.values().copied().collect();
+        numbers.sort_unstable();
+        for number in numbers {
+            events.push(ChromeTraceEvent::metadata(
+                "thread_name".to_string(),
+                SELF_PROFILE_PROCESS.to_string(),
+                format!("Thread {}", number),
+                HashMap::from([("name".to_string(), json!(format!("Worker {}", number)))]),
+            ));
+        }
+        for span in spans {
+            let mut event = ChromeTraceEvent::complete(
+                span.name,
+                span.start_us,
+                span.dur_us,
+                SELF_PROFILE_PROCESS.to_string(),
+                format!("Thread {}", thread_numbers[&span.thread]),
+                span.category.to_string(),
+            )
+            .with_arg("duration_ms", span.dur_us.round() / 1000.0)
+            .with_arg("path", span.path.as_ref());
+            if let Some(parent) = span.parent {
+                event = event.with_arg("parent", parent.as_ref());
+            }
+            if let Some(count) = span.events {
+                event = event.with_arg("events", count);
+            }
+            events.push(event);
+        }
+        events
+    }
+
+    /// Write the recorded phases as a Chrome trace JSON file
+    pub fn write(&self, path: &str) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(
+            &mut writer,
+            &json!({ "traceEvents": self.events(), "displayTimeUnit": "ms" }),
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// The open phase a thread's new phases nest under
+///
+/// Capture it with [`current_context`] before handing work to a rayon pool or
+/// scoped thread, and [`enter`](Self::enter) it on the worker so phases
+/// started there keep their parent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhaseContext {
+    path: Option<Arc<str>>,
+}
+
+impl PhaseContext {
+    /// Slash-separated names of the open phases, outermost first
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Make this the calling thread's context until the guard is dropped
+    pub fn enter(&self) -> ContextGuard {
+        ContextGuard {
+            previous: CURRENT.with(|current| current.replace(self.clone())),
+        }
+    }
+}
+
+/// Restores the thread's previous phase context when dropped
+#[must_use = "the context is left as soon as the guard is dropped"]
+pub struct ContextGuard {
+    previous: PhaseContext,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let previous = std::mem::take(&mut self.previous);
+        CURRENT.with(|current| current.replace(previous));
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<PhaseContext> = RefCell::default();
+}
+
+/// The calling thread's innermost open phase, to carry into other threads
+pub fn current_context() -> PhaseContext {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// A phase in progress, recorded and/or logged when it ends
+struct ActivePhase<'a> {
+    profiler: Option<&'a SelfProfiler>,
+    name: String,
+    path: Arc<str>,
+    parent: PhaseContext,
+    category: &'static str,
+    start: Instant,
+}
+
+/// Times a phase; the phase ends when the guard is dropped
+#[must_use = "the phase ends as soon as the guard is dropped"]
+pub struct PhaseGuard<'a> {
+    active: Option<ActivePhase<'a>>,
+    events: Option<usize>,
+}
+
+impl<'a> PhaseGuard<'a> {
+    fn start(profiler: Option<&'a SelfProfiler>, name: String, category: &'static str) -> Self {
+        let parent = current_context();
+        let path: Arc<str> = match parent.path() {
+            Some(outer) => format!("{}/{}", outer, name).into(),
+            None => name.as_str().into(),
+        };
+        CURRENT.with(|current| {
+            current.replace(PhaseContext {
+                path: Some(path.clone()),
+            })
+        });
+        Self {
+            active: Some(ActivePhase {
+                profiler,
+                name,
+                path,
+                parent,
+                category,
+                start: Instant::now(),
+            }),
+            events: None,
+        }
+    }
+
+    /// Guard that records nothing
+    fn disabled() -> Self {
+        Self {
+            active: None,
+            events: None,
+        }
+    }
+
+    /// Record how many events the phase produced or handled
+    pub fn set_events(&mut self, count: usize) {
+        self.events = Some(count);
+    }
+}
+
+impl Drop for PhaseGuard<'_> {
+    fn drop(&mut self) {
+        let Some(phase) = self.active.take() else {
+            return;
+        };
+        CURRENT.with(|current| current.replace(phase.parent.clone()));
+        let dur_us = phase.start.elapsed().as_secs_f64() * 1e6;
+        let duration_ms = dur_us.round() / 1000.0;
+        match self.events {
+            Some(events) => log::info!(
+                target: PHASE_TARGET,
+                phase = phase.name.as_str(), path = phase.path.as_ref(), category = phase.category,
+                duration_ms, events;
+                "{} took {} ms ({} events)", phase.name, duration_ms, events
+            ),
+            None => log::info!(
+                target: PHASE_TARGET,
+                phase = phase.name.as_str(), path = phase.path.as_ref(), category = phase.category,
+                duration_ms;
+                "{} took {} ms", phase.name, duration_ms
+            ),
+        }
+
+        let Some(profiler) = phase.profiler else {
+            return;
+        };
+        let span = Span {
+            name: phase.name,
+            path: phase.path,
+            parent: phase.parent.path,
+            category: phase.category,
+            start_us: phase.start.duration_since(profiler.epoch).as_secs_f64() * 1e6,
+            dur_us,
+            thread: current_thread(),
+            events: self.events,
+        };
+        profiler.spans.lock().unwrap().push(span);
+    }
+}
+
+/// Stable per-process number of the calling thread
+fn current_thread() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static THREAD: usize = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    THREAD.with(|thread| *thread)
+}
+
+static GLOBAL: OnceLock<SelfProfiler> = OnceLock::new();
+
+/// Start recording phases for the rest of the process
+pub fn enable() -> &'static SelfProfiler {
+    GLOBAL.get_or_init(SelfProfiler::new)
+}
+
+/// The process-wide recorder, if [`enable`] was called
+pub fn global() -> Option<&'static SelfProfiler> {
+    GLOBAL.get()
+}
+
+/// Time a phase with the process-wide recorder and log it when it ends
+///
+/// A no-op unless recording is enabled or phase records would be logged.
+pub fn phase(name: impl Into<String>, category: &'static str) -> PhaseGuard<'static> {
+    match GLOBAL.get() {
+        Some(profiler) => profiler.phase(name, category),
+        None if log::log_enabled!(target: PHASE_TARGET, log::Level::Info) => {
+            PhaseGuard::start(None, name.into(), category)
+        }
+        None => PhaseGuard::disabled(),
+    }
+}
@@ -0,0 +1,629 @@
+//! Conversion-as-a-service over a minimal HTTP/1.1 API
+//!
+//! Endpoints:
+//! - `POST /jobs` — submit a conversion. The body is either the raw SQLite file or,
+//!   with `Content-Type: application/json`, `{"path": "/server/side.sqlite"}` (only
+//!   when local paths are allowed). Optional `?types=kernel,nvtx` selects activities.
+//!   Responds `202 {"id": ...}`, or `503` when the job queue is full.
+//! - `GET /jobs/{id}` — job status (`queued`, `running`, `done`, `failed`).
+//! - `GET /jobs/{id}/result` — gzip-compressed Chrome trace once the job is done.
+//! - `DELETE /jobs/{id}` — drop a finished job and its temporary files. A queued or
+//!   running job is cancelled instead (`202 {"cancelled": true}`) and ends `cancelled`.
+//!
+//! A fixed pool of `max_concurrent_jobs` workers runs the conversions; up to
+//! `max_queued_jobs` further jobs wait queued and later submissions are refused.
+//! Connections are handled by a pool of `max_connections` threads; further
+//! connections wait in the listen backlog until one is free.
+//! Every job owns a temporary directory that is removed when the job is deleted or
+//! expires after `job_ttl`.
+
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cancel::CancellationToken;
+use crate::category::EventCategory;
+use crate::converter::NsysChromeConverter;
+use crate::error::{ConvertError, Result};
+use crate::intern::StringInterner;
+use crate::models::ConversionOptions;
+use crate::writer::ChromeTraceWriter;
+
+/// Largest accepted uploaded SQLite file
+const MAX_UPLOAD_BYTES: u64 = 8 << 30;
+
+/// Largest accepted JSON request body
+const MAX_JSON_BYTES: u64 = 64 * 1024;
+
+/// File name of the converted trace inside a job's directory
+const RESULT_FILE: &str = "trace.json.gz";
+
+/// How long a read from a client may block before its connection is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for the rest of a request after responding
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Most unread request bytes discarded after responding before closing anyway
+const MAX_DRAIN_BYTES: u64 = 64 * 1024;
+
+/// Service configuration
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    /// Address to listen on
+    pub addr: SocketAddr,
+    /// Maximum number of conversions running at the same time
+    pub max_concurrent_jobs: usize,
+    /// Maximum number of jobs waiting for a worker; submissions beyond it get `503`
+    pub max_queued_jobs: usize,
+    /// Maximum number of connections handled at the same time
+    pub max_connections: usize,
+    /// How long finished jobs (and their files) are kept
+    pub job_ttl: Duration,
+    /// Allow jobs that reference files already on the server by path
+    pub allow_local_paths: bool,
+    /// Base conversion options for every job
+    pub options: ConversionOptions,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            max_concurrent_jobs: 2,
+            max_queued_jobs: 8,
+            max_connections: 16,
+            job_ttl: Duration::from_secs(3600),
+            allow_local_paths: false,
+            options: ConversionOptions::default(),
+        }
+    }
+}
+
+/// Lifecycle state of a conversion job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed(_) => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A submitted conversion job
+struct Job {
+    status: JobStatus,
+    /// Temporary directory holding the upload and the result; removed on drop
+    workdir: tempfile::TempDir,
+    updated_at: Instant,
+    /// Stops the conversion when the job is deleted before it finishes
+    cancellation: CancellationToken,
+}
+
+/// A job waiting for a worker
+struct QueuedJob {
+    id: String,
+    input_path: PathBuf,
+    output_path: PathBuf,
+    options: ConversionOptions,
+    cancellation: CancellationToken,
+}
+
+/// Shared job table and scheduling state
+struct JobManager {
+    config: ServiceConfig,
+    jobs: Mutex<HashMap<String, Job>>,
+    queue: SyncSender<QueuedJob>,
+    next_id: AtomicU64,
+}
+
+impl JobManager {
+    /// Create the job table and start its worker pool
+    fn start(config: ServiceConfig) -> Arc<Self> {
+        let (queue, queued) = mpsc::sync_channel(config.max_queued_jobs);
+        let workers = config.max_concurrent_jobs.max(1);
+        let manager = Arc::new(Self {
+            config,
+            jobs: Mutex::new(HashMap::new()),
+            queue,
+            next_id: AtomicU64::new(1),
+        });
+
+        let queued = Arc::new(Mutex::new(queued));
+        for _ in 0..workers {
+            let manager = Arc::clone(&manager);
+            let queued = Arc::clone(&queued);
+            thread::spawn(move || manager.work(&queued));
+        }
+        manager
+    }
+
+    /// Run queued jobs one at a time, for as long as the service runs
+    fn work(&self, queued: &Mutex<Receiver<QueuedJob>>) {
+        loop {
+            // The lock is only held while idle, so busy workers never block the others
+            let Ok(job) = queued.lock().unwrap().recv() else {
+                return;
+            };
+            self.set_status(&job.id, JobStatus::Running);
+
+            let result = convert_job(
+                &job.input_path,
+                &job.output_path,
+                job.options,
+                &job.cancellation,
+            );
+            // Track names and arg keys of this job's events are no longer referenced
+            StringInterner::global().purge();
+            if result.is_err() {
+                // Never serve a partial result
+                let _ = std::fs::remove_file(&job.output_path);
+            }
+
+            self.set_status(
+                &job.id,
+                match result {
+                    Ok(()) => JobStatus::Done,
+                    Err(ConvertError::Cancelled) => JobStatus::Cancelled,
+                    Err(e) => JobStatus::Failed(e.display_chain()),
+                },
+            );
+        }
+    }
+
+    /// Remove finished jobs older than the TTL (dropping their temp directories)
+    fn prune_expired(&self) {
+        let ttl = self.config.job_ttl;
+        self.jobs.lock().unwrap().retain(|_, job| {
+            matches!(job.status, JobStatus::Queued | JobStatus::Running)
+                || job.updated_at.elapsed() < ttl
+        });
+    }
+
+    fn set_status(&self, id: &str, status: JobStatus) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(id) {
+            job.status = status;
+            job.updated_at = Instant::now();
+        }
+    }
+
+    /// Register a job whose input is already at `input_path` and queue it for a worker
+    ///
+    /// Returns `None`, dropping the job, when the queue is full.
+    fn submit(&self, workdir: tempfile::TempDir, input_path: PathBuf, options: ConversionOptions) -> Option<String> {
+        let id = format!("job-{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        let output_path = workdir.path().join(RESULT_FILE);
+        let cancellation = CancellationToken::new();
+
+        self.jobs.lock().unwrap().insert(
+            id.clone(),
+            Job {
+                status: JobStatus::Queued,
+                workdir,
+                updated_at: Instant::now(),
+                cancellation: cancellation.clone(),
+            },
+        );
+
+        let job = QueuedJob {
+            id: id.clone(),
+            input_path,
+            output_path,
+            options,
+            cancellation,
+        };
+        match self.queue.try_send(job) {
+            Ok(()) => Some(id),
+            Err(TrySendError::Full(job) | TrySendError::Disconnected(job)) => {
+                // Dropping the job removes its temporary directory
+                self.jobs.lock().unwrap().remove(&job.id);
+                None
+            }
+        }
+    }
+}
+
+/// Run a single conversion to a gzip-compressed trace
+fn convert_job(
+    input_path: &std::path::Path,
+    output_path: &std::path::Path,
+    options: ConversionOptions,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    // Deleted while queued
+    cancellation.check()?;
+    let input = input_path.to_str().ok_or_else(|| invalid("Input path is not valid UTF-8"))?;
+    let output = output_path.to_str().ok_or_else(|| invalid("Output path is not valid UTF-8"))?;
+    let events = NsysChromeConverter::new(input, Some(options))?
+        .with_cancellation(cancellation.clone())
+        .convert()?;
+    cancellation.check()?;
+    ChromeTraceWriter::write_gz(output, events)
+}
+
+/// Error for a malformed request
+fn invalid(message: &str) -> ConvertError {
+    ConvertError::InvalidInput(message.to_string())
+}
+
+/// Parsed HTTP request head; the body is left unread in the connection
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    content_length: u64,
+}
+
+/// HTTP response
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Body,
+}
+
+/// Body of a response
+enum Body {
+    Bytes(Vec<u8>),
+    /// A file copied to the client as the response is written
+    File(std::fs::File, u64),
+}
+
+impl Body {
+    fn len(&self) -> u64 {
+        match self {
+            Body::Bytes(bytes) => bytes.len() as u64,
+            Body::File(_, len) => *len,
+        }
+    }
+}
+
+impl Response {
+    fn json(status: u16, value: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: Body::Bytes(value.to_string().into_bytes()),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> Result<Request> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or_else(|| invalid("Missing HTTP method"))?.to_string();
+    let target = parts.next().ok_or_else(|| invalid("Missing HTTP request target"))?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let content_length: u64 = headers
+        .get("content-length")
+        .map(|v| v.parse())
+        .transpose()
+        .map_err(|_| invalid("Invalid Content-Length"))?
+        .unwrap_or(0);
+
+    let (path, query_string) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    let query = query_string
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| Ok((percent_decode(k)?, percent_decode(v)?)))
+        .collect::<Result<_>>()?;
+
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query,
+        headers,
+        content_length,
+    })
+}
+
+/// Decode a query string component (`%2C` -> `,`, `+` -> space)
+fn percent_decode(component: &str) -> Result<String> {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let byte = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+                    .ok_or_else(|| invalid("Invalid percent-encoding in query string"))?;
+                decoded.push(byte);
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| invalid("Query string is not valid UTF-8"))
+}
+
+fn write_response(stream: &mut TcpStream, response: Response) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    )?;
+    match response.body {
+        Body::Bytes(bytes) => stream.write_all(&bytes)?,
+        Body::File(file, len) => {
+            let copied = std::io::copy(&mut file.take(len), stream)?;
+            if copied != len {
+                return Err(invalid("Result file ended early"));
+            }
+        }
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+/// Handle `POST /jobs`
+fn handle_submit(manager: &Arc<JobManager>, request: &Request, body: &mut dyn Read) -> Result<Response> {
+    let mut options = manager.config.options.clone();
+    if let Some(types) = request.query.get("types") {
+        match types.split(',').map(EventCategory::parse).collect() {
+            Ok(types) => options.activity_types = types,
+            Err(e) => return Ok(Response::error(400, &e.to_string())),
+        }
+    }
+
+    let workdir = tempfile::Builder::new().prefix("nsys-chrome-job-").tempdir()?;
+    let is_json = request
+        .headers
+        .get("content-type")
+        .map(|ct| ct.starts_with("application/json"))
+        .unwrap_or(false);
+
+    let input_path = if is_json {
+        if request.content_length > MAX_JSON_BYTES {
+            return Ok(Response::error(413, "JSON body too large"));
+        }
+        let mut json_body = Vec::new();
+        body.take(request.content_length).read_to_end(&mut json_body)?;
+        let payload: serde_json::Value = match serde_json::from_slice(&json_body) {
+            Ok(v) => v,
+            Err(_) => return Ok(Response::error(400, "Invalid JSON body")),
+        };
+        let Some(path) = payload.get("path").and_then(|v| v.as_str()) else {
+            return Ok(Response::error(400, "JSON body must contain \"path\""));
+        };
+        if !manager.config.allow_local_paths {
+            return Ok(Response::error(403, "Submitting server-side paths is disabled"));
+        }
+        PathBuf::from(path)
+    } else {
+        if request.content_length == 0 {
+            return Ok(Response::error(400, "Request body must contain the SQLite file"));
+        }
+        if request.content_length > MAX_UPLOAD_BYTES {
+            return Ok(Response::error(413, "Upload too large"));
+        }
+        // Stream the upload straight to disk
+        let upload_path = workdir.path().join("input.sqlite");
+        let mut file = std::fs::File::create(&upload_path)?;
+        let copied = std::io::copy(&mut body.take(request.content_length), &mut file)?;
+        if copied != request.content_length {
+            return Err(invalid("Upload ended early"));
+        }
+        upload_path
+    };
+
+    Ok(match manager.submit(workdir, input_path, options) {
+        Some(id) => Response::json(202, json!({ "id": id, "status": "queued" })),
+        None => Response::error(503, "Job queue is full, retry later"),
+    })
+}
+
+/// Route a request to its handler
+fn handle_request(manager: &Arc<JobManager>, request: &Request, body: &mut dyn Read) -> Result<Response> {
+    manager.prune_expired();
+
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["jobs"]) => handle_submit(manager, request, body),
+        ("GET", ["jobs", id]) => {
+            let jobs = manager.jobs.lock().unwrap();
+            Ok(match jobs.get(*id) {
+                Some(job) => {
+                    let mut body = json!({ "id": id, "status": job.status.as_str() });
+                    if let JobStatus::Failed(error) = &job.status {
+                        body["error"] = json!(error);
+                    }
+                    Response::json(200, body)
+                }
+                None => Response::error(404, "Unknown job"),
+            })
+        }
+        ("GET", ["jobs", id, "result"]) => {
+            // Large results are streamed after the job table is unlocked
+            let path = match manager.jobs.lock().unwrap().get(*id) {
+                Some(job) if job.status == JobStatus::Done => job.workdir.path().join(RESULT_FILE),
+                Some(_) => return Ok(Response::error(409, "Job has not completed successfully")),
+                None => return Ok(Response::error(404, "Unknown job")),
+            };
+            let file = match std::fs::File::open(&path) {
+                Ok(file) => file,
+                // Deleted or expired since the lookup
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok(Response::error(404, "Unknown job"))
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let len = file.metadata()?.len();
+            Ok(Response {
+                status: 200,
+                content_type: "application/gzip",
+                body: Body::File(file, len),
+            })
+        }
+        ("DELETE", ["jobs", id]) => {
+            let mut jobs = manager.jobs.lock().unwrap();
+            Ok(match jobs.get(*id).map(|job| job.status.clone()) {
+                Some(JobStatus::Queued | JobStatus::Running) => {
+                    // The job stays listed until its conversion stops
+                    jobs[*id].cancellation.cancel();
+                    Response::json(202, json!({ "id": id, "cancelled": true }))
+                }
+                Some(_) => {
+                    // Dropping the job removes its temporary directory
+                    jobs.remove(*id);
+                    Response::json(200, json!({ "id": id, "deleted": true }))
+                }
+                None => Response::error(404, "Unknown job"),
+            })
+        }
+        (_, ["jobs", ..]) => Ok(Response::error(405, "Method not allowed")),
+        _ => Ok(Response::error(404, "Not found")),
+    }
+}
+
+fn handle_connection(manager: &Arc<JobManager>, stream: TcpStream) {
+    // A client that stops sending must not hold a connection thread forever
+    if let Err(e) = stream.set_read_timeout(Some(READ_TIMEOUT)) {
+        log::debug!("Failed to set read timeout: {}", e);
+        return;
+    }
+    let mut reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(e) => {
+            log::debug!("Failed to clone connection: {}", e);
+            return;
+        }
+    };
+    let response = match read_request(&mut reader) {
+        Ok(request) => handle_request(manager, &request, &mut reader)
+            .unwrap_or_else(|e| Response::error(500, &e.display_chain())),
+        Err(e) => Response::error(400, &e.display_chain()),
+    };
+    let mut stream = stream;
+    if let Err(e) = write_response(&mut stream, response) {
+        log::debug!("Failed to write HTTP response: {}", e);
+    }
+
+    // Closing with an unread body resets the connection, which can discard
+    // the response before the client reads it, e.g. when rejecting a request.
+    // A client still sending a large body after that gets the reset anyway.
+    let _ = stream.shutdown(Shutdown::Write);
+    let _ = stream.set_read_timeout(Some(DRAIN_TIMEOUT));
+    let _ = std::io::copy(&mut reader.take(MAX_DRAIN_BYTES), &mut std::io::sink());
+}
+
+/// Handle accepted connections one at a time, for as long as the service runs
+fn handle_connections(manager: &Arc<JobManager>, accepted: &Mutex<Receiver<TcpStream>>) {
+    loop {
+        // The lock is only held while idle, as for the job workers
+        let Ok(stream) = accepted.lock().unwrap().recv() else {
+            return;
+        };
+        handle_connection(manager, stream);
+    }
+}
+
+/// Conversion service bound to a listening socket
+pub struct ConversionService {
+    listener: TcpListener,
+    manager: Arc<JobManager>,
+}
+
+impl ConversionService {
+    /// Bind the service to the configured address
+    pub fn bind(config: ServiceConfig) -> Result<Self> {
+        let listener = TcpListener::bind(config.addr).map_err(|source| ConvertError::Bind {
+            addr: config.addr,
+            source,
+        })?;
+        Ok(Self {
+            listener,
+            manager: JobManager::start(config),
+        })
+    }
+
+    /// Address the service is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serve requests until the process exits
+    pub fn run(self) -> Result<()> {
+        // Connections are only accepted while a handler is free to take them
+        let (connections, accepted) = mpsc::sync_channel(0);
+        let accepted = Arc::new(Mutex::new(accepted));
+        for _ in 0..self.manager.config.max_connections.max(1) {
+            let manager = Arc::clone(&self.manager);
+            let accepted = Arc::clone(&accepted);
+            thread::spawn(move || handle_connections(&manager, &accepted));
+        }
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    log::debug!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+            if connections.send(stream).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+//! Capture sessions within one export
+//!
+//! An export can hold several capture ranges (e.g. repeated
+//! `cudaProfilerStart`/`cudaProfilerStop` pairs), separated by stretches with
+//! no events at all. Left in one timeline they sit far apart and their
+//! per-session structure is hard to compare. Sessions are detected from such
+//! idle stretches; each one can be selected or written on its own, rebased so
+//! it starts at time zero and tagged with a `trace_session` metadata event
+//! recording where it was in the capture.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::analysis::time_origin::rebase_timestamps;
+use crate::error::{ConvertError, Result};
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Idle stretches at least this long separate sessions: 1 s
+pub const DEFAULT_SESSION_GAP_NS: i64 = 1_000_000_000;
+
+/// Metadata event recording a session's index and original time range
+pub const SESSION_EVENT: &str = "trace_session";
+
+/// One capture session: a time range separated from the others by idle time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    /// 1-based position in time order
+    pub index: usize,
+    pub start_ns: i64,
+    pub end_ns: i64,
+}
+
+/// Event time range in nanoseconds; instants and counters have no duration
+fn span_ns(event: &ChromeTraceEvent) -> (i64, i64) {
+    let start = (event.ts * 1000.0).round() as i64;
+    let dur = event.dur.filter(|d| d.is_finite()).unwrap_or(0.0);
+    (start, start + (dur * 1000.0).round() as i64)
+}
+
+/// Split the time covered by events at idle stretches of at least `min_gap_ns`
+pub fn detect_sessions(events: &[ChromeTraceEvent], min_gap_ns: i64) -> Vec<Session> {
+    let mut spans: Vec<(i64, i64)> = events
+        .iter()
+        .filter(|e| e.ph != ChromeTracePhase::Metadata && e.ts.is_finite())
+        .map(span_ns)
+        .collect();
+    spans.sort_unstable();
+
+    let mut sessions: Vec<Session> = Vec::new();
+    for (start, end) in spans {
+        match sessions.last_mut() {
+            Some(last) if start - last.end_ns < min_gap_ns => last.end_ns = last.end_ns.max(end),
+            _ => sessions.push(Session {
+                index: sessions.len() + 1,
+                start_ns: start,
+                end_ns: end,
+            }),
+        }
+    }
+    sessions
+}
+
+/// Split events into sessions, each rebased to start at zero
+///
+/// Metadata events name tracks, so every session gets a copy of them.
+pub fn split_sessions(
+    events: Vec<ChromeTraceEvent>,
+    min_gap_ns: i64,
+) -> Vec<Vec<ChromeTraceEvent>> {
+    let sessions = detect_sessions(&events, min_gap_ns);
+    let (metadata, events): (Vec<_>, Vec<_>) = events
+        .into_iter()
+        .partition(|e| e.ph == ChromeTracePhase::Metadata);
+
+    let mut per_session: Vec<Vec<ChromeTraceEvent>> = vec![Vec::new(); sessions.len()];
+    for event in events {
+        // Events without a finite time go with the first session
+        let (start, _) = span_ns(&event);
+        let idx = sessions
+            .partition_point(|s| s.start_ns <= start)
+            .saturating_sub(1);
+        per_session[idx].push(event);
+    }
+
+    sessions
+        .iter()
+        .zip(per_session)
+        .map(|(session, mut session_events)| {
+            rebase_timestamps(&mut session_events, session.start_ns);
+            let pid = session_events[0].pid.to_string();
+            let mut out = metadata.clone();
+            out.push(session_event(session, sessions.len(), pid));
+            out.extend(session_events);
+            out
+        })
+        .collect()
+}
+
+/// Keep only session `index` (1-based), rebased to start at zero
+pub fn select_session(
+    events: Vec<ChromeTraceEvent>,
+    index: usize,
+    min_gap_ns: i64,
+) -> Result<Vec<ChromeTraceEvent>> {
+    let mut sessions = split_sessions(events, min_gap_ns);
+    if index == 0 || index > sessions.len() {
+        return Err(ConvertError::InvalidOption(format!(
+            "Session {} requested but the input has {} session{}",
+            index,
+            sessions.len(),
+            if sessions.len() == 1 { "" } else { "s" }
+        )));
+    }
+    Ok(sessions.swap_remove(index - 1))
+}
+
+/// Output path for one session: `trace.json.gz` -> `trace.session-2.json.gz`
+pub fn session_path(output_path: &str, index: usize) -> String {
+    let (stem, suffix) = if let Some(stem) = output_path.strip_suffix(".json.gz") {
+        (stem, ".json.gz")
+    } else if let Some(stem) = output_path.strip_suffix(".json") {
+        (stem, ".json")
+    } else {
+        (output_path, "")
+    };
+    format!("{}.session-{}{}", stem, index, suffix)
+}
+
+/// Metadata event scoped to a process of the session, so it adds no track
+fn session_event(session: &Session, count: usize, pid: String) -> ChromeTraceEvent {
+    let args = HashMap::from([
+        ("index".to_string(), json!(session.index)),
+        ("sessions".to_string(), json!(count)),
+        ("start_ns".to_string(), json!(session.start_ns)),
+        ("end_ns".to_string(), json!(session.end_ns)),
+    ]);
+    ChromeTraceEvent::metadata(SESSION_EVENT.to_string(), pid, String::new(), args)
+}
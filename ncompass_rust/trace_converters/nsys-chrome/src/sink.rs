@@ -0,0 +1,330 @@
+//! Destinations of written traces
+//!
+//! [`ChromeTraceWriter`] fixes overlaps, maps track IDs and builds outlines;
+//! where the resulting events go is up to a [`TraceSink`]. The built-in sinks
+//! write JSON to any [`Write`], plain ([`JsonSink`]) or gzip-compressed
+//! ([`GzSink`]), or keep the events in memory ([`MemorySink`]). A trace that
+//! keeps growing while it is read, as in follow mode, goes to a
+//! [`JsonArraySink`], which stays loadable whenever it is flushed. Other
+//! destinations, such as an upload or an HTTP response body, implement the
+//! trait and receive events as they are written, with no temporary file.
+//!
+//! [`ChromeTraceWriter`]: crate::writer::ChromeTraceWriter
+
+use gzp::deflate::Gzip;
+use gzp::par::compress::{ParCompress, ParCompressBuilder};
+use gzp::ZWriter;
+use std::io::{BufWriter, Write};
+
+use crate::error::{ConvertError, Result};
+use crate::models::ChromeTraceEvent;
+
+/// Opening of the JSON text; each event goes on its own line, as Perfetto's
+/// parser struggles with very long lines
+const JSON_OPENING: &[u8] = b"{\"traceEvents\":[\n";
+
+/// Closing of the JSON text
+const JSON_CLOSING: &[u8] = b"\n]}";
+
+/// Opening of a trace in the JSON Array Format
+const JSON_ARRAY_OPENING: &[u8] = b"[\n";
+
+/// Closing of a trace in the JSON Array Format, which viewers do not require
+const JSON_ARRAY_CLOSING: &[u8] = b"\n]\n";
+
+/// Batch of JSON text handed to writers at once: 256KB
+const BATCH_SIZE: usize = 256 * 1024;
+
+/// Receiver of the events of one trace, in write order
+pub trait TraceSink {
+    /// Start the trace, before any event
+    fn begin(&mut self) -> Result<()>;
+
+    /// Take one event, ready to be written as is
+    fn write_event(&mut self, event: &ChromeTraceEvent) -> Result<()>;
+
+    /// Take one event together with its JSON text, serialized by the writer
+    /// ahead of time
+    ///
+    /// Sinks producing JSON text append `json` rather than serializing the
+    /// event again; by default it is ignored and the event goes to
+    /// [`Self::write_event`].
+    fn write_serialized(&mut self, event: &ChromeTraceEvent, json: &[u8]) -> Result<()> {
+        let _ = json;
+        self.write_event(event)
+    }
+
+    /// Hand on the events taken so far, without ending the trace
+    ///
+    /// Only sinks whose destination can be read while the trace grows need
+    /// to do anything; by default this does nothing.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// End the trace, flushing everything to the destination
+    fn finish(&mut self) -> Result<()>;
+
+    /// Offset and length in the uncompressed JSON text of the last event
+    /// written, for sinks that produce JSON text (used for outlines)
+    fn last_event_span(&self) -> Option<(u64, u64)> {
+        None
+    }
+}
+
+/// JSON text assembled in batches, tracking where each event lands
+#[derive(Default)]
+struct JsonText {
+    batch: Vec<u8>,
+    /// Bytes handed on before the current batch
+    flushed: u64,
+    events: usize,
+    last_span: Option<(u64, u64)>,
+}
+
+impl JsonText {
+    fn push_event(&mut self, event: &ChromeTraceEvent) -> Result<()> {
+        let start = self.start_event();
+        serde_json::to_writer(&mut self.batch, event)?;
+        self.end_event(start);
+        Ok(())
+    }
+
+    /// Append an event already serialized to JSON
+    fn push_serialized(&mut self, json: &[u8]) {
+        let start = self.start_event();
+        self.batch.extend_from_slice(json);
+        self.end_event(start);
+    }
+
+    /// Separate the next event from the previous one, returning where it starts
+    fn start_event(&mut self) -> usize {
+        if self.events > 0 {
+            self.batch.extend_from_slice(b",\n");
+        }
+        self.batch.len()
+    }
+
+    fn end_event(&mut self, start: usize) {
+        let length = (self.batch.len() - start) as u64;
+        self.last_span = Some((self.flushed + start as u64, length));
+        self.events += 1;
+    }
+
+    /// Hand the batch to `output` once it is large enough, or always if `force`
+    fn drain_into(&mut self, output: &mut impl Write, force: bool) -> Result<()> {
+        if force || self.batch.len() >= BATCH_SIZE {
+            output.write_all(&self.batch)?;
+            self.flushed += self.batch.len() as u64;
+            self.batch.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Plain JSON written to any writer, e.g. a file, stdout or a `Vec<u8>`
+pub struct JsonSink<W: Write> {
+    output: BufWriter<W>,
+    text: JsonText,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output: BufWriter::with_capacity(BATCH_SIZE, output),
+            text: JsonText::default(),
+        }
+    }
+
+    /// The underlying writer, once the trace is finished
+    pub fn into_inner(self) -> Result<W> {
+        self.output
+            .into_inner()
+            .map_err(|e| ConvertError::Output(e.into_error()))
+    }
+}
+
+impl<W: Write> TraceSink for JsonSink<W> {
+    fn begin(&mut self) -> Result<()> {
+        self.text.batch.extend_from_slice(JSON_OPENING);
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &ChromeTraceEvent) -> Result<()> {
+        self.text.push_event(event)?;
+        // Events go straight to the buffered writer
+        self.text.drain_into(&mut self.output, true)
+    }
+
+    fn write_serialized(&mut self, _event: &ChromeTraceEvent, json: &[u8]) -> Result<()> {
+        self.text.push_serialized(json);
+        self.text.drain_into(&mut self.output, true)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.text.batch.extend_from_slice(JSON_CLOSING);
+        self.text.drain_into(&mut self.output, true)?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn last_event_span(&self) -> Option<(u64, u64)> {
+        self.text.last_span
+    }
+}
+
+/// Plain JSON in the JSON Array Format: a bare list of events
+///
+/// The format allows the closing bracket to be missing, so whatever has been
+/// flushed is a trace Perfetto and `chrome://tracing` can load, even while
+/// more events are still being appended.
+pub struct JsonArraySink<W: Write> {
+    output: BufWriter<W>,
+    text: JsonText,
+}
+
+impl<W: Write> JsonArraySink<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output: BufWriter::with_capacity(BATCH_SIZE, output),
+            text: JsonText::default(),
+        }
+    }
+
+    /// Events taken so far
+    pub fn events(&self) -> usize {
+        self.text.events
+    }
+
+    /// The underlying writer, once the trace is finished
+    pub fn into_inner(self) -> Result<W> {
+        self.output
+            .into_inner()
+            .map_err(|e| ConvertError::Output(e.into_error()))
+    }
+}
+
+impl<W: Write> TraceSink for JsonArraySink<W> {
+    fn begin(&mut self) -> Result<()> {
+        self.text.batch.extend_from_slice(JSON_ARRAY_OPENING);
+        self.text.drain_into(&mut self.output, true)
+    }
+
+    fn write_event(&mut self, event: &ChromeTraceEvent) -> Result<()> {
+        self.text.push_event(event)?;
+        self.text.drain_into(&mut self.output, true)
+    }
+
+    fn write_serialized(&mut self, _event: &ChromeTraceEvent, json: &[u8]) -> Result<()> {
+        self.text.push_serialized(json);
+        self.text.drain_into(&mut self.output, true)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.text.batch.extend_from_slice(JSON_ARRAY_CLOSING);
+        self.text.drain_into(&mut self.output, true)?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn last_event_span(&self) -> Option<(u64, u64)> {
+        self.text.last_span
+    }
+}
+
+/// Gzip-compressed JSON, compressed pigz-style on all CPU cores
+///
+/// Output is standard gzip.
+pub struct GzSink {
+    encoder: ParCompress<Gzip>,
+    text: JsonText,
+}
+
+impl GzSink {
+    pub fn new<W: Write + Send + 'static>(output: W) -> Self {
+        Self {
+            encoder: ParCompressBuilder::new().from_writer(output),
+            text: JsonText {
+                batch: Vec::with_capacity(BATCH_SIZE + BATCH_SIZE / 8),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl TraceSink for GzSink {
+    fn begin(&mut self) -> Result<()> {
+        self.text.batch.extend_from_slice(JSON_OPENING);
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &ChromeTraceEvent) -> Result<()> {
+        self.text.push_event(event)?;
+        // Batching reduces the number of writes to the encoder
+        self.text.drain_into(&mut self.encoder, false)
+    }
+
+    fn write_serialized(&mut self, _event: &ChromeTraceEvent, json: &[u8]) -> Result<()> {
+        self.text.push_serialized(json);
+        self.text.drain_into(&mut self.encoder, false)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.text.batch.extend_from_slice(JSON_CLOSING);
+        self.text.drain_into(&mut self.encoder, true)?;
+        self.encoder
+            .finish()
+            .map_err(|e| ConvertError::Output(std::io::Error::other(e)))
+    }
+
+    fn last_event_span(&self) -> Option<(u64, u64)> {
+        self.text.last_span
+    }
+}
+
+/// Events kept in memory, as they would have been written
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    events: Vec<ChromeTraceEvent>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events written so far
+    pub fn events(&self) -> &[ChromeTraceEvent] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<ChromeTraceEvent> {
+        self.events
+    }
+}
+
+impl TraceSink for MemorySink {
+    fn begin(&mut self) -> Result<()> {
+        self.events.clear();
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &ChromeTraceEvent) -> Result<()> {
+        self.events.push(event.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+//! Virtual thread IDs grouped by category or by OS thread
+//!
+//! Track names such as "NVTX Thread 5" and "CUDA API Thread 5" sort and
+//! number unpredictably once a viewer or the numeric-ID mode turns them into
+//! integers: the two tracks of thread 5 may collide or land far apart. The
+//! allocator identifies each track of a process by its category and raw OS
+//! thread (`raw_tid`), orders the tracks by the chosen [`TidGrouping`] and
+//! numbers them from 1. Events get the numbers as tids, the readable names
+//! move to `thread_name` metadata, and `thread_sort_index` metadata pins the
+//! order. Numbers only depend on which tracks exist, not on event order, so
+//! the same capture always gets the same tids.
+
+use serde_json::json;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::error::{ConvertError, Result};
+use crate::intern::InternedStr;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase, TidGrouping};
+
+/// Metadata event fixing a thread's position within its process
+pub const THREAD_SORT_INDEX_EVENT: &str = "thread_sort_index";
+
+/// Order of categories within a process: device work first, then host threads
+const CATEGORY_ORDER: &[&str] = &[
+    "kernel",
+    "memcpy",
+    "nvlink",
+    "nvtx-kernel",
+    "nvtx",
+    "nvtx-mark",
+    "cuda_api",
+    "mpi",
+    "osrt",
+    "sched",
+    "thread-state",
+    "wddm",
+    "graphics",
+];
+
+/// Parse `category` or `thread` into a tid grouping
+pub fn parse_tid_grouping(value: &str) -> Result<TidGrouping> {
+    match value.trim() {
+        "category" => Ok(TidGrouping::Category),
+        "thread" => Ok(TidGrouping::Thread),
+        _ => Err(ConvertError::InvalidOption(format!(
+            "Invalid tid grouping '{}' (use category or thread)",
+            value
+        ))),
+    }
+}
+
+/// What identifies a track within its process
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackKey {
+    /// Category of the track's events (empty for metadata-only tracks)
+    pub category: String,
+    /// OS thread the track's events came from, if they record one
+    pub raw_tid: Option<i64>,
+    /// Original tid string, telling apart tracks of the same thread and category
+    pub name: InternedStr,
+}
+
+impl TrackKey {
+    fn category_order(&self) -> (usize, &str) {
+        let rank = CATEGORY_ORDER
+            .iter()
+            .position(|&c| c == self.category)
+            .unwrap_or(CATEGORY_ORDER.len());
+        (rank, &self.category)
+    }
+
+    /// Tracks without an OS thread (GPU streams) come first in both groupings
+    fn thread_order(&self) -> (bool, Option<i64>) {
+        (self.raw_tid.is_some(), self.raw_tid)
+    }
+
+    /// Order of two tracks of a process under a grouping
+    fn cmp_in(&self, other: &Self, grouping: TidGrouping) -> Ordering {
+        let order = match grouping {
+            TidGrouping::Category => self
+                .category_order()
+                .cmp(&other.category_order())
+                .then_with(|| self.thread_order().cmp(&other.thread_order())),
+            TidGrouping::Thread => self
+                .thread_order()
+                .cmp(&other.thread_order())
+                .then_with(|| self.category_order().cmp(&other.category_order())),
+        };
+        order.then_with(|| self.name.as_str().cmp(other.name.as_str()))
+    }
+}
+
+/// Whether an event names the category and thread of its track
+fn defines_track(event: &ChromeTraceEvent) -> bool {
+    !matches!(
+        event.ph,
+        ChromeTracePhase::Metadata
+            | ChromeTracePhase::FlowStart
+            | ChromeTracePhase::FlowStep
+            | ChromeTracePhase::FlowFinish
+    )
+}
+
+/// Virtual tids of every track in a trace
+#[derive(Debug, Default)]
+pub struct TidAllocator {
+    /// (pid, original tid) -> virtual tid
+    tids: HashMap<(InternedStr, InternedStr), u32>,
+    /// Tracks per process in virtual tid order
+    tracks: BTreeMap<InternedStr, Vec<TrackKey>>,
+}
+
+impl TidAllocator {
+    /// Number the tracks of `events` under `grouping`
+    ///
+    /// A track's category and thread come from its first event that is not
+    /// metadata or a flow; flows and metadata follow the track they sit on.
+    pub fn from_events(events: &[ChromeTraceEvent], grouping: TidGrouping) -> Self {
+        let mut keys: HashMap<(InternedStr, InternedStr), TrackKey> = HashMap::new();
+        for event in events.iter().filter(|e| !e.tid.is_empty()) {
+            let key = keys
+                .entry((event.pid.clone(), event.tid.clone()))
+                .or_insert_with(|| TrackKey {
+                    name: event.tid.clone(),
+                    ..Default::default()
+                });
+            if key.category.is_empty() && defines_track(event) {
+                key.category = event.cat.to_string();
+                key.raw_tid = event.args.get("raw_tid").and_then(|v| v.as_i64());
+            }
+        }
+
+        let mut tracks: BTreeMap<InternedStr, Vec<TrackKey>> = BTreeMap::new();
+        for ((pid, _), key) in keys {
+            tracks.entry(pid).or_default().push(key);
+        }
+        let mut tids = HashMap::new();
+        for (pid, keys) in &mut tracks {
+            keys.sort_by(|a, b| a.cmp_in(b, grouping));
+            for (idx, key) in keys.iter().enumerate() {
+                tids.insert((pid.clone(), key.name.clone()), idx as u32 + 1);
+            }
+        }
+        Self { tids, tracks }
+    }
+
+    /// Virtual tid of a track, if it was seen
+    pub fn virtual_tid(&self, pid: &str, tid: &str) -> Option<u32> {
+        self.tids
+            .get(&(InternedStr::new(pid), InternedStr::new(tid)))
+            .copied()
+    }
+
+    /// Tracks of a process in virtual tid order
+    pub fn tracks(&self, pid: &str) -> &[TrackKey] {
+        self.tracks.get(pid).map_or(&[], |keys| keys.as_slice())
+    }
+
+    /// Replace tids with their virtual tids and add name and sort-index metadata
+    ///
+    /// Existing `thread_name` events are kept; tracks without one are named
+    /// after their original tid.
+    pub fn apply(&self, events: &mut Vec<ChromeTraceEvent>) {
+        let mut named: HashSet<(InternedStr, u32)> = HashSet::new();
+        for event in events.iter_mut() {
+            let Some(&tid) = self.tids.get(&(event.pid.clone(), event.tid.clone())) else {
+                continue;
+            };
+            if event.ph == ChromeTracePhase::Metadata && event.name == "thread_name" {
+                named.insert((event.pid.clone(), tid));
+            }
+            event.tid = InternedStr::from(tid.to_string());
+        }
+
+        for (pid, keys) in &self.tracks {
+            for (idx, key) in keys.iter().enumerate() {
+                let tid = idx as u32 + 1;
+                if !named.contains(&(pid.clone(), tid)) {
+                    events.push(ChromeTraceEvent::metadata(
+                        "thread_name".to_string(),
+                        pid.to_string(),
+                        tid.to_string(),
+                        HashMap::from([("name".to_string(), json!(key.name.to_string()))]),
+                    ));
+                }
+                events.push(ChromeTraceEvent::metadata(
+                    THREAD_SORT_INDEX_EVENT.to_string(),
+                    pid.to_string(),
+                    tid.to_string(),
+                    HashMap::from([("sort_index".to_string(), json!(tid))]),
+                ));
+            }
+        }
+    }
+}
+
+/// Give every track of `events` a virtual tid under `grouping`
+///
+/// Returns the number of tracks renumbered.
+pub fn virtualize_tids(events: &mut Vec<ChromeTraceEvent>, grouping: TidGrouping) -> usize {
+    let allocator = TidAllocator::from_events(events, grouping);
+    allocator.apply(events);
+    allocator.tids.len()
+}
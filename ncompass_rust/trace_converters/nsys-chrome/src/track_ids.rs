@@ -0,0 +1,278 @@
+//! Numeric pid/tid assignment for tools that reject string track IDs
+//!
+//! Events normally use human-readable pid/tid strings ("Device 0", "Stream 7").
+//! In numeric mode every process and thread is given a small integer in order of
+//! first appearance, and the readable names move into `process_name` /
+//! `thread_name` metadata events. The mapping can be saved as a sidecar JSON file.
+
+use serde::Serialize;
+use serde_json::json;
+use serde_json::ser::Formatter;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::error::{ConvertError, Result};
+use crate::intern::InternedStr;
+use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+
+/// Thread ID used for process-scoped metadata events (threads start at 1)
+const PROCESS_SCOPE_TID: &str = "0";
+
+/// Process entry in the sidecar mapping
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessEntry {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Thread entry in the sidecar mapping
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThreadEntry {
+    pub pid: u32,
+    pub tid: u32,
+    pub name: String,
+}
+
+/// Assigns stable small integers to pid/tid strings
+///
+/// IDs start at 1 and are assigned in the order tracks are first seen, so the
+/// same sorted event stream always produces the same mapping. Thread IDs are
+/// unique across all processes.
+#[derive(Debug, Default)]
+pub struct TrackIdMap {
+    process_ids: HashMap<InternedStr, u32>,
+    thread_ids: HashMap<(u32, InternedStr), u32>,
+    processes: Vec<ProcessEntry>,
+    threads: Vec<ThreadEntry>,
+    named_processes: HashSet<u32>,
+    named_threads: HashSet<u32>,
+}
+
+impl TrackIdMap {
+    /// Create an empty mapping
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get or assign the numeric ID for a process name
+    pub fn process_id(&mut self, name: &InternedStr) -> u32 {
+        if let Some(&id) = self.process_ids.get(name) {
+            return id;
+        }
+        let id = self.processes.len() as u32 + 1;
+        self.process_ids.insert(name.clone(), id);
+        self.processes.push(ProcessEntry {
+            pid: id,
+            name: name.to_string(),
+        });
+        id
+    }
+
+    /// Get or assign the numeric ID for a thread name within a process
+    pub fn thread_id(&mut self, pid: u32, name: &InternedStr) -> u32 {
+        if let Some(&id) = self.thread_ids.get(&(pid, name.clone())) {
+            return id;
+        }
+        let id = self.threads.len() as u32 + 1;
+        self.thread_ids.insert((pid, name.clone()), id);
+        self.threads.push(ThreadEntry {
+            pid,
+            tid: id,
+            name: name.to_string(),
+        });
+        id
+    }
+
+    /// Replace an event's pid/tid strings with their numeric IDs
+    ///
+    /// Existing `process_name` / `thread_name` metadata events are kept and mark
+    /// their track as already named.
+    pub fn map_event(&mut self, event: &mut ChromeTraceEvent) {
+        let pid = self.process_id(&event.pid);
+        let tid = if event.tid.is_empty() {
+            None
+        } else {
+            Some(self.thread_id(pid, &event.tid))
+        };
+
+        if event.ph == ChromeTracePhase::Metadata {
+            match (event.name.as_str(), tid) {
+                ("process_name", _) => {
+                    self.named_processes.insert(pid);
+                }
+                ("thread_name", Some(tid)) => {
+                    self.named_threads.insert(tid);
+                }
+                _ => {}
+            }
+        }
+
+        event.pid = InternedStr::from(pid.to_string());
+        event.tid = match tid {
+            Some(tid) => InternedStr::from(tid.to_string()),
+            None => InternedStr::new(PROCESS_SCOPE_TID),
+        };
+    }
+
+    /// Metadata events naming every track that has no name event yet
+    pub fn name_events(&self) -> Vec<ChromeTraceEvent> {
+        let mut events = Vec::new();
+
+        for process in &self.processes {
+            if self.named_processes.contains(&process.pid) {
+                continue;
+            }
+            let mut args = HashMap::new();
+            args.insert("name".to_string(), json!(process.name));
+            events.push(ChromeTraceEvent::metadata(
+                "process_name".to_string(),
+                process.pid.to_string(),
+                PROCESS_SCOPE_TID,
+                args,
+            ));
+        }
+
+        for thread in &self.threads {
+            if self.named_threads.contains(&thread.tid) {
+                continue;
+            }
+            let mut args = HashMap::new();
+            args.insert("name".to_string(), json!(thread.name));
+            events.push(ChromeTraceEvent::metadata(
+                "thread_name".to_string(),
+                thread.pid.to_string(),
+                thread.tid.to_string(),
+                args,
+            ));
+        }
+
+        events
+    }
+
+    /// Processes in ID order
+    pub fn processes(&self) -> &[ProcessEntry] {
+        &self.processes
+    }
+
+    /// Threads in ID order
+    pub fn threads(&self) -> &[ThreadEntry] {
+        &self.threads
+    }
+
+    /// Mapping as JSON: `{"processes": [...], "threads": [...]}`
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "processes": self.processes,
+            "threads": self.threads,
+        })
+    }
+
+    /// Write the mapping as a sidecar JSON file
+    pub fn write_sidecar(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        })?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.to_json())
+            .map_err(|e| ConvertError::Output(e.into()))?;
+        Ok(())
+    }
+}
+
+/// Write an event mapped by [`TrackIdMap::map_event`] as JSON, with its
+/// pid/tid as numbers rather than strings
+pub fn write_numeric_event<W: Write>(writer: W, event: &ChromeTraceEvent) -> Result<()> {
+    let formatter = NumericTrackIdFormatter::default();
+    event.serialize(&mut serde_json::Serializer::with_formatter(
+        writer, formatter,
+    ))?;
+    Ok(())
+}
+
+/// JSON formatter leaving the quotes off the event's top-level `pid` and `tid`
+///
+/// Only correct for events whose pid/tid [`TrackIdMap::map_event`] has
+/// already replaced with decimal IDs; nested objects such as `args` are
+/// written unchanged.
+#[derive(Debug, Default)]
+struct NumericTrackIdFormatter {
+    depth: usize,
+    in_key: bool,
+    key: Vec<u8>,
+    unquoted: bool,
+}
+
+impl NumericTrackIdFormatter {
+    fn is_track_id_value(&self) -> bool {
+        self.depth == 1 && !self.in_key && matches!(self.key.as_slice(), b"pid" | b"tid")
+    }
+}
+
+impl Formatter for NumericTrackIdFormatter {
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth += 1;
+        writer.write_all(b"{")
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth -= 1;
+        writer.write_all(b"}")
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        if self.depth == 1 {
+            self.in_key = true;
+            self.key.clear();
+        }
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b",")
+        }
+    }
+
+    fn end_object_key<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.in_key = false;
+        Ok(())
+    }
+
+    fn begin_string<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.unquoted = self.is_track_id_value();
+        if self.unquoted {
+            Ok(())
+        } else {
+            writer.write_all(b"\"")
+        }
+    }
+
+    fn end_string<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if std::mem::take(&mut self.unquoted) {
+            Ok(())
+        } else {
+            writer.write_all(b"\"")
+        }
+    }
+
+    fn write_string_fragment<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        if self.in_key {
+            self.key.extend_from_slice(fragment.as_bytes());
+        }
+        writer.write_all(fragment.as_bytes())
+    }
+}
+
+/// Default sidecar path for an output trace: `trace.json.gz` -> `trace.ids.json`
+pub fn sidecar_path(output_path: &str) -> String {
+    let stem = output_path.strip_suffix(".gz").unwrap_or(output_path);
+    let stem = stem.strip_suffix(".json").unwrap_or(stem);
+    format!("{}.ids.json", stem)
+}
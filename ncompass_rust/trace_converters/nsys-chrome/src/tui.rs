@@ -0,0 +1,468 @@
+//! Full-screen terminal UI for the event browser
+//!
+//! Draws the views of a [`TraceBrowser`] on the terminal's alternate screen
+//! with ANSI escape codes and reads single key presses in raw mode:
+//!
+//! - arrows, PgUp/PgDn, Home/End move through the current view
+//! - Tab cycles kernels, NVTX ranges and all events
+//! - `s` cycles the order (duration, start, name)
+//! - `/` edits the name filter (Enter applies it, Esc cancels)
+//! - Enter shows the selected event in full, or the timeline of its track
+//! - `l`, `r` and `t` switch to the list, per-track totals and timelines
+//! - `q` or Esc quits
+//!
+//! Raw mode is set through termios, so the UI only drives unix terminals; the
+//! `view` subcommand falls back to the line commands of
+//! [`crate::browser::run_browser`] elsewhere and when stdin or stdout is not a
+//! terminal. [`TuiState::render`] produces plain lines, so every view can be
+//! checked without a terminal.
+
+use std::io::Write;
+
+use crate::browser::{
+    format_duration_us, list_header, list_row, track_row, truncate, EventKind, ListQuery, SortKey,
+    TraceBrowser, NAME_WIDTH,
+};
+use crate::error::Result;
+
+/// A key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Backspace,
+    Escape,
+    Tab,
+    Char(char),
+}
+
+/// Decode the bytes of one terminal read into key presses
+///
+/// Unknown escape sequences are skipped; a lone ESC byte is [`Key::Escape`].
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    let mut keys = Vec::new();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' if chars.peek() == Some(&'[') || chars.peek() == Some(&'O') => {
+                chars.next();
+                let mut sequence = String::new();
+                while let Some(&c) = chars.peek() {
+                    chars.next();
+                    sequence.push(c);
+                    if c.is_ascii_alphabetic() || c == '~' {
+                        break;
+                    }
+                }
+                match sequence.as_str() {
+                    "A" => Key::Up,
+                    "B" => Key::Down,
+                    "H" | "1~" | "7~" => Key::Home,
+                    "F" | "4~" | "8~" => Key::End,
+                    "5~" => Key::PageUp,
+                    "6~" => Key::PageDown,
+                    _ => continue,
+                }
+            }
+            '\x1b' => Key::Escape,
+            '\r' | '\n' => Key::Enter,
+            '\t' => Key::Tab,
+            '\x7f' | '\x08' => Key::Backspace,
+            c if c.is_control() => continue,
+            c => Key::Char(c),
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// View shown by the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    /// Events of the current query
+    List,
+    /// Event count and busy time per track
+    Tracks,
+    /// ASCII timelines, of every track or of one
+    Timeline,
+}
+
+/// One drawn screen: its lines and the row shown highlighted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub lines: Vec<String>,
+    pub highlight: Option<usize>,
+}
+
+/// What the UI shows and where its cursor is
+#[derive(Debug, Clone)]
+pub struct TuiState {
+    pub query: ListQuery,
+    pub screen: Screen,
+    /// Row of the cursor in the current view
+    pub selected: usize,
+    /// First row drawn
+    scroll: usize,
+    /// Name filter being typed after `/`
+    search_input: Option<String>,
+    /// Track the timeline is restricted to
+    timeline_track: Option<String>,
+    /// Message for the status line
+    status: String,
+    /// Rows the last render had room for, for paging
+    page: usize,
+}
+
+impl Default for TuiState {
+    fn default() -> Self {
+        Self {
+            query: ListQuery::default(),
+            screen: Screen::List,
+            selected: 0,
+            scroll: 0,
+            search_input: None,
+            timeline_track: None,
+            status: String::new(),
+            page: 1,
+        }
+    }
+}
+
+/// Lines above and below the rows of a view: title, column header, status
+const CHROME_LINES: usize = 3;
+
+impl TuiState {
+    /// Rows of the current view
+    fn row_count(&self, browser: &TraceBrowser) -> usize {
+        match self.screen {
+            Screen::List => browser.list(&self.query).len(),
+            Screen::Tracks => browser.tracks().len(),
+            // The header line of a rendered timeline is drawn as the column header
+            Screen::Timeline => browser
+                .render_timeline(1, self.timeline_track.as_deref())
+                .lines()
+                .count()
+                .saturating_sub(1),
+        }
+    }
+
+    fn switch(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    /// Apply a key press; returns false when the UI should exit
+    pub fn handle_key(&mut self, key: Key, browser: &TraceBrowser) -> bool {
+        if let Some(input) = &mut self.search_input {
+            match key {
+                Key::Enter => {
+                    let text = input.trim().to_string();
+                    self.query.search = (!text.is_empty()).then_some(text);
+                    self.search_input = None;
+                    self.switch(Screen::List);
+                }
+                Key::Escape => self.search_input = None,
+                Key::Backspace => {
+                    input.pop();
+                }
+                Key::Char(c) => input.push(c),
+                _ => {}
+            }
+            return true;
+        }
+
+        self.status.clear();
+        let rows = self.row_count(browser);
+        let last = rows.saturating_sub(1);
+        match key {
+            Key::Char('q') | Key::Escape => return false,
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => self.selected = (self.selected + 1).min(last),
+            Key::PageUp => self.selected = self.selected.saturating_sub(self.page),
+            Key::PageDown => self.selected = (self.selected + self.page).min(last),
+            Key::Home => self.selected = 0,
+            Key::End => self.selected = last,
+            Key::Tab => {
+                self.query.kind = match self.query.kind {
+                    EventKind::Kernels => EventKind::Nvtx,
+                    EventKind::Nvtx => EventKind::All,
+                    EventKind::All => EventKind::Kernels,
+                };
+                self.switch(Screen::List);
+            }
+            Key::Char('s') => {
+                self.query.sort = match self.query.sort {
+                    SortKey::Duration => SortKey::Start,
+                    SortKey::Start => SortKey::Name,
+                    SortKey::Name => SortKey::Duration,
+                };
+                self.switch(Screen::List);
+            }
+            Key::Char('/') => {
+                self.search_input = Some(self.query.search.clone().unwrap_or_default());
+            }
+            Key::Char('l') => self.switch(Screen::List),
+            Key::Char('r') => self.switch(Screen::Tracks),
+            Key::Char('t') => {
+                self.timeline_track = None;
+                self.switch(Screen::Timeline);
+            }
+            Key::Enter => self.select(browser),
+            _ => {}
+        }
+        true
+    }
+
+    /// Act on the row under the cursor
+    fn select(&mut self, browser: &TraceBrowser) {
+        match self.screen {
+            Screen::List => {
+                if let Some(event) = browser.list(&self.query).get(self.selected) {
+                    self.status = format!(
+                        "{}  [{}]  {}  start {:.3} us  dur {}",
+                        event.name,
+                        event.cat,
+                        event.track,
+                        event.start_us,
+                        format_duration_us(event.dur_us)
+                    );
+                }
+            }
+            Screen::Tracks => {
+                if let Some((track, _, _)) = browser.tracks().into_iter().nth(self.selected) {
+                    self.timeline_track = Some(track);
+                    self.switch(Screen::Timeline);
+                }
+            }
+            Screen::Timeline => {}
+        }
+    }
+
+    /// Lines of the current view for a terminal of `width` x `height`
+    pub fn render(&mut self, browser: &TraceBrowser, width: usize, height: usize) -> Frame {
+        let width = width.max(20);
+        let page = height.saturating_sub(CHROME_LINES).max(1);
+        self.page = page;
+
+        let (title, header, rows) = match self.screen {
+            Screen::List => {
+                let selected = browser.list(&self.query);
+                let title = format!(
+                    "{} {}{}, by {}",
+                    selected.len(),
+                    self.query.kind.label(),
+                    self.query
+                        .search
+                        .as_deref()
+                        .map(|s| format!(" matching '{}'", s))
+                        .unwrap_or_default(),
+                    sort_label(self.query.sort)
+                );
+                let rows: Vec<String> = selected.into_iter().map(list_row).collect();
+                (title, list_header(), rows)
+            }
+            Screen::Tracks => {
+                let tracks = browser.tracks();
+                let title = format!("{} tracks (Enter: timeline of a track)", tracks.len());
+                let rows = tracks
+                    .iter()
+                    .map(|(track, count, busy)| track_row(track, *count, *busy))
+                    .collect();
+                (
+                    title,
+                    format!("{:>8}  {:>12}  track", "events", "busy"),
+                    rows,
+                )
+            }
+            Screen::Timeline => {
+                let bar_width = width.saturating_sub(NAME_WIDTH + 4).max(10);
+                let timeline = browser.render_timeline(bar_width, self.timeline_track.as_deref());
+                let mut lines = timeline.lines().map(str::to_string);
+                let header = lines.next().unwrap_or_default();
+                let title = match &self.timeline_track {
+                    Some(track) => format!("Timeline of {}", track),
+                    None => "Timeline of every track".to_string(),
+                };
+                (title, header, lines.collect())
+            }
+        };
+
+        self.selected = self.selected.min(rows.len().saturating_sub(1));
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + page {
+            self.scroll = self.selected + 1 - page;
+        }
+
+        let mut lines = vec![
+            truncate(&format!("{}  {}", title, KEY_HINT), width),
+            truncate(&header, width),
+        ];
+        lines.extend(
+            rows.iter()
+                .skip(self.scroll)
+                .take(page)
+                .map(|row| truncate(row, width)),
+        );
+        let highlight =
+            (!rows.is_empty()).then_some(CHROME_LINES - 1 + self.selected - self.scroll);
+        while lines.len() < height.saturating_sub(1) {
+            lines.push(String::new());
+        }
+        let status = match &self.search_input {
+            Some(input) => format!("/{}", input),
+            None => self.status.clone(),
+        };
+        lines.push(truncate(&status, width));
+        Frame { lines, highlight }
+    }
+}
+
+const KEY_HINT: &str = "[Tab] kind [s] sort [/] search [l]ist t[r]acks [t]imeline [q]uit";
+
+fn sort_label(sort: SortKey) -> &'static str {
+    match sort {
+        SortKey::Duration => "duration",
+        SortKey::Start => "start",
+        SortKey::Name => "name",
+    }
+}
+
+/// Draw a frame over the previous one
+fn draw(out: &mut impl Write, frame: &Frame) -> Result<()> {
+    write!(out, "\x1b[H")?;
+    for (row, line) in frame.lines.iter().enumerate() {
+        if row > 0 {
+            write!(out, "\r\n")?;
+        }
+        if frame.highlight == Some(row) {
+            write!(out, "\x1b[2K\x1b[7m{}\x1b[0m", line)?;
+        } else {
+            write!(out, "\x1b[2K{}", line)?;
+        }
+    }
+    write!(out, "\x1b[J")?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+mod terminal {
+    use std::io;
+
+    /// Puts stdin in raw mode until dropped
+    pub struct RawMode {
+        original: libc::termios,
+    }
+
+    impl RawMode {
+        pub fn enable() -> io::Result<Self> {
+            // SAFETY: termios is plain data and is filled in by tcgetattr
+            let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+            // SAFETY: fd 0 stays open for the life of the process
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let original = termios;
+            // SAFETY: termios was initialized by tcgetattr
+            unsafe { libc::cfmakeraw(&mut termios) };
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            // SAFETY: as above
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &termios) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { original })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: restores the settings read in enable()
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &self.original);
+            }
+        }
+    }
+
+    /// Terminal columns and rows, 80x24 if unknown
+    pub fn size() -> (usize, usize) {
+        // SAFETY: winsize is plain data and is filled in by ioctl
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        // SAFETY: TIOCGWINSZ only writes to `size`
+        let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+        if ok && size.ws_col > 0 && size.ws_row > 0 {
+            (size.ws_col as usize, size.ws_row as usize)
+        } else {
+            (80, 24)
+        }
+    }
+
+    /// Wait for and read the bytes of the next key presses
+    pub fn read_input(buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            // SAFETY: reads at most buffer.len() bytes into buffer
+            let n = unsafe {
+                libc::read(
+                    libc::STDIN_FILENO,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                )
+            };
+            if n >= 0 {
+                return Ok(n as usize);
+            }
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+    }
+}
+
+/// Run the full-screen UI on the controlling terminal until `q`
+#[cfg(unix)]
+pub fn run_tui(browser: &TraceBrowser) -> Result<()> {
+    let raw_mode = terminal::RawMode::enable()?;
+    let mut out = std::io::stdout().lock();
+    // Alternate screen, cursor hidden
+    write!(out, "\x1b[?1049h\x1b[?25l")?;
+
+    let mut state = TuiState::default();
+    let result = (|| -> Result<()> {
+        let mut buffer = [0u8; 64];
+        loop {
+            let (width, height) = terminal::size();
+            draw(&mut out, &state.render(browser, width, height))?;
+            let n = terminal::read_input(&mut buffer)?;
+            if n == 0 {
+                return Ok(());
+            }
+            for key in parse_keys(&buffer[..n]) {
+                if !state.handle_key(key, browser) {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+
+    write!(out, "\x1b[?25h\x1b[?1049l")?;
+    out.flush()?;
+    drop(raw_mode);
+    result
+}
+
+/// The full-screen UI needs termios; use [`crate::browser::run_browser`]
+#[cfg(not(unix))]
+pub fn run_tui(_browser: &TraceBrowser) -> Result<()> {
+    Err(crate::error::ConvertError::InvalidOption(
+        "The full-screen viewer needs a unix terminal; use --commands".to_string(),
+    ))
+}
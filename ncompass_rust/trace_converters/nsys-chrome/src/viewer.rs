@@ -0,0 +1,169 @@
+//! Open converted traces in the Perfetto UI
+//!
+//! `ui.perfetto.dev` can load a trace from a URL given in its deep link, but its
+//! content security policy only allows fetching from `127.0.0.1:9001`. The
+//! `TraceServer` serves a single trace file there with the CORS headers the UI
+//! needs, so a converted trace opens with one click instead of a manual upload.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::{ConvertError, Result};
+
+/// Perfetto UI origin
+pub const PERFETTO_UI_URL: &str = "https://ui.perfetto.dev";
+
+/// Address the Perfetto UI is allowed to fetch traces from
+pub const DEFAULT_TRACE_SERVER_ADDR: ([u8; 4], u16) = ([127, 0, 0, 1], 9001);
+
+/// How long to wait for a request before dropping the connection
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Percent-encode a string for use as a URL query value
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Build a Perfetto UI link that opens the trace at `trace_url`
+pub fn perfetto_deep_link(trace_url: &str) -> String {
+    format!(
+        "{}/#!/?url={}",
+        PERFETTO_UI_URL,
+        encode_query_value(trace_url)
+    )
+}
+
+/// HTTP server for a single trace file
+pub struct TraceServer {
+    listener: TcpListener,
+    path: PathBuf,
+    file_name: String,
+}
+
+impl TraceServer {
+    /// Bind a server for the trace at `path`
+    pub fn bind(path: impl AsRef<Path>, addr: SocketAddr) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if !path.is_file() {
+            return Err(ConvertError::InputNotFound(path));
+        }
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(encode_query_value)
+            .unwrap_or_else(|| "trace.json".to_string());
+        let listener =
+            TcpListener::bind(addr).map_err(|source| ConvertError::Bind { addr, source })?;
+
+        Ok(Self {
+            listener,
+            path,
+            file_name,
+        })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// URL the trace is served at
+    pub fn trace_url(&self) -> Result<String> {
+        Ok(format!("http://{}/{}", self.local_addr()?, self.file_name))
+    }
+
+    /// Perfetto UI link that loads the served trace
+    pub fn deep_link(&self) -> Result<String> {
+        Ok(perfetto_deep_link(&self.trace_url()?))
+    }
+
+    /// Serve requests until the trace has been downloaded once
+    ///
+    /// CORS preflight and unknown paths are answered without ending the loop, so
+    /// the server stays up until the UI has actually fetched the file.
+    pub fn serve_until_fetched(self) -> Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    log::debug!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+            match self.handle_connection(stream) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => log::debug!("Trace server request failed: {}", e.display_chain()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer one request; returns true if the trace was sent
+    fn handle_connection(&self, mut stream: TcpStream) -> Result<bool> {
+        // A client that connects but never sends must not stall the server
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                break;
+            }
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let target = parts.next().unwrap_or_default();
+        let path = target.split('?').next().unwrap_or_default();
+
+        let status = match method {
+            "OPTIONS" => "204 No Content",
+            "GET" if path.trim_start_matches('/') == self.file_name => {
+                let file =
+                    File::open(&self.path).map_err(|e| ConvertError::open_input(&self.path, e))?;
+                let length = file.metadata()?.len();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\n{}Content-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    cors_headers(),
+                    length
+                )?;
+                std::io::copy(&mut BufReader::new(file), &mut stream)?;
+                stream.flush()?;
+                return Ok(true);
+            }
+            "GET" => "404 Not Found",
+            _ => "405 Method Not Allowed",
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+            status,
+            cors_headers()
+        )?;
+        stream.flush()?;
+        Ok(false)
+    }
+}
+
+fn cors_headers() -> String {
+    format!(
+        "Access-Control-Allow-Origin: {}\r\nAccess-Control-Allow-Methods: GET, OPTIONS\r\nAccess-Control-Allow-Headers: *\r\n",
+        PERFETTO_UI_URL
+    )
+}
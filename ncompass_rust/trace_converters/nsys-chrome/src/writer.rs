@@ -1,20 +1,213 @@
 //! High-performance streaming JSON writer for Chrome Trace format
+//!
+//! With [`WriteOptions::serialize_threads`] above one, events are serialized
+//! to JSON in windows of chunks on a thread pool while the writing thread
+//! hands the previous window's text to the sink in order, so serialization
+//! no longer bounds how fast compression can be fed. The output is the same
+//! byte for byte.
 
-use anyhow::{Context, Result};
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use gzp::deflate::Gzip;
-use gzp::par::compress::{ParCompress, ParCompressBuilder};
-use gzp::ZWriter;
+use rayon::prelude::*;
+use rayon::ThreadPool;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempPath;
 
+use crate::begin_end::BeginEndEvents;
+use crate::error::{ConvertError, Result};
+use crate::intern::InternedStr;
 use crate::models::{ChromeTraceEvent, ChromeTracePhase};
+use crate::outline::TraceOutline;
+use crate::seekable::{SeekIndex, SeekableGzSink};
+use crate::sink::{GzSink, JsonSink, TraceSink};
+use crate::track_ids::{self, TrackIdMap};
 
 /// Unicode arrow prefix for overflow tracks (U+21B3)
 pub const OVERFLOW_PREFIX: &str = "↳ ";
 
+/// Events serialized together by one worker task
+const SERIALIZE_CHUNK_EVENTS: usize = 2048;
+
+/// Output settings for [`ChromeTraceWriter::write_to`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Gzip-compress the output
+    pub gzip: bool,
+    /// Emit numeric pid/tid (see [`ChromeTraceWriter::write_numeric_ids`])
+    pub numeric_ids: bool,
+    /// Build a [`TraceOutline`] while writing
+    pub outline: bool,
+    /// Write Begin/End pairs instead of complete events (see [`crate::begin_end`])
+    pub begin_end: bool,
+    /// Threads serializing events to JSON ahead of the writing thread; 0 or 1
+    /// serializes on the writing thread
+    pub serialize_threads: usize,
+    /// With `gzip`, write independent gzip members and build a [`SeekIndex`]
+    /// (see [`crate::seekable`])
+    pub seekable: bool,
+}
+
+/// Mappings built by [`ChromeTraceWriter::write_to`], depending on its options
+#[derive(Debug, Default)]
+pub struct WriteOutput {
+    pub track_ids: Option<TrackIdMap>,
+    pub outline: Option<TraceOutline>,
+    pub seek_index: Option<SeekIndex>,
+}
+
+/// Compression implied by an output path's extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputCompression {
+    /// Plain JSON
+    None,
+    /// `.gz`
+    Gzip,
+    /// `.zst`; recognized so it is not written as plain JSON, but this build
+    /// has no zstd encoder
+    Zstd,
+}
+
+impl OutputCompression {
+    /// Compression for a path, by its last extension
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("gz") => OutputCompression::Gzip,
+            Some("zst") | Some("zstd") => OutputCompression::Zstd,
+            _ => OutputCompression::None,
+        }
+    }
+}
+
+/// How an [`OutputFile`] treats its destination
+#[derive(Debug, Clone, Copy)]
+pub struct OutputFileOptions {
+    /// Replace an existing file instead of failing
+    pub force: bool,
+    /// Write to a temporary file beside the destination and rename it into
+    /// place once complete, so a crash never leaves a torn trace behind
+    pub atomic: bool,
+}
+
+impl Default for OutputFileOptions {
+    fn default() -> Self {
+        Self {
+            force: false,
+            atomic: true,
+        }
+    }
+}
+
+/// Destination file, created with its parent directories and moved into
+/// place by [`OutputFile::commit`]
+///
+/// Dropping an atomic output without committing removes the temporary file.
+pub struct OutputFile {
+    path: PathBuf,
+    file: File,
+    force: bool,
+    temp: Option<TempPath>,
+}
+
+impl OutputFile {
+    /// Open `path` for writing, refusing to replace it unless `options.force`
+    pub fn create(path: &str, options: OutputFileOptions) -> Result<Self> {
+        let create_error = |source| ConvertError::CreateOutput {
+            path: path.into(),
+            source,
+        };
+        let dest = PathBuf::from(path);
+        if !options.force && dest.exists() {
+            return Err(ConvertError::OutputExists(dest));
+        }
+        let parent = dest
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        std::fs::create_dir_all(parent).map_err(create_error)?;
+
+        let (file, temp) = if options.atomic {
+            let name = dest.file_name().and_then(|n| n.to_str()).unwrap_or("trace");
+            let (file, temp) = tempfile::Builder::new()
+                .prefix(&format!(".{}.", name))
+                .suffix(".tmp")
+                .tempfile_in(parent)
+                .map_err(create_error)?
+                .into_parts();
+            (file, Some(temp))
+        } else {
+            let mut open = OpenOptions::new();
+            open.write(true);
+            if options.force {
+                open.create(true).truncate(true);
+            } else {
+                open.create_new(true);
+            }
+            let file = open.open(&dest).map_err(|source| match source.kind() {
+                ErrorKind::AlreadyExists => ConvertError::OutputExists(dest.clone()),
+                _ => create_error(source),
+            })?;
+            (file, None)
+        };
+        Ok(Self {
+            path: dest,
+            file,
+            force: options.force,
+            temp,
+        })
+    }
+
+    /// A handle to write the output through
+    pub fn writer(&self) -> Result<File> {
+        self.file.try_clone().map_err(ConvertError::Output)
+    }
+
+    /// Move a fully written atomic output into place
+    pub fn commit(self) -> Result<()> {
+        let Some(temp) = self.temp else {
+            return Ok(());
+        };
+        let persisted = if self.force {
+            temp.persist(&self.path)
+        } else {
+            temp.persist_noclobber(&self.path)
+        };
+        persisted.map_err(|e| match e.error.kind() {
+            ErrorKind::AlreadyExists => ConvertError::OutputExists(self.path.clone()),
+            _ => ConvertError::CreateOutput {
+                path: self.path.clone(),
+                source: e.error,
+            },
+        })
+    }
+}
+
+/// Prepared events and their JSON text, serialized in chunks
+struct SerializedWindow {
+    events: Vec<ChromeTraceEvent>,
+    /// JSON text of consecutive events and where each event ends in it
+    chunks: Vec<(Vec<u8>, Vec<usize>)>,
+}
+
+impl SerializedWindow {
+    /// Serialize the chunks of `events` in parallel on the current thread pool
+    fn serialize(events: Vec<ChromeTraceEvent>, numeric_ids: bool) -> Result<Self> {
+        let chunks = events
+            .par_chunks(SERIALIZE_CHUNK_EVENTS)
+            .map(|chunk| {
+                let mut json = Vec::new();
+                let mut ends = Vec::with_capacity(chunk.len());
+                for event in chunk {
+                    ChromeTraceWriter::serialize_event(&mut json, event, numeric_ids)?;
+                    ends.push(json.len());
+                }
+                Ok((json, ends))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { events, chunks })
+    }
+}
+
 /// Streaming JSON writer for Chrome Trace format
 pub struct ChromeTraceWriter;
 
@@ -28,7 +221,7 @@ impl ChromeTraceWriter {
     /// Returns the (potentially modified) event.
     fn process_event_for_overlap(
         event: &mut ChromeTraceEvent,
-        max_end: &mut HashMap<(String, String), f64>,
+        max_end: &mut HashMap<(InternedStr, InternedStr), f64>,
     ) {
         // Only process Complete events (phase X) with duration
         if event.ph != ChromeTracePhase::Complete {
@@ -42,9 +235,6 @@ impl ChromeTraceWriter {
         let ts = event.ts;
         let event_end = ts + dur;
         let original_key = (event.pid.clone(), event.tid.clone());
-        let overflow_tid = format!("{}{}", OVERFLOW_PREFIX, event.tid);
-        let overflow_key = (event.pid.clone(), overflow_tid.clone());
-
         let orig_max = *max_end.get(&original_key).unwrap_or(&f64::NEG_INFINITY);
 
         // Check if event fits on original track:
@@ -56,6 +246,8 @@ impl ChromeTraceWriter {
             max_end.insert(original_key, new_max);
         } else {
             // Partial overlap - move to overflow track
+            let overflow_tid = InternedStr::from(format!("{}{}", OVERFLOW_PREFIX, event.tid));
+            let overflow_key = (event.pid.clone(), overflow_tid.clone());
             event.tid = overflow_tid;
             let overflow_max = *max_end.get(&overflow_key).unwrap_or(&f64::NEG_INFINITY);
             let new_max = overflow_max.max(event_end);
@@ -63,42 +255,293 @@ impl ChromeTraceWriter {
         }
     }
 
+    /// Resolve overlaps in write order, optionally splitting complete events
+    /// into Begin/End pairs afterwards
+    fn prepare_events<'a, I>(
+        events: I,
+        begin_end: bool,
+    ) -> Box<dyn Iterator<Item = ChromeTraceEvent> + 'a>
+    where
+        I: IntoIterator<Item = ChromeTraceEvent>,
+        I::IntoIter: 'a,
+    {
+        // Track max end time per (pid, tid) for overlap detection
+        let mut max_end: HashMap<(InternedStr, InternedStr), f64> = HashMap::new();
+        let events = events.into_iter().map(move |mut event| {
+            // Process event for overlap and potentially assign to overflow track
+            Self::process_event_for_overlap(&mut event, &mut max_end);
+            event
+        });
+        if begin_end {
+            Box::new(BeginEndEvents::new(events))
+        } else {
+            Box::new(events)
+        }
+    }
+
     /// Write Chrome Trace events to JSON file
     ///
     /// Automatically handles overlapping events by moving them to virtual overflow
     /// tracks (e.g., "↳ Stream 7") to prevent Perfetto from dropping them.
-    pub fn write(output_path: &str, mut events: Vec<ChromeTraceEvent>) -> Result<()> {
-        let file = File::create(output_path)
-            .with_context(|| format!("Failed to create output file: {}", output_path))?;
-        let mut writer = BufWriter::with_capacity(256 * 1024, file); // 256KB buffer
+    pub fn write(output_path: &str, events: Vec<ChromeTraceEvent>) -> Result<()> {
+        Self::write_impl(Self::create(output_path)?, events, None, None, false)
+    }
 
-        // Track max end time per (pid, tid) for overlap detection
-        let mut max_end: HashMap<(String, String), f64> = HashMap::new();
+    /// Write Chrome Trace events to a file, choosing compression by extension
+    ///
+    /// `.json.gz` is gzip-compressed and `.json` plain; `.zst` is refused, as this
+    /// build has no zstd encoder. Parent directories are created, an existing
+    /// file is never replaced, and the trace only appears at `output_path` once
+    /// completely written (see [`Self::write_auto_with`] to change either).
+    pub fn write_auto(output_path: &str, events: Vec<ChromeTraceEvent>) -> Result<()> {
+        Self::write_auto_with(
+            output_path,
+            events,
+            WriteOptions::default(),
+            OutputFileOptions::default(),
+        )?;
+        Ok(())
+    }
 
-        // Write opening with newline
-        writer.write_all(b"{\"traceEvents\":[\n")?;
+    /// [`Self::write_auto`] with write and destination options
+    ///
+    /// `options.gzip` is replaced by what the extension implies.
+    pub fn write_auto_with(
+        output_path: &str,
+        events: impl IntoIterator<Item = ChromeTraceEvent>,
+        options: WriteOptions,
+        file_options: OutputFileOptions,
+    ) -> Result<WriteOutput> {
+        let gzip = match OutputCompression::from_path(output_path) {
+            OutputCompression::None => false,
+            OutputCompression::Gzip => true,
+            OutputCompression::Zstd => {
+                return Err(ConvertError::InvalidOption(format!(
+                    "Cannot write '{}': zstd compression is not available in this build \
+                     (use .json.gz or .json)",
+                    output_path
+                )))
+            }
+        };
+        let output = OutputFile::create(output_path, file_options)?;
+        let written = Self::write_to(output.writer()?, events, WriteOptions { gzip, ..options })?;
+        output.commit()?;
+        Ok(written)
+    }
 
-        // Write events with commas between them
-        // Each event on its own line to avoid Perfetto parser issues with very long lines
-        for (i, event) in events.iter_mut().enumerate() {
-            // Process event for overlap and potentially assign to overflow track
-            Self::process_event_for_overlap(event, &mut max_end);
+    /// Write Chrome Trace events to JSON file with numeric pid/tid
+    ///
+    /// Track names are emitted as `process_name` / `thread_name` metadata events.
+    /// Returns the mapping from numeric IDs to track names.
+    pub fn write_numeric_ids(
+        output_path: &str,
+        events: Vec<ChromeTraceEvent>,
+    ) -> Result<TrackIdMap> {
+        let mut track_ids = TrackIdMap::new();
+        Self::write_impl(
+            Self::create(output_path)?,
+            events,
+            Some(&mut track_ids),
+            None,
+            false,
+        )?;
+        Ok(track_ids)
+    }
+
+    /// Write Chrome Trace events to JSON file and build its outline
+    ///
+    /// See [`crate::outline`] for what the outline indexes.
+    pub fn write_with_outline(
+        output_path: &str,
+        events: Vec<ChromeTraceEvent>,
+    ) -> Result<TraceOutline> {
+        let mut outline = TraceOutline::new();
+        Self::write_impl(
+            Self::create(output_path)?,
+            events,
+            None,
+            Some(&mut outline),
+            false,
+        )?;
+        Ok(outline)
+    }
+
+    fn create(output_path: &str) -> Result<File> {
+        File::create(output_path).map_err(|source| ConvertError::CreateOutput {
+            path: output_path.into(),
+            source,
+        })
+    }
+
+    pub(crate) fn write_impl<W: Write>(
+        output: W,
+        events: impl IntoIterator<Item = ChromeTraceEvent>,
+        track_ids: Option<&mut TrackIdMap>,
+        outline: Option<&mut TraceOutline>,
+        begin_end: bool,
+    ) -> Result<()> {
+        Self::write_sink(
+            &mut JsonSink::new(output),
+            events,
+            track_ids,
+            outline,
+            begin_end,
+            0,
+        )
+        .map_err(ConvertError::into_output)
+    }
 
-            if i > 0 {
-                writer.write_all(b",\n")?;
+    /// Feed prepared events to `sink`, naming numeric tracks at the end
+    fn write_sink<S: TraceSink + ?Sized>(
+        sink: &mut S,
+        events: impl IntoIterator<Item = ChromeTraceEvent>,
+        mut track_ids: Option<&mut TrackIdMap>,
+        mut outline: Option<&mut TraceOutline>,
+        begin_end: bool,
+        serialize_threads: usize,
+    ) -> Result<()> {
+        sink.begin()?;
+        let numeric_ids = track_ids.is_some();
+        let events = Self::prepare_events(events, begin_end).map(|mut event| {
+            if let Some(track_ids) = track_ids.as_deref_mut() {
+                track_ids.map_event(&mut event);
+            }
+            event
+        });
+        match Self::serialize_pool(serialize_threads) {
+            Some(pool) => {
+                Self::write_parallel(sink, events, outline.as_deref_mut(), numeric_ids, &pool)?
+            }
+            None => {
+                let mut json = Vec::new();
+                for event in events {
+                    Self::write_event(sink, &event, numeric_ids, &mut json)?;
+                    Self::record_outline(sink, &event, outline.as_deref_mut());
+                }
             }
-            let json = serde_json::to_vec(&event)
-                .with_context(|| format!("Failed to serialize event: {:?}", event))?;
-            writer.write_all(&json)?;
+        }
+        if let Some(outline) = outline {
+            outline.finish();
         }
 
-        // Write closing with newline
-        writer.write_all(b"\n]}")?;
-        writer.flush()?;
+        // Name any numeric tracks that had no metadata event
+        let name_events = track_ids.map(|ids| ids.name_events()).unwrap_or_default();
+        let mut json = Vec::new();
+        for event in &name_events {
+            Self::write_event(sink, event, true, &mut json)?;
+        }
+        sink.finish()
+    }
 
+    /// Append an event's JSON text to `json`, with numeric pid/tid for events
+    /// mapped by a [`TrackIdMap`]
+    fn serialize_event(
+        json: &mut Vec<u8>,
+        event: &ChromeTraceEvent,
+        numeric_ids: bool,
+    ) -> Result<()> {
+        if numeric_ids {
+            track_ids::write_numeric_event(json, event)
+        } else {
+            Ok(serde_json::to_writer(json, event)?)
+        }
+    }
+
+    /// Hand one event to `sink`, serializing it here when its track IDs are numeric
+    fn write_event<S: TraceSink + ?Sized>(
+        sink: &mut S,
+        event: &ChromeTraceEvent,
+        numeric_ids: bool,
+        json: &mut Vec<u8>,
+    ) -> Result<()> {
+        if !numeric_ids {
+            return sink.write_event(event);
+        }
+        json.clear();
+        Self::serialize_event(json, event, true)?;
+        sink.write_serialized(event, json)
+    }
+
+    /// Thread pool for serializing events, if more than one thread is asked for
+    fn serialize_pool(threads: usize) -> Option<ThreadPool> {
+        if threads <= 1 {
+            return None;
+        }
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .inspect_err(|e| {
+                log::debug!(
+                    "Serializing on the writing thread, thread pool unavailable: {}",
+                    e
+                )
+            })
+            .ok()
+    }
+
+    /// Serialize windows of events on `pool`, writing each window to `sink`
+    /// while the next one is serialized
+    fn write_parallel<S: TraceSink + ?Sized>(
+        sink: &mut S,
+        mut events: impl Iterator<Item = ChromeTraceEvent>,
+        mut outline: Option<&mut TraceOutline>,
+        numeric_ids: bool,
+        pool: &ThreadPool,
+    ) -> Result<()> {
+        let window_events = SERIALIZE_CHUNK_EVENTS * pool.current_num_threads();
+        let mut pending: Option<SerializedWindow> = None;
+        loop {
+            let window: Vec<ChromeTraceEvent> = events.by_ref().take(window_events).collect();
+            if window.is_empty() && pending.is_none() {
+                return Ok(());
+            }
+            let mut serialized = None;
+            let mut written = Ok(());
+            // The sink stays on this thread; only serialization moves to the pool
+            pool.in_place_scope(|scope| {
+                if !window.is_empty() {
+                    scope.spawn(|_| {
+                        serialized = Some(SerializedWindow::serialize(window, numeric_ids))
+                    });
+                }
+                if let Some(previous) = pending.take() {
+                    written = Self::write_window(sink, &previous, outline.as_deref_mut());
+                }
+            });
+            written?;
+            pending = serialized.transpose()?;
+        }
+    }
+
+    /// Hand a serialized window to `sink` in order
+    fn write_window<S: TraceSink + ?Sized>(
+        sink: &mut S,
+        window: &SerializedWindow,
+        mut outline: Option<&mut TraceOutline>,
+    ) -> Result<()> {
+        let mut events = window.events.iter();
+        for (json, ends) in &window.chunks {
+            let mut start = 0;
+            for (&end, event) in ends.iter().zip(events.by_ref()) {
+                sink.write_serialized(event, &json[start..end])?;
+                Self::record_outline(sink, event, outline.as_deref_mut());
+                start = end;
+            }
+        }
         Ok(())
     }
 
+    /// Record where the event just written landed, for sinks producing JSON text
+    fn record_outline<S: TraceSink + ?Sized>(
+        sink: &S,
+        event: &ChromeTraceEvent,
+        outline: Option<&mut TraceOutline>,
+    ) {
+        if let (Some(outline), Some((offset, length))) = (outline, sink.last_event_span()) {
+            outline.record(event, offset, length);
+        }
+    }
+
     /// Write Chrome Trace events to gzip-compressed JSON file with parallel compression
     ///
     /// Uses pigz-style parallel gzip compression for significantly faster writes
@@ -106,57 +549,133 @@ impl ChromeTraceWriter {
     ///
     /// Automatically handles overlapping events by moving them to virtual overflow
     /// tracks (e.g., "↳ Stream 7") to prevent Perfetto from dropping them.
-    pub fn write_gz(output_path: &str, mut events: Vec<ChromeTraceEvent>) -> Result<()> {
-        let file = File::create(output_path)
-            .with_context(|| format!("Failed to create output file: {}", output_path))?;
-
-        // Create parallel gzip encoder (pigz-style)
-        // Uses all available CPU cores by default
-        let mut gz_writer: ParCompress<Gzip> = ParCompressBuilder::new()
-            .from_writer(file);
-
-        // Track max end time per (pid, tid) for overlap detection
-        let mut max_end: HashMap<(String, String), f64> = HashMap::new();
-
-        // Batch buffer to reduce the number of write calls to encoder
-        let mut batch_buffer = Vec::with_capacity(300 * 1024); // 256KB batch +
-                                                                                 // Overhead
-
-        // Write opening with newline
-        batch_buffer.extend_from_slice(b"{\"traceEvents\":[\n");
-
-        // Write events with commas between them, batching to reduce encoder overhead
-        // Each event on its own line to avoid Perfetto parser issues with very long lines
-        for (i, event) in events.iter_mut().enumerate() {
-            // Process event for overlap and potentially assign to overflow track
-            Self::process_event_for_overlap(event, &mut max_end);
+    pub fn write_gz(output_path: &str, events: Vec<ChromeTraceEvent>) -> Result<()> {
+        Self::write_gz_impl(Self::create(output_path)?, events, None, None, false)
+    }
 
-            if i > 0 {
-                batch_buffer.extend_from_slice(b",\n");
-            }
-            // Writing to Vec is fast (just memory copies)
-            serde_json::to_writer(&mut batch_buffer, &event)
-                .with_context(|| format!("Failed to serialize event: {:?}", event))?;
-
-            // Flush batch to encoder when it gets large enough (256KB threshold)
-            if batch_buffer.len() >= 256 * 1024 {
-                gz_writer.write_all(&batch_buffer)?;
-                batch_buffer.clear();
-            }
-        }
+    /// Write gzip-compressed Chrome Trace events with numeric pid/tid
+    ///
+    /// See [`ChromeTraceWriter::write_numeric_ids`].
+    pub fn write_gz_numeric_ids(
+        output_path: &str,
+        events: Vec<ChromeTraceEvent>,
+    ) -> Result<TrackIdMap> {
+        let mut track_ids = TrackIdMap::new();
+        Self::write_gz_impl(
+            Self::create(output_path)?,
+            events,
+            Some(&mut track_ids),
+            None,
+            false,
+        )?;
+        Ok(track_ids)
+    }
 
-        // Write closing with newline
-        batch_buffer.extend_from_slice(b"\n]}");
+    /// Write gzip-compressed Chrome Trace events and build their outline
+    ///
+    /// Outline offsets refer to the uncompressed JSON text.
+    pub fn write_gz_with_outline(
+        output_path: &str,
+        events: Vec<ChromeTraceEvent>,
+    ) -> Result<TraceOutline> {
+        let mut outline = TraceOutline::new();
+        Self::write_gz_impl(
+            Self::create(output_path)?,
+            events,
+            None,
+            Some(&mut outline),
+            false,
+        )?;
+        Ok(outline)
+    }
 
-        // Flush remaining buffer
-        if !batch_buffer.is_empty() {
-            gz_writer.write_all(&batch_buffer)?;
-        }
+    /// Write Chrome Trace events to any writer, e.g. stdout
+    ///
+    /// Compression, numeric IDs, the outline and the seek index are chosen by
+    /// `options` rather than by a file extension. Events are consumed one at a
+    /// time, so they can come from an iterator that is still being produced,
+    /// e.g. a [`crate::pipeline`] channel.
+    pub fn write_to<W: Write + Send + 'static>(
+        writer: W,
+        events: impl IntoIterator<Item = ChromeTraceEvent>,
+        options: WriteOptions,
+    ) -> Result<WriteOutput> {
+        let mut track_ids = options.numeric_ids.then(TrackIdMap::new);
+        let mut outline = options.outline.then(TraceOutline::new);
+        let write = |sink: &mut dyn TraceSink| {
+            Self::write_sink(
+                sink,
+                events,
+                track_ids.as_mut(),
+                outline.as_mut(),
+                options.begin_end,
+                options.serialize_threads,
+            )
+            .map_err(ConvertError::into_output)
+        };
+        let seek_index = if options.gzip && options.seekable {
+            let mut sink = SeekableGzSink::new(writer);
+            write(&mut sink)?;
+            Some(sink.into_index())
+        } else {
+            let mut sink: Box<dyn TraceSink> = if options.gzip {
+                Box::new(GzSink::new(writer))
+            } else {
+                Box::new(JsonSink::new(writer))
+            };
+            write(sink.as_mut())?;
+            None
+        };
+        Ok(WriteOutput {
+            track_ids,
+            outline,
+            seek_index,
+        })
+    }
 
-        gz_writer
-            .finish()
-            .with_context(|| "Failed to finish gzip compression")?;
+    /// Write Chrome Trace events into a custom [`TraceSink`]
+    ///
+    /// Overlaps are resolved and numeric IDs, Begin/End pairs and the outline
+    /// produced as `options` asks; `options.gzip` is ignored, as the sink
+    /// decides how events are encoded. The outline is only built for sinks
+    /// reporting where events land in their JSON text.
+    pub fn write_to_sink<S: TraceSink + ?Sized>(
+        sink: &mut S,
+        events: impl IntoIterator<Item = ChromeTraceEvent>,
+        options: WriteOptions,
+    ) -> Result<WriteOutput> {
+        let mut track_ids = options.numeric_ids.then(TrackIdMap::new);
+        let mut outline = options.outline.then(TraceOutline::new);
+        Self::write_sink(
+            sink,
+            events,
+            track_ids.as_mut(),
+            outline.as_mut(),
+            options.begin_end,
+            options.serialize_threads,
+        )?;
+        Ok(WriteOutput {
+            track_ids,
+            outline,
+            ..Default::default()
+        })
+    }
 
-        Ok(())
+    fn write_gz_impl<W: Write + Send + 'static>(
+        output: W,
+        events: impl IntoIterator<Item = ChromeTraceEvent>,
+        track_ids: Option<&mut TrackIdMap>,
+        outline: Option<&mut TraceOutline>,
+        begin_end: bool,
+    ) -> Result<()> {
+        Self::write_sink(
+            &mut GzSink::new(output),
+            events,
+            track_ids,
+            outline,
+            begin_end,
+            0,
+        )
+        .map_err(ConvertError::into_output)
     }
 }
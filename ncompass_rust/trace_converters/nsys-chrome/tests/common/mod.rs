@@ -0,0 +1,82 @@
+//! Fixtures shared by the integration tests
+
+#![allow(dead_code)]
+
+use nsys_chrome::models::EventId;
+use nsys_chrome::{
+    ChromeTraceEvent, ConversionDiagnostics, ConversionOptions, NsysChromeConverter,
+};
+use rusqlite::Connection;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tempfile::TempDir;
+
+/// Create `name` in `dir` as an SQLite export holding `sql`, returning its path
+pub fn create_sqlite(dir: &TempDir, name: &str, sql: &str) -> String {
+    let path = dir.path().join(name);
+    Connection::open(&path).unwrap().execute_batch(sql).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+/// Convert an SQLite export holding `sql`
+pub fn convert_sql(sql: &str, options: ConversionOptions) -> Vec<ChromeTraceEvent> {
+    convert_sql_with_diagnostics(sql, options).0
+}
+
+/// Convert an SQLite export holding `sql`, with the conversion diagnostics
+pub fn convert_sql_with_diagnostics(
+    sql: &str,
+    options: ConversionOptions,
+) -> (Vec<ChromeTraceEvent>, ConversionDiagnostics) {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "trace.sqlite", sql);
+    NsysChromeConverter::new(&path, Some(options))
+        .unwrap()
+        .convert_with_diagnostics()
+        .unwrap()
+}
+
+/// Complete event on device 0 from the `cat` thread with raw TID 1, with
+/// its nanosecond bounds and `correlationId` as args
+pub fn create_event(
+    name: &str,
+    cat: &str,
+    start_ns: i64,
+    end_ns: i64,
+    corr: Option<i64>,
+) -> ChromeTraceEvent {
+    create_thread_event(name, cat, start_ns, end_ns, 1, corr)
+}
+
+/// Like [`create_event`], from the thread with `raw_tid`
+pub fn create_thread_event(
+    name: &str,
+    cat: &str,
+    start_ns: i64,
+    end_ns: i64,
+    raw_tid: i64,
+    corr: Option<i64>,
+) -> ChromeTraceEvent {
+    let event = ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        format!("{} Thread {}", cat, raw_tid),
+        cat.to_string(),
+    )
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+    .with_arg("deviceId", 0)
+    .with_arg("raw_tid", raw_tid);
+    match corr {
+        Some(corr) => event.with_arg("correlationId", corr),
+        None => event,
+    }
+}
+
+/// Give an event the next [`EventId`], as extraction does before linking
+pub fn numbered(mut event: ChromeTraceEvent) -> ChromeTraceEvent {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    event.event_id = Some(EventId(NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+    event
+}
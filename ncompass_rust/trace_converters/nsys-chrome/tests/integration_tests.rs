@@ -1,8 +1,11 @@
 //! Integration tests for nsys-chrome converter
 
 use flate2::read::GzDecoder;
-use nsys_chrome::{convert_file, convert_file_gz, ChromeTraceEvent, ConversionOptions, NsysChromeConverter};
-use rusqlite;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::{
+    convert_file, convert_file_gz, ChromeTraceEvent, ConversionOptions, ConvertError,
+    NsysChromeConverter,
+};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
@@ -16,10 +19,12 @@ use tempfile::{NamedTempFile, TempDir};
 fn test_converter_creation_file_not_found() {
     // Test that error is returned when input file doesn't exist
     let result = NsysChromeConverter::new("/nonexistent/directory/test.sqlite", None);
-    assert!(result.is_err());
-    if let Err(e) = result {
-        let err_msg = e.to_string();
-        assert!(err_msg.to_lowercase().contains("failed to open sqlite"));
+    match result {
+        Err(e @ ConvertError::InputNotFound(_)) => {
+            assert!(e.to_string().to_lowercase().contains("not found"));
+        }
+        Err(e) => panic!("Expected InputNotFound, got {:?}", e),
+        Ok(_) => panic!("Expected an error for a missing input"),
     }
 }
 
@@ -74,12 +79,12 @@ fn test_converter_creation_valid_db() {
 #[test]
 fn test_conversion_options_default() {
     let options = ConversionOptions::default();
-    assert!(options.activity_types.contains(&"kernel".to_string()));
-    assert!(options.activity_types.contains(&"nvtx".to_string()));
-    assert!(options.activity_types.contains(&"cuda-api".to_string()));
-    assert!(options.activity_types.contains(&"osrt".to_string()));
-    assert!(options.activity_types.contains(&"sched".to_string()));
-    assert!(options.activity_types.contains(&"nvtx-kernel".to_string()));
+    assert!(options.activity_types.contains(&EventCategory::Kernel));
+    assert!(options.activity_types.contains(&EventCategory::Nvtx));
+    assert!(options.activity_types.contains(&EventCategory::CudaApi));
+    assert!(options.activity_types.contains(&EventCategory::Osrt));
+    assert!(options.activity_types.contains(&EventCategory::Sched));
+    assert!(options.activity_types.contains(&EventCategory::NvtxKernel));
     assert!(options.include_metadata);
     assert_eq!(options.nvtx_event_prefix, None);
     assert!(options.nvtx_color_scheme.is_empty());
@@ -91,15 +96,16 @@ fn test_conversion_options_custom() {
     color_scheme.insert("test_.*".to_string(), "blue".to_string());
 
     let options = ConversionOptions {
-        activity_types: vec!["kernel".to_string(), "nvtx".to_string()],
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
         nvtx_event_prefix: Some(vec!["test_".to_string()]),
         nvtx_color_scheme: color_scheme.clone(),
         include_metadata: false,
+        ..Default::default()
     };
 
     assert_eq!(options.activity_types.len(), 2);
-    assert!(options.activity_types.contains(&"kernel".to_string()));
-    assert!(options.activity_types.contains(&"nvtx".to_string()));
+    assert!(options.activity_types.contains(&EventCategory::Kernel));
+    assert!(options.activity_types.contains(&EventCategory::Nvtx));
     assert_eq!(
         options.nvtx_event_prefix,
         Some(vec!["test_".to_string()])
@@ -171,11 +177,7 @@ fn test_convert_file_nonexistent_input() {
         None,
     );
 
-    assert!(result.is_err());
-    if let Err(e) = result {
-        let err_msg = e.to_string();
-        assert!(err_msg.to_lowercase().contains("failed to open"));
-    }
+    assert!(matches!(result, Err(ConvertError::InputNotFound(_))));
 }
 
 #[test]
@@ -239,10 +241,11 @@ fn test_convert_file_with_custom_options() {
     drop(conn);
 
     let custom_options = ConversionOptions {
-        activity_types: vec!["kernel".to_string()],
+        activity_types: vec![EventCategory::Kernel],
         include_metadata: false,
         nvtx_event_prefix: Some(vec!["test_".to_string()]),
         nvtx_color_scheme: HashMap::new(),
+        ..Default::default()
     };
 
     let result = convert_file(
@@ -360,10 +363,11 @@ fn test_convert_file_gz_with_custom_options() {
     drop(conn);
 
     let custom_options = ConversionOptions {
-        activity_types: vec!["kernel".to_string(), "nvtx".to_string()],
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
         include_metadata: false,
         nvtx_event_prefix: Some(vec!["test_".to_string()]),
         nvtx_color_scheme: HashMap::new(),
+        ..Default::default()
     };
 
     let result = convert_file_gz(
@@ -442,8 +446,10 @@ fn test_converter_metadata_excluded_when_disabled() {
     .unwrap();
     drop(conn);
 
-    let mut options = ConversionOptions::default();
-    options.include_metadata = false;
+    let options = ConversionOptions {
+        include_metadata: false,
+        ..Default::default()
+    };
 
     let converter = NsysChromeConverter::new(temp_path, Some(options)).unwrap();
     let result = converter.convert();
@@ -0,0 +1,183 @@
+//! Unit tests for custom activity types registered by library users
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::error::{ConvertError, Result};
+use nsys_chrome::mapping::decompose_global_tid;
+use nsys_chrome::models::{ns_to_us, ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::parsers::{ActivityExtractor, ActivityLinkRole, ParseContext};
+use nsys_chrome::{ConversionDiagnostics, NsysChromeConverter};
+use rusqlite::Row;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Two kernels launched by one thread, under framework annotations recorded
+/// in an organization-specific table; there is no NVTX table
+const PLUGIN_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'cudaLaunchKernel'), (2, 'gemm_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (3000, 4000, 0, 7, 1, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (6000, 7000, 0, 7, 2, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+        start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES
+        (1100, 1200, 16777217, 1, 1),
+        (5100, 5200, 16777217, 2, 1);
+    CREATE TABLE FRAMEWORK_ANNOTATIONS (
+        start INTEGER, end INTEGER, globalTid INTEGER, label TEXT
+    );
+    INSERT INTO FRAMEWORK_ANNOTATIONS VALUES
+        (1000, 2000, 16777217, 'attention'),
+        (5000, 5500, 16777217, 'mlp');
+";
+
+/// Reads FRAMEWORK_ANNOTATIONS as NVTX-like ranges
+struct FrameworkAnnotations {
+    activity_type: &'static str,
+    link_role: ActivityLinkRole,
+}
+
+impl ActivityExtractor for FrameworkAnnotations {
+    fn activity_type(&self) -> &str {
+        self.activity_type
+    }
+
+    fn table_name(&self) -> &str {
+        "FRAMEWORK_ANNOTATIONS"
+    }
+
+    fn query(&self) -> String {
+        "SELECT start, end, globalTid, label FROM FRAMEWORK_ANNOTATIONS".to_string()
+    }
+
+    fn map_row(&self, row: &Row, context: &ParseContext) -> Result<Option<ChromeTraceEvent>> {
+        let (start, end): (i64, i64) = (row.get(0)?, row.get(1)?);
+        let (pid, tid) = decompose_global_tid(row.get(2)?);
+        let device_id = context.device_map.get(&pid).copied().unwrap_or(pid);
+        let event = ChromeTraceEvent::complete(
+            row.get(3)?,
+            ns_to_us(start),
+            ns_to_us(end - start),
+            format!("Device {}", device_id),
+            format!("Framework Thread {}", tid),
+            self.activity_type.to_string(),
+        )
+        .with_arg("start_ns", start)
+        .with_arg("end_ns", end)
+        .with_arg("deviceId", device_id)
+        .with_arg("raw_pid", pid)
+        .with_arg("raw_tid", tid);
+        Ok(Some(event))
+    }
+
+    fn link_role(&self) -> ActivityLinkRole {
+        self.link_role
+    }
+}
+
+fn convert(
+    activity_types: &[&str],
+    extractor: FrameworkAnnotations,
+    sql: &str,
+) -> Result<(Vec<ChromeTraceEvent>, ConversionDiagnostics)> {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "plugins.sqlite", sql);
+    let options = ConversionOptions {
+        activity_types: activity_types
+            .iter()
+            .map(|&t| EventCategory::from(t))
+            .collect(),
+        include_metadata: false,
+        ..Default::default()
+    };
+    NsysChromeConverter::new(&path, Some(options))?
+        .with_activity_extractor(Arc::new(extractor))
+        .convert_with_diagnostics()
+}
+
+// ==========================
+// Tests for ActivityExtractor
+// ==========================
+
+#[test]
+fn test_custom_annotations_link_to_kernels() {
+    let extractor = FrameworkAnnotations {
+        activity_type: "framework",
+        link_role: ActivityLinkRole::Annotation,
+    };
+    let (events, diagnostics) = convert(
+        &["kernel", "cuda-api", "framework", "nvtx-kernel"],
+        extractor,
+        PLUGIN_SQL,
+    )
+    .unwrap();
+
+    let linked: Vec<(&str, f64)> = events
+        .iter()
+        .filter(|e| e.cat == "nvtx-kernel")
+        .map(|e| (e.name.as_str(), e.ts))
+        .collect();
+    assert_eq!(linked, vec![("attention", 3.0), ("mlp", 6.0)]);
+    assert!(diagnostics.missing_activities.is_empty());
+    assert!(!diagnostics
+        .unknown_tables
+        .contains(&"FRAMEWORK_ANNOTATIONS".to_string()));
+}
+
+#[test]
+fn test_custom_activity_passes_through_when_requested() {
+    let passthrough = || FrameworkAnnotations {
+        activity_type: "framework",
+        link_role: ActivityLinkRole::None,
+    };
+    let (events, _) = convert(&["kernel", "framework"], passthrough(), PLUGIN_SQL).unwrap();
+    let custom: Vec<&ChromeTraceEvent> = events.iter().filter(|e| e.cat == "framework").collect();
+    assert_eq!(custom.len(), 2);
+    assert_eq!(custom[0].tid, "Framework Thread 1");
+    assert!(events.iter().all(|e| e.cat != "nvtx-kernel"));
+
+    let (events, _) = convert(&["kernel"], passthrough(), PLUGIN_SQL).unwrap();
+    assert!(events.iter().all(|e| e.cat != "framework"));
+}
+
+#[test]
+fn test_missing_custom_table_is_reported() {
+    let extractor = FrameworkAnnotations {
+        activity_type: "framework",
+        link_role: ActivityLinkRole::Annotation,
+    };
+    let sql = format!("{}DROP TABLE FRAMEWORK_ANNOTATIONS;", PLUGIN_SQL);
+    let (events, diagnostics) = convert(&["kernel", "framework"], extractor, &sql).unwrap();
+
+    assert!(events.iter().all(|e| e.cat != "framework"));
+    assert_eq!(
+        diagnostics.missing_activities,
+        vec!["framework".to_string()]
+    );
+}
+
+#[test]
+fn test_builtin_activity_type_is_rejected() {
+    let extractor = FrameworkAnnotations {
+        activity_type: "nvtx",
+        link_role: ActivityLinkRole::Annotation,
+    };
+    let err = convert(&["kernel", "nvtx"], extractor, PLUGIN_SQL).unwrap_err();
+    assert!(matches!(err, ConvertError::InvalidOption(_)));
+}
@@ -0,0 +1,136 @@
+//! Unit tests for heuristic layer inference
+
+use nsys_chrome::analysis::infer_layer_ranges;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::frontends::{assemble_trace, FrontendTrace};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Create a kernel event with the fields used by layer inference
+fn create_kernel(name: &str, start_ns: i64, end_ns: i64, stream: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        stream.to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(start_ns))
+    .with_arg("end_ns", serde_json::json!(end_ns))
+    .with_arg("deviceId", serde_json::json!(0))
+}
+
+/// Back-to-back kernels on one stream, 10us each with 1us gaps
+fn create_sequence(names: &[&str]) -> Vec<ChromeTraceEvent> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let start = i as i64 * 11_000;
+            create_kernel(name, start, start + 10_000, "Stream 7")
+        })
+        .collect()
+}
+
+// ==========================
+// Tests for infer_layer_ranges
+// ==========================
+
+#[test]
+fn test_infer_linear_and_norm_layers() {
+    let kernels = create_sequence(&[
+        "ampere_sgemm_128x64_tn",
+        "vectorized_elementwise_kernel<AddFunctor>",
+        "vectorized_elementwise_kernel<GeluCUDAKernelImpl>",
+        "vectorized_layer_norm_kernel",
+        "ampere_sgemm_64x64_nn",
+    ]);
+    let ranges = infer_layer_ranges(&kernels);
+
+    let names: Vec<&str> = ranges.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, vec!["Linear+GELU", "LayerNorm", "Linear"]);
+
+    let linear = &ranges[0];
+    assert_eq!(linear.tid, "Inferred Layers (Stream 7)");
+    assert_eq!(linear.cat, "nvtx");
+    assert_eq!(linear.args["start_ns"], 0);
+    assert_eq!(linear.args["end_ns"], 32_000);
+    assert_eq!(linear.args["kernels"], 3);
+    assert_eq!(linear.args["pattern"], "gemm+bias+activation");
+    assert_eq!(linear.args["synthetic"], true);
+    assert_eq!(linear.args["heuristic"], "kernel-pattern");
+}
+
+#[test]
+fn test_infer_cudnn_convolution_groups_helper_kernels() {
+    let kernels = create_sequence(&[
+        "nchwToNhwcKernel",
+        "cudnn::winograd::generateWinogradTilesKernel",
+        "sm80_xmma_fprop_implicit_gemm_f16f16",
+        "nhwcToNchwKernel",
+        "relu_kernel",
+        "unrelated_copy_kernel",
+        "vectorized_elementwise_kernel<ReluFunctor>",
+    ]);
+    let ranges = infer_layer_ranges(&kernels);
+
+    // The trailing activation has no anchor to attach to
+    assert_eq!(ranges.len(), 1);
+    assert_eq!(ranges[0].name, "Conv+ReLU");
+    assert_eq!(ranges[0].args["kernels"], 5);
+    assert_eq!(ranges[0].args["pattern"], "layout+conv+layout+activation");
+}
+
+#[test]
+fn test_infer_layers_split_on_gaps_and_streams() {
+    let kernels = vec![
+        create_kernel("cutlass_gemm_relu", 0, 10_000, "Stream 7"),
+        // Too far from the GEMM to be its bias
+        create_kernel("bias_add_kernel", 500_000, 510_000, "Stream 7"),
+        create_kernel("flash_fwd_kernel", 0, 10_000, "Stream 9"),
+    ];
+    let ranges = infer_layer_ranges(&kernels);
+
+    assert_eq!(ranges.len(), 2);
+    assert_eq!(ranges[0].name, "Linear+ReLU");
+    assert_eq!(ranges[0].args["kernels"], 1);
+    assert_eq!(ranges[1].name, "Attention");
+    assert_eq!(ranges[1].tid, "Inferred Layers (Stream 9)");
+}
+
+#[test]
+fn test_assemble_trace_infers_layers_only_without_annotations() {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
+        include_metadata: false,
+        infer_layers: true,
+        ..Default::default()
+    };
+    let kernels = || create_sequence(&["ampere_sgemm_128x64_tn", "relu_kernel"]);
+
+    let events = assemble_trace(
+        FrontendTrace {
+            kernel_events: kernels(),
+            ..Default::default()
+        },
+        &options,
+    )
+    .unwrap();
+    assert!(events.iter().any(|e| e.name == "Linear+ReLU"));
+
+    let annotation = create_kernel("forward", 0, 30_000, "NVTX Thread 1");
+    let events = assemble_trace(
+        FrontendTrace {
+            kernel_events: kernels(),
+            annotation_events: vec![annotation],
+            ..Default::default()
+        },
+        &options,
+    )
+    .unwrap();
+    assert!(!events.iter().any(|e| e.args.contains_key("heuristic")));
+}
@@ -0,0 +1,154 @@
+//! Unit tests for synthetic step marker analysis
+
+use nsys_chrome::analysis::{detect_step_boundaries, synthesize_step_markers};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::frontends::{assemble_trace, FrontendTrace};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Create a kernel event with the fields used by step detection
+fn create_kernel(name: &str, start_ns: i64, end_ns: i64, device_id: i32) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        format!("Device {}", device_id),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(start_ns))
+    .with_arg("end_ns", serde_json::json!(end_ns))
+    .with_arg("deviceId", serde_json::json!(device_id))
+}
+
+/// Build `steps` iterations of 1ms: compute kernels followed by a burst of optimizer kernels
+fn create_training_trace(steps: i64, device_id: i32) -> Vec<ChromeTraceEvent> {
+    let mut kernels = Vec::new();
+    for step in 0..steps {
+        let base = step * 1_000_000;
+        kernels.push(create_kernel("gemm_fwd", base, base + 400_000, device_id));
+        for i in 0..4 {
+            let start = base + 900_000 + i * 20_000;
+            kernels.push(create_kernel("multi_tensor_apply_kernel", start, start + 10_000, device_id));
+        }
+    }
+    kernels
+}
+
+// ==========================
+// Tests for detect_step_boundaries
+// ==========================
+
+#[test]
+fn test_detect_step_boundaries_bursts() {
+    let anchors = vec![
+        (100, 110),
+        (120, 130),
+        (1100, 1110),
+        (1120, 1130),
+        (2100, 2110),
+        (2120, 2130),
+    ];
+    let boundaries = detect_step_boundaries(&anchors).unwrap();
+    assert_eq!(boundaries, vec![130, 1130, 2130]);
+}
+
+#[test]
+fn test_detect_step_boundaries_single_anchor_per_step() {
+    let anchors = vec![(1000, 1100), (2000, 2100), (3000, 3100)];
+    let boundaries = detect_step_boundaries(&anchors).unwrap();
+    assert_eq!(boundaries, vec![1100, 2100, 3100]);
+}
+
+#[test]
+fn test_detect_step_boundaries_not_periodic() {
+    let anchors = vec![(0, 10), (210, 220), (820, 830), (2830, 2840)];
+    assert!(detect_step_boundaries(&anchors).is_none());
+}
+
+#[test]
+fn test_detect_step_boundaries_too_few_anchors() {
+    assert!(detect_step_boundaries(&[]).is_none());
+    assert!(detect_step_boundaries(&[(0, 10)]).is_none());
+}
+
+// ==========================
+// Tests for synthesize_step_markers
+// ==========================
+
+#[test]
+fn test_synthesize_step_markers_optimizer() {
+    let kernels = create_training_trace(3, 0);
+    let markers = synthesize_step_markers(&kernels);
+
+    assert_eq!(markers.len(), 3);
+    assert_eq!(markers[0].name, "step 0");
+    assert_eq!(markers[0].cat, "nvtx");
+    assert_eq!(markers[0].args["synthetic"], serde_json::json!(true));
+    assert_eq!(markers[0].args["heuristic"], serde_json::json!("optimizer"));
+    assert_eq!(markers[0].args["start_ns"], serde_json::json!(0));
+    // Steps are contiguous
+    assert_eq!(markers[1].args["start_ns"], markers[0].args["end_ns"]);
+}
+
+#[test]
+fn test_synthesize_step_markers_nccl_fallback() {
+    let mut kernels = Vec::new();
+    for step in 0..3 {
+        let base = step * 1_000_000;
+        kernels.push(create_kernel("gemm_fwd", base, base + 400_000, 1));
+        kernels.push(create_kernel("ncclKernel_AllReduce_RING_LL_Sum_float", base + 800_000, base + 900_000, 1));
+    }
+    let markers = synthesize_step_markers(&kernels);
+
+    assert_eq!(markers.len(), 3);
+    assert_eq!(markers[0].pid, "Device 1");
+    assert_eq!(markers[0].args["heuristic"], serde_json::json!("nccl-allreduce"));
+}
+
+#[test]
+fn test_synthesize_step_markers_no_anchor_kernels() {
+    let kernels = vec![
+        create_kernel("gemm", 0, 100, 0),
+        create_kernel("gemm", 1000, 1100, 0),
+    ];
+    assert!(synthesize_step_markers(&kernels).is_empty());
+}
+
+#[test]
+fn test_assemble_trace_synthesizes_steps_only_without_annotations() {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
+        include_metadata: false,
+        synthesize_steps: true,
+        ..Default::default()
+    };
+    let is_step = |e: &ChromeTraceEvent| e.args.contains_key("synthetic");
+
+    let events = assemble_trace(
+        FrontendTrace {
+            kernel_events: create_training_trace(3, 0),
+            ..Default::default()
+        },
+        &options,
+    )
+    .unwrap();
+    assert_eq!(events.iter().filter(|e| is_step(e)).count(), 3);
+
+    let mut annotation = create_kernel("train_step", 0, 1_000_000, 0);
+    annotation.cat = "nvtx".into();
+    annotation.tid = "NVTX Thread 1".into();
+    let events = assemble_trace(
+        FrontendTrace {
+            kernel_events: create_training_trace(3, 0),
+            annotation_events: vec![annotation],
+            ..Default::default()
+        },
+        &options,
+    )
+    .unwrap();
+    assert!(!events.iter().any(is_step));
+}
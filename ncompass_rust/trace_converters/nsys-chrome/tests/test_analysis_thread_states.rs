@@ -0,0 +1,112 @@
+//! Unit tests for thread-state coloring of API calls
+
+use nsys_chrome::analysis::{classify_api_call, color_api_thread_states, ApiThreadState};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::frontends::{assemble_trace, FrontendTrace};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn api_event(name: &str, ts: f64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        1.0,
+        "Device 0".to_string(),
+        "CUDA API Thread 1".to_string(),
+        "cuda_api".to_string(),
+    )
+}
+
+// ==========================
+// Tests for classify_api_call
+// ==========================
+
+#[test]
+fn test_classify_api_call() {
+    let waiting = Some(ApiThreadState::Waiting);
+    let io = Some(ApiThreadState::Io);
+    let running = Some(ApiThreadState::Running);
+
+    assert_eq!(classify_api_call("cudaDeviceSynchronize"), waiting);
+    assert_eq!(classify_api_call("cudaStreamSynchronize_v3020"), waiting);
+    assert_eq!(classify_api_call("cuStreamWaitEvent"), waiting);
+    assert_eq!(classify_api_call("zeEventHostSynchronize"), waiting);
+
+    assert_eq!(classify_api_call("cudaMemcpyAsync"), io);
+    assert_eq!(classify_api_call("cuMemsetD32_v2"), io);
+    assert_eq!(classify_api_call("hipMemcpy"), io);
+
+    assert_eq!(classify_api_call("cudaLaunchKernel_v7000"), running);
+    assert_eq!(classify_api_call("cuGraphLaunch"), running);
+    assert_eq!(
+        classify_api_call("zeCommandListAppendLaunchKernel"),
+        running
+    );
+
+    assert_eq!(classify_api_call("cudaMalloc"), None);
+    assert_eq!(classify_api_call("cudaGetDevice"), None);
+}
+
+// ==========================
+// Tests for color_api_thread_states
+// ==========================
+
+#[test]
+fn test_color_api_thread_states() {
+    let mut events = vec![
+        api_event("cudaLaunchKernel", 0.0),
+        api_event("cudaMemcpyAsync", 1.0),
+        api_event("cudaStreamSynchronize", 2.0),
+        api_event("cudaMalloc", 3.0),
+        api_event("cudaDeviceSynchronize", 4.0).with_color("good".to_string()),
+        ChromeTraceEvent::complete(
+            "Launch".to_string(),
+            5.0,
+            1.0,
+            "Device 0".to_string(),
+            "NVTX Thread 1".to_string(),
+            "nvtx".to_string(),
+        ),
+    ];
+
+    assert_eq!(color_api_thread_states(&mut events), 3);
+
+    assert_eq!(events[0].cname.as_deref(), Some("thread_state_running"));
+    assert_eq!(events[0].args["thread_state"], "running");
+    assert_eq!(events[1].cname.as_deref(), Some("thread_state_iowait"));
+    assert_eq!(events[1].args["thread_state"], "io");
+    assert_eq!(events[2].cname.as_deref(), Some("thread_state_sleeping"));
+    assert_eq!(events[2].args["thread_state"], "waiting");
+
+    // Unclassified, already colored and non-API events are untouched
+    assert!(events[3].cname.is_none());
+    assert_eq!(events[4].cname.as_deref(), Some("good"));
+    assert!(!events[4].args.contains_key("thread_state"));
+    assert!(events[5].cname.is_none());
+}
+
+#[test]
+fn test_assemble_trace_api_thread_states_option() {
+    let trace = || FrontendTrace {
+        api_events: vec![api_event("hipDeviceSynchronize", 0.0)],
+        ..Default::default()
+    };
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::CudaApi],
+        include_metadata: false,
+        ..Default::default()
+    };
+
+    let plain = assemble_trace(trace(), &options).unwrap();
+    assert!(plain[0].cname.is_none());
+
+    let options = ConversionOptions {
+        api_thread_states: true,
+        ..options
+    };
+    let colored = assemble_trace(trace(), &options).unwrap();
+    assert_eq!(colored[0].cname.as_deref(), Some("thread_state_sleeping"));
+}
@@ -0,0 +1,132 @@
+//! Unit tests for the NVTX-only annotations mode
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::annotations::{
+    annotate_nesting_depth, annotation_metadata, group_by_process, NVTX_DEPTH_ARG,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// One process with nested ranges on one thread and a range plus a mark on
+/// another, next to a kernel table the annotations mode must not read
+const ANNOTATED_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'gemm_kernel');
+    CREATE TABLE ThreadNames (nameId INTEGER, priority INTEGER, globalTid INTEGER);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (3000, 4000, 3, 7, 1, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES
+        (1000, 9000, 'step', NULL, 16777217, 59),
+        (2000, 5000, 'forward', NULL, 16777217, 59),
+        (2500, 3000, 'attention', NULL, 16777217, 59),
+        (6000, 8000, 'backward', NULL, 16777217, 59),
+        (1500, 2500, 'load', NULL, 16777218, 59),
+        (1700, NULL, 'checkpoint', NULL, 16777218, 34);
+";
+
+fn range(name: &str, start_ns: i64, end_ns: i64, tid: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        "nvtx".to_string(),
+    )
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+    .with_arg("raw_pid", 1)
+    .with_arg("raw_tid", 1)
+}
+
+fn depth(events: &[ChromeTraceEvent], name: &str) -> i64 {
+    events
+        .iter()
+        .find(|e| e.name == name)
+        .and_then(|e| e.args.get(NVTX_DEPTH_ARG))
+        .and_then(|v| v.as_i64())
+        .unwrap()
+}
+
+// ==========================
+// Tests for annotation passes
+// ==========================
+
+#[test]
+fn test_nesting_depth_per_track() {
+    let mut events = vec![
+        range("forward", 2000, 5000, "NVTX Thread 1"),
+        range("step", 1000, 9000, "NVTX Thread 1"),
+        range("attention", 2500, 3000, "NVTX Thread 1"),
+        range("backward", 5000, 8000, "NVTX Thread 1"),
+        range("load", 1500, 2500, "NVTX Thread 2"),
+    ];
+    let deepest = annotate_nesting_depth(&mut events);
+
+    assert_eq!(deepest, 2);
+    assert_eq!(depth(&events, "step"), 0);
+    assert_eq!(depth(&events, "forward"), 1);
+    assert_eq!(depth(&events, "attention"), 2);
+    // Starts as "forward" ends, so it is its sibling rather than its child
+    assert_eq!(depth(&events, "backward"), 1);
+    assert_eq!(depth(&events, "load"), 0);
+}
+
+#[test]
+fn test_group_by_process_and_metadata() {
+    let mut events = vec![range("step", 1000, 9000, "NVTX Thread 1").with_arg("deviceId", 1)];
+    group_by_process(&mut events);
+    assert_eq!(events[0].pid.as_ref(), "Process 1");
+    assert!(!events[0].args.contains_key("deviceId"));
+
+    let thread_names = HashMap::from([(1, "python".to_string())]);
+    let metadata = annotation_metadata(&events, &thread_names);
+    assert_eq!(metadata.len(), 2);
+    assert!(metadata.iter().all(|m| m.ph == ChromeTracePhase::Metadata));
+    assert_eq!(metadata[0].args["name"], "Process 1");
+    assert_eq!(metadata[1].tid.as_ref(), "NVTX Thread 1");
+    assert_eq!(metadata[1].args["name"], "NVTX Thread 1 (python)");
+}
+
+// ==========================
+// Tests for annotations-only conversion
+// ==========================
+
+#[test]
+fn test_annotations_only_skips_kernel_tables() {
+    let options = ConversionOptions {
+        annotations_only: true,
+        ..Default::default()
+    };
+    let events = convert_sql(ANNOTATED_SQL, options);
+
+    assert!(events.iter().all(|e| e.cat != "kernel"));
+    assert!(events.iter().all(|e| !e.pid.starts_with("Device")));
+    let ranges: Vec<&ChromeTraceEvent> = events.iter().filter(|e| e.cat == "nvtx").collect();
+    assert_eq!(ranges.len(), 5);
+    assert_eq!(depth(&events, "attention"), 2);
+    assert!(events
+        .iter()
+        .any(|e| e.name == "checkpoint" && e.ph == ChromeTracePhase::Instant));
+    assert!(events
+        .iter()
+        .any(|e| e.name == "process_name" && e.args["name"] == "Process 1"));
+}
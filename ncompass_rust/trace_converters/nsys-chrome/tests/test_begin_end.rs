@@ -0,0 +1,168 @@
+//! Unit tests for Begin/End event emission
+
+use nsys_chrome::begin_end::to_begin_end;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase};
+use nsys_chrome::writer::WriteOptions;
+use nsys_chrome::ChromeTraceWriter;
+use serde_json::json;
+use std::collections::HashMap;
+use tempfile::NamedTempFile;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, dur: f64, tid: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        tid.to_string(),
+        "nvtx".to_string(),
+    )
+}
+
+/// (phase, name, ts) of each event, for compact assertions
+fn summarize(events: &[ChromeTraceEvent]) -> Vec<(&'static str, String, f64)> {
+    events
+        .iter()
+        .map(|e| {
+            let ph = match e.ph {
+                ChromeTracePhase::DurationBegin => "B",
+                ChromeTracePhase::DurationEnd => "E",
+                ChromeTracePhase::Complete => "X",
+                _ => "?",
+            };
+            (ph, e.name.clone(), e.ts)
+        })
+        .collect()
+}
+
+/// Check every E closes the innermost open B on its track
+fn assert_balanced(events: &[ChromeTraceEvent]) {
+    let mut stacks: HashMap<String, Vec<String>> = HashMap::new();
+    for e in events {
+        let stack = stacks.entry(e.tid.to_string()).or_default();
+        match e.ph {
+            ChromeTracePhase::DurationBegin => stack.push(e.name.clone()),
+            ChromeTracePhase::DurationEnd => assert_eq!(stack.pop(), Some(e.name.clone())),
+            _ => {}
+        }
+    }
+    assert!(stacks.values().all(|s| s.is_empty()));
+}
+
+// ==========================
+// Tests for to_begin_end
+// ==========================
+
+#[test]
+fn test_nested_events_close_in_order() {
+    let events = vec![
+        create_event("outer", 0.0, 100.0, "Thread 1"),
+        create_event("inner", 10.0, 20.0, "Thread 1"),
+        create_event("tail", 70.0, 30.0, "Thread 1"),
+        create_event("next", 100.0, 5.0, "Thread 1"),
+    ];
+    let split = to_begin_end(events);
+
+    assert_eq!(
+        summarize(&split),
+        vec![
+            ("B", "outer".to_string(), 0.0),
+            ("B", "inner".to_string(), 10.0),
+            ("E", "inner".to_string(), 30.0),
+            ("B", "tail".to_string(), 70.0),
+            // Equal ends close the inner event first
+            ("E", "tail".to_string(), 100.0),
+            ("E", "outer".to_string(), 100.0),
+            ("B", "next".to_string(), 100.0),
+            ("E", "next".to_string(), 105.0),
+        ]
+    );
+    assert_balanced(&split);
+    assert!(split.iter().all(|e| e.dur.is_none()));
+}
+
+#[test]
+fn test_args_stay_on_begin_event() {
+    let events = vec![create_event("k", 0.0, 1.0, "Stream 7").with_arg("grid", 4)];
+    let split = to_begin_end(events);
+
+    assert_eq!(split[0].args["grid"], 4);
+    assert!(split[1].args.is_empty());
+    assert_eq!(split[1].pid, split[0].pid);
+    assert_eq!(split[1].tid, split[0].tid);
+}
+
+#[test]
+fn test_truncated_events_are_balanced() {
+    let unfinished = |name: &str, ts: f64, tid: &str| {
+        let mut event = create_event(name, ts, 0.0, tid);
+        event.dur = None;
+        event
+    };
+    let events = vec![
+        create_event("parent", 0.0, 50.0, "Thread 1"),
+        unfinished("open", 5.0, "Thread 1"),
+        unfinished("root", 20.0, "Thread 3"),
+        // Outlives its parent, so it is cut at the parent's end
+        create_event("overhang", 40.0, 30.0, "Thread 1"),
+        create_event("other", 45.0, 100.0, "Thread 2"),
+    ];
+    let split = to_begin_end(events);
+
+    assert_balanced(&split);
+    let end_of = |name: &str| {
+        split
+            .iter()
+            .find(|e| e.ph == ChromeTracePhase::DurationEnd && e.name == name)
+            .unwrap()
+            .ts
+    };
+    assert_eq!(end_of("overhang"), 50.0);
+    // Events without a duration close with their parent, or at the end of the trace
+    assert_eq!(end_of("open"), 50.0);
+    assert_eq!(end_of("root"), 145.0);
+    for name in ["open", "root", "overhang"] {
+        let begin = split.iter().find(|e| e.name == name).unwrap();
+        assert_eq!(begin.args["truncated"], true);
+    }
+    assert!(!split[0].args.contains_key("truncated"));
+}
+
+// ==========================
+// Tests for writer integration
+// ==========================
+
+#[test]
+fn test_write_begin_end_keeps_overflow_tracks() {
+    let events = vec![
+        ChromeTraceEvent::metadata(
+            "thread_name".to_string(),
+            "Device 0".to_string(),
+            "Stream 7".to_string(),
+            HashMap::from([("name".to_string(), json!("Stream 7"))]),
+        ),
+        create_event("a", 0.0, 10.0, "Stream 7"),
+        // Partially overlaps "a" and moves to the overflow track before splitting
+        create_event("b", 5.0, 10.0, "Stream 7"),
+    ];
+    let file = NamedTempFile::new().unwrap();
+    let options = WriteOptions {
+        begin_end: true,
+        ..Default::default()
+    };
+    ChromeTraceWriter::write_to(file.reopen().unwrap(), events, options).unwrap();
+
+    let text = std::fs::read_to_string(file.path()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    let written = json["traceEvents"].as_array().unwrap();
+    let phases: Vec<&str> = written.iter().map(|e| e["ph"].as_str().unwrap()).collect();
+    assert_eq!(phases, vec!["M", "B", "B", "E", "E"]);
+    assert_eq!(written[2]["tid"], "↳ Stream 7");
+    assert_eq!(written[4]["tid"], "↳ Stream 7");
+    assert_eq!(written[4]["ts"], 15.0);
+    assert!(written.iter().all(|e| e.get("dur").is_none()));
+}
@@ -0,0 +1,82 @@
+//! Unit tests for the output writer benchmark
+
+use flate2::read::GzDecoder;
+use nsys_chrome::bench::{parse_bench_writer, run_write_bench, synthetic_events, BenchWriter};
+use std::io::Read;
+use tempfile::TempDir;
+
+// ==========================
+// Tests for synthetic_events
+// ==========================
+
+#[test]
+fn test_synthetic_events_are_deterministic() {
+    for count in [0, 1, 2, 1000] {
+        assert_eq!(synthetic_events(count).len(), count);
+    }
+    let first = serde_json::to_value(synthetic_events(300)).unwrap();
+    let second = serde_json::to_value(synthetic_events(300)).unwrap();
+    assert_eq!(first, second);
+
+    let events = synthetic_events(3);
+    let cats: Vec<&str> = events.iter().map(|e| &*e.cat).collect();
+    assert_eq!(cats, vec!["kernel", "cuda_api", "nvtx"]);
+    assert_eq!(
+        events[0].args["correlationId"],
+        events[1].args["correlationId"]
+    );
+}
+
+// ==========================
+// Tests for run_write_bench
+// ==========================
+
+#[test]
+fn test_run_write_bench_reports_each_writer() {
+    let dir = TempDir::new().unwrap();
+    let events = synthetic_events(3000);
+    let report = run_write_bench(&events, &BenchWriter::ALL, dir.path(), 1).unwrap();
+
+    assert_eq!(report.events, 3000);
+    let writers: Vec<BenchWriter> = report.results.iter().map(|r| r.writer).collect();
+    assert_eq!(
+        writers,
+        vec![
+            BenchWriter::Plain,
+            BenchWriter::Gzip,
+            BenchWriter::ParallelGzip
+        ]
+    );
+    assert_eq!(report.unavailable, vec![BenchWriter::Zstd]);
+
+    // The plain output is exactly the JSON every writer encodes
+    assert_eq!(report.results[0].output_bytes, report.json_bytes);
+    assert!(report.results[1].output_bytes < report.json_bytes / 2);
+
+    let mut plain = String::new();
+    std::fs::File::open(dir.path().join("bench-plain.json"))
+        .unwrap()
+        .read_to_string(&mut plain)
+        .unwrap();
+    for name in ["bench-gz.json.gz", "bench-parallel-gz.json.gz"] {
+        let mut decoded = String::new();
+        GzDecoder::new(std::fs::File::open(dir.path().join(name)).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+    }
+
+    let table = report.render_table();
+    assert!(table.contains("parallel-gz"));
+    assert!(table.contains("zstd         not available in this build"));
+}
+
+#[test]
+fn test_parse_bench_writer() {
+    assert_eq!(
+        parse_bench_writer("parallel-gz").unwrap(),
+        BenchWriter::ParallelGzip
+    );
+    assert_eq!(parse_bench_writer(" gz").unwrap(), BenchWriter::Gzip);
+    assert!(parse_bench_writer("lz4").is_err());
+}
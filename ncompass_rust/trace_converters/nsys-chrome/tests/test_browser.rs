@@ -0,0 +1,122 @@
+//! Unit tests for the terminal event browser
+
+use nsys_chrome::browser::{run_browser, EventKind, ListQuery, SortKey, TraceBrowser};
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::ChromeTraceWriter;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, dur: f64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+}
+
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_event("forward", 0.0, 100.0, "NVTX Thread 1", "nvtx"),
+        create_event("ampere_sgemm_128x64", 10.0, 40.0, "Stream 7", "kernel"),
+        create_event("layer_norm_kernel", 55.0, 5.0, "Stream 7", "kernel"),
+        create_event("ampere_sgemm_64x64", 60.0, 20.0, "Stream 9", "kernel"),
+        create_event("cudaLaunchKernel", 8.0, 2.0, "Thread 1", "cuda_api"),
+    ]
+}
+
+// ==========================
+// Tests for lists
+// ==========================
+
+#[test]
+fn test_list_kernels_sorted_and_searched() {
+    let browser = TraceBrowser::from_events(&sample_events());
+    assert_eq!(browser.len(), 5);
+
+    let query = ListQuery::default();
+    let names: Vec<&str> = browser
+        .list(&query)
+        .iter()
+        .map(|e| e.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "ampere_sgemm_128x64",
+            "ampere_sgemm_64x64",
+            "layer_norm_kernel"
+        ]
+    );
+
+    let query = ListQuery {
+        search: Some("SGEMM".to_string()),
+        sort: SortKey::Start,
+        ..Default::default()
+    };
+    let selected = browser.list(&query);
+    assert_eq!(selected.len(), 2);
+    assert_eq!(selected[0].start_us, 10.0);
+    assert_eq!(selected[0].track, "Device 0 / Stream 7");
+
+    let query = ListQuery {
+        kind: EventKind::Nvtx,
+        ..Default::default()
+    };
+    assert_eq!(browser.list(&query)[0].name, "forward");
+}
+
+#[test]
+fn test_open_written_trace() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json.gz");
+    ChromeTraceWriter::write_gz(path.to_str().unwrap(), sample_events()).unwrap();
+
+    let browser = TraceBrowser::open(&path).unwrap();
+    assert_eq!(browser.len(), 5);
+    let tracks = browser.tracks();
+    assert_eq!(tracks.len(), 4);
+    assert_eq!(tracks[1], ("Device 0 / Stream 7".to_string(), 2, 45.0));
+}
+
+// ==========================
+// Tests for timelines
+// ==========================
+
+#[test]
+fn test_render_timeline() {
+    let browser = TraceBrowser::from_events(&sample_events());
+    let timeline = browser.render_timeline(10, Some("Stream"));
+
+    let lines: Vec<&str> = timeline.lines().collect();
+    // Header plus one bar per matching track
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("Device 0 / Stream 7"));
+    assert!(lines[1].ends_with("|.#####....|"));
+    assert!(lines[2].ends_with("|......##..|"));
+}
+
+// ==========================
+// Tests for run_browser
+// ==========================
+
+#[test]
+fn test_run_browser_commands() {
+    let browser = TraceBrowser::from_events(&sample_events());
+    let input = "nvtx\nsearch for\nsort bogus\ntop 1\nbogus\nquit\nkernels\n";
+    let mut output = Vec::new();
+    run_browser(&browser, input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.starts_with("Loaded 5 events."));
+    assert!(output.contains("1 NVTX ranges matching 'for'"));
+    assert!(output.contains("Usage: sort duration|start|name"));
+    assert!(output.contains("Unknown command 'bogus'"));
+    // Nothing after quit is run
+    assert_eq!(output.matches("3 kernels").count(), 1);
+}
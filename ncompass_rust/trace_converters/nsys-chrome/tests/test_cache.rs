@@ -0,0 +1,339 @@
+//! Unit tests for cache module
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::cache::{
+    decode_trace, encode_trace, is_event_cache, read_event_cache, write_event_cache,
+};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::frontends::{assemble_trace, FrontendTrace};
+use nsys_chrome::models::{
+    BindingPoint, ChromeTraceEvent, ChromeTracePhase, ConversionOptions, InstantScope,
+    StringOrInt,
+};
+use nsys_chrome::NsysChromeConverter;
+use std::collections::HashMap;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        10.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+    .with_arg("deviceId", serde_json::json!(0))
+}
+
+fn sample_trace() -> FrontendTrace {
+    let mut flow = ChromeTraceEvent::new(
+        "flow".to_string(),
+        ChromeTracePhase::FlowStart,
+        1.5,
+        "Device 0".to_string(),
+        "Thread 1".to_string(),
+        "cuda_flow".to_string(),
+    );
+    flow.id = Some(StringOrInt::Int(-42));
+    flow.bp = Some(BindingPoint::Enclosing);
+
+    let mut instant = ChromeTraceEvent::new(
+        "mark".to_string(),
+        ChromeTracePhase::Instant,
+        2.25,
+        "Device 0".to_string(),
+        "Thread 1".to_string(),
+        "osrt".to_string(),
+    );
+    instant.id = Some(StringOrInt::String("mark-1".to_string()));
+    instant.bp = Some(BindingPoint::Same);
+    instant.s = Some(InstantScope::Process);
+
+    FrontendTrace {
+        kernel_events: vec![create_event("gemm", 0.5, "Stream 7", "kernel")
+            .with_arg("grid", serde_json::json!([8, 8, 1]))
+            .with_arg("est_flops", serde_json::json!(1.5e9))
+            .with_arg("big", serde_json::json!(u64::MAX))
+            .with_arg("neg", serde_json::json!(-7))
+            .with_arg("frame", serde_json::json!({"file": "a.py", "line": 3}))
+            .with_arg("exact", serde_json::json!(true))
+            .with_arg("missing", serde_json::Value::Null)
+            .with_color("good".to_string())],
+        api_events: vec![flow],
+        annotation_events: vec![create_event("forward", 0.0, "NVTX Thread 1", "nvtx")],
+        other_events: vec![instant],
+    }
+}
+
+fn to_json(events: &[ChromeTraceEvent]) -> Vec<serde_json::Value> {
+    events
+        .iter()
+        .map(|e| serde_json::to_value(e).unwrap())
+        .collect()
+}
+
+// ==========================
+// Tests for encode/decode
+// ==========================
+
+#[test]
+fn test_cache_round_trip_preserves_events() {
+    let trace = sample_trace();
+    let decoded = decode_trace(&encode_trace(&trace)).unwrap();
+
+    assert_eq!(
+        to_json(&decoded.kernel_events),
+        to_json(&trace.kernel_events)
+    );
+    assert_eq!(to_json(&decoded.api_events), to_json(&trace.api_events));
+    assert_eq!(
+        to_json(&decoded.annotation_events),
+        to_json(&trace.annotation_events)
+    );
+    assert_eq!(to_json(&decoded.other_events), to_json(&trace.other_events));
+    assert_eq!(decoded.kernel_events[0].args["big"], u64::MAX);
+}
+
+#[test]
+fn test_cache_rejects_bad_input() {
+    let bytes = encode_trace(&sample_trace());
+
+    assert!(decode_trace(b"not a cache file").is_err());
+    assert!(decode_trace(&bytes[..bytes.len() - 1]).is_err());
+
+    let mut wrong_version = bytes.clone();
+    wrong_version[8] = 99;
+    let err = decode_trace(&wrong_version).unwrap_err();
+    assert!(err.to_string().contains("version"));
+}
+
+#[test]
+fn test_cache_bytes_are_portable() {
+    // Little-endian and 64-bit throughout, so caches move between hosts
+    let trace = FrontendTrace {
+        kernel_events: vec![ChromeTraceEvent::complete(
+            "k".to_string(),
+            1.5,
+            0.5,
+            "p".to_string(),
+            "t".to_string(),
+            "c".to_string(),
+        )
+        .with_arg("n", -1)],
+        ..Default::default()
+    };
+    let mut expected = b"NSCACHE\0".to_vec();
+    expected.extend_from_slice(&[2, 0, 0, 0]);
+    expected.extend_from_slice(&[5, 1, b'k', 1, b'p', 1, b't', 1, b'c', 1, b'n']);
+    expected.extend_from_slice(&[1, 0, 2]);
+    expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0xf8, 0x3f]);
+    expected.extend_from_slice(&[1, 2, 3, 1]);
+    expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0xe0, 0x3f]);
+    expected.extend_from_slice(&[1, 4, 3, 1]);
+    expected.extend_from_slice(&[0, 0, 0]);
+
+    assert_eq!(encode_trace(&trace), expected);
+    assert_eq!(decode_trace(&expected).unwrap().kernel_events[0].args["n"], -1);
+}
+
+#[test]
+fn test_cache_file_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("events.cache");
+    let path_str = path.to_str().unwrap();
+
+    write_event_cache(path_str, &sample_trace()).unwrap();
+    assert!(is_event_cache(&path));
+    assert!(!is_event_cache(&dir.path().join("missing.cache")));
+
+    let trace = read_event_cache(path_str).unwrap();
+    assert_eq!(trace.kernel_events[0].name, "gemm");
+}
+
+// ==========================
+// Tests for assembling cached events
+// ==========================
+
+#[test]
+fn test_assemble_cached_events_applies_options() {
+    let trace = || FrontendTrace {
+        annotation_events: vec![
+            create_event("forward", 0.0, "NVTX Thread 1", "nvtx"),
+            create_event("backward", 20.0, "NVTX Thread 1", "nvtx"),
+        ],
+        other_events: vec![create_event("read", 5.0, "Thread 1", "osrt")],
+        ..Default::default()
+    };
+
+    let mut color_scheme = HashMap::new();
+    color_scheme.insert("^fwd|^forward".to_string(), "good".to_string());
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Nvtx],
+        nvtx_event_prefix: Some(vec!["forward".to_string()]),
+        nvtx_color_scheme: color_scheme,
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = assemble_trace(trace(), &options).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].cname.as_deref(), Some("good"));
+
+    // Pass-through events follow their activity type
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Osrt],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = assemble_trace(trace(), &options).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "read");
+}
+
+#[test]
+fn test_converter_extract_matches_convert() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(
+        &dir,
+        "gemm.sqlite",
+        "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+        INSERT INTO StringIds VALUES (1, 'ampere_sgemm_128x64_nn');
+        CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+            start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+            correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+            gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+            blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+            registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+            (1000, 2000, 0, 7, 1, 16777216, 1, 4, 4, 1, 256, 1, 1, 32, 0, 0);",
+    );
+    let path = path.as_str();
+
+    let options = ConversionOptions::default();
+    let converted = NsysChromeConverter::new(path, Some(options.clone()))
+        .unwrap()
+        .convert()
+        .unwrap();
+
+    let (trace, _) = NsysChromeConverter::new(path, Some(options.clone()))
+        .unwrap()
+        .extract()
+        .unwrap();
+    assert_eq!(trace.kernel_events.len(), 1);
+    assert!(trace
+        .other_events
+        .iter()
+        .any(|e| e.ph == ChromeTracePhase::Metadata));
+
+    let cached = decode_trace(&encode_trace(&trace)).unwrap();
+    let (assembled, _) =
+        NsysChromeConverter::convert_extracted(cached, &options, Default::default()).unwrap();
+    assert_eq!(to_json(&assembled), to_json(&converted));
+}
+
+/// Kernels launched under "forward" and "backward" ranges, with an NVTX mark
+const LINKED_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'cudaLaunchKernel'), (2, 'gemm_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (3000, 4000, 0, 7, 1, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (4000, 4100, 0, 7, 2, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (8000, 10000, 0, 7, 3, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+        start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES
+        (1100, 1200, 16777217, 1, 1),
+        (1300, 1400, 16777217, 2, 1),
+        (7000, 7100, 16777217, 3, 1);
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES
+        (1000, 2000, 'forward', NULL, 16777217, 59),
+        (1500, NULL, 'backward_mark', NULL, 16777217, 34),
+        (6000, 7500, 'backward', NULL, 16777217, 59);
+";
+
+#[test]
+fn test_cached_conversion_matches_direct() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "linked.sqlite", LINKED_SQL);
+    let path = path.as_str();
+
+    let mut color_scheme = HashMap::new();
+    color_scheme.insert("^forward".to_string(), "good".to_string());
+    let option_sets = [
+        ConversionOptions::default(),
+        ConversionOptions {
+            nvtx_event_prefix: Some(vec!["forward".to_string()]),
+            nvtx_color_scheme: color_scheme,
+            min_kernel_duration_ns: 500,
+            ..Default::default()
+        },
+        ConversionOptions {
+            activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
+            include_metadata: false,
+            ..Default::default()
+        },
+    ];
+
+    // One cache, written with every activity, serves every set of options
+    let (trace, _) = NsysChromeConverter::new(path, None).unwrap().extract().unwrap();
+    let cache = dir.path().join("linked.nscache");
+    write_event_cache(cache.to_str().unwrap(), &trace).unwrap();
+
+    for options in option_sets {
+        let direct = NsysChromeConverter::new(path, Some(options.clone()))
+            .unwrap()
+            .convert()
+            .unwrap();
+        let cached = read_event_cache(cache.to_str().unwrap()).unwrap();
+        let (from_cache, _) =
+            NsysChromeConverter::convert_extracted(cached, &options, Default::default()).unwrap();
+        assert!(direct.iter().any(|e| e.name == "forward"));
+        assert_eq!(to_json(&from_cache), to_json(&direct));
+    }
+}
+
+#[test]
+fn test_convert_extracted_numbers_unnumbered_events() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "linked.sqlite", LINKED_SQL);
+    let direct = NsysChromeConverter::new(&path, None).unwrap().convert().unwrap();
+
+    // A trace assembled by hand carries no event IDs
+    let (mut trace, _) = NsysChromeConverter::new(&path, None).unwrap().extract().unwrap();
+    for event in trace
+        .kernel_events
+        .iter_mut()
+        .chain(&mut trace.api_events)
+        .chain(&mut trace.annotation_events)
+        .chain(&mut trace.other_events)
+    {
+        event.event_id = None;
+    }
+    let (converted, _) = NsysChromeConverter::convert_extracted(
+        trace,
+        &ConversionOptions::default(),
+        Default::default(),
+    )
+    .unwrap();
+    assert_eq!(to_json(&converted), to_json(&direct));
+}
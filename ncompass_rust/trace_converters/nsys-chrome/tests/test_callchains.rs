@@ -0,0 +1,230 @@
+//! Unit tests for callchains module
+
+use nsys_chrome::callchains::{
+    attach_api_call_stacks, attach_kernel_source_frames, extract_callchains,
+    extract_launch_callchain_ids, folded_api_stacks, select_launch_frames, write_folded_stacks,
+    CallchainFrame,
+};
+use nsys_chrome::models::ChromeTraceEvent;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Create an in-memory database with runtime and callchain tables
+fn create_callchain_db() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+            start INTEGER, end INTEGER, globalTid INTEGER,
+            correlationId INTEGER, nameId INTEGER, callchainId INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES (100, 200, 1, 7, 1, 1);
+        INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES (300, 400, 1, 8, 1, NULL);
+        CREATE TABLE CUDA_CALLCHAINS (
+            id INTEGER, symbol INTEGER, module INTEGER, stackDepth INTEGER
+        );
+        INSERT INTO CUDA_CALLCHAINS VALUES (1, 12, 22, 2);
+        INSERT INTO CUDA_CALLCHAINS VALUES (1, 10, 20, 0);
+        INSERT INTO CUDA_CALLCHAINS VALUES (1, 11, 21, 1);",
+    )
+    .unwrap();
+    conn
+}
+
+fn create_strings() -> HashMap<i32, String> {
+    let mut strings = HashMap::new();
+    strings.insert(10, "cudaLaunchKernel".to_string());
+    strings.insert(11, "at::native::launch_gemm".to_string());
+    strings.insert(12, "forward".to_string());
+    strings.insert(20, "/usr/lib/libcudart.so.12".to_string());
+    strings.insert(21, "libtorch_cuda.so".to_string());
+    strings.insert(22, "model.py".to_string());
+    strings
+}
+
+fn create_kernel(correlation_id: i32) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "gemm".to_string(),
+        1.0,
+        1.0,
+        "Device 0".to_string(),
+        "Stream 1".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("correlationId", serde_json::json!(correlation_id))
+}
+
+// ==========================
+// Tests for extraction
+// ==========================
+
+#[test]
+fn test_extract_callchains_orders_by_depth() {
+    let conn = create_callchain_db();
+    let callchains = extract_callchains(&conn, &create_strings()).unwrap();
+
+    let frames = &callchains[&1];
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0].function, "cudaLaunchKernel");
+    assert_eq!(frames[1].function, "at::native::launch_gemm");
+    assert_eq!(frames[2].function, "forward");
+}
+
+#[test]
+fn test_extract_callchains_missing_table() {
+    let conn = Connection::open_in_memory().unwrap();
+    let callchains = extract_callchains(&conn, &HashMap::new()).unwrap();
+    assert!(callchains.is_empty());
+}
+
+#[test]
+fn test_extract_launch_callchain_ids_skips_null() {
+    let conn = create_callchain_db();
+    let ids = extract_launch_callchain_ids(&conn).unwrap();
+    assert_eq!(ids.len(), 1);
+    assert_eq!(ids[&7], 1);
+}
+
+#[test]
+fn test_extract_launch_callchain_ids_without_column() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+            start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+        );",
+    )
+    .unwrap();
+    assert!(extract_launch_callchain_ids(&conn).unwrap().is_empty());
+}
+
+// ==========================
+// Tests for frame selection
+// ==========================
+
+#[test]
+fn test_select_launch_frames_skips_cuda_libraries() {
+    let conn = create_callchain_db();
+    let callchains = extract_callchains(&conn, &create_strings()).unwrap();
+
+    let selected = select_launch_frames(&callchains[&1], 1);
+    assert_eq!(selected.len(), 1);
+    assert_eq!(selected[0].function, "at::native::launch_gemm");
+}
+
+#[test]
+fn test_select_launch_frames_all_library_frames() {
+    let frames = vec![CallchainFrame {
+        function: "cudaLaunchKernel".to_string(),
+        module: Some("libcudart.so".to_string()),
+        file: None,
+        line: None,
+    }];
+    let selected = select_launch_frames(&frames, 3);
+    assert_eq!(selected.len(), 1);
+}
+
+#[test]
+fn test_frame_label_formats() {
+    let mut frame = CallchainFrame {
+        function: "forward".to_string(),
+        module: Some("model.py".to_string()),
+        file: None,
+        line: None,
+    };
+    assert_eq!(frame.label(), "forward (model.py)");
+
+    frame.file = Some("model.py".to_string());
+    frame.line = Some(42);
+    assert_eq!(frame.label(), "model.py:42 forward");
+}
+
+// ==========================
+// Tests for attach_kernel_source_frames
+// ==========================
+
+#[test]
+fn test_attach_kernel_source_frames() {
+    let conn = create_callchain_db();
+    let mut kernels = vec![create_kernel(7), create_kernel(8)];
+
+    let annotated = attach_kernel_source_frames(&conn, &create_strings(), &mut kernels, 2).unwrap();
+
+    assert_eq!(annotated, 1);
+    assert_eq!(
+        kernels[0].args["source_location"],
+        serde_json::json!("at::native::launch_gemm (libtorch_cuda.so)")
+    );
+    assert_eq!(kernels[0].args["source_frames"].as_array().unwrap().len(), 2);
+    assert!(!kernels[1].args.contains_key("source_location"));
+}
+
+#[test]
+fn test_attach_kernel_source_frames_disabled() {
+    let conn = create_callchain_db();
+    let mut kernels = vec![create_kernel(7)];
+
+    let annotated = attach_kernel_source_frames(&conn, &create_strings(), &mut kernels, 0).unwrap();
+
+    assert_eq!(annotated, 0);
+    assert!(!kernels[0].args.contains_key("source_location"));
+}
+
+// ==========================
+// Tests for API call stacks
+// ==========================
+
+fn create_api_call(correlation_id: i32, dur: f64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "cudaLaunchKernel".to_string(),
+        1.0,
+        dur,
+        "Device 0".to_string(),
+        "Thread 1".to_string(),
+        "cuda_api".to_string(),
+    )
+    .with_arg("correlationId", serde_json::json!(correlation_id))
+}
+
+#[test]
+fn test_attach_api_call_stacks_keeps_library_frames() {
+    let conn = create_callchain_db();
+    let mut api_events = vec![create_api_call(7, 0.1), create_api_call(8, 0.1)];
+
+    let annotated = attach_api_call_stacks(&conn, &create_strings(), &mut api_events).unwrap();
+    assert_eq!(annotated, 1);
+    assert_eq!(
+        api_events[0].args["call_stack"],
+        serde_json::json!([
+            "cudaLaunchKernel (/usr/lib/libcudart.so.12)",
+            "at::native::launch_gemm (libtorch_cuda.so)",
+            "forward (model.py)"
+        ])
+    );
+    assert!(!api_events[1].args.contains_key("call_stack"));
+}
+
+#[test]
+fn test_folded_api_stacks_sums_durations() {
+    let conn = create_callchain_db();
+    let mut api_events = vec![
+        create_api_call(7, 1.5),
+        create_api_call(7, 0.5),
+        create_api_call(8, 3.0),
+    ];
+    attach_api_call_stacks(&conn, &create_strings(), &mut api_events).unwrap();
+
+    let folded = folded_api_stacks(&api_events);
+    assert_eq!(folded.len(), 1);
+    let stack = "forward (model.py);at::native::launch_gemm (libtorch_cuda.so);\
+                 cudaLaunchKernel (/usr/lib/libcudart.so.12);cudaLaunchKernel";
+    assert_eq!(folded[stack], 2000);
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("stacks.folded");
+    write_folded_stacks(path.to_str().unwrap(), &api_events).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(text, format!("{} 2000\n", stack));
+}
@@ -0,0 +1,89 @@
+//! Unit tests for cancelling conversions
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::cancel::CancellationToken;
+use nsys_chrome::{ConvertError, NsysChromeConverter};
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Export with enough kernels that reading them takes many SQLite steps
+fn create_kernels(dir: &TempDir, kernels: i64) -> String {
+    let sql = format!(
+        "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+        INSERT INTO StringIds VALUES (1, 'gemm_kernel');
+        CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+            start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+            correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+            gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+            blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+            registersPerThread INTEGER, staticSharedMemory INTEGER,
+            dynamicSharedMemory INTEGER
+        );
+        WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {})
+        INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL
+        SELECT i * 1000, i * 1000 + 500, 0, 7, i, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0 FROM n;",
+        kernels
+    );
+    create_sqlite(dir, "cancel.sqlite", &sql)
+}
+
+fn convert(path: &str, token: &CancellationToken) -> Result<usize, ConvertError> {
+    NsysChromeConverter::new(path, None)?
+        .with_cancellation(token.clone())
+        .convert()
+        .map(|events| events.len())
+}
+
+// ==========================
+// Tests for CancellationToken
+// ==========================
+
+#[test]
+fn test_token_is_shared_by_clones() {
+    let token = CancellationToken::new();
+    let clone = token.clone();
+    assert!(clone.check().is_ok());
+
+    token.cancel();
+    assert!(clone.is_cancelled());
+    assert!(matches!(clone.check(), Err(ConvertError::Cancelled)));
+}
+
+#[test]
+fn test_uncancelled_conversion_completes() {
+    let dir = TempDir::new().unwrap();
+    let path = create_kernels(&dir, 100);
+    assert!(convert(&path, &CancellationToken::new()).unwrap() >= 100);
+}
+
+#[test]
+fn test_cancelled_conversion_fails_with_cancelled() {
+    let dir = TempDir::new().unwrap();
+    let path = create_kernels(&dir, 20_000);
+    let token = CancellationToken::new();
+    token.cancel();
+    assert!(matches!(
+        convert(&path, &token),
+        Err(ConvertError::Cancelled)
+    ));
+}
+
+#[test]
+fn test_interrupted_queries_map_to_cancelled() {
+    let interrupted = rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_INTERRUPT),
+        None,
+    );
+    assert!(matches!(
+        ConvertError::from(interrupted),
+        ConvertError::Cancelled
+    ));
+    let busy =
+        rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None);
+    assert!(matches!(ConvertError::from(busy), ConvertError::Sqlite(_)));
+}
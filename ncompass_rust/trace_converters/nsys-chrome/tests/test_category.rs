@@ -0,0 +1,142 @@
+//! Unit tests for event categories
+
+use nsys_chrome::analysis::{
+    CONCURRENCY_CATEGORY, SKEW_CATEGORY, THROTTLE_CATEGORY, THROUGHPUT_CATEGORY,
+};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::dropped::DROPPED_CATEGORY;
+use nsys_chrome::linker::coverage::UNATTRIBUTED_WORK_CATEGORY;
+use nsys_chrome::linker::mpi_linker::MPI_CATEGORY;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions, OutputRoute};
+use nsys_chrome::parsers::gpu_metrics::GPU_METRICS_CATEGORY;
+use nsys_chrome::parsers::memcpy::MEMCPY_CATEGORY;
+use nsys_chrome::parsers::thread_state::THREAD_STATE_CATEGORY;
+use nsys_chrome::presets::overlay_options;
+use nsys_chrome::routing::COUNTERS_CATEGORY;
+
+// ==========================
+// Tests for names
+// ==========================
+
+#[test]
+fn test_category_names_round_trip() {
+    for category in EventCategory::BUILT_IN {
+        assert_eq!(&EventCategory::from(category.as_str()), category);
+        assert_eq!(&EventCategory::from(category.activity_type()), category);
+        assert_eq!(
+            &category.as_str().parse::<EventCategory>().unwrap(),
+            category
+        );
+        assert!(category.is_built_in());
+    }
+    assert_eq!(EventCategory::CudaApi.as_str(), "cuda_api");
+    assert_eq!(EventCategory::CudaApi.activity_type(), "cuda-api");
+    assert_eq!(EventCategory::NvtxKernel.to_string(), "nvtx-kernel");
+
+    // Categories named by constants elsewhere are the same strings
+    assert_eq!(EventCategory::Mpi.as_str(), MPI_CATEGORY);
+    assert_eq!(EventCategory::Memcpy.as_str(), MEMCPY_CATEGORY);
+    assert_eq!(EventCategory::ThreadState.as_str(), THREAD_STATE_CATEGORY);
+    assert_eq!(EventCategory::GpuMetrics.as_str(), GPU_METRICS_CATEGORY);
+}
+
+#[test]
+fn test_parse_rejects_typos_and_keeps_other_categories() {
+    let error = EventCategory::parse("kernal").unwrap_err().to_string();
+    assert!(error.contains("did you mean 'kernel'"), "{}", error);
+    let error = EventCategory::parse("nvtx_kernel").unwrap_err().to_string();
+    assert!(error.contains("did you mean 'nvtx-kernel'"), "{}", error);
+    let error = EventCategory::parse("cuda-apis").unwrap_err().to_string();
+    assert!(error.contains("did you mean 'cuda-api'"), "{}", error);
+
+    // Derived categories and custom activity types are not typos
+    for name in [
+        CONCURRENCY_CATEGORY,
+        SKEW_CATEGORY,
+        THROTTLE_CATEGORY,
+        THROUGHPUT_CATEGORY,
+        DROPPED_CATEGORY,
+        UNATTRIBUTED_WORK_CATEGORY,
+        COUNTERS_CATEGORY,
+        "gap",
+        "nvtx-mark",
+        "cuda_flow",
+        "vendor-events",
+    ] {
+        assert_eq!(
+            EventCategory::parse(name).unwrap(),
+            EventCategory::Other(name.to_string())
+        );
+    }
+}
+
+// ==========================
+// Tests for events and serde
+// ==========================
+
+#[test]
+fn test_event_category_matches_interned_cat() {
+    let event = ChromeTraceEvent::complete(
+        "gemm".to_string(),
+        0.0,
+        1.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        EventCategory::Kernel,
+    );
+    assert_eq!(&*event.cat, "kernel");
+    assert!(event.cat == EventCategory::Kernel);
+    assert!(event.cat != EventCategory::Nvtx);
+    assert_eq!(event.category(), EventCategory::Kernel);
+
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["cat"], "kernel");
+
+    let categories: Vec<EventCategory> =
+        serde_json::from_str(r#"["cuda_api", "cuda-api", "gap"]"#).unwrap();
+    assert_eq!(
+        categories,
+        vec![
+            EventCategory::CudaApi,
+            EventCategory::CudaApi,
+            EventCategory::Other("gap".to_string()),
+        ]
+    );
+    assert_eq!(
+        serde_json::to_string(&categories).unwrap(),
+        r#"["cuda_api","cuda_api","gap"]"#
+    );
+}
+
+// ==========================
+// Tests for options and routes
+// ==========================
+
+#[test]
+fn test_options_and_routes_reject_typos() {
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"activity_types": ["cuda-api", "my-table"]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        options.activity_types,
+        vec![
+            EventCategory::CudaApi,
+            EventCategory::Other("my-table".to_string())
+        ]
+    );
+    assert!(overlay_options(
+        ConversionOptions::default(),
+        r#"{"activity_types": ["kernels"]}"#
+    )
+    .is_err());
+
+    assert!(OutputRoute::parse("kernel,counters=stats.csv").is_ok());
+    assert!(OutputRoute::parse("*=all.json").is_ok());
+    assert!(OutputRoute::parse("throttle=throttle.json").is_ok());
+    let error = OutputRoute::parse("kernal=stats.csv")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("did you mean 'kernel'"), "{}", error);
+}
@@ -0,0 +1,145 @@
+//! Unit tests for the nsys-chrome command line
+
+mod common;
+
+use common::create_sqlite;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+const KERNEL_SQL: &str = "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'gemm_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL
+    VALUES (1000, 1500, 0, 7, 1, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0);";
+
+/// Run the CLI with `args`, with the file at `stdin` as its stdin and
+/// `tmpdir` as its temporary directory, if given
+fn run(args: &[&str], stdin: Option<&str>, tmpdir: Option<&str>) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_nsys-chrome"));
+    command
+        .args(args)
+        .stdin(match stdin {
+            Some(path) => Stdio::from(File::open(path).unwrap()),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(tmpdir) = tmpdir {
+        command.env("TMPDIR", tmpdir);
+    }
+    command.output().unwrap()
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "nsys-chrome failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn kernel_names(trace: &[u8]) -> Vec<String> {
+    let parsed: serde_json::Value = serde_json::from_slice(trace).unwrap();
+    parsed["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|e| e["cat"] == "kernel")
+        .map(|e| e["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+// ==========================
+// Tests for stdin and stdout
+// ==========================
+
+#[test]
+fn test_stdin_sqlite_input_to_stdout() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "trace.sqlite", KERNEL_SQL);
+
+    let output = run(&["-", "-o", "-"], Some(&path), None);
+    assert_success(&output);
+    // Stdout defaults to plain JSON
+    assert_eq!(kernel_names(&output.stdout), vec!["gemm_kernel"]);
+}
+
+#[test]
+fn test_stdout_output_with_gzip_compression() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "trace.sqlite", KERNEL_SQL);
+
+    let output = run(&[&path, "-o", "-", "--compression", "gzip"], None, None);
+    assert_success(&output);
+    let mut json = Vec::new();
+    GzDecoder::new(output.stdout.as_slice())
+        .read_to_end(&mut json)
+        .unwrap();
+    assert_eq!(kernel_names(&json), vec!["gemm_kernel"]);
+}
+
+#[test]
+fn test_stdin_spool_directory_only_for_stdin() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "trace.sqlite", KERNEL_SQL);
+    let missing = dir.path().join("missing");
+    let missing = missing.to_str().unwrap();
+
+    // A file input needs no temporary directory
+    let output = run(&[&path, "-o", "-"], None, Some(missing));
+    assert_success(&output);
+    assert_eq!(kernel_names(&output.stdout), vec!["gemm_kernel"]);
+
+    // Stdin is spooled into one
+    let output = run(&["-", "-o", "-"], Some(&path), Some(missing));
+    assert!(!output.status.success());
+}
+
+// ==========================
+// Tests for --quiet
+// ==========================
+
+#[test]
+fn test_quiet_suppresses_progress() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "trace.sqlite", KERNEL_SQL);
+    let loud = dir.path().join("loud.json.gz");
+    let quiet = dir.path().join("quiet.json.gz");
+
+    let output = run(&[&path, "-o", loud.to_str().unwrap()], None, None);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Conversion complete"));
+
+    let output = run(
+        &[&path, "-o", quiet.to_str().unwrap(), "--quiet"],
+        None,
+        None,
+    );
+    assert_success(&output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !stderr.contains("Converting"),
+        "progress printed: {}",
+        stderr
+    );
+    assert!(
+        !stderr.contains("Conversion complete"),
+        "progress printed: {}",
+        stderr
+    );
+    assert!(quiet.exists());
+}
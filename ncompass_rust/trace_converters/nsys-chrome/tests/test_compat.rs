@@ -0,0 +1,147 @@
+//! Unit tests for viewer compatibility targets
+
+use nsys_chrome::compat::{
+    apply_compat, parse_compat_target, CompatStats, CompatTarget, TRACE_SCHEMA_EVENT,
+    TRACE_SCHEMA_VERSION,
+};
+use nsys_chrome::models::{BindingPoint, ChromeTraceEvent, ChromeTracePhase, StringOrInt};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_slice(name: &str, ts: f64, tid: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        1.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        "cuda".to_string(),
+    )
+}
+
+/// A launch bound to its kernel, an outlier colored "terrible", a slice with
+/// a custom NVTX color and a nestable async start
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    let mut launch = create_slice("cudaLaunchKernel", 1.0, "Thread 1");
+    launch.bind_id = Some(StringOrInt::Int(7));
+    launch.flow_out = Some(true);
+    let mut kernel = create_slice("gemm", 2.0, "Stream 7");
+    kernel.bind_id = Some(StringOrInt::Int(7));
+    kernel.flow_in = Some(true);
+    let mut outlier = create_slice("gemm", 3.0, "Stream 7");
+    outlier.cname = Some("terrible".to_string());
+    let mut range = create_slice("step", 4.0, "NVTX");
+    range.cname = Some("#ff0000".to_string());
+    let mut nestable = create_slice("request", 5.0, "Thread 1");
+    nestable.ph = ChromeTracePhase::AsyncNestableStart;
+    nestable.dur = None;
+    nestable.id = Some(StringOrInt::Int(1));
+    vec![launch, kernel, outlier, range, nestable]
+}
+
+fn flow_events(events: &[ChromeTraceEvent]) -> Vec<&ChromeTraceEvent> {
+    events
+        .iter()
+        .filter(|e| {
+            matches!(
+                e.ph,
+                ChromeTracePhase::FlowStart | ChromeTracePhase::FlowFinish
+            )
+        })
+        .collect()
+}
+
+// ==========================
+// Tests for parsing
+// ==========================
+
+#[test]
+fn test_parse_compat_target() {
+    assert_eq!(
+        parse_compat_target("perfetto").unwrap(),
+        CompatTarget::Perfetto
+    );
+    assert_eq!(parse_compat_target("chrome").unwrap(), CompatTarget::Chrome);
+    assert_eq!(
+        parse_compat_target("legacy-catapult").unwrap(),
+        CompatTarget::LegacyCatapult
+    );
+    assert!(parse_compat_target("catapult").is_err());
+}
+
+// ==========================
+// Tests for targets
+// ==========================
+
+#[test]
+fn test_perfetto_keeps_events_and_records_schema() {
+    let events = sample_events();
+    let (compat, stats) = apply_compat(events.clone(), CompatTarget::Perfetto);
+
+    assert_eq!(stats, CompatStats::default());
+    assert_eq!(compat.len(), events.len() + 1);
+    assert_eq!(compat[0].name, TRACE_SCHEMA_EVENT);
+    assert_eq!(compat[0].ph, ChromeTracePhase::Metadata);
+    assert_eq!(compat[0].args["version"], TRACE_SCHEMA_VERSION);
+    assert_eq!(compat[0].args["compat"], "perfetto");
+    assert_eq!(
+        serde_json::to_value(&compat[1..]).unwrap(),
+        serde_json::to_value(&events).unwrap()
+    );
+}
+
+#[test]
+fn test_chrome_unbinds_flows_and_drops_custom_colors() {
+    let (events, stats) = apply_compat(sample_events(), CompatTarget::Chrome);
+
+    assert_eq!(
+        stats,
+        CompatStats {
+            flows_rewritten: 2,
+            colors_dropped: 1,
+            events_dropped: 0,
+        }
+    );
+    assert!(events
+        .iter()
+        .all(|e| e.bind_id.is_none() && e.flow_in.is_none() && e.flow_out.is_none()));
+
+    // Each flow event follows the slice it came from, at the slice's start
+    let flows = flow_events(&events);
+    assert_eq!(flows.len(), 2);
+    assert_eq!(events[2].ph, ChromeTracePhase::FlowStart);
+    assert_eq!(events[2].ts, 1.0);
+    assert_eq!(&*events[2].tid, "Thread 1");
+    assert_eq!(events[4].ph, ChromeTracePhase::FlowFinish);
+    assert_eq!(events[4].ts, 2.0);
+    assert_eq!(events[4].bp, Some(BindingPoint::Enclosing));
+    assert!(flows.iter().all(|e| e.id == Some(StringOrInt::Int(7))));
+
+    let colors: Vec<_> = events.iter().filter_map(|e| e.cname.as_deref()).collect();
+    assert_eq!(colors, vec!["terrible"]);
+    assert!(events
+        .iter()
+        .any(|e| e.ph == ChromeTracePhase::AsyncNestableStart));
+}
+
+#[test]
+fn test_legacy_catapult_drops_colors_binding_points_and_nestable_async() {
+    let (events, stats) = apply_compat(sample_events(), CompatTarget::LegacyCatapult);
+
+    assert_eq!(
+        stats,
+        CompatStats {
+            flows_rewritten: 2,
+            colors_dropped: 2,
+            events_dropped: 1,
+        }
+    );
+    assert_eq!(events[0].args["compat"], "legacy-catapult");
+    assert_eq!(flow_events(&events).len(), 2);
+    let json = serde_json::to_string(&events).unwrap();
+    assert!(!json.contains("\"cname\""));
+    assert!(!json.contains("\"bp\""));
+    assert!(!json.contains("\"ph\":\"b\""));
+}
@@ -0,0 +1,152 @@
+//! Unit tests for the per-device kernel concurrency counter
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::analysis::{
+    concurrency_counter_events, concurrency_summary_events, kernel_concurrency,
+    CONCURRENCY_COUNTER, KERNEL_CONCURRENCY_EVENT,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_kernel(start_ns: i64, end_ns: i64, device_id: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "kernel".to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        format!("Device {}", device_id),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("deviceId", device_id)
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+}
+
+// ==========================
+// Tests for kernel_concurrency
+// ==========================
+
+#[test]
+fn test_concurrency_time_at_each_level() {
+    let kernels = vec![
+        create_kernel(0, 1000, 0),
+        create_kernel(500, 2000, 0),
+        create_kernel(600, 800, 0),
+        create_kernel(3000, 4000, 0),
+        create_kernel(0, 100, 1),
+    ];
+    let concurrency = kernel_concurrency(&kernels);
+
+    assert_eq!(concurrency.len(), 2);
+    let device = &concurrency[0];
+    assert_eq!(device.device_id, 0);
+    assert_eq!(
+        device.steps,
+        vec![
+            (0, 1),
+            (500, 2),
+            (600, 3),
+            (800, 2),
+            (1000, 1),
+            (2000, 0),
+            (3000, 1),
+            (4000, 0)
+        ]
+    );
+    assert_eq!(device.idle_ns, 1000);
+    assert_eq!(device.serial_ns, 2500);
+    assert_eq!(device.concurrent_ns, 500);
+    assert_eq!(device.max_concurrency, 3);
+    assert_eq!(device.concurrent_pct(), 16.7);
+
+    assert_eq!(concurrency[1].serial_ns, 100);
+    assert_eq!(concurrency[1].concurrent_pct(), 0.0);
+}
+
+#[test]
+fn test_back_to_back_kernels_are_not_concurrent() {
+    let kernels = vec![create_kernel(0, 1000, 0), create_kernel(1000, 2000, 0)];
+    let concurrency = kernel_concurrency(&kernels);
+
+    assert_eq!(concurrency[0].steps, vec![(0, 1), (2000, 0)]);
+    assert_eq!(concurrency[0].serial_ns, 2000);
+    assert_eq!(concurrency[0].concurrent_ns, 0);
+    assert_eq!(concurrency[0].max_concurrency, 1);
+}
+
+#[test]
+fn test_concurrency_events() {
+    let concurrency = kernel_concurrency(&[create_kernel(0, 1000, 0), create_kernel(0, 500, 0)]);
+
+    let counters = concurrency_counter_events(&concurrency);
+    assert_eq!(counters.len(), 3);
+    assert!(counters
+        .iter()
+        .all(|e| e.ph == ChromeTracePhase::Counter && e.name == CONCURRENCY_COUNTER));
+    assert_eq!(counters[0].pid.as_ref(), "Device 0");
+    let values: Vec<i64> = counters
+        .iter()
+        .map(|e| e.args["kernels"].as_i64().unwrap())
+        .collect();
+    assert_eq!(values, vec![2, 1, 0]);
+
+    let summary = concurrency_summary_events(&concurrency);
+    assert_eq!(summary.len(), 1);
+    assert_eq!(summary[0].ph, ChromeTracePhase::Metadata);
+    assert_eq!(summary[0].args["concurrent_ns"], 500);
+    assert_eq!(summary[0].args["serial_ns"], 500);
+    assert_eq!(summary[0].args["concurrent_pct"], 50.0);
+    assert_eq!(summary[0].args["max_concurrency"], 2);
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_emits_concurrency_when_enabled() {
+    let sql = "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+        INSERT INTO StringIds VALUES (1, 'gemm');
+        CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+            start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+            correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+            gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+            blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+            registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+            (1000, 3000, 0, 7, 1, 16777216, 1, 1, 1, 1, 32, 1, 1, 32, 0, 0),
+            (2000, 4000, 0, 8, 2, 16777216, 1, 1, 1, 1, 32, 1, 1, 32, 0, 0);";
+
+    let convert = |kernel_concurrency: bool| -> Vec<ChromeTraceEvent> {
+        let options = ConversionOptions {
+            kernel_concurrency,
+            ..Default::default()
+        };
+        convert_sql(sql, options)
+    };
+
+    let events = convert(true);
+    let summary = events
+        .iter()
+        .find(|e| e.name == KERNEL_CONCURRENCY_EVENT)
+        .unwrap();
+    assert_eq!(summary.args["concurrent_ns"], 1000);
+    assert_eq!(summary.args["serial_ns"], 2000);
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| e.name == CONCURRENCY_COUNTER)
+            .count(),
+        4
+    );
+
+    assert!(convert(false)
+        .iter()
+        .all(|e| e.name != CONCURRENCY_COUNTER && e.name != KERNEL_CONCURRENCY_EVENT));
+}
@@ -0,0 +1,147 @@
+//! Unit tests for the sorted correlation index
+
+use nsys_chrome::linker::{
+    build_correlation_map, build_correlation_map_for_calls, CorrelationIndex, KernelTimeline,
+    NsysEventAdapter,
+};
+use nsys_chrome::models::ChromeTraceEvent;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn event(name: &str, start_ns: i64, end_ns: i64, correlation_id: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        "Thread 1".to_string(),
+        "test".to_string(),
+    )
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+    .with_arg("correlationId", correlation_id)
+}
+
+/// One launch per 1000 ns, each running its kernel 500 ns later; launch 3
+/// is a graph launching two kernels
+fn launches() -> (Vec<ChromeTraceEvent>, Vec<ChromeTraceEvent>) {
+    let mut calls = Vec::new();
+    let mut kernels = Vec::new();
+    for id in 1..=5 {
+        let start = id * 1000;
+        calls.push(event("launch", start, start + 100, id));
+        kernels.push(event(
+            &format!("kernel {}", id),
+            start + 500,
+            start + 600,
+            id,
+        ));
+    }
+    kernels.push(event("kernel 3b", 3700, 3800, 3));
+    (calls, kernels)
+}
+
+fn names(kernels: &[&ChromeTraceEvent]) -> Vec<String> {
+    kernels.iter().map(|k| k.name.clone()).collect()
+}
+
+// ==========================
+// Tests for CorrelationIndex
+// ==========================
+
+#[test]
+fn test_lookups_match_the_full_map() {
+    let (calls, kernels) = launches();
+    let calls: Vec<&ChromeTraceEvent> = calls.iter().collect();
+    let kernels: Vec<&ChromeTraceEvent> = kernels.iter().collect();
+    let adapter = NsysEventAdapter;
+
+    let full = build_correlation_map(&kernels, &adapter);
+    let index = CorrelationIndex::new(&kernels, &adapter);
+    assert_eq!(index.len(), 6);
+    for id in 1..=5 {
+        let found: Vec<&ChromeTraceEvent> = index.get(id).collect();
+        assert_eq!(names(&found), names(&full[&id]));
+    }
+    assert_eq!(index.get(9).count(), 0);
+    assert_eq!(
+        names(&index.kernels_for(&calls[2..4], &adapter)),
+        vec!["kernel 3", "kernel 3b", "kernel 4"]
+    );
+}
+
+#[test]
+fn test_for_calls_keeps_only_their_kernels() {
+    let (calls, kernels) = launches();
+    let calls: Vec<&ChromeTraceEvent> = calls.iter().collect();
+    let kernels: Vec<&ChromeTraceEvent> = kernels.iter().collect();
+    let adapter = NsysEventAdapter;
+
+    let index = CorrelationIndex::for_calls(&kernels, &calls[1..3], &adapter, None);
+    assert_eq!(index.len(), 3);
+    let map = build_correlation_map_for_calls(&kernels, &calls[1..3], &adapter, None);
+    let mut ids: Vec<i64> = map.keys().copied().collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![2, 3]);
+    assert_eq!(names(&map[&3]), vec!["kernel 3", "kernel 3b"]);
+}
+
+#[test]
+fn test_time_span_bounds_the_kernels() {
+    let (calls, mut kernels) = launches();
+    // A stale kernel reusing correlation ID 4 long before its call, and one long after
+    kernels.push(event("stale", 200, 300, 4));
+    kernels.push(event("late", 90_000, 90_100, 4));
+    let calls: Vec<&ChromeTraceEvent> = calls.iter().collect();
+    let kernels: Vec<&ChromeTraceEvent> = kernels.iter().collect();
+    let adapter = NsysEventAdapter;
+
+    let unbounded = CorrelationIndex::for_calls(&kernels, &calls[3..4], &adapter, None);
+    let found: Vec<&ChromeTraceEvent> = unbounded.get(4).collect();
+    assert_eq!(names(&found), vec!["kernel 4", "late"]);
+
+    let bounded = CorrelationIndex::for_calls(&kernels, &calls[3..4], &adapter, Some(10_000));
+    let found: Vec<&ChromeTraceEvent> = bounded.get(4).collect();
+    assert_eq!(names(&found), vec!["kernel 4"]);
+}
+
+#[test]
+fn test_calls_without_correlation_ids_exit_early() {
+    let (_, kernels) = launches();
+    let kernels: Vec<&ChromeTraceEvent> = kernels.iter().collect();
+    let mut call = event("launch", 1000, 1100, 1);
+    call.args.remove("correlationId");
+
+    let index = CorrelationIndex::for_calls(&kernels, &[&call], &NsysEventAdapter, None);
+    assert!(index.is_empty());
+    assert!(CorrelationIndex::for_calls(&kernels, &[], &NsysEventAdapter, None).is_empty());
+}
+
+#[test]
+fn test_timeline_indexes_one_window_at_a_time() {
+    let (calls, mut kernels) = launches();
+    // Kernel 4 re-uses a lower ID late in the capture, past every other kernel
+    kernels.push(event("replayed", 50_000, 50_100, 2));
+    let calls: Vec<&ChromeTraceEvent> = calls.iter().collect();
+    let kernels: Vec<&ChromeTraceEvent> = kernels.iter().collect();
+    let adapter = NsysEventAdapter;
+    let timeline = KernelTimeline::new(&kernels, &adapter);
+
+    // Same lookups as indexing from scratch, window by window
+    for window in [&calls[0..2], &calls[2..3], &calls[3..5]] {
+        let from_timeline = timeline.index_for(window, &adapter, None);
+        let from_scratch = CorrelationIndex::for_calls(&kernels, window, &adapter, None);
+        assert_eq!(
+            names(&from_timeline.kernels_for(window, &adapter)),
+            names(&from_scratch.kernels_for(window, &adapter))
+        );
+    }
+    let first = timeline.index_for(&calls[0..2], &adapter, None);
+    assert_eq!(
+        names(&first.kernels_for(&calls[0..2], &adapter)),
+        vec!["kernel 1", "kernel 2", "replayed"]
+    );
+    assert!(timeline.index_for(&[], &adapter, None).is_empty());
+}
@@ -0,0 +1,189 @@
+//! Unit tests for cost_model module
+
+mod common;
+
+use common::{convert_sql, create_sqlite};
+use nsys_chrome::cost_model::{
+    attach_cost_estimate, element_size_from_name, DefaultCostModel, KernelCost, KernelCostModel,
+    KernelLaunch,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::NsysChromeConverter;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Create a kernel event with launch configuration args
+fn create_kernel(name: &str, duration_ns: i64, grid: [i64; 3]) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        0.0,
+        duration_ns as f64 / 1000.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(0))
+    .with_arg("end_ns", serde_json::json!(duration_ns))
+    .with_arg("grid", serde_json::json!(grid))
+    .with_arg("block", serde_json::json!([128, 1, 1]))
+}
+
+/// Model that charges a fixed cost per thread block
+struct PerBlockModel;
+
+impl KernelCostModel for PerBlockModel {
+    fn name(&self) -> &str {
+        "per-block"
+    }
+
+    fn estimate(&self, launch: &KernelLaunch) -> Option<KernelCost> {
+        Some(KernelCost {
+            flops: Some(launch.num_blocks() as f64 * 1000.0),
+            bytes: None,
+            exact: false,
+        })
+    }
+}
+
+// ==========================
+// Tests for attach_cost_estimate
+// ==========================
+
+#[test]
+fn test_attach_cost_estimate_exact_gemm() {
+    let mut event = create_kernel(
+        "ampere_h16816gemm_128x128_ldg8_stages_64x3_nn",
+        1_000_000,
+        [8, 8, 1],
+    )
+    .with_arg("m", serde_json::json!(1024))
+    .with_arg("n", serde_json::json!(1024))
+    .with_arg("k", serde_json::json!(1024));
+
+    assert!(attach_cost_estimate(&mut event, &DefaultCostModel));
+
+    let flops = 2.0 * 1024.0 * 1024.0 * 1024.0;
+    assert_eq!(event.args["est_flops"], flops);
+    assert_eq!(event.args["est_bytes"], 3.0 * 1024.0 * 1024.0 * 2.0);
+    // 2.147 GFLOP in 1 ms = 2.147 TFLOP/s
+    assert_eq!(event.args["achieved_tflops"], 2.147);
+    assert_eq!(event.args["cost_model"], "default");
+    assert_eq!(event.args["cost_exact"], true);
+}
+
+#[test]
+fn test_attach_cost_estimate_tile_heuristic() {
+    let mut event = create_kernel("ampere_sgemm_128x64_nn", 1000, [4, 4, 1]);
+
+    assert!(attach_cost_estimate(&mut event, &DefaultCostModel));
+
+    // Output coverage: 16 blocks of 128x64 -> M=512, N=256
+    let (m, n) = (512.0_f64, 256.0_f64);
+    let k = (m * n).sqrt();
+    assert_eq!(event.args["est_flops"], 2.0 * m * n * k);
+    assert_eq!(event.args["cost_exact"], false);
+}
+
+#[test]
+fn test_attach_cost_estimate_attention_needs_shapes() {
+    let mut event = create_kernel("flash_fwd_kernel", 1000, [16, 8, 4]);
+    assert!(!attach_cost_estimate(&mut event, &DefaultCostModel));
+    assert!(!event.args.contains_key("est_flops"));
+
+    let mut event = create_kernel("flash_fwd_kernel", 1000, [16, 8, 4])
+        .with_arg("batch", serde_json::json!(2))
+        .with_arg("heads", serde_json::json!(8))
+        .with_arg("seq_len", serde_json::json!(512))
+        .with_arg("head_dim", serde_json::json!(64));
+    assert!(attach_cost_estimate(&mut event, &DefaultCostModel));
+    assert_eq!(
+        event.args["est_flops"],
+        4.0 * 2.0 * 8.0 * 512.0 * 512.0 * 64.0
+    );
+}
+
+#[test]
+fn test_attach_cost_estimate_unrecognized_kernel() {
+    let mut event = create_kernel("vectorized_elementwise_kernel", 1000, [64, 1, 1]);
+    assert!(!attach_cost_estimate(&mut event, &DefaultCostModel));
+    assert!(!event.args.contains_key("cost_model"));
+}
+
+#[test]
+fn test_attach_cost_estimate_custom_model() {
+    let mut event = create_kernel("anything", 2000, [10, 2, 1]);
+    assert!(attach_cost_estimate(&mut event, &PerBlockModel));
+
+    assert_eq!(event.args["est_flops"], 20000.0);
+    assert_eq!(event.args["cost_model"], "per-block");
+    assert!(!event.args.contains_key("est_bytes"));
+}
+
+#[test]
+fn test_element_size_from_name() {
+    assert_eq!(element_size_from_name("ampere_sgemm_128x64_nn"), 4.0);
+    assert_eq!(
+        element_size_from_name("sm80_xmma_gemm_bf16bf16_bf16f32"),
+        2.0
+    );
+    assert_eq!(element_size_from_name("sm90_gemm_e4m3"), 1.0);
+    assert_eq!(element_size_from_name("my_gemm"), 2.0);
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+/// A single GEMM kernel
+const GEMM_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'ampere_sgemm_128x64_nn');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (1000, 2000, 0, 7, 1, 16777216, 1, 4, 4, 1, 256, 1, 1, 32, 0, 0);
+";
+
+#[test]
+fn test_converter_estimates_disabled_by_default() {
+    let events = convert_sql(GEMM_SQL, ConversionOptions::default());
+
+    let kernel = events.iter().find(|e| e.cat == "kernel").unwrap();
+    assert!(!kernel.args.contains_key("est_flops"));
+}
+
+#[test]
+fn test_converter_default_cost_model() {
+    let options = ConversionOptions {
+        estimate_kernel_costs: true,
+        ..Default::default()
+    };
+    let events = convert_sql(GEMM_SQL, options);
+
+    let kernel = events.iter().find(|e| e.cat == "kernel").unwrap();
+    assert!(kernel.args.contains_key("est_flops"));
+    assert!(kernel.args.contains_key("achieved_tflops"));
+}
+
+#[test]
+fn test_converter_custom_cost_model() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "gemm.sqlite", GEMM_SQL);
+    let converter = NsysChromeConverter::new(&path, None)
+        .unwrap()
+        .with_cost_model(Arc::new(PerBlockModel));
+    let events = converter.convert().unwrap();
+
+    let kernel = events.iter().find(|e| e.cat == "kernel").unwrap();
+    assert_eq!(kernel.args["cost_model"], "per-block");
+    assert_eq!(kernel.args["est_flops"], 16000.0);
+}
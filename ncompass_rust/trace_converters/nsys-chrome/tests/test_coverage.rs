@@ -0,0 +1,215 @@
+//! Unit tests for NVTX linking coverage
+
+mod common;
+
+use common::{convert_sql_with_diagnostics, create_event};
+use nsys_chrome::linker::{
+    link_nvtx_to_kernels_with_stats, tag_unattributed, unattributed_gpu_work,
+    unattributed_work_events, NvtxCoverage, UnattributedWork, UNATTRIBUTED_ARG,
+    UNATTRIBUTED_WORK_EVENT, UNATTRIBUTED_WORK_TRACK,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Two kernels launched inside "forward", a third launched outside any range,
+/// and a "backward" range on a thread that launches nothing
+const COVERAGE_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'cudaLaunchKernel'), (2, 'gemm_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (3000, 4000, 0, 7, 1, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (4000, 5000, 0, 7, 2, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (8000, 10000, 0, 7, 3, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+        start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES
+        (1100, 1200, 16777217, 1, 1),
+        (1300, 1400, 16777217, 2, 1),
+        (7000, 7100, 16777217, 3, 1);
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES
+        (1000, 2000, 'forward', NULL, 16777217, 59),
+        (1000, 2000, 'backward', NULL, 16777218, 59);
+";
+
+// ==========================
+// Tests for NvtxCoverage
+// ==========================
+
+#[test]
+fn test_coverage_percentages() {
+    let kernels = vec![
+        create_event("gemm", "kernel", 0, 3000, None),
+        create_event("relu", "kernel", 3000, 4000, None),
+    ];
+    let coverage = NvtxCoverage::measure(4, 1, &kernels, 3000);
+
+    assert_eq!(coverage.covered_ranges, 3);
+    assert_eq!(coverage.uncovered_ranges(), 1);
+    assert_eq!(coverage.kernel_ns, 4000);
+    assert_eq!(coverage.range_pct(), 75.0);
+    assert_eq!(coverage.kernel_time_pct(), 75.0);
+    assert!(coverage.summary().contains("3/4 ranges"));
+
+    let empty = NvtxCoverage::measure(0, 0, &[], 0);
+    assert_eq!(empty.range_pct(), 0.0);
+    assert_eq!(empty.kernel_time_pct(), 0.0);
+}
+
+#[test]
+fn test_nested_ranges_count_kernel_time_once() {
+    let nvtx = vec![
+        create_event("step", "nvtx", 0, 10_000, None),
+        create_event("forward", "nvtx", 500, 5000, None),
+    ];
+    let api = vec![create_event("cudaLaunchKernel", "cuda_api", 1000, 2000, Some(1))];
+    let kernels = vec![create_event("gemm", "kernel", 3000, 4500, Some(1))];
+
+    let (_, stats) =
+        link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, &ConversionOptions::default());
+    assert_eq!(stats.attributed_kernel_ns, 1500);
+}
+
+#[test]
+fn test_tag_unattributed() {
+    let mut ranges = vec![create_event("backward", "nvtx", 0, 1000, None)];
+    tag_unattributed(&mut ranges);
+    assert_eq!(ranges[0].args[UNATTRIBUTED_ARG], true);
+}
+
+// ==========================
+// Tests for unattributed GPU work
+// ==========================
+
+#[test]
+fn test_unattributed_work_outside_ranges() {
+    let kernels = vec![
+        create_event("gemm", "kernel", 1000, 3000, None),
+        create_event("relu", "kernel", 2500, 4000, None),
+        create_event("gemm", "kernel", 6000, 7000, None),
+        create_event("gemm", "kernel", 9000, 9500, None).with_arg("deviceId", 1),
+    ];
+    let ranges = vec![
+        create_event("forward", "nvtx-kernel", 1500, 2000, None),
+        create_event("backward", "nvtx-kernel", 2500, 3500, None),
+        // Only nvtx-kernel ranges cover work, not the linker's flow events
+        create_event("forward", "cuda_flow", 6000, 7000, None),
+    ];
+
+    let work = unattributed_gpu_work(&kernels, &ranges);
+    let spans: Vec<(i64, i64, i64)> = work
+        .iter()
+        .map(|w| (w.device_id, w.start_ns, w.end_ns))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (0, 1000, 1500),
+            (0, 2000, 2500),
+            (0, 3500, 4000),
+            (0, 6000, 7000),
+            (1, 9000, 9500),
+        ]
+    );
+}
+
+#[test]
+fn test_fully_covered_work_is_attributed() {
+    let kernels = vec![create_event("gemm", "kernel", 1000, 2000, None)];
+    let ranges = vec![
+        create_event("step", "nvtx-kernel", 500, 1500, None),
+        create_event("forward", "nvtx-kernel", 1200, 2500, None),
+    ];
+    assert!(unattributed_gpu_work(&kernels, &ranges).is_empty());
+}
+
+#[test]
+fn test_unattributed_work_events() {
+    let events = unattributed_work_events(&[UnattributedWork {
+        device_id: 2,
+        start_ns: 3000,
+        end_ns: 4500,
+    }]);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, UNATTRIBUTED_WORK_EVENT);
+    assert_eq!(&*events[0].pid, "Device 2");
+    assert_eq!(&*events[0].tid, UNATTRIBUTED_WORK_TRACK);
+    assert_eq!(events[0].ts, 3.0);
+    assert_eq!(events[0].dur, Some(1.5));
+    assert_eq!(events[0].args["start_ns"], 3000);
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_reports_coverage_and_tags_ranges() {
+    let (events, diagnostics) = convert_sql_with_diagnostics(
+        COVERAGE_SQL,
+        ConversionOptions {
+            tag_unattributed: true,
+            ..Default::default()
+        },
+    );
+
+    let coverage = diagnostics.nvtx_coverage.unwrap();
+    assert_eq!((coverage.covered_ranges, coverage.ranges), (1, 2));
+    assert_eq!(coverage.kernel_ns, 4000);
+    assert_eq!(coverage.attributed_kernel_ns, 2000);
+    assert_eq!(diagnostics.uncovered_ranges(), 1);
+    assert!(diagnostics
+        .summary_lines()
+        .iter()
+        .any(|line| line.starts_with("1 NVTX ranges have no kernels")));
+
+    let backward = events
+        .iter()
+        .find(|e| e.name == "backward" && e.cat == "nvtx")
+        .unwrap();
+    assert_eq!(backward.args[UNATTRIBUTED_ARG], true);
+
+    // Untagged unless asked
+    let (events, _) = convert_sql_with_diagnostics(COVERAGE_SQL, ConversionOptions::default());
+    assert!(events
+        .iter()
+        .all(|e| !e.args.contains_key(UNATTRIBUTED_ARG)));
+}
+
+#[test]
+fn test_converter_emits_unattributed_work() {
+    let (events, _) = convert_sql_with_diagnostics(
+        COVERAGE_SQL,
+        ConversionOptions {
+            unattributed_work: true,
+            ..Default::default()
+        },
+    );
+
+    // The kernel launched outside "forward" is the only unattributed work
+    let work: Vec<&ChromeTraceEvent> = events
+        .iter()
+        .filter(|e| e.name == UNATTRIBUTED_WORK_EVENT)
+        .collect();
+    assert_eq!(work.len(), 1);
+    assert_eq!(work[0].args["start_ns"], 8000);
+    assert_eq!(work[0].args["end_ns"], 10000);
+
+    let (events, _) = convert_sql_with_diagnostics(COVERAGE_SQL, ConversionOptions::default());
+    assert!(events.iter().all(|e| e.name != UNATTRIBUTED_WORK_EVENT));
+}
@@ -0,0 +1,86 @@
+//! Unit tests for kernel symbol demangling and the demangle cache
+
+use nsys_chrome::demangle::{demangle, DemangleCache};
+
+// ==========================
+// Tests for demangle
+// ==========================
+
+#[test]
+fn test_demangle_kernel_symbols() {
+    let cases = [
+        ("_Z10add_kernelPfS_i", "add_kernel(float*, float*, int)"),
+        (
+            "_Z6matmulPfS_S_.kd",
+            "matmul(float*, float*, float*) [clone .kd]",
+        ),
+        (
+            "_Z17gemm_kernel_tiledILi32ELi32EEvPK6__halfS2_PS0_iii",
+            "void gemm_kernel_tiled<32, 32>(__half const*, __half const*, __half*, int, int, int)",
+        ),
+        (
+            "_ZN4vllm25paged_attention_v1_kernelIthLi128ELi16ELi128ELNS_18Fp8KVCacheDataTypeE0ELb0EEEvPT_PKS2_PKT0_S8_ifPKiSA_iPKfiiiSC_ii",
+            "void vllm::paged_attention_v1_kernel<unsigned short, unsigned char, 128, 16, 128, \
+             (vllm::Fp8KVCacheDataType)0, false>(unsigned short*, unsigned short const*, \
+             unsigned char const*, unsigned char const*, int, float, int const*, int const*, \
+             int, float const*, int, int, int, float const*, int, int)",
+        ),
+        ("_ZNKSt6vectorIiSaIiEE4sizeEv", "std::vector<int, std::allocator<int> >::size() const"),
+        ("_ZN12_GLOBAL__N_16kernelEv", "(anonymous namespace)::kernel()"),
+    ];
+    for (mangled, expected) in cases {
+        assert_eq!(demangle(mangled).as_deref(), Some(expected), "{}", mangled);
+    }
+}
+
+#[test]
+fn test_demangle_lambdas_in_local_names() {
+    let mangled = "_ZN2at6native29vectorized_elementwise_kernelILi4EZZZNS0_23direct_copy_kernel_cudaERNS_18TensorIteratorBaseEENKUlvE0_clEvENKUlvE5_clEvEUlfE_St5arrayIPcLm2EEEEviT0_T1_";
+    let functor = "at::native::direct_copy_kernel_cuda(at::TensorIteratorBase&)::{lambda()#2}\
+                   ::operator()() const::{lambda()#7}::operator()() const::{lambda(float)#1}";
+    assert_eq!(
+        demangle(mangled).unwrap(),
+        format!(
+            "void at::native::vectorized_elementwise_kernel<4, {f}, std::array<char*, 2ul> >\
+             (int, {f}, std::array<char*, 2ul>)",
+            f = functor
+        )
+    );
+}
+
+#[test]
+fn test_unmangled_or_unsupported_symbols_are_left_alone() {
+    assert_eq!(demangle("ampere_sgemm_128x64_nn"), None);
+    assert_eq!(demangle("_Z"), None);
+    // Expressions in template arguments are not covered
+    assert_eq!(demangle("_Z1fILi1EEvRAplT_Li1E_i"), None);
+}
+
+// ==========================
+// Tests for DemangleCache
+// ==========================
+
+#[test]
+fn test_cache_warms_unique_mangled_names_once() {
+    let cache = DemangleCache::new();
+    let names = [
+        "_Z10add_kernelPfS_i",
+        "_Z10add_kernelPfS_i",
+        "elementwise_kernel",
+        "_Z6matmulPfS_S_.kd",
+    ];
+    assert_eq!(cache.warm(names), 2);
+    assert_eq!(cache.warm(names), 0);
+    assert_eq!(cache.len(), 2);
+
+    assert_eq!(
+        &*cache.get("_Z10add_kernelPfS_i"),
+        "add_kernel(float*, float*, int)"
+    );
+    assert_eq!(&*cache.get("elementwise_kernel"), "elementwise_kernel");
+    assert_eq!(
+        &*cache.get("_Z1fILi1EEvRAplT_Li1E_i"),
+        "_Z1fILi1EEvRAplT_Li1E_i"
+    );
+    assert_eq!(cache.len(), 4);
+}
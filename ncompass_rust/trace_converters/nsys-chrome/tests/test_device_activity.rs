@@ -0,0 +1,119 @@
+//! Unit tests for the run-length encoded device activity summary
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::analysis::{
+    device_activity, device_activity_events, DeviceActivity, DEVICE_ACTIVITY_EVENT,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use serde_json::json;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_kernel(start_ns: i64, end_ns: i64, device_id: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "kernel".to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        format!("Device {}", device_id),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("deviceId", device_id)
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+}
+
+// ==========================
+// Tests for device_activity
+// ==========================
+
+#[test]
+fn test_device_activity_encodes_alternating_runs() {
+    let kernels = vec![
+        create_kernel(1000, 2000, 0),
+        create_kernel(1500, 3000, 0),
+        create_kernel(4000, 5000, 0),
+        create_kernel(9000, 10_000, 0),
+        create_kernel(0, 100, 1),
+    ];
+
+    let activity = device_activity(&kernels);
+    assert_eq!(
+        activity,
+        vec![
+            DeviceActivity {
+                device_id: 0,
+                start_ns: 1000,
+                end_ns: 10_000,
+                runs_ns: vec![2000, 1000, 1000, 4000, 1000],
+            },
+            DeviceActivity {
+                device_id: 1,
+                start_ns: 0,
+                end_ns: 100,
+                runs_ns: vec![100],
+            },
+        ]
+    );
+    assert_eq!(activity[0].active_ns(), 4000);
+    assert_eq!(activity[0].idle_ns(), 5000);
+    assert_eq!(activity[0].utilization_pct(), 44.4);
+    assert_eq!(activity[1].utilization_pct(), 100.0);
+
+    assert!(device_activity(&[]).is_empty());
+}
+
+#[test]
+fn test_device_activity_events_are_metadata() {
+    let activity = device_activity(&[create_kernel(0, 1000, 0), create_kernel(3000, 4000, 0)]);
+    let events = device_activity_events(&activity);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].ph, ChromeTracePhase::Metadata);
+    assert_eq!(events[0].name, DEVICE_ACTIVITY_EVENT);
+    assert_eq!(events[0].pid, "Device 0");
+    assert_eq!(events[0].args["runs_ns"], json!([1000, 2000, 1000]));
+    assert_eq!(events[0].args["active_ns"], 2000);
+    assert_eq!(events[0].args["idle_ns"], 2000);
+    assert_eq!(events[0].args["utilization_pct"], 50.0);
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_embeds_device_activity_with_metadata() {
+    let sql = "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+        INSERT INTO StringIds VALUES (1, 'gemm');
+        CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+            start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+            correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+            gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+            blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+            registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+            (1000, 2000, 0, 7, 1, 16777216, 1, 1, 1, 1, 32, 1, 1, 32, 0, 0),
+            (5000, 6000, 0, 7, 2, 16777216, 1, 1, 1, 1, 32, 1, 1, 32, 0, 0);";
+
+    let summaries = |include_metadata: bool| -> Vec<ChromeTraceEvent> {
+        let options = ConversionOptions {
+            include_metadata,
+            ..Default::default()
+        };
+        convert_sql(sql, options)
+            .into_iter()
+            .filter(|e| e.name == DEVICE_ACTIVITY_EVENT)
+            .collect()
+    };
+
+    let events = summaries(true);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].args["runs_ns"], json!([1000, 3000, 1000]));
+    assert!(summaries(false).is_empty());
+}
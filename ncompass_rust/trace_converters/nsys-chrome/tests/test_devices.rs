@@ -0,0 +1,136 @@
+//! Unit tests for GPU device properties
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::devices::{
+    device_properties_events, device_properties_from_events, extract_device_properties,
+    write_devices_json, DeviceProperties, DEVICE_PROPERTIES_EVENT,
+};
+use nsys_chrome::models::{ChromeTracePhase, ConversionOptions};
+use rusqlite::Connection;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Two GPUs, the first listed once per context
+const TARGET_INFO_SQL: &str = "
+    CREATE TABLE TARGET_INFO_GPU (
+        vmId INTEGER, id INTEGER, name TEXT, busLocation TEXT, totalMemory INTEGER,
+        memoryBandwidth INTEGER, clockRate INTEGER, smCount INTEGER, chipName TEXT,
+        computeMajor INTEGER, computeMinor INTEGER, threadsPerWarp INTEGER,
+        maxWarpsPerSm INTEGER, cuDevice INTEGER
+    );
+    INSERT INTO TARGET_INFO_GPU VALUES
+        (0, 3, 'NVIDIA H100 80GB HBM3', '0000:18:00.0', 85029158912,
+         3352320000000, 1980000000, 132, 'GH100', 9, 0, 32, 64, 0),
+        (0, 3, 'NVIDIA H100 80GB HBM3', '0000:18:00.0', 85029158912,
+         3352320000000, 1980000000, 132, 'GH100', 9, 0, 32, 64, 0),
+        (0, 4, 'NVIDIA H100 80GB HBM3', '0000:2a:00.0', 85029158912,
+         3352320000000, 1980000000, 132, 'GH100', 9, 0, 32, 64, 1);
+";
+
+fn open_db(sql: &str) -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(sql).unwrap();
+    conn
+}
+
+fn h100(id: i64) -> DeviceProperties {
+    DeviceProperties {
+        id,
+        name: Some("NVIDIA H100 80GB HBM3".to_string()),
+        sm_count: Some(132),
+        compute_capability: Some("9.0".to_string()),
+        ..Default::default()
+    }
+}
+
+// ==========================
+// Tests for extract_device_properties
+// ==========================
+
+#[test]
+fn test_extract_device_properties() {
+    let devices = extract_device_properties(&open_db(TARGET_INFO_SQL)).unwrap();
+    assert_eq!(devices.len(), 2);
+
+    // Devices are keyed by CUDA ordinal, not the nsys GPU id
+    let first = &devices[0];
+    assert_eq!(first.id, 0);
+    assert_eq!(first.name.as_deref(), Some("NVIDIA H100 80GB HBM3"));
+    assert_eq!(first.chip_name.as_deref(), Some("GH100"));
+    assert_eq!(first.bus_location.as_deref(), Some("0000:18:00.0"));
+    assert_eq!(first.sm_count, Some(132));
+    assert_eq!(first.clock_rate_hz, Some(1_980_000_000));
+    assert_eq!(first.total_memory_bytes, Some(85_029_158_912));
+    assert_eq!(first.compute_capability.as_deref(), Some("9.0"));
+    assert_eq!(first.max_warps_per_sm, Some(64));
+    assert_eq!(first.l2_cache_bytes, None);
+    assert_eq!(devices[1].id, 1);
+}
+
+#[test]
+fn test_extract_device_properties_older_layouts() {
+    let conn = open_db("CREATE TABLE StringIds (id INTEGER, value TEXT);");
+    assert!(extract_device_properties(&conn).unwrap().is_empty());
+
+    let conn = open_db(
+        "CREATE TABLE TARGET_INFOGPU (id INTEGER, name TEXT);
+         INSERT INTO TARGET_INFOGPU VALUES (2, 'Tesla V100-SXM2-16GB');",
+    );
+    let devices = extract_device_properties(&conn).unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].id, 2);
+    assert_eq!(devices[0].sm_count, None);
+    assert_eq!(devices[0].label().as_deref(), Some("Tesla V100-SXM2-16GB"));
+}
+
+// ==========================
+// Tests for trace metadata
+// ==========================
+
+#[test]
+fn test_device_properties_events_round_trip() {
+    let mut bare = h100(1);
+    bare.name = None;
+    let devices = vec![h100(0), bare];
+    let events = device_properties_events(&devices);
+
+    // The unnamed device gets properties but no label
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].name, DEVICE_PROPERTIES_EVENT);
+    assert_eq!(events[0].ph, ChromeTracePhase::Metadata);
+    assert_eq!(events[0].pid, "Device 0");
+    assert_eq!(events[0].args["sm_count"], 132);
+    assert!(!events[0].args.contains_key("uuid"));
+    assert_eq!(events[1].name, "process_labels");
+    assert_eq!(
+        events[1].args["labels"],
+        "NVIDIA H100 80GB HBM3 (132 SMs, sm_90)"
+    );
+
+    assert_eq!(device_properties_from_events(&events), devices);
+}
+
+#[test]
+fn test_converter_embeds_properties_and_writes_devices_json() {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel],
+        ..Default::default()
+    };
+    let events = convert_sql(TARGET_INFO_SQL, options);
+    let devices = device_properties_from_events(&events);
+    assert_eq!(devices.len(), 2);
+
+    let dir = TempDir::new().unwrap();
+    let json_path = dir.path().join("devices.json");
+    write_devices_json(json_path.to_str().unwrap(), &devices).unwrap();
+    let root: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+    assert_eq!(root["devices"][1]["id"], 1);
+    assert_eq!(root["devices"][1]["bus_location"], "0000:2a:00.0");
+}
@@ -0,0 +1,137 @@
+//! Unit tests for surfacing events the profiler dropped
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::dropped::{
+    extract_dropped_events, parse_dropped_count, DroppedEventStats, DROPPED_CATEGORY,
+    PROFILER_PROCESS,
+};
+use nsys_chrome::frontends::assemble_trace;
+use nsys_chrome::models::{ChromeTracePhase, ConversionOptions};
+use nsys_chrome::NsysChromeConverter;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Three dropped-data messages (one for pid 1, which ran on device 3) among
+/// unrelated diagnostics
+const DIAGNOSTIC_SQL: &str = "
+    CREATE TABLE DIAGNOSTIC_EVENT (
+        timestamp INTEGER, timestampType INTEGER, source INTEGER,
+        severity INTEGER, text TEXT, globalPid INTEGER
+    );
+    INSERT INTO DIAGNOSTIC_EVENT VALUES
+        (500, 1, 1, 1, 'Profiling started', NULL),
+        (2000, 1, 2, 2, 'CUPTI dropped 1024 activity records', 16777216),
+        (3000, 1, 2, 2, '3 CUPTI buffers were lost due to buffer overflow', NULL),
+        (4000, 1, 2, 2, 'Events were dropped by the kernel tracer', NULL);
+";
+
+fn open_db(sql: &str) -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(sql).unwrap();
+    conn
+}
+
+// ==========================
+// Tests for parse_dropped_count
+// ==========================
+
+#[test]
+fn test_parse_dropped_count() {
+    assert_eq!(
+        parse_dropped_count("CUPTI dropped 1024 activity records"),
+        Some((1024, "events"))
+    );
+    assert_eq!(
+        parse_dropped_count("3 CUPTI buffers were lost"),
+        Some((3, "buffers"))
+    );
+    assert_eq!(parse_dropped_count("Lost 7 events"), Some((7, "events")));
+    assert_eq!(parse_dropped_count("Events were dropped"), None);
+}
+
+// ==========================
+// Tests for extract_dropped_events
+// ==========================
+
+#[test]
+fn test_extract_dropped_events_builds_markers() {
+    let device_map = HashMap::from([(1, 3)]);
+    let markers = extract_dropped_events(&open_db(DIAGNOSTIC_SQL), &device_map).unwrap();
+    let names: Vec<&str> = markers.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "profiler dropped 1024 events",
+            "profiler dropped 3 buffers",
+            "profiler dropped events"
+        ]
+    );
+
+    let first = &markers[0];
+    assert_eq!(first.ph, ChromeTracePhase::Instant);
+    assert_eq!(first.cat, DROPPED_CATEGORY);
+    assert_eq!(first.ts, 2.0);
+    assert_eq!(first.pid, "Device 3");
+    assert_eq!(first.args["message"], "CUPTI dropped 1024 activity records");
+    assert_eq!(markers[1].pid, PROFILER_PROCESS);
+
+    assert_eq!(
+        DroppedEventStats::from_events(&markers),
+        DroppedEventStats {
+            reports: 3,
+            events: 1024,
+            buffers: 3,
+            uncounted_reports: 1,
+        }
+    );
+
+    let empty = open_db("CREATE TABLE StringIds (id INTEGER, value TEXT);");
+    assert!(extract_dropped_events(&empty, &device_map)
+        .unwrap()
+        .is_empty());
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_dropped_events_reported_in_diagnostics() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "dropped.sqlite", DIAGNOSTIC_SQL);
+
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let (events, diagnostics) = NsysChromeConverter::new(&path, Some(options.clone()))
+        .unwrap()
+        .convert_with_diagnostics()
+        .unwrap();
+    // Markers are kept even though only kernels were requested
+    assert_eq!(events.len(), 3);
+    assert_eq!(diagnostics.dropped_events.reports, 3);
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics.summary_lines().contains(
+        &"Profiler dropped data 3 times (1024 events, 3 buffers, 1 uncounted reports); \
+          gaps near the 'profiler dropped' markers may not be idle time"
+            .to_string()
+    ));
+
+    // Cached extractions keep them too
+    let (trace, diagnostics) = NsysChromeConverter::new(&path, Some(options.clone()))
+        .unwrap()
+        .extract()
+        .unwrap();
+    assert_eq!(diagnostics.dropped_events.events, 1024);
+    assert_eq!(assemble_trace(trace, &options).unwrap().len(), 3);
+}
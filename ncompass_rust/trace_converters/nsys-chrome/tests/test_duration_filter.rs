@@ -0,0 +1,249 @@
+//! Unit tests for the link-aware duration filter
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::analysis::{filter_short_kernels, parse_duration_ns, DurationFilterStats};
+use nsys_chrome::linker::link_nvtx_to_kernels;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(
+    name: &str,
+    start_ns: i64,
+    end_ns: i64,
+    tid: &str,
+    cat: &str,
+    correlation_id: Option<i64>,
+) -> ChromeTraceEvent {
+    let mut event = ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(start_ns))
+    .with_arg("end_ns", serde_json::json!(end_ns))
+    .with_arg("deviceId", serde_json::json!(0))
+    .with_arg("raw_tid", serde_json::json!(1));
+    if let Some(id) = correlation_id {
+        event = event.with_arg("correlationId", serde_json::json!(id));
+    }
+    event
+}
+
+/// Link NVTX ranges to kernels and return every resulting event
+fn linked_trace(
+    nvtx: Vec<ChromeTraceEvent>,
+    api: Vec<ChromeTraceEvent>,
+    kernels: Vec<ChromeTraceEvent>,
+) -> Vec<ChromeTraceEvent> {
+    let (nvtx_kernel, _, flows) =
+        link_nvtx_to_kernels(&nvtx, &api, &kernels, &ConversionOptions::default());
+    let mut events = kernels;
+    events.extend(api);
+    events.extend(nvtx_kernel);
+    events.extend(flows);
+    events
+}
+
+fn count(events: &[ChromeTraceEvent], cat: &str) -> usize {
+    events.iter().filter(|e| e.cat == cat).count()
+}
+
+// ==========================
+// Tests for parse_duration_ns
+// ==========================
+
+#[test]
+fn test_parse_duration_ns_units() {
+    assert_eq!(parse_duration_ns("5us").unwrap(), 5_000);
+    assert_eq!(parse_duration_ns("250ns").unwrap(), 250);
+    assert_eq!(parse_duration_ns("1.5ms").unwrap(), 1_500_000);
+    assert_eq!(parse_duration_ns("2s").unwrap(), 2_000_000_000);
+    assert_eq!(parse_duration_ns("700").unwrap(), 700);
+    assert!(parse_duration_ns("5 minutes").is_err());
+    assert!(parse_duration_ns("us").is_err());
+}
+
+// ==========================
+// Tests for filter_short_kernels
+// ==========================
+
+#[test]
+fn test_filter_short_kernels_drops_kernels_and_their_flows() {
+    let events = linked_trace(
+        vec![create_event(
+            "forward",
+            100_000,
+            300_000,
+            "NVTX Thread 1",
+            "nvtx",
+            None,
+        )],
+        vec![
+            create_event(
+                "cudaLaunchKernel",
+                110_000,
+                111_000,
+                "CUDA API Thread 1",
+                "cuda_api",
+                Some(1),
+            ),
+            create_event(
+                "cudaLaunchKernel",
+                112_000,
+                113_000,
+                "CUDA API Thread 1",
+                "cuda_api",
+                Some(2),
+            ),
+        ],
+        vec![
+            create_event("big", 140_000, 180_000, "Stream 7", "kernel", Some(1)),
+            create_event("tiny", 181_000, 182_000, "Stream 7", "kernel", Some(2)),
+        ],
+    );
+    assert_eq!(count(&events, "cuda_flow"), 4);
+
+    let (filtered, stats) = filter_short_kernels(events, 5_000);
+
+    assert_eq!(
+        stats,
+        DurationFilterStats {
+            dropped_kernels: 1,
+            kept_for_links: 0,
+            dropped_flow_events: 2,
+        }
+    );
+    assert!(!filtered.iter().any(|e| e.name == "tiny"));
+    assert_eq!(count(&filtered, "cuda_flow"), 2);
+
+    // The remaining flow still has both ends
+    let ids: Vec<_> = filtered.iter().filter_map(|e| e.id.clone()).collect();
+    assert_eq!(ids[0], ids[1]);
+
+    // The range keeps its span and records what was hidden
+    let range = filtered.iter().find(|e| e.cat == "nvtx-kernel").unwrap();
+    assert_eq!(range.ts, 140.0);
+    assert_eq!(range.dur, Some(42.0));
+    assert_eq!(range.args["filtered_kernels"], 1);
+    assert_eq!(range.args["filtered_kernel_ns"], 1000);
+}
+
+#[test]
+fn test_filter_short_kernels_keeps_longest_kernel_of_range() {
+    let events = linked_trace(
+        vec![create_event(
+            "step",
+            100_000,
+            300_000,
+            "NVTX Thread 1",
+            "nvtx",
+            None,
+        )],
+        vec![create_event(
+            "cudaGraphLaunch",
+            110_000,
+            111_000,
+            "CUDA API Thread 1",
+            "cuda_api",
+            Some(1),
+        )],
+        vec![
+            create_event("a", 140_000, 141_000, "Stream 7", "kernel", Some(1)),
+            create_event("b", 142_000, 144_000, "Stream 7", "kernel", Some(1)),
+        ],
+    );
+
+    let (filtered, stats) = filter_short_kernels(events, 5_000);
+
+    assert_eq!(stats.kept_for_links, 1);
+    assert_eq!(stats.dropped_kernels, 1);
+    let kernels: Vec<_> = filtered.iter().filter(|e| e.cat == "kernel").collect();
+    assert_eq!(kernels.len(), 1);
+    assert_eq!(kernels[0].name, "b");
+
+    // One flow pair for the kept kernel remains
+    let flows: Vec<_> = filtered.iter().filter(|e| e.cat == "cuda_flow").collect();
+    assert_eq!(flows.len(), 2);
+    assert!(flows
+        .iter()
+        .any(|e| e.ph == ChromeTracePhase::FlowFinish && e.ts == 142.0));
+}
+
+#[test]
+fn test_filter_short_kernels_unlinked_and_disabled() {
+    let events = vec![
+        create_event("short", 0, 100, "Stream 7", "kernel", None),
+        create_event("long", 1_000, 20_000, "Stream 7", "kernel", None),
+        create_event("cudaMalloc", 0, 100, "CUDA API Thread 1", "cuda_api", None),
+    ];
+
+    let (unchanged, stats) = filter_short_kernels(events.clone(), 0);
+    assert_eq!(unchanged.len(), 3);
+    assert_eq!(stats, DurationFilterStats::default());
+
+    // Only kernels are filtered
+    let (filtered, _) = filter_short_kernels(events, 5_000);
+    let names: Vec<_> = filtered.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["long", "cudaMalloc"]);
+}
+
+// ==========================
+// Tests for the conversion pipeline
+// ==========================
+
+/// One range launching a 1000 ns kernel and a correlated 100 ns kernel
+const SHORT_KERNEL_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'cudaLaunchKernel'), (2, 'gemm_kernel'), (3, 'tiny_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (3000, 4000, 0, 7, 1, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (4000, 4100, 0, 7, 2, 16777216, 3, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+        start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES
+        (1100, 1200, 16777217, 1, 1),
+        (1300, 1400, 16777217, 2, 1);
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES (1000, 2000, 'forward', NULL, 16777217, 59);
+";
+
+#[test]
+fn test_min_duration_drops_correlated_short_kernel() {
+    let kept = convert_sql(SHORT_KERNEL_SQL, ConversionOptions::default());
+    assert!(kept.iter().any(|e| e.name == "tiny_kernel"));
+    let flows = count(&kept, "cuda_flow");
+
+    let options = ConversionOptions {
+        min_kernel_duration_ns: 500,
+        ..Default::default()
+    };
+    let filtered = convert_sql(SHORT_KERNEL_SQL, options);
+
+    // The launch of the short kernel is linked, yet the kernel goes with its flow
+    assert!(!filtered.iter().any(|e| e.name == "tiny_kernel"));
+    assert!(filtered.iter().any(|e| e.name == "gemm_kernel"));
+    assert_eq!(count(&filtered, "cuda_flow"), flows - 2);
+    let range = filtered.iter().find(|e| e.cat == "nvtx-kernel").unwrap();
+    assert_eq!(range.args["filtered_kernels"], 1);
+    assert_eq!(range.args["filtered_kernel_ns"], 100);
+}
@@ -0,0 +1,145 @@
+//! Unit tests for the effective options export
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::effective_config::{
+    effective_config, parse_config_format, render_config, ConfigFormat, CONVERSION_CONFIG_EVENT,
+};
+use nsys_chrome::models::{
+    ChromeTracePhase, ConversionOptions, FlowBind, LinkPolicy, OutputRoute, TidGrouping,
+    TimeOrigin, TimeShift, TimeWindow,
+};
+use nsys_chrome::presets::{overlay_options, Preset};
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Options touching every kind of value the config holds
+fn custom_options() -> ConversionOptions {
+    let mut options = Preset::Training.options();
+    options.nvtx_event_prefix = Some(vec!["step".to_string()]);
+    options.min_kernel_duration_ns = 2500;
+    options.kernel_outlier_factor = Some(4.5);
+    options.time_origin = TimeOrigin::NvtxRange("warmup".to_string());
+    options.time_window = Some(TimeWindow {
+        start_ns: 2_000_000_000,
+        end_ns: i64::MAX,
+    });
+    options.output_routes = vec![OutputRoute::parse("kernel,nvtx-kernel=gpu.json").unwrap()];
+    options.annotation_time_shifts = HashMap::from([
+        ("nvtx".to_string(), TimeShift::Fixed(-20_000)),
+        ("NCCL".to_string(), TimeShift::Estimated),
+    ]);
+    options.link_policy = LinkPolicy::Innermost;
+    options.max_link_gap_ns = Some(1_000_000_000);
+    options.virtual_tids = Some(TidGrouping::Thread);
+    options.flows.launch = FlowBind::Next;
+    options
+}
+
+/// A single kernel
+const KERNEL_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'gemm_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (1000, 2000, 0, 7, 1, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+";
+
+fn convert(include_metadata: bool) -> Vec<nsys_chrome::ChromeTraceEvent> {
+    let options = ConversionOptions {
+        include_metadata,
+        jobs: 4,
+        ..Default::default()
+    };
+    convert_sql(KERNEL_SQL, options)
+}
+
+// ==========================
+// Tests for effective_config
+// ==========================
+
+#[test]
+fn test_config_reproduces_options() {
+    let options = custom_options();
+    let config = effective_config(&options);
+    assert_eq!(config["min_duration"], "2500ns");
+    assert_eq!(config["time_window"], "2000000000ns..");
+    assert_eq!(config["time_origin"], "nvtx:warmup");
+    assert_eq!(config["time_shifts"]["nvtx"], "-20000ns");
+    assert_eq!(config["flow_bind"]["launch"], "next");
+    assert!(!config.contains_key("link_window"));
+
+    let json = render_config(&options, ConfigFormat::Json);
+    let reloaded = overlay_options(ConversionOptions::default(), &json).unwrap();
+    assert_eq!(effective_config(&reloaded), config);
+    assert_eq!(
+        reloaded.annotation_time_shifts,
+        options.annotation_time_shifts
+    );
+    assert_eq!(reloaded.output_routes, options.output_routes);
+    assert_eq!(reloaded.time_window, options.time_window);
+}
+
+#[test]
+fn test_textproto_matches_the_schema() {
+    let text = render_config(&custom_options(), ConfigFormat::TextProto);
+    assert!(text.contains("activity_types: \"kernel\"\n"));
+    assert!(text.contains("link_policy: \"innermost\"\n"));
+    assert!(text.contains("time_shifts { key: \"NCCL\" value: \"auto\" }\n"));
+    assert!(text.contains("outlier_factor: 4.5\n"));
+
+    let schema = include_str!("../proto/conversion_options.proto");
+    for line in text.lines() {
+        let field = line.split([':', ' ']).next().unwrap();
+        assert!(
+            schema.contains(&format!(" {} = ", field)),
+            "field '{}' missing from the schema",
+            field
+        );
+    }
+    // Every option set, so the config holds every field
+    let mut options = custom_options();
+    options.nvtx_domains = Some(vec!["NCCL".to_string()]);
+    options.link_window_ns = Some(10_000_000_000);
+    options.sticky_lookback_ns = Some(5_000_000);
+    let fields = schema.matches(" = ").count() - 1; // minus the syntax line
+    assert_eq!(fields, effective_config(&options).len());
+}
+
+#[test]
+fn test_trace_embeds_the_config() {
+    let events = convert(true);
+    let config: Vec<_> = events
+        .iter()
+        .filter(|e| e.name == CONVERSION_CONFIG_EVENT)
+        .collect();
+    assert_eq!(config.len(), 1);
+    assert_eq!(config[0].ph, ChromeTracePhase::Metadata);
+    let options = config[0].args["options"].as_object().unwrap();
+    assert_eq!(options["include_metadata"], true);
+    assert!(!options.contains_key("jobs"));
+
+    let events = convert(false);
+    assert!(events.iter().all(|e| e.name != CONVERSION_CONFIG_EVENT));
+}
+
+#[test]
+fn test_parse_config_format() {
+    assert_eq!(parse_config_format("json").unwrap(), ConfigFormat::Json);
+    assert_eq!(
+        parse_config_format("textproto").unwrap(),
+        ConfigFormat::TextProto
+    );
+    assert!(parse_config_format("yaml").is_err());
+}
@@ -0,0 +1,119 @@
+//! Unit tests for library error classification
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::analysis::parse_duration_ns;
+use nsys_chrome::cache::read_event_cache;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::{convert_file, ConversionOptions, ConvertError, NsysChromeConverter};
+use tempfile::TempDir;
+
+// ==========================
+// Tests for input errors
+// ==========================
+
+#[test]
+fn test_invalid_input_files() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("report.sqlite");
+    std::fs::write(&path, b"not a database, just some text padding it out").unwrap();
+
+    let result = NsysChromeConverter::new(path.to_str().unwrap(), None)
+        .unwrap()
+        .convert();
+    assert!(matches!(result, Err(ConvertError::InvalidInput(_))));
+
+    let missing = dir.path().join("missing.cache");
+    let result = read_event_cache(missing.to_str().unwrap());
+    assert!(matches!(result, Err(ConvertError::InputNotFound(_))));
+
+    let result = read_event_cache(path.to_str().unwrap());
+    assert!(matches!(result, Err(ConvertError::InvalidInput(_))));
+}
+
+#[test]
+fn test_unsupported_schema() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(
+        &dir,
+        "input.sqlite",
+        "CREATE TABLE OSRT_API (start INTEGER, end INTEGER);",
+    );
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Osrt],
+        ..Default::default()
+    };
+
+    match NsysChromeConverter::new(&path, Some(options))
+        .unwrap()
+        .convert()
+    {
+        Err(ConvertError::UnsupportedSchema(details)) => {
+            assert!(details.contains("OSRT_API lacks globalTid, nameId"));
+        }
+        other => panic!(
+            "Expected UnsupportedSchema, got {:?}",
+            other.map(|e| e.len())
+        ),
+    }
+
+    // An export without the table at all is merely empty
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(
+        &dir,
+        "input.sqlite",
+        "CREATE TABLE StringIds (id INTEGER, value TEXT);",
+    );
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Osrt],
+        ..Default::default()
+    };
+    assert!(NsysChromeConverter::new(&path, Some(options))
+        .unwrap()
+        .convert()
+        .is_ok());
+}
+
+// ==========================
+// Tests for output errors
+// ==========================
+
+#[test]
+fn test_output_create_failure() {
+    let dir = TempDir::new().unwrap();
+    let input = create_sqlite(
+        &dir,
+        "input.sqlite",
+        "CREATE TABLE StringIds (id INTEGER, value TEXT);",
+    );
+    let output = dir.path().join("missing_dir").join("trace.json");
+
+    let error = convert_file(&input, output.to_str().unwrap(), None).unwrap_err();
+    match &error {
+        ConvertError::CreateOutput { path, .. } => assert_eq!(path, &output),
+        other => panic!("Expected CreateOutput, got {:?}", other),
+    }
+    // The I/O cause is kept in the chain
+    assert!(error
+        .display_chain()
+        .starts_with("Failed to create output file: "));
+    assert!(error.display_chain().len() > error.to_string().len());
+}
+
+// ==========================
+// Tests for option errors
+// ==========================
+
+#[test]
+fn test_invalid_option_values() {
+    assert!(matches!(
+        parse_duration_ns("fast"),
+        Err(ConvertError::InvalidOption(_))
+    ));
+    let error = parse_duration_ns("5 weeks").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Unknown duration unit 'weeks' (use ns, us, ms or s)"
+    );
+}
@@ -0,0 +1,216 @@
+//! Unit tests for the C ABI
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::ffi::{
+    nsys_chrome_convert, nsys_chrome_free_buffer, nsys_chrome_free_string, nsys_chrome_last_error,
+    nsys_chrome_link, nsys_chrome_link_pb, options_from_json, NSYS_CHROME_FAILED,
+    NSYS_CHROME_INVALID_ARGUMENT, NSYS_CHROME_OK,
+};
+use nsys_chrome::interop::{export_events_pb, import_events_pb};
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, LinkPolicy};
+use serde_json::{json, Value};
+use std::ffi::{c_char, CStr, CString};
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn c(text: &str) -> CString {
+    CString::new(text).unwrap()
+}
+
+fn last_error() -> String {
+    let ptr = nsys_chrome_last_error();
+    assert!(!ptr.is_null());
+    unsafe { CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Complete event with the args the linker reads, times in nanoseconds
+fn event(name: &str, cat: &str, start_ns: i64, end_ns: i64, corr: i64) -> Value {
+    json!({
+        "name": name, "ph": "X", "cat": cat,
+        "ts": start_ns as f64 / 1000.0, "dur": (end_ns - start_ns) as f64 / 1000.0,
+        "pid": "Device 0", "tid": format!("{} Thread 1", cat),
+        "args": {
+            "start_ns": start_ns, "end_ns": end_ns, "deviceId": 0,
+            "raw_tid": 1, "correlationId": corr
+        }
+    })
+}
+
+// ==========================
+// Tests for options_from_json
+// ==========================
+
+#[test]
+fn test_options_from_json() {
+    let options = options_from_json(
+        r#"{"activity_types": ["kernel"], "min_duration": "5us",
+            "link_policy": "innermost", "include_metadata": false, "jobs": 4}"#,
+    )
+    .unwrap();
+    assert_eq!(options.activity_types, vec!["kernel"]);
+    assert_eq!(options.min_kernel_duration_ns, 5_000);
+    assert_eq!(options.link_policy, LinkPolicy::Innermost);
+    assert!(!options.include_metadata);
+    assert_eq!(options.jobs, 4);
+
+    assert_eq!(options_from_json("").unwrap().jobs, 1);
+    for bad in [r#"{"min_duraton": "5us"}"#, r#"{"jobs": "4"}"#, "[1]", "{"] {
+        assert!(options_from_json(bad).is_err(), "{}", bad);
+    }
+}
+
+// ==========================
+// Tests for the exported functions
+// ==========================
+
+#[test]
+fn test_convert_writes_output_and_reports_errors() {
+    let dir = TempDir::new().unwrap();
+    let input = create_sqlite(
+        &dir,
+        "kernels.sqlite",
+        "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+        INSERT INTO StringIds VALUES (1, 'gemm');
+        CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+            start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+            correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+            gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+            blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+            registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+            (1000, 2000, 0, 7, 1, 16777216, 1, 4, 4, 1, 256, 1, 1, 32, 0, 0);",
+    );
+    let output = dir.path().join("trace.json.gz");
+
+    let (input_c, output_c) = (c(&input), c(output.to_str().unwrap()));
+    let options = c(r#"{"activity_types": ["kernel"]}"#);
+    let status =
+        unsafe { nsys_chrome_convert(input_c.as_ptr(), output_c.as_ptr(), options.as_ptr()) };
+    assert_eq!(status, NSYS_CHROME_OK, "{}", last_error());
+    assert!(output.exists());
+
+    let missing = c(dir.path().join("missing.sqlite").to_str().unwrap());
+    let status =
+        unsafe { nsys_chrome_convert(missing.as_ptr(), output_c.as_ptr(), std::ptr::null()) };
+    assert_eq!(status, NSYS_CHROME_FAILED);
+    assert!(last_error().contains("missing.sqlite"));
+
+    let status =
+        unsafe { nsys_chrome_convert(std::ptr::null(), output_c.as_ptr(), std::ptr::null()) };
+    assert_eq!(status, NSYS_CHROME_INVALID_ARGUMENT);
+    assert_eq!(last_error(), "input is NULL");
+}
+
+#[test]
+fn test_link_returns_linked_events() {
+    let trace = json!({"traceEvents": [
+        event("forward", "nvtx", 1000, 5000, 0),
+        event("cudaLaunchKernel", "cuda_api", 1500, 1800, 7),
+        event("gemm", "kernel", 2000, 4000, 7),
+    ]});
+    let events_json = c(&trace.to_string());
+    let mut result: *mut c_char = std::ptr::null_mut();
+    let status = unsafe { nsys_chrome_link(events_json.as_ptr(), std::ptr::null(), &mut result) };
+    assert_eq!(status, NSYS_CHROME_OK);
+
+    let linked: Value =
+        serde_json::from_str(&unsafe { CStr::from_ptr(result) }.to_string_lossy()).unwrap();
+    unsafe { nsys_chrome_free_string(result) };
+    let linked = linked.as_array().unwrap();
+    assert_eq!(linked[0]["cat"], "nvtx-kernel");
+    assert_eq!(linked[0]["ts"], 2.0);
+    assert!(linked.iter().any(|e| e["ph"] == "s"));
+
+    let bad = c("{\"events\": []}");
+    let status = unsafe { nsys_chrome_link(bad.as_ptr(), std::ptr::null(), &mut result) };
+    assert_eq!(status, NSYS_CHROME_FAILED);
+    assert!(result.is_null());
+}
+
+#[test]
+fn test_link_pb_returns_linked_events() {
+    let events: Vec<ChromeTraceEvent> = [
+        event("forward", "nvtx", 1000, 5000, 0),
+        event("cudaLaunchKernel", "cuda_api", 1500, 1800, 7),
+        event("gemm", "kernel", 2000, 4000, 7),
+    ]
+    .into_iter()
+    .map(|e| serde_json::from_value(e).unwrap())
+    .collect();
+    let mut stream = Vec::new();
+    export_events_pb(&events, &mut stream).unwrap();
+
+    let mut result: *mut u8 = std::ptr::null_mut();
+    let mut result_len = 0;
+    let status = unsafe {
+        nsys_chrome_link_pb(
+            stream.as_ptr(),
+            stream.len(),
+            std::ptr::null(),
+            &mut result,
+            &mut result_len,
+        )
+    };
+    assert_eq!(status, NSYS_CHROME_OK);
+    let linked =
+        import_events_pb(unsafe { std::slice::from_raw_parts(result, result_len) }).unwrap();
+    unsafe { nsys_chrome_free_buffer(result, result_len) };
+    assert_eq!(linked[0].cat, "nvtx-kernel");
+    assert_eq!(linked[0].ts, 2.0);
+    assert!(linked.iter().any(|e| e.ph == ChromeTracePhase::FlowStart));
+
+    let truncated = &stream[..stream.len() - 1];
+    let status = unsafe {
+        nsys_chrome_link_pb(
+            truncated.as_ptr(),
+            truncated.len(),
+            std::ptr::null(),
+            &mut result,
+            &mut result_len,
+        )
+    };
+    assert_eq!(status, NSYS_CHROME_FAILED);
+    assert!(result.is_null());
+    assert!(last_error().contains("Malformed protobuf event"));
+}
+
+// ==========================
+// Tests for the C header
+// ==========================
+
+#[test]
+fn test_header_declares_every_export() {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let source = std::fs::read_to_string(format!("{}/src/ffi.rs", root)).unwrap();
+    let header = std::fs::read_to_string(format!("{}/include/nsys_chrome.h", root)).unwrap();
+
+    let exports: Vec<&str> = source
+        .lines()
+        .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+        .map(|rest| rest.split('(').next().unwrap())
+        .collect();
+    assert_eq!(exports.len(), 6);
+    for name in exports {
+        assert!(
+            header.contains(&format!(" {}(", name)),
+            "{} not declared",
+            name
+        );
+    }
+    for constant in source.lines().filter_map(|l| l.strip_prefix("pub const ")) {
+        let name = constant.split(':').next().unwrap();
+        assert!(
+            header.contains(&format!("#define {} ", name)),
+            "{} not defined",
+            name
+        );
+    }
+}
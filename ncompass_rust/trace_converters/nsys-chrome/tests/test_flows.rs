@@ -0,0 +1,195 @@
+//! Unit tests for flow arrow representation options
+
+use nsys_chrome::linker::{apply_flow_options, parse_flow_bind_spec, parse_flow_style};
+use nsys_chrome::models::{
+    BindingPoint, ChromeTraceEvent, ChromeTracePhase, ConversionOptions, FlowBind, FlowLink,
+    FlowOptions, FlowStyle, StringOrInt,
+};
+use nsys_chrome::presets::overlay_options;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_slice(name: &str, ts: f64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        1.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+}
+
+fn create_flow(
+    from: &ChromeTraceEvent,
+    to: &ChromeTraceEvent,
+    id: StringOrInt,
+) -> [ChromeTraceEvent; 2] {
+    [
+        ChromeTraceEvent::flow_start(from.ts, from.pid.clone(), from.tid.clone(), id.clone()),
+        ChromeTraceEvent::flow_finish(
+            to.ts,
+            to.pid.clone(),
+            to.tid.clone(),
+            id,
+            BindingPoint::Enclosing,
+        ),
+    ]
+}
+
+/// A launch, a copy and an MPI call to the launched NCCL kernel
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    let launch = create_slice("cudaLaunchKernel", 1.0, "CUDA API Thread 1", "cuda_api");
+    let kernel = create_slice("ncclKernel", 5.0, "Stream 7", "kernel");
+    let memcpy_call = create_slice("cudaMemcpyAsync", 2.0, "CUDA API Thread 1", "cuda_api");
+    let copy = create_slice("Memcpy HtoD", 3.0, "Copy Engine 0", "memcpy");
+    let mpi_call = create_slice("MPI_Allreduce", 0.5, "MPI Thread 1", "mpi");
+
+    let mut events = vec![
+        launch.clone(),
+        kernel.clone(),
+        memcpy_call.clone(),
+        copy.clone(),
+        mpi_call.clone(),
+    ];
+    events.extend(create_flow(&launch, &kernel, StringOrInt::Int(1)));
+    events.extend(create_flow(&memcpy_call, &copy, StringOrInt::Int(2)));
+    events.extend(create_flow(
+        &mpi_call,
+        &kernel,
+        StringOrInt::String("mpi-nccl-0".to_string()),
+    ));
+    events
+}
+
+fn finish_binding(events: &[ChromeTraceEvent], id: &StringOrInt) -> Option<BindingPoint> {
+    events
+        .iter()
+        .find(|e| e.ph == ChromeTracePhase::FlowFinish && e.id.as_ref() == Some(id))
+        .and_then(|e| e.bp)
+}
+
+// ==========================
+// Tests for parsing
+// ==========================
+
+#[test]
+fn test_parse_flow_options() {
+    assert_eq!(parse_flow_style("bound").unwrap(), FlowStyle::Bound);
+    assert_eq!(parse_flow_style(" events ").unwrap(), FlowStyle::Events);
+    assert!(parse_flow_style("v2").is_err());
+
+    assert_eq!(
+        parse_flow_bind_spec("launch=next").unwrap(),
+        (vec![FlowLink::Launch], FlowBind::Next)
+    );
+    assert_eq!(
+        parse_flow_bind_spec("all=enclosing").unwrap(),
+        (FlowLink::ALL.to_vec(), FlowBind::Enclosing)
+    );
+    assert!(parse_flow_bind_spec("launch").is_err());
+    assert!(parse_flow_bind_spec("kernel=next").is_err());
+    assert!(parse_flow_bind_spec("launch=same").is_err());
+}
+
+// ==========================
+// Tests for apply_flow_options
+// ==========================
+
+#[test]
+fn test_default_flow_options_keep_events() {
+    let events = sample_events();
+    let applied = apply_flow_options(events.clone(), &FlowOptions::default());
+    assert_eq!(
+        serde_json::to_value(&applied).unwrap(),
+        serde_json::to_value(&events).unwrap()
+    );
+}
+
+#[test]
+fn test_flow_binding_per_link() {
+    let options = FlowOptions {
+        launch: FlowBind::Next,
+        mpi: FlowBind::Next,
+        ..Default::default()
+    };
+    let events = apply_flow_options(sample_events(), &options);
+
+    assert_eq!(events.len(), 11);
+    assert_eq!(finish_binding(&events, &StringOrInt::Int(1)), None);
+    assert_eq!(
+        finish_binding(&events, &StringOrInt::Int(2)),
+        Some(BindingPoint::Enclosing)
+    );
+    assert_eq!(
+        finish_binding(&events, &StringOrInt::String("mpi-nccl-0".to_string())),
+        None
+    );
+    let json = serde_json::to_string(&events).unwrap();
+    assert_eq!(json.matches("\"bp\"").count(), 1);
+}
+
+#[test]
+fn test_bound_flows_move_onto_slices() {
+    let options = FlowOptions {
+        style: FlowStyle::Bound,
+        mpi: FlowBind::Next,
+        ..Default::default()
+    };
+    let events = apply_flow_options(sample_events(), &options);
+
+    // The kernel already holds the launch flow, so the MPI arrow stays a pair
+    let flows: Vec<&ChromeTraceEvent> = events
+        .iter()
+        .filter(|e| {
+            matches!(
+                e.ph,
+                ChromeTracePhase::FlowStart | ChromeTracePhase::FlowFinish
+            )
+        })
+        .collect();
+    assert_eq!(flows.len(), 2);
+    assert!(flows
+        .iter()
+        .all(|e| e.id == Some(StringOrInt::String("mpi-nccl-0".to_string())) && e.bp.is_none()));
+
+    let launch = serde_json::to_value(&events[0]).unwrap();
+    assert_eq!(launch["bind_id"], 1);
+    assert_eq!(launch["flow_out"], true);
+    assert!(launch.get("flow_in").is_none());
+    let kernel = serde_json::to_value(&events[1]).unwrap();
+    assert_eq!(kernel["bind_id"], 1);
+    assert_eq!(kernel["flow_in"], true);
+    assert_eq!(events[3].bind_id, Some(StringOrInt::Int(2)));
+    assert_eq!(events[3].flow_in, Some(true));
+}
+
+// ==========================
+// Tests for option overlays
+// ==========================
+
+#[test]
+fn test_flow_options_overlay() {
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"flow_style": "bound", "flow_bind": {"all": "next", "copy": "enclosing"}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        options.flows,
+        FlowOptions {
+            style: FlowStyle::Bound,
+            launch: FlowBind::Next,
+            copy: FlowBind::Enclosing,
+            mpi: FlowBind::Next,
+        }
+    );
+    assert!(overlay_options(ConversionOptions::default(), r#"{"flow_bind": "next"}"#).is_err());
+    assert!(overlay_options(
+        ConversionOptions::default(),
+        r#"{"flow_bind": {"x": "next"}}"#
+    )
+    .is_err());
+}
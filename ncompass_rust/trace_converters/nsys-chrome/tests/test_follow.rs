@@ -0,0 +1,177 @@
+//! Unit tests for following a growing SQLite export
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::cancel::CancellationToken;
+use nsys_chrome::follow::{FollowOptions, TraceFollower};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::sink::{JsonArraySink, TraceSink};
+use nsys_chrome::ConvertError;
+use rusqlite::Connection;
+use std::time::Duration;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// An export holding no kernels yet
+const EMPTY_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'gemm_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+";
+
+/// Append kernels `first..first + count`, as a periodic export would
+fn append_kernels(path: &str, first: i64, count: i64) {
+    let conn = Connection::open(path).unwrap();
+    conn.execute(
+        "WITH RECURSIVE n(i) AS (SELECT ?1 UNION ALL SELECT i + 1 FROM n WHERE i < ?2)
+        INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL
+        SELECT i * 1000, i * 1000 + 500, 0, 7, i, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0 FROM n",
+        [first, first + count - 1],
+    )
+    .unwrap();
+}
+
+fn kernel_count(events: &[ChromeTraceEvent]) -> usize {
+    events.iter().filter(|e| e.cat == "kernel").count()
+}
+
+fn kernel_options() -> ConversionOptions {
+    ConversionOptions {
+        activity_types: vec!["kernel".into()],
+        ..Default::default()
+    }
+}
+
+// ==========================
+// Tests for TraceFollower::poll
+// ==========================
+
+#[test]
+fn test_poll_returns_only_new_events() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "run.sqlite", EMPTY_SQL);
+    let path = path.as_str();
+    append_kernels(path, 1, 100);
+
+    let mut follower = TraceFollower::new(path, kernel_options());
+    let first = follower.poll().unwrap();
+    assert_eq!(kernel_count(&first), 100);
+    assert!(follower.poll().unwrap().is_empty());
+
+    append_kernels(path, 101, 300);
+    let second = follower.poll().unwrap();
+    assert_eq!(kernel_count(&second), 300);
+    // Track metadata was handed on with the first scan
+    assert_eq!(second.len(), 300);
+    assert!(second.windows(2).all(|w| w[0].ts <= w[1].ts));
+    assert_eq!(follower.events_seen(), first.len() + 300);
+}
+
+#[test]
+fn test_poll_waits_for_missing_export() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("later.sqlite");
+    let path = path.to_str().unwrap();
+
+    let mut follower = TraceFollower::new(path, kernel_options());
+    assert!(matches!(
+        follower.poll(),
+        Err(ConvertError::InputNotFound(_))
+    ));
+
+    create_sqlite(&dir, "later.sqlite", EMPTY_SQL);
+    append_kernels(path, 1, 10);
+    assert_eq!(kernel_count(&follower.poll().unwrap()), 10);
+}
+
+// ==========================
+// Tests for TraceFollower::follow
+// ==========================
+
+#[test]
+fn test_follow_stops_when_idle() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "run.sqlite", EMPTY_SQL);
+    let path = path.as_str();
+    append_kernels(path, 1, 20);
+
+    let options = FollowOptions {
+        interval: Duration::from_millis(10),
+        idle_timeout: Some(Duration::from_millis(50)),
+    };
+    let mut appended = Vec::new();
+    let mut sink = JsonArraySink::new(Vec::new());
+    let written = TraceFollower::new(path, kernel_options())
+        .follow(&mut sink, &options, |n| appended.push(n))
+        .unwrap();
+
+    assert_eq!(appended, vec![written]);
+    let trace: Vec<serde_json::Value> =
+        serde_json::from_slice(&sink.into_inner().unwrap()).unwrap();
+    assert_eq!(trace.len(), written);
+    assert_eq!(trace.iter().filter(|e| e["cat"] == "kernel").count(), 20);
+}
+
+#[test]
+fn test_follow_stops_when_cancelled() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("missing.sqlite");
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let mut sink = JsonArraySink::new(Vec::new());
+    let written = TraceFollower::new(path.to_str().unwrap(), kernel_options())
+        .with_cancellation(token)
+        .follow(&mut sink, &FollowOptions::default(), |_| {})
+        .unwrap();
+    assert_eq!(written, 0);
+    assert_eq!(sink.into_inner().unwrap(), b"[\n\n]\n");
+}
+
+// ==========================
+// Tests for JsonArraySink
+// ==========================
+
+#[test]
+fn test_json_array_sink_is_loadable_when_flushed() {
+    let kernel = |name: &str| {
+        ChromeTraceEvent::complete(
+            name.to_string(),
+            0.0,
+            1.0,
+            "Device 0".to_string(),
+            "Stream 7".to_string(),
+            "kernel".to_string(),
+        )
+    };
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("live.json");
+    let mut sink = JsonArraySink::new(std::fs::File::create(&path).unwrap());
+    sink.begin().unwrap();
+    sink.write_event(&kernel("a")).unwrap();
+    sink.write_event(&kernel("b")).unwrap();
+    sink.flush().unwrap();
+    assert_eq!(sink.events(), 2);
+
+    // Mid-run the closing bracket is missing, as the array format allows
+    let partial = std::fs::read_to_string(&path).unwrap();
+    assert!(partial.starts_with("[\n{"));
+    let events: Vec<serde_json::Value> = serde_json::from_str(&format!("{}]", partial)).unwrap();
+    assert_eq!(events.len(), 2);
+
+    sink.finish().unwrap();
+    let events: Vec<serde_json::Value> =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(events[1]["name"], "b");
+}
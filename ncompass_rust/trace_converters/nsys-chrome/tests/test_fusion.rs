@@ -0,0 +1,145 @@
+//! Unit tests for the kernel fusion report
+
+use nsys_chrome::analysis::{fusion_report, FusionRangeSummary};
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::ConvertError;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Event on `tid` of Device 0, times in microseconds
+fn create_event(name: &str, ts: f64, dur: f64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+}
+
+fn kernel(name: &str, ts: f64, dur: f64, stream: &str) -> ChromeTraceEvent {
+    create_event(name, ts, dur, stream, "kernel")
+}
+
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        // Four 2us kernels 8us apart: launch-bound
+        kernel("add", 0.0, 2.0, "Stream 7"),
+        kernel("mul", 10.0, 2.0, "Stream 7"),
+        kernel("add", 20.0, 2.0, "Stream 7"),
+        kernel("relu", 30.0, 2.0, "Stream 7"),
+        // Too long to count as short
+        kernel("gemm", 40.0, 100.0, "Stream 7"),
+        // Back to back, then only two launch-bound kernels
+        kernel("a", 150.0, 5.0, "Stream 7"),
+        kernel("b", 156.0, 5.0, "Stream 7"),
+        kernel("c", 170.0, 2.0, "Stream 7"),
+        // Three 1us kernels 4us apart on another stream
+        kernel("x", 50.0, 1.0, "Stream 9"),
+        kernel("y", 55.0, 1.0, "Stream 9"),
+        kernel("z", 60.0, 1.0, "Stream 9"),
+        create_event("step", 0.0, 200.0, "NVTX Thread 1", "nvtx-kernel"),
+        create_event("layer1", 0.0, 40.0, "NVTX Thread 1", "nvtx-kernel"),
+    ]
+}
+
+// ==========================
+// Tests for fusion_report
+// ==========================
+
+#[test]
+fn test_launch_bound_runs_become_regions() {
+    let report = fusion_report(&sample_events(), 20_000).unwrap();
+    assert_eq!(report.regions.len(), 2);
+
+    let region = &report.regions[0];
+    assert_eq!(region.stream, "Stream 7");
+    assert_eq!((region.start_ns, region.end_ns), (0, 32_000));
+    assert_eq!(region.kernels, 4);
+    assert_eq!(region.kernel_ns, 8_000);
+    assert_eq!(region.gap_ns, 24_000);
+    assert_eq!(region.estimated_savings_ns, 24_000);
+    assert_eq!(region.kernel_names, vec!["add", "mul", "relu"]);
+
+    assert_eq!(report.regions[1].stream, "Stream 9");
+    assert_eq!(report.total_savings_ns(), 32_000);
+
+    // A lower threshold leaves nothing short enough
+    assert!(fusion_report(&sample_events(), 500)
+        .unwrap()
+        .regions
+        .is_empty());
+}
+
+#[test]
+fn test_regions_aggregate_per_innermost_range() {
+    let report = fusion_report(&sample_events(), 20_000).unwrap();
+    assert_eq!(report.regions[0].range.as_deref(), Some("layer1"));
+    assert_eq!(report.regions[1].range.as_deref(), Some("step"));
+    assert_eq!(
+        report.ranges,
+        vec![
+            FusionRangeSummary {
+                range: Some("layer1".to_string()),
+                regions: 1,
+                kernels: 4,
+                estimated_savings_ns: 24_000,
+            },
+            FusionRangeSummary {
+                range: Some("step".to_string()),
+                regions: 1,
+                kernels: 3,
+                estimated_savings_ns: 8_000,
+            },
+        ]
+    );
+
+    // Without linked ranges every region is unattributed
+    let kernels: Vec<ChromeTraceEvent> = sample_events()
+        .into_iter()
+        .filter(|e| e.cat == "kernel")
+        .collect();
+    let report = fusion_report(&kernels, 20_000).unwrap();
+    assert_eq!(report.ranges.len(), 1);
+    assert_eq!(report.ranges[0].range, None);
+    assert_eq!(report.ranges[0].regions, 2);
+}
+
+// ==========================
+// Tests for FusionReport output
+// ==========================
+
+#[test]
+fn test_fusion_report_output() {
+    let report = fusion_report(&sample_events(), 20_000).unwrap();
+    let mut csv = Vec::new();
+    report.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[1],
+        "Device 0,Stream 7,layer1,0,32000,4,8000,24000,24000,add;mul;relu"
+    );
+
+    let dir = TempDir::new().unwrap();
+    let json_path = dir.path().join("fusion.json");
+    report.write(json_path.to_str().unwrap()).unwrap();
+    let root: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+    assert_eq!(root["regions"][1]["kernels"], 3);
+
+    let txt_path = dir.path().join("fusion.txt");
+    assert!(matches!(
+        report.write(txt_path.to_str().unwrap()),
+        Err(ConvertError::InvalidOption(_))
+    ));
+    assert!(matches!(
+        fusion_report(&sample_events(), 0),
+        Err(ConvertError::InvalidOption(_))
+    ));
+}
@@ -0,0 +1,146 @@
+//! Unit tests for GPU power/thermal/clock counters and throttling ranges
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::analysis::{throttled_regions, THROTTLE_CATEGORY, THROTTLE_TRACK};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use nsys_chrome::parsers::{is_sampled_metric, GPU_METRICS_CATEGORY};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_sample(name: &str, ts_ns: i64, value: f64) -> ChromeTraceEvent {
+    ChromeTraceEvent::new(
+        name.to_string(),
+        ChromeTracePhase::Counter,
+        ts_ns as f64 / 1000.0,
+        "Device 0",
+        "",
+        GPU_METRICS_CATEGORY,
+    )
+    .with_arg("value", value)
+}
+
+fn create_kernel(name: &str, start_ns: i64, dur_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        dur_ns as f64 / 1000.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", start_ns + dur_ns)
+}
+
+/// Clock dipping to 1100 MHz between 20 us and 40 us, then again from 60 us
+fn clock_samples() -> Vec<ChromeTraceEvent> {
+    [
+        (0, 1980.0),
+        (10_000, 1980.0),
+        (20_000, 1200.0),
+        (30_000, 1100.0),
+        (40_000, 1950.0),
+        (50_000, 1980.0),
+        (60_000, 1500.0),
+        (70_000, 1400.0),
+    ]
+    .into_iter()
+    .map(|(ts, value)| create_sample("GPC Clock Frequency [MHz]", ts, value))
+    .collect()
+}
+
+// ==========================
+// Tests for throttled_regions
+// ==========================
+
+#[test]
+fn test_throttled_regions_follow_clock_dips() {
+    let mut metrics = clock_samples();
+    metrics.push(create_sample("Power [W]", 25_000, 50.0));
+
+    // Five gemms of 1 us before the dip, two of 3 us during it
+    let mut kernels: Vec<ChromeTraceEvent> = (0..5)
+        .map(|i| create_kernel("gemm", i * 2_000, 1_000))
+        .collect();
+    kernels.push(create_kernel("gemm", 22_000, 3_000));
+    kernels.push(create_kernel("gemm", 30_000, 3_000));
+
+    let regions = throttled_regions(&metrics, &kernels);
+    assert_eq!(regions.len(), 2);
+
+    let first = &regions[0];
+    assert_eq!(first.cat, THROTTLE_CATEGORY);
+    assert_eq!(first.tid, THROTTLE_TRACK);
+    assert_eq!(first.pid, "Device 0");
+    assert_eq!((first.ts, first.dur), (20.0, Some(20.0)));
+    assert_eq!(first.args["min_clock"], 1100.0);
+    assert_eq!(first.args["peak_clock"], 1980.0);
+    assert_eq!(first.args["kernels"], 2);
+    assert_eq!(first.args["kernel_slowdown"], 3.0);
+
+    // Still throttled at the last sample; no kernels ran
+    let second = &regions[1];
+    assert_eq!((second.ts, second.dur), (60.0, Some(10.0)));
+    assert_eq!(second.args["kernels"], 0);
+    assert!(!second.args.contains_key("kernel_slowdown"));
+}
+
+#[test]
+fn test_sampled_metric_names() {
+    assert!(is_sampled_metric("GPC Clock Frequency [MHz]"));
+    assert!(is_sampled_metric("GPU Temperature [C]"));
+    assert!(is_sampled_metric("Power Draw [W]"));
+    assert!(!is_sampled_metric("SM Active [Throughput %]"));
+    assert!(!is_sampled_metric("PCIe RX Throughput [Throughput %]"));
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_emits_metric_counters_per_device() {
+    let sql = "CREATE TABLE TARGET_INFO_GPU_METRICS (
+            typeId INTEGER, sourceId INTEGER, typeName TEXT, metricId INTEGER, metricName TEXT
+        );
+        INSERT INTO TARGET_INFO_GPU_METRICS VALUES
+            (65793, 1, 'GA100', 0, 'GPC Clock Frequency [MHz]'),
+            (65793, 1, 'GA100', 1, 'GPU Temperature [C]'),
+            (65793, 1, 'GA100', 2, 'SM Active [Throughput %]');
+        CREATE TABLE GPU_METRICS (typeId INTEGER, timestamp INTEGER, metricId INTEGER, value INTEGER);
+        INSERT INTO GPU_METRICS VALUES
+            (65793, 0, 0, 1980), (65793, 0, 1, 60), (65793, 0, 2, 90),
+            (65793, 10000, 0, 1000), (65793, 10000, 1, 85), (65793, 10000, 2, 95),
+            (65793, 20000, 0, 1980), (65793, 20000, 1, 70), (65793, 20000, 2, 40);";
+
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::GpuMetrics],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = convert_sql(sql, options);
+
+    let counters: Vec<(&str, f64)> = events
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Counter)
+        .map(|e| (e.name.as_str(), e.ts))
+        .collect();
+    assert_eq!(counters.len(), 6);
+    assert!(counters.iter().all(|(name, _)| !name.contains("SM Active")));
+    assert!(events
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Counter)
+        .all(|e| e.pid == "Device 1" && e.cat == GPU_METRICS_CATEGORY));
+
+    let throttled: Vec<&ChromeTraceEvent> = events
+        .iter()
+        .filter(|e| e.cat == THROTTLE_CATEGORY)
+        .collect();
+    assert_eq!(throttled.len(), 1);
+    assert_eq!((throttled[0].ts, throttled[0].dur), (10.0, Some(10.0)));
+}
@@ -0,0 +1,178 @@
+//! Unit tests for naming CUDA graph kernels after their nodes
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::graph_nodes::{
+    apply_graph_node_names, graph_node_names, load_graph_node_creations, GraphNodeCreation,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// A graph of two nodes captured inside "attention" and "mlp" ranges (both
+/// nested in "capture"), instantiated as nodes 11 and 12 and replayed once;
+/// a third kernel is launched normally
+const GRAPH_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'flash_fwd_kernel'), (2, 'gemm_kernel');
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES
+        (1000, 9000, 'capture', NULL, 16777217, 59),
+        (2000, 3000, 'attention', NULL, 16777217, 59),
+        (4000, 5000, 'mlp', NULL, 16777217, 59);
+    CREATE TABLE CUDA_GRAPH_NODE_EVENTS (
+        start INTEGER, end INTEGER, globalTid INTEGER,
+        graphNodeId INTEGER, originalGraphNodeId INTEGER
+    );
+    INSERT INTO CUDA_GRAPH_NODE_EVENTS VALUES
+        (2500, 2600, 16777217, 1, NULL),
+        (4500, 4600, 16777217, 2, NULL),
+        (9500, 9600, 16777217, 11, 1),
+        (9500, 9600, 16777217, 12, 2);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER, graphNodeId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (10000, 11000, 0, 7, 1, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0, 11),
+        (11000, 12000, 0, 7, 2, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0, 12),
+        (13000, 14000, 0, 7, 3, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0, NULL);
+";
+
+fn create_range(name: &str, start_ns: i64, end_ns: Option<i64>) -> ChromeTraceEvent {
+    let mut event = ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        0.0,
+        "Device 0".to_string(),
+        "NVTX Thread 1".to_string(),
+        "nvtx".to_string(),
+    )
+    .with_arg("raw_tid", 1)
+    .with_arg("start_ns", start_ns);
+    if let Some(end_ns) = end_ns {
+        event = event.with_arg("end_ns", end_ns);
+    }
+    event
+}
+
+fn creation(start_ns: i64, node_id: i64, original_node_id: Option<i64>) -> GraphNodeCreation {
+    GraphNodeCreation {
+        start_ns,
+        tid: 1,
+        node_id,
+        original_node_id,
+    }
+}
+
+fn create_kernel(name: &str, graph_node_id: Option<i64>) -> ChromeTraceEvent {
+    let kernel = ChromeTraceEvent::complete(
+        name.to_string(),
+        0.0,
+        1.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    );
+    match graph_node_id {
+        Some(id) => kernel.with_arg("graphNodeId", id),
+        None => kernel,
+    }
+}
+
+// ==========================
+// Tests for graph_node_names
+// ==========================
+
+#[test]
+fn test_graph_node_names_use_innermost_range_and_clones() {
+    let ranges = vec![
+        create_range("capture", 1000, Some(9000)),
+        create_range("attention", 2000, Some(3000)),
+        create_range("open", 20000, None),
+    ];
+    let creations = vec![
+        creation(2500, 1, None),
+        creation(4500, 2, None),
+        creation(20500, 3, None),
+        // Clone of a clone
+        creation(9500, 21, Some(11)),
+        creation(9400, 11, Some(1)),
+        creation(9500, 99, Some(50)),
+    ];
+
+    let names = graph_node_names(&creations, &ranges);
+    assert_eq!(names[&1], "attention");
+    assert_eq!(names[&2], "capture");
+    assert_eq!(names[&3], "open");
+    assert_eq!(names[&11], "attention");
+    assert_eq!(names[&21], "attention");
+    assert!(!names.contains_key(&99));
+}
+
+// ==========================
+// Tests for apply_graph_node_names
+// ==========================
+
+#[test]
+fn test_apply_graph_node_names_keeps_symbol() {
+    let mut kernels = vec![
+        create_kernel("flash_fwd_kernel", Some(11)),
+        create_kernel("gemm_kernel", Some(12)),
+        create_kernel("gemm_kernel", None),
+    ];
+    let names = HashMap::from([(11, "attention".to_string())]);
+
+    assert_eq!(apply_graph_node_names(&mut kernels, &names), 1);
+    assert_eq!(kernels[0].name, "attention");
+    assert_eq!(kernels[0].args["kernel_symbol"], "flash_fwd_kernel");
+    assert_eq!(kernels[1].name, "gemm_kernel");
+    assert!(!kernels[1].args.contains_key("kernel_symbol"));
+    assert_eq!(kernels[2].name, "gemm_kernel");
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_load_graph_node_creations() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(GRAPH_SQL).unwrap();
+    let creations = load_graph_node_creations(&conn).unwrap();
+    assert_eq!(creations.len(), 4);
+    assert_eq!(creations[2], creation(9500, 11, Some(1)));
+
+    let empty = Connection::open_in_memory().unwrap();
+    assert!(load_graph_node_creations(&empty).unwrap().is_empty());
+}
+
+#[test]
+fn test_converter_names_graph_kernels() {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = convert_sql(GRAPH_SQL, options);
+
+    let kernels: Vec<&ChromeTraceEvent> = events.iter().filter(|e| e.cat == "kernel").collect();
+    let names: Vec<&str> = kernels.iter().map(|k| k.name.as_str()).collect();
+    assert_eq!(names, vec!["attention", "mlp", "gemm_kernel"]);
+    assert_eq!(kernels[0].args["kernel_symbol"], "flash_fwd_kernel");
+    assert_eq!(kernels[0].args["graphNodeId"], 11);
+    assert!(!kernels[2].args.contains_key("graphNodeId"));
+}
@@ -0,0 +1,210 @@
+//! Unit tests for Vulkan and DX12 workload extraction
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::parsers::graphics::{
+    GRAPHICS_GPU_LABEL_KIND, GRAPHICS_KIND_ARG, GRAPHICS_LABEL_KIND, GRAPHICS_SUBMIT_KIND,
+    GRAPHICS_WORKLOAD_KIND,
+};
+use nsys_chrome::parsers::{link_debug_labels, EventParser, GraphicsParser, ParseContext};
+use nsys_chrome::schema::SchemaProbe;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// A frame labelled "Frame 1" around a "Shadows" pass: two submits from the
+/// labelled thread (one to each GPU), one unrelated call, and a submit from
+/// another thread
+const VULKAN_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'vkQueueSubmit');
+    INSERT INTO StringIds VALUES (2, 'vkCreateBuffer');
+    INSERT INTO StringIds VALUES (3, 'Frame 1');
+    INSERT INTO StringIds VALUES (4, 'Shadows');
+    CREATE TABLE VULKAN_API (
+        start INTEGER, end INTEGER, eventClass INTEGER, globalTid INTEGER,
+        correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO VULKAN_API VALUES (1000, 1100, 0, 16777217, 11, 1);
+    INSERT INTO VULKAN_API VALUES (1500, 1600, 0, 16777217, 12, 2);
+    INSERT INTO VULKAN_API VALUES (3000, 3100, 0, 16777217, 13, 1);
+    INSERT INTO VULKAN_API VALUES (3500, 3600, 0, 16777218, 14, 1);
+    CREATE TABLE VULKAN_WORKLOAD (
+        start INTEGER, end INTEGER, globalTid INTEGER, gpu INTEGER,
+        queue INTEGER, commandBuffer INTEGER, correlationId INTEGER
+    );
+    INSERT INTO VULKAN_WORKLOAD VALUES (2000, 2500, 16777217, 0, 4096, 160, 11);
+    INSERT INTO VULKAN_WORKLOAD VALUES (4000, 4800, 16777217, 1, 4096, 161, 13);
+    INSERT INTO VULKAN_WORKLOAD VALUES (5000, 5200, 16777218, 0, 8192, 162, 14);
+    CREATE TABLE VULKAN_DEBUG_API (
+        start INTEGER, end INTEGER, eventClass INTEGER, globalTid INTEGER,
+        color INTEGER, textId INTEGER
+    );
+    INSERT INTO VULKAN_DEBUG_API VALUES (500, 3200, 0, 16777217, 0, 3);
+    INSERT INTO VULKAN_DEBUG_API VALUES (900, 1200, 0, 16777217, 0, 4);
+";
+
+fn load_strings(conn: &Connection) -> HashMap<i32, String> {
+    let mut stmt = conn.prepare("SELECT id, value FROM StringIds").unwrap();
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
+fn parse_graphics(sql: &str) -> Vec<ChromeTraceEvent> {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(sql).unwrap();
+
+    let strings = load_strings(&conn);
+    let options = ConversionOptions::default();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    // As in the converter, the probe picks whichever API's workload table exists
+    let schema = SchemaProbe::probe(&conn).unwrap();
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names)
+        .with_schema(&schema);
+    GraphicsParser.safe_parse(&context).unwrap()
+}
+
+fn of_kind<'a>(events: &'a [ChromeTraceEvent], kind: &str) -> Vec<&'a ChromeTraceEvent> {
+    let mut matching: Vec<&ChromeTraceEvent> = events
+        .iter()
+        .filter(|e| e.args[GRAPHICS_KIND_ARG] == kind)
+        .collect();
+    matching.sort_by(|a, b| (a.ts, &a.pid).partial_cmp(&(b.ts, &b.pid)).unwrap());
+    matching
+}
+
+// ==========================
+// Tests for GraphicsParser
+// ==========================
+
+#[test]
+fn test_vulkan_workloads_submits_and_labels() {
+    let events = parse_graphics(VULKAN_SQL);
+    assert!(events.iter().all(|e| e.cat == EventCategory::Graphics));
+
+    let workloads = of_kind(&events, GRAPHICS_WORKLOAD_KIND);
+    assert_eq!(workloads.len(), 3);
+    assert_eq!(workloads[0].name, "Command Buffer 0xa0");
+    assert_eq!(workloads[0].pid, "Device 0");
+    assert_eq!(workloads[0].tid, "Vulkan Queue 0x1000");
+    assert_eq!(workloads[1].pid, "Device 1");
+
+    // Only calls that submitted a workload are kept
+    let submits = of_kind(&events, GRAPHICS_SUBMIT_KIND);
+    assert_eq!(submits.len(), 3);
+    assert!(submits.iter().all(|e| e.name == "vkQueueSubmit"));
+    assert_eq!(submits[0].pid, "Process 1");
+    assert_eq!(submits[0].tid, "Thread 1");
+
+    let labels = of_kind(&events, GRAPHICS_LABEL_KIND);
+    assert_eq!(labels.len(), 2);
+    assert_eq!(labels[0].name, "Frame 1");
+    assert_eq!(labels[0].tid, "Vulkan Labels Thread 1");
+}
+
+#[test]
+fn test_labels_are_projected_onto_each_device() {
+    let events = parse_graphics(VULKAN_SQL);
+    let projected = of_kind(&events, GRAPHICS_GPU_LABEL_KIND);
+
+    let summary: Vec<(&str, &str, f64, Option<f64>, i64)> = projected
+        .iter()
+        .map(|e| {
+            (
+                e.name.as_str(),
+                e.pid.as_str(),
+                e.ts,
+                e.dur,
+                e.args["workloads"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("Frame 1", "Device 0", 2.0, Some(0.5), 1),
+            ("Shadows", "Device 0", 2.0, Some(0.5), 1),
+            ("Frame 1", "Device 1", 4.0, Some(0.8), 1),
+        ]
+    );
+    assert!(projected.iter().all(|e| e.tid == "Vulkan Labels"));
+}
+
+#[test]
+fn test_link_debug_labels_ignores_other_threads_and_kinds() {
+    let events = parse_graphics(VULKAN_SQL);
+    let labels = of_kind(&events, GRAPHICS_LABEL_KIND);
+    let labels: Vec<ChromeTraceEvent> = labels.into_iter().cloned().collect();
+
+    // Without submits nothing links
+    assert!(link_debug_labels(&labels, &[], &events).is_empty());
+    // Passing every event as each input picks out the right kinds
+    let projected = link_debug_labels(&events, &events, &events);
+    assert_eq!(projected.len(), 3);
+    // The submit from thread 2 is outside every label
+    assert!(projected.iter().all(|e| e.args["end_ns"] != 5200));
+}
+
+#[test]
+fn test_dx12_capture_without_labels() {
+    let sql = "
+        CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+        INSERT INTO StringIds VALUES (1, 'ExecuteCommandLists');
+        CREATE TABLE DX12_API (
+            start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+        );
+        INSERT INTO DX12_API VALUES (100, 200, 33554433, 7, 1);
+        CREATE TABLE DX12_WORKLOAD (
+            start INTEGER, end INTEGER, gpu INTEGER, commandQueue INTEGER,
+            commandList INTEGER, correlationId INTEGER
+        );
+        INSERT INTO DX12_WORKLOAD VALUES (300, 900, 0, 1, 2, 7);
+    ";
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(sql).unwrap();
+    let probe = SchemaProbe::probe(&conn).unwrap();
+    assert_eq!(probe.table_for("graphics"), Some("DX12_WORKLOAD"));
+    assert!(probe.unknown_tables.is_empty());
+
+    let events = parse_graphics(sql);
+    assert_eq!(events.len(), 2);
+    let workloads = of_kind(&events, GRAPHICS_WORKLOAD_KIND);
+    assert_eq!(workloads[0].name, "Command List 0x2");
+    assert_eq!(workloads[0].tid, "DX12 Queue 0x1");
+    assert!(!workloads[0].args.contains_key("raw_tid"));
+    assert_eq!(of_kind(&events, GRAPHICS_SUBMIT_KIND)[0].pid, "Process 2");
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_extracts_graphics_when_requested() {
+    let convert = |types: Vec<EventCategory>| {
+        let options = ConversionOptions {
+            activity_types: types,
+            include_metadata: false,
+            ..Default::default()
+        };
+        convert_sql(VULKAN_SQL, options)
+    };
+    let events = convert(vec![EventCategory::Graphics]);
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| e.cat == EventCategory::Graphics)
+            .count(),
+        11
+    );
+    assert!(convert(vec![EventCategory::Kernel]).is_empty());
+}
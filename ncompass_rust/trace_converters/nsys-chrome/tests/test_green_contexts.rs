@@ -0,0 +1,87 @@
+//! Unit tests for green context tracks of kernels
+
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::parsers::{green_context_track, CUPTIKernelParser, EventParser, ParseContext};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+const KERNEL_COLUMNS: &str = "
+    start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+    correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+    gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+    blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+    registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER";
+
+/// Parse one kernel on stream 7 per entry of `green_contexts` (NULL for `None`),
+/// stored in `column` if given
+fn parse_kernels(column: Option<&str>, green_contexts: &[Option<i64>]) -> Vec<ChromeTraceEvent> {
+    let conn = Connection::open_in_memory().unwrap();
+    let extra_column = column.map_or(String::new(), |c| format!(", {} INTEGER", c));
+    conn.execute_batch(&format!(
+        "CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL ({}{});",
+        KERNEL_COLUMNS, extra_column
+    ))
+    .unwrap();
+    for (i, green_context) in green_contexts.iter().enumerate() {
+        let start = 1000 * (i as i64 + 1);
+        let extra_value = match (column, green_context) {
+            (None, _) => String::new(),
+            (Some(_), Some(id)) => format!(", {}", id),
+            (Some(_), None) => ", NULL".to_string(),
+        };
+        conn.execute(
+            &format!(
+                "INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+                    (?1, ?2, 0, 7, ?3, 16777216, 1, 1, 1, 1, 32, 1, 1, 32, 0, 0{})",
+                extra_value
+            ),
+            (start, start + 500, i as i64),
+        )
+        .unwrap();
+    }
+
+    let strings = HashMap::from([(1, "gemm".to_string())]);
+    let options = ConversionOptions::default();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names);
+    CUPTIKernelParser.safe_parse(&context).unwrap()
+}
+
+// ==========================
+// Tests for CUPTIKernelParser
+// ==========================
+
+#[test]
+fn test_kernels_in_green_contexts_get_own_tracks() {
+    let kernels = parse_kernels(Some("greenContextId"), &[Some(3), Some(5), None, Some(0)]);
+
+    let tids: Vec<&str> = kernels.iter().map(|e| &*e.tid).collect();
+    assert_eq!(
+        tids,
+        vec![
+            "Green Context 3 / Stream 7",
+            "Green Context 5 / Stream 7",
+            "Stream 7",
+            "Stream 7",
+        ]
+    );
+    assert!(kernels.iter().all(|e| e.pid == "Device 0"));
+    assert_eq!(kernels[0].args["greenContextId"], 3);
+    assert!(!kernels[2].args.contains_key("greenContextId"));
+    assert!(!kernels[3].args.contains_key("greenContextId"));
+}
+
+#[test]
+fn test_green_context_column_names() {
+    let kernels = parse_kernels(Some("greenContext"), &[Some(2)]);
+    assert_eq!(kernels[0].tid, green_context_track(2, 7));
+
+    // Older exports without the column keep plain stream tracks
+    let kernels = parse_kernels(None, &[None, None]);
+    assert!(kernels.iter().all(|e| e.tid == "Stream 7"));
+}
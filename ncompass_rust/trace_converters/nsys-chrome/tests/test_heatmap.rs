@@ -0,0 +1,109 @@
+//! Unit tests for the kernel heatmap exporter
+
+use nsys_chrome::analysis::kernel_heatmap;
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::ConvertError;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Event on `stream` of Device 0, times in microseconds
+fn create_event(ts: f64, dur: f64, stream: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "kernel".to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        stream.to_string(),
+        cat.to_string(),
+    )
+}
+
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        // Spans the first two 10us bins
+        create_event(100.0, 15.0, "Stream 7", "kernel"),
+        // Overlaps the first kernel; only 2us are new
+        create_event(110.0, 7.0, "Stream 7", "kernel"),
+        create_event(125.0, 5.0, "Stream 9", "kernel"),
+        create_event(100.0, 30.0, "CUDA API Thread 1", "cuda_api"),
+    ]
+}
+
+// ==========================
+// Tests for kernel_heatmap
+// ==========================
+
+#[test]
+fn test_heatmap_splits_time_across_bins() {
+    let heatmap = kernel_heatmap(&sample_events(), 10_000).unwrap();
+    assert_eq!(heatmap.start_ns, 100_000);
+    assert_eq!(heatmap.bins, 3);
+    assert_eq!(heatmap.rows.len(), 2);
+
+    let stream7 = &heatmap.rows[0];
+    assert_eq!(stream7.device, "Device 0");
+    assert_eq!(stream7.stream, "Stream 7");
+    assert_eq!(stream7.busy_ns, vec![10_000, 7_000, 0]);
+    assert_eq!(heatmap.rows[1].busy_ns, vec![0, 0, 5_000]);
+    assert_eq!(heatmap.utilization(stream7, 1), 0.7);
+}
+
+#[test]
+fn test_heatmap_without_kernels() {
+    let events = vec![create_event(0.0, 10.0, "CUDA API Thread 1", "cuda_api")];
+    let heatmap = kernel_heatmap(&events, 10_000).unwrap();
+    assert_eq!(heatmap.bins, 0);
+    assert!(heatmap.rows.is_empty());
+
+    assert!(matches!(
+        kernel_heatmap(&events, 0),
+        Err(ConvertError::InvalidOption(_))
+    ));
+}
+
+// ==========================
+// Tests for Heatmap output
+// ==========================
+
+#[test]
+fn test_heatmap_writes_csv_matrix() {
+    let heatmap = kernel_heatmap(&sample_events(), 10_000).unwrap();
+    let mut csv = Vec::new();
+    heatmap.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "device,stream,0,0.01,0.02");
+    assert_eq!(lines[1], "Device 0,Stream 7,1.000,0.700,0.000");
+    assert_eq!(lines[2], "Device 0,Stream 9,0.000,0.000,0.500");
+}
+
+#[test]
+fn test_heatmap_write_by_extension() {
+    let dir = TempDir::new().unwrap();
+    let heatmap = kernel_heatmap(&sample_events(), 10_000).unwrap();
+
+    let json_path = dir.path().join("heatmap.json");
+    heatmap.write(json_path.to_str().unwrap()).unwrap();
+    let root: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+    assert_eq!(root["bin_ns"], 10_000);
+    assert_eq!(
+        root["rows"][0]["busy_ns"],
+        serde_json::json!([10_000, 7_000, 0])
+    );
+
+    let csv_path = dir.path().join("heatmap.csv");
+    heatmap.write(csv_path.to_str().unwrap()).unwrap();
+    assert!(std::fs::read_to_string(&csv_path)
+        .unwrap()
+        .starts_with("device,stream"));
+
+    let txt_path = dir.path().join("heatmap.txt");
+    assert!(matches!(
+        heatmap.write(txt_path.to_str().unwrap()),
+        Err(ConvertError::InvalidOption(_))
+    ));
+}
@@ -0,0 +1,121 @@
+//! Unit tests for intern module
+
+use nsys_chrome::intern::{interned_count, InternedStr, StringInterner};
+use nsys_chrome::models::ChromeTraceEvent;
+
+// ==========================
+// Tests for InternedStr
+// ==========================
+
+#[test]
+fn test_interned_str_shares_allocation() {
+    let a = InternedStr::new("Device 0");
+    let b = InternedStr::from("Device 0".to_string());
+    assert!(a.ptr_eq(&b));
+    assert_eq!(a, b);
+}
+
+#[test]
+fn test_interned_str_distinct_values() {
+    let a = InternedStr::new("Stream 1");
+    let b = InternedStr::new("Stream 2");
+    assert!(!a.ptr_eq(&b));
+    assert_ne!(a, b);
+}
+
+#[test]
+fn test_interned_str_pool_does_not_grow_for_repeats() {
+    let _ = InternedStr::new("intern-test-repeat");
+    let count = interned_count();
+    for _ in 0..100 {
+        let _ = InternedStr::new("intern-test-repeat");
+    }
+    assert!(interned_count() <= count + 1);
+}
+
+#[test]
+fn test_interned_str_compares_with_strings() {
+    let value = InternedStr::new("kernel");
+    assert_eq!(value, "kernel");
+    assert_eq!(value, "kernel".to_string());
+    assert_eq!("kernel", value);
+    assert_eq!(value.as_str(), "kernel");
+    assert!(value.starts_with("ker"));
+    assert_eq!(format!("{}", value), "kernel");
+    assert_eq!(format!("{:?}", value), "\"kernel\"");
+}
+
+#[test]
+fn test_interned_str_serializes_as_string() {
+    let value = InternedStr::new("Stream 7");
+    assert_eq!(serde_json::to_string(&value).unwrap(), "\"Stream 7\"");
+}
+
+// ==========================
+// Tests for StringInterner
+// ==========================
+
+#[test]
+fn test_interner_shares_allocation_across_threads() {
+    let interner = StringInterner::new();
+    let handles: Vec<InternedStr> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..4)
+            .map(|_| scope.spawn(|| interner.intern("Device 0")))
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+    assert!(handles.iter().all(|h| h.ptr_eq(&handles[0])));
+    assert_eq!(interner.len(), 1);
+}
+
+#[test]
+fn test_interner_purge_drops_unreferenced_strings() {
+    let interner = StringInterner::new();
+    let kept = interner.intern("Stream 7");
+    let _ = interner.intern("Stream 8");
+    assert_eq!(interner.len(), 2);
+
+    assert_eq!(interner.purge(), 1);
+    assert_eq!(interner.len(), 1);
+    // A string still in use keeps its allocation
+    assert!(interner.intern("Stream 7").ptr_eq(&kept));
+    assert!(!interner.is_empty());
+}
+
+// ==========================
+// Tests for event fields
+// ==========================
+
+#[test]
+fn test_events_share_track_strings() {
+    let first = ChromeTraceEvent::complete(
+        "a".to_string(),
+        0.0,
+        1.0,
+        "Device 3".to_string(),
+        "Stream 14".to_string(),
+        "kernel".to_string(),
+    );
+    let second = ChromeTraceEvent::complete(
+        "b".to_string(),
+        1.0,
+        1.0,
+        format!("Device {}", 3),
+        format!("Stream {}", 14),
+        "kernel".to_string(),
+    );
+
+    assert!(first.pid.ptr_eq(&second.pid));
+    assert!(first.tid.ptr_eq(&second.tid));
+    assert!(first.cat.ptr_eq(&second.cat));
+
+    let first = first.with_arg("correlationId", 1);
+    let second = second.with_arg("correlationId".to_string(), 2);
+    let (first_key, _) = first.args.get_key_value("correlationId").unwrap();
+    let (second_key, _) = second.args.get_key_value("correlationId").unwrap();
+    assert!(first_key.ptr_eq(second_key));
+
+    let json = serde_json::to_value(&second).unwrap();
+    assert_eq!(json["pid"], "Device 3");
+    assert_eq!(json["tid"], "Stream 14");
+}
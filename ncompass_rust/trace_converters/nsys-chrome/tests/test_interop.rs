@@ -0,0 +1,172 @@
+//! Unit tests for the protobuf event exchange format
+
+use nsys_chrome::interop::{
+    decode_event_pb, encode_event_pb, export_events_pb, import_events_pb, PbEventReader,
+};
+use nsys_chrome::models::{
+    BindingPoint, ChromeTraceEvent, ChromeTracePhase, InstantScope, StringOrInt,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// One event of each shape the converter writes, with every kind of arg
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    let kernel = ChromeTraceEvent::complete(
+        "gemm".to_string(),
+        12.5,
+        3.25,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("start_ns", 12_500)
+    .with_arg("negative", -42)
+    .with_arg("ratio", 0.75)
+    .with_arg("huge", u64::MAX)
+    .with_arg("stream_inferred", true)
+    .with_arg("mangled", "_Z4gemmv")
+    .with_arg("grid", json!([4, 2, 1]))
+    .with_arg("shape", json!({"m": 128}))
+    .with_arg("missing", Value::Null)
+    .with_color("good".to_string());
+
+    let mut flow = ChromeTraceEvent::new(
+        String::new(),
+        ChromeTracePhase::FlowFinish,
+        13.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "cuda_flow".to_string(),
+    );
+    flow.id = Some(StringOrInt::Int(7));
+    flow.bp = Some(BindingPoint::Enclosing);
+
+    let mut bound = kernel.clone();
+    bound.bind_id = Some(StringOrInt::String("launch-7".to_string()));
+    bound.flow_in = Some(true);
+    bound.flow_out = Some(false);
+
+    let mut marker = ChromeTraceEvent::new(
+        "dropped".to_string(),
+        ChromeTracePhase::Instant,
+        0.0,
+        "1".to_string(),
+        "2".to_string(),
+        "dropped".to_string(),
+    );
+    marker.s = Some(InstantScope::Global);
+
+    let metadata = ChromeTraceEvent::metadata(
+        "process_name".to_string(),
+        "Device 0".to_string(),
+        String::new(),
+        HashMap::from([("name".to_string(), json!("Device 0"))]),
+    );
+    vec![kernel, flow, bound, marker, metadata]
+}
+
+fn as_json(events: &[ChromeTraceEvent]) -> Vec<Value> {
+    events
+        .iter()
+        .map(|e| serde_json::to_value(e).unwrap())
+        .collect()
+}
+
+// ==========================
+// Tests for export_events_pb / import_events_pb
+// ==========================
+
+#[test]
+fn test_round_trip_preserves_every_field() {
+    let events = sample_events();
+    let mut stream = Vec::new();
+    export_events_pb(&events, &mut stream).unwrap();
+
+    let imported = import_events_pb(stream.as_slice()).unwrap();
+    assert_eq!(as_json(&imported), as_json(&events));
+    assert_eq!(imported[0].args["huge"], json!(u64::MAX));
+    assert!(import_events_pb(&[][..]).unwrap().is_empty());
+}
+
+#[test]
+fn test_encoding_matches_protobuf_wire_format() {
+    let event = ChromeTraceEvent::complete(
+        "k".to_string(),
+        1.5,
+        2.0,
+        "p".to_string(),
+        String::new(),
+        "kernel".to_string(),
+    )
+    .with_arg("n", -1);
+
+    let mut expected = vec![0x0a, 1, b'k', 0x12, 1, b'X', 0x19];
+    expected.extend(1.5f64.to_le_bytes());
+    expected.extend([0x22, 1, b'p', 0x32, 6]);
+    expected.extend(b"kernel");
+    // args entry {key: "n", value: {int_value: -1}}, -1 zigzags to 1
+    expected.extend([0x3a, 7, 0x0a, 1, b'n', 0x12, 2, 0x18, 1, 0x41]);
+    expected.extend(2.0f64.to_le_bytes());
+    assert_eq!(encode_event_pb(&event), expected);
+}
+
+#[test]
+fn test_reader_streams_and_rejects_damage() {
+    let events = sample_events();
+    let mut stream = Vec::new();
+    export_events_pb(&events, &mut stream).unwrap();
+
+    let mut reader = PbEventReader::new(stream.as_slice());
+    assert_eq!(reader.next().unwrap().unwrap().name, "gemm");
+    assert_eq!(reader.count(), events.len() - 1);
+
+    let truncated = import_events_pb(&stream[..stream.len() - 3]);
+    assert!(truncated
+        .unwrap_err()
+        .to_string()
+        .contains("ends inside a message"));
+
+    // Unknown fields are skipped; a message without a phase is rejected
+    let mut message = encode_event_pb(&events[0]);
+    message.extend([0xa8, 0x06, 1]);
+    assert_eq!(decode_event_pb(&message).unwrap().name, "gemm");
+    assert!(decode_event_pb(&[0x0a, 1, b'k']).is_err());
+}
+
+#[test]
+fn test_proto_schema_declares_the_encoded_fields() {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let proto =
+        std::fs::read_to_string(format!("{}/proto/chrome_trace_event.proto", root)).unwrap();
+    for field in [
+        "string name = 1;",
+        "string ph = 2;",
+        "double ts = 3;",
+        "string pid = 4;",
+        "string tid = 5;",
+        "string cat = 6;",
+        "map<string, ArgValue> args = 7;",
+        "optional double dur = 8;",
+        "optional string cname = 9;",
+        "string id_str = 10;",
+        "int64 id_int = 11;",
+        "optional string bp = 12;",
+        "string bind_id_str = 13;",
+        "int64 bind_id_int = 14;",
+        "optional bool flow_in = 15;",
+        "optional bool flow_out = 16;",
+        "optional string s = 17;",
+        "bool null_value = 1;",
+        "bool bool_value = 2;",
+        "sint64 int_value = 3;",
+        "double double_value = 4;",
+        "string string_value = 5;",
+        "string json_value = 6;",
+    ] {
+        assert!(proto.contains(field), "{} not declared", field);
+    }
+}
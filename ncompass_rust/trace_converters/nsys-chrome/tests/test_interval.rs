@@ -0,0 +1,134 @@
+//! Unit tests for the generic interval algorithms
+
+use nsys_chrome::linker::{
+    correlated_events, correlation_map, covered_time, merged_ranges, overlapping_intervals,
+    overlapping_intervals_by_thread, time_span, HasCorrelation, HasTimeRange,
+};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// A tool's own event struct, unrelated to Chrome trace events
+#[derive(Debug, PartialEq)]
+struct Span {
+    name: &'static str,
+    start: i64,
+    end: i64,
+    thread: Option<i64>,
+    correlation: Option<i64>,
+}
+
+impl HasTimeRange for Span {
+    fn time_range_ns(&self) -> Option<(i64, i64)> {
+        Some((self.start, self.end))
+    }
+
+    fn thread_id(&self) -> Option<i64> {
+        self.thread
+    }
+}
+
+impl HasCorrelation for Span {
+    fn correlation_id(&self) -> Option<i64> {
+        self.correlation
+    }
+}
+
+fn span(name: &'static str, start: i64, end: i64) -> Span {
+    Span {
+        name,
+        start,
+        end,
+        thread: None,
+        correlation: None,
+    }
+}
+
+fn on_thread(mut span: Span, thread: i64) -> Span {
+    span.thread = Some(thread);
+    span
+}
+
+fn correlated(mut span: Span, correlation: i64) -> Span {
+    span.correlation = Some(correlation);
+    span
+}
+
+// ==========================
+// Tests for overlap detection
+// ==========================
+
+#[test]
+fn test_overlaps_between_different_types() {
+    // Sources are plain (start, end) pairs, targets a custom struct
+    let ranges = [(0, 100), (200, 300)];
+    let calls = [span("a", 10, 20), span("b", 100, 150), span("c", 250, 260)];
+
+    let result =
+        overlapping_intervals(&[&ranges[0], &ranges[1]], &calls.iter().collect::<Vec<_>>());
+    let names = |idx: usize| result[&idx].iter().map(|s| s.name).collect::<Vec<_>>();
+    // Touching intervals count as overlapping
+    assert_eq!(names(0), vec!["a", "b"]);
+    assert_eq!(names(1), vec!["c"]);
+}
+
+#[test]
+fn test_overlaps_by_thread() {
+    let ranges = [
+        on_thread(span("range1", 0, 100), 1),
+        on_thread(span("range2", 0, 100), 2),
+    ];
+    let calls = [
+        on_thread(span("call1", 10, 20), 1),
+        on_thread(span("call2", 30, 40), 2),
+        span("unthreaded", 50, 60),
+    ];
+
+    let result = overlapping_intervals_by_thread(
+        &ranges.iter().collect::<Vec<_>>(),
+        &calls.iter().collect::<Vec<_>>(),
+    );
+    let mut names: Vec<_> = result[&0].iter().map(|s| s.name).collect();
+    names.sort();
+    assert_eq!(names, vec!["call1", "unthreaded"]);
+    let mut names: Vec<_> = result[&1].iter().map(|s| s.name).collect();
+    names.sort();
+    assert_eq!(names, vec!["call2", "unthreaded"]);
+}
+
+// ==========================
+// Tests for correlation and coverage
+// ==========================
+
+#[test]
+fn test_correlation_through_custom_events() {
+    let kernels = [
+        correlated(span("k1", 100, 200), 1),
+        correlated(span("k2", 150, 300), 1),
+        correlated(span("k3", 400, 450), 2),
+        span("orphan", 0, 10),
+    ];
+    let map = correlation_map(&kernels.iter().collect::<Vec<_>>());
+    assert_eq!(map.len(), 2);
+    assert_eq!(map[&1].len(), 2);
+
+    let launches = [correlated(span("launch", 90, 95), 1), span("no id", 0, 1)];
+    let found = correlated_events(&launches.iter().collect::<Vec<_>>(), &map);
+    assert_eq!(
+        found.iter().map(|k| k.name).collect::<Vec<_>>(),
+        vec!["k1", "k2"]
+    );
+
+    assert_eq!(time_span(&found), Some((100, 300)));
+}
+
+#[test]
+fn test_merged_ranges_and_covered_time_on_pairs() {
+    let ranges = [(300, 400), (0, 100), (50, 150), (150, 200), (500, 450)];
+    let refs: Vec<_> = ranges.iter().collect();
+    // Inverted ranges are skipped; touching ones merge
+    assert_eq!(merged_ranges(&refs), vec![(0, 200), (300, 400)]);
+    assert_eq!(covered_time(&refs), 300);
+    assert_eq!(covered_time::<(i64, i64)>(&[]), 0);
+}
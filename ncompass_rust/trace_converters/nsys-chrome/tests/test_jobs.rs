@@ -0,0 +1,114 @@
+//! Unit tests for concurrent conversion with several jobs
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Two processes on devices 0 and 1, each launching two kernels inside an
+/// NVTX range, with kernels and ranges sharing timestamps across devices
+const TWO_DEVICE_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES
+        (1, 'cudaLaunchKernel'), (2, 'gemm_kernel'), (3, 'relu_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (3000, 4000, 0, 7, 1, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (4000, 5000, 0, 7, 2, 16777216, 3, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (3000, 4000, 1, 7, 1, 33554432, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (4000, 5000, 1, 7, 2, 33554432, 3, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+        start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES
+        (1100, 1200, 16777217, 1, 1),
+        (1300, 1400, 16777217, 2, 1),
+        (1100, 1200, 33554434, 1, 1),
+        (1300, 1400, 33554434, 2, 1);
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES
+        (1000, 2000, 'forward', NULL, 16777217, 59),
+        (1000, 2000, 'forward', NULL, 33554434, 59);
+";
+
+fn convert(jobs: usize) -> Vec<ChromeTraceEvent> {
+    let options = ConversionOptions {
+        activity_types: vec![
+            EventCategory::Kernel,
+            EventCategory::CudaApi,
+            EventCategory::Nvtx,
+            EventCategory::NvtxKernel,
+        ],
+        jobs,
+        ..Default::default()
+    };
+    convert_sql(TWO_DEVICE_SQL, options)
+}
+
+// ==========================
+// Tests for ConversionOptions::worker_threads
+// ==========================
+
+#[test]
+fn test_worker_threads_resolution() {
+    let options = ConversionOptions::default();
+    assert_eq!(options.jobs, 1);
+    assert_eq!(options.worker_threads(), 1);
+
+    let options = ConversionOptions {
+        jobs: 4,
+        ..Default::default()
+    };
+    assert_eq!(options.worker_threads(), 4);
+
+    let options = ConversionOptions {
+        jobs: 0,
+        ..Default::default()
+    };
+    assert!(options.worker_threads() >= 1);
+}
+
+// ==========================
+// Tests for concurrent conversion
+// ==========================
+
+#[test]
+fn test_jobs_link_every_device() {
+    let events = convert(4);
+
+    let mut linked: Vec<&str> = events
+        .iter()
+        .filter(|e| e.cat == "nvtx-kernel")
+        .map(|e| &*e.pid)
+        .collect();
+    linked.sort_unstable();
+    assert_eq!(linked, vec!["Device 0", "Device 1"]);
+    assert_eq!(events.iter().filter(|e| e.cat == "kernel").count(), 4);
+    assert_eq!(events.iter().filter(|e| e.cat == "cuda_api").count(), 4);
+}
+
+#[test]
+fn test_jobs_output_is_deterministic() {
+    let sequential = serde_json::to_value(convert(1)).unwrap();
+    for jobs in [0, 2, 4] {
+        for _ in 0..3 {
+            let concurrent = serde_json::to_value(convert(jobs)).unwrap();
+            assert_eq!(concurrent, sequential, "jobs = {}", jobs);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Unit tests for replacing oversized args
+
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::large_args::{parse_args_limit, LargeArgs};
+use nsys_chrome::models::ChromeTraceEvent;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_kernel(backtrace: Value) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "gemm".to_string(),
+        0.0,
+        1.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        EventCategory::Kernel,
+    )
+    .with_arg("backtrace", backtrace)
+    .with_arg("deviceId", json!(0))
+}
+
+fn long_backtrace() -> Value {
+    let frames: Vec<String> = (0..100)
+        .map(|i| format!("frame_{}() at model.py:{}", i, i))
+        .collect();
+    json!(frames)
+}
+
+/// Split a replacement into its preview, size and ID
+fn parse_replacement(value: &Value) -> (&str, usize, &str) {
+    let text = value.as_str().unwrap();
+    let (preview, rest) = text.split_once(" ... [").unwrap();
+    let (bytes, id) = rest
+        .trim_end_matches(']')
+        .split_once(" bytes, id ")
+        .unwrap();
+    (preview, bytes.parse().unwrap(), id)
+}
+
+// ==========================
+// Tests for compaction
+// ==========================
+
+#[test]
+fn test_truncating_replaces_only_large_args() {
+    let mut events = vec![
+        create_kernel(long_backtrace()),
+        create_kernel(json!(["main() at train.py:1"])),
+    ];
+    let mut large_args = LargeArgs::truncating(256);
+    assert_eq!(large_args.compact(&mut events), 1);
+
+    let (preview, bytes, id) = parse_replacement(&events[0].args["backtrace"]);
+    assert!(preview.starts_with(r#"["frame_0() at model.py:0""#));
+    assert_eq!(preview.chars().count(), 64);
+    assert_eq!(bytes, long_backtrace().to_string().len());
+    assert_eq!(id.len(), 16);
+    assert_eq!(events[0].args["deviceId"], 0);
+    assert_eq!(events[1].args["backtrace"], json!(["main() at train.py:1"]));
+    // Nothing is kept without a sidecar
+    assert!(large_args.is_empty());
+}
+
+#[test]
+fn test_identical_values_share_an_id() {
+    let mut events = vec![
+        create_kernel(long_backtrace()),
+        create_kernel(long_backtrace()),
+        create_kernel(json!("x".repeat(1000))),
+    ];
+    let mut large_args = LargeArgs::with_sidecar(256);
+    assert_eq!(large_args.compact(&mut events), 3);
+    assert_eq!(large_args.len(), 2);
+
+    let ids: Vec<&str> = events
+        .iter()
+        .map(|e| parse_replacement(&e.args["backtrace"]).2)
+        .collect();
+    assert_eq!(ids[0], ids[1]);
+    assert_ne!(ids[0], ids[2]);
+    assert_eq!(large_args.get(ids[0]), Some(&long_backtrace()));
+
+    // IDs depend only on the content, so a second run assigns the same ones
+    let mut again = vec![create_kernel(long_backtrace())];
+    LargeArgs::truncating(256).compact(&mut again);
+    assert_eq!(parse_replacement(&again[0].args["backtrace"]).2, ids[0]);
+}
+
+#[test]
+fn test_metadata_args_are_kept_whole() {
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), json!("x".repeat(1000)));
+    let mut events = vec![ChromeTraceEvent::metadata(
+        "process_name".to_string(),
+        "Device 0".to_string(),
+        String::new(),
+        args,
+    )];
+    assert_eq!(LargeArgs::truncating(16).compact(&mut events), 0);
+    assert_eq!(events[0].args["name"].as_str().unwrap().len(), 1000);
+}
+
+// ==========================
+// Tests for the sidecar and options
+// ==========================
+
+#[test]
+fn test_sidecar_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("args.json");
+    let path = path.to_str().unwrap();
+
+    let mut events = vec![create_kernel(long_backtrace())];
+    let mut large_args = LargeArgs::with_sidecar(256);
+    large_args.compact(&mut events);
+    large_args.save(path).unwrap();
+
+    let (_, _, id) = parse_replacement(&events[0].args["backtrace"]);
+    let loaded = LargeArgs::load(path).unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded.get(id), Some(&long_backtrace()));
+
+    std::fs::write(path, r#"{"names": []}"#).unwrap();
+    assert!(LargeArgs::load(path).is_err());
+}
+
+#[test]
+fn test_parse_args_limit() {
+    assert_eq!(parse_args_limit("4096").unwrap(), 4096);
+    assert_eq!(parse_args_limit("4k").unwrap(), 4096);
+    assert_eq!(parse_args_limit("2M").unwrap(), 2 << 20);
+    for value in ["0", "", "k", "-1", "4kb"] {
+        assert!(parse_args_limit(value).is_err(), "{}", value);
+    }
+}
@@ -0,0 +1,122 @@
+//! Unit tests for link policies, link confidence and ambiguity counts
+
+mod common;
+
+use common::create_event;
+use nsys_chrome::linker::{link_nvtx_to_kernels_with_stats, parse_link_policy, LinkStats};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions, LinkPolicy};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Link the ranges to one launch over [launch_start, launch_end)
+fn link(
+    ranges: &[(&str, i64, i64)],
+    launch: (i64, i64),
+    policy: LinkPolicy,
+) -> (Vec<(String, f64)>, LinkStats) {
+    let nvtx: Vec<ChromeTraceEvent> = ranges
+        .iter()
+        .map(|&(name, start, end)| create_event(name, "nvtx", start, end, None))
+        .collect();
+    let api = vec![create_event(
+        "cudaLaunchKernel",
+        "cuda_api",
+        launch.0,
+        launch.1,
+        Some(1),
+    )];
+    let kernels = vec![create_event("gemm", "kernel", 5000, 6000, Some(1))];
+    let options = ConversionOptions {
+        link_policy: policy,
+        ..Default::default()
+    };
+
+    let ((linked, _, _), stats) = link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, &options);
+    let linked = linked
+        .iter()
+        .map(|e| (e.name.clone(), e.args["link_confidence"].as_f64().unwrap()))
+        .collect();
+    (linked, stats)
+}
+
+// ==========================
+// Tests for parse_link_policy
+// ==========================
+
+#[test]
+fn test_parse_link_policy() {
+    assert_eq!(parse_link_policy("all").unwrap(), LinkPolicy::All);
+    assert_eq!(
+        parse_link_policy("innermost").unwrap(),
+        LinkPolicy::Innermost
+    );
+    assert_eq!(
+        parse_link_policy("longest-overlap").unwrap(),
+        LinkPolicy::LongestOverlap
+    );
+    assert!(parse_link_policy("outermost").is_err());
+}
+
+// ==========================
+// Tests for link policies
+// ==========================
+
+#[test]
+fn test_nested_ranges_follow_policy() {
+    let ranges = [("step", 0, 4000), ("forward", 1000, 2000)];
+
+    let (linked, stats) = link(&ranges, (1200, 1300), LinkPolicy::All);
+    assert_eq!(
+        linked,
+        vec![("step".to_string(), 1.0), ("forward".to_string(), 1.0)]
+    );
+    assert_eq!(
+        stats,
+        LinkStats {
+            shared_calls: 1,
+            ambiguous_calls: 0,
+            distant_kernels: 0,
+            attributed_kernel_ns: 1000,
+            sticky_calls: 0,
+        }
+    );
+
+    let (linked, _) = link(&ranges, (1200, 1300), LinkPolicy::Innermost);
+    assert_eq!(linked, vec![("forward".to_string(), 1.0)]);
+
+    // The outer range covers all of a call straddling the inner one's end
+    let (linked, _) = link(&ranges, (1900, 2300), LinkPolicy::LongestOverlap);
+    assert_eq!(linked, vec![("step".to_string(), 1.0)]);
+    let (linked, _) = link(&ranges, (1900, 2300), LinkPolicy::Innermost);
+    assert_eq!(linked, vec![("forward".to_string(), 0.25)]);
+}
+
+#[test]
+fn test_identical_ranges_are_ambiguous() {
+    let ranges = [("a", 0, 1000), ("b", 0, 1000)];
+
+    let (linked, stats) = link(&ranges, (100, 200), LinkPolicy::All);
+    assert_eq!(linked, vec![("a".to_string(), 1.0), ("b".to_string(), 1.0)]);
+    assert_eq!(stats.ambiguous_calls, 1);
+
+    // One range receives the call, with the confidence shared between the tied ones
+    let (linked, stats) = link(&ranges, (100, 200), LinkPolicy::LongestOverlap);
+    assert_eq!(linked, vec![("a".to_string(), 0.5)]);
+    assert_eq!(stats.ambiguous_calls, 1);
+}
+
+#[test]
+fn test_partial_overlap_lowers_confidence() {
+    // Half of the call runs after the range ends
+    let (linked, stats) = link(&[("a", 0, 1000)], (900, 1100), LinkPolicy::All);
+    assert_eq!(linked, vec![("a".to_string(), 0.5)]);
+    assert_eq!(
+        stats,
+        LinkStats {
+            attributed_kernel_ns: 1000,
+            ..Default::default()
+        }
+    );
+}
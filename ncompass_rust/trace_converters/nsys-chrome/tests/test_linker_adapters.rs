@@ -1,7 +1,15 @@
 //! Integration tests for linker adapters module
 
-use nsys_chrome::linker::adapters::{EventAdapter, NsysEventAdapter};
-use nsys_chrome::models::ChromeTraceEvent;
+mod common;
+
+use common::numbered;
+use nsys_chrome::error::ConvertError;
+use nsys_chrome::frontends::FrontendTrace;
+use nsys_chrome::linker::adapters::{
+    EventAdapter, EventId, KinetoEventAdapter, MixedEventAdapter, NsysEventAdapter, TimeUnit,
+};
+use nsys_chrome::linker::find_overlapping_intervals;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase};
 use std::collections::HashMap;
 
 // ==========================
@@ -153,6 +161,7 @@ fn test_get_event_id_unique_per_instance() {
         "Stream 1".to_string(),
         "kernel".to_string(),
     );
+    let (event1, event2) = (numbered(event1), numbered(event2));
 
     let id1 = adapter.get_event_id(&event1);
     let id2 = adapter.get_event_id(&event2);
@@ -172,6 +181,7 @@ fn test_get_event_id_stable_for_same_reference() {
         "Stream 1".to_string(),
         "kernel".to_string(),
     );
+    let event = numbered(event);
 
     let id1 = adapter.get_event_id(&event);
     let id2 = adapter.get_event_id(&event);
@@ -180,6 +190,23 @@ fn test_get_event_id_stable_for_same_reference() {
     assert_eq!(id1, id2);
 }
 
+#[test]
+fn test_event_id_is_64_bit_on_every_target() {
+    let mut event = ChromeTraceEvent::complete(
+        "kernel".to_string(),
+        100.0,
+        50.0,
+        "Device 0".to_string(),
+        "Stream 1".to_string(),
+        "kernel".to_string(),
+    );
+    event.event_id = Some(EventId(7));
+
+    assert_eq!(std::mem::size_of::<EventId>(), 8);
+    assert_eq!(EventId::of(&event), NsysEventAdapter.get_event_id(&event));
+    assert_eq!(EventId::of(&event), KinetoEventAdapter.get_event_id(&event));
+}
+
 // ==========================
 // Negative Tests - Malformed Data Types
 // ==========================
@@ -430,10 +457,10 @@ fn test_get_time_range_metadata_phase() {
     // Add time args even though it's a metadata event
     event
         .args
-        .insert("start_ns".to_string(), serde_json::json!(100000));
+        .insert("start_ns".into(), serde_json::json!(100000));
     event
         .args
-        .insert("end_ns".to_string(), serde_json::json!(150000));
+        .insert("end_ns".into(), serde_json::json!(150000));
 
     let result = adapter.get_time_range(&event);
     // Metadata phase should return None regardless of time args
@@ -452,10 +479,10 @@ fn test_get_time_range_flow_start_phase() {
     );
     event
         .args
-        .insert("start_ns".to_string(), serde_json::json!(100000));
+        .insert("start_ns".into(), serde_json::json!(100000));
     event
         .args
-        .insert("end_ns".to_string(), serde_json::json!(150000));
+        .insert("end_ns".into(), serde_json::json!(150000));
 
     let result = adapter.get_time_range(&event);
     assert!(result.is_none());
@@ -474,10 +501,10 @@ fn test_get_time_range_flow_finish_phase() {
     );
     event
         .args
-        .insert("start_ns".to_string(), serde_json::json!(100000));
+        .insert("start_ns".into(), serde_json::json!(100000));
     event
         .args
-        .insert("end_ns".to_string(), serde_json::json!(150000));
+        .insert("end_ns".into(), serde_json::json!(150000));
 
     let result = adapter.get_time_range(&event);
     assert!(result.is_none());
@@ -592,7 +619,7 @@ fn test_get_correlation_id_i32_max() {
     .with_arg("correlationId", serde_json::json!(i32::MAX as i64));
 
     let result = adapter.get_correlation_id(&event);
-    assert_eq!(result, Some(i32::MAX));
+    assert_eq!(result, Some(i32::MAX as i64));
 }
 
 #[test]
@@ -610,7 +637,25 @@ fn test_get_correlation_id_i32_min() {
     .with_arg("correlationId", serde_json::json!(i32::MIN as i64));
 
     let result = adapter.get_correlation_id(&event);
-    assert_eq!(result, Some(i32::MIN));
+    assert_eq!(result, Some(i32::MIN as i64));
+}
+
+#[test]
+fn test_get_correlation_id_beyond_i32() {
+    // Long captures produce IDs past i32::MAX; they must not wrap
+    let adapter = NsysEventAdapter;
+    let event = ChromeTraceEvent::complete(
+        "kernel".to_string(),
+        100.0,
+        50.0,
+        "Device 0".to_string(),
+        "Stream 1".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("correlationId", serde_json::json!(i32::MAX as i64 + 1));
+
+    let result = adapter.get_correlation_id(&event);
+    assert_eq!(result, Some(i32::MAX as i64 + 1));
 }
 
 // ==========================
@@ -652,3 +697,193 @@ fn test_get_correlation_id_empty_args() {
     assert!(result.is_none());
 }
 
+
+// ==========================
+// Tests for time units
+// ==========================
+
+#[test]
+fn test_time_unit_conversion() {
+    assert_eq!(TimeUnit::Nanoseconds.to_ns(1500.4), 1500);
+    assert_eq!(TimeUnit::Microseconds.to_ns(1.5), 1500);
+    assert_eq!(
+        TimeUnit::Microseconds.value_to_ns(&serde_json::json!(1_700_000_000_000_001i64)),
+        Some(1_700_000_000_000_001_000)
+    );
+    assert_eq!(
+        TimeUnit::Microseconds.value_to_ns(&serde_json::json!(2.25)),
+        Some(2250)
+    );
+    assert_eq!(TimeUnit::Microseconds.value_to_ns(&serde_json::json!("1")), None);
+    assert_eq!(NsysEventAdapter.time_unit(), TimeUnit::Nanoseconds);
+    assert_eq!(KinetoEventAdapter.time_unit(), TimeUnit::Microseconds);
+}
+
+// ==========================
+// Tests for KinetoEventAdapter
+// ==========================
+
+#[test]
+fn test_kineto_time_range_normalized_to_ns() {
+    let adapter = KinetoEventAdapter;
+    let event = ChromeTraceEvent::complete(
+        "ampere_sgemm".to_string(),
+        100.5,
+        2.25,
+        "0".to_string(),
+        "7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("correlation", serde_json::json!(42));
+
+    assert_eq!(adapter.get_time_range_ns(&event), Some((100_500, 102_750)));
+    assert_eq!(adapter.get_correlation_id(&event), Some(42));
+    assert_eq!(adapter.get_thread_id(&event), Some(7));
+
+    let instant = ChromeTraceEvent::new(
+        "marker".to_string(),
+        ChromeTracePhase::Instant,
+        100.0,
+        "0".to_string(),
+        "stream 7".to_string(),
+        "user_annotation".to_string(),
+    );
+    assert_eq!(adapter.get_time_range_ns(&instant), None);
+    assert_eq!(adapter.get_thread_id(&instant), None);
+}
+
+#[test]
+fn test_mixed_adapter_links_across_sources() {
+    let adapter = MixedEventAdapter;
+    // nsys NVTX range over [100us, 200us)
+    let nvtx = ChromeTraceEvent::complete(
+        "forward".to_string(),
+        100.0,
+        100.0,
+        "Device 0".to_string(),
+        "NVTX Thread 1".to_string(),
+        "nvtx".to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(100_000))
+    .with_arg("end_ns", serde_json::json!(200_000));
+    // Kineto kernel inside it, and one after it
+    let inside = ChromeTraceEvent::complete(
+        "inside".to_string(),
+        150.0,
+        10.0,
+        "0".to_string(),
+        "7".to_string(),
+        "kernel".to_string(),
+    );
+    let after = ChromeTraceEvent::complete(
+        "after".to_string(),
+        250.0,
+        10.0,
+        "0".to_string(),
+        "7".to_string(),
+        "kernel".to_string(),
+    );
+
+    assert_eq!(adapter.get_time_range_ns(&nvtx), Some((100_000, 200_000)));
+    assert_eq!(adapter.get_time_range_ns(&inside), Some((150_000, 160_000)));
+
+    let (nvtx, inside, after) = (numbered(nvtx), numbered(inside), numbered(after));
+    let overlaps = find_overlapping_intervals(&[&nvtx], &[&inside, &after], &adapter).unwrap();
+    let found = &overlaps[&adapter.get_event_id(&nvtx)];
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].name, "inside");
+}
+
+// ==========================
+// Tests for EventId
+// ==========================
+
+fn create_range(name: &str, start_ns: i64, end_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Process 1".to_string(),
+        "Thread 1".to_string(),
+        "nvtx".to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(start_ns))
+    .with_arg("end_ns", serde_json::json!(end_ns))
+}
+
+#[test]
+fn test_assigned_event_id_survives_moves_and_clones() {
+    let mut event = create_range("forward", 0, 100);
+    assert_eq!(event.event_id, None);
+
+    event.event_id = Some(EventId(42));
+    let moved = [event];
+    let cloned = moved[0].clone();
+    assert_eq!(EventId::of(&moved[0]), EventId(42));
+    assert_eq!(NsysEventAdapter.get_event_id(&cloned), EventId(42));
+
+    // The ID is for linking only and not written to the trace
+    let json = serde_json::to_value(&cloned).unwrap();
+    assert!(json.get("event_id").is_none());
+}
+
+#[test]
+fn test_frontend_trace_assigns_sequential_ids() {
+    let mut trace = FrontendTrace {
+        kernel_events: vec![create_range("k0", 0, 10), create_range("k1", 10, 20)],
+        annotation_events: vec![create_range("step", 0, 20)],
+        ..Default::default()
+    };
+    assert_eq!(trace.assign_event_ids(), 3);
+    let ids: Vec<Option<EventId>> = trace
+        .kernel_events
+        .iter()
+        .chain(&trace.annotation_events)
+        .map(|e| e.event_id)
+        .collect();
+    assert_eq!(ids, vec![Some(EventId(0)), Some(EventId(1)), Some(EventId(2))]);
+
+    // A later pass numbers only its new events, after the existing ones
+    trace.other_events.push(create_range("gap", 20, 30));
+    assert_eq!(trace.assign_event_ids(), 1);
+    assert_eq!(trace.other_events[0].event_id, Some(EventId(3)));
+    assert_eq!(trace.kernel_events[0].event_id, Some(EventId(0)));
+
+    // A copy added alongside its original gets an ID of its own
+    let copy = trace.kernel_events[1].clone();
+    trace.other_events.push(copy);
+    assert_eq!(trace.assign_event_ids(), 1);
+    assert_eq!(trace.other_events[1].event_id, Some(EventId(4)));
+    assert_eq!(trace.kernel_events[1].event_id, Some(EventId(1)));
+}
+
+#[test]
+fn test_overlaps_of_unnumbered_events_are_an_error() {
+    let source = create_range("forward", 0, 100);
+    let target = create_range("inside", 10, 20);
+    let result = find_overlapping_intervals(&[&source], &[&target], &NsysEventAdapter);
+    assert!(matches!(result, Err(ConvertError::InvalidInput(_))));
+}
+
+#[test]
+fn test_overlap_keys_stay_valid_after_events_move() {
+    let mut trace = FrontendTrace {
+        kernel_events: vec![create_range("inside", 10, 20), create_range("after", 200, 210)],
+        annotation_events: vec![create_range("forward", 0, 100)],
+        ..Default::default()
+    };
+    trace.assign_event_ids();
+    let adapter = NsysEventAdapter;
+
+    let sources: Vec<&ChromeTraceEvent> = trace.annotation_events.iter().collect();
+    let targets: Vec<&ChromeTraceEvent> = trace.kernel_events.iter().collect();
+    let overlaps = find_overlapping_intervals(&sources, &targets, &adapter).unwrap();
+    let key = *overlaps.keys().next().unwrap();
+
+    // A later pass working on moved copies finds the same entry
+    let mut copies = trace.annotation_events.clone();
+    copies.reserve(1000);
+    let moved = copies.remove(0);
+    assert_eq!(adapter.get_event_id(&moved), key);
+    assert_eq!(overlaps[&adapter.get_event_id(&moved)][0].name, "inside");
+}
@@ -0,0 +1,84 @@
+//! Unit tests for log formats and phase logging
+
+use nsys_chrome::logging::{json_record, parse_log_format, LogFormat, PHASE_TARGET};
+use nsys_chrome::models::ChromeTracePhase;
+use nsys_chrome::self_profile::SelfProfiler;
+
+// ==========================
+// Tests for log formats
+// ==========================
+
+#[test]
+fn test_parse_log_format() {
+    assert_eq!(parse_log_format("text").unwrap(), LogFormat::Text);
+    assert_eq!(parse_log_format(" json ").unwrap(), LogFormat::Json);
+    assert_eq!(LogFormat::default(), LogFormat::Text);
+    assert!(parse_log_format("xml").is_err());
+}
+
+#[test]
+fn test_json_record_keeps_typed_fields() {
+    let kvs: [(&str, log::kv::Value); 4] = [
+        ("phase", log::kv::Value::from("sort")),
+        ("events", log::kv::Value::from(42u64)),
+        ("duration_ms", log::kv::Value::from(1.5f64)),
+        ("cached", log::kv::Value::from(true)),
+    ];
+    let value = json_record(
+        &log::Record::builder()
+            .args(format_args!("sort took {} ms", 1.5))
+            .level(log::Level::Info)
+            .target(PHASE_TARGET)
+            .key_values(&kvs)
+            .build(),
+        "2026-01-01T00:00:00.000Z",
+    );
+
+    assert_eq!(value["level"], "INFO");
+    assert_eq!(value["target"], PHASE_TARGET);
+    assert_eq!(value["message"], "sort took 1.5 ms");
+    assert_eq!(value["timestamp"], "2026-01-01T00:00:00.000Z");
+    assert_eq!(value["fields"]["phase"], "sort");
+    assert_eq!(value["fields"]["events"], 42);
+    assert_eq!(value["fields"]["duration_ms"], 1.5);
+    assert_eq!(value["fields"]["cached"], true);
+}
+
+#[test]
+fn test_json_record_without_fields() {
+    let value = json_record(
+        &log::Record::builder()
+            .args(format_args!("Reading database"))
+            .level(log::Level::Warn)
+            .target("nsys_chrome::status")
+            .build(),
+        "0",
+    );
+    assert_eq!(value["level"], "WARN");
+    assert!(value.get("fields").is_none());
+}
+
+// ==========================
+// Tests for phase event counts
+// ==========================
+
+#[test]
+fn test_phase_event_count_becomes_arg() {
+    let profiler = SelfProfiler::new();
+    {
+        let mut read = profiler.phase("read kernels", "read");
+        read.set_events(128);
+        let _write = profiler.phase("write", "write");
+    }
+
+    let events = profiler.events();
+    let phases: Vec<_> = events
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Complete)
+        .collect();
+    assert_eq!(phases.len(), 2);
+    let read = phases.iter().find(|e| e.name == "read kernels").unwrap();
+    let write = phases.iter().find(|e| e.name == "write").unwrap();
+    assert_eq!(read.args["events"], 128);
+    assert!(!write.args.contains_key("events"));
+}
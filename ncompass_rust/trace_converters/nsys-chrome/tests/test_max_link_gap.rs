@@ -0,0 +1,77 @@
+//! Unit tests for the maximum gap between a CUDA API call and its kernels
+
+mod common;
+
+use common::create_event;
+use nsys_chrome::diagnostics::ConversionDiagnostics;
+use nsys_chrome::linker::link_nvtx_to_kernels_with_stats;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use nsys_chrome::presets::overlay_options;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// One range over two launches; the second launch's kernel starts 5 ms after it
+fn link(max_link_gap_ns: Option<i64>) -> (Vec<ChromeTraceEvent>, Vec<ChromeTraceEvent>, usize) {
+    let nvtx = vec![create_event("forward", "nvtx", 0, 10_000, None)];
+    let api = vec![
+        create_event("cudaLaunchKernel", "cuda_api", 1000, 2000, Some(1)),
+        create_event("cudaLaunchKernel", "cuda_api", 3000, 4000, Some(2)),
+    ];
+    let kernels = vec![
+        create_event("gemm", "kernel", 5000, 6000, Some(1)),
+        create_event("stale", "kernel", 5_004_000, 5_005_000, Some(2)),
+    ];
+    let options = ConversionOptions {
+        max_link_gap_ns,
+        ..Default::default()
+    };
+
+    let ((linked, _, flows), stats) =
+        link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, &options);
+    (linked, flows, stats.distant_kernels)
+}
+
+// ==========================
+// Tests for max_link_gap_ns
+// ==========================
+
+#[test]
+fn test_distant_kernels_are_not_linked() {
+    let (linked, flows, distant) = link(Some(1_000_000));
+    assert_eq!(distant, 1);
+    assert_eq!(linked.len(), 1);
+    assert_eq!(linked[0].ts + linked[0].dur.unwrap(), 6.0);
+    let finishes: Vec<f64> = flows
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::FlowFinish)
+        .map(|e| e.ts)
+        .collect();
+    assert_eq!(finishes, vec![5.0]);
+}
+
+#[test]
+fn test_gap_within_threshold_or_disabled_links_all() {
+    for max_link_gap_ns in [None, Some(5_000_000)] {
+        let (linked, flows, distant) = link(max_link_gap_ns);
+        assert_eq!(distant, 0);
+        assert_eq!(linked[0].ts + linked[0].dur.unwrap(), 5005.0);
+        assert_eq!(flows.len(), 4);
+    }
+}
+
+#[test]
+fn test_max_link_gap_option_and_diagnostics() {
+    let options =
+        overlay_options(ConversionOptions::default(), r#"{"max_link_gap": "2ms"}"#).unwrap();
+    assert_eq!(options.max_link_gap_ns, Some(2_000_000));
+    assert_eq!(ConversionOptions::default().max_link_gap_ns, None);
+
+    let diagnostics = ConversionDiagnostics {
+        distant_links: 3,
+        ..Default::default()
+    };
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics.summary_lines()[0].starts_with("3 kernels started too long"));
+}
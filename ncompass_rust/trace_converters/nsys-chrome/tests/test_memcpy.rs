@@ -0,0 +1,173 @@
+//! Unit tests for memcpy parsing onto copy engine tracks
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::linker::link_copies_to_api_calls;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use nsys_chrome::parsers::{EventParser, MemcpyParser, ParseContext};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// HtoD and DtoH copies on engines 0 and 1, a P2P copy and a DtoD copy
+/// without channel info
+const MEMCPY_SQL: &str = "
+    CREATE TABLE CUPTI_ACTIVITY_KIND_MEMCPY (
+        start INTEGER, end INTEGER, deviceId INTEGER, contextId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, bytes INTEGER, copyKind INTEGER,
+        srcKind INTEGER, dstKind INTEGER, srcDeviceId INTEGER, dstDeviceId INTEGER,
+        channelID INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_MEMCPY VALUES
+        (1000, 3000, 0, 1, 7, 11, 16777216, 100000, 1, 0, 2, NULL, 0, 0),
+        (2000, 4000, 0, 1, 9, 12, 16777216, 50000, 2, 2, 0, 0, NULL, 1),
+        (5000, 6000, 0, 1, 7, 13, 16777216, 4096, 10, 2, 2, 0, 1, 2),
+        (7000, 8000, 0, 1, 7, 14, 16777216, 4096, 8, 2, 2, 0, 0, NULL);
+";
+
+fn parse_copies(sql: &str) -> Vec<ChromeTraceEvent> {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(sql).unwrap();
+
+    let strings = HashMap::new();
+    let options = ConversionOptions::default();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names);
+    MemcpyParser.safe_parse(&context).unwrap()
+}
+
+fn create_event(name: &str, start_ns: i64, end_ns: i64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+    .with_arg("deviceId", 0)
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+}
+
+// ==========================
+// Tests for MemcpyParser
+// ==========================
+
+#[test]
+fn test_memcpy_parser_uses_copy_engine_tracks() {
+    let copies = parse_copies(MEMCPY_SQL);
+
+    // The P2P copy belongs to the nvlink activity
+    let tracks: Vec<(&str, &str)> = copies.iter().map(|e| (e.name.as_str(), &*e.tid)).collect();
+    assert_eq!(
+        tracks,
+        vec![
+            ("Memcpy HtoD", "Copy Engine 0"),
+            ("Memcpy DtoH", "Copy Engine 1"),
+            ("Memcpy DtoD", "Stream 7"),
+        ]
+    );
+    assert!(copies
+        .iter()
+        .all(|e| e.cat == "memcpy" && e.pid == "Device 0"));
+    assert_eq!(copies[0].args["copyEngine"], 0);
+    assert_eq!(copies[0].args["bandwidth_gbps"], 50.0);
+    assert!(!copies[2].args.contains_key("copyEngine"));
+}
+
+#[test]
+fn test_memcpy_parser_without_channel_column() {
+    let sql = MEMCPY_SQL
+        .replace(",\n        channelID INTEGER", "")
+        .replace(", 0, 0),", ", 0),")
+        .replace(", NULL, 1),", ", NULL),")
+        .replace(", 1, 2),", ", 1),")
+        .replace(", 0, NULL);", ", 0);");
+    let copies = parse_copies(&sql);
+    let tids: Vec<&str> = copies.iter().map(|e| &*e.tid).collect();
+    assert_eq!(tids, vec!["Stream 7", "Stream 9", "Stream 7"]);
+}
+
+// ==========================
+// Tests for link_copies_to_api_calls
+// ==========================
+
+#[test]
+fn test_link_copies_to_api_calls_and_kernels() {
+    let mut events = vec![
+        create_event("Memcpy HtoD", 1000, 3000, "Copy Engine 0", "memcpy")
+            .with_arg("correlationId", 11),
+        create_event("Memcpy DtoH", 5000, 6000, "Copy Engine 1", "memcpy")
+            .with_arg("correlationId", 99),
+        create_event("read", 1000, 3000, "Thread 1", "osrt"),
+    ];
+    let api_events =
+        vec![
+            create_event("cudaMemcpyAsync", 500, 900, "CUDA API Thread 1", "cuda_api")
+                .with_arg("correlationId", 11),
+        ];
+    // Overlapping kernels count once: 1500-2500 is busy
+    let kernel_events = vec![
+        create_event("gemm", 1500, 2000, "Stream 7", "kernel"),
+        create_event("relu", 1800, 2500, "Stream 9", "kernel"),
+    ];
+
+    let flows = link_copies_to_api_calls(&mut events, &api_events, &kernel_events);
+
+    assert_eq!(events[0].args["api"], "cudaMemcpyAsync");
+    assert_eq!(events[0].args["compute_overlap_pct"], 50.0);
+    assert_eq!(events[1].args["compute_overlap_pct"], 0.0);
+    assert!(!events[1].args.contains_key("api"));
+    assert!(!events[2].args.contains_key("compute_overlap_pct"));
+
+    assert_eq!(flows.len(), 2);
+    assert_eq!(flows[0].ph, ChromeTracePhase::FlowStart);
+    assert_eq!(flows[0].tid, "CUDA API Thread 1");
+    assert_eq!(flows[1].tid, "Copy Engine 0");
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_emits_memcpy_when_requested() {
+    let sql = [
+        MEMCPY_SQL,
+        "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+        INSERT INTO StringIds VALUES (1, 'cudaMemcpyAsync');
+        CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+            start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES (500, 900, 1, 11, 1);",
+    ]
+    .concat();
+
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Memcpy, EventCategory::CudaApi],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = convert_sql(&sql, options);
+    let copies: Vec<&ChromeTraceEvent> = events.iter().filter(|e| e.cat == "memcpy").collect();
+    assert_eq!(copies.len(), 3);
+    assert_eq!(copies[0].args["api"], "cudaMemcpyAsync");
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| e.ph == ChromeTracePhase::FlowStart)
+            .count(),
+        1
+    );
+
+    // Not part of the default activity types
+    let events = convert_sql(&sql, ConversionOptions::default());
+    assert!(!events.iter().any(|e| e.cat == "memcpy"));
+}
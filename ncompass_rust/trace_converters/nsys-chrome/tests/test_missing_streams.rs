@@ -0,0 +1,184 @@
+//! Unit tests for kernels recorded without a stream ID
+
+use nsys_chrome::analysis::{
+    parse_missing_stream_policy, resolve_missing_streams, MissingStreamStats, STREAM_INFERRED_ARG,
+    STREAM_MISSING_ARG,
+};
+use nsys_chrome::diagnostics::ConversionDiagnostics;
+use nsys_chrome::frontends::FrontendTrace;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions, MissingStreamPolicy};
+use nsys_chrome::parsers::{CUPTIKernelParser, EventParser, ParseContext, UNKNOWN_STREAM_TRACK};
+use nsys_chrome::presets::overlay_options;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_kernel(corr: i64, stream: Option<i64>) -> ChromeTraceEvent {
+    let start_ns = corr * 1_000;
+    let kernel = ChromeTraceEvent::complete(
+        format!("kernel_{}", corr),
+        start_ns as f64 / 1000.0,
+        0.5,
+        "Device 0".to_string(),
+        stream.map_or(UNKNOWN_STREAM_TRACK.to_string(), |s| {
+            format!("Stream {}", s)
+        }),
+        "kernel".to_string(),
+    )
+    .with_arg("deviceId", 0)
+    .with_arg("correlationId", corr)
+    .with_arg("start_ns", start_ns);
+    match stream {
+        Some(stream) => kernel.with_arg("streamId", stream),
+        None => kernel.with_arg(STREAM_MISSING_ARG, true),
+    }
+}
+
+fn create_launch(corr: i64, tid: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "cudaLaunchKernel".to_string(),
+        corr as f64,
+        0.1,
+        "Device 0".to_string(),
+        format!("CUDA API Thread {}", tid),
+        "cuda_api".to_string(),
+    )
+    .with_arg("correlationId", corr)
+    .with_arg("raw_pid", 100)
+    .with_arg("raw_tid", tid)
+}
+
+/// Thread 1 launches onto stream 7, thread 2 onto stream 9; launches 3 and 5
+/// lost their stream, and launch 8 came from a thread with no other launches
+fn sample_trace() -> FrontendTrace {
+    FrontendTrace {
+        kernel_events: vec![
+            create_kernel(1, Some(7)),
+            create_kernel(2, Some(9)),
+            create_kernel(3, None),
+            create_kernel(4, Some(7)),
+            create_kernel(5, None),
+            create_kernel(8, None),
+        ],
+        api_events: vec![
+            create_launch(1, 1),
+            create_launch(2, 2),
+            create_launch(3, 1),
+            create_launch(4, 1),
+            create_launch(5, 2),
+            create_launch(8, 3),
+        ],
+        ..Default::default()
+    }
+}
+
+// ==========================
+// Tests for CUPTIKernelParser
+// ==========================
+
+#[test]
+fn test_null_stream_goes_to_unknown_track() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+            start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+            correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+            gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+            blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+            registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER);
+         INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+            (1000, 1500, 0, 7, 1, 16777216, 1, 1, 1, 1, 32, 1, 1, 32, 0, 0),
+            (2000, 2500, 0, NULL, 2, 16777216, 1, 1, 1, 1, 32, 1, 1, 32, 0, 0);",
+    )
+    .unwrap();
+    let strings = HashMap::from([(1, "gemm".to_string())]);
+    let options = ConversionOptions::default();
+    let (device_map, thread_names) = (HashMap::new(), HashMap::new());
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names);
+    let kernels = CUPTIKernelParser.safe_parse(&context).unwrap();
+
+    assert_eq!(kernels.len(), 2);
+    assert_eq!(kernels[0].tid, "Stream 7");
+    assert_eq!(kernels[1].tid, UNKNOWN_STREAM_TRACK);
+    assert_eq!(kernels[1].args[STREAM_MISSING_ARG], true);
+    assert!(!kernels[1].args.contains_key("streamId"));
+}
+
+// ==========================
+// Tests for resolve_missing_streams
+// ==========================
+
+#[test]
+fn test_infer_from_launching_thread() {
+    let mut trace = sample_trace();
+    let stats = resolve_missing_streams(&mut trace, MissingStreamPolicy::Infer);
+    assert_eq!(
+        stats,
+        MissingStreamStats {
+            inferred: 2,
+            unknown: 1,
+            dropped: 0
+        }
+    );
+
+    let kernels = &trace.kernel_events;
+    // Launch 3 follows thread 1's launch 1, launch 5 follows thread 2's launch 2
+    assert_eq!(kernels[2].tid, "Stream 7");
+    assert_eq!(kernels[2].args["streamId"], 7);
+    assert_eq!(kernels[2].args[STREAM_INFERRED_ARG], true);
+    assert!(!kernels[2].args.contains_key(STREAM_MISSING_ARG));
+    assert_eq!(kernels[4].tid, "Stream 9");
+    assert_eq!(kernels[5].tid, UNKNOWN_STREAM_TRACK);
+    assert_eq!(kernels[5].args[STREAM_MISSING_ARG], true);
+}
+
+#[test]
+fn test_unknown_and_drop_policies() {
+    let mut trace = sample_trace();
+    let stats = resolve_missing_streams(&mut trace, MissingStreamPolicy::Unknown);
+    assert_eq!(stats.unknown, 3);
+    assert_eq!(trace.kernel_events.len(), 6);
+
+    let mut trace = sample_trace();
+    let stats = resolve_missing_streams(&mut trace, MissingStreamPolicy::Drop);
+    assert_eq!(stats.dropped, 3);
+    assert_eq!(trace.kernel_events.len(), 3);
+    assert!(trace
+        .kernel_events
+        .iter()
+        .all(|e| e.args.contains_key("streamId")));
+
+    let diagnostics = ConversionDiagnostics {
+        missing_streams: stats,
+        ..Default::default()
+    };
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics.summary_lines()[0].starts_with("3 kernels had no stream ID"));
+}
+
+#[test]
+fn test_parse_missing_stream_policy() {
+    assert_eq!(
+        parse_missing_stream_policy("infer").unwrap(),
+        MissingStreamPolicy::Infer
+    );
+    assert_eq!(
+        parse_missing_stream_policy(" drop ").unwrap(),
+        MissingStreamPolicy::Drop
+    );
+    assert!(parse_missing_stream_policy("guess").is_err());
+    assert_eq!(
+        ConversionOptions::default().missing_stream_policy,
+        MissingStreamPolicy::Unknown
+    );
+
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"missing_stream": "infer"}"#,
+    )
+    .unwrap();
+    assert_eq!(options.missing_stream_policy, MissingStreamPolicy::Infer);
+}
@@ -1,7 +1,9 @@
 //! Unit tests for models module
 
+use nsys_chrome::category::EventCategory;
 use nsys_chrome::models::{
-    ns_to_us, BindingPoint, ChromeTraceEvent, ChromeTracePhase, ConversionOptions, StringOrInt,
+    ns_to_us, BindingPoint, ChromeTraceEvent, ChromeTracePhase, ConversionOptions, RawTrace,
+    RawTraceEvent, StringOrInt,
 };
 use std::collections::HashMap;
 
@@ -317,14 +319,12 @@ fn test_string_or_int_from_i32() {
 #[test]
 fn test_conversion_options_default() {
     let options = ConversionOptions::default();
-    assert!(options.activity_types.contains(&"kernel".to_string()));
-    assert!(options.activity_types.contains(&"nvtx".to_string()));
-    assert!(options
-        .activity_types
-        .contains(&"nvtx-kernel".to_string()));
-    assert!(options.activity_types.contains(&"cuda-api".to_string()));
-    assert!(options.activity_types.contains(&"osrt".to_string()));
-    assert!(options.activity_types.contains(&"sched".to_string()));
+    assert!(options.activity_types.contains(&EventCategory::Kernel));
+    assert!(options.activity_types.contains(&EventCategory::Nvtx));
+    assert!(options.activity_types.contains(&EventCategory::NvtxKernel));
+    assert!(options.activity_types.contains(&EventCategory::CudaApi));
+    assert!(options.activity_types.contains(&EventCategory::Osrt));
+    assert!(options.activity_types.contains(&EventCategory::Sched));
     assert_eq!(options.activity_types.len(), 6);
     assert_eq!(options.nvtx_event_prefix, None);
     assert!(options.nvtx_color_scheme.is_empty());
@@ -337,15 +337,16 @@ fn test_conversion_options_custom() {
     color_scheme.insert("test_.*".to_string(), "blue".to_string());
 
     let options = ConversionOptions {
-        activity_types: vec!["kernel".to_string(), "nvtx".to_string()],
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
         nvtx_event_prefix: Some(vec!["test_".to_string()]),
         nvtx_color_scheme: color_scheme.clone(),
         include_metadata: false,
+        ..Default::default()
     };
 
     assert_eq!(options.activity_types.len(), 2);
-    assert!(options.activity_types.contains(&"kernel".to_string()));
-    assert!(options.activity_types.contains(&"nvtx".to_string()));
+    assert!(options.activity_types.contains(&EventCategory::Kernel));
+    assert!(options.activity_types.contains(&EventCategory::Nvtx));
     assert_eq!(
         options.nvtx_event_prefix,
         Some(vec!["test_".to_string()])
@@ -357,3 +358,65 @@ fn test_conversion_options_custom() {
     assert!(!options.include_metadata);
 }
 
+// ==========================
+// Tests for RawTraceEvent
+// ==========================
+
+#[test]
+fn test_raw_event_passes_args_through_verbatim() {
+    let text = r#"{"name":"k","ph":"X","ts":1.5,"args":{"grid":[8, 8,1],"flops":1.50e9}}"#;
+    let mut event: RawTraceEvent = serde_json::from_str(text).unwrap();
+
+    assert!(event.args_are_raw());
+    assert_eq!(event.get_str("name"), Some("k"));
+    assert_eq!(
+        event.raw_args(),
+        Some(r#"{"grid":[8, 8,1],"flops":1.50e9}"#)
+    );
+
+    event.set("name", serde_json::json!("gemm"));
+    let written = serde_json::to_string(&event).unwrap();
+    assert!(written.contains(r#""args":{"grid":[8, 8,1],"flops":1.50e9}"#));
+    assert!(written.contains(r#""name":"gemm""#));
+}
+
+#[test]
+fn test_raw_event_parses_args_on_access() {
+    let mut event: RawTraceEvent =
+        serde_json::from_str(r#"{"name":"k","args":{"correlationId":7}}"#).unwrap();
+    event
+        .args_mut()
+        .unwrap()
+        .insert("stream".to_string(), serde_json::json!(3));
+    assert!(!event.args_are_raw());
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(
+        value["args"],
+        serde_json::json!({"correlationId": 7, "stream": 3})
+    );
+
+    // Events without args stay without them until written to
+    let mut event: RawTraceEvent = serde_json::from_str(r#"{"name":"m","args":null}"#).unwrap();
+    assert!(event.args_mut().unwrap().is_empty());
+    let mut event: RawTraceEvent = serde_json::from_str(r#"{"name":"m"}"#).unwrap();
+    assert!(!serde_json::to_string(&event).unwrap().contains("args"));
+    event.args_mut().unwrap();
+    assert!(serde_json::to_string(&event).unwrap().contains(r#""args":{}"#));
+}
+
+#[test]
+fn test_raw_trace_from_slice() {
+    let trace =
+        RawTrace::from_slice(br#"{"displayTimeUnit":"ns","traceEvents":[{"name":"a"}]}"#).unwrap();
+    assert_eq!(trace.events.len(), 1);
+    assert_eq!(trace.other["displayTimeUnit"], "ns");
+    let value = serde_json::to_value(&trace).unwrap();
+    assert_eq!(value["traceEvents"][0]["name"], "a");
+    assert_eq!(value["displayTimeUnit"], "ns");
+
+    let bare = RawTrace::from_slice(b" [{\"name\":\"a\"},{\"name\":\"b\"}]").unwrap();
+    assert_eq!(bare.events.len(), 2);
+
+    assert!(RawTrace::from_slice(br#"{"events":[]}"#).is_err());
+    assert!(RawTrace::from_slice(b"{").is_err());
+}
@@ -0,0 +1,205 @@
+//! Unit tests for MPI call parsing and NCCL kernel linking
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::linker::link_mpi_to_nccl_kernels;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use nsys_chrome::parsers::{EventParser, MPIParser, ParseContext};
+use rusqlite::Connection;
+use serde_json::json;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Two ranks (pid 1 on device 0, pid 2 on device 1) in one all-reduce, where
+/// rank 1 arrives late; rank 0 also sends a message and rank 1 hits a barrier
+const MPI_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES
+        (1, 'ncclDevKernel_AllReduce_Sum_f32_RING_LL'), (2, 'gemm'),
+        (3, 'MPI_Allreduce'), (4, 'MPI_Send'), (5, 'MPI_Barrier');
+    CREATE TABLE MPI_RANKS (rank INTEGER, globalTid INTEGER);
+    INSERT INTO MPI_RANKS VALUES (0, 16777217), (1, 33554437);
+    CREATE TABLE MPI_COMMUNICATORS (
+        globalTid INTEGER, commHandle INTEGER, parentHandle INTEGER,
+        localRank INTEGER, size INTEGER
+    );
+    INSERT INTO MPI_COMMUNICATORS VALUES
+        (16777217, 1140850688, NULL, 0, 2), (33554437, 1140850688, NULL, 1, 2);
+    CREATE TABLE MPI_COLLECTIVES_EVENTS (
+        start INTEGER, end INTEGER, globalTid INTEGER, textId INTEGER,
+        commHandle INTEGER, rootRank INTEGER, size INTEGER, recvSize INTEGER
+    );
+    INSERT INTO MPI_COLLECTIVES_EVENTS VALUES
+        (1000, 5000, 16777217, 3, 1140850688, NULL, 4096, 4096),
+        (1200, 9000, 33554437, 3, 1140850688, NULL, 4096, 4096);
+    CREATE TABLE MPI_P2P_EVENTS (
+        start INTEGER, end INTEGER, globalTid INTEGER, textId INTEGER,
+        commHandle INTEGER, tag INTEGER, remoteRank INTEGER, size INTEGER
+    );
+    INSERT INTO MPI_P2P_EVENTS VALUES (10000, 11000, 16777217, 4, 1140850688, 7, 1, 256);
+    CREATE TABLE MPI_OTHER_EVENTS (start INTEGER, end INTEGER, globalTid INTEGER, textId INTEGER);
+    INSERT INTO MPI_OTHER_EVENTS VALUES (12000, 13000, 33554437, 5);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (2000, 4500, 0, 7, 1, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (3000, 3500, 0, 7, 2, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (6000, 8800, 1, 7, 3, 33554432, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+";
+
+fn parse_mpi(sql: &str) -> Vec<ChromeTraceEvent> {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(sql).unwrap();
+
+    let strings = HashMap::from([
+        (3, "MPI_Allreduce".to_string()),
+        (4, "MPI_Send".to_string()),
+        (5, "MPI_Barrier".to_string()),
+    ]);
+    let options = ConversionOptions::default();
+    let device_map = HashMap::from([(1, 0), (2, 1)]);
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names);
+    MPIParser.safe_parse(&context).unwrap()
+}
+
+fn create_event(name: &str, start_ns: i64, end_ns: i64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+    .with_arg("deviceId", 0)
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+}
+
+// ==========================
+// Tests for MPIParser
+// ==========================
+
+#[test]
+fn test_mpi_parser_emits_rank_tracks() {
+    let events = parse_mpi(MPI_SQL);
+    let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["MPI_Allreduce", "MPI_Allreduce", "MPI_Send", "MPI_Barrier"]
+    );
+
+    let allreduce = &events[1];
+    assert_eq!(allreduce.cat, "mpi");
+    assert_eq!(allreduce.pid, "Device 1");
+    assert_eq!(allreduce.tid, "MPI Rank 1 Thread 5");
+    assert_eq!(allreduce.args["rank"], 1);
+    assert_eq!(allreduce.args["communicator"], "0x44000000");
+    assert_eq!(allreduce.args["comm_rank"], 1);
+    assert_eq!(allreduce.args["comm_size"], 2);
+    assert_eq!(allreduce.args["bytes"], 4096);
+    assert!(!allreduce.args.contains_key("root_rank"));
+
+    let send = &events[2];
+    assert_eq!(send.tid, "MPI Rank 0 Thread 1");
+    assert_eq!(send.args["remote_rank"], 1);
+    assert_eq!(send.args["tag"], 7);
+    assert_eq!(send.args["bytes"], 256);
+    assert!(!events[3].args.contains_key("communicator"));
+}
+
+#[test]
+fn test_mpi_parser_without_rank_tables() {
+    assert!(parse_mpi("CREATE TABLE StringIds (id INTEGER, value TEXT);").is_empty());
+
+    // Calls are still extracted when ranks and communicators were not recorded
+    let events = parse_mpi(
+        "CREATE TABLE MPI_OTHER_EVENTS (start INTEGER, end INTEGER, globalTid INTEGER, textId INTEGER);
+         INSERT INTO MPI_OTHER_EVENTS VALUES (100, NULL, 16777217, 5);",
+    );
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].tid, "MPI Thread 1");
+    assert_eq!(events[0].dur, None);
+    assert!(!events[0].args.contains_key("rank"));
+}
+
+// ==========================
+// Tests for link_mpi_to_nccl_kernels
+// ==========================
+
+#[test]
+fn test_link_prefers_largest_overlap() {
+    let mut events = vec![
+        create_event("MPI_Allreduce", 1000, 2000, "MPI Rank 0 Thread 1", "mpi")
+            .with_arg("rank", 0)
+            .with_arg("communicator", "0x44000000"),
+        create_event("MPI_Bcast", 1900, 5000, "MPI Rank 0 Thread 1", "mpi"),
+        create_event(
+            "cudaLaunchKernel",
+            1900,
+            5000,
+            "CUDA API Thread 1",
+            "cuda_api",
+        ),
+    ];
+    let mut kernels = vec![
+        create_event("ncclKernel_AllReduce", 1500, 2100, "Stream 7", "kernel"),
+        create_event("ncclKernel_Broadcast", 3000, 4000, "Stream 7", "kernel"),
+        create_event("gemm", 1500, 1800, "Stream 7", "kernel"),
+        create_event("ncclKernel_Broadcast", 6000, 7000, "Stream 7", "kernel"),
+    ];
+
+    let flows = link_mpi_to_nccl_kernels(&mut events, &mut kernels);
+    assert_eq!(kernels[0].args["mpi_call"], "MPI_Allreduce");
+    assert_eq!(kernels[0].args["mpi_rank"], 0);
+    assert_eq!(kernels[0].args["mpi_communicator"], "0x44000000");
+    assert_eq!(kernels[1].args["mpi_call"], "MPI_Bcast");
+    assert!(!kernels[1].args.contains_key("mpi_rank"));
+    assert!(!kernels[2].args.contains_key("mpi_call"));
+    assert!(!kernels[3].args.contains_key("mpi_call"));
+    assert_eq!(events[0].args["nccl_kernels"], 1);
+    assert!(!events[2].args.contains_key("nccl_kernels"));
+
+    assert_eq!(flows.len(), 4);
+    assert_eq!(flows[0].ph, ChromeTracePhase::FlowStart);
+    assert_eq!(flows[0].tid, "MPI Rank 0 Thread 1");
+    assert_eq!(flows[1].tid, "Stream 7");
+    assert_eq!(flows[0].id, flows[1].id);
+}
+
+#[test]
+fn test_converter_links_nccl_kernels_per_rank() {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel, EventCategory::Mpi],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = convert_sql(MPI_SQL, options);
+
+    let nccl: Vec<&ChromeTraceEvent> = events
+        .iter()
+        .filter(|e| e.cat == "kernel" && e.name.starts_with("nccl"))
+        .collect();
+    assert_eq!(nccl.len(), 2);
+    let ranks: Vec<_> = nccl.iter().map(|k| k.args["mpi_rank"].clone()).collect();
+    assert_eq!(ranks, vec![json!(0), json!(1)]);
+    assert!(nccl
+        .iter()
+        .all(|k| k.args["mpi_call"] == "MPI_Allreduce" && k.args["mpi_comm_size"] == 2));
+
+    let gemm = events.iter().find(|e| e.name == "gemm").unwrap();
+    assert!(!gemm.args.contains_key("mpi_call"));
+    let barrier = events.iter().find(|e| e.name == "MPI_Barrier").unwrap();
+    assert_eq!(barrier.args["rank"], 1);
+}
@@ -0,0 +1,159 @@
+//! Unit tests for the kernel name dictionary
+
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, RawTrace};
+use nsys_chrome::name_dictionary::{
+    expand_names, expand_raw_names, expand_trace_file, NameDictionary, NAME_DICTIONARY_EVENT,
+};
+use nsys_chrome::ChromeTraceWriter;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+const LONG_NAME: &str =
+    "void cutlass::Kernel<cutlass_80_tensorop_s1688gemm_128x128_32x3_nn_align4>(Params)";
+
+fn create_event(name: &str, ts: f64, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        1.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        cat.to_string(),
+    )
+}
+
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_event(LONG_NAME, 0.0, "kernel"),
+        create_event("forward", 0.0, "nvtx"),
+        create_event("elementwise_kernel", 2.0, "kernel"),
+        create_event(LONG_NAME, 4.0, "kernel"),
+    ]
+}
+
+// ==========================
+// Tests for NameDictionary
+// ==========================
+
+#[test]
+fn test_compress_replaces_kernel_names() {
+    let mut events = sample_events();
+    let mut dictionary = NameDictionary::new();
+    assert_eq!(dictionary.compress(&mut events), 3);
+
+    let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![NAME_DICTIONARY_EVENT, "k0", "forward", "k1", "k0"]
+    );
+    let dictionary_event = &events[0];
+    assert_eq!(dictionary_event.ph, ChromeTracePhase::Metadata);
+    assert_eq!(dictionary_event.pid, "Device 0");
+    assert_eq!(dictionary_event.args["names"]["k0"], LONG_NAME);
+    assert_eq!(dictionary_event.args["names"]["k1"], "elementwise_kernel");
+    assert_eq!(dictionary.name(1), Some("elementwise_kernel"));
+
+    // Nothing to compress, no dictionary event
+    let mut events = vec![create_event("forward", 0.0, "nvtx")];
+    assert_eq!(dictionary.compress(&mut events), 0);
+    assert_eq!(events.len(), 1);
+}
+
+#[test]
+fn test_saved_dictionary_keeps_ids_across_traces() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("names.json");
+
+    let mut dictionary = NameDictionary::new();
+    dictionary.compress(&mut sample_events());
+    dictionary.save(path.to_str().unwrap()).unwrap();
+
+    // A second rank sees the kernels in another order
+    let mut loaded = NameDictionary::load(&path).unwrap();
+    assert_eq!(loaded.len(), 2);
+    let mut events = vec![
+        create_event("elementwise_kernel", 0.0, "kernel"),
+        create_event("reduce_kernel", 1.0, "kernel"),
+    ];
+    loaded.compress(&mut events);
+    assert_eq!(events[1].name, "k1");
+    assert_eq!(events[2].name, "k2");
+    // Only the names this trace uses are embedded
+    let embedded = events[0].args["names"].as_object().unwrap();
+    assert_eq!(embedded.len(), 2);
+    assert!(!embedded.contains_key("k0"));
+
+    std::fs::write(&path, r#"{"names": [1]}"#).unwrap();
+    assert!(NameDictionary::load(&path).is_err());
+}
+
+// ==========================
+// Tests for expansion
+// ==========================
+
+#[test]
+fn test_expand_names_restores_original_trace() {
+    let mut events = sample_events();
+    NameDictionary::new().compress(&mut events);
+    let mut root = serde_json::json!({ "traceEvents": events });
+    // A non-kernel event named like a short ID is left alone
+    root["traceEvents"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::to_value(create_event("k0", 9.0, "nvtx")).unwrap());
+
+    assert_eq!(expand_names(&mut root).unwrap(), 3);
+    let expanded = root["traceEvents"].as_array().unwrap();
+    assert_eq!(expanded.len(), 5);
+    assert_eq!(expanded[0]["name"], LONG_NAME);
+    assert_eq!(expanded[2]["name"], "elementwise_kernel");
+    assert_eq!(expanded[4]["name"], "k0");
+
+    assert!(expand_names(&mut serde_json::json!({})).is_err());
+}
+
+#[test]
+fn test_expand_raw_names_leaves_other_args_unparsed() {
+    let mut events = sample_events();
+    events[0] = events[0]
+        .clone()
+        .with_arg("grid", serde_json::json!([8, 8, 1]));
+    NameDictionary::new().compress(&mut events);
+    let bytes = serde_json::to_vec(&serde_json::json!({ "traceEvents": events })).unwrap();
+    let mut trace = RawTrace::from_slice(&bytes).unwrap();
+
+    assert_eq!(expand_raw_names(&mut trace).unwrap(), 3);
+    assert_eq!(trace.events.len(), 4);
+    assert_eq!(trace.events[0].get_str("name"), Some(LONG_NAME));
+    assert!(trace.events[0].args_are_raw());
+    assert_eq!(trace.events[0].raw_args(), Some(r#"{"grid":[8,8,1]}"#));
+}
+
+#[test]
+fn test_expand_trace_file_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let compressed = dir.path().join("compressed.json.gz");
+    let mut events = sample_events();
+    NameDictionary::new().compress(&mut events);
+    ChromeTraceWriter::write_gz(compressed.to_str().unwrap(), events).unwrap();
+
+    let expanded = dir.path().join("expanded.json");
+    let file = std::fs::File::create(&expanded).unwrap();
+    assert_eq!(expand_trace_file(&compressed, file, false).unwrap(), 3);
+
+    let text = std::fs::read_to_string(&expanded).unwrap();
+    let root: serde_json::Value = serde_json::from_str(&text).unwrap();
+    let names: Vec<&str> = root["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        vec![LONG_NAME, "forward", "elementwise_kernel", LONG_NAME]
+    );
+}
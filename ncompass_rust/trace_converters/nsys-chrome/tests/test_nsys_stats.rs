@@ -0,0 +1,127 @@
+//! Unit tests for the nsys stats CSV front-end
+
+use nsys_chrome::frontends::nsys_stats::{
+    is_nsys_stats_csv, parse_stats_csv, StatsReportKind, API_SUMMARY_TRACK, KERNEL_SUMMARY_TRACK,
+};
+use nsys_chrome::frontends::{assemble_trace, NsysStatsReader};
+use nsys_chrome::models::ConversionOptions;
+use nsys_chrome::routing::duration_stats;
+use std::fs;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// `cuda_gpu_kern_sum` report, including a templated name with commas
+const KERNEL_SUM: &str = "\
+Time (%),Total Time (ns),Instances,Avg (ns),Med (ns),Min (ns),Max (ns),StdDev (ns),Name
+60.0,6000,3,2000.0,2000.0,1500,2500,500.0,\"void gemm<float, 128>(int, float*)\"
+40.0,4000,4,1000.0,1000.0,900,1100,100.0,elementwise_kernel
+";
+
+/// `cuda_api_sum` report as printed to stdout, after nsys status lines
+const API_SUM: &str = "\
+Processing [report.sqlite] with [cuda_api_sum.py]...
+
+Time (%),Total Time (ns),Num Calls,Avg (ns),Med (ns),Min (ns),Max (ns),StdDev (ns),Name
+75.0,300,7,42.9,40.0,30,60,10.0,cudaLaunchKernel
+25.0,100,1,100.0,100.0,100,100,0.0,cudaMalloc
+";
+
+/// Directory holding both reports as `nsys stats --output` names them
+fn stats_dir() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("report_cuda_gpu_kern_sum.csv"), KERNEL_SUM).unwrap();
+    fs::write(dir.path().join("report_cuda_api_sum.csv"), API_SUM).unwrap();
+    fs::write(dir.path().join("report_osrt_sum.csv"), API_SUM).unwrap();
+    dir
+}
+
+// ==========================
+// Tests for parse_stats_csv
+// ==========================
+
+#[test]
+fn test_parse_reports_detects_kind_from_header() {
+    let (kind, rows) = parse_stats_csv(KERNEL_SUM, None).unwrap();
+    assert_eq!(kind, StatsReportKind::Kernel);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].name, "void gemm<float, 128>(int, float*)");
+    assert_eq!(rows[0].total_ns, 6000);
+    assert_eq!(rows[0].instances, 3);
+    assert_eq!(rows[0].min_ns, Some(1500.0));
+
+    let (kind, rows) = parse_stats_csv(API_SUM, None).unwrap();
+    assert_eq!(kind, StatsReportKind::Api);
+    assert_eq!(rows[0].instances, 7);
+
+    assert!(parse_stats_csv("Operation,Total Time (ns)\n", None).is_err());
+}
+
+// ==========================
+// Tests for NsysStatsReader
+// ==========================
+
+#[test]
+fn test_reader_lays_out_aggregate_bars() {
+    let dir = stats_dir();
+    assert!(is_nsys_stats_csv(dir.path()));
+    let trace = NsysStatsReader::open(dir.path()).unwrap().read().unwrap();
+
+    // The osrt report is skipped despite its API-like header
+    assert_eq!(trace.api_events.len(), 2);
+    let bars: Vec<(&str, f64, f64)> = trace
+        .kernel_events
+        .iter()
+        .map(|e| (e.name.as_str(), e.ts, e.dur.unwrap()))
+        .collect();
+    assert_eq!(
+        bars,
+        vec![
+            ("void gemm<float, 128>(int, float*)", 0.0, 6.0),
+            ("elementwise_kernel", 6.0, 4.0),
+        ]
+    );
+    assert_eq!(trace.kernel_events[0].tid, KERNEL_SUMMARY_TRACK);
+    assert_eq!(trace.kernel_events[1].args["instances"], 4);
+    assert_eq!(trace.api_events[0].tid, API_SUMMARY_TRACK);
+    assert_eq!(&*trace.api_events[0].cat, "cuda_api");
+}
+
+#[test]
+fn test_stats_report_counts_instances() {
+    let mut reader = NsysStatsReader::default();
+    reader.add_csv(KERNEL_SUM, None).unwrap();
+    let events = assemble_trace(reader.read().unwrap(), &ConversionOptions::default()).unwrap();
+    assert!(events
+        .iter()
+        .any(|e| e.name == "thread_name" && e.tid == KERNEL_SUMMARY_TRACK));
+
+    let stats = duration_stats(&events);
+    let gemm = stats
+        .iter()
+        .find(|s| s.name.starts_with("void gemm"))
+        .unwrap();
+    assert_eq!(gemm.count, 3);
+    assert_eq!(gemm.total_us, 6.0);
+    assert_eq!(gemm.avg_us(), 2.0);
+    assert_eq!((gemm.min_us, gemm.max_us), (1.5, 2.5));
+}
+
+#[test]
+fn test_detection_rejects_other_csvs() {
+    let dir = TempDir::new().unwrap();
+    let kernels = dir.path().join("kernels.csv");
+    let other = dir.path().join("other.csv");
+    fs::write(&kernels, KERNEL_SUM).unwrap();
+    fs::write(&other, "a,b\n1,2\n").unwrap();
+
+    assert!(is_nsys_stats_csv(&kernels));
+    assert!(!is_nsys_stats_csv(&other));
+    assert!(NsysStatsReader::open(&other).is_err());
+    // No report named as a kernel or API summary
+    fs::remove_file(&kernels).unwrap();
+    assert!(!is_nsys_stats_csv(dir.path()));
+    assert!(NsysStatsReader::open(dir.path()).is_err());
+}
@@ -0,0 +1,102 @@
+//! Unit tests for NVTX domain handling in the NVTX parser
+
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::parsers::{EventParser, NVTXParser, ParseContext};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// NVTX_EVENTS with a NCCL domain (id 7), one NCCL range and one default-domain range
+const NVTX_DOMAINS_SQL: &str = "
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER, domainId INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES (100, NULL, 'NCCL', NULL, 16777217, 75, 7);
+    INSERT INTO NVTX_EVENTS VALUES (1000, 5000, 'AllReduce', NULL, 16777217, 59, 7);
+    INSERT INTO NVTX_EVENTS VALUES (2000, 3000, 'forward', NULL, 16777217, 59, 0);
+";
+
+fn parse_nvtx(sql: &str, options: &ConversionOptions) -> Vec<ChromeTraceEvent> {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(sql).unwrap();
+
+    let strings = HashMap::new();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, options, &device_map, &thread_names);
+    let mut events = NVTXParser.safe_parse(&context).unwrap();
+    events.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+    events
+}
+
+// ==========================
+// Tests for NVTX domains
+// ==========================
+
+#[test]
+fn test_nvtx_domains_recorded_in_args() {
+    let events = parse_nvtx(NVTX_DOMAINS_SQL, &ConversionOptions::default());
+
+    // The domain creation event is not a range
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].name, "AllReduce");
+    assert_eq!(events[0].args["domain"], "NCCL");
+    assert_eq!(events[0].args["domainId"], 7);
+    assert_eq!(events[0].tid, "NVTX Thread 1");
+    assert_eq!(events[1].args["domain"], "default");
+}
+
+#[test]
+fn test_nvtx_domains_filter() {
+    let options = ConversionOptions {
+        nvtx_domains: Some(vec!["NCCL".to_string()]),
+        ..Default::default()
+    };
+    let events = parse_nvtx(NVTX_DOMAINS_SQL, &options);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "AllReduce");
+
+    // Domains can also be selected by ID
+    let options = ConversionOptions {
+        nvtx_domains: Some(vec!["0".to_string()]),
+        ..Default::default()
+    };
+    let events = parse_nvtx(NVTX_DOMAINS_SQL, &options);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "forward");
+}
+
+#[test]
+fn test_nvtx_domains_prefix_and_tracks() {
+    let options = ConversionOptions {
+        nvtx_domain_prefix: true,
+        nvtx_domain_tracks: true,
+        ..Default::default()
+    };
+    let events = parse_nvtx(NVTX_DOMAINS_SQL, &options);
+
+    assert_eq!(events[0].name, "NCCL: AllReduce");
+    assert_eq!(events[0].tid, "NVTX Thread 1 [NCCL]");
+
+    // The default domain keeps plain names and the thread's own track
+    assert_eq!(events[1].name, "forward");
+    assert_eq!(events[1].tid, "NVTX Thread 1");
+}
+
+#[test]
+fn test_nvtx_without_domain_column() {
+    let sql = "
+        CREATE TABLE NVTX_EVENTS (
+            start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+            globalTid INTEGER, eventType INTEGER
+        );
+        INSERT INTO NVTX_EVENTS VALUES (1000, 5000, 'forward', NULL, 16777217, 59);
+    ";
+    let events = parse_nvtx(sql, &ConversionOptions::default());
+    assert_eq!(events.len(), 1);
+    assert!(!events[0].args.contains_key("domain"));
+}
@@ -0,0 +1,141 @@
+//! Unit tests for NVTX name filtering by prefix, glob and regex
+
+use nsys_chrome::frontends::{assemble_trace, FrontendTrace};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::parsers::nvtx::NvtxNameFilter;
+use nsys_chrome::parsers::{EventParser, NVTXParser, ParseContext};
+use nsys_chrome::ConvertError;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// NVTX_EVENTS mixing inline names and names stored in the string table
+const NVTX_SQL: &str = "
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES (1000, 2000, 'model/forward', NULL, 16777217, 59);
+    INSERT INTO NVTX_EVENTS VALUES (2000, 3000, 'data/load', NULL, 16777217, 59);
+    INSERT INTO NVTX_EVENTS VALUES (3000, 4000, 'optimizer/step', NULL, 16777217, 59);
+    INSERT INTO NVTX_EVENTS VALUES (4000, 5000, NULL, 1, 16777217, 59);
+    INSERT INTO NVTX_EVENTS VALUES (5000, 6000, NULL, 2, 16777217, 59);
+";
+
+fn filter(patterns: &[&str]) -> NvtxNameFilter {
+    let patterns = Some(patterns.iter().map(|p| p.to_string()).collect());
+    NvtxNameFilter::new(&patterns).unwrap().unwrap()
+}
+
+fn options_with(patterns: &[&str]) -> ConversionOptions {
+    ConversionOptions {
+        nvtx_event_prefix: Some(patterns.iter().map(|p| p.to_string()).collect()),
+        ..Default::default()
+    }
+}
+
+fn parse_nvtx(options: &ConversionOptions) -> Vec<String> {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(NVTX_SQL).unwrap();
+
+    let strings = HashMap::from([
+        (1, "model/backward".to_string()),
+        (2, "logging".to_string()),
+    ]);
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, options, &device_map, &thread_names);
+    let mut events = NVTXParser.parse(&context).unwrap();
+    events.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+    events.into_iter().map(|e| e.name).collect()
+}
+
+// ==========================
+// Tests for NvtxNameFilter
+// ==========================
+
+#[test]
+fn test_filter_patterns() {
+    let globs = filter(&["model/*", "data/l?ad", "step[0-9]", "opt[!x]*"]);
+    assert!(globs.matches("model/forward"));
+    assert!(globs.matches("model/encoder/block"));
+    assert!(!globs.matches("my_model/forward"));
+    assert!(globs.matches("data/load"));
+    assert!(!globs.matches("data/loader"));
+    assert!(globs.matches("step3"));
+    assert!(globs.matches("optimizer"));
+
+    // Regexes match anywhere unless anchored
+    let regexes = filter(&["re:^(model|data)/", "re:attn"]);
+    assert!(regexes.matches("data/load"));
+    assert!(regexes.matches("layer.0.self_attn"));
+    assert!(!regexes.matches("my_model/forward"));
+
+    // Plain patterns keep the prefix behavior, with regex characters taken literally
+    let prefixes = filter(&["fwd.", "bwd"]);
+    assert!(prefixes.matches("fwd.layer"));
+    assert!(!prefixes.matches("fwd_layer"));
+    assert!(prefixes.matches("bwd_pass"));
+}
+
+#[test]
+fn test_filter_empty_and_invalid() {
+    assert!(NvtxNameFilter::new(&None).unwrap().is_none());
+    assert!(NvtxNameFilter::new(&Some(Vec::new())).unwrap().is_none());
+
+    let result = NvtxNameFilter::new(&Some(vec!["re:(unclosed".to_string()]));
+    match result {
+        Err(ConvertError::InvalidOption(message)) => {
+            assert!(message.starts_with("Invalid NVTX filter 're:(unclosed'"))
+        }
+        other => panic!("Expected an invalid option, got {:?}", other),
+    }
+}
+
+// ==========================
+// Tests for extraction
+// ==========================
+
+#[test]
+fn test_parser_applies_globs_and_prefixes() {
+    let names = parse_nvtx(&options_with(&["model/*", "data/*"]));
+    assert_eq!(names, vec!["model/forward", "data/load", "model/backward"]);
+
+    // Prefixes pushed down to SQL still see names stored by textId
+    let names = parse_nvtx(&options_with(&["model/", "log"]));
+    assert_eq!(names, vec!["model/forward", "model/backward", "logging"]);
+}
+
+#[test]
+fn test_assemble_trace_matches_undecorated_names() {
+    let range = |name: &str, ts: f64| {
+        ChromeTraceEvent::complete(
+            name.to_string(),
+            ts,
+            1.0,
+            "Device 0".to_string(),
+            "NVTX Thread 1".to_string(),
+            "nvtx".to_string(),
+        )
+        .with_arg("domain", "PyTorch")
+    };
+    let trace = FrontendTrace {
+        annotation_events: vec![
+            range("PyTorch: model/forward", 0.0),
+            range("PyTorch: data/load", 1.0),
+            range("PyTorch: logging", 2.0),
+        ],
+        ..Default::default()
+    };
+    let events = assemble_trace(trace, &options_with(&["re:^(model|data)/"])).unwrap();
+
+    let names: Vec<&str> = events
+        .iter()
+        .filter(|e| e.cat == "nvtx")
+        .map(|e| e.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["PyTorch: model/forward", "PyTorch: data/load"]);
+}
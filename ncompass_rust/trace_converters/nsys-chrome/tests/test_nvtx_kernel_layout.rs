@@ -0,0 +1,148 @@
+//! Unit tests for placing nvtx-kernel ranges on their NVTX track
+
+use nsys_chrome::effective_config::effective_config;
+use nsys_chrome::linker::{link_nvtx_to_kernels, parse_nvtx_kernel_layout, unattributed_gpu_work};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions, NvtxKernelLayout};
+use nsys_chrome::presets::overlay_options;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(
+    name: &str,
+    cat: &str,
+    pid: &str,
+    tid: &str,
+    start_ns: i64,
+    end_ns: i64,
+) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        pid.to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+    .with_arg("deviceId", 1)
+    .with_arg("raw_tid", 42)
+}
+
+fn kernels() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_event("compute", "kernel", "Device 1", "Stream 7", 5000, 6000)
+            .with_arg("correlationId", 1)
+            .with_arg("streamId", 7),
+        create_event("copy", "kernel", "Device 1", "Stream 13", 5500, 9000)
+            .with_arg("correlationId", 2)
+            .with_arg("streamId", 13),
+    ]
+}
+
+/// One range on thread 42 of process 3 launching a kernel on each of two streams
+fn link(layout: NvtxKernelLayout, per_stream: bool) -> Vec<ChromeTraceEvent> {
+    let nvtx = vec![create_event(
+        "step",
+        "nvtx",
+        "Process 3",
+        "NVTX Thread 42",
+        0,
+        10_000,
+    )];
+    let api: Vec<ChromeTraceEvent> = (1..=2)
+        .map(|corr| {
+            create_event(
+                "cudaLaunchKernel",
+                "cuda_api",
+                "Process 3",
+                "CUDA API Thread 42",
+                corr * 1000,
+                corr * 1000 + 500,
+            )
+            .with_arg("correlationId", corr)
+        })
+        .collect();
+    let options = ConversionOptions {
+        nvtx_kernel_layout: layout,
+        nvtx_kernel_per_stream: per_stream,
+        ..Default::default()
+    };
+    link_nvtx_to_kernels(&nvtx, &api, &kernels(), &options).0
+}
+
+// ==========================
+// Tests for nvtx_kernel_layout
+// ==========================
+
+#[test]
+fn test_device_layout_is_default() {
+    assert_eq!(
+        ConversionOptions::default().nvtx_kernel_layout,
+        NvtxKernelLayout::Device
+    );
+    let linked = link(NvtxKernelLayout::Device, false);
+    assert_eq!(linked.len(), 1);
+    assert_eq!(linked[0].pid, "Device 1");
+    assert_eq!(linked[0].tid, "NVTX Kernel Thread 42");
+    assert!(!linked[0].args.contains_key("deviceId"));
+}
+
+#[test]
+fn test_annotation_layout_uses_nvtx_track() {
+    let linked = link(NvtxKernelLayout::Annotation, false);
+    assert_eq!(linked.len(), 1);
+    assert_eq!(linked[0].pid, "Process 3");
+    assert_eq!(linked[0].tid, "NVTX Thread 42");
+    assert_eq!(linked[0].args["deviceId"], 1);
+    assert_eq!(linked[0].ts, 5.0);
+    assert_eq!(linked[0].dur, Some(4.0));
+
+    // Per-stream ranges still get a track per stream
+    let mut tids: Vec<String> = link(NvtxKernelLayout::Annotation, true)
+        .iter()
+        .map(|e| e.tid.to_string())
+        .collect();
+    tids.sort();
+    assert_eq!(
+        tids,
+        vec!["NVTX Thread 42 Stream 13", "NVTX Thread 42 Stream 7",]
+    );
+}
+
+#[test]
+fn test_coverage_finds_device_of_annotation_ranges() {
+    let linked = link(NvtxKernelLayout::Annotation, false);
+    assert!(unattributed_gpu_work(&kernels(), &linked).is_empty());
+
+    // Without the ranges all kernel time on the device is unattributed
+    let work = unattributed_gpu_work(&kernels(), &[]);
+    assert_eq!(work.len(), 1);
+    assert_eq!(work[0].device_id, 1);
+}
+
+#[test]
+fn test_parse_nvtx_kernel_layout() {
+    assert_eq!(
+        parse_nvtx_kernel_layout("device").unwrap(),
+        NvtxKernelLayout::Device
+    );
+    assert_eq!(
+        parse_nvtx_kernel_layout("annotation").unwrap(),
+        NvtxKernelLayout::Annotation
+    );
+    assert!(parse_nvtx_kernel_layout("thread").is_err());
+
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"nvtx_kernel_layout": "annotation"}"#,
+    )
+    .unwrap();
+    assert_eq!(options.nvtx_kernel_layout, NvtxKernelLayout::Annotation);
+    assert_eq!(
+        effective_config(&options)["nvtx_kernel_layout"],
+        "annotation"
+    );
+}
@@ -0,0 +1,98 @@
+//! Unit tests for splitting nvtx-kernel ranges per CUDA stream
+
+mod common;
+
+use common::create_event;
+use nsys_chrome::linker::link_nvtx_to_kernels;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::presets::overlay_options;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_kernel(
+    name: &str,
+    start_ns: i64,
+    end_ns: i64,
+    corr: i64,
+    stream: i64,
+) -> ChromeTraceEvent {
+    create_event(name, "kernel", start_ns, end_ns, Some(corr)).with_arg("streamId", stream)
+}
+
+/// One range launching two kernels on stream 7 and one on stream 13
+fn link(per_stream: bool) -> Vec<ChromeTraceEvent> {
+    let nvtx = vec![create_event("pipeline", "nvtx", 0, 10_000, None)];
+    let api: Vec<ChromeTraceEvent> = (1..=3)
+        .map(|corr| {
+            create_event(
+                "cudaLaunchKernel",
+                "cuda_api",
+                corr * 1000,
+                corr * 1000 + 500,
+                Some(corr),
+            )
+        })
+        .collect();
+    let kernels = vec![
+        create_kernel("compute", 5000, 6000, 1, 7),
+        create_kernel("copy", 5500, 9000, 2, 13),
+        create_kernel("compute", 6000, 8000, 3, 7),
+    ];
+    let options = ConversionOptions {
+        nvtx_kernel_per_stream: per_stream,
+        ..Default::default()
+    };
+    link_nvtx_to_kernels(&nvtx, &api, &kernels, &options).0
+}
+
+// ==========================
+// Tests for nvtx_kernel_per_stream
+// ==========================
+
+#[test]
+fn test_single_range_spans_all_streams_by_default() {
+    let linked = link(false);
+    assert_eq!(linked.len(), 1);
+    assert_eq!(linked[0].ts, 5.0);
+    assert_eq!(linked[0].dur, Some(4.0));
+    assert_eq!(linked[0].tid, "NVTX Kernel Thread 1");
+    assert!(!linked[0].args.contains_key("streamId"));
+}
+
+#[test]
+fn test_one_range_per_stream_on_own_track() {
+    let linked = link(true);
+    let ranges: Vec<(&str, f64, Option<f64>, i64, f64)> = linked
+        .iter()
+        .map(|e| {
+            (
+                &*e.tid,
+                e.ts,
+                e.dur,
+                e.args["streamId"].as_i64().unwrap(),
+                e.args["gpu_busy_pct"].as_f64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            ("NVTX Kernel Thread 1 Stream 7", 5.0, Some(3.0), 7, 100.0),
+            ("NVTX Kernel Thread 1 Stream 13", 5.5, Some(3.5), 13, 100.0),
+        ]
+    );
+    assert!(linked.iter().all(|e| e.name == "pipeline"));
+}
+
+#[test]
+fn test_per_stream_option_key() {
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"nvtx_kernel_per_stream": true}"#,
+    )
+    .unwrap();
+    assert!(options.nvtx_kernel_per_stream);
+    assert!(!ConversionOptions::default().nvtx_kernel_per_stream);
+}
@@ -1,6 +1,7 @@
 //! Unit tests for NVTX linker module
 
-use nsys_chrome::linker::link_nvtx_to_kernels;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::linker::{flow_id, link_nvtx_to_kernels};
 use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
 use std::collections::HashMap;
 
@@ -37,7 +38,7 @@ fn create_cuda_api_event(
     end_ns: i64,
     device_id: i32,
     tid: i32,
-    correlation_id: i32,
+    correlation_id: i64,
 ) -> ChromeTraceEvent {
     ChromeTraceEvent::complete(
         name.to_string(),
@@ -61,7 +62,7 @@ fn create_kernel_event(
     end_ns: i64,
     device_id: i32,
     stream_id: i32,
-    correlation_id: i32,
+    correlation_id: i64,
 ) -> ChromeTraceEvent {
     ChromeTraceEvent::complete(
         name.to_string(),
@@ -285,7 +286,7 @@ fn test_link_nvtx_to_kernels_missing_time_fields() {
         "NVTX Thread 1".to_string(),
         "nvtx".to_string(),
     );
-    nvtx_event.args.insert("deviceId".to_string(), serde_json::json!(0));
+    nvtx_event.args.insert("deviceId".into(), serde_json::json!(0));
     // Missing start_ns and end_ns
 
     let cuda_api_event = create_cuda_api_event("cudaLaunchKernel", 110000, 130000, 0, 1, 12345);
@@ -321,13 +322,14 @@ fn test_link_nvtx_to_kernels_with_color_scheme() {
 
     let options = ConversionOptions {
         activity_types: vec![
-            "kernel".to_string(),
-            "nvtx".to_string(),
-            "nvtx-kernel".to_string(),
+            EventCategory::Kernel,
+            EventCategory::Nvtx,
+            EventCategory::NvtxKernel,
         ],
         nvtx_event_prefix: None,
         nvtx_color_scheme: color_scheme,
         include_metadata: true,
+        ..Default::default()
     };
 
     let (nvtx_kernel_events, _mapped_identifiers, _flow_events) =
@@ -357,13 +359,14 @@ fn test_link_nvtx_to_kernels_color_scheme_no_match() {
 
     let options = ConversionOptions {
         activity_types: vec![
-            "kernel".to_string(),
-            "nvtx".to_string(),
-            "nvtx-kernel".to_string(),
+            EventCategory::Kernel,
+            EventCategory::Nvtx,
+            EventCategory::NvtxKernel,
         ],
         nvtx_event_prefix: None,
         nvtx_color_scheme: color_scheme,
         include_metadata: true,
+        ..Default::default()
     };
 
     let (nvtx_kernel_events, _mapped_identifiers, _flow_events) =
@@ -430,13 +433,13 @@ fn test_link_nvtx_to_kernels_cuda_api_no_correlation() {
     );
     cuda_api_event
         .args
-        .insert("start_ns".to_string(), serde_json::json!(110000));
+        .insert("start_ns".into(), serde_json::json!(110000));
     cuda_api_event
         .args
-        .insert("end_ns".to_string(), serde_json::json!(130000));
+        .insert("end_ns".into(), serde_json::json!(130000));
     cuda_api_event
         .args
-        .insert("deviceId".to_string(), serde_json::json!(0));
+        .insert("deviceId".into(), serde_json::json!(0));
     // Missing correlationId
 
     let kernel_event = create_kernel_event("kernel", 140000, 180000, 0, 1, 12345);
@@ -499,10 +502,11 @@ fn test_link_nvtx_to_kernels_invalid_regex_pattern() {
     color_scheme.insert("[invalid(regex".to_string(), "thread_state_running".to_string()); // Invalid regex!
 
     let options = ConversionOptions {
-        activity_types: vec!["kernel".to_string(), "nvtx".to_string(), "nvtx-kernel".to_string()],
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx, EventCategory::NvtxKernel],
         nvtx_event_prefix: None,
         nvtx_color_scheme: color_scheme,
         include_metadata: true,
+        ..Default::default()
     };
 
     // Should not panic
@@ -531,10 +535,11 @@ fn test_link_nvtx_to_kernels_multiple_invalid_regex_patterns() {
     color_scheme.insert("(unclosed".to_string(), "color3".to_string());
 
     let options = ConversionOptions {
-        activity_types: vec!["kernel".to_string(), "nvtx".to_string(), "nvtx-kernel".to_string()],
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx, EventCategory::NvtxKernel],
         nvtx_event_prefix: None,
         nvtx_color_scheme: color_scheme,
         include_metadata: true,
+        ..Default::default()
     };
 
     // Should not panic
@@ -559,10 +564,10 @@ fn test_link_nvtx_to_kernels_device_id_as_string() {
         "NVTX Thread 1".to_string(),
         "nvtx".to_string(),
     );
-    nvtx_event.args.insert("deviceId".to_string(), serde_json::json!("0")); // String!
-    nvtx_event.args.insert("start_ns".to_string(), serde_json::json!(100000));
-    nvtx_event.args.insert("end_ns".to_string(), serde_json::json!(200000));
-    nvtx_event.args.insert("raw_tid".to_string(), serde_json::json!(1));
+    nvtx_event.args.insert("deviceId".into(), serde_json::json!("0")); // String!
+    nvtx_event.args.insert("start_ns".into(), serde_json::json!(100000));
+    nvtx_event.args.insert("end_ns".into(), serde_json::json!(200000));
+    nvtx_event.args.insert("raw_tid".into(), serde_json::json!(1));
 
     let cuda_api_event = create_cuda_api_event("cudaLaunchKernel", 110000, 130000, 0, 1, 12345);
     let kernel_event = create_kernel_event("kernel", 140000, 180000, 0, 1, 12345);
@@ -594,10 +599,10 @@ fn test_link_nvtx_to_kernels_cuda_api_device_id_as_string() {
         "CUDA API Thread 1".to_string(),
         "cuda_api".to_string(),
     );
-    cuda_api_event.args.insert("deviceId".to_string(), serde_json::json!("0")); // String!
-    cuda_api_event.args.insert("start_ns".to_string(), serde_json::json!(110000));
-    cuda_api_event.args.insert("end_ns".to_string(), serde_json::json!(130000));
-    cuda_api_event.args.insert("correlationId".to_string(), serde_json::json!(12345));
+    cuda_api_event.args.insert("deviceId".into(), serde_json::json!("0")); // String!
+    cuda_api_event.args.insert("start_ns".into(), serde_json::json!(110000));
+    cuda_api_event.args.insert("end_ns".into(), serde_json::json!(130000));
+    cuda_api_event.args.insert("correlationId".into(), serde_json::json!(12345));
 
     let kernel_event = create_kernel_event("kernel", 140000, 180000, 0, 1, 12345);
 
@@ -629,10 +634,10 @@ fn test_link_nvtx_to_kernels_kernel_device_id_as_string() {
         "Stream 1".to_string(),
         "kernel".to_string(),
     );
-    kernel_event.args.insert("deviceId".to_string(), serde_json::json!("0")); // String!
-    kernel_event.args.insert("start_ns".to_string(), serde_json::json!(140000));
-    kernel_event.args.insert("end_ns".to_string(), serde_json::json!(180000));
-    kernel_event.args.insert("correlationId".to_string(), serde_json::json!(12345));
+    kernel_event.args.insert("deviceId".into(), serde_json::json!("0")); // String!
+    kernel_event.args.insert("start_ns".into(), serde_json::json!(140000));
+    kernel_event.args.insert("end_ns".into(), serde_json::json!(180000));
+    kernel_event.args.insert("correlationId".into(), serde_json::json!(12345));
 
     let nvtx_events = vec![nvtx_event];
     let cuda_api_events = vec![cuda_api_event];
@@ -664,8 +669,8 @@ fn test_link_nvtx_to_kernels_nvtx_missing_device_id() {
         "nvtx".to_string(),
     );
     // No deviceId!
-    nvtx_event.args.insert("start_ns".to_string(), serde_json::json!(100000));
-    nvtx_event.args.insert("end_ns".to_string(), serde_json::json!(200000));
+    nvtx_event.args.insert("start_ns".into(), serde_json::json!(100000));
+    nvtx_event.args.insert("end_ns".into(), serde_json::json!(200000));
 
     let cuda_api_event = create_cuda_api_event("cudaLaunchKernel", 110000, 130000, 0, 1, 12345);
     let kernel_event = create_kernel_event("kernel", 140000, 180000, 0, 1, 12345);
@@ -698,9 +703,9 @@ fn test_link_nvtx_to_kernels_cuda_api_missing_device_id() {
         "cuda_api".to_string(),
     );
     // No deviceId!
-    cuda_api_event.args.insert("start_ns".to_string(), serde_json::json!(110000));
-    cuda_api_event.args.insert("end_ns".to_string(), serde_json::json!(130000));
-    cuda_api_event.args.insert("correlationId".to_string(), serde_json::json!(12345));
+    cuda_api_event.args.insert("start_ns".into(), serde_json::json!(110000));
+    cuda_api_event.args.insert("end_ns".into(), serde_json::json!(130000));
+    cuda_api_event.args.insert("correlationId".into(), serde_json::json!(12345));
 
     let kernel_event = create_kernel_event("kernel", 140000, 180000, 0, 1, 12345);
 
@@ -733,9 +738,9 @@ fn test_link_nvtx_to_kernels_kernel_missing_device_id() {
         "kernel".to_string(),
     );
     // No deviceId!
-    kernel_event.args.insert("start_ns".to_string(), serde_json::json!(140000));
-    kernel_event.args.insert("end_ns".to_string(), serde_json::json!(180000));
-    kernel_event.args.insert("correlationId".to_string(), serde_json::json!(12345));
+    kernel_event.args.insert("start_ns".into(), serde_json::json!(140000));
+    kernel_event.args.insert("end_ns".into(), serde_json::json!(180000));
+    kernel_event.args.insert("correlationId".into(), serde_json::json!(12345));
 
     let nvtx_events = vec![nvtx_event];
     let cuda_api_events = vec![cuda_api_event];
@@ -869,7 +874,7 @@ fn test_link_nvtx_to_kernels_all_events_missing_time_fields() {
         "Thread 1".to_string(),
         "nvtx".to_string(),
     );
-    nvtx.args.insert("deviceId".to_string(), serde_json::json!(0));
+    nvtx.args.insert("deviceId".into(), serde_json::json!(0));
     // No start_ns/end_ns
 
     let mut cuda_api = ChromeTraceEvent::complete(
@@ -880,8 +885,8 @@ fn test_link_nvtx_to_kernels_all_events_missing_time_fields() {
         "Thread 1".to_string(),
         "cuda_api".to_string(),
     );
-    cuda_api.args.insert("deviceId".to_string(), serde_json::json!(0));
-    cuda_api.args.insert("correlationId".to_string(), serde_json::json!(12345));
+    cuda_api.args.insert("deviceId".into(), serde_json::json!(0));
+    cuda_api.args.insert("correlationId".into(), serde_json::json!(12345));
     // No start_ns/end_ns
 
     let mut kernel = ChromeTraceEvent::complete(
@@ -892,8 +897,8 @@ fn test_link_nvtx_to_kernels_all_events_missing_time_fields() {
         "Thread 1".to_string(),
         "kernel".to_string(),
     );
-    kernel.args.insert("deviceId".to_string(), serde_json::json!(0));
-    kernel.args.insert("correlationId".to_string(), serde_json::json!(12345));
+    kernel.args.insert("deviceId".into(), serde_json::json!(0));
+    kernel.args.insert("correlationId".into(), serde_json::json!(12345));
     // No start_ns/end_ns
 
     let nvtx_events = vec![nvtx];
@@ -961,3 +966,96 @@ fn test_link_nvtx_to_kernels_multiple_cuda_api_one_matches() {
     assert_eq!(mapped_identifiers.len(), 1);
 }
 
+
+#[test]
+fn test_link_nvtx_to_kernels_correlation_beyond_i32() {
+    // Correlation IDs past i32::MAX must not wrap into a different ID
+    let large_id = i32::MAX as i64 + 5;
+    let wrapped_id = large_id as i32 as i64;
+    let nvtx_event = create_nvtx_event("forward", 100000, 300000, 0, 1);
+    let cuda_api_event = create_cuda_api_event("cudaLaunchKernel", 110000, 130000, 0, 1, large_id);
+    let wrong_kernel = create_kernel_event("wrong", 140000, 150000, 0, 1, wrapped_id);
+    let kernel_event = create_kernel_event("kernel", 160000, 180000, 0, 1, large_id);
+
+    let options = ConversionOptions::default();
+    let (nvtx_kernel_events, _, _) = link_nvtx_to_kernels(
+        &[nvtx_event],
+        &[cuda_api_event],
+        &[wrong_kernel, kernel_event],
+        &options,
+    );
+
+    assert_eq!(nvtx_kernel_events.len(), 1);
+    assert_eq!(nvtx_kernel_events[0].ts, 160.0);
+}
+
+#[test]
+fn test_link_nvtx_to_kernels_flow_ids_unique_across_devices() {
+    // The same correlation ID on two devices must produce distinct flows
+    let nvtx_events = vec![
+        create_nvtx_event("forward", 100000, 200000, 0, 1),
+        create_nvtx_event("forward", 100000, 200000, 1, 2),
+    ];
+    let cuda_api_events = vec![
+        create_cuda_api_event("cudaLaunchKernel", 110000, 130000, 0, 1, 42),
+        create_cuda_api_event("cudaLaunchKernel", 110000, 130000, 1, 2, 42),
+    ];
+    let kernel_events = vec![
+        create_kernel_event("kernel", 140000, 180000, 0, 1, 42),
+        create_kernel_event("kernel", 140000, 180000, 1, 1, 42),
+    ];
+
+    let options = ConversionOptions::default();
+    let (_, _, flow_events) =
+        link_nvtx_to_kernels(&nvtx_events, &cuda_api_events, &kernel_events, &options);
+
+    let ids: std::collections::HashSet<_> = flow_events
+        .iter()
+        .filter_map(|e| match &e.id {
+            Some(nsys_chrome::models::StringOrInt::Int(id)) => Some(*id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&flow_id(0, 42)));
+    assert!(ids.contains(&flow_id(1, 42)));
+}
+
+#[test]
+fn test_link_nvtx_to_kernels_gpu_busy_pct() {
+    // Kernels cover 80us of the 90us span (140-180, 190-230)
+    let nvtx_event = create_nvtx_event("forward", 100000, 300000, 0, 1);
+    let cuda_api_event = create_cuda_api_event("cudaLaunchKernel", 110000, 130000, 0, 1, 12345);
+    let kernel1 = create_kernel_event("kernel1", 140000, 180000, 0, 1, 12345);
+    let kernel2 = create_kernel_event("kernel2", 190000, 230000, 0, 1, 12345);
+
+    let options = ConversionOptions::default();
+    let (nvtx_kernel_events, _, _) = link_nvtx_to_kernels(
+        &[nvtx_event],
+        &[cuda_api_event],
+        &[kernel1, kernel2],
+        &options,
+    );
+
+    assert_eq!(nvtx_kernel_events.len(), 1);
+    assert_eq!(nvtx_kernel_events[0].args["gpu_busy_pct"], 88.9);
+}
+
+#[test]
+fn test_link_nvtx_to_kernels_gpu_busy_pct_concurrent_kernels() {
+    // Overlapping kernels on different streams are counted once
+    let nvtx_event = create_nvtx_event("forward", 100000, 300000, 0, 1);
+    let cuda_api_event = create_cuda_api_event("cudaLaunchKernel", 110000, 130000, 0, 1, 12345);
+    let kernel1 = create_kernel_event("kernel1", 140000, 200000, 0, 1, 12345);
+    let kernel2 = create_kernel_event("kernel2", 150000, 180000, 0, 2, 12345);
+
+    let options = ConversionOptions::default();
+    let (nvtx_kernel_events, _, _) = link_nvtx_to_kernels(
+        &[nvtx_event],
+        &[cuda_api_event],
+        &[kernel1, kernel2],
+        &options,
+    );
+
+    assert_eq!(nvtx_kernel_events[0].args["gpu_busy_pct"], 100.0);
+}
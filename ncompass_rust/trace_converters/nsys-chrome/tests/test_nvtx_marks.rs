@@ -0,0 +1,126 @@
+//! Unit tests for NVTX marks and their payload counters
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions, InstantScope};
+use nsys_chrome::parsers::{EventParser, NvtxMarkParser, ParseContext};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Two "step" marks with integer payloads, a "loss" mark with a double
+/// payload in the NCCL domain, a plain mark and a range
+const NVTX_MARKS_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'step');
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER, domainId INTEGER,
+        int64Value INTEGER, doubleValue REAL
+    );
+    INSERT INTO NVTX_EVENTS VALUES
+        (100, NULL, 'NCCL', NULL, 16777217, 75, 7, NULL, NULL),
+        (1000, NULL, NULL, 1, 16777217, 34, 0, 1, NULL),
+        (2000, NULL, NULL, 1, 16777217, 34, 0, 2, NULL),
+        (2500, NULL, 'loss', NULL, 16777217, 34, 7, NULL, 0.25),
+        (3000, NULL, 'checkpoint', NULL, 16777217, 34, 0, NULL, NULL),
+        (4000, 5000, 'forward', NULL, 16777217, 59, 0, NULL, NULL);
+";
+
+fn parse_marks(options: &ConversionOptions) -> Vec<ChromeTraceEvent> {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(NVTX_MARKS_SQL).unwrap();
+
+    let strings = HashMap::from([(1, "step".to_string())]);
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, options, &device_map, &thread_names);
+    NvtxMarkParser.safe_parse(&context).unwrap()
+}
+
+fn of_phase(events: &[ChromeTraceEvent], ph: ChromeTracePhase) -> Vec<&ChromeTraceEvent> {
+    events.iter().filter(|e| e.ph == ph).collect()
+}
+
+// ==========================
+// Tests for NvtxMarkParser
+// ==========================
+
+#[test]
+fn test_marks_become_thread_scoped_instants() {
+    let events = parse_marks(&ConversionOptions::default());
+    let marks = of_phase(&events, ChromeTracePhase::Instant);
+
+    let names: Vec<&str> = marks.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["step", "step", "loss", "checkpoint"]);
+    assert!(marks.iter().all(|e| e.cat == "nvtx-mark"));
+    assert_eq!(marks[0].s, Some(InstantScope::Thread));
+    assert_eq!(marks[0].ts, 1.0);
+    assert_eq!(marks[0].tid, "NVTX Thread 1");
+    assert_eq!(marks[0].args["payload"], 1.0);
+    assert_eq!(marks[2].args["domain"], "NCCL");
+    assert!(!marks[3].args.contains_key("payload"));
+
+    let json = serde_json::to_value(marks[0]).unwrap();
+    assert_eq!(json["ph"], "i");
+    assert_eq!(json["s"], "t");
+}
+
+#[test]
+fn test_mark_payloads_become_counters() {
+    let events = parse_marks(&ConversionOptions::default());
+    let counters = of_phase(&events, ChromeTracePhase::Counter);
+
+    let series: Vec<(&str, f64, f64)> = counters
+        .iter()
+        .map(|e| (e.name.as_str(), e.ts, e.args["value"].as_f64().unwrap()))
+        .collect();
+    assert_eq!(
+        series,
+        vec![("step", 1.0, 1.0), ("step", 2.0, 2.0), ("loss", 2.5, 0.25)]
+    );
+    assert!(counters.iter().all(|e| e.cat == "nvtx-counter"));
+}
+
+#[test]
+fn test_marks_follow_nvtx_filters() {
+    let options = ConversionOptions {
+        nvtx_domains: Some(vec!["NCCL".to_string()]),
+        ..Default::default()
+    };
+    let events = parse_marks(&options);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|e| e.name == "loss"));
+
+    let options = ConversionOptions {
+        nvtx_event_prefix: Some(vec!["check".to_string()]),
+        ..Default::default()
+    };
+    let events = parse_marks(&options);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "checkpoint");
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_keeps_marks_with_ranges() {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Nvtx],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = convert_sql(NVTX_MARKS_SQL, options);
+
+    let count = |cat: &str| events.iter().filter(|e| e.cat == cat).count();
+    assert_eq!(count("nvtx"), 1);
+    assert_eq!(count("nvtx-mark"), 4);
+    assert_eq!(count("nvtx-counter"), 3);
+}
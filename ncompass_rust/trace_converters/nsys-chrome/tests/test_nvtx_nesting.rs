@@ -0,0 +1,158 @@
+//! Unit tests for folding nested NVTX ranges
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::effective_config::effective_config;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions, NvtxNesting};
+use nsys_chrome::parsers::nvtx::{collapse_nested_ranges, parse_nvtx_nesting};
+use nsys_chrome::presets::overlay_options;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_range(name: &str, tid: &str, start_us: f64, dur_us: f64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_us,
+        dur_us,
+        "Process 1".to_string(),
+        tid.to_string(),
+        EventCategory::Nvtx,
+    )
+}
+
+/// `fib` recursing four levels deep around a `leaf` range, plus an unrelated
+/// `fib` on another thread
+fn recursive_ranges() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_range("fib", "NVTX Thread 1", 0.0, 100.0),
+        create_range("fib", "NVTX Thread 1", 10.0, 80.0),
+        create_range("fib", "NVTX Thread 1", 20.0, 60.0),
+        create_range("leaf", "NVTX Thread 1", 30.0, 10.0),
+        create_range("fib", "NVTX Thread 1", 50.0, 20.0),
+        create_range("fib", "NVTX Thread 2", 10.0, 80.0),
+    ]
+}
+
+fn names(events: &[ChromeTraceEvent]) -> Vec<(&str, &str)> {
+    events
+        .iter()
+        .map(|e| (e.name.as_str(), e.tid.as_str()))
+        .collect()
+}
+
+/// Three nested `recurse` ranges around a `work` range
+const NESTED_NVTX_SQL: &str = "
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES (1000, 9000, 'recurse', NULL, 16777217, 59);
+    INSERT INTO NVTX_EVENTS VALUES (2000, 8000, 'recurse', NULL, 16777217, 59);
+    INSERT INTO NVTX_EVENTS VALUES (3000, 7000, 'recurse', NULL, 16777217, 59);
+    INSERT INTO NVTX_EVENTS VALUES (4000, 5000, 'work', NULL, 16777217, 59);
+";
+
+// ==========================
+// Tests for collapse_nested_ranges
+// ==========================
+
+#[test]
+fn test_collapse_same_name_keeps_outermost_instance() {
+    let mut events = recursive_ranges();
+    let removed = collapse_nested_ranges(&mut events, NvtxNesting::CollapseSameName);
+
+    assert_eq!(removed, 3);
+    assert_eq!(
+        names(&events),
+        vec![
+            ("fib", "NVTX Thread 1"),
+            ("leaf", "NVTX Thread 1"),
+            ("fib", "NVTX Thread 2"),
+        ]
+    );
+    assert_eq!(events[0].args["collapsed_ranges"], 3);
+    assert_eq!(events[0].dur, Some(100.0));
+    assert!(!events[1].args.contains_key("collapsed_ranges"));
+    assert!(!events[2].args.contains_key("collapsed_ranges"));
+}
+
+#[test]
+fn test_max_depth_folds_into_deepest_kept_ancestor() {
+    let mut events = recursive_ranges();
+    assert_eq!(
+        collapse_nested_ranges(&mut events, NvtxNesting::MaxDepth(1)),
+        4
+    );
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].args["collapsed_ranges"], 4);
+
+    let mut events = recursive_ranges();
+    assert_eq!(
+        collapse_nested_ranges(&mut events, NvtxNesting::MaxDepth(2)),
+        3
+    );
+    assert_eq!(events.len(), 3);
+    // The second level absorbs everything below it
+    assert_eq!(events[1].ts, 10.0);
+    assert_eq!(events[1].args["collapsed_ranges"], 3);
+    assert!(!events[0].args.contains_key("collapsed_ranges"));
+}
+
+#[test]
+fn test_keep_leaves_ranges_untouched() {
+    let mut events = recursive_ranges();
+    assert_eq!(collapse_nested_ranges(&mut events, NvtxNesting::Keep), 0);
+    assert_eq!(events.len(), 6);
+    assert!(events
+        .iter()
+        .all(|e| !e.args.contains_key("collapsed_ranges")));
+}
+
+// ==========================
+// Tests for options
+// ==========================
+
+#[test]
+fn test_parse_nvtx_nesting() {
+    assert_eq!(parse_nvtx_nesting("keep").unwrap(), NvtxNesting::Keep);
+    assert_eq!(
+        parse_nvtx_nesting("collapse").unwrap(),
+        NvtxNesting::CollapseSameName
+    );
+    assert_eq!(
+        parse_nvtx_nesting("depth:3").unwrap(),
+        NvtxNesting::MaxDepth(3)
+    );
+    for value in ["depth:0", "depth:", "depth:x", "flatten"] {
+        assert!(parse_nvtx_nesting(value).is_err(), "{}", value);
+    }
+
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"nvtx_nesting": "depth:2"}"#,
+    )
+    .unwrap();
+    assert_eq!(options.nvtx_nesting, NvtxNesting::MaxDepth(2));
+    assert_eq!(effective_config(&options)["nvtx_nesting"], "depth:2");
+}
+
+#[test]
+fn test_converter_collapses_recursive_ranges() {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Nvtx],
+        include_metadata: false,
+        nvtx_nesting: NvtxNesting::CollapseSameName,
+        ..Default::default()
+    };
+    let mut events = convert_sql(NESTED_NVTX_SQL, options);
+    events.retain(|e| e.cat == "nvtx");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].name, "recurse");
+    assert_eq!(events[0].args["collapsed_ranges"], 2);
+    assert_eq!(events[1].name, "work");
+}
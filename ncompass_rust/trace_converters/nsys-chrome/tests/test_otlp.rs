@@ -0,0 +1,195 @@
+//! Unit tests for OTLP metrics export
+
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::otlp::{
+    conversion_metrics, parse_otlp_endpoint, push_otlp_metrics, OtlpEndpoint,
+    DEFAULT_OTLP_METRICS_PATH,
+};
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_kernel(
+    name: &str,
+    device: i64,
+    corr: i64,
+    start_ns: i64,
+    end_ns: i64,
+) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        format!("Device {}", device),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("deviceId", device)
+    .with_arg("correlationId", corr)
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+}
+
+fn create_launch(device: i64, corr: i64, start_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "cudaLaunchKernel".to_string(),
+        start_ns as f64 / 1000.0,
+        5.0,
+        format!("Device {}", device),
+        "CUDA API Thread 1".to_string(),
+        "cuda_api".to_string(),
+    )
+    .with_arg("deviceId", device)
+    .with_arg("correlationId", corr)
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", start_ns + 5_000)
+}
+
+/// Device 0 runs a GEMM and a softmax half of its span; device 1 one GEMM
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_launch(0, 1, 0),
+        create_kernel("ampere_sgemm_128x64_tn", 0, 1, 10_000, 30_000),
+        create_launch(0, 2, 20_000),
+        create_kernel("softmax_warp_forward", 0, 2, 70_000, 80_000),
+        // Same correlation ID in another process must not pair with device 0's launch
+        create_kernel("cutlass_gemm", 1, 2, 5_000, 6_000),
+    ]
+}
+
+/// Accept one request, answer with `status`, and return the request body
+fn serve_once(status: &'static str) -> (OtlpEndpoint, thread::JoinHandle<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim_end().is_empty() {
+                break;
+            }
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        let mut stream = stream;
+        write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+        (request_line, String::from_utf8(body).unwrap())
+    });
+    let endpoint = OtlpEndpoint {
+        host: "127.0.0.1".to_string(),
+        port,
+        path: DEFAULT_OTLP_METRICS_PATH.to_string(),
+    };
+    (endpoint, handle)
+}
+
+// ==========================
+// Tests for conversion_metrics
+// ==========================
+
+#[test]
+fn test_metrics_per_device_and_class() {
+    let metrics = conversion_metrics(&sample_events());
+
+    assert_eq!(metrics.devices.len(), 2);
+    assert_eq!(metrics.devices[0].device_id, 0);
+    assert_eq!(metrics.devices[0].gpu_time_ns, 30_000);
+    // 30 us of kernels over a 70 us span
+    assert_eq!(metrics.devices[0].utilization_pct, 42.9);
+    assert_eq!(metrics.devices[1].utilization_pct, 100.0);
+
+    assert_eq!(metrics.kernel_class_ns[&(0, "gemm")], 20_000);
+    assert_eq!(metrics.kernel_class_ns[&(0, "softmax")], 10_000);
+    assert_eq!(metrics.kernel_class_ns[&(1, "gemm")], 1_000);
+
+    assert_eq!(metrics.launches, 2);
+    assert_eq!(metrics.launch_latency_p99_ns, Some(50_000));
+}
+
+#[test]
+fn test_otlp_json_shape() {
+    let metrics = conversion_metrics(&sample_events());
+    let request = metrics.to_otlp_json(&[("git.commit".to_string(), "abc123".to_string())], 42);
+
+    let resource = &request["resourceMetrics"][0];
+    let attributes = resource["resource"]["attributes"].as_array().unwrap();
+    assert!(attributes
+        .contains(&json!({"key": "service.name", "value": {"stringValue": "nsys-chrome"}})));
+    assert!(attributes.contains(&json!({"key": "git.commit", "value": {"stringValue": "abc123"}})));
+
+    let metrics = resource["scopeMetrics"][0]["metrics"].as_array().unwrap();
+    let names: Vec<_> = metrics
+        .iter()
+        .map(|m| m["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "gpu.kernel.time",
+            "gpu.kernel_class.time",
+            "gpu.utilization",
+            "cuda.launch_latency.p99"
+        ]
+    );
+    let point = &metrics[0]["gauge"]["dataPoints"][0];
+    assert_eq!(point["asInt"], "30000");
+    assert_eq!(point["timeUnixNano"], "42");
+    assert_eq!(
+        point["attributes"][0],
+        json!({"key": "device.id", "value": {"intValue": "0"}})
+    );
+    assert_eq!(metrics[2]["gauge"]["dataPoints"][0]["asDouble"], 42.9);
+}
+
+// ==========================
+// Tests for endpoints and pushing
+// ==========================
+
+#[test]
+fn test_parse_otlp_endpoint() {
+    let endpoint = parse_otlp_endpoint("http://collector").unwrap();
+    assert_eq!(endpoint.port, 4318);
+    assert_eq!(endpoint.path, "/v1/metrics");
+
+    let endpoint = parse_otlp_endpoint("http://10.0.0.5:9000/otlp/v1/metrics/").unwrap();
+    assert_eq!(endpoint.host, "10.0.0.5");
+    assert_eq!(endpoint.port, 9000);
+    assert_eq!(endpoint.path, "/otlp/v1/metrics");
+
+    assert!(parse_otlp_endpoint("https://collector").is_err());
+    assert!(parse_otlp_endpoint("collector:4318").is_err());
+    assert!(parse_otlp_endpoint("http://collector:http").is_err());
+}
+
+#[test]
+fn test_push_posts_json() {
+    let (endpoint, handle) = serve_once("200 OK");
+    let metrics = conversion_metrics(&sample_events());
+    metrics.push(&endpoint, &[]).unwrap();
+
+    let (request_line, body) = handle.join().unwrap();
+    assert_eq!(request_line.trim_end(), "POST /v1/metrics HTTP/1.1");
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(body["resourceMetrics"][0]["scopeMetrics"].is_array());
+}
+
+#[test]
+fn test_push_fails_on_rejection() {
+    let (endpoint, handle) = serve_once("400 Bad Request");
+    let err = push_otlp_metrics(&endpoint, &json!({})).unwrap_err();
+    handle.join().unwrap();
+    assert!(err.to_string().contains("400 Bad Request"));
+}
@@ -0,0 +1,89 @@
+//! Unit tests for kernel duration outlier flagging
+
+use nsys_chrome::analysis::{flag_kernel_outliers, parse_outlier_factor, OUTLIER_COLOR};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::presets::overlay_options;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_kernel(name: &str, start_ns: i64, dur_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        dur_ns as f64 / 1000.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", start_ns + dur_ns)
+}
+
+/// One kernel per duration, launched 1 ms apart
+fn create_kernels(name: &str, durations_ns: &[i64]) -> Vec<ChromeTraceEvent> {
+    durations_ns
+        .iter()
+        .enumerate()
+        .map(|(i, &dur)| create_kernel(name, i as i64 * 1_000_000, dur))
+        .collect()
+}
+
+// ==========================
+// Tests for flag_kernel_outliers
+// ==========================
+
+#[test]
+fn test_flags_kernels_slower_than_factor_times_median() {
+    let mut events = create_kernels("gemm", &[1000, 1100, 900, 1000, 6000, 4900, 1000]);
+
+    assert_eq!(flag_kernel_outliers(&mut events, 5.0), 1);
+    let outlier = &events[4];
+    assert_eq!(outlier.cname.as_deref(), Some(OUTLIER_COLOR));
+    assert_eq!(outlier.args["outlier"], true);
+    assert_eq!(outlier.args["median_ns"], 1000);
+    assert_eq!(outlier.args["outlier_ratio"], 6.0);
+
+    // 4.9x the median is not enough
+    assert!(events[5].cname.is_none());
+    assert!(!events[5].args.contains_key("outlier"));
+}
+
+#[test]
+fn test_outliers_use_per_name_medians() {
+    let mut events = create_kernels("gemm", &[10_000, 10_000, 10_000, 10_000, 10_000]);
+    events.extend(create_kernels("relu", &[100, 120, 80, 100, 1000]));
+    // Too few launches for a trustworthy median
+    events.extend(create_kernels("rare", &[100, 100, 100, 10_000]));
+    let mut api_call = create_kernel("cudaLaunchKernel", 0, 100_000);
+    api_call.cat = "cuda_api".into();
+    events.push(api_call);
+
+    assert_eq!(flag_kernel_outliers(&mut events, 5.0), 1);
+    let flagged: Vec<&str> = events
+        .iter()
+        .filter(|e| e.args.contains_key("outlier"))
+        .map(|e| e.name.as_str())
+        .collect();
+    assert_eq!(flagged, vec!["relu"]);
+    assert_eq!(events[9].args["median_ns"], 100);
+}
+
+// ==========================
+// Tests for option parsing
+// ==========================
+
+#[test]
+fn test_outlier_factor_options() {
+    assert_eq!(parse_outlier_factor("5").unwrap(), 5.0);
+    assert_eq!(parse_outlier_factor(" 2.5 ").unwrap(), 2.5);
+    assert!(parse_outlier_factor("1").is_err());
+    assert!(parse_outlier_factor("five").is_err());
+
+    let options =
+        overlay_options(ConversionOptions::default(), r#"{"outlier_factor": 3}"#).unwrap();
+    assert_eq!(options.kernel_outlier_factor, Some(3.0));
+    assert!(overlay_options(ConversionOptions::default(), r#"{"outlier_factor": "3"}"#).is_err());
+    assert!(overlay_options(ConversionOptions::default(), r#"{"outlier_factor": 0.5}"#).is_err());
+}
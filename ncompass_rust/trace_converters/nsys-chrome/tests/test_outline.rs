@@ -0,0 +1,125 @@
+//! Unit tests for outline module
+
+use flate2::read::GzDecoder;
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::outline::{outline_path, OutlineEntry};
+use nsys_chrome::writer::ChromeTraceWriter;
+use std::io::Read;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, dur: f64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+    .with_arg("deviceId", serde_json::json!(0))
+}
+
+/// Sorted trace: a step containing a range that contains two kernels, then a trailing kernel
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_event("step 0", 10.0, 100.0, "Synthetic Steps", "nvtx")
+            .with_arg("step", serde_json::json!(0)),
+        create_event("forward", 10.0, 40.0, "NVTX Thread 1", "nvtx"),
+        create_event("gemm", 20.0, 10.0, "Stream 7", "kernel"),
+        create_event("relu", 50.0, 5.0, "Stream 7", "kernel"),
+        create_event("late", 200.0, 5.0, "Stream 7", "kernel"),
+    ]
+}
+
+fn slice<'a>(text: &'a str, entry: &OutlineEntry) -> &'a str {
+    &text[entry.slice_start as usize..entry.slice_end as usize]
+}
+
+// ==========================
+// Tests for TraceOutline
+// ==========================
+
+#[test]
+fn test_outline_offsets_point_at_events() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json");
+    let path = path.to_str().unwrap();
+    let outline = ChromeTraceWriter::write_with_outline(path, sample_events()).unwrap();
+    let text = std::fs::read_to_string(path).unwrap();
+
+    assert_eq!(outline.ranges().len(), 1);
+    assert_eq!(outline.steps().len(), 1);
+
+    let range = &outline.ranges()[0];
+    assert_eq!(range.name, "forward");
+    assert_eq!(range.device, Some(0));
+    assert_eq!((range.start_ns, range.end_ns), (10_000, 50_000));
+    let event: serde_json::Value =
+        serde_json::from_str(&text[range.offset as usize..(range.offset + range.length) as usize])
+            .unwrap();
+    assert_eq!(event["name"], "forward");
+}
+
+#[test]
+fn test_outline_slices_cover_events_in_range() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json");
+    let path = path.to_str().unwrap();
+    let outline = ChromeTraceWriter::write_with_outline(path, sample_events()).unwrap();
+    let text = std::fs::read_to_string(path).unwrap();
+
+    // The range slice starts at the first event with its timestamp
+    let range_slice = slice(&text, &outline.ranges()[0]);
+    assert!(range_slice.starts_with("{\"name\":\"step 0\""));
+    assert!(range_slice.contains("\"gemm\""));
+    assert!(range_slice.contains("\"relu\""));
+    assert!(!range_slice.contains("\"late\""));
+    assert!(range_slice.ends_with('}'));
+
+    // The slice is a comma-separated list of complete events
+    let parsed: Vec<serde_json::Value> =
+        serde_json::from_str(&format!("[{}]", range_slice)).unwrap();
+    assert_eq!(parsed.len(), 4);
+
+    let step_slice = slice(&text, &outline.steps()[0]);
+    assert!(!step_slice.contains("\"late\""));
+}
+
+#[test]
+fn test_outline_gz_offsets_are_uncompressed() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json.gz");
+    let path = path.to_str().unwrap();
+    let outline = ChromeTraceWriter::write_gz_with_outline(path, sample_events()).unwrap();
+
+    let mut text = String::new();
+    GzDecoder::new(std::fs::File::open(path).unwrap())
+        .read_to_string(&mut text)
+        .unwrap();
+
+    let step = &outline.steps()[0];
+    assert_eq!(step.name, "step 0");
+    assert!(text[step.offset as usize..].starts_with("{\"name\":\"step 0\""));
+    assert!(slice(&text, step).contains("\"relu\""));
+}
+
+#[test]
+fn test_outline_json_and_path() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json");
+    let outline =
+        ChromeTraceWriter::write_with_outline(path.to_str().unwrap(), sample_events()).unwrap();
+
+    let json = outline.to_json();
+    assert_eq!(json["version"], 1);
+    assert_eq!(json["offsets"], "uncompressed");
+    assert_eq!(json["ranges"][0]["cat"], "nvtx");
+    assert_eq!(json["steps"][0]["start_ns"], 10_000);
+
+    assert_eq!(outline_path("out/trace.json.gz"), "out/trace.outline.json");
+    assert_eq!(outline_path("trace"), "trace.outline.json");
+}
@@ -0,0 +1,122 @@
+//! Unit tests for peer-to-peer (NVLink) memcpy parsing
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions};
+use nsys_chrome::parsers::p2p::{nvlink_throughput_counter, NVLINK_COUNTER};
+use nsys_chrome::parsers::{EventParser, P2PParser, ParseContext};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Two overlapping P2P copies (0→1 and 1→0), a same-device DtoD copy and a HtoD copy
+const MEMCPY_SQL: &str = "
+    CREATE TABLE CUPTI_ACTIVITY_KIND_MEMCPY (
+        start INTEGER, end INTEGER, deviceId INTEGER, contextId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, bytes INTEGER, copyKind INTEGER,
+        srcKind INTEGER, dstKind INTEGER, srcDeviceId INTEGER, dstDeviceId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_MEMCPY VALUES
+        (1000, 3000, 0, 1, 7, 11, 16777216, 100000, 10, 2, 2, 0, 1),
+        (2000, 4000, 1, 1, 9, 12, 16777216, 50000, 10, 2, 2, 1, 0),
+        (5000, 6000, 0, 1, 7, 13, 16777216, 4096, 8, 2, 2, 0, 0),
+        (7000, 8000, 0, 1, 7, 14, 16777216, 4096, 1, 0, 2, NULL, 0);
+";
+
+fn parse_p2p(sql: &str) -> Vec<ChromeTraceEvent> {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(sql).unwrap();
+
+    let strings = HashMap::new();
+    let options = ConversionOptions::default();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names);
+    P2PParser.safe_parse(&context).unwrap()
+}
+
+fn counter_values(events: &[ChromeTraceEvent]) -> Vec<(f64, f64)> {
+    events
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Counter)
+        .map(|e| (e.ts, e.args["GB/s"].as_f64().unwrap()))
+        .collect()
+}
+
+// ==========================
+// Tests for P2PParser
+// ==========================
+
+#[test]
+fn test_p2p_parser_emits_link_tracks() {
+    let events = parse_p2p(MEMCPY_SQL);
+    let transfers: Vec<&ChromeTraceEvent> = events
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Complete)
+        .collect();
+
+    // Same-device and host copies are not peer-to-peer
+    assert_eq!(transfers.len(), 2);
+    assert_eq!(transfers[0].pid, "NVLink");
+    assert_eq!(transfers[0].tid, "NVLink Device0→Device1");
+    assert_eq!(transfers[1].tid, "NVLink Device1→Device0");
+    assert_eq!(transfers[0].cat, "nvlink");
+    assert_eq!(transfers[0].args["bytes"], 100000);
+    assert_eq!(transfers[0].args["bandwidth_gbps"], 50.0);
+    assert_eq!(transfers[1].args["srcDeviceId"], 1);
+    assert_eq!(transfers[1].args["dstDeviceId"], 0);
+}
+
+#[test]
+fn test_p2p_parser_missing_table() {
+    let events = parse_p2p("CREATE TABLE StringIds (id INTEGER, value TEXT);");
+    assert!(events.is_empty());
+}
+
+// ==========================
+// Tests for nvlink_throughput_counter
+// ==========================
+
+#[test]
+fn test_nvlink_throughput_counter_sums_overlapping_transfers() {
+    let events = parse_p2p(MEMCPY_SQL);
+    let counter = counter_values(&events);
+
+    // 50 GB/s from 1us, plus 25 GB/s between 2us and 3us
+    assert_eq!(
+        counter,
+        vec![(1.0, 50.0), (2.0, 75.0), (3.0, 25.0), (4.0, 0.0)]
+    );
+    let sample = events
+        .iter()
+        .find(|e| e.ph == ChromeTracePhase::Counter)
+        .unwrap();
+    assert_eq!(sample.name, NVLINK_COUNTER);
+    assert_eq!(sample.pid, "NVLink");
+
+    assert!(nvlink_throughput_counter(&[]).is_empty());
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_emits_nvlink_when_requested() {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Nvlink],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = convert_sql(MEMCPY_SQL, options);
+    assert_eq!(events.iter().filter(|e| e.cat == "nvlink").count(), 6);
+
+    // Not part of the default activity types
+    let events = convert_sql(MEMCPY_SQL, ConversionOptions::default());
+    assert!(!events.iter().any(|e| e.cat == "nvlink"));
+}
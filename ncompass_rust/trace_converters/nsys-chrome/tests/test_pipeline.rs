@@ -0,0 +1,163 @@
+//! Unit tests for the bounded producer → writer pipeline
+
+use nsys_chrome::pipeline::{spawn_writer, write_pipelined, PipelineConfig};
+use nsys_chrome::writer::WriteOptions;
+use nsys_chrome::{ChromeTraceEvent, ChromeTraceWriter, ConvertError};
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_events(count: usize) -> Vec<ChromeTraceEvent> {
+    (0..count)
+        .map(|i| {
+            ChromeTraceEvent::complete(
+                format!("kernel_{}", i),
+                i as f64 * 10.0,
+                5.0,
+                "Device 0".to_string(),
+                "Stream 7".to_string(),
+                "kernel".to_string(),
+            )
+        })
+        .collect()
+}
+
+fn read_events(path: &std::path::Path) -> Vec<serde_json::Value> {
+    let text = std::fs::read_to_string(path).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    json["traceEvents"].as_array().unwrap().clone()
+}
+
+/// Writer that sleeps on every write, standing in for slow compression
+struct SlowWriter(Vec<u8>);
+
+impl Write for SlowWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        thread::sleep(Duration::from_millis(2));
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer that always fails
+struct FailingWriter;
+
+impl Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// ==========================
+// Tests for write_pipelined
+// ==========================
+
+#[test]
+fn test_pipelined_output_matches_direct_write() {
+    let dir = TempDir::new().unwrap();
+    let direct = dir.path().join("direct.json");
+    let piped = dir.path().join("piped.json");
+
+    ChromeTraceWriter::write(direct.to_str().unwrap(), create_events(250)).unwrap();
+    let config = PipelineConfig {
+        capacity: 2,
+        batch_size: 16,
+    };
+    let (_, metrics) = write_pipelined(
+        std::fs::File::create(&piped).unwrap(),
+        create_events(250),
+        WriteOptions::default(),
+        config,
+    )
+    .unwrap();
+
+    assert_eq!(read_events(&direct), read_events(&piped));
+    assert_eq!(metrics.events, 250);
+    // 15 full batches and one of 10
+    assert_eq!(metrics.batches, 16);
+    assert!(metrics.peak_queued <= 2);
+}
+
+#[test]
+fn test_slow_writer_stalls_producer() {
+    let config = PipelineConfig {
+        capacity: 1,
+        batch_size: 1,
+    };
+    let (mut sender, writer) =
+        spawn_writer(SlowWriter(Vec::new()), WriteOptions::default(), config);
+    // Payloads larger than the writer's buffer make every event reach the slow writer
+    let payload = serde_json::json!("x".repeat(300 * 1024));
+    let events = create_events(20)
+        .into_iter()
+        .map(|e| e.with_arg("payload", payload.clone()));
+    sender.send_all(events).unwrap();
+    drop(sender);
+    let (_, metrics) = writer.finish().unwrap();
+
+    assert_eq!(metrics.events, 20);
+    assert!(metrics.stalled_sends > 0);
+    assert!(metrics.producer_stall > Duration::ZERO);
+    assert_eq!(metrics.peak_queued, 1);
+}
+
+#[test]
+fn test_multiple_producers() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json");
+    let config = PipelineConfig {
+        capacity: 4,
+        batch_size: 8,
+    };
+    let (sender, writer) = spawn_writer(
+        std::fs::File::create(&path).unwrap(),
+        WriteOptions::default(),
+        config,
+    );
+
+    let producers: Vec<_> = (0..3)
+        .map(|_| {
+            let mut sender = sender.clone();
+            thread::spawn(move || sender.send_all(create_events(50)).unwrap())
+        })
+        .collect();
+    drop(sender);
+    for producer in producers {
+        producer.join().unwrap();
+    }
+    let (_, metrics) = writer.finish().unwrap();
+
+    assert_eq!(metrics.events, 150);
+    assert_eq!(read_events(&path).len(), 150);
+}
+
+#[test]
+fn test_writer_error_is_reported() {
+    let config = PipelineConfig {
+        capacity: 1,
+        batch_size: 4,
+    };
+    let result = write_pipelined(
+        FailingWriter,
+        create_events(100),
+        WriteOptions::default(),
+        config,
+    );
+    match result {
+        Err(ConvertError::Output(e)) => assert_eq!(e.to_string(), "disk full"),
+        other => panic!("Expected an output error, got {:?}", other.map(|_| ())),
+    }
+}
@@ -0,0 +1,95 @@
+//! Unit tests for option presets and JSON overlays
+
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ConversionOptions, LinkPolicy};
+use nsys_chrome::presets::{load_overlay, overlay_options, parse_preset, Preset};
+use tempfile::TempDir;
+
+// ==========================
+// Tests for Preset
+// ==========================
+
+#[test]
+fn test_presets_select_use_case_options() {
+    let training = Preset::Training.options();
+    assert!(training.synthesize_steps);
+    assert!(training.api_thread_states);
+    assert_eq!(
+        training.activity_types,
+        ConversionOptions::default().activity_types
+    );
+    assert_eq!(training.nvtx_color_scheme.len(), 3);
+
+    let inference = Preset::Inference.options();
+    assert!(inference.activity_types.contains(&EventCategory::Memcpy));
+    assert!(inference.infer_layers && inference.estimate_kernel_costs);
+    assert_eq!(inference.link_policy, LinkPolicy::Innermost);
+
+    let comm = Preset::CommDebug.options();
+    for activity in ["nvlink", "mpi", "memcpy"] {
+        assert!(comm.activity_types.contains(&EventCategory::from(activity)));
+    }
+    assert!(comm.nvtx_domain_tracks);
+}
+
+#[test]
+fn test_parse_preset() {
+    for preset in Preset::ALL {
+        assert_eq!(parse_preset(preset.name()).unwrap(), preset);
+    }
+    let err = parse_preset("debug").unwrap_err();
+    assert!(err.to_string().contains("comm-debug"));
+}
+
+// ==========================
+// Tests for overlays
+// ==========================
+
+#[test]
+fn test_overlay_extends_preset() {
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"preset": "training", "nvtx_colors": {"^loss": "bad"},
+            "min_duration": "2us", "synthesize_steps": false}"#,
+    )
+    .unwrap();
+    assert_eq!(options.nvtx_color_scheme.len(), 4);
+    assert_eq!(options.nvtx_color_scheme["^loss"], "bad");
+    assert_eq!(options.min_kernel_duration_ns, 2000);
+    assert!(!options.synthesize_steps);
+    assert!(options.api_thread_states);
+
+    // Without a preset key the base is kept
+    let options = overlay_options(Preset::Inference.options(), r#"{"jobs": 4}"#).unwrap();
+    assert_eq!(options.jobs, 4);
+    assert!(options.infer_layers);
+
+    for bad in [
+        r#"{"preset": "serving"}"#,
+        r#"{"nvtx_colors": ["bad"]}"#,
+        r#"{"synthesise_steps": true}"#,
+    ] {
+        assert!(
+            overlay_options(ConversionOptions::default(), bad).is_err(),
+            "{}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn test_load_overlay_from_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("options.json");
+    std::fs::write(&path, r#"{"activity_types": ["kernel"]}"#).unwrap();
+
+    let options = load_overlay(Preset::CommDebug.options(), &path).unwrap();
+    assert_eq!(options.activity_types, vec![EventCategory::Kernel]);
+    assert!(options.nvtx_domain_tracks);
+
+    let err = load_overlay(
+        ConversionOptions::default(),
+        dir.path().join("missing.json"),
+    );
+    assert!(err.is_err());
+}
@@ -0,0 +1,130 @@
+//! Unit tests for query module
+
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::query::{run_query_shell, QueryResult, TraceDatabase};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, dur: f64, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        cat.to_string(),
+    )
+}
+
+fn sample_db() -> TraceDatabase {
+    let events = vec![
+        ChromeTraceEvent::metadata(
+            "process_name".to_string(),
+            "Device 0".to_string(),
+            String::new(),
+            HashMap::from([("name".to_string(), json!("Device 0"))]),
+        ),
+        create_event("gemm", 0.0, 30.0, "kernel").with_arg("correlationId", 1),
+        create_event("relu", 30.0, 5.0, "kernel").with_arg("correlationId", 2),
+        create_event("gemm", 40.0, 20.0, "kernel").with_arg("correlationId", 3),
+        create_event("forward", 0.0, 70.0, "nvtx"),
+    ];
+    TraceDatabase::from_events(&events).unwrap()
+}
+
+// ==========================
+// Tests for TraceDatabase
+// ==========================
+
+#[test]
+fn test_query_aggregates_slices() {
+    let db = sample_db();
+    assert_eq!(db.len().unwrap(), 5);
+
+    let result = db
+        .query(
+            "SELECT name, SUM(dur) AS total FROM slices WHERE cat = 'kernel'
+             GROUP BY name ORDER BY 2 DESC",
+        )
+        .unwrap();
+    assert_eq!(result.columns, vec!["name", "total"]);
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![json!("gemm"), json!(50.0)],
+            vec![json!("relu"), json!(5.0)],
+        ]
+    );
+
+    let ids = db
+        .query("SELECT json_extract(args, '$.correlationId') FROM slices WHERE name = 'gemm'")
+        .unwrap();
+    assert_eq!(ids.rows, vec![vec![json!(1)], vec![json!(3)]]);
+    assert!(db.query("SELECT * FROM missing").is_err());
+}
+
+#[test]
+fn test_from_value_accepts_json_traces() {
+    let trace = json!({"traceEvents": [
+        {"name": "k", "ph": "X", "ts": 1.5, "dur": 2, "pid": 0, "tid": 7, "cat": "kernel"},
+        {"name": "i", "ph": "i", "ts": 2.0, "pid": 0, "tid": 7},
+    ]});
+    let db = TraceDatabase::from_value(&trace).unwrap();
+    let result = db.query("SELECT pid, tid, dur FROM slices").unwrap();
+    assert_eq!(result.rows, vec![vec![json!("0"), json!("7"), json!(2.0)]]);
+    assert_eq!(
+        db.query("SELECT COUNT(*) FROM events").unwrap().rows[0][0],
+        2
+    );
+
+    assert!(TraceDatabase::from_value(&json!({"events": []})).is_err());
+}
+
+// ==========================
+// Tests for rendering
+// ==========================
+
+#[test]
+fn test_render_table_and_csv() {
+    let result = QueryResult {
+        columns: vec!["name".to_string(), "dur".to_string()],
+        rows: vec![
+            vec![json!("gemm, fused"), json!(30.5)],
+            vec![json!("relu"), Value::Null],
+            vec![json!("line\r\nbreak"), json!(1)],
+        ],
+    };
+    assert_eq!(
+        result.render_table(),
+        "name         dur\n-----------  ----\ngemm, fused  30.5\nrelu\nline\r\nbreak  1\n(3 rows)\n"
+    );
+    assert_eq!(
+        result.render_csv(),
+        "name,dur\n\"gemm, fused\",30.5\nrelu,\n\"line\r\nbreak\",1\n"
+    );
+}
+
+// ==========================
+// Tests for run_query_shell
+// ==========================
+
+#[test]
+fn test_query_shell_runs_statements_and_reports_errors() {
+    let db = sample_db();
+    let input = "help\nSELECT COUNT(*) AS n FROM slices WHERE name IN (\n'gemm', 'relu')\nSELEC 1\nquit\nSELECT 2\n";
+    let mut output = Vec::new();
+    run_query_shell(&db, input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.starts_with("Loaded 5 events."));
+    assert!(output.contains("slices   complete events"));
+    assert!(output.contains("...> "));
+    assert!(output.contains("n\n-\n3\n(1 row)\n"));
+    assert!(output.contains("Error: "));
+    // Nothing runs after quit
+    assert_eq!(output.matches("(1 row)").count(), 1);
+}
@@ -0,0 +1,115 @@
+//! Unit tests for reading converted traces back into events
+
+use nsys_chrome::models::{
+    BindingPoint, ChromeTraceEvent, ChromeTracePhase, InstantScope, StringOrInt,
+};
+use nsys_chrome::{ChromeTraceReader, ChromeTraceWriter, ConvertError};
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    let launch = ChromeTraceEvent::complete(
+        "cudaLaunchKernel".to_string(),
+        10.0,
+        2.5,
+        "Device 0".to_string(),
+        "CUDA API Thread 7".to_string(),
+        "cuda_api".to_string(),
+    )
+    .with_arg("correlationId", 42);
+    let kernel = ChromeTraceEvent::complete(
+        "gemm".to_string(),
+        15.0,
+        100.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("grid", serde_json::json!([128, 1, 1]));
+    let marker = ChromeTraceEvent::new(
+        "checkpoint".to_string(),
+        ChromeTracePhase::Instant,
+        20.0,
+        "Device 0",
+        "Stream 7",
+        "marker",
+    )
+    .with_scope(InstantScope::Thread);
+    vec![
+        ChromeTraceEvent::flow_start(10.0, "Device 0", "CUDA API Thread 7", StringOrInt::Int(42)),
+        ChromeTraceEvent::flow_finish(
+            15.0,
+            "Device 0",
+            "Stream 7",
+            StringOrInt::Int(42),
+            BindingPoint::Enclosing,
+        ),
+        launch,
+        kernel,
+        marker,
+    ]
+}
+
+fn to_json(events: &[ChromeTraceEvent]) -> serde_json::Value {
+    serde_json::to_value(events).unwrap()
+}
+
+// ==========================
+// Tests for ChromeTraceReader
+// ==========================
+
+#[test]
+fn test_read_round_trips_written_trace() {
+    let dir = TempDir::new().unwrap();
+    for name in ["trace.json", "trace.json.gz"] {
+        let path = dir.path().join(name);
+        ChromeTraceWriter::write_auto(path.to_str().unwrap(), sample_events()).unwrap();
+
+        let events = ChromeTraceReader::read(&path).unwrap();
+        assert_eq!(to_json(&events), to_json(&sample_events()), "{}", name);
+        assert_eq!(events[1].bp, Some(BindingPoint::Enclosing));
+        assert_eq!(events[3].dur, Some(100.0));
+        assert_eq!(events[4].s, Some(InstantScope::Thread));
+    }
+}
+
+#[test]
+fn test_read_bare_array_with_numeric_ids() {
+    let json = r#"[
+        {"name": "k", "ph": "X", "ts": 1.5, "dur": 2, "pid": 3, "tid": 12, "cat": "kernel",
+         "args": {"nested": {"a": [1, 2]}}, "unknown_field": true},
+        {"name": "process_name", "ph": "M", "pid": 3, "args": {"name": "GPU"}},
+        {"ph": "I", "ts": 4, "pid": "p", "tid": "t", "s": "g", "id": "x-1"}
+    ]"#;
+    let events = ChromeTraceReader::from_slice(json.as_bytes()).unwrap();
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].pid, "3");
+    assert_eq!(events[0].tid, "12");
+    assert_eq!(events[0].args["nested"]["a"][1], 2);
+    assert_eq!(events[1].ph, ChromeTracePhase::Metadata);
+    assert_eq!(events[1].tid, "");
+    assert_eq!(events[1].cat, "");
+    assert_eq!(events[2].ph, ChromeTracePhase::Instant);
+    assert_eq!(events[2].s, Some(InstantScope::Global));
+    assert_eq!(events[2].id, Some(StringOrInt::String("x-1".to_string())));
+
+    // Track IDs are written back as strings unless mapped by --numeric-ids
+    let written = serde_json::to_value(&events[0]).unwrap();
+    assert_eq!(written["pid"], "3");
+}
+
+#[test]
+fn test_read_rejects_non_traces() {
+    let err = ChromeTraceReader::from_slice(br#"{"events": []}"#).unwrap_err();
+    assert!(matches!(err, ConvertError::InvalidInput(_)), "{:?}", err);
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("broken.json");
+    std::fs::write(&path, r#"{"traceEvents": [{"name": "k"}]}"#).unwrap();
+    let err = ChromeTraceReader::read(&path).unwrap_err();
+    assert!(err.to_string().contains("broken.json"), "{}", err);
+}
@@ -0,0 +1,143 @@
+//! Unit tests for the HTML report
+
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::report::{escape_html, report_path, ReportOptions, TraceReport};
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, cat: &str, pid: &str, ts: f64, dur: f64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        pid.to_string(),
+        "Stream 7".to_string(),
+        cat.to_string(),
+    )
+}
+
+/// Device 0 busy for the first half of a 100 us span, device 1 for the
+/// last quarter, with one GPU and one CPU NVTX range
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_event("gemm", "kernel", "Device 0", 0.0, 30.0),
+        create_event("relu", "kernel", "Device 0", 20.0, 30.0),
+        create_event("gemm", "kernel", "Device 1", 75.0, 25.0),
+        create_event("forward", "nvtx-kernel", "Device 0", 0.0, 50.0),
+        create_event("forward", "nvtx", "Process 1", 0.0, 40.0),
+    ]
+}
+
+fn options(bins: usize) -> ReportOptions {
+    ReportOptions {
+        title: "Run <1>".to_string(),
+        bins,
+        ..Default::default()
+    }
+}
+
+// ==========================
+// Tests for TraceReport
+// ==========================
+
+#[test]
+fn test_report_aggregates_kernels_and_ranges() {
+    let report = TraceReport::build(&sample_events(), &options(4));
+
+    assert_eq!(report.kernels, 3);
+    assert_eq!(report.events, 5);
+    assert_eq!(report.kernel_us, 85.0);
+    assert_eq!(report.span_us, (0.0, 100.0));
+
+    let top: Vec<(&str, usize, f64)> = report
+        .top_kernels
+        .iter()
+        .map(|s| (s.name.as_str(), s.count, s.total_us))
+        .collect();
+    assert_eq!(top, vec![("gemm", 2, 55.0), ("relu", 1, 30.0)]);
+
+    let ranges: Vec<(&str, &str)> = report
+        .nvtx_ranges
+        .iter()
+        .map(|s| (s.category.as_str(), s.name.as_str()))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![("nvtx-kernel", "forward"), ("nvtx", "forward")]
+    );
+}
+
+#[test]
+fn test_report_utilization_counts_overlaps_once() {
+    let report = TraceReport::build(&sample_events(), &options(4));
+
+    assert_eq!(report.bin_us, 25.0);
+    let devices: Vec<(&str, f64, Vec<f64>)> = report
+        .utilization
+        .iter()
+        .map(|d| (d.device.as_str(), d.busy_us, d.busy_pct.clone()))
+        .collect();
+    assert_eq!(
+        devices,
+        vec![
+            ("Device 0", 50.0, vec![100.0, 100.0, 0.0, 0.0]),
+            ("Device 1", 25.0, vec![0.0, 0.0, 0.0, 100.0]),
+        ]
+    );
+}
+
+#[test]
+fn test_report_html_is_self_contained_and_escaped() {
+    let mut events = sample_events();
+    events.push(create_event(
+        "kernel<</script>>",
+        "kernel",
+        "Device 0",
+        60.0,
+        1.0,
+    ));
+    let html = TraceReport::build(&events, &options(10)).render_html();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Run &lt;1&gt;</title>"));
+    assert!(html.contains("kernel&lt;&lt;/script&gt;&gt;"));
+    assert_eq!(html.matches("</script>").count(), 1);
+    assert!(html.contains("const UTILIZATION = "));
+    assert!(!html.contains("src=\"http"));
+    assert!(!html.contains("href=\"http"));
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.report.html");
+    let report = TraceReport::build(&events, &options(10));
+    report.write(path.to_str().unwrap()).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), html);
+}
+
+#[test]
+fn test_report_without_kernels() {
+    let events = vec![create_event("forward", "nvtx", "Process 1", 0.0, 40.0)];
+    let report = TraceReport::build(&events, &ReportOptions::default());
+
+    assert_eq!(report.kernels, 0);
+    assert!(report.utilization.is_empty());
+    assert!(report.render_html().contains("No kernels in this trace."));
+}
+
+// ==========================
+// Tests for helpers
+// ==========================
+
+#[test]
+fn test_report_path_and_escaping() {
+    assert_eq!(report_path("run.json.gz"), "run.report.html");
+    assert_eq!(report_path("out/run.json"), "out/run.report.html");
+    assert_eq!(report_path("run.nsys-rep"), "run.report.html");
+    assert_eq!(report_path("run.sqlite"), "run.report.html");
+    assert_eq!(
+        escape_html("a<b & \"c\"'"),
+        "a&lt;b &amp; &quot;c&quot;&#39;"
+    );
+}
@@ -0,0 +1,202 @@
+//! Unit tests for the rocprof front-end
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::frontends::rocprof::{is_rocprof_json, unsupported_rocprof_format};
+use nsys_chrome::frontends::{assemble_trace, RocprofReader};
+use nsys_chrome::models::ConversionOptions;
+use serde_json::json;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// rocprofv3 JSON with one GPU, one roctx range, one HIP launch and its kernel
+fn rocprof_json() -> serde_json::Value {
+    json!({
+        "rocprofiler-sdk-tool": [{
+            "metadata": {"pid": 4242},
+            "agents": [
+                {"id": {"handle": 100}, "type": 1, "logical_node_type_id": 0},
+                {"id": {"handle": 200}, "type": 2, "logical_node_type_id": 1, "name": "gfx942"}
+            ],
+            "kernel_symbols": [
+                {"kernel_id": 7, "kernel_name": "_Z6matmulPfS_S_.kd", "truncated_kernel_name": "matmul"}
+            ],
+            "strings": {
+                "buffer_records": [
+                    {"kind": "HIP_RUNTIME_API", "operations": ["hipMalloc", "hipLaunchKernel"]}
+                ],
+                "marker_api": [{"key": 1, "value": "forward"}]
+            },
+            "buffer_records": {
+                "kernel_dispatch": [{
+                    "correlation_id": {"internal": 2, "external": 0},
+                    "start_timestamp": 140000,
+                    "end_timestamp": 180000,
+                    "thread_id": 4243,
+                    "dispatch_info": {
+                        "agent_id": {"handle": 200},
+                        "queue_id": {"handle": 9000},
+                        "kernel_id": 7,
+                        "grid_size": {"x": 1024, "y": 1, "z": 1},
+                        "workgroup_size": {"x": 256, "y": 1, "z": 1}
+                    }
+                }],
+                "hip_api": [{
+                    "kind": 3,
+                    "operation": 1,
+                    "correlation_id": {"internal": 2, "external": 0},
+                    "start_timestamp": 110000,
+                    "end_timestamp": 130000,
+                    "thread_id": 4243
+                }],
+                "marker_api": [{
+                    "operation": 2,
+                    "correlation_id": {"internal": 1, "external": 0},
+                    "start_timestamp": 100000,
+                    "end_timestamp": 200000,
+                    "thread_id": 4243
+                }]
+            }
+        }]
+    })
+}
+
+// ==========================
+// Tests for RocprofReader
+// ==========================
+
+#[test]
+fn test_rocprof_reader_maps_records() {
+    let trace = RocprofReader::from_value(rocprof_json())
+        .unwrap()
+        .read()
+        .unwrap();
+
+    assert_eq!(trace.kernel_events.len(), 1);
+    let kernel = &trace.kernel_events[0];
+    assert_eq!(kernel.name, "matmul");
+    assert_eq!(kernel.pid, "Device 1");
+    assert_eq!(kernel.tid, "Stream 0");
+    assert_eq!(kernel.args["correlationId"], 2);
+    assert_eq!(kernel.args["grid"], json!([4, 1, 1]));
+    assert_eq!(kernel.args["block"], json!([256, 1, 1]));
+
+    assert_eq!(trace.api_events.len(), 1);
+    let api = &trace.api_events[0];
+    assert_eq!(api.name, "hipLaunchKernel");
+    assert_eq!(api.pid, "Device 1");
+    assert_eq!(api.tid, "HIP API Thread 4243");
+    assert_eq!(api.cat, "cuda_api");
+    assert_eq!(api.args["deviceId"], 1);
+
+    assert_eq!(trace.annotation_events.len(), 1);
+    let marker = &trace.annotation_events[0];
+    assert_eq!(marker.name, "forward");
+    assert_eq!(marker.cat, "nvtx");
+    assert!(!marker.args.contains_key("correlationId"));
+}
+
+#[test]
+fn test_rocprof_reader_rejects_other_json() {
+    assert!(RocprofReader::from_value(json!({"traceEvents": []})).is_err());
+}
+
+#[test]
+fn test_is_rocprof_json() {
+    let dir = TempDir::new().unwrap();
+    let rocprof = dir.path().join("results.json");
+    std::fs::write(&rocprof, rocprof_json().to_string()).unwrap();
+    let other = dir.path().join("trace.json");
+    std::fs::write(&other, r#"{"traceEvents": []}"#).unwrap();
+
+    assert!(is_rocprof_json(&rocprof));
+    assert!(!is_rocprof_json(&other));
+    assert!(!is_rocprof_json(&dir.path().join("missing.json")));
+}
+
+#[test]
+fn test_rocprof_csv_and_rocpd_are_rejected() {
+    let dir = TempDir::new().unwrap();
+    let csv = dir.path().join("kernel_trace.csv");
+    std::fs::write(
+        &csv,
+        "\"Kind\",\"Agent_Id\",\"Kernel_Name\",\"Correlation_Id\",\"Start_Timestamp\",\"End_Timestamp\"\n\
+         \"KERNEL_DISPATCH\",1,\"matmul\",1,100,200\n",
+    )
+    .unwrap();
+    let rocpd = create_sqlite(
+        &dir,
+        "results.db",
+        "CREATE TABLE rocpd_op (id INTEGER PRIMARY KEY, start INTEGER);",
+    );
+    let nsys = create_sqlite(
+        &dir,
+        "report.sqlite",
+        "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);",
+    );
+
+    assert_eq!(unsupported_rocprof_format(&csv), Some("rocprof CSV trace"));
+    assert_eq!(
+        unsupported_rocprof_format(rocpd.as_ref()),
+        Some("rocpd SQLite database")
+    );
+    assert_eq!(unsupported_rocprof_format(nsys.as_ref()), None);
+
+    let err = RocprofReader::open(&csv).err().unwrap().to_string();
+    assert!(
+        err.starts_with("Invalid input: Unsupported rocprof format"),
+        "{}",
+        err
+    );
+    assert!(err.contains("rocprof CSV trace"));
+    let err = RocprofReader::open(&rocpd).err().unwrap().to_string();
+    assert!(err.contains("rocpd SQLite database"), "{}", err);
+}
+
+// ==========================
+// Tests for assemble_trace
+// ==========================
+
+#[test]
+fn test_assemble_trace_links_roctx_to_kernels() {
+    let trace = RocprofReader::from_value(rocprof_json())
+        .unwrap()
+        .read()
+        .unwrap();
+    let events = assemble_trace(trace, &ConversionOptions::default()).unwrap();
+
+    let linked = events.iter().find(|e| e.cat == "nvtx-kernel").unwrap();
+    assert_eq!(linked.name, "forward");
+    assert_eq!(linked.pid, "Device 1");
+    assert_eq!(linked.ts, 140.0);
+    assert_eq!(linked.dur, Some(40.0));
+
+    // The linked roctx range is replaced by its nvtx-kernel event
+    assert!(!events.iter().any(|e| e.cat == "nvtx"));
+    assert!(events
+        .iter()
+        .any(|e| e.name == "process_name" && e.pid == "Device 1"));
+}
+
+#[test]
+fn test_assemble_trace_honors_activity_types_and_prefix() {
+    let trace = RocprofReader::from_value(rocprof_json())
+        .unwrap()
+        .read()
+        .unwrap();
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
+        nvtx_event_prefix: Some(vec!["back".to_string()]),
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = assemble_trace(trace, &options).unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].cat, "kernel");
+}
@@ -0,0 +1,225 @@
+//! Unit tests for routing event categories to separate outputs
+
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, OutputRoute};
+use nsys_chrome::routing::{duration_stats, summary_events, MultiSinkWriter, RouteFormat};
+use nsys_chrome::ConvertError;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Read;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, dur: f64, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        cat.to_string(),
+    )
+}
+
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    let mut counter = ChromeTraceEvent::new(
+        "GPU Utilization".to_string(),
+        ChromeTracePhase::Counter,
+        0.0,
+        "Device 0".to_string(),
+        String::new(),
+        "gpu_metrics".to_string(),
+    );
+    counter.args.insert("value".into(), json!(50));
+    vec![
+        ChromeTraceEvent::metadata(
+            "process_name".to_string(),
+            "Device 0".to_string(),
+            String::new(),
+            HashMap::from([("name".to_string(), json!("Device 0"))]),
+        ),
+        counter,
+        create_event("gemm", 0.0, 10.0, "kernel"),
+        create_event("forward", 0.0, 30.0, "nvtx-kernel"),
+        create_event("gemm", 20.0, 30.0, "kernel"),
+        create_event("cudaLaunchKernel", 1.0, 2.0, "cuda_api"),
+    ]
+}
+
+fn route(spec: &str, dir: &TempDir) -> OutputRoute {
+    let (categories, file) = spec.split_once('=').unwrap();
+    let path = dir.path().join(file);
+    OutputRoute::parse(&format!("{}={}", categories, path.display())).unwrap()
+}
+
+fn read_trace(path: &str) -> Vec<serde_json::Value> {
+    let mut text = String::new();
+    if path.ends_with(".gz") {
+        flate2::read::GzDecoder::new(std::fs::File::open(path).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+    } else {
+        text = std::fs::read_to_string(path).unwrap();
+    }
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    json["traceEvents"].as_array().unwrap().clone()
+}
+
+// ==========================
+// Tests for OutputRoute
+// ==========================
+
+#[test]
+fn test_parse_route() {
+    let route = OutputRoute::parse("kernel, nvtx-kernel=out/trace.json.gz").unwrap();
+    assert_eq!(route.categories, vec!["kernel", "nvtx-kernel"]);
+    assert_eq!(route.path, "out/trace.json.gz");
+    assert_eq!(route.format().unwrap(), RouteFormat::JsonGz);
+    assert_eq!(
+        OutputRoute::parse("*=stats.csv").unwrap().format().unwrap(),
+        RouteFormat::StatsCsv
+    );
+
+    for spec in ["kernel", "=trace.json", "kernel=", "kernel=trace.txt"] {
+        assert!(
+            matches!(
+                OutputRoute::parse(spec),
+                Err(ConvertError::InvalidOption(_))
+            ),
+            "{} should be rejected",
+            spec
+        );
+    }
+}
+
+// ==========================
+// Tests for MultiSinkWriter
+// ==========================
+
+#[test]
+fn test_route_splits_categories() {
+    let dir = TempDir::new().unwrap();
+    let routes = vec![
+        route("kernel,nvtx-kernel=trace.json", &dir),
+        route("counters=counters.json", &dir),
+        route("kernel=kernels.csv", &dir),
+    ];
+    let mut sinks = MultiSinkWriter::new(&routes).unwrap();
+    let unrouted = sinks.route(sample_events());
+
+    // Only the metadata and the API call stay in the main output
+    let names: Vec<&str> = unrouted.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["process_name", "cudaLaunchKernel"]);
+
+    let outputs = sinks.finish().unwrap();
+    let counts: Vec<usize> = outputs.iter().map(|o| o.events).collect();
+    assert_eq!(counts, vec![3, 1, 2]);
+
+    // Trace routes keep the track-naming metadata
+    let trace = read_trace(&outputs[0].path);
+    assert_eq!(trace.len(), 4);
+    assert_eq!(trace[0]["ph"], "M");
+    let counters = read_trace(&outputs[1].path);
+    assert_eq!(counters[1]["name"], "GPU Utilization");
+}
+
+#[test]
+fn test_route_writes_gzip_and_csv() {
+    let dir = TempDir::new().unwrap();
+    let routes = vec![route("*=all.json.gz", &dir), route("*=stats.csv", &dir)];
+    let mut sinks = MultiSinkWriter::new(&routes).unwrap();
+    let mut events = sample_events();
+    events.push(create_event("copy \"a\", b", 40.0, 1.0, "cuda_api"));
+    sinks.route(events);
+    let outputs = sinks.finish().unwrap();
+
+    assert_eq!(read_trace(&outputs[0].path).len(), 7);
+    let csv = std::fs::read_to_string(&outputs[1].path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "category,name,count,total_us,avg_us,min_us,max_us,total_bytes,avg_gbps"
+    );
+    assert_eq!(lines[1], "kernel,gemm,2,40.000,20.000,10.000,30.000,,");
+    assert_eq!(
+        lines[4],
+        "cuda_api,\"copy \"\"a\"\", b\",1,1.000,1.000,1.000,1.000,,"
+    );
+    assert_eq!(lines.len(), 5);
+}
+
+// ==========================
+// Tests for duration_stats
+// ==========================
+
+#[test]
+fn test_duration_stats_ignores_non_complete_events() {
+    let stats = duration_stats(&sample_events());
+    assert_eq!(stats.len(), 3);
+    assert_eq!(stats[0].name, "gemm");
+    assert_eq!(stats[0].count, 2);
+    assert_eq!(stats[0].avg_us(), 20.0);
+    assert_eq!(stats[1].name, "forward");
+    assert_eq!(stats[2].category, "cuda_api");
+}
+
+// ==========================
+// Tests for summary_events
+// ==========================
+
+#[test]
+fn test_summary_keeps_synthesized_events() {
+    let mut step = create_event("step 1", 0.0, 50.0, "nvtx");
+    step.tid = "Synthetic Steps".into();
+    let mut events = sample_events();
+    events.push(step);
+
+    let summary = summary_events(events);
+    let names: Vec<&str> = summary.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["process_name", "GPU Utilization", "forward", "step 1"]
+    );
+}
+
+#[test]
+fn test_summary_drops_metadata_of_empty_tracks() {
+    let thread_name = |pid: &str, tid: &str| {
+        ChromeTraceEvent::metadata(
+            "thread_name".to_string(),
+            pid.to_string(),
+            tid.to_string(),
+            HashMap::from([("name".to_string(), json!(tid))]),
+        )
+    };
+    let origin = ChromeTraceEvent::metadata(
+        "trace_time_origin".to_string(),
+        "Host".to_string(),
+        String::new(),
+        HashMap::new(),
+    );
+    let mut events = sample_events();
+    events.extend([
+        thread_name("Device 0", "Stream 7"),
+        thread_name("Device 0", "CUDA API Thread 1"),
+        origin,
+    ]);
+
+    let summary = summary_events(events);
+    let metadata: Vec<(&str, &str)> = summary
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Metadata)
+        .map(|e| (e.name.as_str(), e.tid.as_str()))
+        .collect();
+    // The nvtx-kernel range sits on Stream 7; the API thread has nothing left
+    assert_eq!(
+        metadata,
+        vec![
+            ("process_name", ""),
+            ("thread_name", "Stream 7"),
+            ("trace_time_origin", "")
+        ]
+    );
+}
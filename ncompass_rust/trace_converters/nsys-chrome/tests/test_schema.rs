@@ -1,7 +1,14 @@
 //! Unit tests for schema module
 
-use nsys_chrome::schema::{detect_available_tables, detect_event_types, table_exists, TableRegistry};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::diagnostics::ConversionDiagnostics;
+use nsys_chrome::models::ConversionOptions;
+use nsys_chrome::parsers::{CUPTIRuntimeParser, EventParser, ParseContext};
+use nsys_chrome::schema::{
+    detect_available_tables, detect_event_types, table_exists, SchemaProbe, TableRegistry,
+};
 use rusqlite::Connection;
+use std::collections::HashMap;
 use tempfile::NamedTempFile;
 
 // ==========================
@@ -512,3 +519,133 @@ fn test_detect_event_types_unrecognized_tables_ignored() {
     assert!(result.contains("kernel"));
 }
 
+
+// ==========================
+// Tests for SchemaProbe
+// ==========================
+
+#[test]
+fn test_table_registry_variants_start_with_canonical() {
+    for activity in ["kernel", "cuda-api", "nvtx", "osrt", "sched"] {
+        let variants = TableRegistry::get_table_variants(activity);
+        assert_eq!(variants[0], TableRegistry::get_tables_for_activity(activity)[0]);
+    }
+    assert_eq!(
+        TableRegistry::get_activity_type("CUPTI_ACTIVITY_KIND_DRIVER"),
+        Some("cuda-api")
+    );
+    assert!(!TableRegistry::get_table_variants("cuda-api").contains(&"CUPTI_ACTIVITY_KIND_DRIVER"));
+}
+
+#[test]
+fn test_schema_probe_resolves_canonical_tables() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+            start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+        );
+        CREATE TABLE OSRT_API (start INTEGER, end INTEGER, globalTid INTEGER, nameId INTEGER);",
+    )
+    .unwrap();
+
+    let probe = SchemaProbe::probe(&conn).unwrap();
+    assert_eq!(probe.table_for("cuda-api"), Some("CUPTI_ACTIVITY_KIND_RUNTIME"));
+    assert_eq!(probe.table_for("osrt"), Some("OSRT_API"));
+    assert_eq!(probe.table_for("kernel"), None);
+    assert!(probe.incompatible.is_empty());
+}
+
+#[test]
+fn test_schema_probe_reads_driver_table_without_runtime() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE CUPTI_ACTIVITY_KIND_DRIVER (
+            start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+        );",
+    )
+    .unwrap();
+
+    let probe = SchemaProbe::probe(&conn).unwrap();
+    assert_eq!(probe.table_for("cuda-api"), Some("CUPTI_ACTIVITY_KIND_DRIVER"));
+}
+
+#[test]
+fn test_runtime_parser_merges_driver_calls() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+            start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES (100, 200, 1, 1, 1);
+        CREATE TABLE CUPTI_ACTIVITY_KIND_DRIVER (
+            start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_DRIVER VALUES (300, 400, 1, 2, 2);",
+    )
+    .unwrap();
+
+    let probe = SchemaProbe::probe(&conn).unwrap();
+    assert_eq!(
+        probe.tables_for("cuda-api").collect::<Vec<_>>(),
+        vec!["CUPTI_ACTIVITY_KIND_RUNTIME", "CUPTI_ACTIVITY_KIND_DRIVER"]
+    );
+
+    let strings = HashMap::from([
+        (1, "cudaLaunchKernel".to_string()),
+        (2, "cuLaunchKernel".to_string()),
+    ]);
+    let options = ConversionOptions::default();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names)
+        .with_schema(&probe);
+    let events = CUPTIRuntimeParser.safe_parse(&context).unwrap();
+    let names: Vec<&str> = events.iter().map(|e| &*e.name).collect();
+    assert_eq!(names, vec!["cudaLaunchKernel", "cuLaunchKernel"]);
+    assert_eq!(events[1].args["correlationId"], 2);
+}
+
+#[test]
+fn test_schema_probe_reports_incompatible_table() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE OSRT_API (start INTEGER, end INTEGER);")
+        .unwrap();
+
+    let probe = SchemaProbe::probe(&conn).unwrap();
+    assert_eq!(probe.table_for("osrt"), None);
+    assert_eq!(probe.incompatible.len(), 1);
+    assert_eq!(probe.incompatible[0].table, "OSRT_API");
+    assert_eq!(probe.incompatible[0].missing_columns, vec!["globalTid", "nameId"]);
+}
+
+#[test]
+fn test_schema_probe_reports_unknown_relevant_tables() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE CUPTI_ACTIVITY_KIND_MEMSET (id INTEGER);
+        CREATE TABLE CUDA_CALLCHAINS (id INTEGER);
+        CREATE TABLE StringIds (id INTEGER, value TEXT);",
+    )
+    .unwrap();
+
+    let probe = SchemaProbe::probe(&conn).unwrap();
+    assert_eq!(probe.unknown_tables, vec!["CUPTI_ACTIVITY_KIND_MEMSET"]);
+}
+
+#[test]
+fn test_conversion_diagnostics_missing_activities() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE OSRT_API (start INTEGER, end INTEGER, globalTid INTEGER, nameId INTEGER);")
+        .unwrap();
+
+    let probe = SchemaProbe::probe(&conn).unwrap();
+    let requested = vec![
+        EventCategory::Osrt,
+        EventCategory::Kernel,
+        EventCategory::NvtxKernel,
+    ];
+    let diagnostics = ConversionDiagnostics::from_schema(&probe, &requested);
+
+    assert_eq!(diagnostics.missing_activities, vec!["kernel", "nvtx-kernel"]);
+    assert_eq!(diagnostics.summary_lines().len(), 1);
+}
@@ -0,0 +1,183 @@
+//! Unit tests for seekable gzip output and its time index
+
+use flate2::read::MultiGzDecoder;
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::seekable::{
+    read_block, read_time_range, seek_index_path, SeekIndex, SeekableGzSink, SEEK_INDEX_VERSION,
+};
+use nsys_chrome::writer::{ChromeTraceWriter, WriteOptions};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, dur: f64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+}
+
+/// A process name, one long kernel spanning the trace and 200 short ones
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), serde_json::json!("GPU 0"));
+    let mut events = vec![
+        ChromeTraceEvent::metadata(
+            "process_name".to_string(),
+            "Device 0".to_string(),
+            String::new(),
+            args,
+        ),
+        create_event("long", 0.0, 1000.0),
+    ];
+    events.extend((0..200).map(|i| create_event(&format!("k{}", i), i as f64 * 5.0, 2.0)));
+    events
+}
+
+/// Write events with small blocks, returning the compressed bytes and index
+fn write_seekable(events: Vec<ChromeTraceEvent>) -> (Vec<u8>, SeekIndex) {
+    let mut sink = SeekableGzSink::with_block_size(Vec::new(), 1024);
+    ChromeTraceWriter::write_to_sink(&mut sink, events, WriteOptions::default()).unwrap();
+    let index = sink.index().clone();
+    (sink.into_inner(), index)
+}
+
+fn names(events: &[ChromeTraceEvent]) -> Vec<String> {
+    events.iter().map(|e| e.name.clone()).collect()
+}
+
+// ==========================
+// Tests for SeekableGzSink
+// ==========================
+
+#[test]
+fn test_seekable_output_is_standard_gzip() {
+    let (bytes, index) = write_seekable(sample_events());
+
+    let mut text = String::new();
+    MultiGzDecoder::new(&bytes[..])
+        .read_to_string(&mut text)
+        .unwrap();
+    let trace: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(trace["traceEvents"].as_array().unwrap().len(), 202);
+
+    assert!(index.blocks.len() > 3);
+    assert_eq!(index.version, SEEK_INDEX_VERSION);
+    let mut offset = 0;
+    let mut text_offset = 0;
+    for block in &index.blocks {
+        assert_eq!(block.offset, offset);
+        assert_eq!(block.text_offset, text_offset);
+        offset += block.length;
+        text_offset += block.text_length;
+    }
+    assert_eq!(offset, bytes.len() as u64);
+    assert_eq!(text_offset, text.len() as u64);
+    assert_eq!(index.blocks.iter().map(|b| b.events).sum::<usize>(), 202);
+}
+
+#[test]
+fn test_seekable_blocks_hold_whole_events() {
+    let (bytes, index) = write_seekable(sample_events());
+    let mut trace = Cursor::new(bytes);
+
+    let mut events = Vec::new();
+    for block in &index.blocks {
+        let block_events = read_block(&mut trace, block).unwrap();
+        assert_eq!(block_events.len(), block.events);
+        events.extend(block_events);
+    }
+    assert_eq!(names(&events), names(&sample_events()));
+
+    // Starts and running ends never decrease, so both can be binary searched
+    assert!(index
+        .blocks
+        .windows(2)
+        .all(|w| w[0].start_us <= w[1].start_us && w[0].max_end_us <= w[1].max_end_us));
+}
+
+#[test]
+fn test_seek_index_of_negative_timestamps() {
+    // A trace rebased to an origin after all of its events
+    let events: Vec<ChromeTraceEvent> = (0..200)
+        .map(|i| create_event(&format!("k{}", i), -2000.0 + i as f64 * 5.0, 2.0))
+        .collect();
+    let (_, index) = write_seekable(events);
+
+    assert!(index.blocks.iter().all(|b| b.max_end_us < 0.0));
+    assert_eq!(index.blocks[0].max_end_us, index.blocks[0].end_us);
+    let blocks = index.blocks_in_range(-1500.0, -1490.0);
+    assert!(blocks.len() < index.blocks.len() / 2);
+}
+
+// ==========================
+// Tests for SeekIndex
+// ==========================
+
+#[test]
+fn test_blocks_in_range_fetches_only_overlapping_blocks() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json.gz");
+    let (bytes, index) = write_seekable(sample_events());
+    std::fs::write(&path, bytes).unwrap();
+
+    let blocks = index.blocks_in_range(500.0, 510.0);
+    assert!(blocks.len() < index.blocks.len());
+    // The metadata and the long kernel sit in the first block
+    assert_eq!(blocks[0], &index.blocks[0]);
+
+    let events = read_time_range(path.to_str().unwrap(), &index, 500.0, 510.0).unwrap();
+    assert_eq!(
+        names(&events),
+        vec!["process_name", "long", "k100", "k101", "k102"]
+    );
+    let events = read_time_range(path.to_str().unwrap(), &index, 2000.0, 3000.0).unwrap();
+    assert_eq!(names(&events), vec!["process_name"]);
+}
+
+#[test]
+fn test_write_to_builds_seek_index_and_same_outline() {
+    let dir = TempDir::new().unwrap();
+    let write = |name: &str, seekable: bool| {
+        let path = dir.path().join(name);
+        let options = WriteOptions {
+            gzip: true,
+            outline: true,
+            seekable,
+            ..Default::default()
+        };
+        let written =
+            ChromeTraceWriter::write_to(File::create(&path).unwrap(), sample_events(), options)
+                .unwrap();
+        let mut text = String::new();
+        MultiGzDecoder::new(File::open(&path).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        (text, written)
+    };
+
+    let (plain_text, plain) = write("plain.json.gz", false);
+    let (seekable_text, seekable) = write("seekable.json.gz", true);
+    assert_eq!(seekable_text, plain_text);
+    assert!(plain.seek_index.is_none());
+    let index = seekable.seek_index.unwrap();
+    assert_eq!(index.blocks.len(), 1);
+    assert_eq!(
+        plain.outline.unwrap().to_json(),
+        seekable.outline.unwrap().to_json()
+    );
+
+    let sidecar = seek_index_path(dir.path().join("seekable.json.gz").to_str().unwrap());
+    assert!(sidecar.ends_with("seekable.seek.json"));
+    index.write_sidecar(&sidecar).unwrap();
+    assert_eq!(SeekIndex::read(&sidecar).unwrap(), index);
+}
@@ -0,0 +1,179 @@
+//! Unit tests for the converter's self-profiling
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTracePhase, ConversionOptions};
+use nsys_chrome::self_profile::{self, SelfProfiler, SELF_PROFILE_PROCESS};
+use tempfile::TempDir;
+
+// ==========================
+// Tests for SelfProfiler
+// ==========================
+
+#[test]
+fn test_phases_become_complete_events() {
+    let profiler = SelfProfiler::new();
+    {
+        let _outer = profiler.phase("convert", "convert");
+        let _inner = profiler.phase("read StringIds", "read");
+    }
+    assert_eq!(profiler.len(), 2);
+
+    let events = profiler.events();
+    assert_eq!(events[0].name, "process_name");
+    assert_eq!(events[1].name, "thread_name");
+    assert_eq!(events[1].tid, "Thread 1");
+
+    let phases: Vec<_> = events
+        .iter()
+        .filter(|e| e.ph == ChromeTracePhase::Complete)
+        .collect();
+    assert_eq!(phases.len(), 2);
+    assert_eq!(phases[0].name, "convert");
+    assert_eq!(phases[1].name, "read StringIds");
+    assert_eq!(phases[1].cat, "read");
+    assert_eq!(phases[0].pid, SELF_PROFILE_PROCESS);
+    // The inner phase starts and ends within the outer one
+    let (outer, inner) = (phases[0], phases[1]);
+    assert!(inner.ts >= outer.ts);
+    assert!(inner.ts + inner.dur.unwrap() <= outer.ts + outer.dur.unwrap());
+}
+
+#[test]
+fn test_phases_on_worker_threads_get_own_tracks() {
+    let profiler = SelfProfiler::new();
+    {
+        let _main = profiler.phase("link NVTX ranges", "link");
+        std::thread::scope(|scope| {
+            for device in 0..2 {
+                let profiler = &profiler;
+                scope.spawn(move || {
+                    let _phase = profiler.phase(format!("link Device {}", device), "link");
+                });
+            }
+        });
+    }
+
+    let events = profiler.events();
+    let threads = events.iter().filter(|e| e.name == "thread_name").count();
+    assert_eq!(threads, 3);
+    let main = events
+        .iter()
+        .find(|e| e.name == "link NVTX ranges")
+        .unwrap();
+    let workers: Vec<_> = events
+        .iter()
+        .filter(|e| e.name.starts_with("link Device"))
+        .collect();
+    assert_eq!(workers.len(), 2);
+    assert!(workers.iter().all(|w| w.tid != main.tid));
+}
+
+#[test]
+fn test_nested_phases_record_their_path() {
+    let profiler = SelfProfiler::new();
+    {
+        let _outer = profiler.phase("convert", "convert");
+        {
+            let _inner = profiler.phase("link NVTX ranges", "link");
+            assert_eq!(
+                self_profile::current_context().path(),
+                Some("convert/link NVTX ranges")
+            );
+        }
+        // The outer phase is current again once the inner one ends
+        assert_eq!(self_profile::current_context().path(), Some("convert"));
+    }
+    assert_eq!(self_profile::current_context().path(), None);
+
+    let events = profiler.events();
+    let inner = events
+        .iter()
+        .find(|e| e.name == "link NVTX ranges")
+        .unwrap();
+    assert_eq!(inner.args["path"], "convert/link NVTX ranges");
+    assert_eq!(inner.args["parent"], "convert");
+    let outer = events.iter().find(|e| e.name == "convert").unwrap();
+    assert_eq!(outer.args["path"], "convert");
+    assert!(!outer.args.contains_key("parent"));
+}
+
+#[test]
+fn test_worker_phases_nest_under_entered_context() {
+    let profiler = SelfProfiler::new();
+    {
+        let _main = profiler.phase("link NVTX ranges", "link");
+        let parent = self_profile::current_context();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let _context = parent.enter();
+                let _phase = profiler.phase("link Device 0", "link");
+            });
+            // Without the context a worker's phase is a root
+            scope.spawn(|| {
+                let _phase = profiler.phase("link Device 1", "link");
+            });
+        });
+    }
+
+    let events = profiler.events();
+    let path_of = |name: &str| events.iter().find(|e| e.name == name).unwrap().args["path"].clone();
+    assert_eq!(path_of("link Device 0"), "link NVTX ranges/link Device 0");
+    assert_eq!(path_of("link Device 1"), "link Device 1");
+}
+
+#[test]
+fn test_write_self_profile_json() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("profile.json");
+    let profiler = SelfProfiler::new();
+    drop(profiler.phase("write output", "write"));
+    profiler.write(path.to_str().unwrap()).unwrap();
+
+    let root: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let events = root["traceEvents"].as_array().unwrap();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[2]["name"], "write output");
+    assert_eq!(events[2]["ph"], "X");
+    assert!(events[2]["args"]["duration_ms"].is_f64());
+}
+
+// ==========================
+// Tests for the process-wide recorder
+// ==========================
+
+#[test]
+fn test_converter_records_phases_when_enabled() {
+    // Disabled: nothing is recorded
+    drop(self_profile::phase("ignored", "convert"));
+    assert!(self_profile::global().is_none());
+
+    let profiler = self_profile::enable();
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Nvtx],
+        ..Default::default()
+    };
+    convert_sql(
+        "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+         CREATE TABLE NVTX_EVENTS (
+             start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+             globalTid INTEGER, eventType INTEGER
+         );
+         INSERT INTO NVTX_EVENTS VALUES (1000, 2000, 'forward', NULL, 16777217, 59);",
+        options,
+    );
+
+    let names: Vec<String> = profiler
+        .events()
+        .into_iter()
+        .filter(|e| e.ph == ChromeTracePhase::Complete)
+        .map(|e| e.name)
+        .collect();
+    for expected in ["probe schema", "read StringIds", "read NVTX_EVENTS", "sort"] {
+        assert!(names.iter().any(|n| n == expected), "missing {}", expected);
+    }
+    assert!(!names.iter().any(|n| n == "ignored"));
+}
@@ -0,0 +1,251 @@
+//! Integration tests for the HTTP conversion service
+
+mod common;
+
+use common::create_sqlite;
+use flate2::read::GzDecoder;
+use nsys_chrome::service::{ConversionService, ServiceConfig};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Start a service on an ephemeral port and return its address
+fn start_service(allow_local_paths: bool) -> SocketAddr {
+    start_service_with(ServiceConfig {
+        allow_local_paths,
+        ..Default::default()
+    })
+}
+
+fn start_service_with(config: ServiceConfig) -> SocketAddr {
+    let config = ServiceConfig {
+        addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        ..config
+    };
+    let service = ConversionService::bind(config).unwrap();
+    let addr = service.local_addr().unwrap();
+    thread::spawn(move || service.run());
+    addr
+}
+
+/// Send a request and return (status code, body)
+fn send(addr: SocketAddr, method: &str, path: &str, content_type: &str, body: &[u8]) -> (u16, Vec<u8>) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+        method,
+        path,
+        content_type,
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(body).unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let header_end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8_lossy(&response[..header_end]).to_string();
+    let status: u16 = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, response[header_end + 4..].to_vec())
+}
+
+fn send_json(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, serde_json::Value) {
+    let (status, body) = send(addr, method, path, "application/json", body.as_bytes());
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+/// A single kernel
+const KERNEL_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'my_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (1000, 2000, 0, 7, 1, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+";
+
+/// Enough kernels that converting them takes a while
+const SLOW_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'my_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+    );
+    WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200000)
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL
+    SELECT i * 1000, i * 1000 + 500, 0, 7, i, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0 FROM n;
+";
+
+/// Poll a job until it leaves the queued/running states
+fn wait_for_job(addr: SocketAddr, id: &str) -> serde_json::Value {
+    for _ in 0..200 {
+        let (_, status) = send_json(addr, "GET", &format!("/jobs/{}", id), "");
+        if status["status"] != "queued" && status["status"] != "running" {
+            return status;
+        }
+        thread::sleep(Duration::from_millis(25));
+    }
+    panic!("job {} did not finish", id);
+}
+
+// ==========================
+// Tests
+// ==========================
+
+#[test]
+fn test_service_upload_convert_download() {
+    let addr = start_service(false);
+    let dir = TempDir::new().unwrap();
+    let sqlite = std::fs::read(create_sqlite(&dir, "input.sqlite", KERNEL_SQL)).unwrap();
+
+    let (status, body) = send(addr, "POST", "/jobs?types=kernel", "application/octet-stream", &sqlite);
+    assert_eq!(status, 202);
+    let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let final_status = wait_for_job(addr, &id);
+    assert_eq!(final_status["status"], "done");
+
+    let (status, body) = send(addr, "GET", &format!("/jobs/{}/result", id), "", b"");
+    assert_eq!(status, 200);
+    let mut content = String::new();
+    GzDecoder::new(body.as_slice()).read_to_string(&mut content).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    let events = parsed["traceEvents"].as_array().unwrap();
+    assert!(events.iter().any(|e| e["name"] == "my_kernel"));
+
+    let (status, _) = send_json(addr, "DELETE", &format!("/jobs/{}", id), "");
+    assert_eq!(status, 200);
+    let (status, _) = send_json(addr, "GET", &format!("/jobs/{}", id), "");
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn test_service_path_submission_requires_opt_in() {
+    let addr = start_service(false);
+    let (status, body) = send_json(addr, "POST", "/jobs", r#"{"path": "/tmp/x.sqlite"}"#);
+    assert_eq!(status, 403);
+    assert!(body["error"].is_string());
+}
+
+#[test]
+fn test_service_path_submission() {
+    let addr = start_service(true);
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "input.sqlite", KERNEL_SQL);
+
+    let request = serde_json::json!({ "path": path }).to_string();
+    let (status, body) = send_json(addr, "POST", "/jobs", &request);
+    assert_eq!(status, 202);
+
+    let final_status = wait_for_job(addr, body["id"].as_str().unwrap());
+    assert_eq!(final_status["status"], "done");
+}
+
+#[test]
+fn test_service_failed_job_reports_error() {
+    let addr = start_service(false);
+    let (status, body) = send(addr, "POST", "/jobs", "application/octet-stream", b"not a database");
+    assert_eq!(status, 202);
+    let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let final_status = wait_for_job(addr, &id);
+    assert_eq!(final_status["status"], "failed");
+    assert!(final_status["error"].is_string());
+
+    let (status, _) = send(addr, "GET", &format!("/jobs/{}/result", id), "", b"");
+    assert_eq!(status, 409);
+}
+
+#[test]
+fn test_service_unknown_routes() {
+    let addr = start_service(false);
+    assert_eq!(send_json(addr, "GET", "/jobs/job-999", "").0, 404);
+    assert_eq!(send_json(addr, "GET", "/nope", "").0, 404);
+    assert_eq!(send_json(addr, "PUT", "/jobs", "").0, 405);
+    assert_eq!(send(addr, "POST", "/jobs", "application/octet-stream", b"").0, 400);
+}
+
+#[test]
+fn test_service_decodes_query_strings() {
+    let addr = start_service(false);
+    let dir = TempDir::new().unwrap();
+    let sqlite = std::fs::read(create_sqlite(&dir, "input.sqlite", KERNEL_SQL)).unwrap();
+
+    let (status, body) = send(addr, "POST", "/jobs?types=kernel%2Cnvtx", "application/octet-stream", &sqlite);
+    assert_eq!(status, 202);
+    let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(wait_for_job(addr, &id)["status"], "done");
+
+    let (status, _) = send(addr, "POST", "/jobs?types=kernel%2", "application/octet-stream", &sqlite);
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn test_service_refuses_jobs_when_queue_is_full() {
+    let addr = start_service_with(ServiceConfig {
+        max_concurrent_jobs: 1,
+        max_queued_jobs: 1,
+        allow_local_paths: true,
+        ..Default::default()
+    });
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "slow.sqlite", SLOW_SQL);
+    let request = serde_json::json!({ "path": path }).to_string();
+
+    // The first job occupies the only worker and the second the only queue slot
+    let responses: Vec<(u16, serde_json::Value)> =
+        (0..3).map(|_| send_json(addr, "POST", "/jobs", &request)).collect();
+    assert_eq!(responses[0].0, 202);
+    assert_eq!(responses.last().unwrap().0, 503);
+    assert!(responses.last().unwrap().1["error"].is_string());
+
+    // Cancel the accepted jobs rather than wait for them
+    for (_, body) in responses.iter().filter(|(status, _)| *status == 202) {
+        let id = body["id"].as_str().unwrap();
+        send_json(addr, "DELETE", &format!("/jobs/{}", id), "");
+        wait_for_job(addr, id);
+    }
+}
+
+#[test]
+fn test_service_limits_concurrent_connections() {
+    let addr = start_service_with(ServiceConfig {
+        max_connections: 1,
+        ..Default::default()
+    });
+
+    // An idle client holds the only connection handler
+    let idle = TcpStream::connect(addr).unwrap();
+    let (done, finished) = std::sync::mpsc::channel();
+    thread::spawn(move || done.send(send_json(addr, "GET", "/nope", "").0).unwrap());
+    thread::sleep(Duration::from_millis(300));
+    assert!(finished.try_recv().is_err());
+
+    drop(idle);
+    assert_eq!(finished.recv_timeout(Duration::from_secs(10)).unwrap(), 404);
+}
@@ -0,0 +1,120 @@
+//! Unit tests for capture session detection and splitting
+
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase};
+use nsys_chrome::sessions::{
+    detect_sessions, select_session, session_path, split_sessions, Session, SESSION_EVENT,
+};
+use nsys_chrome::ConvertError;
+use serde_json::json;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Kernel on Device 0, times in microseconds
+fn create_event(name: &str, ts: f64, dur: f64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+}
+
+/// Two sessions 2 s apart, with a track name
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        ChromeTraceEvent::metadata(
+            "thread_name".to_string(),
+            "Device 0".to_string(),
+            "Stream 7".to_string(),
+            HashMap::from([("name".to_string(), json!("Stream 7"))]),
+        ),
+        create_event("a", 100.0, 50.0),
+        create_event("b", 200.0, 800.0),
+        create_event("c", 3_000_000.0, 10.0),
+        create_event("d", 3_000_500.0, 10.0),
+    ]
+}
+
+fn names(events: &[ChromeTraceEvent]) -> Vec<&str> {
+    events.iter().map(|e| e.name.as_str()).collect()
+}
+
+// ==========================
+// Tests for detect_sessions
+// ==========================
+
+#[test]
+fn test_detect_sessions_splits_at_idle_time() {
+    let sessions = detect_sessions(&sample_events(), 1_000_000_000);
+    assert_eq!(
+        sessions,
+        vec![
+            Session {
+                index: 1,
+                start_ns: 100_000,
+                end_ns: 1_000_000,
+            },
+            Session {
+                index: 2,
+                start_ns: 3_000_000_000,
+                end_ns: 3_000_510_000,
+            },
+        ]
+    );
+
+    // A longer threshold keeps one session; no events means none
+    assert_eq!(detect_sessions(&sample_events(), 5_000_000_000).len(), 1);
+    assert!(detect_sessions(&sample_events()[..1], 1_000_000_000).is_empty());
+}
+
+// ==========================
+// Tests for splitting and selecting
+// ==========================
+
+#[test]
+fn test_split_sessions_rebases_each_session() {
+    let sessions = split_sessions(sample_events(), 1_000_000_000);
+    assert_eq!(sessions.len(), 2);
+
+    assert_eq!(
+        names(&sessions[0]),
+        vec!["thread_name", SESSION_EVENT, "a", "b"]
+    );
+    assert_eq!(sessions[0][2].ts, 0.0);
+    assert_eq!(sessions[0][3].ts, 100.0);
+
+    let second = &sessions[1];
+    assert_eq!(names(second), vec!["thread_name", SESSION_EVENT, "c", "d"]);
+    assert_eq!((second[2].ts, second[3].ts), (0.0, 500.0));
+    assert_eq!(second[1].ph, ChromeTracePhase::Metadata);
+    assert_eq!(second[1].args["index"], 2);
+    assert_eq!(second[1].args["start_ns"], 3_000_000_000_i64);
+}
+
+#[test]
+fn test_select_session() {
+    let events = select_session(sample_events(), 2, 1_000_000_000).unwrap();
+    assert_eq!(names(&events), vec!["thread_name", SESSION_EVENT, "c", "d"]);
+
+    for index in [0, 3] {
+        assert!(matches!(
+            select_session(sample_events(), index, 1_000_000_000),
+            Err(ConvertError::InvalidOption(_))
+        ));
+    }
+}
+
+#[test]
+fn test_session_path() {
+    assert_eq!(session_path("trace.json.gz", 2), "trace.session-2.json.gz");
+    assert_eq!(
+        session_path("out/trace.json", 1),
+        "out/trace.session-1.json"
+    );
+    assert_eq!(session_path("trace", 3), "trace.session-3");
+}
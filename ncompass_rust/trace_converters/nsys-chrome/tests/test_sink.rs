@@ -0,0 +1,128 @@
+//! Unit tests for trace sinks
+
+use nsys_chrome::error::Result;
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::sink::{GzSink, JsonSink, MemorySink, TraceSink};
+use nsys_chrome::writer::{WriteOptions, OVERFLOW_PREFIX};
+use nsys_chrome::ChromeTraceWriter;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_kernel(name: &str, ts: f64, dur: f64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+}
+
+/// Two kernels, the second partially overlapping the first
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![create_kernel("a", 0.0, 10.0), create_kernel("b", 5.0, 10.0)]
+}
+
+/// Sink recording the calls it receives
+#[derive(Default)]
+struct CallLog(Vec<String>);
+
+impl TraceSink for CallLog {
+    fn begin(&mut self) -> Result<()> {
+        self.0.push("begin".to_string());
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &ChromeTraceEvent) -> Result<()> {
+        self.0.push(event.name.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.0.push("finish".to_string());
+        Ok(())
+    }
+}
+
+// ==========================
+// Tests for TraceSink
+// ==========================
+
+#[test]
+fn test_custom_sink_receives_prepared_events() {
+    let mut log = CallLog::default();
+    let output =
+        ChromeTraceWriter::write_to_sink(&mut log, sample_events(), WriteOptions::default())
+            .unwrap();
+    assert_eq!(log.0, vec!["begin", "a", "b", "finish"]);
+    assert!(output.outline.is_none());
+
+    let mut memory = MemorySink::new();
+    let options = WriteOptions {
+        numeric_ids: true,
+        ..Default::default()
+    };
+    let output = ChromeTraceWriter::write_to_sink(&mut memory, sample_events(), options).unwrap();
+    let events = memory.into_events();
+    // Track naming metadata follows the two kernels
+    assert!(events.len() > 2);
+    assert_eq!(events[0].pid, "1");
+    let track_ids = output.track_ids.unwrap();
+    let threads: Vec<&str> = track_ids
+        .threads()
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect();
+    assert_eq!(threads, vec!["Stream 7", "↳ Stream 7"]);
+}
+
+#[test]
+fn test_json_sink_matches_file_output() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json");
+    ChromeTraceWriter::write_auto(path.to_str().unwrap(), sample_events()).unwrap();
+
+    let mut sink = JsonSink::new(Vec::new());
+    let options = WriteOptions {
+        outline: true,
+        ..Default::default()
+    };
+    ChromeTraceWriter::write_to_sink(&mut sink, sample_events(), options).unwrap();
+    let (offset, length) = sink.last_event_span().unwrap();
+    let bytes = sink.into_inner().unwrap();
+    assert_eq!(bytes, std::fs::read(&path).unwrap());
+
+    let last: serde_json::Value =
+        serde_json::from_slice(&bytes[offset as usize..(offset + length) as usize]).unwrap();
+    assert_eq!(last["name"], "b");
+    assert!(last["tid"].as_str().unwrap().starts_with(OVERFLOW_PREFIX));
+}
+
+#[test]
+fn test_gz_sink_writes_gzip() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json.gz");
+    let file = std::fs::File::create(&path).unwrap();
+    let mut sink = GzSink::new(file);
+    ChromeTraceWriter::write_to_sink(&mut sink, sample_events(), WriteOptions::default()).unwrap();
+
+    let events = nsys_chrome::ChromeTraceReader::read(&path).unwrap();
+    let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b"]);
+}
+
+#[test]
+fn test_custom_sink_with_parallel_serialization() {
+    let mut log = CallLog::default();
+    let options = WriteOptions {
+        serialize_threads: 2,
+        ..Default::default()
+    };
+    ChromeTraceWriter::write_to_sink(&mut log, sample_events(), options).unwrap();
+    // Sinks not taking serialized JSON still receive every event, in order
+    assert_eq!(log.0, vec!["begin", "a", "b", "finish"]);
+}
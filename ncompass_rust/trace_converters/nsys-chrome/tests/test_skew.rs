@@ -0,0 +1,196 @@
+//! Unit tests for the per-rank collective skew report
+
+use nsys_chrome::analysis::{
+    merge_rank_traces, skew_report, straggler_events, RankMerger, SkewKind, SKEW_CATEGORY,
+    STRAGGLER_EVENT,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, StringOrInt};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_mpi_call(name: &str, rank: i64, start_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        50.0,
+        format!("Device {}", rank),
+        format!("MPI Rank {} Thread 1", rank),
+        "mpi".to_string(),
+    )
+    .with_arg("rank", rank)
+    .with_arg("deviceId", rank)
+    .with_arg("communicator", "0x44000000")
+    .with_arg("start_ns", start_ns)
+}
+
+fn create_step(step: i64, device: i64, start_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        format!("step {}", step),
+        start_ns as f64 / 1000.0,
+        1000.0,
+        format!("Device {}", device),
+        "Synthetic Steps".to_string(),
+        "nvtx".to_string(),
+    )
+    .with_arg("deviceId", device)
+    .with_arg("step", step)
+    .with_arg("start_ns", start_ns)
+}
+
+/// Three ranks at two all-reduces; rank 2 is 500 us late at the first
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_mpi_call("MPI_Allreduce", 0, 1_000_000),
+        create_mpi_call("MPI_Allreduce", 1, 1_020_000),
+        create_mpi_call("MPI_Allreduce", 2, 1_500_000),
+        create_mpi_call("MPI_Allreduce", 0, 3_010_000),
+        create_mpi_call("MPI_Allreduce", 1, 3_000_000),
+        create_mpi_call("MPI_Allreduce", 2, 3_005_000),
+        // Point-to-point calls are not collectives
+        create_mpi_call("MPI_Send", 0, 2_000_000),
+        create_mpi_call("MPI_Send", 1, 2_900_000),
+    ]
+}
+
+// ==========================
+// Tests for skew_report
+// ==========================
+
+#[test]
+fn test_skew_report_matches_occurrences_across_ranks() {
+    let report = skew_report(&sample_events());
+    assert_eq!(report.collectives.len(), 2);
+
+    let first = &report.collectives[0];
+    assert_eq!(first.kind, SkewKind::Mpi);
+    assert_eq!(first.name, "MPI_Allreduce");
+    assert_eq!(first.communicator.as_deref(), Some("0x44000000"));
+    assert_eq!(first.occurrence, 0);
+    let offsets: Vec<(i64, i64)> = first
+        .arrivals
+        .iter()
+        .map(|a| (a.rank, a.offset_ns))
+        .collect();
+    assert_eq!(offsets, vec![(0, 0), (1, 20_000), (2, 500_000)]);
+    assert_eq!(first.straggler(), Some(2));
+    assert_eq!(first.skew_ns(), 500_000);
+
+    let second = &report.collectives[1];
+    assert_eq!(second.occurrence, 1);
+    assert_eq!(second.straggler(), Some(0));
+    assert_eq!(second.skew_ns(), 10_000);
+}
+
+#[test]
+fn test_steps_take_rank_of_their_device() {
+    let mut events = sample_events();
+    events.push(create_step(0, 0, 5_000_000));
+    events.push(create_step(0, 1, 5_300_000));
+    // No MPI calls tell which rank device 9 belongs to
+    events.push(create_step(0, 9, 5_100_000));
+
+    let report = skew_report(&events);
+    let step = report
+        .collectives
+        .iter()
+        .find(|c| c.kind == SkewKind::Step)
+        .unwrap();
+    let ranks: Vec<i64> = step.arrivals.iter().map(|a| a.rank).collect();
+    assert_eq!(ranks, vec![0, 1]);
+    assert_eq!(step.skew_ns(), 300_000);
+}
+
+#[test]
+fn test_straggler_markers_and_csv() {
+    let events = sample_events();
+    let markers = straggler_events(&events, 100_000);
+    assert_eq!(markers.len(), 1);
+    let marker = &markers[0];
+    assert_eq!(marker.name, STRAGGLER_EVENT);
+    assert_eq!(marker.cat, SKEW_CATEGORY);
+    assert_eq!(marker.ph, ChromeTracePhase::Instant);
+    assert_eq!(marker.tid, "MPI Rank 2 Thread 1");
+    assert_eq!(marker.ts, 1500.0);
+    assert_eq!(marker.args["rank"], 2);
+    assert_eq!(marker.args["first_rank"], 0);
+    assert_eq!(marker.args["skew_ns"], 500_000);
+    assert_eq!(straggler_events(&events, 1).len(), 2);
+
+    let mut csv = Vec::new();
+    skew_report(&events).write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(
+        lines[0],
+        "kind,name,communicator,occurrence,rank,arrival_ns,offset_ns,straggler"
+    );
+    assert_eq!(
+        lines[3],
+        "mpi,MPI_Allreduce,0x44000000,0,2,1500000,500000,true"
+    );
+}
+
+// ==========================
+// Tests for merge_rank_traces
+// ==========================
+
+#[test]
+fn test_merge_rank_traces_keeps_ranks_apart() {
+    // Each rank captured on its own host, where its GPU is device 0
+    let rank = |rank: i64, step_ns: i64| {
+        let mut mpi = create_mpi_call("MPI_Allreduce", rank, 1_000_000).with_arg("deviceId", 0);
+        mpi.id = Some(StringOrInt::Int(1));
+        let mut step = create_step(0, 0, step_ns);
+        step.bind_id = Some(StringOrInt::Int(1));
+        vec![mpi, step]
+    };
+    let merged = merge_rank_traces(vec![
+        ("rank0".to_string(), rank(0, 2_000_000)),
+        ("rank1".to_string(), rank(1, 2_400_000)),
+    ]);
+
+    assert_eq!(merged.len(), 4);
+    assert_eq!(merged[1].pid, "rank0: Device 0");
+    assert_eq!(merged[3].pid, "rank1: Device 0");
+    assert_eq!(merged[1].args["rank"], 0);
+    assert_eq!(merged[3].args["rank"], 1);
+    assert_eq!(merged[0].id, Some(StringOrInt::Int(1)));
+    assert_eq!(merged[2].id, Some(StringOrInt::Int(2)));
+    assert_eq!(merged[3].bind_id, Some(StringOrInt::Int(2)));
+
+    let report = skew_report(&merged);
+    let step = report
+        .collectives
+        .iter()
+        .find(|c| c.kind == SkewKind::Step)
+        .unwrap();
+    assert_eq!(step.skew_ns(), 400_000);
+    assert_eq!(step.straggler(), Some(1));
+}
+
+#[test]
+fn test_rank_merger_matches_merge_rank_traces() {
+    let rank = |rank: i64| {
+        let mut mpi = create_mpi_call("MPI_Allreduce", rank, 1_000_000).with_arg("deviceId", 0);
+        mpi.id = Some(StringOrInt::Int(3));
+        let mut step = create_step(0, 0, 2_000_000);
+        step.bind_id = Some(StringOrInt::Int(3));
+        vec![mpi, step]
+    };
+    let merged = merge_rank_traces(vec![
+        ("rank0".to_string(), rank(0)),
+        ("rank1".to_string(), rank(1)),
+    ]);
+
+    // One capture at a time, as the CLI streams them to the writer
+    let mut merger = RankMerger::default();
+    let mut streamed = merger.merge("rank0", rank(0));
+    streamed.extend(merger.merge("rank1", rank(1)));
+
+    let json = |events: &[ChromeTraceEvent]| serde_json::to_value(events).unwrap();
+    assert_eq!(json(&streamed), json(&merged));
+    assert_eq!(streamed[2].id, Some(StringOrInt::Int(6)));
+}
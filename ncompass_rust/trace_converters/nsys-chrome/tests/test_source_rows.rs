@@ -0,0 +1,117 @@
+//! Unit tests for recording the SQLite rows events were read from
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::parsers::{SOURCE_ROWID_ARG, SOURCE_TABLE_ARG};
+use nsys_chrome::NsysChromeConverter;
+use rusqlite::Connection;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Rows inserted out of time order, so rowids differ from event order
+const TRACE_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'cudaLaunchKernel'), (2, 'gemm_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (8000, 9000, 0, 7, 2, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (3000, 4000, 0, 7, 1, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+        start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES
+        (1100, 1200, 16777217, 1, 1),
+        (7000, 7100, 16777217, 2, 1);
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES
+        (500, 600, 'marker', NULL, 16777217, 34),
+        (1000, 2000, 'forward', NULL, 16777217, 59);
+";
+
+fn convert(source_rows: bool) -> (TempDir, Vec<ChromeTraceEvent>) {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "trace.sqlite", TRACE_SQL);
+
+    let options = ConversionOptions {
+        activity_types: vec![
+            EventCategory::Kernel,
+            EventCategory::CudaApi,
+            EventCategory::Nvtx,
+        ],
+        include_metadata: false,
+        source_rows,
+        ..Default::default()
+    };
+    let events = NsysChromeConverter::new(&path, Some(options))
+        .unwrap()
+        .convert()
+        .unwrap();
+    (dir, events)
+}
+
+// ==========================
+// Tests for source rows
+// ==========================
+
+#[test]
+fn test_source_rows_point_back_to_rows() {
+    let (dir, events) = convert(true);
+    let conn = Connection::open(dir.path().join("trace.sqlite")).unwrap();
+
+    assert_eq!(events.len(), 6);
+    for event in &events {
+        let table = event.args[SOURCE_TABLE_ARG].as_str().unwrap();
+        let rowid = event.args[SOURCE_ROWID_ARG].as_i64().unwrap();
+        let start: i64 = conn
+            .query_row(
+                &format!("SELECT start FROM {} WHERE rowid = ?1", table),
+                [rowid],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(
+            Some(start),
+            event.args["start_ns"].as_i64(),
+            "{}",
+            event.name
+        );
+    }
+
+    let kernels: Vec<i64> = events
+        .iter()
+        .filter(|e| e.cat == "kernel")
+        .map(|e| e.args[SOURCE_ROWID_ARG].as_i64().unwrap())
+        .collect();
+    assert_eq!(kernels, vec![2, 1]);
+    let forward = events.iter().find(|e| e.name == "forward").unwrap();
+    assert_eq!(forward.args[SOURCE_TABLE_ARG], "NVTX_EVENTS");
+    assert_eq!(forward.args[SOURCE_ROWID_ARG], 2);
+    let marker = events.iter().find(|e| e.name == "marker").unwrap();
+    assert_eq!(marker.args[SOURCE_ROWID_ARG], 1);
+}
+
+#[test]
+fn test_source_rows_off_by_default() {
+    assert!(!ConversionOptions::default().source_rows);
+    let (_dir, events) = convert(false);
+    assert_eq!(events.len(), 6);
+    assert!(events
+        .iter()
+        .all(|e| !e.args.contains_key(SOURCE_TABLE_ARG) && !e.args.contains_key(SOURCE_ROWID_ARG)));
+}
@@ -0,0 +1,128 @@
+//! Unit tests for opening exports by URI and from memory
+
+mod common;
+
+use common::create_sqlite;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::converter::read_only_uri;
+use nsys_chrome::models::ConversionOptions;
+use nsys_chrome::{ConvertError, NsysChromeConverter};
+use std::path::Path;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+const NVTX_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES
+        (1000, 2000, 'forward', NULL, 16777217, 59),
+        (3000, 4000, 'backward', NULL, 16777217, 59);
+";
+
+fn options(jobs: usize) -> Option<ConversionOptions> {
+    Some(ConversionOptions {
+        activity_types: vec![EventCategory::Nvtx],
+        jobs,
+        ..Default::default()
+    })
+}
+
+fn nvtx_names(converter: NsysChromeConverter) -> Vec<String> {
+    converter
+        .convert()
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.cat == "nvtx")
+        .map(|e| e.name)
+        .collect()
+}
+
+// ==========================
+// Tests for read_only_uri
+// ==========================
+
+#[test]
+fn test_read_only_uri_escapes_path() {
+    assert_eq!(
+        read_only_uri(Path::new("/mnt/traces/run 1.sqlite")),
+        "file:/mnt/traces/run 1.sqlite?mode=ro&immutable=1"
+    );
+    assert_eq!(
+        read_only_uri(Path::new("/tmp/a?b#c%d.sqlite")),
+        "file:/tmp/a%3fb%23c%25d.sqlite?mode=ro&immutable=1"
+    );
+}
+
+// ==========================
+// Tests for URI sources
+// ==========================
+
+#[test]
+fn test_open_read_only_and_uri() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "run #1?.sqlite", NVTX_SQL);
+
+    for jobs in [1, 2] {
+        let converter = NsysChromeConverter::open_read_only(&path, options(jobs)).unwrap();
+        assert_eq!(nvtx_names(converter), vec!["forward", "backward"]);
+    }
+
+    let uri = read_only_uri(Path::new(&path));
+    let converter = NsysChromeConverter::from_uri(&uri, options(1)).unwrap();
+    assert_eq!(nvtx_names(converter), vec!["forward", "backward"]);
+
+    // Missing files are reported, not created
+    assert!(matches!(
+        NsysChromeConverter::open_read_only(
+            dir.path().join("missing.sqlite").to_str().unwrap(),
+            None
+        ),
+        Err(ConvertError::InputNotFound(_))
+    ));
+}
+
+// ==========================
+// Tests for in-memory sources
+// ==========================
+
+#[test]
+fn test_from_bytes_matches_file() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "trace.sqlite", NVTX_SQL);
+    let bytes = std::fs::read(&path).unwrap();
+
+    let from_file = NsysChromeConverter::new(&path, options(1))
+        .unwrap()
+        .convert()
+        .unwrap();
+    for jobs in [1, 4] {
+        let from_bytes = NsysChromeConverter::from_bytes(&bytes, options(jobs))
+            .unwrap()
+            .convert()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&from_bytes).unwrap(),
+            serde_json::to_value(&from_file).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_from_bytes_rejects_non_sqlite() {
+    let converter =
+        NsysChromeConverter::from_bytes(b"{\"traceEvents\": []} not a database", None).unwrap();
+    assert!(matches!(
+        converter.convert(),
+        Err(ConvertError::InvalidInput(_))
+    ));
+
+    // No bytes is an empty database, like an empty file
+    let converter = NsysChromeConverter::from_bytes(&[], options(1)).unwrap();
+    assert!(converter.convert().is_ok());
+}
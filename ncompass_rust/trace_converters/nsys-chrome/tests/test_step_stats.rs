@@ -0,0 +1,168 @@
+//! Unit tests for per-step statistics
+
+use nsys_chrome::analysis::{
+    parse_step_pattern, step_stats, synthesize_step_markers, DEFAULT_STEP_PATTERN,
+};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::ChromeTraceEvent;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(
+    name: &str,
+    cat: EventCategory,
+    pid: &str,
+    start_us: f64,
+    dur_us: f64,
+) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_us,
+        dur_us,
+        pid.to_string(),
+        "Thread 1".to_string(),
+        cat,
+    )
+}
+
+fn create_kernel(name: &str, device_id: i64, start_us: f64, dur_us: f64) -> ChromeTraceEvent {
+    create_event(
+        name,
+        EventCategory::Kernel,
+        &format!("Device {}", device_id),
+        start_us,
+        dur_us,
+    )
+    .with_arg("deviceId", serde_json::json!(device_id))
+}
+
+/// Three 100 us steps; the second waits 60 us on the data loader
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_event("step 0", EventCategory::Nvtx, "Process 1", 0.0, 100.0),
+        create_event("forward", EventCategory::Nvtx, "Process 1", 0.0, 50.0),
+        create_kernel("gemm", 0, 0.0, 40.0),
+        create_kernel("relu", 0, 30.0, 30.0),
+        create_kernel("ncclAllReduceKernel", 0, 70.0, 20.0),
+        create_event("step 1", EventCategory::Nvtx, "Process 1", 100.0, 100.0),
+        create_kernel("gemm", 0, 160.0, 40.0),
+        create_event("step 2", EventCategory::Nvtx, "Process 1", 200.0, 120.0),
+        // Starts in step 1 and runs into step 2
+        create_kernel("gemm", 1, 190.0, 100.0),
+    ]
+}
+
+// ==========================
+// Tests for step_stats
+// ==========================
+
+#[test]
+fn test_step_stats_measures_work_inside_each_step() {
+    let pattern = parse_step_pattern(DEFAULT_STEP_PATTERN).unwrap();
+    let stats = step_stats(&sample_events(), &pattern);
+
+    let rows: Vec<(i64, i64, usize, i64, i64)> = stats
+        .steps
+        .iter()
+        .map(|s| (s.step, s.gpu_ns, s.kernels, s.comm_ns, s.idle_ns))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (0, 80_000, 3, 20_000, 20_000),
+            (1, 40_000, 2, 0, 60_000),
+            (2, 90_000, 0, 0, 30_000),
+        ]
+    );
+    assert_eq!(stats.steps[1].event_index, 5);
+    assert_eq!(stats.median_duration_ns(), 100_000);
+    assert_eq!(stats.deviation_pct(&stats.steps[2]), 20.0);
+}
+
+#[test]
+fn test_step_pattern_matches_common_step_names() {
+    let pattern = parse_step_pattern(DEFAULT_STEP_PATTERN).unwrap();
+    for name in ["step 3", "Step#12", "train_step", "iteration_7", "iter 2"] {
+        assert!(pattern.is_match(name), "{}", name);
+    }
+    for name in [
+        "forward",
+        "steps_per_epoch",
+        "iterator_next",
+        "optimizer.step",
+    ] {
+        assert!(!pattern.is_match(name), "{}", name);
+    }
+    assert!(parse_step_pattern("step(").is_err());
+
+    // A custom pattern picks other ranges, numbered by position
+    let custom = parse_step_pattern("^forward$").unwrap();
+    let stats = step_stats(&sample_events(), &custom);
+    assert_eq!(stats.steps.len(), 1);
+    assert_eq!(stats.steps[0].step, 0);
+    assert_eq!(stats.steps[0].gpu_ns, 50_000);
+}
+
+#[test]
+fn test_synthetic_steps_count_only_their_device() {
+    let mut kernels = Vec::new();
+    for step in 0..3 {
+        let base = step as f64 * 1000.0;
+        kernels.push(
+            create_kernel("gemm_fwd", 0, base, 400.0)
+                .with_arg("start_ns", serde_json::json!(step * 1_000_000))
+                .with_arg("end_ns", serde_json::json!(step * 1_000_000 + 400_000)),
+        );
+        let start = step * 1_000_000 + 900_000;
+        kernels.push(
+            create_kernel("multi_tensor_apply_kernel", 0, start as f64 / 1000.0, 10.0)
+                .with_arg("start_ns", serde_json::json!(start))
+                .with_arg("end_ns", serde_json::json!(start + 10_000)),
+        );
+    }
+    let mut events = synthesize_step_markers(&kernels);
+    assert_eq!(events.len(), 3);
+    events.extend(kernels);
+    events.push(create_kernel("other_device", 1, 100.0, 100.0));
+
+    let pattern = parse_step_pattern("^never$").unwrap();
+    let stats = step_stats(&events, &pattern);
+    assert_eq!(stats.steps.len(), 3);
+    assert!(stats.steps.iter().all(|s| s.device_id == Some(0)));
+    assert_eq!(stats.steps[0].kernels, 2);
+    assert_eq!(stats.steps[0].gpu_ns, 410_000);
+}
+
+// ==========================
+// Tests for output
+// ==========================
+
+#[test]
+fn test_step_stats_annotate_and_csv() {
+    let mut events = sample_events();
+    let pattern = parse_step_pattern(DEFAULT_STEP_PATTERN).unwrap();
+    let stats = step_stats(&events, &pattern);
+    stats.annotate(&mut events);
+
+    assert_eq!(events[5].args["step_gpu_ns"], 40_000);
+    assert_eq!(events[5].args["step_kernels"], 2);
+    assert_eq!(events[5].args["step_idle_ns"], 60_000);
+    assert_eq!(events[7].args["step_deviation_pct"], 20.0);
+    assert!(!events[1].args.contains_key("step_gpu_ns"));
+
+    let mut csv = Vec::new();
+    stats.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "step,name,device_id,start_ns,end_ns,duration_ns,gpu_ns,kernels,comm_ns,idle_ns,deviation_pct"
+    );
+    assert_eq!(
+        lines[1],
+        "0,step 0,,0,100000,100000,80000,3,20000,20000,0.0"
+    );
+    assert_eq!(lines.len(), 4);
+}
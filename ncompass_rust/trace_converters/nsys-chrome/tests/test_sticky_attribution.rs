@@ -0,0 +1,150 @@
+//! Unit tests for sticky attribution of calls launched outside every NVTX range
+
+mod common;
+
+use common::create_thread_event;
+use nsys_chrome::linker::{link_nvtx_to_kernels_windowed, link_nvtx_to_kernels_with_stats};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::presets::overlay_options;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Thread 1 pops "forward" (nested "attn" ending with it) before launching
+/// kernel 2, and launches kernel 3 long after; thread 2 launches kernel 4
+/// without any range of its own
+#[allow(clippy::type_complexity)]
+fn popped_ranges() -> (
+    Vec<ChromeTraceEvent>,
+    Vec<ChromeTraceEvent>,
+    Vec<ChromeTraceEvent>,
+) {
+    let nvtx = vec![
+        create_thread_event("forward", "nvtx", 0, 10_000, 1, None),
+        create_thread_event("attn", "nvtx", 5000, 10_000, 1, None),
+    ];
+    let api = vec![
+        create_thread_event("cudaLaunchKernel", "cuda_api", 1000, 2000, 1, Some(1)),
+        create_thread_event("cudaLaunchKernel", "cuda_api", 12_000, 13_000, 1, Some(2)),
+        create_thread_event("cudaLaunchKernel", "cuda_api", 900_000, 901_000, 1, Some(3)),
+        create_thread_event("cudaLaunchKernel", "cuda_api", 12_000, 13_000, 2, Some(4)),
+    ];
+    let kernels = vec![
+        create_thread_event("k1", "kernel", 3000, 4000, 0, Some(1)),
+        create_thread_event("k2", "kernel", 20_000, 21_000, 0, Some(2)),
+        create_thread_event("k3", "kernel", 905_000, 906_000, 0, Some(3)),
+        create_thread_event("k4", "kernel", 22_000, 23_000, 0, Some(4)),
+    ];
+    (nvtx, api, kernels)
+}
+
+fn options(sticky_lookback_ns: Option<i64>) -> ConversionOptions {
+    ConversionOptions {
+        sticky_lookback_ns,
+        ..Default::default()
+    }
+}
+
+/// (name, start ns, end ns, attribution arg) of each linked range, sorted
+fn summarize(linked: &[ChromeTraceEvent]) -> Vec<(String, i64, i64, Option<String>)> {
+    let mut ranges: Vec<_> = linked
+        .iter()
+        .map(|e| {
+            (
+                e.name.clone(),
+                (e.ts * 1000.0).round() as i64,
+                ((e.ts + e.dur.unwrap()) * 1000.0).round() as i64,
+                e.args
+                    .get("attribution")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+            )
+        })
+        .collect();
+    ranges.sort();
+    ranges
+}
+
+// ==========================
+// Tests for sticky_lookback_ns
+// ==========================
+
+#[test]
+fn test_calls_after_a_range_stick_to_the_innermost_one() {
+    let (nvtx, api, kernels) = popped_ranges();
+    let ((linked, _, _), stats) =
+        link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, &options(Some(5000)));
+
+    assert_eq!(stats.sticky_calls, 1);
+    assert_eq!(
+        summarize(&linked),
+        vec![
+            (
+                "attn".to_string(),
+                20_000,
+                21_000,
+                Some("sticky".to_string())
+            ),
+            ("forward".to_string(), 3000, 4000, None),
+        ]
+    );
+    let attn = linked.iter().find(|e| e.name == "attn").unwrap();
+    assert_eq!(attn.args["link_confidence"], 0.0);
+}
+
+#[test]
+fn test_disabled_or_out_of_lookback_calls_stay_unlinked() {
+    let (nvtx, api, kernels) = popped_ranges();
+
+    let ((linked, _, _), stats) =
+        link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, &options(None));
+    assert_eq!(stats.sticky_calls, 0);
+    assert_eq!(
+        summarize(&linked),
+        vec![("forward".to_string(), 3000, 4000, None)]
+    );
+
+    // Too short for the 2 us gap after "attn" ends
+    let ((linked, _, _), stats) =
+        link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, &options(Some(1000)));
+    assert_eq!(stats.sticky_calls, 0);
+    assert_eq!(linked.len(), 1);
+}
+
+#[test]
+fn test_long_lookback_reaches_later_calls_on_the_same_thread_only() {
+    let (nvtx, api, kernels) = popped_ranges();
+    let ((linked, _, _), stats) =
+        link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, &options(Some(1_000_000)));
+
+    // Kernel 4 was launched by thread 2, which has no ranges
+    assert_eq!(stats.sticky_calls, 2);
+    assert_eq!(
+        summarize(&linked),
+        vec![
+            (
+                "attn".to_string(),
+                20_000,
+                906_000,
+                Some("sticky".to_string())
+            ),
+            ("forward".to_string(), 3000, 4000, None),
+        ]
+    );
+}
+
+#[test]
+fn test_windowed_linking_and_overlay_match() {
+    let (nvtx, api, kernels) = popped_ranges();
+    let options = overlay_options(options(None), r#"{"sticky_lookback": "5us"}"#).unwrap();
+    assert_eq!(options.sticky_lookback_ns, Some(5000));
+
+    let ((in_memory, _, _), _) = link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, &options);
+    for window_ns in [1, 5000, 1_000_000] {
+        let ((windowed, _, _), stats) =
+            link_nvtx_to_kernels_windowed(&nvtx, &api, &kernels, &options, window_ns).unwrap();
+        assert_eq!(summarize(&windowed), summarize(&in_memory));
+        assert_eq!(stats.sticky_calls, 1);
+    }
+}
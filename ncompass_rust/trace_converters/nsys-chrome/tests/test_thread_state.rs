@@ -0,0 +1,121 @@
+//! Unit tests for thread-state slices built from context switches
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::parsers::{
+    classify_switch_out, CpuThreadState, EventParser, ParseContext, ThreadStateParser,
+};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// A launcher thread that runs, is preempted, runs, waits on an event, and
+/// runs again; rows are inserted out of order on purpose
+const SCHED_SQL: &str = "
+    CREATE TABLE SCHED_EVENTS (
+        start INTEGER, cpu INTEGER, isSchedIn INTEGER, globalTid INTEGER,
+        threadState INTEGER, threadBlock INTEGER
+    );
+    INSERT INTO SCHED_EVENTS VALUES
+        (3000, 2, 1, 16777217, NULL, NULL),
+        (1000, 0, 1, 16777217, NULL, NULL),
+        (2000, 0, 0, 16777217, 1, 32),
+        (5000, 2, 0, 16777217, 5, 6),
+        (9000, 1, 1, 16777217, NULL, NULL);
+";
+
+fn parse(conn: &Connection) -> Vec<ChromeTraceEvent> {
+    let strings = HashMap::new();
+    let options = ConversionOptions::default();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::from([(1, "launcher".to_string())]);
+    let context = ParseContext::new(conn, &strings, &options, &device_map, &thread_names);
+    ThreadStateParser.safe_parse(&context).unwrap()
+}
+
+// ==========================
+// Tests for ThreadStateParser
+// ==========================
+
+#[test]
+fn test_switches_become_state_slices() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(SCHED_SQL).unwrap();
+    let events = parse(&conn);
+
+    let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["Running", "Preempted", "Running", "Waiting (UserRequest)"]
+    );
+    assert!(events
+        .iter()
+        .all(|e| e.pid == "Process 1" && e.tid == "launcher (state)"));
+
+    let preempted = &events[1];
+    assert_eq!(preempted.args["start_ns"], 2000);
+    assert_eq!(preempted.args["end_ns"], 3000);
+    assert_eq!(preempted.args["thread_state"], "preempted");
+    assert_eq!(preempted.args["wait_reason"], "WrPreempted");
+    assert_eq!(preempted.cname.as_deref(), Some("thread_state_runnable"));
+
+    let running = &events[2];
+    assert_eq!(running.args["cpu"], 2);
+    assert_eq!(running.cname.as_deref(), Some("thread_state_running"));
+
+    let waiting = &events[3];
+    assert_eq!(waiting.args["end_ns"], 9000);
+    assert_eq!(waiting.cname.as_deref(), Some("thread_state_sleeping"));
+}
+
+#[test]
+fn test_classify_switch_out() {
+    assert_eq!(
+        classify_switch_out(Some(1), None),
+        CpuThreadState::Preempted
+    );
+    assert_eq!(
+        classify_switch_out(Some(5), Some(30)),
+        CpuThreadState::Preempted
+    );
+    assert_eq!(classify_switch_out(Some(5), Some(9)), CpuThreadState::Io);
+    assert_eq!(
+        classify_switch_out(Some(5), Some(13)),
+        CpuThreadState::Waiting
+    );
+    // Linux captures record no state
+    assert_eq!(classify_switch_out(None, None), CpuThreadState::Unknown);
+}
+
+#[test]
+fn test_missing_sched_table() {
+    let conn = Connection::open_in_memory().unwrap();
+    assert!(parse(&conn).is_empty());
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_emits_thread_states_when_requested() {
+    let convert = |types: &[&str]| -> Vec<ChromeTraceEvent> {
+        let options = ConversionOptions {
+            activity_types: types.iter().map(|&t| EventCategory::from(t)).collect(),
+            ..Default::default()
+        };
+        convert_sql(SCHED_SQL, options)
+    };
+
+    let events = convert(&["sched", "thread-state"]);
+    assert_eq!(events.iter().filter(|e| e.cat == "thread-state").count(), 4);
+    assert_eq!(events.iter().filter(|e| e.cat == "sched").count(), 5);
+
+    assert!(convert(&["sched"]).iter().all(|e| e.cat != "thread-state"));
+}
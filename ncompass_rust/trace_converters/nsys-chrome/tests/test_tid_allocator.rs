@@ -0,0 +1,180 @@
+//! Unit tests for virtual thread IDs
+
+use nsys_chrome::models::{
+    BindingPoint, ChromeTraceEvent, ChromeTracePhase, ConversionOptions, StringOrInt, TidGrouping,
+};
+use nsys_chrome::presets::overlay_options;
+use nsys_chrome::tid_allocator::{
+    parse_tid_grouping, virtualize_tids, TidAllocator, THREAD_SORT_INDEX_EVENT,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn slice(tid: &str, cat: &str, raw_tid: Option<i64>) -> ChromeTraceEvent {
+    let event = ChromeTraceEvent::complete(
+        format!("{} slice", cat),
+        1.0,
+        2.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    );
+    match raw_tid {
+        Some(raw_tid) => event.with_arg("raw_tid", raw_tid),
+        None => event,
+    }
+}
+
+/// NVTX and CUDA API tracks of OS threads 5 and 9, plus one GPU stream
+fn two_threads() -> Vec<ChromeTraceEvent> {
+    vec![
+        slice("NVTX Thread 9", "nvtx", Some(9)),
+        slice("CUDA API Thread 5", "cuda_api", Some(5)),
+        slice("Stream 7", "kernel", None),
+        slice("NVTX Thread 5", "nvtx", Some(5)),
+        slice("CUDA API Thread 9", "cuda_api", Some(9)),
+    ]
+}
+
+fn track_names(allocator: &TidAllocator) -> Vec<&str> {
+    allocator
+        .tracks("Device 0")
+        .iter()
+        .map(|key| key.name.as_str())
+        .collect()
+}
+
+fn metadata_arg(events: &[ChromeTraceEvent], name: &str, tid: &str) -> Option<serde_json::Value> {
+    events
+        .iter()
+        .find(|e| e.ph == ChromeTracePhase::Metadata && e.name == name && e.tid == tid)
+        .and_then(|e| e.args.values().next().cloned())
+}
+
+// ==========================
+// Tests for TidAllocator
+// ==========================
+
+#[test]
+fn test_grouping_by_category_and_by_thread() {
+    let events = two_threads();
+
+    let by_category = TidAllocator::from_events(&events, TidGrouping::Category);
+    assert_eq!(
+        track_names(&by_category),
+        vec![
+            "Stream 7",
+            "NVTX Thread 5",
+            "NVTX Thread 9",
+            "CUDA API Thread 5",
+            "CUDA API Thread 9",
+        ]
+    );
+
+    let by_thread = TidAllocator::from_events(&events, TidGrouping::Thread);
+    assert_eq!(
+        track_names(&by_thread),
+        vec![
+            "Stream 7",
+            "NVTX Thread 5",
+            "CUDA API Thread 5",
+            "NVTX Thread 9",
+            "CUDA API Thread 9",
+        ]
+    );
+    assert_eq!(
+        by_thread.virtual_tid("Device 0", "CUDA API Thread 5"),
+        Some(3)
+    );
+    assert_eq!(by_thread.virtual_tid("Device 0", "Thread 1"), None);
+}
+
+#[test]
+fn test_tids_do_not_depend_on_event_order() {
+    let events = two_threads();
+    let mut reversed = events.clone();
+    reversed.reverse();
+
+    let forward = TidAllocator::from_events(&events, TidGrouping::Category);
+    let backward = TidAllocator::from_events(&reversed, TidGrouping::Category);
+    for event in &events {
+        assert_eq!(
+            forward.virtual_tid("Device 0", event.tid.as_str()),
+            backward.virtual_tid("Device 0", event.tid.as_str())
+        );
+    }
+}
+
+#[test]
+fn test_flows_and_metadata_follow_their_track() {
+    let mut events = two_threads();
+    events.push(ChromeTraceEvent::flow_start(
+        1.0,
+        "Device 0".to_string(),
+        "CUDA API Thread 5".to_string(),
+        StringOrInt::Int(1),
+    ));
+    events.push(ChromeTraceEvent::flow_finish(
+        3.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        StringOrInt::Int(1),
+        BindingPoint::Enclosing,
+    ));
+    events.push(ChromeTraceEvent::metadata(
+        "thread_name".to_string(),
+        "Device 0".to_string(),
+        "NVTX Thread 5".to_string(),
+        HashMap::from([("name".to_string(), json!("main"))]),
+    ));
+
+    let renumbered = virtualize_tids(&mut events, TidGrouping::Category);
+    assert_eq!(renumbered, 5);
+
+    let flow_tids: Vec<&str> = events
+        .iter()
+        .filter(|e| e.id.is_some())
+        .map(|e| e.tid.as_str())
+        .collect();
+    assert_eq!(flow_tids, vec!["4", "1"]);
+
+    // The existing name is kept, the other tracks are named after their old tid
+    assert_eq!(
+        metadata_arg(&events, "thread_name", "2"),
+        Some(json!("main"))
+    );
+    assert_eq!(
+        metadata_arg(&events, "thread_name", "3"),
+        Some(json!("NVTX Thread 9"))
+    );
+    let names = events.iter().filter(|e| e.name == "thread_name").count();
+    assert_eq!(names, 5);
+    for tid in 1..=5 {
+        assert_eq!(
+            metadata_arg(&events, THREAD_SORT_INDEX_EVENT, &tid.to_string()),
+            Some(json!(tid))
+        );
+    }
+}
+
+#[test]
+fn test_tid_grouping_option() {
+    assert_eq!(
+        parse_tid_grouping("category").unwrap(),
+        TidGrouping::Category
+    );
+    assert_eq!(parse_tid_grouping("thread").unwrap(), TidGrouping::Thread);
+    assert!(parse_tid_grouping("device").is_err());
+
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"virtual_tids": "thread"}"#,
+    )
+    .unwrap();
+    assert_eq!(options.virtual_tids, Some(TidGrouping::Thread));
+    assert_eq!(ConversionOptions::default().virtual_tids, None);
+}
@@ -0,0 +1,136 @@
+//! Unit tests for timestamp origin rebasing
+
+use nsys_chrome::analysis::{
+    apply_time_origin, parse_time_origin, resolve_time_origin, TIME_ORIGIN_EVENT,
+};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::frontends::{assemble_trace, FrontendTrace};
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase, ConversionOptions, TimeOrigin};
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+const EPOCH_NS: i64 = 1_700_000_000_000_000_000;
+
+fn create_event(name: &str, start_ns: i64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        10.0,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(start_ns))
+    .with_arg("end_ns", serde_json::json!(start_ns + 10_000))
+}
+
+fn sample_events() -> Vec<ChromeTraceEvent> {
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), serde_json::json!("Device 0"));
+    vec![
+        ChromeTraceEvent::metadata(
+            "process_name".to_string(),
+            "Device 0".to_string(),
+            String::new(),
+            args,
+        ),
+        create_event("warmup", EPOCH_NS + 1_000, "NVTX Thread 1", "nvtx"),
+        create_event("step", EPOCH_NS + 50_000, "NVTX Thread 1", "nvtx"),
+        create_event("gemm", EPOCH_NS + 60_000, "Stream 7", "kernel"),
+        create_event("step", EPOCH_NS + 90_000, "NVTX Thread 1", "nvtx"),
+    ]
+}
+
+// ==========================
+// Tests for parse_time_origin
+// ==========================
+
+#[test]
+fn test_parse_time_origin() {
+    assert_eq!(parse_time_origin("absolute").unwrap(), TimeOrigin::Absolute);
+    assert_eq!(
+        parse_time_origin("capture-start").unwrap(),
+        TimeOrigin::CaptureStart
+    );
+    assert_eq!(
+        parse_time_origin("nvtx:train step").unwrap(),
+        TimeOrigin::NvtxRange("train step".to_string())
+    );
+    assert!(parse_time_origin("nvtx:").is_err());
+    assert!(parse_time_origin("start").is_err());
+}
+
+// ==========================
+// Tests for apply_time_origin
+// ==========================
+
+#[test]
+fn test_capture_start_origin() {
+    let mut events = sample_events();
+    let epoch = apply_time_origin(&mut events, &TimeOrigin::CaptureStart);
+    assert_eq!(epoch, Some(EPOCH_NS + 1_000));
+
+    assert_eq!(events[1].ts, 0.0);
+    assert_eq!(events[3].ts, 59.0);
+    // Exact nanosecond args stay absolute
+    assert_eq!(events[3].args["start_ns"], EPOCH_NS + 60_000);
+    // Metadata events are not shifted
+    assert_eq!(events[0].ts, 0.0);
+
+    let origin = events.last().unwrap();
+    assert_eq!(origin.ph, ChromeTracePhase::Metadata);
+    assert_eq!(origin.name, TIME_ORIGIN_EVENT);
+    assert_eq!(origin.pid, "Device 0");
+    assert_eq!(origin.args["epoch_ns"], EPOCH_NS + 1_000);
+    assert_eq!(origin.args["origin"], "capture-start");
+}
+
+#[test]
+fn test_nvtx_range_origin() {
+    let events = sample_events();
+    let origin = TimeOrigin::NvtxRange("step".to_string());
+    assert_eq!(
+        resolve_time_origin(&events, &origin),
+        Some(EPOCH_NS + 50_000)
+    );
+
+    let mut events = sample_events();
+    apply_time_origin(&mut events, &origin);
+    assert_eq!(events[1].ts, -49.0);
+    assert_eq!(events[2].ts, 0.0);
+    assert_eq!(events[4].ts, 40.0);
+
+    // Unknown range names and the absolute origin leave timestamps untouched
+    let mut events = sample_events();
+    let unknown = TimeOrigin::NvtxRange("missing".to_string());
+    assert_eq!(apply_time_origin(&mut events, &unknown), None);
+    assert_eq!(apply_time_origin(&mut events, &TimeOrigin::Absolute), None);
+    assert_eq!(events.len(), 5);
+    assert_eq!(events[1].ts, (EPOCH_NS + 1_000) as f64 / 1000.0);
+}
+
+#[test]
+fn test_assemble_trace_rebases_linked_events() {
+    let kernel = create_event("gemm", EPOCH_NS + 2_000, "Stream 7", "kernel")
+        .with_arg("deviceId", serde_json::json!(0))
+        .with_arg("correlationId", serde_json::json!(1));
+    let trace = FrontendTrace {
+        kernel_events: vec![kernel],
+        annotation_events: vec![create_event("step", EPOCH_NS, "NVTX Thread 1", "nvtx")],
+        ..Default::default()
+    };
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel, EventCategory::Nvtx],
+        include_metadata: false,
+        time_origin: TimeOrigin::NvtxRange("step".to_string()),
+        ..Default::default()
+    };
+    let events = assemble_trace(trace, &options).unwrap();
+
+    let kernel = events.iter().find(|e| e.cat == "kernel").unwrap();
+    assert_eq!(kernel.ts, 2.0);
+    assert!(events.iter().any(|e| e.name == TIME_ORIGIN_EVENT));
+}
@@ -0,0 +1,179 @@
+//! Unit tests for shifting annotations before NVTX-kernel linking
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::linker::{align_annotations, estimate_time_shift, parse_time_shift_spec};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions, TimeShift};
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// One thread launching two kernels at 1100 and 1300 ns, wrapped by an NVTX
+/// range recorded 4 us late
+const LATE_RANGE_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES
+        (1, 'cudaLaunchKernel'), (2, 'gemm_kernel'), (3, 'relu_kernel');
+    CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+        start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+        correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+        gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+        blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+        registersPerThread INTEGER, staticSharedMemory INTEGER,
+        dynamicSharedMemory INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+        (3000, 4000, 0, 7, 1, 16777216, 2, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+        (4000, 4500, 0, 7, 2, 16777216, 3, 1, 1, 1, 1, 1, 1, 32, 0, 0);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+        start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES
+        (1100, 1200, 16777217, 1, 1),
+        (1300, 1400, 16777217, 2, 1);
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES (5000, 6000, 'forward', NULL, 16777217, 59);
+";
+
+fn convert(shift: Option<TimeShift>) -> Vec<ChromeTraceEvent> {
+    let options = ConversionOptions {
+        activity_types: vec![
+            EventCategory::Kernel,
+            EventCategory::CudaApi,
+            EventCategory::Nvtx,
+            EventCategory::NvtxKernel,
+        ],
+        annotation_time_shifts: shift
+            .map(|shift| HashMap::from([("nvtx".to_string(), shift)]))
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    convert_sql(LATE_RANGE_SQL, options)
+}
+
+fn create_event(cat: &str, start_ns: i64, end_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        cat.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        "Thread 1".to_string(),
+        cat.to_string(),
+    )
+    .with_arg("deviceId", 0)
+    .with_arg("raw_tid", 1)
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+}
+
+/// Launches at `starts`, each with its kernel
+fn launches(starts: &[i64]) -> (Vec<ChromeTraceEvent>, Vec<ChromeTraceEvent>) {
+    let mut api = Vec::new();
+    let mut kernels = Vec::new();
+    for (correlation, &start) in starts.iter().enumerate() {
+        api.push(
+            create_event("cuda_api", start, start + 50).with_arg("correlationId", correlation),
+        );
+        kernels.push(
+            create_event("kernel", start + 5000, start + 6000)
+                .with_arg("correlationId", correlation),
+        );
+    }
+    (api, kernels)
+}
+
+// ==========================
+// Tests for parse_time_shift_spec
+// ==========================
+
+#[test]
+fn test_parse_time_shift_spec() {
+    assert_eq!(
+        parse_time_shift_spec("nvtx=-20us").unwrap(),
+        ("nvtx".to_string(), TimeShift::Fixed(-20_000))
+    );
+    assert_eq!(
+        parse_time_shift_spec("MyWrapper=+1.5ms").unwrap(),
+        ("MyWrapper".to_string(), TimeShift::Fixed(1_500_000))
+    );
+    assert_eq!(
+        parse_time_shift_spec("nvtx=auto").unwrap(),
+        ("nvtx".to_string(), TimeShift::Estimated)
+    );
+    assert!(parse_time_shift_spec("-20us").is_err());
+    assert!(parse_time_shift_spec("=5us").is_err());
+    assert!(parse_time_shift_spec("nvtx=soon").is_err());
+}
+
+// ==========================
+// Tests for align_annotations
+// ==========================
+
+#[test]
+fn test_fixed_shift_prefers_domain_over_category() {
+    let mut annotations = vec![
+        create_event("nvtx", 5000, 6000),
+        create_event("nvtx", 5000, 6000).with_arg("domain", "MyWrapper"),
+    ];
+    let shifts = HashMap::from([
+        ("nvtx".to_string(), TimeShift::Fixed(-1000)),
+        ("MyWrapper".to_string(), TimeShift::Fixed(2000)),
+    ]);
+
+    let applied = align_annotations(&mut annotations, &[], &[], &shifts);
+    assert_eq!(applied.len(), 2);
+    assert_eq!(annotations[0].args["start_ns"], 4000);
+    assert_eq!(annotations[0].args["end_ns"], 5000);
+    assert_eq!(annotations[0].ts, 4.0);
+    assert_eq!(annotations[0].args["time_shift_ns"], -1000);
+    assert_eq!(annotations[1].args["start_ns"], 7000);
+    assert_eq!(annotations[1].args["time_shift_ns"], 2000);
+}
+
+#[test]
+fn test_estimate_recovers_offset() {
+    // Each range wraps its launches but was recorded about 3 us late
+    let (api, kernels) = launches(&[1000, 1200, 5000, 5400, 9000]);
+    let annotations = [
+        create_event("nvtx", 3900, 4300),
+        create_event("nvtx", 7900, 8500),
+        create_event("nvtx", 11900, 12100),
+    ];
+    let refs: Vec<&ChromeTraceEvent> = annotations.iter().collect();
+    // Shifts from -3100 to -2900 ns cover all five launches
+    assert_eq!(estimate_time_shift(&refs, &api, &kernels), -2900);
+
+    // Ranges already covering their launches stay in place
+    let aligned = [
+        create_event("nvtx", 900, 1300),
+        create_event("nvtx", 4900, 5500),
+    ];
+    let refs: Vec<&ChromeTraceEvent> = aligned.iter().collect();
+    assert_eq!(estimate_time_shift(&refs, &api, &kernels), 0);
+    assert_eq!(estimate_time_shift(&refs, &[], &kernels), 0);
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_shift_links_late_range() {
+    let linked =
+        |events: &[ChromeTraceEvent]| events.iter().filter(|e| e.cat == "nvtx-kernel").count();
+
+    assert_eq!(linked(&convert(None)), 0);
+    assert_eq!(linked(&convert(Some(TimeShift::Fixed(-4000)))), 1);
+
+    let events = convert(Some(TimeShift::Estimated));
+    assert_eq!(linked(&events), 1);
+    // The linked range is replaced by its nvtx-kernel event
+    assert!(!events.iter().any(|e| e.cat == "nvtx"));
+}
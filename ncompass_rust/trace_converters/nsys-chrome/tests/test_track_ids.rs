@@ -0,0 +1,197 @@
+//! Unit tests for track_ids module
+
+use nsys_chrome::intern::InternedStr;
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::track_ids::{sidecar_path, TrackIdMap};
+use nsys_chrome::writer::ChromeTraceWriter;
+use std::collections::HashMap;
+use tempfile::NamedTempFile;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, dur: f64, pid: &str, tid: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        pid.to_string(),
+        tid.to_string(),
+        "kernel".to_string(),
+    )
+}
+
+/// Write events in numeric mode and return (parsed events, mapping)
+fn write_numeric(events: Vec<ChromeTraceEvent>) -> (Vec<serde_json::Value>, TrackIdMap) {
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_str().unwrap();
+    let track_ids = ChromeTraceWriter::write_numeric_ids(path, events).unwrap();
+
+    let content = std::fs::read_to_string(path).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    (parsed["traceEvents"].as_array().unwrap().clone(), track_ids)
+}
+
+// ==========================
+// Tests for TrackIdMap
+// ==========================
+
+#[test]
+fn test_track_ids_assigned_in_first_seen_order() {
+    let mut ids = TrackIdMap::new();
+    let device1 = InternedStr::new("Device 1");
+    let device0 = InternedStr::new("Device 0");
+
+    assert_eq!(ids.process_id(&device1), 1);
+    assert_eq!(ids.process_id(&device0), 2);
+    assert_eq!(ids.process_id(&device1), 1);
+
+    let stream = InternedStr::new("Stream 7");
+    assert_eq!(ids.thread_id(1, &stream), 1);
+    // Same thread name in another process is a different track
+    assert_eq!(ids.thread_id(2, &stream), 2);
+    assert_eq!(ids.thread_id(1, &stream), 1);
+}
+
+#[test]
+fn test_track_ids_map_event_replaces_strings() {
+    let mut ids = TrackIdMap::new();
+    let mut event = create_event("k", 0.0, 1.0, "Device 0", "Stream 7");
+    ids.map_event(&mut event);
+
+    assert_eq!(event.pid, "1");
+    assert_eq!(event.tid, "1");
+    assert_eq!(ids.processes()[0].name, "Device 0");
+    assert_eq!(ids.threads()[0].name, "Stream 7");
+}
+
+#[test]
+fn test_track_ids_name_events_skip_named_tracks() {
+    let mut ids = TrackIdMap::new();
+    let mut args = HashMap::new();
+    args.insert("name".to_string(), serde_json::json!("GPU 0"));
+    let mut metadata = ChromeTraceEvent::metadata(
+        "process_name".to_string(),
+        "Device 0".to_string(),
+        String::new(),
+        args,
+    );
+    ids.map_event(&mut metadata);
+    assert_eq!(metadata.tid, "0");
+
+    let mut event = create_event("k", 0.0, 1.0, "Device 0", "Stream 7");
+    ids.map_event(&mut event);
+
+    let names = ids.name_events();
+    assert_eq!(names.len(), 1);
+    assert_eq!(names[0].name, "thread_name");
+    assert_eq!(names[0].args["name"], "Stream 7");
+}
+
+#[test]
+fn test_sidecar_path() {
+    assert_eq!(sidecar_path("trace.json.gz"), "trace.ids.json");
+    assert_eq!(sidecar_path("trace.json"), "trace.ids.json");
+    assert_eq!(sidecar_path("out/trace"), "out/trace.ids.json");
+}
+
+#[test]
+fn test_track_ids_sidecar_json() {
+    let mut ids = TrackIdMap::new();
+    let mut event = create_event("k", 0.0, 1.0, "Device 0", "Stream 7");
+    ids.map_event(&mut event);
+
+    let json = ids.to_json();
+    assert_eq!(json["processes"][0]["pid"], 1);
+    assert_eq!(json["processes"][0]["name"], "Device 0");
+    assert_eq!(json["threads"][0]["pid"], 1);
+    assert_eq!(json["threads"][0]["tid"], 1);
+    assert_eq!(json["threads"][0]["name"], "Stream 7");
+}
+
+// ==========================
+// Tests for numeric writer output
+// ==========================
+
+#[test]
+fn test_write_numeric_ids_emits_numbers_and_names() {
+    let events = vec![
+        create_event("a", 0.0, 1.0, "Device 0", "Stream 7"),
+        create_event("b", 2.0, 1.0, "Device 1", "Stream 7"),
+    ];
+    let (written, track_ids) = write_numeric(events);
+
+    assert_eq!(written[0]["pid"], 1);
+    assert_eq!(written[0]["tid"], 1);
+    assert_eq!(written[1]["pid"], 2);
+    assert_eq!(written[1]["tid"], 2);
+    assert!(written
+        .iter()
+        .all(|e| e["pid"].is_u64() && e["tid"].is_u64()));
+
+    let process_names: Vec<_> = written
+        .iter()
+        .filter(|e| e["name"] == "process_name")
+        .map(|e| e["args"]["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(process_names, vec!["Device 0", "Device 1"]);
+    assert_eq!(track_ids.threads().len(), 2);
+}
+
+#[test]
+fn test_write_numeric_ids_overflow_tracks_get_ids() {
+    // Partial overlap moves the second event to an overflow track before mapping
+    let events = vec![
+        create_event("a", 0.0, 10.0, "Device 0", "Stream 7"),
+        create_event("b", 5.0, 10.0, "Device 0", "Stream 7"),
+    ];
+    let (written, track_ids) = write_numeric(events);
+
+    assert_ne!(written[0]["tid"], written[1]["tid"]);
+    assert!(written[1]["tid"].is_u64());
+    assert!(track_ids
+        .threads()
+        .iter()
+        .any(|t| t.name.starts_with(nsys_chrome::writer::OVERFLOW_PREFIX)));
+}
+
+#[test]
+fn test_write_numeric_ids_leaves_args_strings() {
+    let event = create_event("a", 0.0, 1.0, "Device 0", "Stream 7")
+        .with_arg("pid", serde_json::json!("123"))
+        .with_arg("tid", serde_json::json!("Stream 7"));
+    let (written, _) = write_numeric(vec![event]);
+
+    assert_eq!(written[0]["pid"], 1);
+    assert_eq!(written[0]["args"]["pid"], "123");
+    assert_eq!(written[0]["args"]["tid"], "Stream 7");
+}
+
+#[test]
+fn test_write_default_keeps_string_ids() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_str().unwrap();
+    ChromeTraceWriter::write(
+        path,
+        vec![create_event("a", 0.0, 1.0, "Device 0", "Stream 7")],
+    )
+    .unwrap();
+
+    let parsed: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(parsed["traceEvents"][0]["pid"], "Device 0");
+    assert_eq!(parsed["traceEvents"][0]["tid"], "Stream 7");
+}
+
+#[test]
+fn test_write_default_keeps_all_digit_ids_as_strings() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_str().unwrap();
+    ChromeTraceWriter::write(path, vec![create_event("a", 0.0, 1.0, "1234", "42")]).unwrap();
+
+    let parsed: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(parsed["traceEvents"][0]["pid"], "1234");
+    assert_eq!(parsed["traceEvents"][0]["tid"], "42");
+}
@@ -0,0 +1,138 @@
+//! Unit tests for the per-device memcpy throughput counters
+
+use nsys_chrome::analysis::{
+    throughput_counter_events, transfer_throughput, transfer_totals_events, TransferDirection,
+    THROUGHPUT_COUNTER, TRANSFER_TOTALS_EVENT,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase};
+use nsys_chrome::routing::duration_stats;
+use std::collections::BTreeMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_copy(
+    kind: &str,
+    start_ns: i64,
+    end_ns: i64,
+    bytes: i64,
+    device_id: i64,
+) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        format!("Memcpy {}", kind),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        format!("Device {}", device_id),
+        "Copy Engine 1".to_string(),
+        "memcpy".to_string(),
+    )
+    .with_arg("deviceId", device_id)
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+    .with_arg("bytes", bytes)
+    .with_arg("copyKind", kind)
+}
+
+/// Two overlapping uploads at 10 GB/s each, a download at 5 GB/s and a
+/// host-to-host copy, which moves nothing over the bus
+fn copies() -> Vec<ChromeTraceEvent> {
+    vec![
+        create_copy("HtoD", 0, 1000, 10_000, 0),
+        create_copy("HtoD", 500, 1500, 10_000, 0),
+        create_copy("DtoH", 2000, 3000, 5000, 0),
+        create_copy("HtoH", 0, 1000, 1000, 0),
+        create_copy("DtoD", 0, 100, 20_000, 1),
+    ]
+}
+
+fn rates(pairs: &[(TransferDirection, f64)]) -> BTreeMap<TransferDirection, f64> {
+    pairs.iter().copied().collect()
+}
+
+// ==========================
+// Tests for transfer_throughput
+// ==========================
+
+#[test]
+fn test_throughput_sums_overlapping_copies_per_direction() {
+    use TransferDirection::*;
+    let throughput = transfer_throughput(&copies());
+    assert_eq!(throughput.len(), 2);
+
+    let device = &throughput[0];
+    assert_eq!(device.device_id, 0);
+    assert_eq!(
+        device.steps,
+        vec![
+            (0, rates(&[(HostToDevice, 10.0)])),
+            (500, rates(&[(HostToDevice, 20.0)])),
+            (1000, rates(&[(HostToDevice, 10.0)])),
+            (1500, rates(&[(HostToDevice, 0.0)])),
+            (2000, rates(&[(HostToDevice, 0.0), (DeviceToHost, 5.0)])),
+            (3000, rates(&[(HostToDevice, 0.0), (DeviceToHost, 0.0)])),
+        ]
+    );
+    assert_eq!(
+        throughput[1].steps.last().unwrap().1,
+        rates(&[(DeviceToDevice, 0.0)])
+    );
+}
+
+#[test]
+fn test_totals_count_copies_bytes_and_busy_time() {
+    let throughput = transfer_throughput(&copies());
+    let uploads = throughput[0].totals[&TransferDirection::HostToDevice];
+    assert_eq!(uploads.copies, 2);
+    assert_eq!(uploads.bytes, 20_000);
+    // Overlapping copies count once
+    assert_eq!(uploads.busy_ns, 1500);
+    assert_eq!(uploads.avg_gbps(), 13.333);
+    assert!(!throughput[0]
+        .totals
+        .contains_key(&TransferDirection::DeviceToDevice));
+    assert_eq!(
+        throughput[1].totals[&TransferDirection::DeviceToDevice].avg_gbps(),
+        200.0
+    );
+}
+
+#[test]
+fn test_counter_and_summary_events() {
+    let throughput = transfer_throughput(&copies());
+    let counters = throughput_counter_events(&throughput);
+    assert_eq!(counters.len(), 8);
+    assert!(counters
+        .iter()
+        .all(|e| e.ph == ChromeTracePhase::Counter && e.name == THROUGHPUT_COUNTER));
+    assert_eq!(counters[1].args["H2D GB/s"], 20.0);
+    assert_eq!(counters[4].args["D2H GB/s"], 5.0);
+
+    let totals = transfer_totals_events(&throughput);
+    assert_eq!(totals.len(), 2);
+    assert_eq!(totals[0].name, TRANSFER_TOTALS_EVENT);
+    assert_eq!(totals[0].pid, "Device 0");
+    assert_eq!(totals[0].args["D2H"]["bytes"], 5000);
+    assert_eq!(totals[0].args["H2D"]["copies"], 2);
+}
+
+#[test]
+fn test_duration_stats_total_bytes() {
+    let stats = duration_stats(&copies());
+    let uploads = stats.iter().find(|s| s.name == "Memcpy HtoD").unwrap();
+    assert_eq!(uploads.total_bytes, Some(20_000));
+    // 20 KB over 2 us of copy time
+    assert_eq!(uploads.avg_gbps(), Some(10.0));
+
+    let kernel = ChromeTraceEvent::complete(
+        "gemm".to_string(),
+        0.0,
+        1.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    );
+    let stats = duration_stats(&[kernel]);
+    assert_eq!(stats[0].total_bytes, None);
+    assert_eq!(stats[0].avg_gbps(), None);
+}
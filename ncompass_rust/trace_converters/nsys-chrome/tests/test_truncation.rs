@@ -0,0 +1,161 @@
+//! Unit tests for repairing records left unfinished by an interrupted capture
+
+mod common;
+
+use common::{convert_sql_with_diagnostics, create_sqlite};
+use nsys_chrome::analysis::{repair_truncated, RepairStats};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::frontends::FrontendTrace;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::NsysChromeConverter;
+use serde_json::json;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// A capture killed at 9us: one NVTX range and one API call never ended,
+/// and one API call ends before it starts
+const TRUNCATED_SQL: &str = "
+    CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+    INSERT INTO StringIds VALUES (1, 'cudaLaunchKernel'), (2, 'cudaMemcpy');
+    CREATE TABLE NVTX_EVENTS (
+        start INTEGER, end INTEGER, text TEXT, textId INTEGER,
+        globalTid INTEGER, eventType INTEGER
+    );
+    INSERT INTO NVTX_EVENTS VALUES (1000, 4000, 'step 0', NULL, 16777217, 59);
+    INSERT INTO NVTX_EVENTS VALUES (5000, NULL, 'step 1', NULL, 16777217, 59);
+    CREATE TABLE CUPTI_ACTIVITY_KIND_RUNTIME (
+        start INTEGER, end INTEGER, globalTid INTEGER, correlationId INTEGER, nameId INTEGER
+    );
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES (2000, 3000, 16777217, 1, 1);
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES (6000, 5500, 16777217, 2, 1);
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES (8000, 9000, 16777217, 3, 2);
+    INSERT INTO CUPTI_ACTIVITY_KIND_RUNTIME VALUES (8500, NULL, 16777217, 4, 2);
+";
+
+fn create_event(name: &str, start_ns: i64, end_ns: Option<i64>, cat: &str) -> ChromeTraceEvent {
+    let mut event = ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        0.0,
+        "Device 0".to_string(),
+        "Thread 1".to_string(),
+        cat.to_string(),
+    )
+    .with_arg("start_ns", start_ns);
+    match end_ns {
+        Some(end_ns) => {
+            event.dur = Some((end_ns - start_ns) as f64 / 1000.0);
+            event.args.insert("end_ns".into(), json!(end_ns));
+        }
+        None => event.dur = None,
+    }
+    event
+}
+
+fn convert(sql: &str) -> (Vec<ChromeTraceEvent>, RepairStats) {
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Nvtx, EventCategory::CudaApi],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let (events, diagnostics) = convert_sql_with_diagnostics(sql, options);
+    (events, diagnostics.repaired_records)
+}
+
+// ==========================
+// Tests for repair_truncated
+// ==========================
+
+#[test]
+fn test_repair_closes_clamps_and_drops() {
+    let mut nan = create_event("nan", 3000, Some(4000), "kernel");
+    nan.dur = Some(f64::NAN);
+    let mut trace = FrontendTrace {
+        kernel_events: vec![
+            create_event("ok", 1000, Some(2000), "kernel"),
+            create_event("backwards", 5000, Some(4000), "kernel"),
+            nan,
+        ],
+        annotation_events: vec![create_event("open", 500, None, "nvtx")],
+        other_events: vec![create_event("late", 6000, Some(7000), "osrt")],
+        ..Default::default()
+    };
+
+    let stats = repair_truncated(&mut trace);
+    assert_eq!(
+        stats,
+        RepairStats {
+            closed_at_capture_end: 1,
+            clamped_negative: 1,
+            dropped: 1,
+        }
+    );
+    assert_eq!(stats.total(), 3);
+
+    // Capture end is the latest end among all events
+    let open = &trace.annotation_events[0];
+    assert_eq!(open.dur, Some(6.5));
+    assert_eq!(open.args["end_ns"], 7000);
+    assert_eq!(open.args["truncated"], true);
+
+    assert_eq!(trace.kernel_events.len(), 2);
+    let backwards = &trace.kernel_events[1];
+    assert_eq!(backwards.dur, Some(0.0));
+    assert_eq!(backwards.args["end_ns"], 5000);
+    assert!(!trace.kernel_events[0].args.contains_key("truncated"));
+}
+
+#[test]
+fn test_repair_leaves_complete_traces_alone() {
+    let mut trace = FrontendTrace {
+        kernel_events: vec![create_event("k", 1000, Some(2000), "kernel")],
+        ..Default::default()
+    };
+    assert_eq!(repair_truncated(&mut trace).total(), 0);
+    assert_eq!(trace.kernel_events[0].dur, Some(1.0));
+
+    let mut empty = FrontendTrace::default();
+    assert_eq!(repair_truncated(&mut empty), RepairStats::default());
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_repairs_interrupted_capture() {
+    let (events, repaired) = convert(TRUNCATED_SQL);
+
+    assert_eq!(repaired.closed_at_capture_end, 2);
+    assert_eq!(repaired.clamped_negative, 1);
+    assert!(events
+        .iter()
+        .all(|e| e.dur.is_some_and(|dur| dur.is_finite() && dur >= 0.0)));
+
+    // The unterminated range is kept rather than skipped, ending at capture end
+    let step = events.iter().find(|e| e.name == "step 1").unwrap();
+    assert_eq!(step.dur, Some(4.0));
+    assert_eq!(step.args["truncated"], true);
+    let memcpy: Vec<&ChromeTraceEvent> = events.iter().filter(|e| e.name == "cudaMemcpy").collect();
+    assert_eq!(memcpy[1].args["end_ns"], 9000);
+}
+
+#[test]
+fn test_repairs_reported_in_diagnostics() {
+    let dir = TempDir::new().unwrap();
+    let path = create_sqlite(&dir, "truncated.sqlite", TRUNCATED_SQL);
+
+    let (_, diagnostics) = NsysChromeConverter::new(&path, None)
+        .unwrap()
+        .extract()
+        .unwrap();
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics.summary_lines().contains(
+        &"Repaired 3 truncated records: 2 closed at capture end, \
+          1 negative durations clamped, 0 dropped"
+            .to_string()
+    ));
+}
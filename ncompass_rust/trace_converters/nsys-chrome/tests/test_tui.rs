@@ -0,0 +1,126 @@
+//! Unit tests for the full-screen event browser
+
+use nsys_chrome::browser::{EventKind, TraceBrowser};
+use nsys_chrome::models::ChromeTraceEvent;
+use nsys_chrome::tui::{parse_keys, Key, Screen, TuiState};
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_event(name: &str, ts: f64, dur: f64, tid: &str, cat: &str) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        ts,
+        dur,
+        "Device 0".to_string(),
+        tid.to_string(),
+        cat.to_string(),
+    )
+}
+
+fn browser() -> TraceBrowser {
+    TraceBrowser::from_events(&[
+        create_event("forward", 0.0, 100.0, "NVTX Thread 1", "nvtx"),
+        create_event("ampere_sgemm_128x64", 10.0, 40.0, "Stream 7", "kernel"),
+        create_event("layer_norm_kernel", 55.0, 5.0, "Stream 7", "kernel"),
+        create_event("ampere_sgemm_64x64", 60.0, 20.0, "Stream 9", "kernel"),
+    ])
+}
+
+fn press(state: &mut TuiState, browser: &TraceBrowser, keys: &str) -> bool {
+    parse_keys(keys.as_bytes())
+        .into_iter()
+        .all(|key| state.handle_key(key, browser))
+}
+
+// ==========================
+// Tests for key decoding
+// ==========================
+
+#[test]
+fn test_parse_keys() {
+    assert_eq!(
+        parse_keys(b"\x1b[A\x1b[B\x1b[6~\x1bOH/x\r\t\x7f"),
+        vec![
+            Key::Up,
+            Key::Down,
+            Key::PageDown,
+            Key::Home,
+            Key::Char('/'),
+            Key::Char('x'),
+            Key::Enter,
+            Key::Tab,
+            Key::Backspace,
+        ]
+    );
+    assert_eq!(parse_keys(b"\x1b"), vec![Key::Escape]);
+    // Unknown sequences and control bytes are skipped
+    assert_eq!(parse_keys(b"\x1b[15~\x01q"), vec![Key::Char('q')]);
+}
+
+// ==========================
+// Tests for TuiState
+// ==========================
+
+#[test]
+fn test_list_navigation_and_details() {
+    let browser = browser();
+    let mut state = TuiState::default();
+    let frame = state.render(&browser, 120, 10);
+    assert_eq!(frame.lines.len(), 10);
+    assert!(frame.lines[0].starts_with("3 kernels, by duration"));
+    assert!(frame.lines[2].contains("ampere_sgemm_128x64"));
+    assert_eq!(frame.highlight, Some(2));
+
+    // The cursor stops at the last row
+    assert!(press(&mut state, &browser, "\x1b[B\x1b[B\x1b[B\r"));
+    let frame = state.render(&browser, 120, 10);
+    assert_eq!(frame.highlight, Some(4));
+    let status = frame.lines.last().unwrap();
+    assert!(status.starts_with("layer_norm_kernel  [kernel]  Device 0 / Stream 7"));
+}
+
+#[test]
+fn test_search_sort_and_kind() {
+    let browser = browser();
+    let mut state = TuiState::default();
+
+    assert!(press(&mut state, &browser, "/SGEM"));
+    assert_eq!(state.render(&browser, 120, 10).lines[9], "/SGEM");
+    assert!(press(&mut state, &browser, "\x7fMM\rs"));
+    let frame = state.render(&browser, 120, 10);
+    assert!(frame.lines[0].starts_with("2 kernels matching 'SGEMM', by start"));
+    assert!(frame.lines[2].contains("ampere_sgemm_128x64"));
+
+    assert!(press(&mut state, &browser, "/\x7f\x7f\x7f\x7f\x7f\r\t"));
+    assert_eq!(state.query.kind, EventKind::Nvtx);
+    assert!(state.render(&browser, 120, 10).lines[0].starts_with("1 NVTX ranges"));
+    // Esc leaves the search without applying it; a second Esc quits
+    assert!(press(&mut state, &browser, "/x\x1b"));
+    assert_eq!(state.query.search, None);
+    assert!(!press(&mut state, &browser, "\x1b"));
+}
+
+#[test]
+fn test_tracks_open_a_timeline_and_scroll() {
+    let browser = browser();
+    let mut state = TuiState::default();
+    assert!(press(&mut state, &browser, "r\x1b[B\r"));
+    assert_eq!(state.screen, Screen::Timeline);
+    let frame = state.render(&browser, 80, 10);
+    assert_eq!(
+        frame.lines[0].split("  ").next(),
+        Some("Timeline of Device 0 / Stream 7")
+    );
+    assert!(frame.lines[2].starts_with("Device 0 / Stream 7"));
+    assert!(frame.lines[3].is_empty());
+
+    // Three timeline rows on a screen with room for two
+    assert!(press(&mut state, &browser, "t\x1b[F"));
+    let frame = state.render(&browser, 80, 5);
+    assert!(frame.lines[2].starts_with("Device 0 / Stream 7"));
+    assert!(frame.lines[3].starts_with("Device 0 / Stream 9"));
+    assert_eq!(frame.highlight, Some(3));
+    assert!(!press(&mut state, &browser, "q"));
+}
@@ -0,0 +1,118 @@
+//! Unit tests for the unitrace front-end
+
+use nsys_chrome::frontends::unitrace::is_unitrace_json;
+use nsys_chrome::frontends::{assemble_trace, UnitraceReader};
+use nsys_chrome::models::ConversionOptions;
+use serde_json::json;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// unitrace trace with a SYCL task, a kernel append linked by a flow, and its kernel
+fn unitrace_json() -> serde_json::Value {
+    json!({
+        "traceEvents": [
+            {"ph": "X", "pid": 900, "tid": 901, "name": "sycl::queue.submit", "cat": "sycl",
+             "ts": 100.0, "dur": 100.0},
+            {"ph": "X", "pid": 900, "tid": 901, "name": "zeCommandListAppendLaunchKernel",
+             "ts": 110.0, "dur": 20.0},
+            {"ph": "s", "pid": 900, "tid": 901, "name": "dep", "id": 77, "ts": 125.0},
+            {"ph": "X", "pid": 900, "tid": "GPU Queue 0", "name": "gemm_kernel", "cat": "gpu_op",
+             "ts": 140.0, "dur": 40.0, "args": {"device_id": 1}},
+            {"ph": "f", "pid": 900, "tid": "GPU Queue 0", "name": "dep", "id": 77, "ts": 140.0, "bp": "e"},
+            {"ph": "X", "pid": 900, "tid": 901, "name": "zeMemAllocDevice", "ts": 300.0, "dur": 5.0},
+            {"ph": "X", "pid": 900, "tid": 901, "name": "malloc", "ts": 310.0, "dur": 5.0}
+        ]
+    })
+}
+
+// ==========================
+// Tests for UnitraceReader
+// ==========================
+
+#[test]
+fn test_unitrace_reader_classifies_events() {
+    let trace = UnitraceReader::from_value(unitrace_json())
+        .unwrap()
+        .read()
+        .unwrap();
+
+    assert_eq!(trace.kernel_events.len(), 1);
+    let kernel = &trace.kernel_events[0];
+    assert_eq!(kernel.name, "gemm_kernel");
+    assert_eq!(kernel.pid, "Device 1");
+    assert_eq!(kernel.args["start_ns"], 140000);
+    assert_eq!(kernel.args["correlationId"], 77);
+
+    assert_eq!(trace.api_events.len(), 2);
+    let launch = trace
+        .api_events
+        .iter()
+        .find(|e| e.name == "zeCommandListAppendLaunchKernel")
+        .unwrap();
+    assert_eq!(launch.args["correlationId"], 77);
+    assert_eq!(launch.args["deviceId"], 1);
+    assert_eq!(launch.tid, "Level Zero API Thread 901");
+    let alloc = trace
+        .api_events
+        .iter()
+        .find(|e| e.name == "zeMemAllocDevice")
+        .unwrap();
+    assert!(!alloc.args.contains_key("correlationId"));
+
+    assert_eq!(trace.annotation_events.len(), 1);
+    assert_eq!(trace.annotation_events[0].name, "sycl::queue.submit");
+    assert_eq!(trace.annotation_events[0].pid, "Device 1");
+}
+
+#[test]
+fn test_unitrace_reader_explicit_ids() {
+    let value = json!([
+        {"ph": "X", "pid": 1, "tid": 2, "name": "zeCommandListAppendLaunchKernel",
+         "ts": 10.0, "dur": 1.0, "args": {"id": "5"}},
+        {"ph": "X", "pid": 1, "tid": "Device 0 Queue", "name": "k", "ts": 12.0, "dur": 1.0,
+         "args": {"id": 5}}
+    ]);
+    let trace = UnitraceReader::from_value(value).unwrap().read().unwrap();
+
+    assert_eq!(trace.api_events[0].args["correlationId"], 5);
+    assert_eq!(trace.kernel_events[0].args["correlationId"], 5);
+}
+
+#[test]
+fn test_unitrace_reader_rejects_other_json() {
+    assert!(UnitraceReader::from_value(json!({"events": []})).is_err());
+}
+
+#[test]
+fn test_is_unitrace_json() {
+    let dir = TempDir::new().unwrap();
+    let unitrace = dir.path().join("app.12345.json");
+    std::fs::write(&unitrace, unitrace_json().to_string()).unwrap();
+    let chrome = dir.path().join("trace.json");
+    std::fs::write(&chrome, r#"{"traceEvents": [{"name": "cudaMalloc"}]}"#).unwrap();
+
+    assert!(is_unitrace_json(&unitrace));
+    assert!(!is_unitrace_json(&chrome));
+}
+
+// ==========================
+// Tests for assemble_trace
+// ==========================
+
+#[test]
+fn test_assemble_trace_links_sycl_tasks_to_kernels() {
+    let trace = UnitraceReader::from_value(unitrace_json())
+        .unwrap()
+        .read()
+        .unwrap();
+    let events = assemble_trace(trace, &ConversionOptions::default()).unwrap();
+
+    let linked = events.iter().find(|e| e.cat == "nvtx-kernel").unwrap();
+    assert_eq!(linked.name, "sycl::queue.submit");
+    assert_eq!(linked.pid, "Device 1");
+    assert_eq!(linked.ts, 140.0);
+    assert_eq!(linked.dur, Some(40.0));
+}
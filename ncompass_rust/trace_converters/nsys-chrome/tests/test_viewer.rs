@@ -0,0 +1,82 @@
+//! Unit tests for viewer module
+
+use nsys_chrome::viewer::{perfetto_deep_link, TraceServer};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use tempfile::TempDir;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// Send a request and return the raw response
+fn send(addr: SocketAddr, method: &str, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: 127.0.0.1\r\nOrigin: https://ui.perfetto.dev\r\n\r\n",
+        method, path
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+// ==========================
+// Tests for perfetto_deep_link
+// ==========================
+
+#[test]
+fn test_perfetto_deep_link_encodes_url() {
+    assert_eq!(
+        perfetto_deep_link("http://127.0.0.1:9001/my trace.json.gz"),
+        "https://ui.perfetto.dev/#!/?url=http%3A%2F%2F127.0.0.1%3A9001%2Fmy%20trace.json.gz"
+    );
+}
+
+// ==========================
+// Tests for TraceServer
+// ==========================
+
+#[test]
+fn test_trace_server_missing_file() {
+    let dir = TempDir::new().unwrap();
+    let result = TraceServer::bind(
+        dir.path().join("missing.json.gz"),
+        SocketAddr::from(([127, 0, 0, 1], 0)),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_trace_server_serves_trace_once() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json");
+    std::fs::write(&path, br#"{"traceEvents":[]}"#).unwrap();
+
+    let server = TraceServer::bind(&path, SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+    let addr = server.local_addr().unwrap();
+    assert_eq!(
+        server.trace_url().unwrap(),
+        format!("http://{}/trace.json", addr)
+    );
+    assert!(server
+        .deep_link()
+        .unwrap()
+        .starts_with("https://ui.perfetto.dev/#!/?url=http%3A%2F%2F127.0.0.1"));
+    let handle = thread::spawn(move || server.serve_until_fetched());
+
+    // Preflight and unknown paths keep the server running
+    let preflight = send(addr, "OPTIONS", "/trace.json");
+    assert!(preflight.starts_with("HTTP/1.1 204"));
+    assert!(preflight.contains("Access-Control-Allow-Origin: https://ui.perfetto.dev"));
+    assert!(send(addr, "GET", "/other.json").starts_with("HTTP/1.1 404"));
+
+    let response = send(addr, "GET", "/trace.json");
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.ends_with(r#"{"traceEvents":[]}"#));
+
+    handle.join().unwrap().unwrap();
+}
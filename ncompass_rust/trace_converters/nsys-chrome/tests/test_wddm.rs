@@ -0,0 +1,218 @@
+//! Unit tests for WDDM parsing and GPU gap analysis
+
+mod common;
+
+use common::convert_sql;
+use nsys_chrome::analysis::{attribute_wddm_queue_time, find_kernel_gaps, gap_events, GpuGap};
+use nsys_chrome::category::EventCategory;
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions};
+use nsys_chrome::parsers::{EventParser, ParseContext, WDDMParser};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// SQL creating WDDM queue and DMA packet tables with one paired packet each
+const WDDM_TABLES_SQL: &str = "
+    CREATE TABLE WDDM_QUEUE_PACKET_START_EVENTS (
+        start INTEGER, globalTid INTEGER, gpu INTEGER, context INTEGER,
+        dmaBufferSize INTEGER, submitSequence INTEGER, packetType INTEGER
+    );
+    CREATE TABLE WDDM_QUEUE_PACKET_STOP_EVENTS (
+        start INTEGER, globalTid INTEGER, gpu INTEGER, context INTEGER,
+        packetType INTEGER, submitSequence INTEGER, preempted INTEGER
+    );
+    CREATE TABLE WDDM_DMA_PACKET_START_EVENTS (
+        start INTEGER, globalTid INTEGER, gpu INTEGER, context INTEGER,
+        packetType INTEGER, ulQueueSubmitSequence INTEGER
+    );
+    CREATE TABLE WDDM_DMA_PACKET_STOP_EVENTS (
+        start INTEGER, globalTid INTEGER, gpu INTEGER, context INTEGER,
+        packetType INTEGER, ulQueueSubmitSequence INTEGER
+    );
+    INSERT INTO WDDM_QUEUE_PACKET_START_EVENTS VALUES (3000, 16777217, 0, 4096, 64, 1, 0);
+    INSERT INTO WDDM_QUEUE_PACKET_STOP_EVENTS VALUES (9000, 16777217, 0, 4096, 0, 1, 0);
+    INSERT INTO WDDM_DMA_PACKET_START_EVENTS VALUES (9500, 16777217, 0, 4096, 0, 1);
+    INSERT INTO WDDM_DMA_PACKET_STOP_EVENTS VALUES (9800, 16777217, 0, 4096, 0, 1);
+";
+
+fn create_kernel(start_ns: i64, end_ns: i64, device_id: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "kernel".to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        format!("Device {}", device_id),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(start_ns))
+    .with_arg("end_ns", serde_json::json!(end_ns))
+    .with_arg("deviceId", serde_json::json!(device_id))
+}
+
+fn create_queue_packet(start_ns: i64, end_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "Render".to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "WDDM GPU 0".to_string(),
+        "Queue Context 0x1000".to_string(),
+        "wddm".to_string(),
+    )
+    .with_arg("start_ns", serde_json::json!(start_ns))
+    .with_arg("end_ns", serde_json::json!(end_ns))
+    .with_arg("packet_kind", serde_json::json!("queue"))
+}
+
+// ==========================
+// Tests for WDDMParser
+// ==========================
+
+#[test]
+fn test_wddm_parser_pairs_queue_and_dma_packets() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(WDDM_TABLES_SQL).unwrap();
+
+    let strings = HashMap::new();
+    let options = ConversionOptions::default();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names);
+
+    let events = WDDMParser.safe_parse(&context).unwrap();
+    assert_eq!(events.len(), 2);
+
+    let queue = events
+        .iter()
+        .find(|e| e.args["packet_kind"] == "queue")
+        .unwrap();
+    assert_eq!(queue.name, "Render");
+    assert_eq!(queue.pid, "WDDM GPU 0");
+    assert_eq!(queue.tid, "Queue Context 0x1000");
+    assert_eq!(queue.cat, "wddm");
+    assert_eq!(queue.args["start_ns"], 3000);
+    assert_eq!(queue.args["end_ns"], 9000);
+    assert_eq!(queue.args["raw_tid"], 1);
+
+    let dma = events
+        .iter()
+        .find(|e| e.args["packet_kind"] == "dma")
+        .unwrap();
+    assert_eq!(dma.name, "Client Render");
+    assert_eq!(dma.tid, "DMA Context 0x1000");
+}
+
+#[test]
+fn test_wddm_parser_missing_tables() {
+    let conn = Connection::open_in_memory().unwrap();
+    let strings = HashMap::new();
+    let options = ConversionOptions::default();
+    let device_map = HashMap::new();
+    let thread_names = HashMap::new();
+    let context = ParseContext::new(&conn, &strings, &options, &device_map, &thread_names);
+
+    assert!(WDDMParser.safe_parse(&context).unwrap().is_empty());
+}
+
+// ==========================
+// Tests for gap analysis
+// ==========================
+
+#[test]
+fn test_find_kernel_gaps_per_device() {
+    let kernels = vec![
+        create_kernel(0, 1000, 0),
+        create_kernel(500, 2000, 0),
+        create_kernel(50_000, 60_000, 0),
+        create_kernel(0, 1000, 1),
+        create_kernel(1500, 2000, 1),
+    ];
+
+    let gaps = find_kernel_gaps(&kernels, 10_000);
+    assert_eq!(
+        gaps,
+        vec![GpuGap {
+            device_id: 0,
+            start_ns: 2000,
+            end_ns: 50_000,
+            wddm_queued_ns: 0,
+        }]
+    );
+}
+
+#[test]
+fn test_attribute_wddm_queue_time_merges_overlapping_packets() {
+    let mut gaps = vec![GpuGap {
+        device_id: 0,
+        start_ns: 1000,
+        end_ns: 2000,
+        wddm_queued_ns: 0,
+    }];
+    let packets = vec![
+        create_queue_packet(500, 1200),
+        create_queue_packet(1100, 1300),
+        create_queue_packet(1900, 2500),
+        create_queue_packet(3000, 4000),
+    ];
+
+    attribute_wddm_queue_time(&mut gaps, &packets);
+    // [1000, 1300) + [1900, 2000)
+    assert_eq!(gaps[0].wddm_queued_ns, 400);
+}
+
+#[test]
+fn test_gap_events_format() {
+    let gaps = vec![GpuGap {
+        device_id: 2,
+        start_ns: 1000,
+        end_ns: 3000,
+        wddm_queued_ns: 500,
+    }];
+
+    let events = gap_events(&gaps);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].pid, "Device 2");
+    assert_eq!(events[0].tid, "GPU Idle");
+    assert_eq!(events[0].dur, Some(2.0));
+    assert_eq!(events[0].args["wddm_queued_ns"], 500);
+    assert_eq!(events[0].args["wddm_queued_pct"], 25.0);
+}
+
+// ==========================
+// Tests for converter integration
+// ==========================
+
+#[test]
+fn test_converter_emits_wddm_tracks_and_gaps() {
+    let sql = [
+        WDDM_TABLES_SQL,
+        "CREATE TABLE StringIds (id INTEGER PRIMARY KEY, value TEXT);
+        INSERT INTO StringIds VALUES (1, 'my_kernel');
+        CREATE TABLE CUPTI_ACTIVITY_KIND_KERNEL (
+            start INTEGER, end INTEGER, deviceId INTEGER, streamId INTEGER,
+            correlationId INTEGER, globalPid INTEGER, shortName INTEGER,
+            gridX INTEGER, gridY INTEGER, gridZ INTEGER,
+            blockX INTEGER, blockY INTEGER, blockZ INTEGER,
+            registersPerThread INTEGER, staticSharedMemory INTEGER, dynamicSharedMemory INTEGER
+        );
+        INSERT INTO CUPTI_ACTIVITY_KIND_KERNEL VALUES
+            (0, 1000, 0, 7, 1, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0),
+            (20000, 21000, 0, 7, 2, 16777216, 1, 1, 1, 1, 1, 1, 1, 32, 0, 0);",
+    ]
+    .concat();
+
+    let options = ConversionOptions {
+        activity_types: vec![EventCategory::Kernel, EventCategory::Wddm],
+        include_metadata: false,
+        ..Default::default()
+    };
+    let events = convert_sql(&sql, options);
+
+    assert_eq!(events.iter().filter(|e| e.cat == "wddm").count(), 2);
+    let gap = events.iter().find(|e| e.cat == "gap").unwrap();
+    assert_eq!(gap.args["start_ns"], 1000);
+    assert_eq!(gap.args["end_ns"], 20000);
+    assert_eq!(gap.args["wddm_queued_ns"], 6000);
+}
@@ -0,0 +1,158 @@
+//! Unit tests for clamping events to a time window
+
+use nsys_chrome::analysis::{apply_time_window, clamp_to_window, parse_time_window, ClampStats};
+use nsys_chrome::models::{
+    BindingPoint, ChromeTraceEvent, ChromeTracePhase, ConversionOptions, StringOrInt, TimeWindow,
+};
+use nsys_chrome::presets::overlay_options;
+use std::collections::HashMap;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+fn create_range(name: &str, start_ns: i64, end_ns: i64) -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        name.to_string(),
+        start_ns as f64 / 1000.0,
+        (end_ns - start_ns) as f64 / 1000.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+    .with_arg("start_ns", start_ns)
+    .with_arg("end_ns", end_ns)
+}
+
+fn find<'a>(events: &'a [ChromeTraceEvent], name: &str) -> Option<&'a ChromeTraceEvent> {
+    events.iter().find(|e| e.name == name)
+}
+
+const WINDOW: TimeWindow = TimeWindow {
+    start_ns: 10_000,
+    end_ns: 20_000,
+};
+
+// ==========================
+// Tests for clamp_to_window
+// ==========================
+
+#[test]
+fn test_straddling_ranges_are_clamped_to_window() {
+    let events = vec![
+        create_range("before", 0, 5_000),
+        create_range("left", 8_000, 12_000),
+        create_range("inside", 12_000, 15_000),
+        create_range("right", 18_000, 25_000),
+        create_range("spanning", 0, 30_000),
+        create_range("after", 20_000, 22_000),
+        ChromeTraceEvent::metadata(
+            "process_name".to_string(),
+            "Device 0".to_string(),
+            String::new(),
+            HashMap::new(),
+        ),
+    ];
+    let (kept, stats) = clamp_to_window(events, WINDOW);
+    assert_eq!(
+        stats,
+        ClampStats {
+            clamped: 3,
+            dropped: 2
+        }
+    );
+    assert_eq!(kept.len(), 5);
+    assert!(find(&kept, "before").is_none() && find(&kept, "after").is_none());
+
+    let left = find(&kept, "left").unwrap();
+    assert_eq!((left.ts, left.dur), (10.0, Some(2.0)));
+    assert_eq!(left.args["start_ns"], 10_000);
+    assert_eq!(left.args["truncated_start"], true);
+    assert!(!left.args.contains_key("truncated_end"));
+
+    let right = find(&kept, "right").unwrap();
+    assert_eq!((right.ts, right.dur), (18.0, Some(2.0)));
+    assert_eq!(right.args["end_ns"], 20_000);
+    assert_eq!(right.args["truncated_end"], true);
+
+    let spanning = find(&kept, "spanning").unwrap();
+    assert_eq!((spanning.ts, spanning.dur), (10.0, Some(10.0)));
+    assert_eq!(spanning.args["truncated_start"], true);
+    assert_eq!(spanning.args["truncated_end"], true);
+
+    let inside = find(&kept, "inside").unwrap();
+    assert!(!inside.args.contains_key("truncated_start"));
+    assert!(find(&kept, "process_name").is_some());
+}
+
+#[test]
+fn test_flows_losing_an_end_are_removed_in_pairs() {
+    let flow = |id: i64, start_us: f64, finish_us: f64| {
+        vec![
+            ChromeTraceEvent::flow_start(start_us, "Device 0", "CUDA API", StringOrInt::Int(id)),
+            ChromeTraceEvent::flow_finish(
+                finish_us,
+                "Device 0",
+                "Stream 7",
+                StringOrInt::Int(id),
+                BindingPoint::Enclosing,
+            ),
+        ]
+    };
+    let mut events = flow(1, 12.0, 14.0);
+    events.extend(flow(2, 8.0, 11.0));
+    events.extend(flow(3, 19.0, 21.0));
+
+    let (kept, stats) = clamp_to_window(events, WINDOW);
+    let ids: Vec<(ChromeTracePhase, Option<StringOrInt>)> =
+        kept.iter().map(|e| (e.ph, e.id.clone())).collect();
+    assert_eq!(
+        ids,
+        vec![
+            (ChromeTracePhase::FlowStart, Some(StringOrInt::Int(1))),
+            (ChromeTracePhase::FlowFinish, Some(StringOrInt::Int(1))),
+        ]
+    );
+    assert_eq!(stats.dropped, 4);
+}
+
+// ==========================
+// Tests for the time window option
+// ==========================
+
+#[test]
+fn test_window_is_relative_to_capture_start() {
+    assert_eq!(
+        parse_time_window("2s..3.5s").unwrap(),
+        TimeWindow {
+            start_ns: 2_000_000_000,
+            end_ns: 3_500_000_000
+        }
+    );
+    assert_eq!(parse_time_window("..10us").unwrap().start_ns, 0);
+    assert_eq!(parse_time_window("1ms..").unwrap().end_ns, i64::MAX);
+    assert!(parse_time_window("5s..1s").is_err());
+    assert!(parse_time_window("5s").is_err());
+
+    let events = vec![
+        create_range("first", 1_000_000, 1_002_000),
+        create_range("second", 1_009_000, 1_013_000),
+    ];
+    let (kept, stats) = apply_time_window(events, parse_time_window("10us..").unwrap());
+    assert_eq!(stats.clamped, 1);
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0].args["start_ns"], 1_010_000);
+
+    let options = overlay_options(
+        ConversionOptions::default(),
+        r#"{"time_window": "10us..20us"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        options.time_window,
+        Some(TimeWindow {
+            start_ns: 10_000,
+            end_ns: 20_000
+        })
+    );
+}
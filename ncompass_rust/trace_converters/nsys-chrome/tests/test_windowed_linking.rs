@@ -0,0 +1,134 @@
+//! Unit tests for windowed NVTX-kernel linking
+
+mod common;
+
+use common::create_event;
+use nsys_chrome::linker::nvtx_linker::LinkResult;
+use nsys_chrome::linker::{
+    link_nvtx_to_kernels_windowed, link_nvtx_to_kernels_with_stats, LinkStats, NvtxIdentifier,
+};
+use nsys_chrome::models::{ChromeTraceEvent, ConversionOptions, LinkPolicy};
+use nsys_chrome::presets::overlay_options;
+use std::collections::HashSet;
+
+// ==========================
+// Helper Functions
+// ==========================
+
+/// A long "step" range around two "layer" ranges and a range past the last
+/// launch; one launch every 1000 ns, kernels on alternating streams
+fn trace() -> (
+    Vec<ChromeTraceEvent>,
+    Vec<ChromeTraceEvent>,
+    Vec<ChromeTraceEvent>,
+) {
+    let nvtx = vec![
+        create_event("step", "nvtx", 0, 20_000, None),
+        create_event("layer", "nvtx", 500, 6_500, None),
+        create_event("layer", "nvtx", 8_500, 15_500, None),
+        create_event("idle", "nvtx", 30_000, 31_000, None),
+    ];
+    let mut api = Vec::new();
+    let mut kernels = Vec::new();
+    for corr in 1..=18 {
+        let launch = corr * 1000;
+        api.push(create_event(
+            "cudaLaunchKernel",
+            "cuda_api",
+            launch,
+            launch + 100,
+            Some(corr),
+        ));
+        kernels.push(
+            create_event("gemm", "kernel", launch + 400, launch + 1500, Some(corr))
+                .with_arg("streamId", 7 + corr % 2),
+        );
+    }
+    (nvtx, api, kernels)
+}
+
+type Linked = (Vec<String>, HashSet<NvtxIdentifier>, Vec<String>, LinkStats);
+
+/// Events as sorted JSON strings (args keys sorted too), so results compare
+/// regardless of order
+fn normalize(((events, mapped, flows), stats): (LinkResult, LinkStats)) -> Linked {
+    let sorted = |events: Vec<ChromeTraceEvent>| {
+        let mut json: Vec<String> = events
+            .iter()
+            .map(|e| serde_json::to_value(e).unwrap().to_string())
+            .collect();
+        json.sort();
+        json
+    };
+    (sorted(events), mapped, sorted(flows), stats)
+}
+
+fn link_both(options: &ConversionOptions, window_ns: i64) -> (Linked, Linked) {
+    let (nvtx, api, kernels) = trace();
+    let in_memory = link_nvtx_to_kernels_with_stats(&nvtx, &api, &kernels, options);
+    let windowed =
+        link_nvtx_to_kernels_windowed(&nvtx, &api, &kernels, options, window_ns).unwrap();
+    (normalize(in_memory), normalize(windowed))
+}
+
+// ==========================
+// Tests for link_nvtx_to_kernels_windowed
+// ==========================
+
+#[test]
+fn test_windowed_matches_in_memory_for_any_window() {
+    let options = ConversionOptions::default();
+    for window_ns in [1, 1000, 2500, 7000, 1_000_000] {
+        let (in_memory, windowed) = link_both(&options, window_ns);
+        assert_eq!(in_memory, windowed, "window {} ns", window_ns);
+    }
+}
+
+#[test]
+fn test_ranges_spanning_windows_are_stitched() {
+    let (nvtx, api, kernels) = trace();
+    let ((events, mapped, _), stats) =
+        link_nvtx_to_kernels_windowed(&nvtx, &api, &kernels, &ConversionOptions::default(), 1000)
+            .unwrap();
+
+    // One event per linked range, not one per window
+    let step: Vec<&ChromeTraceEvent> = events.iter().filter(|e| e.name == "step").collect();
+    assert_eq!(step.len(), 1);
+    assert_eq!(step[0].ts, 1.4);
+    assert_eq!(step[0].dur, Some(18.1));
+    assert_eq!(events.iter().filter(|e| e.name == "layer").count(), 2);
+    assert_eq!(mapped.len(), 3);
+    assert_eq!(stats.attributed_kernel_ns, 18 * 1100);
+}
+
+#[test]
+fn test_windowed_matches_with_policy_and_per_stream() {
+    let options = ConversionOptions {
+        link_policy: LinkPolicy::Innermost,
+        nvtx_kernel_per_stream: true,
+        max_link_gap_ns: Some(350),
+        ..Default::default()
+    };
+    for window_ns in [1, 3000] {
+        let (in_memory, windowed) = link_both(&options, window_ns);
+        assert_eq!(in_memory, windowed, "window {} ns", window_ns);
+    }
+}
+
+#[test]
+fn test_non_positive_window_is_rejected() {
+    let (nvtx, api, kernels) = trace();
+    let options = ConversionOptions::default();
+    for window_ns in [0, -1] {
+        let linked = link_nvtx_to_kernels_windowed(&nvtx, &api, &kernels, &options, window_ns);
+        assert!(linked.is_err(), "window {} ns", window_ns);
+    }
+}
+
+#[test]
+fn test_link_window_option() {
+    let options =
+        overlay_options(ConversionOptions::default(), r#"{"link_window": "10s"}"#).unwrap();
+    assert_eq!(options.link_window_ns, Some(10_000_000_000));
+    assert_eq!(ConversionOptions::default().link_window_ns, None);
+}
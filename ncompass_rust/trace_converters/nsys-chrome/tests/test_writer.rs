@@ -2,11 +2,15 @@
 
 use flate2::read::GzDecoder;
 use nsys_chrome::models::{ChromeTraceEvent, ChromeTracePhase};
-use nsys_chrome::writer::{ChromeTraceWriter, OVERFLOW_PREFIX};
+use nsys_chrome::writer::{
+    ChromeTraceWriter, OutputCompression, OutputFile, OutputFileOptions, WriteOptions,
+    OVERFLOW_PREFIX,
+};
+use nsys_chrome::ConvertError;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
-use tempfile::NamedTempFile;
+use tempfile::{NamedTempFile, TempDir};
 
 // ==========================
 // Tests for write
@@ -764,3 +768,234 @@ fn test_overlap_gz_handles_partial_overlap() {
     );
 }
 
+// ==========================
+// Tests for write_to
+// ==========================
+
+#[test]
+fn test_write_to_compression_follows_options() {
+    let event = || {
+        vec![ChromeTraceEvent::complete(
+            "A".to_string(),
+            1.0,
+            2.0,
+            "Device 0".to_string(),
+            "Stream 7".to_string(),
+            "kernel".to_string(),
+        )]
+    };
+
+    // Plain JSON even though the name ends in .gz
+    let plain_file = NamedTempFile::with_suffix(".json.gz").unwrap();
+    let options = WriteOptions::default();
+    let written =
+        ChromeTraceWriter::write_to(plain_file.reopen().unwrap(), event(), options).unwrap();
+    assert!(written.track_ids.is_none() && written.outline.is_none());
+    let content = std::fs::read_to_string(plain_file.path()).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["traceEvents"][0]["name"], "A");
+
+    // Gzip with numeric IDs and an outline, whatever the name
+    let gz_file = NamedTempFile::with_suffix(".json").unwrap();
+    let options = WriteOptions {
+        gzip: true,
+        numeric_ids: true,
+        outline: true,
+        ..Default::default()
+    };
+    let written = ChromeTraceWriter::write_to(gz_file.reopen().unwrap(), event(), options).unwrap();
+    assert_eq!(written.track_ids.unwrap().processes().len(), 1);
+    assert!(written.outline.is_some());
+
+    let mut content = String::new();
+    GzDecoder::new(File::open(gz_file.path()).unwrap())
+        .read_to_string(&mut content)
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(parsed["traceEvents"][0]["pid"], 1);
+}
+
+// ==========================
+// Tests for parallel serialization
+// ==========================
+
+/// Enough kernels for several serialization windows, every third one
+/// partially overlapping its predecessor, under a few NVTX ranges
+fn many_events() -> Vec<ChromeTraceEvent> {
+    let mut events = Vec::new();
+    for i in 0..40_000 {
+        let ts = i as f64 * 2.0 - if i % 3 == 0 { 1.0 } else { 0.0 };
+        events.push(
+            ChromeTraceEvent::complete(
+                format!("kernel_{}", i % 7),
+                ts,
+                1.5,
+                format!("Device {}", i % 2),
+                "Stream 7".to_string(),
+                "kernel".to_string(),
+            )
+            .with_arg("correlationId", i),
+        );
+        if i % 10_000 == 0 {
+            events.push(ChromeTraceEvent::complete(
+                "step".to_string(),
+                ts,
+                20_000.0,
+                "Device 0".to_string(),
+                "NVTX".to_string(),
+                "nvtx".to_string(),
+            ));
+        }
+    }
+    events
+}
+
+fn write_bytes(options: WriteOptions) -> (Vec<u8>, serde_json::Value) {
+    let file = NamedTempFile::new().unwrap();
+    let written =
+        ChromeTraceWriter::write_to(file.reopen().unwrap(), many_events(), options).unwrap();
+    let outline = written.outline.map(|o| o.to_json()).unwrap_or_default();
+    (std::fs::read(file.path()).unwrap(), outline)
+}
+
+#[test]
+fn test_parallel_serialization_output_is_identical() {
+    let sequential = WriteOptions {
+        numeric_ids: true,
+        outline: true,
+        ..Default::default()
+    };
+    let parallel = WriteOptions {
+        serialize_threads: 4,
+        ..sequential
+    };
+    let (expected, expected_outline) = write_bytes(sequential);
+    let (actual, actual_outline) = write_bytes(parallel);
+    assert!(
+        actual == expected,
+        "parallel output differs from sequential output"
+    );
+    assert_eq!(actual_outline, expected_outline);
+
+    let parsed: serde_json::Value = serde_json::from_slice(&actual).unwrap();
+    assert!(parsed["traceEvents"].as_array().unwrap().len() > 40_000);
+}
+
+#[test]
+fn test_parallel_serialization_gzip_is_identical() {
+    let decompress = |bytes: Vec<u8>| {
+        let mut content = String::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    };
+    let sequential = WriteOptions {
+        gzip: true,
+        begin_end: true,
+        ..Default::default()
+    };
+    let parallel = WriteOptions {
+        serialize_threads: 3,
+        ..sequential
+    };
+    assert_eq!(
+        decompress(write_bytes(parallel).0),
+        decompress(write_bytes(sequential).0)
+    );
+}
+
+// ==========================
+// Tests for write_auto
+// ==========================
+
+fn kernel_event() -> ChromeTraceEvent {
+    ChromeTraceEvent::complete(
+        "gemm".to_string(),
+        0.0,
+        10.0,
+        "Device 0".to_string(),
+        "Stream 7".to_string(),
+        "kernel".to_string(),
+    )
+}
+
+fn dir_entries(dir: &std::path::Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_write_auto_picks_compression_and_creates_dirs() {
+    let dir = TempDir::new().unwrap();
+    let gz_path = dir.path().join("nested/out/trace.json.gz");
+    ChromeTraceWriter::write_auto(gz_path.to_str().unwrap(), vec![kernel_event()]).unwrap();
+    let mut content = String::new();
+    GzDecoder::new(File::open(&gz_path).unwrap())
+        .read_to_string(&mut content)
+        .unwrap();
+    assert!(content.contains("gemm"));
+
+    let json_path = dir.path().join("trace.json");
+    ChromeTraceWriter::write_auto(json_path.to_str().unwrap(), vec![kernel_event()]).unwrap();
+    assert!(std::fs::read_to_string(&json_path)
+        .unwrap()
+        .contains("gemm"));
+    // Nothing but the outputs is left behind
+    assert_eq!(dir_entries(dir.path()), vec!["nested", "trace.json"]);
+
+    assert_eq!(
+        OutputCompression::from_path("a.json.zst"),
+        OutputCompression::Zstd
+    );
+    let zst_path = dir.path().join("trace.json.zst");
+    assert!(matches!(
+        ChromeTraceWriter::write_auto(zst_path.to_str().unwrap(), vec![]),
+        Err(ConvertError::InvalidOption(_))
+    ));
+    assert!(!zst_path.exists());
+}
+
+#[test]
+fn test_write_auto_refuses_to_overwrite_without_force() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json");
+    let path_str = path.to_str().unwrap();
+    std::fs::write(&path, "keep me").unwrap();
+
+    let err = ChromeTraceWriter::write_auto(path_str, vec![kernel_event()]).unwrap_err();
+    assert!(matches!(err, ConvertError::OutputExists(_)));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+
+    for atomic in [true, false] {
+        let options = OutputFileOptions {
+            force: true,
+            atomic,
+        };
+        ChromeTraceWriter::write_auto_with(
+            path_str,
+            vec![kernel_event()],
+            WriteOptions::default(),
+            options,
+        )
+        .unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains("gemm"));
+    }
+    assert_eq!(dir_entries(dir.path()), vec!["trace.json"]);
+}
+
+#[test]
+fn test_uncommitted_output_leaves_nothing_behind() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("trace.json");
+    let output = OutputFile::create(path.to_str().unwrap(), OutputFileOptions::default()).unwrap();
+    std::io::Write::write_all(&mut output.writer().unwrap(), b"{\"traceEv").unwrap();
+    assert!(!path.exists());
+
+    drop(output);
+    assert!(dir_entries(dir.path()).is_empty());
+}